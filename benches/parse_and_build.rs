@@ -0,0 +1,148 @@
+use criterion::{Criterion, criterion_group, criterion_main};
+use ruxguitar::audio::midi_builder::MidiBuilder;
+use ruxguitar::audio::playback_order::compute_playback_order;
+use ruxguitar::parser::parse_gp_data;
+use ruxguitar::parser::song_parser::{
+    Beat, DEFAULT_BANK, Measure, MeasureHeader, MidiChannel, Note, NoteEffect, NoteType,
+    QUARTER_TIME, Song, Track, Voice,
+};
+use std::hint::black_box;
+use std::sync::Arc;
+
+const TEST_FILES: [&str; 4] = [
+    "test-files/Demo v5.gp5",
+    "test-files/Meshuggah - Bleed.gp5",
+    "test-files/Tyr - Evening Star.gpx",
+    "test-files/The Black Dahlia Murder - Nightbringers.gp",
+];
+
+// synthetic song size
+const SYNTHETIC_TRACKS: usize = 8;
+const SYNTHETIC_MEASURES: usize = 2_000;
+const TIE_CHAIN_MEASURES: usize = 64; // the low string rings tied over this many measures
+
+fn file_name(path: &str) -> &str {
+    path.rsplit('/').next().unwrap_or(path)
+}
+
+fn build_events(song: &Arc<Song>) -> usize {
+    let order = compute_playback_order(&song.measure_headers);
+    MidiBuilder::new()
+        .build_for_song_with_order(song, &order)
+        .len()
+}
+
+fn note(string: i8, value: i16, kind: NoteType) -> Note {
+    let mut note = Note::new(NoteEffect::default());
+    note.string = string;
+    note.value = value;
+    note.kind = kind;
+    note
+}
+
+/// Large song of quarter note chords, the low string is tied over long chains
+/// to stress the tie duration lookup.
+fn synthetic_song(track_count: usize, measure_count: usize) -> Song {
+    let headers: Vec<MeasureHeader> = (0..measure_count)
+        .map(|index| MeasureHeader {
+            start: QUARTER_TIME + index as u32 * QUARTER_TIME * 4,
+            ..MeasureHeader::default()
+        })
+        .collect();
+    let channels = (0..track_count)
+        .map(|index| MidiChannel {
+            channel_id: index as u8,
+            effect_channel_id: index as u8,
+            instrument: 30,
+            volume: 127,
+            balance: 64,
+            chorus: 0,
+            reverb: 0,
+            phaser: 0,
+            tremolo: 0,
+            bank: DEFAULT_BANK,
+        })
+        .collect();
+    let tracks = (0..track_count)
+        .map(|track_index| {
+            let measures = headers
+                .iter()
+                .enumerate()
+                .map(|(header_index, header)| {
+                    let beats = (0..4)
+                        .map(|beat_index| {
+                            let chain_start = header_index % TIE_CHAIN_MEASURES == 0;
+                            let low_kind = if chain_start && beat_index == 0 {
+                                NoteType::Normal
+                            } else {
+                                NoteType::Tie
+                            };
+                            Beat {
+                                notes: vec![
+                                    note(6, 0, low_kind),
+                                    note(3, beat_index * 2, NoteType::Normal),
+                                    note(2, beat_index, NoteType::Normal),
+                                ],
+                                start: header.start + beat_index as u32 * QUARTER_TIME,
+                                ..Beat::default()
+                            }
+                        })
+                        .collect();
+                    Measure {
+                        track_index,
+                        header_index,
+                        voices: vec![Voice {
+                            measure_index: header_index as i16,
+                            beats,
+                        }],
+                        ..Measure::default()
+                    }
+                })
+                .collect();
+            Track {
+                number: track_index as i32 + 1,
+                channel_id: track_index as u8,
+                strings: vec![(1, 64), (2, 59), (3, 55), (4, 50), (5, 45), (6, 40)],
+                measures,
+                ..Track::default()
+            }
+        })
+        .collect();
+    Song {
+        midi_channels: channels,
+        measure_headers: headers,
+        tracks,
+        ..Song::default()
+    }
+}
+
+fn parse_benchmarks(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parse_gp_data");
+    for path in TEST_FILES {
+        let data = std::fs::read(path).unwrap();
+        group.bench_function(file_name(path), |b| {
+            b.iter(|| parse_gp_data(black_box(&data)).unwrap());
+        });
+    }
+    group.finish();
+}
+
+fn build_benchmarks(c: &mut Criterion) {
+    let mut group = c.benchmark_group("build_for_song");
+    for path in TEST_FILES {
+        let data = std::fs::read(path).unwrap();
+        let song = Arc::new(parse_gp_data(&data).unwrap());
+        group.bench_function(file_name(path), |b| {
+            b.iter(|| build_events(black_box(&song)));
+        });
+    }
+    let song = Arc::new(synthetic_song(SYNTHETIC_TRACKS, SYNTHETIC_MEASURES));
+    group.sample_size(10);
+    group.bench_function("synthetic", |b| {
+        b.iter(|| build_events(black_box(&song)));
+    });
+    group.finish();
+}
+
+criterion_group!(benches, parse_benchmarks, build_benchmarks);
+criterion_main!(benches);
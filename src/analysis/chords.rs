@@ -0,0 +1,144 @@
+//! Chord names guessed from the notes played together on a beat.
+
+use crate::analysis::{note_pitch, pitch_class};
+use crate::parser::song_parser::{Beat, Track};
+
+/// Chord qualities by their intervals above the root, the simplest names first.
+const QUALITIES: [(&[i32], &str); 23] = [
+    (&[0, 4, 7], ""),
+    (&[0, 3, 7], "m"),
+    (&[0, 7], "5"),
+    (&[0, 4, 7, 10], "7"),
+    (&[0, 4, 7, 11], "maj7"),
+    (&[0, 3, 7, 10], "m7"),
+    (&[0, 2, 7], "sus2"),
+    (&[0, 5, 7], "sus4"),
+    (&[0, 3, 6], "dim"),
+    (&[0, 4, 8], "aug"),
+    (&[0, 4, 7, 9], "6"),
+    (&[0, 3, 7, 9], "m6"),
+    (&[0, 3, 6, 10], "m7b5"),
+    (&[0, 3, 6, 9], "dim7"),
+    (&[0, 5, 7, 10], "7sus4"),
+    (&[0, 2, 4, 7], "add9"),
+    (&[0, 2, 3, 7], "madd9"),
+    (&[0, 2, 4, 7, 10], "9"),
+    (&[0, 2, 4, 7, 11], "maj9"),
+    (&[0, 2, 3, 7, 10], "m9"),
+    // the fifth is often left out of the seventh chords
+    (&[0, 4, 10], "7"),
+    (&[0, 4, 11], "maj7"),
+    (&[0, 3, 10], "m7"),
+];
+
+/// Set of the pitch classes, one bit per class from C.
+fn pitch_classes(pitches: impl IntoIterator<Item = i32>) -> u16 {
+    pitches
+        .into_iter()
+        .fold(0, |classes, pitch| classes | 1 << pitch.rem_euclid(12))
+}
+
+/// Name of the chord made of the pitches, e.g. "Am7" or "C/E" when the bass is not the root.
+/// Stacks matching no known chord, single notes and intervals other than fifths are not named.
+pub fn chord_name(pitches: &[i32]) -> Option<String> {
+    let bass = *pitches.iter().min()?;
+    let classes = pitch_classes(pitches.iter().copied());
+    if classes.count_ones() < 2 {
+        return None;
+    }
+    // the bass is the most likely root, inversions are named after it
+    let roots = std::iter::once(bass.rem_euclid(12))
+        .chain((0..12).filter(|root| *root != bass.rem_euclid(12) && classes & 1 << root != 0));
+    for root in roots {
+        let quality = QUALITIES
+            .iter()
+            .find(|(intervals, _)| {
+                pitch_classes(intervals.iter().map(|interval| root + interval)) == classes
+            })
+            .map(|(_, quality)| quality);
+        if let Some(quality) = quality {
+            let name = format!("{}{quality}", pitch_class(root));
+            return Some(if root == bass.rem_euclid(12) {
+                name
+            } else {
+                format!("{name}/{}", pitch_class(bass))
+            });
+        }
+    }
+    None
+}
+
+/// Pitches sounding on the beat, the dead notes have none.
+pub fn beat_pitches(track: &Track, beat: &Beat) -> Vec<i32> {
+    beat.notes
+        .iter()
+        .filter_map(|note| note_pitch(track, note))
+        .collect()
+}
+
+/// Chord names suggested per measure and beat of the first voice, where no chord is authored.
+/// A chord is suggested where it changes, not again on the following beats playing it.
+pub fn suggested_chords(track: &Track) -> Vec<Vec<Option<String>>> {
+    let mut previous: Option<String> = None;
+    track
+        .measures
+        .iter()
+        .map(|measure| {
+            let beats = measure.voices.first().map_or(&[][..], |v| &v.beats);
+            beats
+                .iter()
+                .map(|beat| {
+                    if let Some(chord) = &beat.effect.chord {
+                        previous = Some(chord.name.clone());
+                        return None;
+                    }
+                    let name = chord_name(&beat_pitches(track, beat))?;
+                    if previous.as_ref() == Some(&name) {
+                        None
+                    } else {
+                        previous = Some(name.clone());
+                        Some(name)
+                    }
+                })
+                .collect()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::song_parser_tests::parse_gp_file;
+
+    #[test]
+    fn name_chords() {
+        let name = chord_name;
+        // open C and its first inversion
+        assert_eq!(name(&[48, 52, 55, 60, 64]).as_deref(), Some("C"));
+        assert_eq!(name(&[40, 48, 55, 60, 64]).as_deref(), Some("C/E"));
+        // open Am7 rather than C6 with the A bass
+        assert_eq!(name(&[45, 52, 55, 60, 64]).as_deref(), Some("Am7"));
+        assert_eq!(name(&[48, 52, 55, 57]).as_deref(), Some("C6"));
+        // power chord, open D7 without root doubling, diminished seventh
+        assert_eq!(name(&[40, 47, 52]).as_deref(), Some("E5"));
+        assert_eq!(name(&[50, 57, 60, 66]).as_deref(), Some("D7"));
+        assert_eq!(name(&[47, 50, 53, 56]).as_deref(), Some("Bdim7"));
+        // single notes, octaves, thirds and clusters are not chords
+        assert_eq!(name(&[]), None);
+        assert_eq!(name(&[40, 52]), None);
+        assert_eq!(name(&[60, 64]), None);
+        assert_eq!(name(&[60, 61, 62]), None);
+    }
+
+    #[test]
+    fn suggestions_where_the_chord_changes() {
+        let song = parse_gp_file("test-files/canon_rock.gp4").unwrap();
+        let track = &song.tracks[0];
+        let suggestions = suggested_chords(track);
+        assert_eq!(suggestions.len(), track.measures.len());
+        let named: Vec<&String> = suggestions.iter().flatten().flatten().collect();
+        assert!(!named.is_empty());
+        // no suggestion repeats the previous one
+        assert!(named.windows(2).all(|pair| pair[0] != pair[1]));
+    }
+}
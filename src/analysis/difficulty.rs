@@ -0,0 +1,94 @@
+//! Rough difficulty of each measure of a track, to find the hardest sections of a song.
+
+use crate::analysis::is_percussion;
+use crate::parser::song_parser::{
+    Measure, MeasureHeader, NoteEffect, NoteType, QUARTER_TIME, SlapEffect, Song,
+};
+
+/// Frets the hand covers without stretching.
+const COMFORTABLE_SPAN: i16 = 3;
+
+/// Whether the note asks for more than picking and fretting it.
+const fn is_technique(effect: &NoteEffect) -> bool {
+    effect.bend.is_some()
+        || effect.slide.is_some()
+        || effect.hammer
+        || effect.harmonic.is_some()
+        || effect.grace.is_some()
+        || effect.trill.is_some()
+        || effect.tremolo_picking.is_some()
+        || effect.tremolo_bar.is_some()
+        || !matches!(effect.slap, SlapEffect::None)
+}
+
+/// Difficulty of a measure: the notes and techniques played per second at the measure tempo,
+/// plus the widest stretch over the comfortable span. Stretches do not apply to drums.
+fn measure_difficulty(header: &MeasureHeader, measure: &Measure, stretches: bool) -> f32 {
+    let seconds = header.length() as f32 / QUARTER_TIME as f32 * 60.0 / header.tempo.value as f32;
+    let mut actions = 0;
+    let mut widest_span = 0;
+    for beat in measure.voices.iter().flat_map(|voice| &voice.beats) {
+        let played = beat
+            .notes
+            .iter()
+            .filter(|note| note.kind == NoteType::Normal);
+        for note in played.clone() {
+            actions += 1 + usize::from(is_technique(&note.effect));
+        }
+        // open strings do not need the fretting hand
+        let frets = played.map(|note| note.value).filter(|fret| *fret > 0);
+        if let (Some(lowest), Some(highest)) = (frets.clone().min(), frets.max()) {
+            widest_span = widest_span.max(highest - lowest);
+        }
+    }
+    let stretch = if stretches {
+        (widest_span - COMFORTABLE_SPAN).max(0)
+    } else {
+        0
+    };
+    actions as f32 / seconds.max(f32::EPSILON) + f32::from(stretch)
+}
+
+/// Difficulty of each measure of the track, only meaningful compared to the other measures.
+pub fn track_difficulty(song: &Song, track_id: usize) -> Vec<f32> {
+    let track = &song.tracks[track_id];
+    let stretches = !is_percussion(song, track);
+    song.measure_headers
+        .iter()
+        .zip(&track.measures)
+        .map(|(header, measure)| measure_difficulty(header, measure, stretches))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::song_parser_tests::parse_gp_file;
+
+    #[test]
+    fn difficulty_per_measure() {
+        let song = parse_gp_file("test-files/canon_rock.gp4").unwrap();
+        let difficulty = track_difficulty(&song, 0);
+        assert_eq!(difficulty.len(), song.measure_headers.len());
+        assert!(difficulty.iter().all(|score| *score >= 0.0));
+        // the song is not played evenly
+        let hardest = difficulty.iter().copied().fold(0.0, f32::max);
+        let easiest = difficulty.iter().copied().fold(f32::MAX, f32::min);
+        assert!(hardest > easiest);
+    }
+
+    #[test]
+    fn faster_is_harder() {
+        let song = parse_gp_file("test-files/canon_rock.gp4").unwrap();
+        let (i, measure) = song.tracks[0]
+            .measures
+            .iter()
+            .enumerate()
+            .find(|(_, m)| m.voices[0].beats.iter().any(|b| !b.notes.is_empty()))
+            .unwrap();
+        let mut header = song.measure_headers[i].clone();
+        let score = measure_difficulty(&header, measure, true);
+        header.tempo.value *= 2;
+        assert!(measure_difficulty(&header, measure, true) > score);
+    }
+}
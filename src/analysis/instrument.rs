@@ -0,0 +1,80 @@
+//! Instrument family of a track, from its General MIDI program and percussion flag.
+
+use crate::analysis::is_percussion;
+use crate::parser::song_parser::{Song, Track};
+
+/// Broad family of the instrument playing a track.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum InstrumentFamily {
+    Guitar,
+    Bass,
+    Drums,
+    Keys,
+    Vocals,
+    Orchestral,
+    #[default]
+    Other,
+}
+
+/// Family of the General MIDI program, numbered from 0.
+pub const fn program_family(program: i32) -> InstrumentFamily {
+    match program {
+        // pianos, chromatic percussion, organs, synth leads and pads
+        0..=23 | 80..=95 => InstrumentFamily::Keys,
+        // the plucked ethnic instruments (sitar, banjo, shamisen, koto) are fretted
+        24..=31 | 104..=107 => InstrumentFamily::Guitar,
+        32..=39 => InstrumentFamily::Bass,
+        // choir aahs, voice oohs and synth voice
+        52..=54 => InstrumentFamily::Vocals,
+        // strings, ensembles, brass, reeds and pipes
+        40..=79 | 108..=111 => InstrumentFamily::Orchestral,
+        // percussive programs: steel drums, woodblock, taiko, toms...
+        112..=119 => InstrumentFamily::Drums,
+        _ => InstrumentFamily::Other,
+    }
+}
+
+/// Family of the track, the drum tracks whatever their program.
+pub fn instrument_family(song: &Song, track: &Track) -> InstrumentFamily {
+    if is_percussion(song, track) {
+        return InstrumentFamily::Drums;
+    }
+    song.midi_channels
+        .iter()
+        .find(|channel| channel.channel_id == track.channel_id)
+        .map_or(InstrumentFamily::Other, |channel| {
+            program_family(channel.instrument)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::song_parser_tests::parse_gp_file;
+
+    #[test]
+    fn general_midi_families() {
+        assert_eq!(program_family(0), InstrumentFamily::Keys);
+        assert_eq!(program_family(29), InstrumentFamily::Guitar);
+        assert_eq!(program_family(33), InstrumentFamily::Bass);
+        assert_eq!(program_family(48), InstrumentFamily::Orchestral);
+        assert_eq!(program_family(52), InstrumentFamily::Vocals);
+        assert_eq!(program_family(105), InstrumentFamily::Guitar);
+        assert_eq!(program_family(118), InstrumentFamily::Drums);
+        assert_eq!(program_family(125), InstrumentFamily::Other);
+        assert_eq!(program_family(-1), InstrumentFamily::Other);
+    }
+
+    #[test]
+    fn demo_song_families() {
+        let song = parse_gp_file("test-files/Demo v5.gp5").unwrap();
+        let families: Vec<InstrumentFamily> = song
+            .tracks
+            .iter()
+            .map(|track| instrument_family(&song, track))
+            .collect();
+        assert_eq!(families[0], InstrumentFamily::Guitar);
+        assert!(families.contains(&InstrumentFamily::Drums));
+        assert!(families.contains(&InstrumentFamily::Bass));
+    }
+}
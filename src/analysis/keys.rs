@@ -0,0 +1,195 @@
+//! Key of the song guessed per section from the notes played, to learn which scale fits.
+
+use crate::analysis::{is_percussion, note_pitch, pitch_class};
+use crate::parser::song_parser::{Measure, Song, Track};
+use std::fmt;
+use std::ops::Range;
+
+/// Krumhansl-Kessler key profiles: how well each degree of the scale fits the key.
+const MAJOR_PROFILE: [f64; 12] = [
+    6.35, 2.23, 3.48, 2.33, 4.38, 4.09, 2.52, 5.19, 2.39, 3.66, 2.29, 2.88,
+];
+const MINOR_PROFILE: [f64; 12] = [
+    6.33, 2.68, 3.52, 5.38, 2.60, 3.53, 2.54, 4.75, 3.98, 2.69, 3.34, 3.17,
+];
+
+/// Degrees of the major and natural minor scales, in semitones above the tonic.
+const MAJOR_SCALE: [i32; 7] = [0, 2, 4, 5, 7, 9, 11];
+const MINOR_SCALE: [i32; 7] = [0, 2, 3, 5, 7, 8, 10];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Key {
+    pub tonic: i32, // pitch class from C
+    pub minor: bool,
+}
+
+impl Key {
+    /// Whether the pitch is a degree of the scale of the key, natural minor for minor keys.
+    pub fn contains(self, pitch: i32) -> bool {
+        let scale = if self.minor { MINOR_SCALE } else { MAJOR_SCALE };
+        scale.contains(&(pitch - self.tonic).rem_euclid(12))
+    }
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mode = if self.minor { "minor" } else { "major" };
+        write!(f, "{} {mode}", pitch_class(self.tonic))
+    }
+}
+
+/// Pearson correlation of the two series, 0 if one of them is flat.
+fn correlation(a: &[f64; 12], b: &[f64; 12]) -> f64 {
+    let mean_a = a.iter().sum::<f64>() / 12.0;
+    let mean_b = b.iter().sum::<f64>() / 12.0;
+    let (mut covariance, mut variance_a, mut variance_b) = (0.0, 0.0, 0.0);
+    for (x, y) in a.iter().zip(b) {
+        covariance += (x - mean_a) * (y - mean_b);
+        variance_a += (x - mean_a).powi(2);
+        variance_b += (y - mean_b).powi(2);
+    }
+    if variance_a == 0.0 || variance_b == 0.0 {
+        0.0
+    } else {
+        covariance / (variance_a * variance_b).sqrt()
+    }
+}
+
+/// Key best matching how long each pitch class sounds, `None` without notes.
+pub fn estimate_key(weights: &[f64; 12]) -> Option<Key> {
+    if weights.iter().all(|weight| *weight == 0.0) {
+        return None;
+    }
+    let mut best: Option<(Key, f64)> = None;
+    for minor in [false, true] {
+        let profile = if minor { MINOR_PROFILE } else { MAJOR_PROFILE };
+        for tonic in 0..12 {
+            let rotated: [f64; 12] = std::array::from_fn(|class| {
+                profile[(class as i32 - tonic).rem_euclid(12) as usize]
+            });
+            let score = correlation(weights, &rotated);
+            if best.is_none_or(|(_, best_score)| score > best_score) {
+                best = Some((Key { tonic, minor }, score));
+            }
+        }
+    }
+    best.map(|(key, _)| key)
+}
+
+/// Time each pitch class sounds in the measures, over all the pitched tracks.
+fn pitch_class_weights(song: &Song, measures: Range<usize>) -> [f64; 12] {
+    let mut weights = [0.0; 12];
+    for track in song.tracks.iter().filter(|t| !is_percussion(song, t)) {
+        let track_measures = track.measures.get(measures.clone()).unwrap_or_default();
+        for beat in track_measures
+            .iter()
+            .flat_map(|measure| &measure.voices)
+            .flat_map(|voice| &voice.beats)
+        {
+            let duration = f64::from(beat.duration.time());
+            for pitch in beat.notes.iter().filter_map(|note| note_pitch(track, note)) {
+                weights[pitch.rem_euclid(12) as usize] += duration;
+            }
+        }
+    }
+    weights
+}
+
+/// Key of each measure, a section starts at each marker.
+/// Sections without notes get the key of the whole song.
+pub fn section_keys(song: &Song) -> Vec<Option<Key>> {
+    let measure_count = song.measure_headers.len();
+    let song_key = estimate_key(&pitch_class_weights(song, 0..measure_count));
+    let mut starts: Vec<usize> = (1..measure_count)
+        .filter(|i| song.measure_headers[*i].marker.is_some())
+        .collect();
+    starts.insert(0, 0);
+    let mut keys = Vec::with_capacity(measure_count);
+    for (i, start) in starts.iter().enumerate() {
+        let end = starts.get(i + 1).copied().unwrap_or(measure_count);
+        let key = estimate_key(&pitch_class_weights(song, *start..end)).or(song_key);
+        keys.resize(end, key);
+    }
+    keys
+}
+
+/// Strings of the notes outside the scale of the key, per beat of the first voice.
+pub fn outside_scale(track: &Track, measure: &Measure, key: Key) -> Vec<Vec<i8>> {
+    let beats = measure.voices.first().map_or(&[][..], |v| &v.beats);
+    beats
+        .iter()
+        .map(|beat| {
+            beat.notes
+                .iter()
+                .filter(|note| note_pitch(track, note).is_some_and(|pitch| !key.contains(pitch)))
+                .map(|note| note.string)
+                .collect()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::song_parser_tests::parse_gp_file;
+
+    fn weights_of(pitches: &[(i32, f64)]) -> [f64; 12] {
+        let mut weights = [0.0; 12];
+        for (pitch, weight) in pitches {
+            weights[pitch.rem_euclid(12) as usize] += weight;
+        }
+        weights
+    }
+
+    #[test]
+    fn estimate_keys() {
+        assert_eq!(estimate_key(&[0.0; 12]), None);
+        // C major scale resting on the tonic triad
+        let c_major = weights_of(&[
+            (60, 4.0),
+            (62, 1.0),
+            (64, 3.0),
+            (65, 1.0),
+            (67, 3.0),
+            (69, 1.0),
+            (71, 1.0),
+        ]);
+        let key = estimate_key(&c_major).unwrap();
+        assert_eq!(key.to_string(), "C major");
+        // same notes resting on A
+        let a_minor = weights_of(&[
+            (57, 4.0),
+            (60, 3.0),
+            (64, 3.0),
+            (62, 1.0),
+            (65, 1.0),
+            (67, 1.0),
+            (71, 1.0),
+        ]);
+        assert_eq!(estimate_key(&a_minor).unwrap().to_string(), "A minor");
+    }
+
+    #[test]
+    fn scale_degrees() {
+        let e_minor = Key {
+            tonic: 4,
+            minor: true,
+        };
+        // E F# G A B C D
+        let in_scale: Vec<i32> = (52..64).filter(|p| e_minor.contains(*p)).collect();
+        assert_eq!(in_scale, vec![52, 54, 55, 57, 59, 60, 62]);
+    }
+
+    #[test]
+    fn keys_per_section() {
+        let song = parse_gp_file("test-files/canon_rock.gp4").unwrap();
+        let keys = section_keys(&song);
+        assert_eq!(keys.len(), song.measure_headers.len());
+        assert!(keys.iter().all(Option::is_some));
+
+        let track = &song.tracks[0];
+        let key = keys[0].unwrap();
+        let outside = outside_scale(track, &track.measures[0], key);
+        assert_eq!(outside.len(), track.measures[0].voices[0].beats.len());
+    }
+}
@@ -0,0 +1,114 @@
+//! Music theory analysis of the notes of a song, independent of the display.
+
+use crate::audio::playback_order::{compute_playback_order, playback_tick};
+use crate::audio::tempo_map::TempoMap;
+use crate::parser::song_parser::{MidiChannel, Note, NoteType, Song, Track};
+use std::ops::RangeInclusive;
+
+pub mod chords;
+pub mod difficulty;
+pub mod instrument;
+pub mod keys;
+
+/// Name of the pitch class of a MIDI pitch, with sharps.
+pub const fn pitch_class(midi_pitch: i32) -> &'static str {
+    const NOTES: [&str; 12] = [
+        "C", "C#", "D", "D#", "E", "F", "F#", "G", "G#", "A", "A#", "B",
+    ];
+    NOTES[midi_pitch.rem_euclid(12) as usize]
+}
+
+/// Name of a MIDI pitch with its octave, middle C is "C4".
+pub fn note_name(midi_pitch: i32) -> String {
+    let octave = midi_pitch.div_euclid(12) - 1;
+    format!("{}{octave}", pitch_class(midi_pitch))
+}
+
+/// MIDI pitch of the note on its string, the dead notes and rests have none.
+pub fn note_pitch(track: &Track, note: &Note) -> Option<i32> {
+    if !matches!(note.kind, NoteType::Normal | NoteType::Tie) {
+        return None;
+    }
+    let string = usize::try_from(note.string).ok()?.checked_sub(1)?;
+    let (_, tuning) = track.strings.get(string)?;
+    Some(track.offset + tuning + i32::from(note.value))
+}
+
+/// Drum tracks have no pitches, their notes are drum sounds.
+pub fn is_percussion(song: &Song, track: &Track) -> bool {
+    song.midi_channels
+        .iter()
+        .find(|c| c.channel_id == track.channel_id)
+        .is_some_and(MidiChannel::is_percussion)
+}
+
+/// Lowest and highest MIDI notes of the track, to choose a clef or check an instrument range.
+/// `None` without notes and for drum tracks, their notes are drum sounds.
+pub fn note_range(song: &Song, track: &Track) -> Option<RangeInclusive<i32>> {
+    if is_percussion(song, track) {
+        return None;
+    }
+    let pitches = track
+        .measures
+        .iter()
+        .flat_map(|measure| &measure.voices)
+        .flat_map(|voice| &voice.beats)
+        .flat_map(|beat| &beat.notes)
+        .filter_map(|note| note_pitch(track, note));
+    pitches.fold(None, |range, pitch| match range {
+        None => Some(pitch..=pitch),
+        Some(range) => Some(*range.start().min(&pitch)..=*range.end().max(&pitch)),
+    })
+}
+
+/// Length of a song, in ticks of `QUARTER_TIME` per quarter note and in seconds.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct SongDuration {
+    pub ticks: u32,
+    pub seconds: f64,
+}
+
+/// Length of the playback at the tempo of the file, repeats and jumps included.
+pub fn song_duration(song: &Song) -> SongDuration {
+    let order = compute_playback_order(&song.measure_headers);
+    let (Some(&(first, first_offset)), Some(&(last, last_offset))) = (order.first(), order.last())
+    else {
+        return SongDuration::default();
+    };
+    let start = playback_tick(song.measure_headers[first].start, first_offset);
+    let last = &song.measure_headers[last];
+    let end = playback_tick(last.start, last_offset) + last.length();
+    SongDuration {
+        ticks: end - start,
+        seconds: TempoMap::from_song(song, &order).seconds_between(start, end, 100),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::song_parser::QUARTER_TIME;
+    use crate::parser::song_parser_tests::parse_gp_file;
+
+    #[test]
+    fn song_duration_and_note_range() {
+        let song = parse_gp_file("test-files/Demo v5.gp5").unwrap();
+        let duration = song_duration(&song);
+        // 60 measures of 4/4 through the repeats and the D.S. al Coda
+        assert_eq!(duration.ticks, 60 * 4 * QUARTER_TIME);
+        assert!((duration.seconds - 89.45).abs() < 0.01);
+        assert_eq!(song_duration(&Song::default()), SongDuration::default());
+
+        assert_eq!(note_range(&song, &song.tracks[0]), Some(45..=68));
+        assert_eq!(note_range(&song, &song.tracks[3]), Some(28..=48));
+        assert_eq!(note_range(&song, &Track::default()), None);
+        // drum notes are drum sounds
+        let drums = song
+            .tracks
+            .iter()
+            .find(|track| is_percussion(&song, track))
+            .unwrap();
+        assert!(!drums.measures.is_empty());
+        assert_eq!(note_range(&song, drums), None);
+    }
+}
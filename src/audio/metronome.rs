@@ -0,0 +1,169 @@
+//! Metronome clicks on the General MIDI drum channel, along the playback or counting in a section.
+
+use crate::audio::midi_event::{MidiEvent, MidiEventType};
+use crate::parser::song_parser::{MeasureHeader, QUARTER_TIME};
+use std::fmt::Display;
+
+const PERCUSSION_CHANNEL: i32 = 9;
+const CLICK_DOWNBEAT_KEY: i32 = 76; // hi wood block
+const CLICK_KEY: i32 = 77; // low wood block
+const CLICK_VELOCITY: i16 = 100;
+const SUBDIVISION_VELOCITY: i16 = 60; // softer than the beats
+
+/// Clicks per beat of the metronome.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum Subdivision {
+    #[default]
+    Beat,
+    Eighth,
+    Sixteenth,
+}
+
+impl Subdivision {
+    pub const ALL: [Self; 3] = [Self::Beat, Self::Eighth, Self::Sixteenth];
+
+    /// Ticks between two clicks, never more than a beat of `beat_ticks`.
+    fn ticks(self, beat_ticks: u32) -> u32 {
+        let value = match self {
+            Self::Beat => beat_ticks,
+            Self::Eighth => QUARTER_TIME / 2,
+            Self::Sixteenth => QUARTER_TIME / 4,
+        };
+        value.min(beat_ticks).max(1)
+    }
+}
+
+impl Display for Subdivision {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Beat => write!(f, "Beats"),
+            Self::Eighth => write!(f, "Eighths"),
+            Self::Sixteenth => write!(f, "Sixteenths"),
+        }
+    }
+}
+
+/// Metronome settings, from the local configuration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct Metronome {
+    pub enabled: bool,
+    pub subdivision: Subdivision,
+    /// The first beat of the measure clicks on a higher wood block.
+    pub accent_downbeat: bool,
+}
+
+impl Default for Metronome {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            subdivision: Subdivision::Beat,
+            accent_downbeat: true,
+        }
+    }
+}
+
+impl Metronome {
+    /// Counting in a measure: the beats with the downbeat accented.
+    pub const COUNT_IN: Self = Self {
+        enabled: true,
+        subdivision: Subdivision::Beat,
+        accent_downbeat: true,
+    };
+
+    /// Clicks of the measure starting at `start`.
+    pub fn measure_clicks(self, header: &MeasureHeader, start: u32) -> Vec<MidiEvent> {
+        let beat_ticks = header.time_signature.denominator.time();
+        let step = self.subdivision.ticks(beat_ticks);
+        (0..header.length())
+            .step_by(step as usize)
+            .flat_map(|offset| {
+                let (key, velocity) = if offset == 0 && self.accent_downbeat {
+                    (CLICK_DOWNBEAT_KEY, CLICK_VELOCITY)
+                } else if offset % beat_ticks == 0 {
+                    (CLICK_KEY, CLICK_VELOCITY)
+                } else {
+                    (CLICK_KEY, SUBDIVISION_VELOCITY)
+                };
+                let tick = start + offset;
+                [
+                    MidiEvent {
+                        tick,
+                        event: MidiEventType::NoteOn(PERCUSSION_CHANNEL, key, velocity),
+                        track: None,
+                    },
+                    MidiEvent {
+                        tick: tick + step / 2,
+                        event: MidiEventType::NoteOff(PERCUSSION_CHANNEL, key),
+                        track: None,
+                    },
+                ]
+            })
+            .collect()
+    }
+
+    /// Clicks along the playback from the measures and their start ticks in playback order,
+    /// none when the metronome is off.
+    pub fn clicks(
+        self,
+        headers: &[MeasureHeader],
+        playback_starts: &[(usize, u32)],
+    ) -> Vec<MidiEvent> {
+        if !self.enabled {
+            return Vec::new();
+        }
+        playback_starts
+            .iter()
+            .flat_map(|(measure, start)| self.measure_clicks(&headers[*measure], *start))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn note_ons(clicks: &[MidiEvent]) -> Vec<(u32, i32, i16)> {
+        clicks
+            .iter()
+            .filter_map(|event| match event.event {
+                MidiEventType::NoteOn(_, key, velocity) => Some((event.tick, key, velocity)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn subdivisions_of_the_beats() {
+        // 4/4
+        let header = MeasureHeader::default();
+        let eighths = Metronome {
+            enabled: true,
+            subdivision: Subdivision::Eighth,
+            accent_downbeat: true,
+        };
+        let clicks = note_ons(&eighths.measure_clicks(&header, 1));
+        assert_eq!(clicks.len(), 8);
+        assert_eq!(clicks[0], (1, CLICK_DOWNBEAT_KEY, CLICK_VELOCITY));
+        assert_eq!(clicks[1], (481, CLICK_KEY, SUBDIVISION_VELOCITY));
+        assert_eq!(clicks[2], (961, CLICK_KEY, CLICK_VELOCITY));
+
+        // without the accent the downbeat is a beat like the others
+        let sixteenths = Metronome {
+            subdivision: Subdivision::Sixteenth,
+            accent_downbeat: false,
+            ..eighths
+        };
+        let clicks = note_ons(&sixteenths.measure_clicks(&header, 1));
+        assert_eq!(clicks.len(), 16);
+        assert_eq!(clicks[0], (1, CLICK_KEY, CLICK_VELOCITY));
+        assert_eq!(clicks[4], (961, CLICK_KEY, CLICK_VELOCITY));
+
+        // a disabled metronome does not click
+        let headers = [header];
+        assert!(Metronome::default().clicks(&headers, &[(0, 1)]).is_empty());
+        let clicks = Metronome::COUNT_IN.clicks(&headers, &[(0, 1), (0, 3841)]);
+        assert!(clicks.is_sorted_by_key(|event| event.tick));
+        assert_eq!(note_ons(&clicks).len(), 8);
+    }
+}
@@ -1,31 +1,51 @@
 /// Thanks to `TuxGuitar` for the reference implementation in `MidiSequenceParser.java`
-use crate::audio::midi_event::{FIRST_TICK, MidiEvent};
+use crate::audio::midi_event::{FIRST_TICK, MidiEvent, MidiEventType};
 use crate::audio::playback_order::playback_tick;
+use crate::audio::tempo_map::{tempo_changes, transition_steps};
 use crate::parser::song_parser::{
-    Beat, BendEffect, BendPoint, HarmonicType, MIN_VELOCITY, Measure, MeasureHeader, MidiChannel,
-    Note, NoteType, QUARTER_TIME, SEMITONE_LENGTH, Song, Track, TremoloBarEffect,
-    VELOCITY_INCREMENT,
+    Beat, BendEffect, BendPoint, DEFAULT_BANK, HarmonicType, MIN_VELOCITY, Measure, MeasureHeader,
+    MidiChannel, MixTableChange, Note, NoteType, PORT_CHANNELS, PORT_COUNT, QUARTER_TIME,
+    SEMITONE_LENGTH, Song, Track, TremoloBarEffect, VELOCITY_INCREMENT, WahEffect,
 };
-use std::rc::Rc;
+use crate::parser::warnings::warn;
+use std::num::NonZeroUsize;
+use std::panic;
+use std::sync::Arc;
+use std::thread;
 
 #[cfg(test)]
 use crate::audio::playback_order::compute_playback_order;
 
+use super::channels::{allocate_channels, allocate_port_channels};
+use super::compaction::compact_controls;
+use super::curves::{VibratoSettings, bend_segment, vibrato_curve};
 use super::effects::{
     DEFAULT_DURATION_DEAD, TripletAdjustment, apply_duration_effect, apply_static_duration,
-    apply_triplet_feel, apply_velocity_effect, compute_stroke_offsets,
+    apply_triplet_feel, apply_velocity_effect, compute_stroke_offsets, note_channel,
 };
+use super::mix_table::{MIX_TABLE_CONTROLLERS, initial_levels, track_mix_changes};
+use super::presets::PresetRemaps;
+use super::ties::TieChains;
 
 const DEFAULT_BEND: f32 = 64.0;
 const DEFAULT_BEND_SEMI_TONE: f32 = 2.75;
 
+/// Pan controller (CC 0x0A).
+const PAN_CONTROLLER: i32 = 0x0A;
+
 /// Scale a raw Guitar Pro channel byte (0-16) to a MIDI value (0-127),
-/// matching TuxGuitar's `toChannelShort`. Used for channel volume, pan,
+/// matching TuxGuitar's `toChannelShort`. Used for channel volume,
 /// chorus and reverb (raw 16 -> 127, raw 8 -> 63, raw 0 -> 0).
 fn to_channel_short(value: i8) -> i32 {
     (i32::from(value) * 8 - 1).clamp(0, 127)
 }
 
+/// Scale a raw Guitar Pro balance (0-16) to a MIDI pan (0-127) centered on 64,
+/// the value the synthesizer plays in the middle (raw 8 -> 64, raw 16 -> 127).
+fn to_pan(value: i8) -> i32 {
+    (i32::from(value) * 8).clamp(0, 127)
+}
+
 const NATURAL_FREQUENCIES: [(i32, i32); 6] = [
     (12, 12), //AH12 (+12 frets)
     (9, 28),  //AH9 (+28 frets)
@@ -36,17 +56,60 @@ const NATURAL_FREQUENCIES: [(i32, i32); 6] = [
 ];
 
 pub struct MidiBuilder {
-    events: Vec<MidiEvent>, // events accumulated during build
+    events: Vec<MidiEvent>,      // events accumulated during build
+    vibrato: VibratoSettings,    // shape of the vibrato effect
+    play_muted_tracks: bool,     // ignore the solo/mute flags from the file
+    compact_controls: bool,      // drop the redundant controller and pitch bend messages
+    preset_remaps: PresetRemaps, // programs played with another preset of the sound font
+    midi_ports: bool, // channels numbered through the ports instead of a single synthesizer
+}
+
+impl Default for MidiBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl MidiBuilder {
     pub const fn new() -> Self {
-        Self { events: Vec::new() }
+        Self {
+            events: Vec::new(),
+            vibrato: VibratoSettings::DEFAULT,
+            play_muted_tracks: false,
+            compact_controls: true,
+            preset_remaps: PresetRemaps::new(),
+            midi_ports: false,
+        }
+    }
+
+    pub const fn with_vibrato(mut self, vibrato: VibratoSettings) -> Self {
+        self.vibrato = vibrato;
+        self
+    }
+
+    pub const fn with_muted_tracks(mut self, play_muted_tracks: bool) -> Self {
+        self.play_muted_tracks = play_muted_tracks;
+        self
+    }
+
+    pub fn with_preset_remaps(mut self, preset_remaps: PresetRemaps) -> Self {
+        self.preset_remaps = preset_remaps;
+        self
+    }
+
+    pub const fn with_compaction(mut self, compact_controls: bool) -> Self {
+        self.compact_controls = compact_controls;
+        self
+    }
+
+    pub const fn with_midi_ports(mut self, midi_ports: bool) -> Self {
+        self.midi_ports = midi_ports;
+        self
     }
 
     /// Parse song and record events, computing playback order internally.
     #[cfg(test)]
-    pub fn build_for_song(self, song: &Rc<Song>) -> Vec<MidiEvent> {
+    pub fn build_for_song(self, song: &Arc<Song>) -> Vec<MidiEvent> {
         let playback_order = compute_playback_order(&song.measure_headers);
         self.build_for_song_with_order(song, &playback_order)
     }
@@ -54,38 +117,106 @@ impl MidiBuilder {
     /// Parse song and record events using a pre-computed playback order.
     pub fn build_for_song_with_order(
         mut self,
-        song: &Rc<Song>,
+        song: &Arc<Song>,
         playback_order: &[(usize, i64)],
     ) -> Vec<MidiEvent> {
-        for (track_id, track) in song.tracks.iter().enumerate() {
+        let track_channels: Vec<MidiChannel> = song
+            .tracks
+            .iter()
+            .enumerate()
+            .map(|(track_id, track)| {
+                song.midi_channels
+                    .iter()
+                    .find(|c| c.channel_id == track.channel_id)
+                    .cloned()
+                    .unwrap_or_else(|| {
+                        warn(format!(
+                            "MIDI channel {} not found for track {}, played with a piano",
+                            track.channel_id,
+                            track_id + 1
+                        ));
+                        fallback_channel(track.channel_id)
+                    })
+            })
+            .collect();
+        let track_channels: Vec<&MidiChannel> = track_channels.iter().collect();
+        let allocations = if self.midi_ports {
+            allocate_port_channels(&track_channels)
+        } else {
+            allocate_channels(&track_channels)
+        };
+        let tempo_changes = tempo_changes(song, playback_order);
+        let tempo_changes = tempo_changes.as_slice();
+        let mix_changes = track_mix_changes(song);
+        let (vibrato, midi_ports) = (self.vibrato, self.midi_ports);
+        let build_track = |track_id: usize| {
             log::debug!("building events for track {track_id}");
-            let midi_channel = song
-                .midi_channels
-                .iter()
-                .find(|c| c.channel_id == track.channel_id)
-                .unwrap_or_else(|| {
-                    panic!(
-                        "midi channel {} not found for track {}",
-                        track.channel_id, track_id
-                    )
-                });
-            self.add_track_events(
-                song.tempo.value,
+            // play the track on its allocated synthesizer channels
+            let allocation = allocations[track_id];
+            let midi_channel = MidiChannel {
+                channel_id: allocation.channel,
+                effect_channel_id: allocation.effect_channel,
+                ..*track_channels[track_id]
+            };
+            let mut builder = Self::new()
+                .with_vibrato(vibrato)
+                .with_midi_ports(midi_ports);
+            builder.add_track_events(
+                tempo_changes,
+                &mix_changes[track_id],
                 track_id,
-                track,
+                &song.tracks[track_id],
                 &song.measure_headers,
                 playback_order,
-                midi_channel,
+                &midi_channel,
             );
+            builder.events
+        };
+        // tracks are independent, they are built in chunks on at most one thread per core
+        let workers = thread::available_parallelism().map_or(1, NonZeroUsize::get);
+        let track_ids: Vec<usize> = (0..song.tracks.len()).collect();
+        let chunk_size = track_ids.len().div_ceil(workers).max(1);
+        let track_events: Vec<Vec<MidiEvent>> = thread::scope(|scope| {
+            let build_track = &build_track;
+            let handles: Vec<_> = track_ids
+                .chunks(chunk_size)
+                .map(|chunk| {
+                    scope.spawn(move || chunk.iter().map(|&id| build_track(id)).collect::<Vec<_>>())
+                })
+                .collect();
+            handles
+                .into_iter()
+                .flat_map(|handle| handle.join().unwrap_or_else(|e| panic::resume_unwind(e)))
+                .collect()
+        });
+        // merged in track order, the stable sort below keeps the sequential ordering
+        self.events.extend(track_events.into_iter().flatten());
+        if !self.play_muted_tracks {
+            // silenced tracks keep their controls and tempo changes, only the notes are dropped
+            let silenced: Vec<bool> = (0..song.tracks.len())
+                .map(|track_id| song.is_track_silenced(track_id))
+                .collect();
+            self.events.retain(|event| {
+                !matches!(event.event, MidiEventType::NoteOn(..))
+                    || !event
+                        .track
+                        .is_some_and(|track_id| silenced[usize::from(track_id)])
+            });
+        }
+        // Sort events by tick, simultaneous events in a fixed order
+        self.events.sort_by_key(MidiEvent::order_key);
+        self.preset_remaps.apply(&mut self.events);
+        if self.compact_controls {
+            compact_controls(&mut self.events);
         }
-        // Sort events by tick
-        self.events.sort_by_key(|event| event.tick);
         self.events
     }
 
+    #[allow(clippy::too_many_arguments)]
     fn add_track_events(
         &mut self,
-        song_tempo: u32,
+        tempo_changes: &[(u32, u32)],
+        mix_changes: &[Vec<(u32, MixTableChange)>],
         track_id: usize,
         track: &Track,
         measure_headers: &[MeasureHeader],
@@ -96,7 +227,9 @@ impl MidiBuilder {
         self.add_track_channel_midi_control(track_id, midi_channel);
 
         let strings = &track.strings;
-        let mut prev_tempo = song_tempo;
+        let ties = TieChains::new(track);
+        let mut tempo_changes = tempo_changes.iter().peekable();
+        let mut levels = initial_levels(midi_channel);
         assert_eq!(track.measures.len(), measure_headers.len());
         for (measure_index, tick_offset) in playback_order {
             let measure = &track.measures[*measure_index];
@@ -104,17 +237,21 @@ impl MidiBuilder {
 
             // add song info events once for all tracks
             if track_id == 0 {
-                // change tempo if necessary
-                let measure_tempo = measure_header.tempo.value;
-                if measure_tempo != prev_tempo {
-                    let tick = playback_tick(measure_header.start, *tick_offset);
-                    self.add_tempo_change(tick, measure_tempo);
-                    prev_tempo = measure_tempo;
+                // tempo changes within the measure
+                let measure_end =
+                    playback_tick(measure_header.start + measure_header.length(), *tick_offset);
+                while let Some(&(tick, tempo)) =
+                    tempo_changes.next_if(|(tick, _)| *tick < measure_end)
+                {
+                    self.add_tempo_change(tick, tempo);
                 }
             }
 
             // record event count to shift new events by tick_offset
             let event_start = self.events.len();
+            for (start, change) in &mix_changes[*measure_index] {
+                self.add_mix_table_change(*start, track_id, midi_channel, &mut levels, change);
+            }
             self.add_beat_events(
                 track_id,
                 track,
@@ -122,6 +259,7 @@ impl MidiBuilder {
                 measure_header,
                 midi_channel,
                 strings,
+                &ties,
             );
             // shift events generated for this measure by tick_offset
             if *tick_offset != 0 {
@@ -132,6 +270,7 @@ impl MidiBuilder {
         }
     }
 
+    #[allow(clippy::too_many_arguments)]
     fn add_beat_events(
         &mut self,
         track_id: usize,
@@ -140,12 +279,17 @@ impl MidiBuilder {
         measure_header: &MeasureHeader,
         midi_channel: &MidiChannel,
         strings: &[(i32, i32)],
+        ties: &TieChains,
     ) {
         let measure_id = measure.voices[0].measure_index as usize;
         for voice in &measure.voices {
             let beats = &voice.beats;
             for (beat_id, beat) in beats.iter().enumerate() {
-                if beat.empty || beat.notes.is_empty() {
+                // wah changes also happen on rests
+                if let Some(wah) = beat.effect.wah {
+                    self.add_wah(beat.start, track_id, midi_channel, wah);
+                }
+                if !beat.has_notes() {
                     continue;
                 }
                 // extract surrounding beats
@@ -175,6 +319,7 @@ impl MidiBuilder {
                     beat,
                     next_beat,
                     strings,
+                    ties,
                     triplet_adj,
                 );
             }
@@ -194,12 +339,18 @@ impl MidiBuilder {
         beat: &Beat,
         next_beat: Option<&Beat>,
         strings: &[(i32, i32)],
+        ties: &TieChains,
         triplet_adj: TripletAdjustment,
     ) {
-        let channel_id = midi_channel.channel_id;
         let tempo = measure_header.tempo.value;
-        // GP files define an effect channel per track, but TuxGuitar doesn't use it for playback.
-        assert!(channel_id < 16);
+        // channels are remapped into the synthesizer range, or the ports, at build time
+        let channel_count = if self.midi_ports {
+            PORT_CHANNELS * PORT_COUNT
+        } else {
+            PORT_CHANNELS
+        };
+        assert!(midi_channel.channel_id < channel_count);
+        assert!(midi_channel.effect_channel_id < channel_count);
         let track_offset = track.offset;
         let beat_duration = triplet_adj.duration;
         let stroke = &beat.effect.stroke;
@@ -215,15 +366,9 @@ impl MidiBuilder {
                 let mut note_start = triplet_adj.start;
 
                 // apply effects on duration
-                let mut duration = apply_duration_effect(
-                    track,
-                    measure_id,
-                    beat_id,
-                    note,
-                    next_beat,
-                    tempo,
-                    beat_duration,
-                );
+                let tied_duration = ties.tied_duration(measure_id, beat_id, note.string);
+                let mut duration =
+                    apply_duration_effect(note, tied_duration, next_beat, tempo, beat_duration);
                 assert_ne!(duration, 0);
 
                 // apply stroke effect: stagger note start times across strings
@@ -249,6 +394,9 @@ impl MidiBuilder {
                 // apply effects on velocity
                 let velocity = apply_velocity_effect(note, previous_note, midi_channel);
 
+                // pitch bending notes are isolated on the effect channel
+                let channel_id = i32::from(note_channel(note, midi_channel));
+
                 // apply effects on key
                 if let Some(key) = self.add_key_effect(
                     track_id,
@@ -260,16 +408,10 @@ impl MidiBuilder {
                     note,
                     next_note,
                     velocity,
+                    channel_id,
                     midi_channel,
                 ) {
-                    self.add_note(
-                        track_id,
-                        key,
-                        note_start,
-                        duration,
-                        velocity,
-                        i32::from(channel_id),
-                    );
+                    self.add_note(track_id, key, note_start, duration, velocity, channel_id);
                 }
             }
         }
@@ -287,9 +429,9 @@ impl MidiBuilder {
         note: &Note,
         next_note_beat: Option<(&Beat, &Note)>,
         velocity: i16,
+        channel_id: i32,
         midi_channel: &MidiChannel,
     ) -> Option<i32> {
-        let channel_id = i32::from(midi_channel.channel_id);
         let is_percussion = midi_channel.is_percussion();
 
         // compute key without effect
@@ -424,11 +566,11 @@ impl MidiBuilder {
                 let tone = ((length / points) * p_offset) * distance / length;
                 let bend = DEFAULT_BEND + (tone as f32 * DEFAULT_BEND_SEMI_TONE * 2.0);
                 let bend_tick = tick1 as i32 + (length / points) * p_offset;
-                self.add_pitch_bend(bend_tick as u32, track_id, channel_id, bend as i32);
+                self.add_pitch_bend(bend_tick as u32, track_id, channel_id, bend);
             }
 
             // normalise the bend
-            self.add_pitch_bend(tick2, track_id, channel_id, DEFAULT_BEND as i32);
+            self.add_pitch_bend(tick2, track_id, channel_id, DEFAULT_BEND);
         }
 
         // vibrato
@@ -491,26 +633,78 @@ impl MidiBuilder {
         Some(key)
     }
 
-    fn add_vibrato(&mut self, track_id: usize, start: u32, duration: u32, channel_id: i32) {
-        let end = start + duration;
-        let mut next_start = start;
-        while next_start < end {
-            next_start = if next_start + 160 > end {
-                end
-            } else {
-                next_start + 160
-            };
-            self.add_pitch_bend(next_start, track_id, channel_id, DEFAULT_BEND as i32);
+    /// The synthesizer has no filter control, the wah pedal is rendered on the expression:
+    /// open at full expression down to half expression when closed.
+    fn add_wah(&mut self, tick: u32, track_id: usize, midi_channel: &MidiChannel, wah: WahEffect) {
+        let expression = match wah {
+            WahEffect::Off => 127,
+            WahEffect::Position(position) => 127 - i32::from(position.min(100)) * 64 / 100,
+        };
+        self.add_expression(
+            tick,
+            track_id,
+            i32::from(midi_channel.channel_id),
+            expression,
+        );
+        if midi_channel.effect_channel_id != midi_channel.channel_id {
+            let effect_channel_id = i32::from(midi_channel.effect_channel_id);
+            self.add_expression(tick, track_id, effect_channel_id, expression);
+        }
+    }
 
-            next_start = if next_start + 160 > end {
-                end
+    /// Instrument and channel values set by a mix table, gradual changes start from the current `levels`.
+    fn add_mix_table_change(
+        &mut self,
+        tick: u32,
+        track_id: usize,
+        midi_channel: &MidiChannel,
+        levels: &mut [i8; 6],
+        change: &MixTableChange,
+    ) {
+        let mut channels = vec![i32::from(midi_channel.channel_id)];
+        if midi_channel.effect_channel_id != midi_channel.channel_id {
+            channels.push(i32::from(midi_channel.effect_channel_id));
+        }
+        if let Some(instrument) = change.instrument {
+            for &channel in &channels {
+                self.add_program_selection(tick, track_id, channel, instrument);
+            }
+        }
+        let items = change.items();
+        for ((item, level), controller) in items.iter().zip(levels).zip(MIX_TABLE_CONTROLLERS) {
+            let Some(item) = item else {
+                continue;
+            };
+            let scale = if controller == PAN_CONTROLLER {
+                to_pan
             } else {
-                next_start + 160
+                to_channel_short
             };
-            let value = DEFAULT_BEND + DEFAULT_BEND_SEMI_TONE / 2.0;
-            self.add_pitch_bend(next_start, track_id, channel_id, value as i32);
+            let from = i64::from(scale(*level));
+            let to = i64::from(scale(item.value));
+            for (step_tick, value) in transition_steps(tick, from, to, item.transition) {
+                for &channel in &channels {
+                    let event = MidiEvent::new_midi_message(
+                        step_tick,
+                        track_id,
+                        channel,
+                        0xB0,
+                        controller,
+                        value as i32,
+                    );
+                    self.add_event(event);
+                }
+            }
+            *level = item.value;
+        }
+    }
+
+    fn add_vibrato(&mut self, track_id: usize, start: u32, duration: u32, channel_id: i32) {
+        let semitone = DEFAULT_BEND_SEMI_TONE * 2.0;
+        let curve = vibrato_curve(start, duration, DEFAULT_BEND, semitone, &self.vibrato);
+        for (tick, value) in curve {
+            self.add_pitch_bend(tick, track_id, channel_id, value);
         }
-        self.add_pitch_bend(next_start, track_id, channel_id, DEFAULT_BEND as i32);
     }
 
     fn add_bend(
@@ -521,102 +715,65 @@ impl MidiBuilder {
         channel_id: i32,
         bend: &BendEffect,
     ) {
-        for (point_id, point) in bend.points.iter().enumerate() {
-            let value =
-                DEFAULT_BEND + (f32::from(point.value) * DEFAULT_BEND_SEMI_TONE / SEMITONE_LENGTH);
-            let value = value.clamp(0.0, 127.0) as i32;
-            let bend_start = start + point.get_time(duration);
-            self.add_pitch_bend(bend_start, track_id, channel_id, value);
-
-            // look ahead to next bend point
-            if let Some(next_point) = bend.points.get(point_id + 1) {
-                let next_value = DEFAULT_BEND
-                    + (f32::from(next_point.value) * DEFAULT_BEND_SEMI_TONE / SEMITONE_LENGTH);
-                self.process_next_bend_values(
-                    track_id,
-                    channel_id,
-                    value,
-                    next_value as i32,
-                    bend_start,
-                    start,
-                    next_point,
-                    duration,
-                );
-            }
-        }
-        self.add_pitch_bend(start + duration, track_id, channel_id, DEFAULT_BEND as i32);
+        // bend points are expressed in quarter tones
+        let value_of = |point: &BendPoint| {
+            DEFAULT_BEND + (f32::from(point.value) * DEFAULT_BEND_SEMI_TONE / SEMITONE_LENGTH)
+        };
+        self.add_bend_points(
+            track_id,
+            start,
+            duration,
+            channel_id,
+            &bend.points,
+            value_of,
+        );
     }
 
-    #[allow(clippy::too_many_arguments)]
-    fn process_next_bend_values(
+    fn add_tremolo_bar(
         &mut self,
         track_id: usize,
-        channel_id: i32,
-        mut value: i32,
-        next_value: i32,
-        mut bend_start: u32,
         start: u32,
-        next_point: &BendPoint,
         duration: u32,
+        channel_id: i32,
+        tremolo_bar: &TremoloBarEffect,
     ) {
-        if value != next_value {
-            let next_bend_start = start + next_point.get_time(duration);
-            let width = (next_bend_start - bend_start) as f32 / (next_value - value).abs() as f32;
-            let width = width as u32;
-            // ascending
-            if value < next_value {
-                while value < next_value {
-                    value += 1;
-                    bend_start += width;
-                    // clamp to 127
-                    let value = value.min(127);
-                    self.add_pitch_bend(bend_start, track_id, channel_id, value);
-                }
-            }
-            // descending
-            if value > next_value {
-                while value > next_value {
-                    value -= 1;
-                    bend_start += width;
-                    // clamp to 0
-                    let value = value.max(0);
-                    self.add_pitch_bend(bend_start, track_id, channel_id, value);
-                }
-            }
-        }
+        // tremolo bar points are expressed in semitones
+        let value_of = |point: &BendPoint| {
+            DEFAULT_BEND + (f32::from(point.value) * DEFAULT_BEND_SEMI_TONE * 2.0)
+        };
+        self.add_bend_points(
+            track_id,
+            start,
+            duration,
+            channel_id,
+            &tremolo_bar.points,
+            value_of,
+        );
     }
 
-    fn add_tremolo_bar(
+    /// Play the bend points with a linear interpolation between them,
+    /// then reset the pitch at the end of the note.
+    fn add_bend_points(
         &mut self,
         track_id: usize,
         start: u32,
         duration: u32,
         channel_id: i32,
-        tremolo_bar: &TremoloBarEffect,
+        points: &[BendPoint],
+        value_of: impl Fn(&BendPoint) -> f32,
     ) {
-        for (point_id, point) in tremolo_bar.points.iter().enumerate() {
-            let value = DEFAULT_BEND + (f32::from(point.value) * DEFAULT_BEND_SEMI_TONE * 2.0);
-            let value = value.clamp(0.0, 127.0) as i32;
-            let bend_start = start + point.get_time(duration);
-            self.add_pitch_bend(bend_start, track_id, channel_id, value);
-
-            // look ahead to next bend point
-            if let Some(next_point) = tremolo_bar.points.get(point_id + 1) {
-                let next_value =
-                    DEFAULT_BEND + (f32::from(next_point.value) * DEFAULT_BEND_SEMI_TONE * 2.0);
-                self.process_next_bend_values(
-                    track_id,
-                    channel_id,
-                    value,
-                    next_value as i32,
-                    bend_start,
-                    start,
-                    next_point,
-                    duration,
-                );
+        let mut previous: Option<(u32, f32)> = None;
+        for point in points {
+            let current = (start + point.get_time(duration), value_of(point));
+            if let Some(previous) = previous {
+                for (tick, value) in bend_segment(previous, current) {
+                    self.add_pitch_bend(tick, track_id, channel_id, value);
+                }
             }
+            self.add_pitch_bend(current.0, track_id, channel_id, current.1);
+            previous = Some(current);
         }
-        self.add_pitch_bend(start + duration, track_id, channel_id, DEFAULT_BEND as i32);
+        self.add_pitch_bend(start + duration, track_id, channel_id, DEFAULT_BEND);
     }
 
     fn add_note(
@@ -655,8 +812,8 @@ impl MidiBuilder {
     }
 
     fn add_balance_selection(&mut self, tick: u32, track_id: usize, channel: i32, balance: i32) {
-        // Pan controller (CC 0x0A).
-        let event = MidiEvent::new_midi_message(tick, track_id, channel, 0xB0, 0x0A, balance);
+        let event =
+            MidiEvent::new_midi_message(tick, track_id, channel, 0xB0, PAN_CONTROLLER, balance);
         self.add_event(event);
     }
 
@@ -681,10 +838,10 @@ impl MidiBuilder {
         self.add_event(event);
     }
 
-    fn add_pitch_bend(&mut self, tick: u32, track_id: usize, channel: i32, value: i32) {
+    fn add_pitch_bend(&mut self, tick: u32, track_id: usize, channel: i32, value: f32) {
         // GP uses a value between 0 and 128
         // MIDI uses a value between 0 and 16383 (128 * 128)
-        let midi_value = value * 128;
+        let midi_value = ((value * 128.0).round() as i32).clamp(0, 16383);
 
         // the bend value must be split into two bytes and sent to the synthesizer.
         let data1 = midi_value & 0x7F;
@@ -723,47 +880,50 @@ impl MidiBuilder {
     }
 
     fn add_track_channel_midi_control(&mut self, track_id: usize, midi_channel: &MidiChannel) {
-        let channel_id = midi_channel.channel_id;
+        // the effect channel plays the same instrument as the main channel
+        self.add_channel_midi_control(track_id, midi_channel.channel_id, midi_channel);
+        if midi_channel.effect_channel_id != midi_channel.channel_id {
+            self.add_channel_midi_control(track_id, midi_channel.effect_channel_id, midi_channel);
+        }
+    }
+
+    fn add_channel_midi_control(
+        &mut self,
+        track_id: usize,
+        channel_id: u8,
+        midi_channel: &MidiChannel,
+    ) {
         // publish MIDI control messages for the track channel at the start
         let info_tick = FIRST_TICK;
+        let channel_id = i32::from(channel_id);
         self.add_volume_selection(
             info_tick,
             track_id,
-            i32::from(channel_id),
+            channel_id,
             to_channel_short(midi_channel.volume),
         );
         self.add_balance_selection(
             info_tick,
             track_id,
-            i32::from(channel_id),
-            to_channel_short(midi_channel.balance),
+            channel_id,
+            to_pan(midi_channel.balance),
         );
-        self.add_expression_selection(info_tick, track_id, i32::from(channel_id), 127);
+        self.add_expression_selection(info_tick, track_id, channel_id, 127);
         self.add_chorus_selection(
             info_tick,
             track_id,
-            i32::from(channel_id),
+            channel_id,
             to_channel_short(midi_channel.chorus),
         );
         self.add_reverb_selection(
             info_tick,
             track_id,
-            i32::from(channel_id),
+            channel_id,
             to_channel_short(midi_channel.reverb),
         );
-        self.add_bank_selection(
-            info_tick,
-            track_id,
-            i32::from(channel_id),
-            i32::from(midi_channel.bank),
-        );
-        self.add_program_selection(
-            info_tick,
-            track_id,
-            i32::from(channel_id),
-            midi_channel.instrument,
-        );
-        self.add_pitch_bend_range(info_tick, track_id, i32::from(channel_id));
+        self.add_bank_selection(info_tick, track_id, channel_id, midi_bank(midi_channel));
+        self.add_program_selection(info_tick, track_id, channel_id, midi_channel.instrument);
+        self.add_pitch_bend_range(info_tick, track_id, channel_id);
     }
 
     fn add_event(&mut self, event: MidiEvent) {
@@ -771,18 +931,79 @@ impl MidiBuilder {
     }
 }
 
+/// Bank select value of the channel: the percussion channel is in the drum bank by itself,
+/// its bank select only picks a kit bank within it.
+/// Melodic channel at full volume for a track pointing at a channel the song does not have,
+/// e.g. in an edited `.ruxg` file.
+const fn fallback_channel(channel_id: u8) -> MidiChannel {
+    let channel_id = if channel_id < PORT_CHANNELS * PORT_COUNT {
+        channel_id
+    } else {
+        0
+    };
+    MidiChannel {
+        channel_id,
+        effect_channel_id: channel_id,
+        instrument: 0,
+        volume: 16, // full
+        balance: 8, // centered
+        chorus: 0,
+        reverb: 0,
+        phaser: 0,
+        tremolo: 0,
+        bank: DEFAULT_BANK,
+    }
+}
+
+const fn midi_bank(midi_channel: &MidiChannel) -> i32 {
+    if midi_channel.is_percussion() {
+        0
+    } else {
+        midi_channel.bank as i32
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use super::to_channel_short;
+    use super::{midi_bank, to_channel_short, to_pan};
+    use crate::parser::song_parser::{DEFAULT_BANK, DEFAULT_PERCUSSION_BANK, MidiChannel};
 
     #[test]
     fn channel_short_scaling() {
         // Raw Guitar Pro channel scale (0-16) -> MIDI (0-127), per TuxGuitar.
-        assert_eq!(to_channel_short(0), 0); // silent / hard left
-        assert_eq!(to_channel_short(8), 63); // half / center
-        assert_eq!(to_channel_short(16), 127); // full / hard right
+        assert_eq!(to_channel_short(0), 0); // silent
+        assert_eq!(to_channel_short(8), 63); // half
+        assert_eq!(to_channel_short(16), 127); // full
         // Out-of-range values are clamped.
         assert_eq!(to_channel_short(-1), 0);
         assert_eq!(to_channel_short(127), 127);
     }
+
+    #[test]
+    fn pan_scaling() {
+        assert_eq!(to_pan(0), 0); // hard left
+        assert_eq!(to_pan(8), 64); // center
+        assert_eq!(to_pan(16), 127); // hard right
+        assert_eq!(to_pan(-1), 0);
+    }
+
+    #[test]
+    fn percussion_bank_selection() {
+        let mut midi_channel = MidiChannel {
+            channel_id: 9,
+            effect_channel_id: 9,
+            instrument: 25,
+            volume: 16,
+            balance: 8,
+            chorus: 0,
+            reverb: 0,
+            phaser: 0,
+            tremolo: 0,
+            bank: DEFAULT_PERCUSSION_BANK,
+        };
+        // the synthesizer adds the drum bank on the percussion channel
+        assert_eq!(midi_bank(&midi_channel), 0);
+        midi_channel.bank = DEFAULT_BANK;
+        assert_eq!(midi_bank(&midi_channel), i32::from(DEFAULT_BANK));
+    }
 }
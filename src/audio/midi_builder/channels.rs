@@ -0,0 +1,319 @@
+//! Assign synthesizer channels to tracks at build time.
+//! Guitar Pro addresses up to 64 channels (4 ports of 16) and lets tracks point
+//! at any of them, while the synthesizer only has 16 channels with 9 reserved for percussion.
+
+use crate::parser::song_parser::{MidiChannel, PORT_CHANNELS};
+
+pub(super) const PERCUSSION_CHANNEL: u8 = 9;
+const MIDI_CHANNEL_COUNT: u8 = 16;
+
+/// Channels used by a track during playback.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) struct TrackChannels {
+    pub channel: u8,
+    pub effect_channel: u8,
+}
+
+struct ChannelPool {
+    used: [bool; MIDI_CHANNEL_COUNT as usize],
+}
+
+impl ChannelPool {
+    const fn new() -> Self {
+        let mut used = [false; MIDI_CHANNEL_COUNT as usize];
+        used[PERCUSSION_CHANNEL as usize] = true;
+        Self { used }
+    }
+
+    /// Claim the requested channel if it is valid and still free.
+    fn claim(&mut self, channel: u8) -> Option<u8> {
+        let slot = self.used.get_mut(channel as usize)?;
+        if *slot {
+            None
+        } else {
+            *slot = true;
+            Some(channel)
+        }
+    }
+
+    /// Claim the lowest free channel.
+    fn claim_any(&mut self) -> Option<u8> {
+        let channel = self.used.iter().position(|used| !used)?;
+        self.used[channel] = true;
+        Some(channel as u8)
+    }
+}
+
+/// Map each track's parsed channel to a distinct synthesizer channel.
+///
+/// Tracks pointing at the same parsed channel share their allocation.
+/// Parsed channel ids are kept when possible, the others take the lowest free channel.
+/// Once all channels are taken, a channel playing the same instrument is shared, or one is reused in rotation.
+/// Effect channels are only handed out after all tracks have a main channel and fall back to it.
+pub(super) fn allocate_channels(track_channels: &[&MidiChannel]) -> Vec<TrackChannels> {
+    let mut pool = ChannelPool::new();
+    // allocation per distinct parsed channel id
+    let mut allocated: Vec<(u8, TrackChannels)> = Vec::new();
+    let mut pending: Vec<&MidiChannel> = Vec::new();
+
+    // keep the parsed channel ids that are valid
+    for midi_channel in track_channels {
+        let parsed_id = midi_channel.channel_id;
+        if allocated.iter().any(|(id, _)| *id == parsed_id)
+            || pending.iter().any(|c| c.channel_id == parsed_id)
+        {
+            continue;
+        }
+        let channel = if midi_channel.is_percussion() {
+            Some(PERCUSSION_CHANNEL)
+        } else {
+            pool.claim(parsed_id)
+        };
+        match channel {
+            Some(channel) => allocated.push((
+                parsed_id,
+                TrackChannels {
+                    channel,
+                    effect_channel: channel,
+                },
+            )),
+            None => pending.push(midi_channel),
+        }
+    }
+
+    // move the remaining ones to free channels
+    let mut rotation = 0;
+    for midi_channel in pending {
+        let channel = pool.claim_any().unwrap_or_else(|| {
+            let shared = track_channels
+                .iter()
+                .filter(|c| c.instrument == midi_channel.instrument && c.bank == midi_channel.bank)
+                .find_map(|c| allocated.iter().find(|(id, _)| *id == c.channel_id))
+                .map(|(_, allocation)| allocation.channel);
+            shared.unwrap_or_else(|| {
+                let melodic: Vec<u8> = (0..MIDI_CHANNEL_COUNT)
+                    .filter(|c| *c != PERCUSSION_CHANNEL)
+                    .collect();
+                let channel = melodic[rotation % melodic.len()];
+                rotation += 1;
+                channel
+            })
+        });
+        log::warn!(
+            "Remapping MIDI channel {} to channel {channel}",
+            midi_channel.channel_id
+        );
+        allocated.push((
+            midi_channel.channel_id,
+            TrackChannels {
+                channel,
+                effect_channel: channel,
+            },
+        ));
+    }
+
+    // hand out effect channels with what is left
+    for midi_channel in track_channels {
+        if midi_channel.is_percussion() || midi_channel.effect_channel_id == midi_channel.channel_id
+        {
+            continue;
+        }
+        let Some((_, allocation)) = allocated
+            .iter_mut()
+            .find(|(id, _)| *id == midi_channel.channel_id)
+        else {
+            continue;
+        };
+        if allocation.effect_channel != allocation.channel {
+            continue;
+        }
+        if let Some(effect_channel) = pool
+            .claim(midi_channel.effect_channel_id)
+            .or_else(|| pool.claim_any())
+        {
+            allocation.effect_channel = effect_channel;
+        }
+    }
+
+    track_channels
+        .iter()
+        .map(|c| {
+            allocated
+                .iter()
+                .find(|(id, _)| *id == c.channel_id)
+                .map(|(_, allocation)| *allocation)
+                .expect("all channels allocated")
+        })
+        .collect()
+}
+
+/// Allocate the channels of each port on its own, for outputs addressing the ports.
+///
+/// The channels stay numbered through the ports: a track on the second port gets a channel from 16 to 31.
+/// Effect channels on another port than their track are ignored.
+pub(super) fn allocate_port_channels(track_channels: &[&MidiChannel]) -> Vec<TrackChannels> {
+    let mut allocations = vec![
+        TrackChannels {
+            channel: 0,
+            effect_channel: 0,
+        };
+        track_channels.len()
+    ];
+    let mut ports: Vec<u8> = track_channels.iter().map(|c| c.port()).collect();
+    ports.sort_unstable();
+    ports.dedup();
+    for port in ports {
+        let offset = port * PORT_CHANNELS;
+        let (indexes, port_channels): (Vec<usize>, Vec<MidiChannel>) = track_channels
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| c.port() == port)
+            .map(|(index, c)| {
+                let effect_channel_id = if c.effect_channel_id / PORT_CHANNELS == port {
+                    c.effect_channel_id % PORT_CHANNELS
+                } else {
+                    c.port_channel()
+                };
+                let channel = MidiChannel {
+                    channel_id: c.port_channel(),
+                    effect_channel_id,
+                    ..**c
+                };
+                (index, channel)
+            })
+            .unzip();
+        let refs: Vec<&MidiChannel> = port_channels.iter().collect();
+        for (index, allocation) in indexes.into_iter().zip(allocate_channels(&refs)) {
+            allocations[index] = TrackChannels {
+                channel: allocation.channel + offset,
+                effect_channel: allocation.effect_channel + offset,
+            };
+        }
+    }
+    allocations
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::song_parser::{DEFAULT_BANK, DEFAULT_PERCUSSION_BANK};
+
+    fn channel(channel_id: u8, effect_channel_id: u8, instrument: i32) -> MidiChannel {
+        MidiChannel {
+            channel_id,
+            effect_channel_id,
+            instrument,
+            volume: 16,
+            balance: 8,
+            chorus: 0,
+            reverb: 0,
+            phaser: 0,
+            tremolo: 0,
+            bank: DEFAULT_BANK,
+        }
+    }
+
+    fn percussion(channel_id: u8) -> MidiChannel {
+        MidiChannel {
+            bank: DEFAULT_PERCUSSION_BANK,
+            ..channel(channel_id, channel_id, 0)
+        }
+    }
+
+    const fn allocation(channel: u8, effect_channel: u8) -> TrackChannels {
+        TrackChannels {
+            channel,
+            effect_channel,
+        }
+    }
+
+    #[test]
+    fn keeps_valid_channels() {
+        let guitar = channel(0, 1, 30);
+        let bass = channel(2, 3, 33);
+        let drums = percussion(9);
+        let allocations = allocate_channels(&[&guitar, &bass, &drums]);
+        assert_eq!(
+            allocations,
+            vec![allocation(0, 1), allocation(2, 3), allocation(9, 9)]
+        );
+    }
+
+    #[test]
+    fn shared_channel_shares_allocation() {
+        let guitar = channel(4, 5, 30);
+        let allocations = allocate_channels(&[&guitar, &guitar]);
+        assert_eq!(allocations, vec![allocation(4, 5), allocation(4, 5)]);
+    }
+
+    #[test]
+    fn remaps_channels_beyond_first_port() {
+        let guitar = channel(0, 0, 30);
+        let second_port = channel(16, 16, 29);
+        let misplaced_on_percussion = channel(9, 9, 27);
+        let allocations = allocate_channels(&[&guitar, &second_port, &misplaced_on_percussion]);
+        assert_eq!(
+            allocations,
+            vec![allocation(0, 0), allocation(1, 1), allocation(2, 2)]
+        );
+    }
+
+    #[test]
+    fn percussion_on_any_channel_uses_channel_nine() {
+        let drums = percussion(25);
+        let guitar = channel(9, 9, 30);
+        let allocations = allocate_channels(&[&drums, &guitar]);
+        assert_eq!(allocations, vec![allocation(9, 9), allocation(0, 0)]);
+    }
+
+    #[test]
+    fn main_channels_take_priority_over_effect_channels() {
+        let tracks: Vec<MidiChannel> = (0..15).map(|i| channel(i * 2, i * 2 + 1, 30)).collect();
+        let refs: Vec<&MidiChannel> = tracks.iter().collect();
+        let allocations = allocate_channels(&refs);
+        let mut main_channels: Vec<u8> = allocations.iter().map(|a| a.channel).collect();
+        main_channels.sort_unstable();
+        main_channels.dedup();
+        assert_eq!(main_channels.len(), 15);
+        assert!(!main_channels.contains(&PERCUSSION_CHANNEL));
+        // no channel left for effects
+        assert!(allocations.iter().all(|a| a.effect_channel == a.channel));
+    }
+
+    #[test]
+    fn ports_keep_their_channels() {
+        let guitar = channel(0, 1, 30);
+        let second_port = channel(16, 17, 29);
+        let second_port_drums = percussion(25);
+        let misplaced_on_percussion = channel(41, 41, 27);
+        let allocations = allocate_port_channels(&[
+            &guitar,
+            &second_port,
+            &second_port_drums,
+            &misplaced_on_percussion,
+        ]);
+        assert_eq!(
+            allocations,
+            vec![
+                allocation(0, 1),
+                allocation(16, 17),
+                allocation(25, 25),
+                allocation(32, 32)
+            ]
+        );
+    }
+
+    #[test]
+    fn exhausted_channels_are_shared() {
+        let mut tracks: Vec<MidiChannel> = (16..31).map(|i| channel(i, i, i32::from(i))).collect();
+        // same instrument as the first track
+        tracks.push(channel(40, 40, 16));
+        // unique instrument
+        tracks.push(channel(41, 41, 100));
+        let refs: Vec<&MidiChannel> = tracks.iter().collect();
+        let allocations = allocate_channels(&refs);
+        assert!(allocations.iter().all(|a| a.channel < 16));
+        assert_eq!(allocations[15].channel, allocations[0].channel);
+        assert_eq!(allocations[16].channel, 0);
+    }
+}
@@ -0,0 +1,58 @@
+//! Removal of the controller and pitch bend messages that do not change anything.
+//! Bend and vibrato curves are sampled at a fixed rate and resend the same value
+//! for as long as the pitch holds.
+
+use crate::audio::midi_event::{MidiEvent, MidiEventType};
+use std::collections::{HashMap, HashSet};
+
+/// Controllers holding a continuous value: volume, pan, expression, reverb, tremolo, chorus and phaser.
+/// Others are left alone, e.g. the RPN messages must be sent as a whole sequence.
+const CONTINUOUS_CONTROLLERS: [i32; 7] = [0x07, 0x0A, 0x0B, 0x5B, 0x5C, 0x5D, 0x5F];
+
+/// Channel state written by a message: (channel, command, controller).
+type Control = (i32, i32, i32);
+
+/// Control written by the event and the value it sets, if it can be compacted.
+fn control_value(event: &MidiEvent) -> Option<(Control, (i32, i32))> {
+    match event.event {
+        MidiEventType::MidiMessage(channel, 0xE0, data1, data2) => {
+            Some(((channel, 0xE0, 0), (data1, data2)))
+        }
+        MidiEventType::MidiMessage(channel, 0xB0, controller, value)
+            if CONTINUOUS_CONTROLLERS.contains(&controller) =>
+        {
+            Some(((channel, 0xB0, controller), (value, 0)))
+        }
+        _ => None,
+    }
+}
+
+/// Drop the compactable messages of sorted events that are either overwritten at the same tick
+/// or resend the value the channel already has. The channel state is unchanged at every tick.
+pub(super) fn compact_controls(events: &mut Vec<MidiEvent>) {
+    let mut keep = vec![true; events.len()];
+
+    // only the last value written at a tick is heard
+    let mut written: HashSet<(u32, Control)> = HashSet::new();
+    for (index, event) in events.iter().enumerate().rev() {
+        if let Some((control, _)) = control_value(event)
+            && !written.insert((event.tick, control))
+        {
+            keep[index] = false;
+        }
+    }
+
+    // values already set on the channel
+    let mut current: HashMap<Control, (i32, i32)> = HashMap::new();
+    for (index, event) in events.iter().enumerate() {
+        if keep[index]
+            && let Some((control, value)) = control_value(event)
+            && current.insert(control, value) == Some(value)
+        {
+            keep[index] = false;
+        }
+    }
+
+    let mut keep = keep.into_iter();
+    events.retain(|_| keep.next().unwrap_or(true));
+}
@@ -0,0 +1,151 @@
+//! Pitch bend curves for vibrato, bends and tremolo bar.
+//! Curves are sampled as `(tick, value)` points on the Guitar Pro bend scale (0-128, 64 is no bend).
+
+use crate::parser::song_parser::QUARTER_TIME;
+use serde::{Deserialize, Serialize};
+use std::f32::consts::TAU;
+
+/// Interval between two interpolated pitch bend values.
+const BEND_STEP_TICKS: u32 = QUARTER_TIME / 32;
+
+/// Pitch bend values sampled per vibrato cycle.
+const VIBRATO_STEPS_PER_CYCLE: u32 = 16;
+
+/// Vibrato shape, from the local configuration.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VibratoSettings {
+    /// Oscillations per quarter note.
+    pub rate: f32,
+    /// Pitch deviation in semitones on each side of the note.
+    pub depth: f32,
+}
+
+impl Default for VibratoSettings {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+impl VibratoSettings {
+    pub const DEFAULT: Self = Self {
+        rate: 3.0,
+        depth: 0.25,
+    };
+
+    /// Length of a vibrato cycle in ticks.
+    fn period(&self) -> u32 {
+        let rate = if self.rate.is_finite() && self.rate > 0.0 {
+            self.rate
+        } else {
+            Self::DEFAULT.rate
+        };
+        ((QUARTER_TIME as f32 / rate) as u32).max(VIBRATO_STEPS_PER_CYCLE)
+    }
+}
+
+/// Sine shaped vibrato around `center` over `[start, start + duration]`.
+/// `semitone` is the bend scale value of one semitone.
+/// The curve ends on `center` so the next note is not detuned.
+pub(super) fn vibrato_curve(
+    start: u32,
+    duration: u32,
+    center: f32,
+    semitone: f32,
+    settings: &VibratoSettings,
+) -> Vec<(u32, f32)> {
+    let end = start + duration;
+    let period = settings.period();
+    let step = period / VIBRATO_STEPS_PER_CYCLE;
+    let amplitude = settings.depth.clamp(0.0, 12.0) * semitone;
+    let mut curve = Vec::new();
+    let mut tick = start;
+    while tick < end {
+        let phase = (tick - start) as f32 / period as f32;
+        let value = center + amplitude * (phase * TAU).sin();
+        curve.push((tick, value));
+        tick += step;
+    }
+    curve.push((end, center));
+    curve
+}
+
+/// Linear interpolation strictly between two bend points, one value every `BEND_STEP_TICKS`.
+pub(super) fn bend_segment(from: (u32, f32), to: (u32, f32)) -> Vec<(u32, f32)> {
+    let (start, start_value) = from;
+    let (end, end_value) = to;
+    if end <= start || start_value == end_value {
+        return Vec::new();
+    }
+    let length = (end - start) as f32;
+    (start + BEND_STEP_TICKS..end)
+        .step_by(BEND_STEP_TICKS as usize)
+        .map(|tick| {
+            let progress = (tick - start) as f32 / length;
+            (tick, start_value + (end_value - start_value) * progress)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vibrato_follows_sine() {
+        let settings = VibratoSettings::default();
+        let curve = vibrato_curve(0, QUARTER_TIME, 64.0, 5.5, &settings);
+        // 3 cycles of 320 ticks sampled every 20 ticks plus the closing point
+        assert_eq!(curve.len(), 49);
+        assert_eq!(curve[0], (0, 64.0));
+        // peak a quarter cycle in, trough three quarters in
+        let (tick, value) = curve[4];
+        assert_eq!(tick, 80);
+        assert!((value - (64.0 + 0.25 * 5.5)).abs() < 0.001);
+        let (tick, value) = curve[12];
+        assert_eq!(tick, 240);
+        assert!((value - (64.0 - 0.25 * 5.5)).abs() < 0.001);
+        assert_eq!(curve.last(), Some(&(QUARTER_TIME, 64.0)));
+    }
+
+    #[test]
+    fn vibrato_rate_and_depth() {
+        let settings = VibratoSettings {
+            rate: 1.0,
+            depth: 1.0,
+        };
+        let curve = vibrato_curve(100, QUARTER_TIME, 64.0, 5.5, &settings);
+        assert_eq!(curve.len(), 17);
+        let max = curve.iter().map(|(_, v)| *v).fold(f32::MIN, f32::max);
+        let min = curve.iter().map(|(_, v)| *v).fold(f32::MAX, f32::min);
+        assert!((max - 69.5).abs() < 0.001);
+        assert!((min - 58.5).abs() < 0.001);
+    }
+
+    #[test]
+    fn vibrato_invalid_rate_uses_default() {
+        let settings = VibratoSettings {
+            rate: 0.0,
+            depth: 0.25,
+        };
+        assert_eq!(settings.period(), VibratoSettings::DEFAULT.period());
+    }
+
+    #[test]
+    fn bend_segment_interpolates() {
+        let segment = bend_segment((0, 64.0), (120, 75.0));
+        assert_eq!(
+            segment,
+            vec![(30, 66.75), (60, 69.5), (90, 72.25)],
+            "end point excluded"
+        );
+        assert!(bend_segment((0, 64.0), (120, 64.0)).is_empty());
+        assert!(bend_segment((120, 64.0), (120, 70.0)).is_empty());
+    }
+
+    #[test]
+    fn bend_segment_descending() {
+        let segment = bend_segment((0, 75.0), (60, 64.0));
+        assert_eq!(segment, vec![(30, 69.5)]);
+    }
+}
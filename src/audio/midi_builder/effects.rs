@@ -3,12 +3,14 @@
 //! with no MIDI/event-emitting side effects.
 
 use crate::parser::song_parser::{
-    Beat, BeatStrokeDirection, MIN_VELOCITY, MidiChannel, Note, NoteType, QUARTER_TIME, Track,
+    Beat, BeatStrokeDirection, MIN_VELOCITY, MidiChannel, Note, NoteType, QUARTER_TIME, SlapEffect,
     TripletFeel, VELOCITY_INCREMENT,
 };
 
 pub(super) const DEFAULT_DURATION_DEAD: u32 = 30;
 const DEFAULT_DURATION_PM: u32 = 60;
+// percentage of the duration heard for slapped and popped notes, the string is muted quickly
+const SLAP_DURATION_PERCENTAGE: u32 = 75;
 
 pub(super) fn apply_velocity_effect(
     note: &Note,
@@ -18,10 +20,21 @@ pub(super) fn apply_velocity_effect(
     let effect = &note.effect;
     let mut velocity = note.velocity;
 
-    if !midi_channel.is_percussion() && previous_note.is_some_and(|n| n.effect.hammer) {
+    // a tapped note is struck even when it follows a hammer-on
+    if !midi_channel.is_percussion()
+        && previous_note.is_some_and(|n| n.effect.hammer)
+        && effect.slap != SlapEffect::Tapping
+    {
         velocity = MIN_VELOCITY.max(velocity - 25);
     }
 
+    // the thumb slap and the pulled string pop are accented, popping the most
+    match effect.slap {
+        SlapEffect::Slapping => velocity += VELOCITY_INCREMENT,
+        SlapEffect::Popping => velocity += VELOCITY_INCREMENT * 2,
+        SlapEffect::Tapping | SlapEffect::None => {}
+    }
+
     if effect.ghost_note {
         velocity = MIN_VELOCITY.max(velocity - VELOCITY_INCREMENT);
     } else if effect.accentuated_note {
@@ -32,36 +45,31 @@ pub(super) fn apply_velocity_effect(
     velocity.min(127)
 }
 
+/// Channel playing the note: like TuxGuitar, notes bending the pitch go to the effect
+/// channel so the pitch bend messages do not warp the other notes of the track.
+pub(super) const fn note_channel(note: &Note, midi_channel: &MidiChannel) -> u8 {
+    let effect = &note.effect;
+    let bends_pitch = effect.bend.is_some()
+        || effect.tremolo_bar.is_some()
+        || effect.slide.is_some()
+        || effect.vibrato;
+    if bends_pitch && !midi_channel.is_percussion() {
+        midi_channel.effect_channel_id
+    } else {
+        midi_channel.channel_id
+    }
+}
+
 pub(super) fn apply_duration_effect(
-    track: &Track,
-    measure_id: usize,
-    beat_id: usize,
     note: &Note,
+    tied_duration: u32,
     first_next_beat: Option<&Beat>,
     tempo: u32,
     mut duration: u32,
 ) -> u32 {
     let note_type = &note.kind;
-    let next_beats_in_next_measures = track.measures[measure_id..]
-        .iter()
-        .flat_map(|m| m.voices[0].beats.iter())
-        .skip(beat_id + 1); // skip current and previous beats
-
     // handle chains of tie notes
-    for next_beat in next_beats_in_next_measures {
-        // filter for only next notes on matching string
-        if let Some(next_note) = next_beat.notes.iter().find(|n| n.string == note.string) {
-            if next_note.kind == NoteType::Tie {
-                duration += next_beat.duration.time();
-            } else {
-                // stop chain
-                break;
-            }
-        } else {
-            // break chain of tie notes
-            break;
-        }
-    }
+    duration += tied_duration;
     // hande let-ring
     if let Some(first_next_beat) = first_next_beat
         && note.effect.let_ring
@@ -77,6 +85,9 @@ pub(super) fn apply_duration_effect(
     if note.effect.staccato {
         return (duration as f32 * 50.0 / 100.00) as u32;
     }
+    if matches!(note.effect.slap, SlapEffect::Slapping | SlapEffect::Popping) {
+        return (duration * SLAP_DURATION_PERCENTAGE / 100).max(1);
+    }
     duration
 }
 
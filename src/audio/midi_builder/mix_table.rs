@@ -0,0 +1,63 @@
+//! Instrument and channel changes from the mix tables of the beats.
+
+use crate::parser::song_parser::{MidiChannel, MixTableChange, MixTableItem, Song};
+
+/// Controllers of the mix table channel values, in the order of `MixTableChange::items`:
+/// volume, pan, chorus, reverb, phaser and tremolo.
+pub(super) const MIX_TABLE_CONTROLLERS: [i32; 6] = [0x07, 0x0A, 0x5D, 0x5B, 0x5F, 0x5C];
+
+/// Mix table changes heard by each track, `[track][measure]` lists of (beat start, change) sorted by start.
+/// The values of a change flagged for all tracks are heard by the other tracks as well.
+pub(super) fn track_mix_changes(song: &Song) -> Vec<Vec<Vec<(u32, MixTableChange)>>> {
+    let measure_count = song.measure_headers.len();
+    let mut changes = vec![vec![Vec::new(); measure_count]; song.tracks.len()];
+    for (track_id, track) in song.tracks.iter().enumerate() {
+        for (measure_index, measure) in track.measures.iter().enumerate() {
+            for beat in measure.voices.iter().flat_map(|voice| &voice.beats) {
+                let Some(change) = &beat.mix_table_change else {
+                    continue;
+                };
+                let shared = all_tracks_values(change);
+                for (other_id, other_changes) in changes.iter_mut().enumerate() {
+                    if other_id == track_id {
+                        other_changes[measure_index].push((beat.start, change.clone()));
+                    } else if let Some(shared) = &shared {
+                        other_changes[measure_index].push((beat.start, shared.clone()));
+                    }
+                }
+            }
+        }
+    }
+    for measure_changes in changes.iter_mut().flatten() {
+        measure_changes.sort_by_key(|(start, _)| *start);
+    }
+    changes
+}
+
+/// Channel values of the change flagged for all tracks, `None` if there are none.
+fn all_tracks_values(change: &MixTableChange) -> Option<MixTableChange> {
+    let shared = |item: Option<MixTableItem>| item.filter(|item| item.all_tracks);
+    let values = MixTableChange {
+        volume: shared(change.volume),
+        balance: shared(change.balance),
+        chorus: shared(change.chorus),
+        reverb: shared(change.reverb),
+        phaser: shared(change.phaser),
+        tremolo: shared(change.tremolo),
+        ..MixTableChange::default()
+    };
+    (values != MixTableChange::default()).then_some(values)
+}
+
+/// Mix table channel values of a track in the Guitar Pro channel scale,
+/// the starting point of gradual changes.
+pub(super) const fn initial_levels(midi_channel: &MidiChannel) -> [i8; 6] {
+    [
+        midi_channel.volume,
+        midi_channel.balance,
+        midi_channel.chorus,
+        midi_channel.reverb,
+        midi_channel.phaser,
+        midi_channel.tremolo,
+    ]
+}
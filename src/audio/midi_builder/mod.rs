@@ -1,6 +1,14 @@
 mod builder;
+mod channels;
+mod compaction;
+mod curves;
 mod effects;
+mod mix_table;
+mod presets;
 #[cfg(test)]
 mod tests;
+mod ties;
 
 pub use builder::MidiBuilder;
+pub use curves::VibratoSettings;
+pub use presets::{PresetId, PresetRemaps};
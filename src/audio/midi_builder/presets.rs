@@ -0,0 +1,124 @@
+//! Programs played with another preset of the sound font, for sound fonts missing some GM programs.
+
+use crate::audio::midi_builder::channels::PERCUSSION_CHANNEL;
+use crate::audio::midi_event::{MidiEvent, MidiEventType};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Display;
+
+/// Preset of a sound font, selected with a bank select followed by a program change.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct PresetId {
+    pub bank: u8,
+    pub patch: u8,
+}
+
+impl Display for PresetId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:03}:{:03}", self.bank, self.patch)
+    }
+}
+
+/// Preset played instead of a program, the percussion channel is left alone.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PresetRemaps(BTreeMap<u8, PresetId>);
+
+impl PresetRemaps {
+    pub const fn new() -> Self {
+        Self(BTreeMap::new())
+    }
+
+    pub fn get(&self, program: u8) -> Option<PresetId> {
+        self.0.get(&program).copied()
+    }
+
+    /// Play the program with the preset, or with its own preset again if `None`.
+    pub fn set(&mut self, program: u8, preset: Option<PresetId>) {
+        match preset {
+            Some(preset) => self.0.insert(program, preset),
+            None => self.0.remove(&program),
+        };
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Rewrite the program changes of the remapped programs, each program change
+    /// is preceded by its bank select so the bank of a remap does not stick to the channel.
+    pub(super) fn apply(&self, events: &mut Vec<MidiEvent>) {
+        if self.is_empty() {
+            return;
+        }
+        let mut file_banks: HashMap<i32, i32> = HashMap::new();
+        let mut remapped = Vec::with_capacity(events.len());
+        for event in events.drain(..) {
+            match event.event {
+                MidiEventType::MidiMessage(channel, 0xB0, 0x00, bank)
+                    if channel != i32::from(PERCUSSION_CHANNEL) =>
+                {
+                    // sent again with the next program change
+                    file_banks.insert(channel, bank);
+                }
+                MidiEventType::MidiMessage(channel, 0xC0, program, data2)
+                    if channel != i32::from(PERCUSSION_CHANNEL) =>
+                {
+                    let file_bank = file_banks.get(&channel).copied().unwrap_or(0);
+                    let preset = u8::try_from(program).ok().and_then(|p| self.get(p));
+                    let (bank, patch) = preset.map_or((file_bank, program), |preset| {
+                        (i32::from(preset.bank), i32::from(preset.patch))
+                    });
+                    let bank_select = MidiEventType::MidiMessage(channel, 0xB0, 0x00, bank);
+                    remapped.push(MidiEvent {
+                        event: bank_select,
+                        ..event.clone()
+                    });
+                    remapped.push(MidiEvent {
+                        event: MidiEventType::MidiMessage(channel, 0xC0, patch, data2),
+                        ..event
+                    });
+                }
+                _ => remapped.push(event),
+            }
+        }
+        *events = remapped;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(tick: u32, channel: i32, command: i32, data1: i32, data2: i32) -> MidiEvent {
+        MidiEvent::new_midi_message(tick, 0, channel, command, data1, data2)
+    }
+
+    #[test]
+    fn remapped_programs_select_their_preset() {
+        let mut remaps = PresetRemaps::new();
+        remaps.set(30, Some(PresetId { bank: 8, patch: 29 }));
+        remaps.set(25, Some(PresetId { bank: 0, patch: 24 }));
+        remaps.set(25, None);
+
+        let mut events = vec![
+            message(1, 0, 0xB0, 0x00, 0),
+            message(1, 0, 0xC0, 30, 0),
+            message(1, 9, 0xC0, 30, 0),
+            message(960, 0, 0xC0, 25, 0),
+        ];
+        remaps.apply(&mut events);
+        let messages: Vec<MidiEventType> = events.into_iter().map(|e| e.event).collect();
+        assert_eq!(
+            messages,
+            vec![
+                MidiEventType::MidiMessage(0, 0xB0, 0x00, 8),
+                MidiEventType::MidiMessage(0, 0xC0, 29, 0),
+                // percussion kits are not remapped
+                MidiEventType::MidiMessage(9, 0xC0, 30, 0),
+                // the file bank is restored for the next program
+                MidiEventType::MidiMessage(0, 0xB0, 0x00, 0),
+                MidiEventType::MidiMessage(0, 0xC0, 25, 0),
+            ]
+        );
+    }
+}
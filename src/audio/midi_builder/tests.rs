@@ -1,16 +1,25 @@
 use super::MidiBuilder;
-use super::effects::{apply_triplet_feel, compute_stroke_offsets};
+use super::compaction::compact_controls;
+use super::effects::{
+    apply_duration_effect, apply_triplet_feel, apply_velocity_effect, compute_stroke_offsets,
+    note_channel,
+};
+use super::ties::TieChains;
 use crate::audio::midi_event::{MidiEvent, MidiEventType};
-use crate::audio::playback_order::compute_playback_order;
+use crate::audio::playback_order::{compute_playback_order, playback_tick};
 use crate::parser::song_parser::{
-    Beat, BeatStrokeDirection, DURATION_EIGHTH, DURATION_SIXTEENTH, Note, NoteEffect, NoteType,
-    TripletFeel,
+    Beat, BeatStrokeDirection, DEFAULT_BANK, DEFAULT_PERCUSSION_BANK, DURATION_EIGHTH,
+    DURATION_SIXTEENTH, Measure, MidiChannel, Note, NoteEffect, NoteType, QUARTER_TIME, SlapEffect,
+    SlideType, Track, TripletFeel, VELOCITY_INCREMENT, Voice,
 };
 use crate::parser::song_parser_tests::parse_gp_file;
 use std::collections::HashSet;
 use std::io::Write;
 use std::path::{Path, PathBuf};
-use std::rc::Rc;
+use std::sync::Arc;
+
+/// Formats whose test files are checked against a golden MIDI event list.
+const GOLD_FILE_EXTENSIONS: [&str; 5] = ["gp3", "gp4", "gp5", "gpx", "gp"];
 
 #[test]
 fn test_midi_events_for_all_files() {
@@ -23,12 +32,7 @@ fn test_midi_events_for_all_files() {
             continue;
         }
         let extension = path.extension().unwrap();
-        if extension != "gp5"
-            && extension != "gp4"
-            && extension != "gp3"
-            && extension != "gpx"
-            && extension != "gp"
-        {
+        if !GOLD_FILE_EXTENSIONS.iter().any(|e| extension == *e) {
             continue;
         }
         let file_name = path.file_name().unwrap().to_str().unwrap();
@@ -36,13 +40,17 @@ fn test_midi_events_for_all_files() {
         let file_path = path.to_str().unwrap();
         let song = parse_gp_file(file_path)
             .unwrap_or_else(|err| panic!("Failed to parse file: {file_name}\n{err}"));
-        let song = Rc::new(song);
+        let song = Arc::new(song);
         let builder = MidiBuilder::new();
         let events = builder.build_for_song(&song);
         assert!(!events.is_empty(), "No events found for {file_name}");
 
-        // assert sorted by tick
-        assert!(events.windows(2).all(|w| w[0].tick <= w[1].tick));
+        // assert sorted by tick, then controls < note-off < note-on
+        assert!(
+            events
+                .windows(2)
+                .all(|w| w[0].order_key() <= w[1].order_key())
+        );
         assert_eq!(events[0].tick, 1);
 
         // check against golden file
@@ -60,6 +68,83 @@ fn test_midi_events_for_all_files() {
     }
 }
 
+#[test]
+fn compaction_keeps_the_channel_state() {
+    use std::collections::HashMap;
+    // channel state after each tick holding a note event
+    let note_states = |events: &[MidiEvent]| {
+        let mut state = HashMap::new();
+        let mut states = Vec::new();
+        for (index, event) in events.iter().enumerate() {
+            if let MidiEventType::MidiMessage(channel, command, data1, data2) = event.event {
+                let key = if command == 0xB0 { data1 } else { 0 };
+                state.insert((channel, command, key), (data1, data2));
+            }
+            let last_of_tick = events.get(index + 1).is_none_or(|e| e.tick != event.tick);
+            if last_of_tick
+                && events
+                    .iter()
+                    .any(|e| e.tick == event.tick && e.is_note_event())
+            {
+                states.push((event.tick, state.clone()));
+            }
+        }
+        states
+    };
+    let song = Arc::new(parse_gp_file("test-files/Demo v5.gp5").unwrap());
+    let raw = MidiBuilder::new()
+        .with_compaction(false)
+        .build_for_song(&song);
+    let compacted = MidiBuilder::new().build_for_song(&song);
+
+    // bends resend the same pitch while it holds
+    let messages = |events: &[MidiEvent]| events.iter().filter(|e| e.is_midi_message()).count();
+    assert_eq!(messages(&raw) - messages(&compacted), 101);
+    // notes are untouched
+    let notes = |events: &[MidiEvent]| {
+        events
+            .iter()
+            .filter(|e| !e.is_midi_message())
+            .cloned()
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(notes(&compacted), notes(&raw));
+    assert_eq!(note_states(&compacted), note_states(&raw));
+}
+
+#[test]
+fn compaction_drops_repeated_and_overwritten_values() {
+    let message = |tick, command, data1, data2| {
+        MidiEvent::new_midi_message(tick, 0, 1, command, data1, data2)
+    };
+    let mut events = vec![
+        message(1, 0xB0, 0x65, 0), // RPN sequences are kept
+        message(1, 0xB0, 0x65, 0),
+        message(10, 0xE0, 0, 64), // overwritten at the same tick
+        message(10, 0xE0, 0, 70),
+        MidiEvent::new_note_on(10, 0, 60, 95, 1),
+        message(20, 0xE0, 0, 70), // held pitch
+        message(30, 0xE0, 0, 70),
+        message(30, 0xB0, 0x0B, 127),
+        message(40, 0xB0, 0x0B, 127),
+        MidiEvent::new_note_off(40, 0, 60, 1),
+        message(50, 0xE0, 0, 64),
+    ];
+    compact_controls(&mut events);
+    let kept: Vec<(u32, i32)> = events
+        .iter()
+        .filter_map(|e| match e.event {
+            MidiEventType::MidiMessage(_, _, data1, data2) => Some((e.tick, data1 + data2)),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        kept,
+        vec![(1, 0x65), (1, 0x65), (10, 70), (30, 0x0B + 127), (50, 64)]
+    );
+    assert_eq!(events.len(), 7);
+}
+
 fn print_event(event: &MidiEvent) -> String {
     format!("{:?} {:?} {:?}", event.tick, event.event, event.track)
 }
@@ -84,11 +169,11 @@ fn validate_gold_rendered_result(events: &[MidiEvent], gold_path: PathBuf) {
 fn test_midi_events_for_demo_song() {
     const FILE_PATH: &str = "test-files/Demo v5.gp5";
     let song = parse_gp_file(FILE_PATH).unwrap();
-    let song = Rc::new(song);
+    let song = Arc::new(song);
     let builder = MidiBuilder::new();
     let events = builder.build_for_song(&song);
 
-    assert_eq!(events.len(), 4682);
+    assert_eq!(events.len(), 6342);
     assert_eq!(events[0].tick, 1);
 
     // assert number of tracks
@@ -96,11 +181,11 @@ fn test_midi_events_for_demo_song() {
     let unique_tracks: HashSet<_> = events.iter().map(|event| event.track).collect();
     assert_eq!(unique_tracks.len(), track_count + 1); // plus None for info events
 
-    // skip MIDI program messages
+    // skip MIDI program messages (main and effect channels)
     let rhythm_track_events: Vec<_> = events
         .iter()
         .filter(|e| e.track == Some(0))
-        .skip(11)
+        .skip(22)
         .collect();
 
     // print 20 first for debugging
@@ -180,11 +265,11 @@ fn test_midi_events_for_demo_song() {
     assert_eq!(event.track, Some(0));
     assert!(matches!(event.event, MidiEventType::NoteOn(0, 48, 127)));
 
-    // skip MIDI program messages
+    // skip MIDI program messages (main and effect channels)
     let solo_track_events: Vec<_> = events
         .iter()
         .filter(|e| e.track == Some(1))
-        .skip(11)
+        .skip(22)
         .collect();
 
     //print 100 first for debugging
@@ -254,26 +339,34 @@ fn test_midi_events_for_demo_song() {
     assert_eq!(event.track, Some(1));
     assert!(matches!(event.event, MidiEventType::NoteOff(2, 60)));
 
-    // note ON (after all tremolo and repeated sections)
+    // wah opened on main and effect channels
     let event = &solo_track_events[48];
     assert_eq!(event.tick, 77760);
+    assert!(matches!(
+        event.event,
+        MidiEventType::MidiMessage(2, 0xB0, 0x0B, 127)
+    ));
+
+    // note ON (after all tremolo and repeated sections)
+    let event = &solo_track_events[50];
+    assert_eq!(event.tick, 77760);
     assert_eq!(event.track, Some(1));
     assert!(matches!(event.event, MidiEventType::NoteOn(2, 63, 95)));
 
-    // note OFF
-    let event = &solo_track_events[49];
+    // note OFF (after the wah closing on both channels)
+    let event = &solo_track_events[53];
     assert_eq!(event.tick, 78240);
     assert_eq!(event.track, Some(1));
     assert!(matches!(event.event, MidiEventType::NoteOff(2, 63)));
 
-    // note ON hammer
-    let event = &solo_track_events[50];
+    // note ON hammer (after the note OFF on the same tick)
+    let event = &solo_track_events[54];
     assert_eq!(event.tick, 78240);
     assert_eq!(event.track, Some(1));
     assert!(matches!(event.event, MidiEventType::NoteOn(2, 65, 70)));
 
-    // note OFF hammer
-    let event = &solo_track_events[51];
+    // note OFF hammer (after the wah opening again)
+    let event = &solo_track_events[57];
     assert_eq!(event.tick, 78720);
     assert_eq!(event.track, Some(1));
     assert!(matches!(event.event, MidiEventType::NoteOff(2, 65)));
@@ -283,11 +376,11 @@ fn test_midi_events_for_demo_song() {
 fn test_midi_events_for_bleed() {
     const FILE_PATH: &str = "test-files/Meshuggah - Bleed.gp5";
     let song = parse_gp_file(FILE_PATH).unwrap();
-    let song = Rc::new(song);
+    let song = Arc::new(song);
     let builder = MidiBuilder::new();
     let events = builder.build_for_song(&song);
 
-    assert_eq!(events.len(), 44449);
+    assert_eq!(events.len(), 43319);
     assert_eq!(events[0].tick, 1);
 
     // assert number of tracks
@@ -295,11 +388,11 @@ fn test_midi_events_for_bleed() {
     let unique_tracks: HashSet<_> = events.iter().map(|event| event.track).collect();
     assert_eq!(unique_tracks.len(), track_count);
 
-    // skip MIDI program messages
+    // skip MIDI program messages (main and effect channels)
     let rhythm_track_events: Vec<_> = events
         .iter()
         .filter(|e| e.track == Some(0))
-        .skip(11)
+        .skip(22)
         .collect();
 
     // print 60 first for debugging
@@ -435,7 +528,7 @@ fn playback_order_damage_control() {
     }
 
     // build MIDI events and verify they are sorted
-    let song = Rc::new(song);
+    let song = Arc::new(song);
     let builder = MidiBuilder::new();
     let events = builder.build_for_song(&song);
     assert!(!events.is_empty());
@@ -468,7 +561,7 @@ fn triplet_feel_guthrie_eric() {
     let measure_end = measure_start + song.measure_headers[first_triplet_idx].length();
 
     // build events and verify they are sorted
-    let song = Rc::new(song);
+    let song = Arc::new(song);
     let builder = MidiBuilder::new();
     let events = builder.build_for_song(&song);
     assert!(!events.is_empty());
@@ -605,6 +698,49 @@ fn make_note(string: i8) -> Note {
     note
 }
 
+#[test]
+fn tie_chains_cross_measures() {
+    // one note per string, tied or not
+    let beat = |ties: &[bool]| Beat {
+        notes: ties
+            .iter()
+            .enumerate()
+            .map(|(index, tie)| {
+                let mut note = make_note(index as i8 + 1);
+                if *tie {
+                    note.kind = NoteType::Tie;
+                }
+                note
+            })
+            .collect(),
+        ..Beat::default()
+    };
+    let measure = |beats| Measure {
+        voices: vec![Voice {
+            measure_index: 0,
+            beats,
+        }],
+        ..Measure::default()
+    };
+    let track = Track {
+        strings: vec![(1, 64), (2, 59)],
+        measures: vec![
+            measure(vec![beat(&[false, false]), beat(&[true, false])]),
+            measure(vec![beat(&[true, true]), beat(&[false])]),
+        ],
+        ..Track::default()
+    };
+    let ties = TieChains::new(&track);
+    // string 1 is tied over the next two quarter notes, into the next measure
+    assert_eq!(ties.tied_duration(0, 0, 1), 1920);
+    assert_eq!(ties.tied_duration(0, 1, 1), 960);
+    assert_eq!(ties.tied_duration(0, 1, 2), 960);
+    assert_eq!(ties.tied_duration(1, 0, 1), 0);
+    // last beat of the track and unknown strings
+    assert_eq!(ties.tied_duration(1, 1, 1), 0);
+    assert_eq!(ties.tied_duration(0, 0, 3), 0);
+}
+
 #[test]
 fn stroke_offsets_no_stroke() {
     let beat = Beat::default();
@@ -642,3 +778,223 @@ fn stroke_offsets_up_stroke() {
     assert_eq!(offsets[2], 10); // string 3: second
     assert_eq!(offsets[4], 20); // string 5: third
 }
+
+#[test]
+fn pitch_bends_use_effect_channel() {
+    const FILE_PATH: &str = "test-files/Demo v5.gp5";
+    let song = parse_gp_file(FILE_PATH).unwrap();
+    let song = Arc::new(song);
+    let builder = MidiBuilder::new();
+    let events = builder.build_for_song(&song);
+
+    for track_id in 0..song.tracks.len() {
+        let track_events: Vec<_> = events
+            .iter()
+            .filter(|e| e.track == Some(track_id as u8))
+            .collect();
+        // the first control message targets the main channel
+        let MidiEventType::MidiMessage(main_channel, ..) = track_events[0].event else {
+            panic!("expected control message for track {track_id}");
+        };
+        if main_channel == 9 {
+            continue;
+        }
+        for event in &track_events {
+            if let MidiEventType::MidiMessage(channel, 0xE0, _, _) = event.event {
+                assert_ne!(channel, main_channel, "track {track_id} at {}", event.tick);
+            }
+        }
+    }
+}
+
+#[test]
+fn note_channel_for_effects() {
+    let midi_channel = MidiChannel {
+        channel_id: 0,
+        effect_channel_id: 1,
+        instrument: 30,
+        volume: 16,
+        balance: 8,
+        chorus: 0,
+        reverb: 0,
+        phaser: 0,
+        tremolo: 0,
+        bank: DEFAULT_BANK,
+    };
+    let mut note = make_note(1);
+    assert_eq!(note_channel(&note, &midi_channel), 0);
+    note.effect.vibrato = true;
+    assert_eq!(note_channel(&note, &midi_channel), 1);
+    note.effect.vibrato = false;
+    note.effect.slide = Some(SlideType::ShiftSlideTo);
+    assert_eq!(note_channel(&note, &midi_channel), 1);
+
+    // percussion never bends
+    let percussion = MidiChannel {
+        channel_id: 9,
+        effect_channel_id: 9,
+        bank: DEFAULT_PERCUSSION_BANK,
+        ..midi_channel
+    };
+    assert_eq!(note_channel(&note, &percussion), 9);
+}
+
+#[test]
+fn slap_techniques_accent_and_shorten_notes() {
+    let midi_channel = MidiChannel {
+        channel_id: 0,
+        effect_channel_id: 1,
+        instrument: 33,
+        volume: 16,
+        balance: 8,
+        chorus: 0,
+        reverb: 0,
+        phaser: 0,
+        tremolo: 0,
+        bank: DEFAULT_BANK,
+    };
+    let with_slap = |slap| {
+        let mut note = make_note(1);
+        note.effect.slap = slap;
+        note
+    };
+    let plain = with_slap(SlapEffect::None);
+    let slapped = with_slap(SlapEffect::Slapping);
+    let popped = with_slap(SlapEffect::Popping);
+    let tapped = with_slap(SlapEffect::Tapping);
+
+    // pops are louder than slaps, both louder than plain notes
+    let velocity = |note: &Note| apply_velocity_effect(note, None, &midi_channel);
+    assert_eq!(velocity(&slapped), velocity(&plain) + VELOCITY_INCREMENT);
+    assert_eq!(velocity(&popped), velocity(&plain) + VELOCITY_INCREMENT * 2);
+    assert_eq!(velocity(&tapped), velocity(&plain));
+
+    // a tapped note is not softened after a hammer-on
+    let mut hammer = with_slap(SlapEffect::None);
+    hammer.effect.hammer = true;
+    let after_hammer = |note: &Note| apply_velocity_effect(note, Some(&hammer), &midi_channel);
+    assert!(after_hammer(&plain) < velocity(&plain));
+    assert_eq!(after_hammer(&tapped), velocity(&tapped));
+
+    // slapped and popped strings are muted quickly
+    let duration = |note: &Note| apply_duration_effect(note, 0, None, 120, QUARTER_TIME);
+    assert_eq!(duration(&plain), QUARTER_TIME);
+    assert_eq!(duration(&tapped), QUARTER_TIME);
+    assert_eq!(duration(&slapped), QUARTER_TIME * 3 / 4);
+    assert_eq!(duration(&popped), QUARTER_TIME * 3 / 4);
+}
+
+#[test]
+fn wah_changes_expression() {
+    const FILE_PATH: &str = "test-files/Demo v5.gp5";
+    let song = parse_gp_file(FILE_PATH).unwrap();
+    let song = Arc::new(song);
+    let builder = MidiBuilder::new();
+    let events = builder.build_for_song(&song);
+
+    // closed wah lowers the expression, open wah restores it
+    let expressions: HashSet<_> = events
+        .iter()
+        .filter_map(|e| match e.event {
+            MidiEventType::MidiMessage(_, 0xB0, 0x0B, value) if e.tick > 1 => Some(value),
+            _ => None,
+        })
+        .collect();
+    assert!(expressions.contains(&63));
+    assert!(expressions.contains(&127));
+}
+
+#[test]
+fn muted_and_solo_tracks_are_silenced() {
+    const FILE_PATH: &str = "test-files/Demo v5.gp5";
+    let note_on_tracks = |song: &Arc<_>, play_muted_tracks| {
+        let events = MidiBuilder::new()
+            .with_muted_tracks(play_muted_tracks)
+            .build_for_song(song);
+        events
+            .iter()
+            .filter(|e| matches!(e.event, MidiEventType::NoteOn(..)))
+            .filter_map(|e| e.track)
+            .collect::<HashSet<u8>>()
+    };
+
+    let mut song = parse_gp_file(FILE_PATH).unwrap();
+    song.tracks[1].mute = true;
+    let song = Arc::new(song);
+    let tracks = note_on_tracks(&song, false);
+    assert!(!tracks.contains(&1));
+    assert!(tracks.contains(&0));
+    // the override plays every track
+    assert!(note_on_tracks(&song, true).contains(&1));
+
+    // a solo track silences the others, even when it is also muted
+    let mut song = parse_gp_file(FILE_PATH).unwrap();
+    song.tracks[2].solo = true;
+    song.tracks[2].mute = true;
+    let song = Arc::new(song);
+    assert_eq!(note_on_tracks(&song, false), HashSet::from([2]));
+}
+
+#[test]
+fn mix_table_changes_instrument_and_fade_all_tracks() {
+    const FILE_PATH: &str = "test-files/Demo v5.gp5";
+    let song = parse_gp_file(FILE_PATH).unwrap();
+    let song = Arc::new(song);
+    let order = compute_playback_order(&song.measure_headers);
+    let events = MidiBuilder::new().build_for_song_with_order(&song, &order);
+    // first playback of a measure
+    let measure_start = |measure_index: usize| {
+        let (_, offset) = order
+            .iter()
+            .find(|(index, _)| *index == measure_index)
+            .unwrap();
+        playback_tick(song.measure_headers[measure_index].start, *offset)
+    };
+
+    // instrument change of the first track on both of its channels
+    let programs: Vec<_> = events
+        .iter()
+        .filter(|e| e.tick == measure_start(37) && e.track == Some(0))
+        .filter_map(|e| match e.event {
+            MidiEventType::MidiMessage(channel, 0xC0, program, _) => Some((channel, program)),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(programs, vec![(0, 27), (1, 27)]);
+
+    // the fade out flagged for all tracks lowers the volume of each of them gradually
+    let fade_start = measure_start(45);
+    let fade_end = fade_start + 16 * QUARTER_TIME;
+    for track_id in 0..song.tracks.len() as u8 {
+        let volumes: Vec<(u32, i32)> = events
+            .iter()
+            .filter(|e| e.track == Some(track_id) && (fade_start..fade_end).contains(&e.tick))
+            .filter_map(|e| match e.event {
+                MidiEventType::MidiMessage(_, 0xB0, 0x07, value) => Some((e.tick, value)),
+                _ => None,
+            })
+            .collect();
+        assert!(volumes.len() > 1, "track {track_id}");
+        assert!(volumes.windows(2).all(|w| w[0].1 >= w[1].1));
+        assert_eq!(volumes.last(), Some(&(fade_end - QUARTER_TIME / 4, 15)));
+    }
+}
+
+#[test]
+fn track_without_channel_played_with_a_piano() {
+    let mut song = parse_gp_file("test-files/Demo v5.gp5").unwrap();
+    // a hand edited saved song may point at a channel that does not exist
+    song.tracks[0].channel_id = 200;
+    let song = Arc::new(song);
+    let (events, warnings) =
+        crate::parser::warnings::collect(|| MidiBuilder::new().build_for_song(&song));
+    assert_eq!(
+        warnings,
+        vec!["MIDI channel 200 not found for track 1, played with a piano"]
+    );
+    assert!(
+        events
+            .iter()
+            .any(|event| event.track == Some(0) && matches!(event.event, MidiEventType::NoteOn(..)))
+    );
+}
@@ -0,0 +1,65 @@
+//! Tie chain durations of a track, precomputed in a single pass over its beats
+//! so that resolving the length of a tied note does not scan the rest of the song.
+
+use crate::parser::song_parser::{NoteType, Track};
+
+/// Duration tied onto the notes of each beat, per string.
+/// Like the lookup it replaces, chains follow the beats of the first voice.
+pub(super) struct TieChains {
+    measure_starts: Vec<usize>, // position of the first beat of each measure
+    string_count: usize,
+    tied: Vec<u32>, // [position * string_count + string - 1]: length of the tie chain starting there
+}
+
+impl TieChains {
+    pub(super) fn new(track: &Track) -> Self {
+        let string_count = track.strings.len();
+        let mut measure_starts = Vec::with_capacity(track.measures.len());
+        let mut beats = Vec::new();
+        for measure in &track.measures {
+            measure_starts.push(beats.len());
+            if let Some(voice) = measure.voices.first() {
+                beats.extend(&voice.beats);
+            }
+        }
+
+        // each chain is the tie on this beat plus the chain starting on the next one
+        let mut tied = vec![0; (beats.len() + 1) * string_count];
+        for (position, beat) in beats.iter().enumerate().rev() {
+            for string in 1..=string_count {
+                let is_tie = beat
+                    .notes
+                    .iter()
+                    .find(|n| n.string as usize == string)
+                    .is_some_and(|n| n.kind == NoteType::Tie);
+                if is_tie {
+                    let index = position * string_count + string - 1;
+                    tied[index] = beat.duration.time() + tied[index + string_count];
+                }
+            }
+        }
+        Self {
+            measure_starts,
+            string_count,
+            tied,
+        }
+    }
+
+    /// Duration of the tie notes following the given beat on a string.
+    pub(super) fn tied_duration(&self, measure_id: usize, beat_id: usize, string: i8) -> u32 {
+        let Some(measure_start) = self.measure_starts.get(measure_id) else {
+            return 0;
+        };
+        let Some(string) = usize::try_from(string)
+            .ok()
+            .filter(|s| (1..=self.string_count).contains(s))
+        else {
+            return 0;
+        };
+        let position = measure_start + beat_id + 1;
+        self.tied
+            .get(position * self.string_count + string - 1)
+            .copied()
+            .unwrap_or(0)
+    }
+}
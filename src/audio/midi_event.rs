@@ -25,6 +25,36 @@ impl MidiEvent {
         )
     }
 
+    /// Program changes and controllers holding a channel setting: bank, volume, pan and effect levels.
+    /// Together they are the state of the channels at a point of the song.
+    pub const fn is_channel_setting(&self) -> bool {
+        matches!(
+            self.event,
+            MidiEventType::MidiMessage(_, 0xC0, _, _)
+                | MidiEventType::MidiMessage(
+                    _,
+                    0xB0,
+                    0x00 | 0x07 | 0x0A | 0x5B | 0x5C | 0x5D | 0x5F,
+                    _
+                )
+        )
+    }
+
+    /// Rank among the events sharing a tick: controls and tempo changes come first,
+    /// then note-offs so a note released and struck again at the same tick is not cut.
+    pub const fn order_rank(&self) -> u8 {
+        match self.event {
+            MidiEventType::MidiMessage(..) | MidiEventType::TempoChange(_) => 0,
+            MidiEventType::NoteOff(..) => 1,
+            MidiEventType::NoteOn(..) => 2,
+        }
+    }
+
+    /// Deterministic ordering of the events, by tick then by rank.
+    pub const fn order_key(&self) -> (u32, u8) {
+        (self.tick, self.order_rank())
+    }
+
     pub const fn new_note_on(
         tick: u32,
         track: usize,
@@ -0,0 +1,597 @@
+//! Standard MIDI File (SMF) export of the song events.
+
+use crate::audio::midi_builder::{MidiBuilder, VibratoSettings};
+use crate::audio::midi_event::{MidiEvent, MidiEventType};
+use crate::audio::playback_order::{compute_playback_order, notated_order, playback_tick};
+use crate::parser::song_parser::{PORT_CHANNELS, QUARTER_TIME, Song};
+use std::sync::Arc;
+
+const END_OF_TRACK: [u8; 3] = [0xFF, 0x2F, 0x00];
+// text meta event types
+const TRACK_NAME: u8 = 0x03;
+const LYRIC: u8 = 0x05;
+const MARKER: u8 = 0x06;
+const CUE_POINT: u8 = 0x07;
+const MIDI_PORT: u8 = 0x21;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MidiExportOptions {
+    pub expand_repeats: bool, // timeline matches the playback instead of the notation
+    pub beat_text_cues: bool, // beat texts as cue point meta events
+    pub stems: bool,          // one file per track instead of a single file
+}
+
+impl Default for MidiExportOptions {
+    fn default() -> Self {
+        Self {
+            expand_repeats: true,
+            beat_text_cues: true,
+            stems: false,
+        }
+    }
+}
+
+/// Text meta event placed on the timeline of the tempo track or of a song track.
+#[derive(Clone, Copy)]
+struct TextEvent<'a> {
+    track: Option<usize>, // `None` for the tempo track
+    tick: u32,
+    kind: u8,
+    text: &'a str,
+}
+
+/// Export every track, ignoring the solo/mute flags, as a format 1 MIDI file.
+pub fn export_smf(
+    song: &Arc<Song>,
+    vibrato: VibratoSettings,
+    options: MidiExportOptions,
+) -> Vec<u8> {
+    let (events, texts) = export_events(song, vibrato, options);
+    write_smf(&events, &texts, song.tempo.value, &track_ports(song))
+}
+
+/// Export each track on its own, as if soloed, into a format 1 MIDI file sharing the tempo map.
+/// The files are returned in the order of the song tracks.
+pub fn export_stems(
+    song: &Arc<Song>,
+    vibrato: VibratoSettings,
+    options: MidiExportOptions,
+) -> Vec<Vec<u8>> {
+    let (events, texts) = export_events(song, vibrato, options);
+    let ports = track_ports(song);
+    (0..song.tracks.len())
+        .map(|track| {
+            let stem_track = u8::try_from(track).ok();
+            // the events of the track move to the single song track of the stem
+            let stem_events: Vec<_> = events
+                .iter()
+                .filter(|event| event.track.is_none() || event.track == stem_track)
+                .map(|event| MidiEvent {
+                    track: event.track.map(|_| 0),
+                    ..event.clone()
+                })
+                .collect();
+            let stem_texts: Vec<_> = texts
+                .iter()
+                .filter(|text| text.track.is_none_or(|t| t == track))
+                .map(|text| TextEvent {
+                    track: text.track.map(|_| 0),
+                    ..*text
+                })
+                .collect();
+            write_smf(
+                &stem_events,
+                &stem_texts,
+                song.tempo.value,
+                &ports[track..=track],
+            )
+        })
+        .collect()
+}
+
+/// File name of the stem of a track, characters invalid in file names are replaced.
+pub fn stem_file_name(song_file_name: &str, track: usize, track_name: &str) -> String {
+    let song_name = song_file_name
+        .rsplit_once('.')
+        .map_or(song_file_name, |(name, _)| name);
+    let name = format!("{song_name} - {:02} {}.mid", track + 1, track_name.trim());
+    name.chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect()
+}
+
+/// Events of every track and the texts of the song: names, markers, lyrics and
+/// the beat texts requested by the options.
+fn export_events(
+    song: &Arc<Song>,
+    vibrato: VibratoSettings,
+    options: MidiExportOptions,
+) -> (Vec<MidiEvent>, Vec<TextEvent<'_>>) {
+    let order = if options.expand_repeats {
+        compute_playback_order(&song.measure_headers)
+    } else {
+        notated_order(&song.measure_headers)
+    };
+    let events = MidiBuilder::new()
+        .with_vibrato(vibrato)
+        .with_muted_tracks(true)
+        .with_midi_ports(true)
+        .build_for_song_with_order(song, &order);
+    let mut texts = track_names(song);
+    texts.extend(markers(song, &order));
+    texts.extend(lyrics(song, &order));
+    if options.beat_text_cues {
+        texts.extend(beat_text_cues(song, &order));
+    }
+    (events, texts)
+}
+
+/// Zero-based MIDI port of each song track, from its channel numbered through the ports.
+fn track_ports(song: &Song) -> Vec<u8> {
+    song.tracks
+        .iter()
+        .map(|track| track.channel_id / PORT_CHANNELS)
+        .collect()
+}
+
+/// Name of the song on the tempo track and names of the song tracks.
+fn track_names(song: &Song) -> Vec<TextEvent<'_>> {
+    let song_name = (None, song.song_info.name.as_str());
+    let track_names = song
+        .tracks
+        .iter()
+        .enumerate()
+        .map(|(track, song_track)| (Some(track), song_track.name.as_str()));
+    std::iter::once(song_name)
+        .chain(track_names)
+        .filter(|(_, name)| !name.trim().is_empty())
+        .map(|(track, name)| TextEvent {
+            track,
+            tick: 0,
+            kind: TRACK_NAME,
+            text: name.trim(),
+        })
+        .collect()
+}
+
+/// Measure markers on the tempo track, repeated along with their measure.
+fn markers<'a>(song: &'a Song, order: &[(usize, i64)]) -> Vec<TextEvent<'a>> {
+    order
+        .iter()
+        .filter_map(|&(measure_index, tick_offset)| {
+            let header = song.measure_headers.get(measure_index)?;
+            let marker = header.marker.as_ref()?;
+            Some(TextEvent {
+                track: None,
+                tick: playback_tick(header.start, tick_offset),
+                kind: MARKER,
+                text: &marker.title,
+            })
+        })
+        .collect()
+}
+
+/// Syllables of the lyrics: words are split on spaces and hyphens, the hyphen stays
+/// with the first part like in karaoke files.
+fn syllables(text: &str) -> impl Iterator<Item = &str> {
+    text.split_whitespace()
+        .flat_map(|word| word.split_inclusive('-'))
+        .filter(|syllable| *syllable != "-")
+}
+
+/// Lyrics on the track they are bound to, the first track if none, repeated along with their measure.
+/// Each line starts at its measure and puts a syllable on each following beat playing notes.
+fn lyrics<'a>(song: &'a Song, order: &[(usize, i64)]) -> Vec<TextEvent<'a>> {
+    let Some(lyrics) = &song.lyrics else {
+        return Vec::new();
+    };
+    // the track choice counts from one
+    let track = usize::try_from(lyrics.track_choice - 1).unwrap_or(0);
+    let Some(song_track) = song.tracks.get(track) else {
+        return Vec::new();
+    };
+    // syllables of each measure with the tick of their beat
+    let mut measure_syllables = vec![Vec::new(); song_track.measures.len()];
+    for (from, text) in &lyrics.lines {
+        let first_measure = usize::try_from(from - 1).unwrap_or(0);
+        let beats = song_track
+            .measures
+            .iter()
+            .enumerate()
+            .skip(first_measure)
+            .flat_map(|(index, measure)| {
+                let beats = measure.voices.first().map_or(&[][..], |v| &v.beats[..]);
+                beats.iter().map(move |beat| (index, beat))
+            })
+            .filter(|(_, beat)| !beat.empty && !beat.notes.is_empty());
+        for ((index, beat), syllable) in beats.zip(syllables(text)) {
+            measure_syllables[index].push((beat.start, syllable));
+        }
+    }
+    let mut texts = Vec::new();
+    for &(measure_index, tick_offset) in order {
+        let Some(syllables) = measure_syllables.get(measure_index) else {
+            continue;
+        };
+        for &(start, syllable) in syllables {
+            texts.push(TextEvent {
+                track: Some(track),
+                tick: playback_tick(start, tick_offset),
+                kind: LYRIC,
+                text: syllable,
+            });
+        }
+    }
+    texts
+}
+
+/// Beat texts of every track, repeated along with their measure.
+fn beat_text_cues<'a>(song: &'a Song, order: &[(usize, i64)]) -> Vec<TextEvent<'a>> {
+    let mut cues = Vec::new();
+    for &(measure_index, tick_offset) in order {
+        for (track, song_track) in song.tracks.iter().enumerate() {
+            let Some(measure) = song_track.measures.get(measure_index) else {
+                continue;
+            };
+            let beats = measure.voices.iter().flat_map(|voice| &voice.beats);
+            for beat in beats.filter(|beat| !beat.text.is_empty()) {
+                cues.push(TextEvent {
+                    track: Some(track),
+                    tick: playback_tick(beat.start, tick_offset),
+                    kind: CUE_POINT,
+                    text: &beat.text,
+                });
+            }
+        }
+    }
+    cues
+}
+
+/// Encode the events, the first track holds the tempo map and each song track gets its own track.
+/// Ticks are shifted so that the first measure starts at zero.
+/// The song tracks are given their MIDI port once a port other than the first is used.
+fn write_smf(
+    events: &[MidiEvent],
+    texts: &[TextEvent],
+    initial_tempo: u32,
+    ports: &[u8],
+) -> Vec<u8> {
+    let mut tracks = vec![Vec::new(); ports.len() + 1];
+    tracks[0].push(((0, 0), tempo_meta(initial_tempo)));
+    // the sort below keeps the insertion order, the names are the first texts
+    for text in texts {
+        let tick = text.tick.saturating_sub(QUARTER_TIME);
+        let index = text.track.map_or(0, |track| track + 1);
+        tracks[index].push(((tick, 0), text_meta(text.kind, text.text)));
+    }
+    if ports.iter().any(|port| *port > 0) {
+        for (track, port) in ports.iter().enumerate() {
+            tracks[track + 1].push(((0, 0), vec![0xFF, MIDI_PORT, 0x01, *port]));
+        }
+    }
+    for event in events {
+        let tick = event.tick.saturating_sub(QUARTER_TIME);
+        let (index, bytes) = match (&event.event, event.track) {
+            (MidiEventType::TempoChange(tempo), _) => (0, tempo_meta(*tempo)),
+            (_, Some(track)) => match channel_message(&event.event) {
+                Some(bytes) => (usize::from(track) + 1, bytes),
+                None => continue,
+            },
+            (_, None) => continue,
+        };
+        tracks[index].push(((tick, event.order_rank()), bytes));
+    }
+
+    let mut smf = Vec::new();
+    smf.extend_from_slice(b"MThd");
+    smf.extend_from_slice(&6_u32.to_be_bytes());
+    smf.extend_from_slice(&1_u16.to_be_bytes()); // format 1: simultaneous tracks
+    smf.extend_from_slice(&(tracks.len() as u16).to_be_bytes());
+    smf.extend_from_slice(&(QUARTER_TIME as u16).to_be_bytes());
+    for mut track in tracks {
+        // meta events rank with the controls, before the notes
+        track.sort_by_key(|(key, _)| *key);
+        let mut chunk = Vec::new();
+        let mut previous_tick = 0;
+        for ((tick, _), bytes) in track {
+            write_variable_length(&mut chunk, tick - previous_tick);
+            chunk.extend_from_slice(&bytes);
+            previous_tick = tick;
+        }
+        write_variable_length(&mut chunk, 0);
+        chunk.extend_from_slice(&END_OF_TRACK);
+        smf.extend_from_slice(b"MTrk");
+        smf.extend_from_slice(&(chunk.len() as u32).to_be_bytes());
+        smf.extend_from_slice(&chunk);
+    }
+    smf
+}
+
+/// Set tempo meta event in microseconds per quarter note.
+fn tempo_meta(tempo_bpm: u32) -> Vec<u8> {
+    let micros = 60_000_000 / tempo_bpm.max(1);
+    let [_, a, b, c] = micros.to_be_bytes();
+    vec![0xFF, 0x51, 0x03, a, b, c]
+}
+
+/// Text meta event of the kind, the text is UTF-8 encoded.
+fn text_meta(kind: u8, text: &str) -> Vec<u8> {
+    let mut bytes = vec![0xFF, kind];
+    write_variable_length(&mut bytes, text.len() as u32);
+    bytes.extend_from_slice(text.as_bytes());
+    bytes
+}
+
+fn channel_message(event: &MidiEventType) -> Option<Vec<u8>> {
+    let data = |value: i32| value.clamp(0, 127) as u8;
+    let status = |command: i32, channel: i32| (command & 0xF0) as u8 | (channel & 0x0F) as u8;
+    match *event {
+        MidiEventType::NoteOn(channel, key, velocity) => Some(vec![
+            status(0x90, channel),
+            data(key),
+            data(i32::from(velocity)),
+        ]),
+        MidiEventType::NoteOff(channel, key) => Some(vec![status(0x80, channel), data(key), 0]),
+        // program change and channel pressure have a single data byte
+        MidiEventType::MidiMessage(channel, command @ (0xC0 | 0xD0), data1, _) => {
+            Some(vec![status(command, channel), data(data1)])
+        }
+        MidiEventType::MidiMessage(channel, command, data1, data2) => {
+            Some(vec![status(command, channel), data(data1), data(data2)])
+        }
+        MidiEventType::TempoChange(_) => None,
+    }
+}
+
+/// Delta times are written 7 bits per byte, most significant first.
+fn write_variable_length(out: &mut Vec<u8>, value: u32) {
+    let mut bytes = vec![(value & 0x7F) as u8];
+    let mut value = value >> 7;
+    while value > 0 {
+        bytes.push((value & 0x7F) as u8 | 0x80);
+        value >>= 7;
+    }
+    out.extend(bytes.iter().rev());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::song_parser::MidiChannel;
+    use crate::parser::song_parser_tests::parse_gp_file;
+
+    fn variable_length(value: u32) -> Vec<u8> {
+        let mut out = Vec::new();
+        write_variable_length(&mut out, value);
+        out
+    }
+
+    #[test]
+    fn variable_length_quantities() {
+        assert_eq!(variable_length(0), vec![0x00]);
+        assert_eq!(variable_length(0x7F), vec![0x7F]);
+        assert_eq!(variable_length(0x80), vec![0x81, 0x00]);
+        assert_eq!(variable_length(0x2000), vec![0xC0, 0x00]);
+        assert_eq!(variable_length(0x0FFF_FFFF), vec![0xFF, 0xFF, 0xFF, 0x7F]);
+    }
+
+    #[test]
+    fn tempo_in_microseconds() {
+        assert_eq!(tempo_meta(120), vec![0xFF, 0x51, 0x03, 0x07, 0xA1, 0x20]);
+    }
+
+    #[test]
+    fn cue_point_text() {
+        assert_eq!(
+            text_meta(CUE_POINT, "Fade out"),
+            vec![
+                0xFF, 0x07, 0x08, b'F', b'a', b'd', b'e', b' ', b'o', b'u', b't'
+            ]
+        );
+    }
+
+    #[test]
+    fn channel_messages() {
+        assert_eq!(
+            channel_message(&MidiEventType::NoteOn(2, 64, 95)),
+            Some(vec![0x92, 64, 95])
+        );
+        assert_eq!(
+            channel_message(&MidiEventType::NoteOff(9, 36)),
+            Some(vec![0x89, 36, 0])
+        );
+        assert_eq!(
+            channel_message(&MidiEventType::MidiMessage(1, 0xC0, 30, 0)),
+            Some(vec![0xC1, 30])
+        );
+        assert_eq!(
+            channel_message(&MidiEventType::MidiMessage(0, 0xE0, 0, 64)),
+            Some(vec![0xE0, 0, 64])
+        );
+    }
+
+    /// Track chunk data after the header.
+    fn track_chunks(smf: &[u8]) -> Vec<&[u8]> {
+        let mut chunks = Vec::new();
+        let mut rest = &smf[14..];
+        while !rest.is_empty() {
+            assert_eq!(&rest[..4], b"MTrk");
+            let len = u32::from_be_bytes(rest[4..8].try_into().unwrap()) as usize;
+            chunks.push(&rest[8..8 + len]);
+            rest = &rest[8 + len..];
+        }
+        chunks
+    }
+
+    #[test]
+    fn export_demo_song() {
+        let song = Arc::new(parse_gp_file("test-files/Demo v5.gp5").unwrap());
+        let options = MidiExportOptions::default();
+        let expanded = export_smf(&song, VibratoSettings::default(), options);
+        let notated = MidiExportOptions {
+            expand_repeats: false,
+            ..options
+        };
+        let notated = export_smf(&song, VibratoSettings::default(), notated);
+
+        assert_eq!(&expanded[..4], b"MThd");
+        let track_count = u16::from_be_bytes([expanded[10], expanded[11]]);
+        assert_eq!(usize::from(track_count), song.tracks.len() + 1);
+        assert_eq!(u16::from_be_bytes([expanded[12], expanded[13]]), 960);
+
+        let chunks = track_chunks(&expanded);
+        assert_eq!(chunks.len(), song.tracks.len() + 1);
+        assert!(chunks.iter().all(|c| c.ends_with(&END_OF_TRACK)));
+        // initial tempo (165 bpm) first in the tempo track
+        assert_eq!(song.tempo.value, 165);
+        assert_eq!(&chunks[0][..7], &[0x00, 0xFF, 0x51, 0x03, 0x05, 0x8C, 0x74]);
+
+        // repeats and the D.S. al Coda replay measures
+        assert!(expanded.len() > notated.len());
+        assert_eq!(track_chunks(&notated).len(), chunks.len());
+    }
+
+    #[test]
+    fn export_beat_text_cues() {
+        let song = parse_gp_file("test-files/Demo v5.gp5").unwrap();
+        let order = compute_playback_order(&song.measure_headers);
+        let cues: Vec<(Option<usize>, u32, &str)> = beat_text_cues(&song, &order)
+            .iter()
+            .map(|cue| (cue.track, cue.tick, cue.text))
+            .collect();
+        // measure 46 after the D.S. al Coda
+        assert_eq!(cues, vec![(Some(0), 216_000, "Fade out")]);
+
+        let song = Arc::new(song);
+        let with_cues = export_smf(
+            &song,
+            VibratoSettings::default(),
+            MidiExportOptions::default(),
+        );
+        let without_cues = MidiExportOptions {
+            beat_text_cues: false,
+            ..MidiExportOptions::default()
+        };
+        let without_cues = export_smf(&song, VibratoSettings::default(), without_cues);
+        let first_track = |smf: &[u8]| track_chunks(smf)[1].to_vec();
+        let cue = text_meta(CUE_POINT, "Fade out");
+        assert!(first_track(&with_cues).windows(cue.len()).any(|w| w == cue));
+        assert!(
+            !first_track(&without_cues)
+                .windows(cue.len())
+                .any(|w| w == cue)
+        );
+    }
+
+    #[test]
+    fn export_midi_ports() {
+        let port_meta = |port: u8| vec![0xFF, MIDI_PORT, 0x01, port];
+        let contains = |chunk: &[u8], meta: &[u8]| chunk.windows(meta.len()).any(|w| w == meta);
+        let mut song = parse_gp_file("test-files/Demo v5.gp5").unwrap();
+        let smf = export_smf(
+            &Arc::new(song.clone()),
+            VibratoSettings::default(),
+            MidiExportOptions::default(),
+        );
+        // a single port is not written
+        assert!(!contains(&smf, &port_meta(0)[..3]));
+
+        // the first track moves to the second port, on the channel it has in the first one
+        let channel_id = song.tracks[0].channel_id;
+        let channel = song.midi_channels[usize::from(channel_id)].clone();
+        let second_port = &mut song.midi_channels[usize::from(channel_id + PORT_CHANNELS)];
+        *second_port = MidiChannel {
+            channel_id: second_port.channel_id,
+            effect_channel_id: channel.effect_channel_id + PORT_CHANNELS,
+            ..channel
+        };
+        song.tracks[0].channel_id = channel_id + PORT_CHANNELS;
+        let song = Arc::new(song);
+        let smf = export_smf(
+            &song,
+            VibratoSettings::default(),
+            MidiExportOptions::default(),
+        );
+        let chunks = track_chunks(&smf);
+        assert!(contains(chunks[1], &port_meta(1)));
+        assert!(contains(chunks[2], &port_meta(0)));
+        let note_on = 0x90 | channel_id;
+        assert!(chunks[1].contains(&note_on));
+
+        let stems = export_stems(
+            &song,
+            VibratoSettings::default(),
+            MidiExportOptions::default(),
+        );
+        assert!(contains(track_chunks(&stems[0])[1], &port_meta(1)));
+        assert!(!contains(&stems[1], &port_meta(0)[..3]));
+    }
+
+    #[test]
+    fn lyrics_syllables() {
+        let syllables: Vec<&str> = syllables("I feel your pre-sence\r\na - mongst us").collect();
+        assert_eq!(
+            syllables,
+            vec!["I", "feel", "your", "pre-", "sence", "a", "mongst", "us"]
+        );
+    }
+
+    #[test]
+    fn export_names_markers_and_lyrics() {
+        let contains = |chunk: &[u8], meta: Vec<u8>| chunk.windows(meta.len()).any(|w| w == meta);
+        let song = Arc::new(parse_gp_file("test-files/Demo v5.gp5").unwrap());
+        let smf = export_smf(
+            &song,
+            VibratoSettings::default(),
+            MidiExportOptions::default(),
+        );
+        let chunks = track_chunks(&smf);
+        assert!(contains(chunks[0], text_meta(MARKER, "Intro")));
+        let track_name = text_meta(TRACK_NAME, song.tracks[0].name.trim());
+        assert_eq!(&chunks[1][1..=track_name.len()], &track_name[..]);
+
+        // lyrics not bound to a track are sung by the first one
+        let song = Arc::new(parse_gp_file("test-files/Ghost - Cirice.gp5").unwrap());
+        let order = compute_playback_order(&song.measure_headers);
+        let lyrics = lyrics(&song, &order);
+        assert!(lyrics.iter().all(|lyric| lyric.track == Some(0)));
+        assert_eq!(lyrics[0].text, "I");
+        assert!(lyrics.windows(2).all(|pair| pair[0].tick <= pair[1].tick));
+        let smf = export_smf(
+            &song,
+            VibratoSettings::default(),
+            MidiExportOptions::default(),
+        );
+        assert!(contains(
+            track_chunks(&smf)[1],
+            text_meta(LYRIC, "presence")
+        ));
+    }
+
+    #[test]
+    fn export_stems_per_track() {
+        let song = Arc::new(parse_gp_file("test-files/Demo v5.gp5").unwrap());
+        let options = MidiExportOptions::default();
+        let full = export_smf(&song, VibratoSettings::default(), options);
+        let stems = export_stems(&song, VibratoSettings::default(), options);
+        assert_eq!(stems.len(), song.tracks.len());
+
+        let full_chunks = track_chunks(&full);
+        for (track, stem) in stems.iter().enumerate() {
+            // tempo map and the single track of the stem
+            assert_eq!(u16::from_be_bytes([stem[10], stem[11]]), 2);
+            let chunks = track_chunks(stem);
+            assert_eq!(chunks[0], full_chunks[0]);
+            assert_eq!(chunks[1], full_chunks[track + 1]);
+        }
+
+        assert_eq!(
+            stem_file_name("Demo v5.gp5", 0, " Lead: solo/fill "),
+            "Demo v5 - 01 Lead_ solo_fill.mid"
+        );
+    }
+}
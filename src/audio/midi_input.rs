@@ -0,0 +1,199 @@
+//! MIDI controller input (foot switches, expression pedals) used to drive the transport.
+
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+
+/// Controller values from this threshold count as a pressed pedal.
+const PEDAL_DOWN_THRESHOLD: u8 = 64;
+
+/// Incoming MIDI message that can be bound to a transport action.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum MidiTrigger {
+    Note { channel: u8, key: u8 },
+    ControlChange { channel: u8, controller: u8 },
+}
+
+impl MidiTrigger {
+    /// Note off and zero velocity note on are ignored.
+    pub const fn from_note(channel: u8, key: u8, velocity: u8) -> Option<Self> {
+        if velocity == 0 {
+            None
+        } else {
+            Some(Self::Note { channel, key })
+        }
+    }
+
+    /// Pedal releases are ignored so that a press triggers a single action.
+    pub const fn from_control(channel: u8, controller: u8, value: u8) -> Option<Self> {
+        if value < PEDAL_DOWN_THRESHOLD {
+            None
+        } else {
+            Some(Self::ControlChange {
+                channel,
+                controller,
+            })
+        }
+    }
+}
+
+impl Display for MidiTrigger {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Note { channel, key } => write!(f, "Note {key} (ch {})", channel + 1),
+            Self::ControlChange {
+                channel,
+                controller,
+            } => write!(f, "CC {controller} (ch {})", channel + 1),
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum MidiInputError {
+    #[cfg(not(target_os = "linux"))]
+    #[error("MIDI input is not supported on this platform")]
+    Unsupported,
+    #[cfg(target_os = "linux")]
+    #[error("ALSA sequencer error: {0}")]
+    Alsa(#[from] alsa::Error),
+    #[error("failed to start MIDI input thread: {0}")]
+    Thread(#[from] std::io::Error),
+}
+
+/// Listen to all MIDI input ports available at startup on a background thread.
+/// Controllers plugged in later can be connected to the `ruxguitar:transport` port (e.g. with `aconnect`).
+#[cfg(target_os = "linux")]
+pub fn listen(
+    mut on_trigger: impl FnMut(MidiTrigger) + Send + 'static,
+) -> Result<(), MidiInputError> {
+    use alsa::Direction;
+    use alsa::seq::{
+        Addr, ClientIter, EvCtrl, EvNote, EventType, PortCap, PortIter, PortSubscribe, PortType,
+        Seq,
+    };
+
+    let seq = Seq::open(None, Some(Direction::Capture), false)?;
+    seq.set_client_name(c"ruxguitar")?;
+    let port = seq.create_simple_port(
+        c"transport",
+        PortCap::WRITE | PortCap::SUBS_WRITE,
+        PortType::MIDI_GENERIC | PortType::APPLICATION,
+    )?;
+    let dest = Addr {
+        client: seq.client_id()?,
+        port,
+    };
+
+    // connect every readable port except the system ones and our own
+    for client in ClientIter::new(&seq) {
+        let client_id = client.get_client();
+        if client_id == 0 || client_id == dest.client {
+            continue;
+        }
+        for port_info in PortIter::new(&seq, client_id) {
+            if !port_info
+                .get_capability()
+                .contains(PortCap::READ | PortCap::SUBS_READ)
+            {
+                continue;
+            }
+            let name = port_info.get_name().unwrap_or_default().to_string();
+            let subscribe = PortSubscribe::empty()?;
+            subscribe.set_sender(port_info.addr());
+            subscribe.set_dest(dest);
+            match seq.subscribe_port(&subscribe) {
+                Ok(()) => log::info!("Listening to MIDI input {name}"),
+                Err(err) => log::warn!("Could not connect MIDI input {name}: {err}"),
+            }
+        }
+    }
+
+    std::thread::Builder::new()
+        .name("midi-input".to_string())
+        .spawn(move || {
+            let mut input = seq.input();
+            loop {
+                let event = match input.event_input() {
+                    Ok(event) => event,
+                    Err(err) => {
+                        log::warn!("MIDI input stopped: {err}");
+                        return;
+                    }
+                };
+                let trigger = match event.get_type() {
+                    EventType::Noteon => event
+                        .get_data::<EvNote>()
+                        .and_then(|n| MidiTrigger::from_note(n.channel, n.note, n.velocity)),
+                    EventType::Controller => event.get_data::<EvCtrl>().and_then(|c| {
+                        MidiTrigger::from_control(
+                            c.channel,
+                            u8::try_from(c.param).ok()?,
+                            u8::try_from(c.value).ok()?,
+                        )
+                    }),
+                    _ => None,
+                };
+                if let Some(trigger) = trigger {
+                    log::debug!("MIDI input {trigger}");
+                    on_trigger(trigger);
+                }
+            }
+        })?;
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn listen(_on_trigger: impl FnMut(MidiTrigger) + Send + 'static) -> Result<(), MidiInputError> {
+    Err(MidiInputError::Unsupported)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pedal_press_only() {
+        assert_eq!(
+            MidiTrigger::from_control(0, 64, 127),
+            Some(MidiTrigger::ControlChange {
+                channel: 0,
+                controller: 64
+            })
+        );
+        assert_eq!(
+            MidiTrigger::from_control(0, 64, 64),
+            Some(MidiTrigger::ControlChange {
+                channel: 0,
+                controller: 64
+            })
+        );
+        assert_eq!(MidiTrigger::from_control(0, 64, 0), None);
+        assert_eq!(MidiTrigger::from_control(0, 64, 63), None);
+    }
+
+    #[test]
+    fn note_on_only() {
+        assert_eq!(
+            MidiTrigger::from_note(9, 36, 100),
+            Some(MidiTrigger::Note {
+                channel: 9,
+                key: 36
+            })
+        );
+        assert_eq!(MidiTrigger::from_note(9, 36, 0), None);
+    }
+
+    #[test]
+    fn trigger_label() {
+        let trigger = MidiTrigger::ControlChange {
+            channel: 0,
+            controller: 80,
+        };
+        assert_eq!(trigger.to_string(), "CC 80 (ch 1)");
+        let trigger = MidiTrigger::Note {
+            channel: 15,
+            key: 60,
+        };
+        assert_eq!(trigger.to_string(), "Note 60 (ch 16)");
+    }
+}
@@ -1,13 +1,21 @@
-use crate::audio::midi_builder::MidiBuilder;
-use crate::audio::midi_event::{FIRST_TICK, MidiEventType};
+use crate::audio::metronome::Metronome;
+use crate::audio::midi_builder::{MidiBuilder, PresetId, PresetRemaps, VibratoSettings};
+use crate::audio::midi_event::{FIRST_TICK, MidiEvent, MidiEventType};
 use crate::audio::midi_player_params::MidiPlayerParams;
-use crate::audio::midi_sequencer::MidiSequencer;
-use crate::audio::playback_order::first_playback_ticks;
-use crate::parser::song_parser::Song;
+pub use crate::audio::midi_player_params::{MAX_MASTER_VOLUME, TrackFilter};
+use crate::audio::midi_sequencer::{LoopGap, MidiSequencer};
+use crate::audio::playback_order::{
+    first_playback_ticks, playback_starts, pre_roll_tick, section_ticks,
+};
+pub use crate::audio::render_ahead::RENDER_AHEAD_MS;
+use crate::audio::render_ahead::{self, RenderSource, RenderThread};
+use crate::audio::synth_rack::{SoundFontAssignment, SynthRack};
+use crate::parser::song_parser::{MeasureHeader, Song};
 use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
-use rustysynth::{SoundFont, Synthesizer, SynthesizerSettings};
+use rustysynth::SoundFont;
+use std::fmt::Display;
 use std::fs::File;
-use std::path::PathBuf;
+use std::path::Path;
 use std::rc::Rc;
 use std::sync::atomic::{AtomicU32, Ordering};
 use std::sync::{Arc, Mutex};
@@ -15,74 +23,130 @@ use tokio::sync::Notify;
 
 const DEFAULT_SAMPLE_RATE: u32 = 44100; // number of samples per second
 
+/// What playback does at the end of a section.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum SectionEnd {
+    /// Wait at the end, play starts the section again.
+    #[default]
+    Stop,
+    /// Play the section again right away.
+    Loop,
+    /// Play the section again after a silent measure.
+    LoopAfterSilence,
+    /// Play the section again after a measure of clicks on the beats.
+    LoopAfterCountIn,
+}
+
+impl SectionEnd {
+    pub const ALL: [Self; 4] = [
+        Self::Stop,
+        Self::Loop,
+        Self::LoopAfterSilence,
+        Self::LoopAfterCountIn,
+    ];
+
+    /// Pause before the repetition of the section starting with the measure.
+    fn loop_gap(self, header: &MeasureHeader) -> LoopGap {
+        match self {
+            Self::Stop | Self::Loop => LoopGap::default(),
+            Self::LoopAfterSilence => LoopGap {
+                ticks: header.length(),
+                events: Vec::new(),
+            },
+            Self::LoopAfterCountIn => LoopGap {
+                ticks: header.length(),
+                events: Metronome::COUNT_IN.measure_clicks(header, FIRST_TICK),
+            },
+        }
+    }
+}
+
+impl Display for SectionEnd {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Stop => write!(f, "Stop at the end"),
+            Self::Loop => write!(f, "Loop"),
+            Self::LoopAfterSilence => write!(f, "Loop after a silent bar"),
+            Self::LoopAfterCountIn => write!(f, "Loop after a count-in"),
+        }
+    }
+}
+
 /// Default sound font file is embedded in the binary (6MB)
 const TIMIDITY_SOUND_FONT: &[u8] = include_bytes!("../../resources/TimGM6mb.sf2");
 
 pub struct AudioPlayer {
     is_playing: bool,
-    song: Rc<Song>,                       // Song to play (shared with app)
-    stream: Option<Rc<cpal::Stream>>,     // Stream is not Send & Sync
+    stream: Option<Rc<cpal::Stream>>,    // Stream is not Send & Sync
+    render_thread: Option<RenderThread>, // Renders audio ahead of the stream
     sequencer: Arc<Mutex<MidiSequencer>>, // Need a handle to reset sequencer
     player_params: Arc<MidiPlayerParams>, // Lock-free playback parameters
-    synthesizer: Arc<Mutex<Synthesizer>>, // Synthesizer for audio output
-    sound_font: Arc<SoundFont>,           // Sound font for synthesizer
-    current_tick: Arc<AtomicU32>,         // Latest tick reached by the audio callback
-    beat_notify: Arc<Notify>,             // Wake UI when current_tick changes
-    measure_playback_ticks: Vec<u32>,     // first playback tick per measure (for seeking)
+    synthesizer: Arc<Mutex<SynthRack>>,  // Synthesizers for audio output
+    sound_font: Arc<SoundFont>,          // Default sound font
+    current_tick: Arc<AtomicU32>,        // Latest tick reached by the audio callback
+    beat_notify: Arc<Notify>,            // Wake UI when current_tick changes
+    measure_playback_ticks: Vec<u32>,    // first playback tick per measure (for seeking)
+    playback_starts: Vec<(usize, u32)>,  // measure and start tick in playback order
+    section: Option<(u32, Option<u32>)>, // start and end ticks playback is bounded to
+    section_measure: Option<usize>,      // first measure of the section
+    section_end: SectionEnd,             // stop or loop at the end of the section
+    song: Arc<Song>,                     // measures of the song, for the loop gap
+    audio_backend: Option<String>,       // audio backend name, the platform default when unset
+    stream_info: Option<StreamInfo>,     // output stream opened on the first play
 }
 
 impl AudioPlayer {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
-        song: Rc<Song>,
+        song: Arc<Song>,
         song_tempo: u32,
         tempo_percentage: u32,
-        sound_font_file: Option<PathBuf>,
+        sound_fonts: &SoundFontAssignment,
         current_tick: Arc<AtomicU32>,
         beat_notify: Arc<Notify>,
         playback_order: &[(usize, i64)],
+        vibrato: VibratoSettings,
+        play_muted_tracks: bool,
+        preset_remaps: PresetRemaps,
     ) -> Result<Self, AudioPlayerError> {
-        // default to no solo track
-        let solo_track_id = None;
-
-        // player params
+        // player params, every track heard by default
         let player_params = Arc::new(MidiPlayerParams::new(
-            song_tempo,
             tempo_percentage,
-            solo_track_id,
+            TrackFilter::default(),
         ));
 
         // midi sequencer initialization
-        let builder = MidiBuilder::new();
+        let builder = MidiBuilder::new()
+            .with_vibrato(vibrato)
+            .with_muted_tracks(play_muted_tracks)
+            .with_preset_remaps(preset_remaps);
         let events = builder.build_for_song_with_order(&song, playback_order);
 
         // build first-playback-tick lookup per measure (for seeking)
         let measure_playback_ticks = first_playback_ticks(&song.measure_headers, playback_order);
+        let playback_starts = playback_starts(&song.measure_headers, playback_order);
 
-        // sound font setup
-        let sound_font = if let Some(ref sound_font_file) = sound_font_file {
-            let mut sf2 = File::open(sound_font_file).map_err(|e| {
-                AudioPlayerError::SoundFontFileError(format!("{}: {e}", sound_font_file.display()))
-            })?;
-            SoundFont::new(&mut sf2).map_err(|e| {
-                AudioPlayerError::SoundFontLoadError(format!("{}: {e}", sound_font_file.display()))
-            })?
-        } else {
-            let mut sf2 = TIMIDITY_SOUND_FONT;
-            SoundFont::new(&mut sf2)
-                .map_err(|e| AudioPlayerError::SoundFontLoadError(format!("embedded: {e}")))?
-        };
-        let sound_font = Arc::new(sound_font);
+        // sound fonts setup, the default one first
+        let sound_font = Arc::new(load_sound_font(sound_fonts.default.as_deref())?);
+        let mut loaded = vec![sound_font.clone()];
+        for file in sound_fonts.extra_files() {
+            loaded.push(Arc::new(load_sound_font(Some(file))?));
+        }
+        let track_synthesizers = (0..song.tracks.len())
+            .map(|track| sound_fonts.sound_font_index(track))
+            .collect();
 
-        // build new default synthesizer for the stream
-        let synthesizer = Self::make_synthesizer(sound_font.clone(), DEFAULT_SAMPLE_RATE)?;
-        let midi_sequencer = MidiSequencer::new(events);
+        // build new default synthesizers for the stream
+        let synthesizer = SynthRack::new(loaded, track_synthesizers, DEFAULT_SAMPLE_RATE)
+            .map_err(AudioPlayerError::SynthesizerError)?;
+        let midi_sequencer = MidiSequencer::new(events, song_tempo, DEFAULT_SAMPLE_RATE);
 
         let synthesizer = Arc::new(Mutex::new(synthesizer));
         let sequencer = Arc::new(Mutex::new(midi_sequencer));
         Ok(Self {
             is_playing: false,
-            song,
             stream: None,
+            render_thread: None,
             sequencer,
             player_params,
             synthesizer,
@@ -90,38 +154,147 @@ impl AudioPlayer {
             current_tick,
             beat_notify,
             measure_playback_ticks,
+            playback_starts,
+            section: None,
+            section_measure: None,
+            section_end: SectionEnd::default(),
+            song,
+            audio_backend: None,
+            stream_info: None,
         })
     }
 
-    fn make_synthesizer(
-        sound_font: Arc<SoundFont>,
-        sample_rate: u32,
-    ) -> Result<Synthesizer, AudioPlayerError> {
-        let synthesizer_settings = SynthesizerSettings::new(sample_rate as i32);
-        let synthesizer_settings = Arc::new(synthesizer_settings);
-        debug_assert_eq!(synthesizer_settings.sample_rate, sample_rate as i32);
-        Synthesizer::new(&sound_font, &synthesizer_settings)
-            .map_err(|e| AudioPlayerError::SynthesizerError(e.to_string()))
-    }
-
     pub const fn is_playing(&self) -> bool {
         self.is_playing
     }
 
-    pub fn solo_track_id(&self) -> Option<usize> {
-        self.player_params.solo_track_id()
+    pub fn track_filter(&self) -> TrackFilter {
+        self.player_params.track_filter()
+    }
+
+    pub fn set_track_filter(&self, track_filter: TrackFilter) {
+        log::info!("Playing {track_filter:?}");
+        self.player_params.set_track_filter(track_filter);
+        // notes of the tracks switched off would otherwise ring until their note-off
+        self.silence();
     }
 
     pub fn toggle_solo_mode(&self, new_track_id: usize) {
-        if self.player_params.solo_track_id() == Some(new_track_id) {
-            log::info!("Disable solo mode on track {new_track_id}");
-            self.player_params.set_solo_track_id(None);
+        let solo = TrackFilter::Solo(new_track_id);
+        if self.track_filter() == solo {
+            self.set_track_filter(TrackFilter::AllTracks);
+        } else {
+            self.set_track_filter(solo);
+        }
+    }
+
+    /// Mute the track to play it along the others, replaces the solo mode.
+    pub fn toggle_backing_mode(&self, new_track_id: usize) {
+        let backing = TrackFilter::Backing(new_track_id);
+        if self.track_filter() == backing {
+            self.set_track_filter(TrackFilter::AllTracks);
+        } else {
+            self.set_track_filter(backing);
+        }
+    }
+
+    /// Pause playback at the end of every measure until `next_measure` is called.
+    pub fn set_practice_mode(&self, enabled: bool) {
+        let stop_ticks = if enabled {
+            // a measure ends where the next one in playback order starts
+            self.playback_starts
+                .iter()
+                .skip(1)
+                .map(|(_, tick)| *tick)
+                .collect()
         } else {
-            log::info!("Enable solo mode on track {new_track_id}");
-            self.player_params.set_solo_track_id(Some(new_track_id));
+            Vec::new()
+        };
+        self.sequencer.lock().unwrap().set_stop_ticks(stop_ticks);
+    }
+
+    /// Bound the playback to the measures from `first` to `last` in playback order,
+    /// `None` plays the whole song again. Playback moves to the start of the section.
+    pub fn set_section(&mut self, measures: Option<(usize, usize)>) {
+        self.section =
+            measures.and_then(|(first, last)| section_ticks(&self.playback_starts, first, last));
+        self.section_measure = measures.map(|(first, _)| first);
+        self.update_section_end();
+        if let Some((start, _)) = self.section {
+            self.seek(start);
         }
     }
 
+    /// Stop or loop at the end of the section, with a pause before each repetition.
+    pub fn set_section_end(&mut self, section_end: SectionEnd) {
+        self.section_end = section_end;
+        self.update_section_end();
+    }
+
+    fn update_section_end(&self) {
+        let mut sequencer_guard = self.sequencer.lock().unwrap();
+        let Some((start, end)) = self.section else {
+            sequencer_guard.set_end_tick(None);
+            sequencer_guard.set_loop(None, LoopGap::default());
+            return;
+        };
+        if self.section_end == SectionEnd::Stop {
+            sequencer_guard.set_end_tick(end);
+            sequencer_guard.set_loop(None, LoopGap::default());
+            return;
+        }
+        // a section running to the end of the song loops after its last measure
+        let song_end = self
+            .playback_starts
+            .last()
+            .map(|(measure, tick)| tick + self.song.measure_headers[*measure].length());
+        let header = &self.song.measure_headers[self.section_measure.unwrap_or_default()];
+        sequencer_guard.set_end_tick(end.or(song_end));
+        sequencer_guard.set_loop(Some(start), self.section_end.loop_gap(header));
+    }
+
+    /// Whether playback waits at the end of a measure in practice mode or at the end of the section.
+    pub fn is_waiting_at_measure_end(&self) -> bool {
+        self.sequencer.lock().unwrap().is_halted()
+    }
+
+    /// Whether playback waits at the end of the section.
+    pub fn is_at_section_end(&self) -> bool {
+        self.sequencer.lock().unwrap().is_at_end()
+    }
+
+    /// Play the next measure in practice mode, or the section again from its end.
+    pub fn next_measure(&self) {
+        if self.is_at_section_end()
+            && let Some((start, _)) = self.section
+        {
+            self.seek(start);
+            return;
+        }
+        let mut sequencer_guard = self.sequencer.lock().unwrap();
+        sequencer_guard.resume();
+        // drop the silence rendered ahead while waiting
+        self.player_params.request_flush();
+    }
+
+    /// Move the playback to the start of the section unless it is within it.
+    fn enter_section(&self) {
+        let Some((start, end)) = self.section else {
+            return;
+        };
+        let tick = self.current_tick.load(Ordering::Relaxed);
+        // waiting right before the end counts as outside
+        if tick < start || end.is_some_and(|end| tick + 1 >= end) {
+            self.seek(start);
+        }
+    }
+
+    /// Click along the playback, the change is heard right away.
+    pub fn set_metronome(&self, metronome: Metronome) {
+        let clicks = metronome.clicks(&self.song.measure_headers, &self.playback_starts);
+        self.sequencer.lock().unwrap().set_clicks(clicks);
+    }
+
     pub fn set_tempo_percentage(&self, new_tempo_percentage: u32) {
         self.player_params
             .set_tempo_percentage(new_tempo_percentage);
@@ -135,6 +308,56 @@ impl AudioPlayer {
         self.player_params.set_master_volume(volume);
     }
 
+    /// Play the same downmix on both channels, for single speaker setups.
+    pub fn set_mono_output(&self, mono: bool) {
+        self.player_params.set_mono(mono);
+    }
+
+    /// Play through the named audio backend, the stream is reopened on the next play.
+    pub fn set_audio_backend(&mut self, audio_backend: Option<String>) {
+        if self.audio_backend != audio_backend {
+            self.audio_backend = audio_backend;
+            self.stop();
+            self.stream_info = None;
+        }
+    }
+
+    /// Output stream opened for the playback, with the device buffer seen so far.
+    pub fn stream_info(&self) -> Option<StreamInfo> {
+        self.stream_info.clone().map(|info| StreamInfo {
+            buffer_frames: Some(self.player_params.buffer_frames()).filter(|frames| *frames > 0),
+            ..info
+        })
+    }
+
+    /// Presets of the loaded sound font.
+    pub fn preset_count(&self) -> usize {
+        self.sound_font.get_presets().len()
+    }
+
+    /// Presets of the loaded sound font with their names, by bank and patch.
+    pub fn presets(&self) -> Vec<(PresetId, String)> {
+        let mut presets: Vec<(PresetId, String)> = self
+            .sound_font
+            .get_presets()
+            .iter()
+            .filter_map(|preset| {
+                let id = PresetId {
+                    bank: u8::try_from(preset.get_bank_number()).ok()?,
+                    patch: u8::try_from(preset.get_patch_number()).ok()?,
+                };
+                Some((id, preset.get_name().to_string()))
+            })
+            .collect();
+        presets.sort();
+        presets
+    }
+
+    /// RMS level of the left & right output while playing.
+    pub fn output_levels(&self) -> (f32, f32) {
+        self.player_params.output_levels()
+    }
+
     pub fn stop(&mut self) {
         // Pause stream
         if let Some(stream) = &self.stream {
@@ -146,16 +369,15 @@ impl AudioPlayer {
         }
         self.is_playing = false;
 
+        // stop rendering before touching the sequencer and synthesizer
+        self.render_thread.take();
+
         // reset ticks
-        let mut sequencer_guard = self.sequencer.lock().unwrap();
-        sequencer_guard.reset_last_time();
-        sequencer_guard.reset_ticks();
-        drop(sequencer_guard);
+        self.sequencer.lock().unwrap().reset_ticks();
 
         // stop all sound in synthesizer
-        let mut synthesizer_guard = self.synthesizer.lock().unwrap();
-        synthesizer_guard.note_off_all(false);
-        drop(synthesizer_guard);
+        self.silence();
+        self.player_params.set_output_levels(0.0, 0.0);
 
         // reset the UI cursor to the first playable tick so the measure lookup resolves cleanly
         self.current_tick.store(FIRST_TICK, Ordering::Relaxed);
@@ -174,30 +396,35 @@ impl AudioPlayer {
                 if let Err(err) = stream.pause() {
                     return Some(format!("Failed to pause audio stream: {err}"));
                 }
+                self.player_params.set_output_levels(0.0, 0.0);
+                // drop the notes rendered ahead, playback resumes from the displayed tick
+                self.seek(self.current_tick.load(Ordering::Relaxed));
             } else {
                 self.is_playing = true;
-                // reset last time to not advance time too fast on resume
-                self.sequencer.lock().unwrap().reset_last_time();
+                self.enter_section();
                 if let Err(err) = stream.play() {
                     return Some(format!("Failed to resume audio stream: {err}"));
                 }
             }
         } else {
             self.is_playing = true;
+            self.enter_section();
 
             // Initialize audio output stream
             let stream = new_output_stream(
                 self.sequencer.clone(),
                 self.player_params.clone(),
                 self.synthesizer.clone(),
-                self.sound_font.clone(),
                 self.current_tick.clone(),
                 self.beat_notify.clone(),
+                self.audio_backend.as_deref(),
             );
 
             match stream {
-                Ok(stream) => {
+                Ok((stream, render_thread, stream_info)) => {
                     self.stream = Some(Rc::new(stream));
+                    self.render_thread = Some(render_thread);
+                    self.stream_info = Some(stream_info);
                 }
                 Err(err) => {
                     self.is_playing = false;
@@ -210,26 +437,60 @@ impl AudioPlayer {
     }
 
     pub fn focus_measure(&self, measure_id: usize) {
-        log::debug!("Focus audio player on measure:{measure_id}");
-        let measure = &self.song.measure_headers[measure_id];
-        let measure_start_tick = self.measure_playback_ticks[measure_id];
-        let tempo = measure.tempo.value;
+        self.focus_measure_with_pre_roll(measure_id, 0);
+    }
+
+    /// Focus on the measure, playback starts `pre_roll` measures earlier to lead into it.
+    pub fn focus_measure_with_pre_roll(&self, measure_id: usize, pre_roll: usize) {
+        log::debug!("Focus audio player on measure:{measure_id} with pre-roll:{pre_roll}");
+        let measure_start_tick = pre_roll_tick(&self.playback_starts, measure_id, pre_roll)
+            .unwrap_or(self.measure_playback_ticks[measure_id]);
+        self.seek(measure_start_tick);
+    }
+
+    /// Move the playback to a tick of the expanded playback timeline.
+    pub fn seek(&self, tick: u32) {
+        log::debug!("Seek audio player to tick:{tick}");
+        self.current_tick.store(tick, Ordering::Relaxed);
 
-        // move sequencer to measure start tick
+        // move sequencer to the tick
+        // discard the audio rendered ahead while the sequencer cannot move
         let mut sequencer_guard = self.sequencer.lock().unwrap();
-        sequencer_guard.set_tick(measure_start_tick);
+        sequencer_guard.set_tick(tick);
+        self.player_params.request_flush();
+        // mix table changes may have switched instruments and levels since the start
+        let settings: Vec<MidiEvent> = sequencer_guard
+            .channel_settings_before(tick)
+            .cloned()
+            .collect();
         drop(sequencer_guard);
 
-        // stop current sound
+        // stop current sound, then restore the channels as they are at the tick
+        self.silence();
         let mut synthesizer_guard = self.synthesizer.lock().unwrap();
-        synthesizer_guard.note_off_all(false);
-        drop(synthesizer_guard);
+        render_ahead::dispatch_events(&settings, &mut synthesizer_guard, TrackFilter::AllTracks);
+    }
 
-        // set tempo for focuses measure
-        self.player_params.set_tempo(tempo);
+    /// Release the sounding notes and reset the controllers of the synthesizer.
+    fn silence(&self) {
+        let mut synthesizer_guard = self.synthesizer.lock().unwrap();
+        render_ahead::silence(&mut synthesizer_guard);
     }
 }
 
+/// Sound font from the file, the embedded one if `None`.
+fn load_sound_font(file: Option<&Path>) -> Result<SoundFont, AudioPlayerError> {
+    let Some(file) = file else {
+        let mut sf2 = TIMIDITY_SOUND_FONT;
+        return SoundFont::new(&mut sf2)
+            .map_err(|e| AudioPlayerError::SoundFontLoadError(format!("embedded: {e}")));
+    };
+    let mut sf2 = File::open(file)
+        .map_err(|e| AudioPlayerError::SoundFontFileError(format!("{}: {e}", file.display())))?;
+    SoundFont::new(&mut sf2)
+        .map_err(|e| AudioPlayerError::SoundFontLoadError(format!("{}: {e}", file.display())))
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum AudioPlayerError {
     #[error("audio device not found")]
@@ -246,16 +507,64 @@ pub enum AudioPlayerError {
     StreamError(String),
 }
 
-/// Create a new output stream for audio playback.
+/// Output stream opened by the player, shown in the diagnostics.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StreamInfo {
+    pub backend: &'static str,
+    pub device: String,
+    pub sample_rate: u32,
+    pub channels: u16,
+    pub buffer_frames: Option<u32>, // known once the device requested audio
+}
+
+impl StreamInfo {
+    /// Latency added by the device buffer.
+    pub fn buffer_ms(&self) -> Option<f64> {
+        self.buffer_frames
+            .map(|frames| f64::from(frames) * 1000.0 / f64::from(self.sample_rate))
+    }
+}
+
+/// Audio backends available in this build, e.g. ALSA, JACK or PipeWire on Linux.
+/// JACK and PipeWire need the `jack` and `pipewire` features.
+pub fn audio_backends() -> Vec<&'static str> {
+    cpal::available_hosts()
+        .iter()
+        .map(cpal::HostId::name)
+        .collect()
+}
+
+/// Host of the named audio backend, the platform default when unset or unavailable.
+fn output_host(backend: Option<&str>) -> cpal::Host {
+    let Some(name) = backend else {
+        return cpal::default_host();
+    };
+    let host_id = cpal::available_hosts()
+        .into_iter()
+        .find(|host_id| host_id.name() == name);
+    match host_id.map(cpal::host_from_id) {
+        Some(Ok(host)) => host,
+        Some(Err(err)) => {
+            log::warn!("Audio backend {name} unavailable ({err}), using the default one");
+            cpal::default_host()
+        }
+        None => {
+            log::warn!("Audio backend {name} not available in this build, using the default one");
+            cpal::default_host()
+        }
+    }
+}
+
+/// Create a new output stream for audio playback, fed by a render ahead thread.
 fn new_output_stream(
     sequencer: Arc<Mutex<MidiSequencer>>,
     player_params: Arc<MidiPlayerParams>,
-    synthesizer: Arc<Mutex<Synthesizer>>,
-    sound_font: Arc<SoundFont>,
+    synthesizer: Arc<Mutex<SynthRack>>,
     current_tick: Arc<AtomicU32>,
     beat_notify: Arc<Notify>,
-) -> Result<cpal::Stream, AudioPlayerError> {
-    let host = cpal::default_host();
+    backend: Option<&str>,
+) -> Result<(cpal::Stream, RenderThread, StreamInfo), AudioPlayerError> {
+    let host = output_host(backend);
     let Some(device) = host.default_output_device() else {
         return Err(AudioPlayerError::CpalDeviceNotFound);
     };
@@ -275,142 +584,59 @@ fn new_output_stream(
     let channel_count = usize::from(stream_config.channels).max(1);
 
     log::info!("Audio output stream config: {stream_config:?}");
+    let stream_info = StreamInfo {
+        backend: host.id().name(),
+        device: device.to_string(),
+        sample_rate,
+        channels: stream_config.channels,
+        buffer_frames: None,
+    };
 
     let mut synthesizer_guard = synthesizer.lock().unwrap();
     if sample_rate != DEFAULT_SAMPLE_RATE {
-        // audio output is not using the default sample rate - recreate synthesizer with proper sample rate
-        let new_synthesizer = AudioPlayer::make_synthesizer(sound_font, sample_rate)?;
+        // audio output is not using the default sample rate - recreate synthesizers with proper sample rate
+        let new_synthesizer = synthesizer_guard
+            .with_sample_rate(sample_rate)
+            .map_err(AudioPlayerError::SynthesizerError)?;
         *synthesizer_guard = new_synthesizer;
     }
 
+    let mut sequencer_guard = sequencer.lock().unwrap();
+    sequencer_guard.set_sample_rate(sample_rate);
+
     // Apply events at tick=FIRST_TICK to set up synthesizer state
     // otherwise clicking on a measure *before* playing does not produce the correct instrument sound
-    sequencer
-        .lock()
-        .unwrap()
+    sequencer_guard
         .events()
         .iter()
         .take_while(|event| event.tick == FIRST_TICK)
         .filter(|event| event.is_midi_message())
         .for_each(|event| {
             if let MidiEventType::MidiMessage(channel, command, data1, data2) = event.event {
-                synthesizer_guard.process_midi_message(channel, command, data1, data2);
+                synthesizer_guard.process_midi_message(event.track, channel, command, data1, data2);
             }
         });
 
     drop(synthesizer_guard);
+    drop(sequencer_guard);
 
-    // Size left and right buffers according to sample rate.
-    // The buffer accounts for 0.1 second of audio.
-    // e.g. 4410 samples at 44100 Hz is 0.1 second
-    let channel_sample_count = sample_rate / 10;
-
-    // reuse buffer for left and right channels across all calls
-    let mut left: Vec<f32> = vec![0_f32; channel_sample_count as usize];
-    let mut right: Vec<f32> = vec![0_f32; channel_sample_count as usize];
+    let (render_thread, mut consumer) = render_ahead::start(
+        RenderSource {
+            sequencer,
+            player_params: player_params.clone(),
+            synthesizer,
+            current_tick,
+            beat_notify,
+        },
+        sample_rate,
+    );
 
     let err_fn = |err| log::error!("an error occurred on stream: {err}");
 
     let stream = device.build_output_stream(
         stream_config,
         move |output: &mut [f32], _: &cpal::OutputCallbackInfo| {
-            let mut sequencer_guard = sequencer.lock().unwrap();
-            sequencer_guard.advance(player_params.adjusted_tempo());
-            let mut synthesizer_guard = synthesizer.lock().unwrap();
-            // process midi events for current tick
-            if let Some(events) = sequencer_guard.get_next_events() {
-                let tick = sequencer_guard.get_tick();
-                let last_tick = sequencer_guard.get_last_tick();
-                if !events.is_empty() {
-                    log::debug!(
-                        "---> Increase {} ticks [{} -> {}] ({} events)",
-                        tick - last_tick,
-                        last_tick,
-                        tick,
-                        events.len()
-                    );
-                }
-                let solo_track_id = player_params.solo_track_id();
-                if events
-                    .iter()
-                    .any(super::midi_event::MidiEvent::is_note_event)
-                {
-                    current_tick.store(tick, Ordering::Release);
-                    beat_notify.notify_one();
-                }
-                for midi_event in events {
-                    match midi_event.event {
-                        MidiEventType::NoteOn(channel, key, velocity) => {
-                            if let Some(track_id) = solo_track_id {
-                                // skip note on events for other tracks in solo mode
-                                if midi_event.track != Some(track_id as u8) {
-                                    continue;
-                                }
-                            }
-                            log::debug!(
-                                "[{}] Note on: channel={}, key={}, velocity={}",
-                                midi_event.tick,
-                                channel,
-                                key,
-                                velocity
-                            );
-                            synthesizer_guard.note_on(channel, key, i32::from(velocity));
-                        }
-                        MidiEventType::NoteOff(channel, key) => {
-                            log::debug!(
-                                "[{}] Note off: channel={}, key={}",
-                                midi_event.tick,
-                                channel,
-                                key
-                            );
-                            synthesizer_guard.note_off(channel, key);
-                        }
-                        MidiEventType::TempoChange(tempo) => {
-                            // debug level: runs on the real-time audio thread
-                            log::debug!("Tempo changed to {tempo}");
-                            player_params.set_tempo(tempo);
-                        }
-                        MidiEventType::MidiMessage(channel, command, data1, data2) => {
-                            log::debug!(
-                                "[{}] Midi message: channel={}, command={}, data1={}, data2={}",
-                                midi_event.tick,
-                                channel,
-                                command,
-                                data1,
-                                data2
-                            );
-                            synthesizer_guard.process_midi_message(channel, command, data1, data2);
-                        }
-                    }
-                }
-            }
-            // frames requested by the device for its channel layout
-            let frame_count = output.len() / channel_count;
-            let render_len = frame_count.min(left.len());
-            if render_len < frame_count {
-                // debug level: runs on the real-time audio thread
-                log::debug!(
-                    "Output buffer larger than render buffer {frame_count} > {}",
-                    left.len()
-                );
-            }
-
-            // Render the waveform.
-            synthesizer_guard.render(&mut left[..render_len], &mut right[..render_len]);
-
-            let master_volume = player_params.master_volume();
-
-            // Drop locks
-            drop(sequencer_guard);
-            drop(synthesizer_guard);
-
-            write_frames(
-                output,
-                &left[..render_len],
-                &right[..render_len],
-                channel_count,
-                master_volume,
-            );
+            consumer.fill(output, channel_count, &player_params);
         },
         err_fn,
         None, // blocking stream
@@ -419,93 +645,104 @@ fn new_output_stream(
     stream
         .play()
         .map_err(|e| AudioPlayerError::StreamError(e.to_string()))?;
-    Ok(stream)
-}
-
-/// Interleave rendered stereo samples into the device's frame layout.
-///
-/// Mono devices get a downmix, channels beyond stereo are zeroed.
-/// Frames past the rendered samples are silenced explicitly: the output
-/// buffer is not guaranteed to be zeroed and would replay stale samples.
-fn write_frames(
-    output: &mut [f32],
-    left: &[f32],
-    right: &[f32],
-    channel_count: usize,
-    master_volume: f32,
-) {
-    let rendered = left.len().min(right.len());
-    let mut frames = output.chunks_exact_mut(channel_count);
-    for (i, frame) in frames.by_ref().enumerate() {
-        let (l, r) = if i < rendered {
-            (left[i] * master_volume, right[i] * master_volume)
-        } else {
-            (0.0, 0.0)
-        };
-        match frame {
-            [mono] => *mono = (l + r) / 2.0,
-            [first, second, rest @ ..] => {
-                *first = l;
-                *second = r;
-                rest.fill(0.0);
-            }
-            [] => {}
-        }
-    }
-    // leftover samples when the buffer is not a whole number of frames
-    frames.into_remainder().fill(0.0);
+    Ok((stream, render_thread, stream_info))
 }
 
 #[cfg(test)]
 mod tests {
-    use super::write_frames;
-
-    #[test]
-    fn write_frames_stereo_applies_volume() {
-        let left = [1.0, 0.5];
-        let right = [-1.0, 0.25];
-        let mut output = [9.0_f32; 4];
-        write_frames(&mut output, &left, &right, 2, 0.5);
-        assert_eq!(output, [0.5, -0.5, 0.25, 0.125]);
+    use super::*;
+    use crate::parser::song_parser::QUARTER_TIME;
+    use crate::parser::song_parser_tests::parse_gp_file;
+
+    fn peak(synthesizer: &mut SynthRack, frames: usize) -> f32 {
+        let mut left = vec![0.0; frames];
+        let mut right = vec![0.0; frames];
+        synthesizer.render(&mut left, &mut right);
+        left.iter()
+            .chain(&right)
+            .fold(0.0, |peak, s| peak.max(s.abs()))
     }
 
     #[test]
-    fn write_frames_mono_downmixes() {
-        let left = [1.0, 0.5];
-        let right = [0.5, 0.25];
-        let mut output = [9.0_f32; 2];
-        write_frames(&mut output, &left, &right, 1, 1.0);
-        assert_eq!(output, [0.75, 0.375]);
+    fn count_in_clicks_the_beats_of_the_measure() {
+        let mut header = MeasureHeader::default();
+        header.time_signature.numerator = 3;
+        let gap = SectionEnd::LoopAfterCountIn.loop_gap(&header);
+        assert_eq!(gap.ticks, 3 * QUARTER_TIME);
+        let clicks: Vec<u32> = gap
+            .events
+            .iter()
+            .filter(|event| matches!(event.event, MidiEventType::NoteOn(..)))
+            .map(|event| event.tick)
+            .collect();
+        assert_eq!(clicks, vec![1, 961, 1921]);
+        assert!(gap.events.is_sorted_by_key(|event| event.tick));
+        assert_eq!(
+            SectionEnd::LoopAfterSilence.loop_gap(&header).events,
+            vec![]
+        );
+        assert_eq!(SectionEnd::Loop.loop_gap(&header).ticks, 0);
     }
 
     #[test]
-    fn write_frames_zeroes_extra_channels() {
-        let left = [1.0];
-        let right = [0.5];
-        // 4-channel device: one frame, extra channels silenced
-        let mut output = [9.0_f32; 4];
-        write_frames(&mut output, &left, &right, 4, 1.0);
-        assert_eq!(output, [1.0, 0.5, 0.0, 0.0]);
+    fn silence_releases_notes_and_controllers() {
+        let sound_font = Arc::new(load_sound_font(None).unwrap());
+        let mut synthesizer =
+            SynthRack::new(vec![sound_font], vec![0], DEFAULT_SAMPLE_RATE).unwrap();
+        let second = DEFAULT_SAMPLE_RATE as usize;
+
+        // sustained organ note bent up, its note-off never comes
+        synthesizer.process_midi_message(Some(0), 0, 0xC0, 19, 0);
+        synthesizer.process_midi_message(Some(0), 0, 0xE0, 0, 127);
+        synthesizer.note_on(Some(0), 0, 60, 100);
+        assert!(peak(&mut synthesizer, second) > 0.01);
+        assert!(peak(&mut synthesizer, second) > 0.01);
+
+        render_ahead::silence(&mut synthesizer);
+        peak(&mut synthesizer, second);
+        // only a faint reverb tail is left
+        assert!(peak(&mut synthesizer, second / 10) < 0.001);
+
+        // the program is kept and the bend is reset
+        synthesizer.note_on(Some(0), 0, 60, 100);
+        assert!(peak(&mut synthesizer, second / 10) > 0.01);
     }
 
     #[test]
-    fn write_frames_silences_unrendered_tail() {
-        let left = [1.0];
-        let right = [0.5];
-        // device asks for 3 frames but only 1 was rendered:
-        // the stale tail must be silenced, not replayed
-        let mut output = [9.0_f32; 6];
-        write_frames(&mut output, &left, &right, 2, 1.0);
-        assert_eq!(output, [1.0, 0.5, 0.0, 0.0, 0.0, 0.0]);
-    }
-
-    #[test]
-    fn write_frames_zeroes_partial_frame_remainder() {
-        let left = [1.0];
-        let right = [0.5];
-        // 5 samples on a stereo device: the dangling half-frame is silenced
-        let mut output = [9.0_f32; 5];
-        write_frames(&mut output, &left, &right, 2, 1.0);
-        assert_eq!(output, [1.0, 0.5, 0.0, 0.0, 0.0]);
+    fn tracks_play_at_their_pan() {
+        // the rhythm guitars of the reference file are panned apart
+        let song = parse_gp_file("test-files/Tyr - Evening Star.gpx").unwrap();
+        let events = MidiBuilder::new().build_for_song(&Arc::new(song));
+        let sound_font = Arc::new(load_sound_font(None).unwrap());
+        for (track, left_side) in [(2, true), (3, false)] {
+            let mut synthesizer =
+                SynthRack::new(vec![sound_font.clone()], vec![0; 4], DEFAULT_SAMPLE_RATE).unwrap();
+            // channel settings at the start of the song, pan included
+            let mut track_channel = 0;
+            for event in events
+                .iter()
+                .filter(|e| e.tick == FIRST_TICK && e.track == Some(track))
+            {
+                if let MidiEventType::MidiMessage(channel, command @ (0xB0 | 0xC0), data1, data2) =
+                    event.event
+                {
+                    track_channel = channel;
+                    synthesizer.process_midi_message(Some(track), channel, command, data1, data2);
+                }
+            }
+            synthesizer.note_on(Some(track), track_channel, 52, 100);
+            let frames = DEFAULT_SAMPLE_RATE as usize / 2;
+            let mut left = vec![0.0; frames];
+            let mut right = vec![0.0; frames];
+            synthesizer.render(&mut left, &mut right);
+            let energy = |samples: &[f32]| samples.iter().map(|s| s * s).sum::<f32>();
+            let (left, right) = (energy(&left), energy(&right));
+            assert!(
+                left > 0.0 && right > 0.0,
+                "track {track} is silent on a side"
+            );
+            assert_eq!(left > right * 1.2, left_side, "track {track}");
+            assert_eq!(right > left * 1.2, !left_side, "track {track}");
+        }
     }
 }
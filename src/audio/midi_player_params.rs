@@ -1,23 +1,55 @@
-use std::sync::atomic::{AtomicI32, AtomicU32, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicI32, AtomicU32, Ordering};
 
 const SOLO_NONE: i32 = -1;
 
+/// Loudest master volume, boosting quiet files; the limiter keeps the output from clipping.
+pub const MAX_MASTER_VOLUME: f32 = 2.0;
+
+/// Tracks heard during the playback, the notes of the others are not started.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TrackFilter {
+    #[default]
+    AllTracks,
+    Solo(usize),    // only this track
+    Backing(usize), // every track but this one, to play along
+}
+
+impl TrackFilter {
+    pub fn plays(self, track: Option<u8>) -> bool {
+        match self {
+            Self::AllTracks => true,
+            Self::Solo(id) => track == Some(id as u8),
+            Self::Backing(id) => track != Some(id as u8),
+        }
+    }
+}
+
 /// Playback parameters shared lock-free between UI and audio callback.
 pub struct MidiPlayerParams {
-    tempo: AtomicU32,
     tempo_percentage: AtomicU32,
-    solo_track_id: AtomicI32, // -1 == None
-    master_volume: AtomicU32, // f32 bits
+    solo_track_id: AtomicI32,      // -1 == None
+    backing_track_id: AtomicI32,   // -1 == None, muted to play along
+    master_volume: AtomicU32,      // f32 bits
+    flush_generation: AtomicU32,   // bumped when audio rendered ahead is obsolete
+    output_levels: [AtomicU32; 2], // f32 bits, RMS of the left & right output
+    buffer_frames: AtomicU32,      // frames requested by the last device callback
+    mono: AtomicBool,              // both channels play the downmix
 }
 
 impl MidiPlayerParams {
-    pub fn new(tempo: u32, tempo_percentage: u32, solo_track_id: Option<usize>) -> Self {
-        Self {
-            tempo: AtomicU32::new(tempo),
+    pub fn new(tempo_percentage: u32, track_filter: TrackFilter) -> Self {
+        let params = Self {
             tempo_percentage: AtomicU32::new(tempo_percentage),
-            solo_track_id: AtomicI32::new(solo_track_id.map_or(SOLO_NONE, |id| id as i32)),
+            solo_track_id: AtomicI32::new(SOLO_NONE),
+            backing_track_id: AtomicI32::new(SOLO_NONE),
             master_volume: AtomicU32::new(1.0_f32.to_bits()),
-        }
+            flush_generation: AtomicU32::new(0),
+            output_levels: [AtomicU32::new(0), AtomicU32::new(0)],
+            buffer_frames: AtomicU32::new(0),
+            mono: AtomicBool::new(false),
+        };
+        params.set_track_filter(track_filter);
+        params
     }
 
     pub fn master_volume(&self) -> f32 {
@@ -25,38 +57,107 @@ impl MidiPlayerParams {
     }
 
     pub fn set_master_volume(&self, volume: f32) {
-        self.master_volume
-            .store(volume.clamp(0.0, 1.0).to_bits(), Ordering::Relaxed);
+        self.master_volume.store(
+            volume.clamp(0.0, MAX_MASTER_VOLUME).to_bits(),
+            Ordering::Relaxed,
+        );
     }
 
-    pub fn solo_track_id(&self) -> Option<usize> {
-        match self.solo_track_id.load(Ordering::Relaxed) {
+    /// Solo takes precedence, both are not set together.
+    pub fn track_filter(&self) -> TrackFilter {
+        let track_id = |atomic: &AtomicI32| match atomic.load(Ordering::Relaxed) {
             SOLO_NONE => None,
             id => Some(id as usize),
+        };
+        match (
+            track_id(&self.solo_track_id),
+            track_id(&self.backing_track_id),
+        ) {
+            (Some(id), _) => TrackFilter::Solo(id),
+            (None, Some(id)) => TrackFilter::Backing(id),
+            (None, None) => TrackFilter::AllTracks,
         }
     }
 
-    pub fn set_solo_track_id(&self, solo_track_id: Option<usize>) {
-        self.solo_track_id.store(
-            solo_track_id.map_or(SOLO_NONE, |id| id as i32),
-            Ordering::Relaxed,
-        );
-    }
-
-    pub fn adjusted_tempo(&self) -> u32 {
-        let tempo = self.tempo.load(Ordering::Relaxed);
-        let pct = self.tempo_percentage.load(Ordering::Relaxed);
-        // clamp to 1 BPM: at tempo 0 the sequencer would never advance again,
-        // freezing playback with no way to reach the next tempo change event
-        ((tempo as f32 * pct as f32 / 100.0) as u32).max(1)
+    pub fn set_track_filter(&self, track_filter: TrackFilter) {
+        let (solo, backing) = match track_filter {
+            TrackFilter::AllTracks => (SOLO_NONE, SOLO_NONE),
+            TrackFilter::Solo(id) => (id as i32, SOLO_NONE),
+            TrackFilter::Backing(id) => (SOLO_NONE, id as i32),
+        };
+        self.solo_track_id.store(solo, Ordering::Relaxed);
+        self.backing_track_id.store(backing, Ordering::Relaxed);
     }
 
-    pub fn set_tempo(&self, tempo: u32) {
-        self.tempo.store(tempo, Ordering::Relaxed);
+    pub fn tempo_percentage(&self) -> u32 {
+        self.tempo_percentage.load(Ordering::Relaxed)
     }
 
     pub fn set_tempo_percentage(&self, tempo_percentage: u32) {
         self.tempo_percentage
             .store(tempo_percentage, Ordering::Relaxed);
     }
+
+    pub fn flush_generation(&self) -> u32 {
+        self.flush_generation.load(Ordering::Acquire)
+    }
+
+    pub fn request_flush(&self) {
+        self.flush_generation.fetch_add(1, Ordering::AcqRel);
+    }
+
+    /// Level of the left & right output, set by the audio callback.
+    pub fn output_levels(&self) -> (f32, f32) {
+        let [left, right] = &self.output_levels;
+        (
+            f32::from_bits(left.load(Ordering::Relaxed)),
+            f32::from_bits(right.load(Ordering::Relaxed)),
+        )
+    }
+
+    /// Frames of the device buffer, 0 before the first callback.
+    pub fn buffer_frames(&self) -> u32 {
+        self.buffer_frames.load(Ordering::Relaxed)
+    }
+
+    pub fn mono(&self) -> bool {
+        self.mono.load(Ordering::Relaxed)
+    }
+
+    pub fn set_mono(&self, mono: bool) {
+        self.mono.store(mono, Ordering::Relaxed);
+    }
+
+    pub fn set_buffer_frames(&self, buffer_frames: u32) {
+        self.buffer_frames.store(buffer_frames, Ordering::Relaxed);
+    }
+
+    pub fn set_output_levels(&self, left: f32, right: f32) {
+        let [left_level, right_level] = &self.output_levels;
+        left_level.store(left.to_bits(), Ordering::Relaxed);
+        right_level.store(right.to_bits(), Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn track_filters() {
+        let params = MidiPlayerParams::new(100, TrackFilter::default());
+        assert_eq!(params.track_filter(), TrackFilter::AllTracks);
+        assert!(TrackFilter::AllTracks.plays(Some(1)));
+
+        params.set_track_filter(TrackFilter::Backing(1));
+        assert_eq!(params.track_filter(), TrackFilter::Backing(1));
+        assert!(!params.track_filter().plays(Some(1)));
+        assert!(params.track_filter().plays(Some(0)));
+
+        // switching to solo unmutes the backing track
+        params.set_track_filter(TrackFilter::Solo(2));
+        assert_eq!(params.track_filter(), TrackFilter::Solo(2));
+        assert!(params.track_filter().plays(Some(2)));
+        assert!(!params.track_filter().plays(Some(1)));
+    }
 }
@@ -1,17 +1,44 @@
 use crate::audio::midi_event::MidiEvent;
-use crate::parser::song_parser::QUARTER_TIME;
-use std::time::Instant;
-
+use crate::audio::tempo_map::TempoMap;
+
+/// Playback clock driven by the number of rendered samples.
+///
+/// The tick position is recomputed from the samples rendered since the last anchor
+/// (seek, tempo percentage or sample rate change) through the tempo map,
+/// so odd callback buffer sizes do not accumulate rounding errors.
+/// Scheduling only depends on the events, the tempo percentage and the samples played,
+/// which makes it usable without an audio device.
 pub struct MidiSequencer {
     last_tick: u32,                // last Midi tick
     tick_position: f64,            // exact tick position; the current tick is its integer part
     needs_init: bool,              // true until the first advance after a reset or seek
-    last_time: Instant,            // last time in milliseconds
+    anchor_tick: f64,              // tick position when the sample counter started
+    anchor_samples: u64,           // samples rendered since the anchor
+    sample_rate: u32,              // output samples per second
+    tempo_percentage: u32,         // playback speed relative to the song tempo
+    tempo_map: TempoMap,           // tempo changes of the events
+    stop_ticks: Vec<u32>,          // playback halts right before these sorted ticks
+    end_tick: Option<u32>,         // playback halts for good right before this tick
+    halted: bool,                  // waiting at a stop tick
+    loop_start: Option<u32>,       // playback wraps there at the end tick instead of halting
+    loop_gap: LoopGap,             // pause before each repetition
+    gap_samples: Option<u64>,      // samples rendered since the gap started, `None` outside it
+    gap_tick: u32,                 // ticks of the gap played
+    gap_span: Option<(u32, u32)>,  // gap ticks covered by the last advance
     sorted_events: Vec<MidiEvent>, // sorted Midi events
+    clicks: Vec<MidiEvent>,        // sorted metronome clicks, played along the events
+}
+
+/// Pause before each repetition of a looped section, at the tempo of its start.
+/// Its events (e.g. count-in clicks) are timed from tick 1 of the pause.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LoopGap {
+    pub ticks: u32,
+    pub events: Vec<MidiEvent>,
 }
 
 impl MidiSequencer {
-    pub fn new(sorted_events: Vec<MidiEvent>) -> Self {
+    pub fn new(sorted_events: Vec<MidiEvent>, initial_tempo: u32, sample_rate: u32) -> Self {
         // events are sorted by tick
         assert!(
             sorted_events
@@ -19,12 +46,26 @@ impl MidiSequencer {
                 .windows(2)
                 .all(|w| w[0].tick <= w[1].tick)
         );
+        let tempo_map = TempoMap::from_events(initial_tempo, &sorted_events);
         Self {
             last_tick: 0,
             tick_position: 0.0,
             needs_init: true,
-            last_time: Instant::now(),
+            anchor_tick: 0.0,
+            anchor_samples: 0,
+            sample_rate,
+            tempo_percentage: 100,
+            tempo_map,
+            stop_ticks: Vec::new(),
+            end_tick: None,
+            halted: false,
+            loop_start: None,
+            loop_gap: LoopGap::default(),
+            gap_samples: None,
+            gap_tick: 0,
+            gap_span: None,
             sorted_events,
+            clicks: Vec::new(),
         }
     }
 
@@ -33,17 +74,103 @@ impl MidiSequencer {
         &self.sorted_events
     }
 
+    /// Channel settings sent before `tick`, replaying them restores the channel state at the tick.
+    pub fn channel_settings_before(&self, tick: u32) -> impl Iterator<Item = &MidiEvent> {
+        let end = self
+            .sorted_events
+            .partition_point(|event| event.tick < tick);
+        self.sorted_events[..end]
+            .iter()
+            .filter(|event| event.is_channel_setting())
+    }
+
     pub fn set_tick(&mut self, tick: u32) {
         // set last_tick before the target so get_next_events includes events at target tick
-        // mark for init so the next advance() bumps by 1 instead of using a stale clock
+        // mark for init so the next advance() bumps by 1 and restarts the sample counter
         let adjusted = tick.saturating_sub(1);
         self.last_tick = adjusted;
         self.tick_position = f64::from(adjusted);
         self.needs_init = true;
+        self.halted = false;
+        self.gap_samples = None;
+    }
+
+    /// Halt playback right before each of the sorted `stop_ticks`, an empty list plays through.
+    pub fn set_stop_ticks(&mut self, stop_ticks: Vec<u32>) {
+        debug_assert!(stop_ticks.is_sorted());
+        self.stop_ticks = stop_ticks;
+        if self.stop_ticks.is_empty() && self.halted {
+            self.resume();
+        }
+    }
+
+    /// Halt playback right before `end_tick`, resuming does not go past it.
+    pub const fn set_end_tick(&mut self, end_tick: Option<u32>) {
+        self.end_tick = end_tick;
+    }
+
+    /// Wrap to `loop_start` after the gap when reaching the end tick, `None` halts there.
+    pub fn set_loop(&mut self, loop_start: Option<u32>, loop_gap: LoopGap) {
+        debug_assert!(loop_gap.events.is_sorted_by_key(|event| event.tick));
+        self.loop_start = loop_start;
+        self.loop_gap = loop_gap;
+        if loop_start.is_none() {
+            self.gap_samples = None;
+        }
+    }
+
+    /// Metronome clicks played along the events, an empty list is silent.
+    pub fn set_clicks(&mut self, clicks: Vec<MidiEvent>) {
+        debug_assert!(clicks.is_sorted_by_key(|event| event.tick));
+        self.clicks = clicks;
+    }
+
+    /// Clicks reached by the last advance, like `get_next_events`.
+    pub fn click_events(&self) -> &[MidiEvent] {
+        let current_tick = self.get_tick();
+        if self.last_tick >= current_tick {
+            return &[];
+        }
+        let start = self
+            .clicks
+            .partition_point(|event| event.tick <= self.last_tick);
+        let end = self
+            .clicks
+            .partition_point(|event| event.tick <= current_tick);
+        &self.clicks[start..end]
+    }
+
+    /// Whether playback is in the pause before the repetition of the section.
+    pub const fn is_in_gap(&self) -> bool {
+        self.gap_samples.is_some()
+    }
+
+    /// Events of the gap reached by the last advance.
+    pub fn gap_events(&self) -> &[MidiEvent] {
+        let Some((last, current)) = self.gap_span else {
+            return &[];
+        };
+        let events = &self.loop_gap.events;
+        let start = events.partition_point(|event| event.tick <= last);
+        let end = events.partition_point(|event| event.tick <= current);
+        &events[start..end.max(start)]
+    }
+
+    pub const fn is_halted(&self) -> bool {
+        self.halted
     }
 
-    pub fn reset_last_time(&mut self) {
-        self.last_time = Instant::now();
+    /// Whether playback is waiting right before the end tick.
+    pub fn is_at_end(&self) -> bool {
+        self.halted && self.end_tick == Some(self.get_tick() + 1)
+    }
+
+    /// Continue past the stop tick playback is waiting at, unless it is the end tick.
+    pub fn resume(&mut self) {
+        if self.halted && !self.is_at_end() {
+            // the stop tick is the one right after the halted position
+            self.set_tick(self.get_tick() + 1);
+        }
     }
 
     pub fn reset_ticks(&mut self) {
@@ -81,29 +208,94 @@ impl MidiSequencer {
         Some(&self.sorted_events[start_index..start_index + len])
     }
 
-    pub fn advance(&mut self, tempo: u32) {
+    pub const fn set_sample_rate(&mut self, sample_rate: u32) {
+        if sample_rate != self.sample_rate {
+            self.anchor();
+            self.sample_rate = sample_rate;
+        }
+    }
+
+    pub const fn set_tempo_percentage(&mut self, tempo_percentage: u32) {
+        if tempo_percentage != self.tempo_percentage {
+            self.anchor();
+            self.tempo_percentage = tempo_percentage;
+        }
+    }
+
+    /// Move the clock forward by the `samples` rendered since the previous advance.
+    pub fn advance(&mut self, samples: u32) {
         // init sequencer if first advance after a reset or seek
         if self.needs_init {
             self.needs_init = false;
             // tick_position is integral here (set from a u32), so the bump is exact
             self.tick_position += 1.0;
-            self.last_time = Instant::now();
+            self.anchor();
             return;
         }
-        // check how many ticks have passed since last advance
-        let now = Instant::now();
-        let elapsed = now.duration_since(self.last_time);
-        self.last_time = now;
-        self.advance_by(tempo, elapsed.as_secs_f64());
-    }
-
-    fn advance_by(&mut self, tempo: u32, elapsed_secs: f64) {
-        // cap the elapsed time so a clock jump (system suspend, stalled stream)
-        // cannot teleport playback far ahead; audio callbacks run every ~0.1s
-        const MAX_ELAPSED_SECS: f64 = 0.5;
-        let elapsed_secs = elapsed_secs.min(MAX_ELAPSED_SECS);
         self.last_tick = self.get_tick();
-        self.tick_position += tick_increase(tempo, elapsed_secs);
+        self.gap_span = None;
+        if let Some(gap_samples) = self.gap_samples {
+            self.advance_gap(gap_samples + u64::from(samples));
+            return;
+        }
+        if self.halted {
+            return;
+        }
+        self.anchor_samples += u64::from(samples);
+        self.tick_position = self.tick_after_samples(self.anchor_tick, self.anchor_samples);
+
+        // stop right before the next stop tick, its events play on resume
+        let next_stop = self.stop_ticks.get(
+            self.stop_ticks
+                .partition_point(|tick| *tick <= self.last_tick),
+        );
+        let end_tick = self.end_tick.filter(|tick| *tick > self.last_tick);
+        let next_stop = next_stop.copied().into_iter().chain(end_tick).min();
+        if let Some(stop_tick) = next_stop
+            && self.tick_position >= f64::from(stop_tick)
+        {
+            self.tick_position = f64::from(stop_tick - 1);
+            if self.loop_start.is_some() && end_tick == Some(stop_tick) {
+                self.gap_samples = Some(0);
+                self.gap_tick = 0;
+                self.advance_gap(0);
+            } else {
+                self.halted = true;
+            }
+        }
+    }
+
+    /// Move through the gap before the repetition, wrapping once it is over.
+    fn advance_gap(&mut self, gap_samples: u64) {
+        let Some(loop_start) = self.loop_start else {
+            self.gap_samples = None;
+            return;
+        };
+        let start = f64::from(loop_start);
+        let position = (self.tick_after_samples(start, gap_samples) - start) as u32;
+        self.gap_span = Some((self.gap_tick, position));
+        self.gap_tick = position;
+        if position >= self.loop_gap.ticks {
+            self.set_tick(loop_start);
+        } else {
+            self.gap_samples = Some(gap_samples);
+        }
+    }
+
+    /// Restart the sample counter from the current position.
+    const fn anchor(&mut self) {
+        self.anchor_tick = self.tick_position;
+        self.anchor_samples = 0;
+    }
+
+    /// Tick position reached after playing `samples` from `start_tick`, following the tempo map.
+    fn tick_after_samples(&self, start_tick: f64, samples: u64) -> f64 {
+        if self.sample_rate == 0 {
+            return start_tick;
+        }
+        let seconds = samples as f64 / f64::from(self.sample_rate);
+        self.tempo_map
+            .tick_after_seconds(start_tick, seconds, self.tempo_percentage)
     }
 
     #[cfg(test)]
@@ -111,98 +303,422 @@ impl MidiSequencer {
         self.needs_init = false;
         self.last_tick = self.get_tick();
         self.tick_position += f64::from(tick);
+        self.anchor();
     }
 }
 
-fn tick_increase(tempo_bpm: u32, elapsed_seconds: f64) -> f64 {
-    let tempo_bps = f64::from(tempo_bpm) / 60.0;
-    f64::from(QUARTER_TIME) * tempo_bps * elapsed_seconds
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::audio::midi_builder::MidiBuilder;
     use crate::audio::midi_event::MidiEventType;
+    use crate::parser::song_parser::QUARTER_TIME;
     use crate::parser::song_parser_tests::parse_gp_file;
-    use std::rc::Rc;
-    use std::time::Duration;
+    use std::sync::Arc;
 
-    #[test]
-    fn test_tick_increase() {
-        let tempo = 100;
-        let elapsed = Duration::from_millis(32);
-        let result = tick_increase(tempo, elapsed.as_secs_f64());
-        assert!((result - 51.2).abs() < 1e-9);
-    }
+    const SAMPLE_RATE: u32 = 44100;
 
-    #[test]
-    fn test_tick_increase_bis() {
-        let tempo = 120;
-        let elapsed = Duration::from_millis(100);
-        let result = tick_increase(tempo, elapsed.as_secs_f64());
-        assert!((result - 192.0).abs() < 1e-9);
+    fn tempo_change(tick: u32, tempo: u32) -> MidiEvent {
+        MidiEvent {
+            tick,
+            event: MidiEventType::TempoChange(tempo),
+            track: None,
+        }
     }
 
     #[test]
     fn fractional_ticks_accumulate_across_advances() {
-        let mut sequencer = MidiSequencer::new(vec![]);
+        let mut sequencer = MidiSequencer::new(vec![], 120, SAMPLE_RATE);
         // first advance after reset bumps to tick 1
-        sequencer.advance(120);
+        sequencer.advance(0);
         assert_eq!(sequencer.get_tick(), 1);
 
-        // simulate 1000 audio callbacks of 5.8 ms each at 120 BPM
-        // (256 frames at 44.1 kHz), i.e. 11.136 ticks per callback
+        // 1000 audio callbacks of 256 frames at 120 BPM, i.e. ~11.146 ticks per callback
         for _ in 0..1000 {
-            sequencer.advance_by(120, 0.0058);
+            sequencer.advance(256);
         }
 
-        // exact total: 1 + 11.136 * 1000 = 11137 ticks
-        // truncating per callback would yield 11001 (~1.2% slow)
-        let expected = 1 + 11_136;
-        assert!((i64::from(sequencer.get_tick()) - expected).abs() <= 1);
+        // exact total: 1 + 1920 * 256_000 / 44100 = 11146.58
+        // truncating per callback would yield 11001 (~1.3% slow)
+        assert_eq!(sequencer.get_tick(), 11_146);
     }
 
     #[test]
     fn sub_tick_advances_do_not_retrigger_init() {
-        let mut sequencer = MidiSequencer::new(vec![]);
-        sequencer.advance(120);
+        let mut sequencer = MidiSequencer::new(vec![], 120, SAMPLE_RATE);
+        sequencer.advance(0);
         assert_eq!(sequencer.get_tick(), 1);
 
-        // 0.4 ms at 120 BPM is 0.768 ticks: no whole tick passes,
+        // 16 frames at 120 BPM is 0.697 ticks: no whole tick passes,
         // so current_tick stalls at 1 with last_tick == current_tick
-        sequencer.advance_by(120, 0.0004);
+        sequencer.advance(16);
         assert_eq!(sequencer.get_tick(), 1);
         assert_eq!(sequencer.get_last_tick(), 1);
 
         // the next sub-tick advance must accumulate to a whole tick,
         // not fall back into the init path (which would reset the position)
-        sequencer.advance_by(120, 0.0004);
+        sequencer.advance(16);
         assert_eq!(sequencer.get_tick(), 2);
         assert_eq!(sequencer.get_last_tick(), 1);
     }
+
+    #[test]
+    fn odd_buffer_sizes_do_not_drift() {
+        let mut sequencer = MidiSequencer::new(vec![], 120, SAMPLE_RATE);
+        sequencer.advance(0);
+
+        // ten minutes of audio rendered through irregular buffer sizes
+        let total_frames = u64::from(SAMPLE_RATE) * 600;
+        let sizes: [u32; 6] = [1, 37, 441, 1013, 127, 4409];
+        let mut rendered = 0;
+        for size in sizes.iter().cycle() {
+            let size = u64::from(*size).min(total_frames - rendered);
+            if size == 0 {
+                break;
+            }
+            sequencer.advance(size as u32);
+            rendered += size;
+        }
+
+        // 120 BPM for 600s is 1200 quarters
+        let expected = 1.0 + 1200.0 * f64::from(QUARTER_TIME);
+        assert!((sequencer.tick_position - expected).abs() < 1e-6);
+        assert_eq!(sequencer.get_tick(), 1 + 1200 * QUARTER_TIME);
+    }
+
+    #[test]
+    fn tempo_map_changes_speed_at_the_exact_tick() {
+        // one second at 120 BPM from tick 1, then 60 BPM
+        let events = vec![tempo_change(1921, 60)];
+        let mut sequencer = MidiSequencer::new(events, 120, SAMPLE_RATE);
+        sequencer.advance(0);
+
+        // two seconds through odd buffers straddling the change
+        let mut remaining = 2 * SAMPLE_RATE;
+        while remaining > 0 {
+            let size = remaining.min(1013);
+            sequencer.advance(size);
+            remaining -= size;
+        }
+        // the second second runs at 960 ticks per second
+        assert!((sequencer.tick_position - 2881.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn seek_uses_tempo_at_target() {
+        let events = vec![tempo_change(1000, 60)];
+        let mut sequencer = MidiSequencer::new(events, 120, SAMPLE_RATE);
+        sequencer.set_tick(2001);
+        sequencer.advance(0);
+        assert_eq!(sequencer.get_tick(), 2001);
+        sequencer.advance(SAMPLE_RATE);
+        assert_eq!(sequencer.get_tick(), 2001 + 960);
+    }
+
+    #[test]
+    fn tempo_percentage_change_keeps_position() {
+        let mut sequencer = MidiSequencer::new(vec![], 120, SAMPLE_RATE);
+        sequencer.advance(0);
+        sequencer.advance(SAMPLE_RATE);
+        assert_eq!(sequencer.get_tick(), 1921);
+
+        // slowing down only affects the samples rendered afterwards
+        sequencer.set_tempo_percentage(50);
+        sequencer.advance(SAMPLE_RATE);
+        assert_eq!(sequencer.get_tick(), 1921 + 960);
+
+        // a different output sample rate is accounted for as well
+        sequencer.set_sample_rate(48000);
+        sequencer.advance(48000);
+        assert_eq!(sequencer.get_tick(), 1921 + 960 + 960);
+    }
+
+    #[test]
+    fn channel_settings_before_seek_target() {
+        let program = |tick, program| MidiEvent::new_midi_message(tick, 0, 0, 0xC0, program, 0);
+        let volume = |tick, value| MidiEvent::new_midi_message(tick, 0, 0, 0xB0, 0x07, value);
+        let events = vec![
+            program(1, 25),
+            volume(1, 100),
+            MidiEvent::new_midi_message(500, 0, 0, 0xE0, 0, 64),
+            program(1000, 27),
+            volume(2000, 50),
+        ];
+        let sequencer = MidiSequencer::new(events.clone(), 120, SAMPLE_RATE);
+        // pitch bends are not settings, changes from the target tick are played anyway
+        let settings: Vec<_> = sequencer.channel_settings_before(2000).cloned().collect();
+        assert_eq!(
+            settings,
+            vec![program(1, 25), volume(1, 100), program(1000, 27)]
+        );
+        assert_eq!(sequencer.channel_settings_before(1).count(), 0);
+    }
+
+    #[test]
+    fn halts_before_stop_ticks_until_resumed() {
+        let note = |tick| MidiEvent {
+            tick,
+            event: MidiEventType::NoteOn(0, 60, 95),
+            track: Some(0),
+        };
+        let events = vec![note(1), note(1000), note(1920), note(3840)];
+        let mut sequencer = MidiSequencer::new(events.clone(), 120, SAMPLE_RATE);
+        sequencer.set_stop_ticks(vec![1920, 3840]);
+        sequencer.advance(0);
+        assert_eq!(sequencer.get_next_events().unwrap(), &events[0..1]);
+
+        // two seconds would reach tick 3841, playback stops right before 1920
+        sequencer.advance(2 * SAMPLE_RATE);
+        assert!(sequencer.is_halted());
+        assert_eq!(sequencer.get_tick(), 1919);
+        assert_eq!(sequencer.get_next_events().unwrap(), &events[1..2]);
+
+        // waiting does not move
+        sequencer.advance(SAMPLE_RATE);
+        assert_eq!(sequencer.get_tick(), 1919);
+        assert_eq!(sequencer.get_next_events().unwrap(), &[]);
+
+        // the events at the stop tick play on resume
+        sequencer.resume();
+        assert!(!sequencer.is_halted());
+        sequencer.advance(0);
+        assert_eq!(sequencer.get_tick(), 1920);
+        assert_eq!(sequencer.get_next_events().unwrap(), &events[2..3]);
+        sequencer.advance(2 * SAMPLE_RATE);
+        assert_eq!(sequencer.get_tick(), 3839);
+
+        // disabling the stops releases a waiting sequencer
+        sequencer.set_stop_ticks(vec![]);
+        sequencer.advance(0);
+        assert_eq!(sequencer.get_tick(), 3840);
+        assert_eq!(sequencer.get_next_events().unwrap(), &events[3..4]);
+    }
+
+    #[test]
+    fn halts_at_the_end_tick_for_good() {
+        let mut sequencer = MidiSequencer::new(vec![], 120, SAMPLE_RATE);
+        sequencer.set_stop_ticks(vec![960]);
+        sequencer.set_end_tick(Some(1920));
+        sequencer.advance(0);
+        sequencer.advance(SAMPLE_RATE);
+        // a practice stop is not the end
+        assert_eq!(sequencer.get_tick(), 959);
+        assert!(!sequencer.is_at_end());
+        sequencer.resume();
+        sequencer.advance(0);
+        sequencer.advance(2 * SAMPLE_RATE);
+        assert_eq!(sequencer.get_tick(), 1919);
+        assert!(sequencer.is_at_end());
+
+        // resuming does not go past the end
+        sequencer.resume();
+        sequencer.advance(SAMPLE_RATE);
+        assert_eq!(sequencer.get_tick(), 1919);
+
+        // without an end the playback goes on
+        sequencer.set_end_tick(None);
+        sequencer.set_tick(1000);
+        sequencer.advance(0);
+        sequencer.advance(SAMPLE_RATE);
+        assert!(!sequencer.is_halted());
+        assert_eq!(sequencer.get_tick(), 1000 + 1920);
+    }
+
+    #[test]
+    fn loops_after_the_gap() {
+        let event = |tick, key| MidiEvent {
+            tick,
+            event: MidiEventType::NoteOn(9, key, 95),
+            track: None,
+        };
+        let events = vec![event(1000, 60), event(1920, 60)];
+        let gap = LoopGap {
+            ticks: 960,
+            events: vec![event(1, 76), event(481, 77)],
+        };
+        let mut sequencer = MidiSequencer::new(events.clone(), 120, SAMPLE_RATE);
+        sequencer.set_end_tick(Some(1920));
+        sequencer.set_loop(Some(1000), gap.clone());
+        sequencer.set_tick(1000);
+        sequencer.advance(0);
+        assert_eq!(sequencer.get_next_events().unwrap(), &events[..1]);
+
+        // the end is not played, the gap starts in silence
+        sequencer.advance(SAMPLE_RATE);
+        assert!(sequencer.is_in_gap());
+        assert!(!sequencer.is_halted());
+        assert_eq!(sequencer.get_tick(), 1919);
+        assert_eq!(sequencer.gap_events(), &[]);
+
+        // the clicks of the gap at the tempo of the section start
+        sequencer.advance(SAMPLE_RATE / 4);
+        assert_eq!(sequencer.get_tick(), 1919);
+        assert_eq!(sequencer.gap_events(), &gap.events[..1]);
+        sequencer.advance(SAMPLE_RATE / 4);
+        assert_eq!(sequencer.gap_events(), &gap.events[1..]);
+
+        // then the section again
+        assert!(!sequencer.is_in_gap());
+        sequencer.advance(0);
+        assert_eq!(sequencer.get_tick(), 1000);
+        assert_eq!(sequencer.get_next_events().unwrap(), &events[..1]);
+
+        // without a gap the section starts again right away
+        sequencer.set_loop(Some(1000), LoopGap::default());
+        sequencer.advance(SAMPLE_RATE);
+        assert!(!sequencer.is_in_gap());
+        sequencer.advance(0);
+        assert_eq!(sequencer.get_tick(), 1000);
+    }
+
     #[test]
-    fn clock_jumps_are_clamped() {
-        let mut sequencer = MidiSequencer::new(vec![]);
-        sequencer.advance(120); // init: tick 1
+    fn seeking_clears_halt() {
+        let mut sequencer = MidiSequencer::new(vec![], 120, SAMPLE_RATE);
+        sequencer.set_stop_ticks(vec![960, 1920]);
+        sequencer.advance(0);
+        sequencer.advance(SAMPLE_RATE);
+        assert!(sequencer.is_halted());
+        assert_eq!(sequencer.get_tick(), 959);
+
+        // after a seek the next stop is the one following the target
+        sequencer.set_tick(1000);
+        assert!(!sequencer.is_halted());
+        sequencer.advance(0);
+        sequencer.advance(SAMPLE_RATE);
+        assert!(sequencer.is_halted());
+        assert_eq!(sequencer.get_tick(), 1919);
+    }
 
-        // an hour-long clock jump (e.g. system suspend) advances playback
-        // by at most 0.5s of ticks: 120 BPM = 2 quarters/s * 960 * 0.5 = 960
-        sequencer.advance_by(120, 3600.0);
+    #[test]
+    fn stop_then_play_restarts_from_the_first_tick() {
+        let note = |tick| MidiEvent {
+            tick,
+            event: MidiEventType::NoteOn(0, 60, 95),
+            track: Some(0),
+        };
+        let events = vec![note(1), note(961), note(1921)];
+        let mut sequencer = MidiSequencer::new(events.clone(), 120, SAMPLE_RATE);
+        sequencer.advance(0);
+        sequencer.advance(SAMPLE_RATE / 2);
         assert_eq!(sequencer.get_tick(), 961);
+
+        // stop
+        sequencer.reset_ticks();
+        sequencer.advance(0);
+        assert_eq!(sequencer.get_tick(), 1);
+        assert_eq!(sequencer.get_next_events().unwrap(), &events[..1]);
+
+        // pause is a seek back to the displayed tick, the beat plays again on resume
+        sequencer.advance(SAMPLE_RATE / 2);
+        sequencer.set_tick(961);
+        sequencer.advance(0);
+        assert_eq!(sequencer.get_next_events().unwrap(), &events[1..2]);
+    }
+
+    #[test]
+    fn zero_tempo_still_advances() {
+        let mut sequencer = MidiSequencer::new(vec![], 0, SAMPLE_RATE);
+        sequencer.advance(0);
+        // clamped to 1 BPM: 16 ticks per second
+        sequencer.advance(SAMPLE_RATE);
+        assert_eq!(sequencer.get_tick(), 17);
+    }
+
+    /// Play the sequencer headless until the end, returning each event with
+    /// the number of samples rendered before it was delivered.
+    fn play_headless(sequencer: &mut MidiSequencer, buffer: u32) -> Vec<(u64, MidiEvent)> {
+        let mut delivered = Vec::new();
+        let mut samples = 0;
+        sequencer.advance(0);
+        while let Some(events) = sequencer.get_next_events() {
+            delivered.extend(events.iter().map(|event| (samples, event.clone())));
+            sequencer.advance(buffer);
+            samples += u64::from(buffer);
+        }
+        delivered
+    }
+
+    #[test]
+    fn headless_playback_with_repeats_delivers_events_once() {
+        const FILE_PATH: &str = "test-files/John Petrucci - Damage Control (ver 6 by Feio666).gp5";
+        let song = Arc::new(parse_gp_file(FILE_PATH).unwrap());
+        let playback_order =
+            crate::audio::playback_order::compute_playback_order(&song.measure_headers);
+        let events = MidiBuilder::new().build_for_song_with_order(&song, &playback_order);
+        let mut sequencer = MidiSequencer::new(events.clone(), song.tempo.value, SAMPLE_RATE);
+        let delivered = play_headless(&mut sequencer, 1013);
+        let delivered: Vec<MidiEvent> = delivered.into_iter().map(|(_, event)| event).collect();
+        assert_eq!(delivered, events);
+    }
+
+    #[test]
+    fn headless_playback_is_deterministic() {
+        const FILE_PATH: &str = "test-files/Demo v5.gp5";
+        let song = parse_gp_file(FILE_PATH).unwrap();
+        let events = MidiBuilder::new().build_for_song(&Arc::new(song));
+        let play = || {
+            let mut sequencer = MidiSequencer::new(events.clone(), 120, SAMPLE_RATE);
+            sequencer.set_tempo_percentage(75);
+            play_headless(&mut sequencer, 441)
+        };
+        assert_eq!(play(), play());
+    }
+
+    #[test]
+    fn headless_playback_follows_tempo_changes() {
+        let note = |tick| MidiEvent {
+            tick,
+            event: MidiEventType::NoteOn(0, 60, 95),
+            track: Some(0),
+        };
+        // one second at 120 BPM, then one second at 60 BPM
+        let events = vec![note(1), tempo_change(1921, 60), note(1921), note(2881)];
+        let buffer = 441; // 10ms
+        for (percentage, seconds) in [(100, 2), (50, 4), (200, 1)] {
+            let mut sequencer = MidiSequencer::new(events.clone(), 120, SAMPLE_RATE);
+            sequencer.set_tempo_percentage(percentage);
+            let delivered = play_headless(&mut sequencer, buffer);
+            let (samples, last) = delivered.last().unwrap();
+            assert_eq!(last.tick, 2881);
+            // delivered by the first buffer starting after the note is due
+            let due = u64::from(SAMPLE_RATE) * seconds;
+            assert!(
+                (due..due + u64::from(buffer)).contains(samples),
+                "{percentage}%: delivered at {samples} samples, due at {due}"
+            );
+        }
+    }
+
+    #[test]
+    fn headless_seek_resumes_at_target() {
+        let note = |tick| MidiEvent {
+            tick,
+            event: MidiEventType::NoteOn(0, 60, 95),
+            track: Some(0),
+        };
+        let events = vec![note(1), tempo_change(961, 60), note(961), note(1921)];
+        let mut sequencer = MidiSequencer::new(events, 120, SAMPLE_RATE);
+        sequencer.set_tick(961);
+        let delivered = play_headless(&mut sequencer, 441);
+        let ticks: Vec<(u64, u32)> = delivered.iter().map(|(s, e)| (*s, e.tick)).collect();
+        // the seek target is delivered right away, the next note one second later at 60 BPM
+        assert_eq!(&ticks[..2], &[(0, 961), (0, 961)]);
+        let (samples, tick) = ticks[2];
+        assert_eq!(tick, 1921);
+        assert!((44100..44100 + 441).contains(&samples));
     }
 
     #[test]
     fn test_sequence_demo_song() {
         const FILE_PATH: &str = "test-files/Demo v5.gp5";
         let song = parse_gp_file(FILE_PATH).unwrap();
-        let song = Rc::new(song);
+        let song = Arc::new(song);
         let builder = MidiBuilder::new();
         let events = builder.build_for_song(&song);
-        let events_len = 4682;
+        let events_len = 6342;
         assert_eq!(events.len(), events_len);
         assert_eq!(events[0].tick, 1);
-        let mut sequencer = MidiSequencer::new(events.clone());
+        let mut sequencer = MidiSequencer::new(events.clone(), song.tempo.value, SAMPLE_RATE);
 
         // last_tick:0 current_tick:0
         let batch = sequencer.get_next_events().unwrap();
@@ -255,7 +771,7 @@ mod tests {
             event(20, 60),
             event(20, 64),
         ];
-        let mut sequencer = MidiSequencer::new(events.clone());
+        let mut sequencer = MidiSequencer::new(events.clone(), 120, SAMPLE_RATE);
 
         // first batch ends exactly on the chord tick: all three notes included
         sequencer.advance_tick(10); // last:0 current:10
@@ -292,12 +808,12 @@ mod tests {
                 track: Some(0),
             },
         ];
-        let mut sequencer = MidiSequencer::new(events);
+        let mut sequencer = MidiSequencer::new(events, 120, SAMPLE_RATE);
 
         // seek to tick 200 — set_tick sets last_tick and tick_position to 199
         sequencer.set_tick(200);
         // first advance takes the init path: last_tick stays 199, current tick becomes 200
-        sequencer.advance(120);
+        sequencer.advance(0);
         let batch = sequencer.get_next_events().unwrap();
 
         // should include the event at tick 200
@@ -326,10 +842,10 @@ mod tests {
             &playback_order,
         );
 
-        let song = Rc::new(song);
+        let song = Arc::new(song);
         let builder = MidiBuilder::new();
         let events = builder.build_for_song(&song);
-        let mut sequencer = MidiSequencer::new(events.clone());
+        let mut sequencer = MidiSequencer::new(events.clone(), song.tempo.value, SAMPLE_RATE);
 
         // seek to measure 5 (index 4)
         let target_measure = 4;
@@ -340,7 +856,7 @@ mod tests {
         );
 
         sequencer.set_tick(target_tick);
-        sequencer.advance(120);
+        sequencer.advance(0);
         let batch = sequencer.get_next_events().unwrap();
 
         // verify we get events at or near the target tick, not from earlier measures
@@ -1,6 +1,12 @@
+pub mod metronome;
 pub mod midi_builder;
 pub mod midi_event;
+pub mod midi_export;
+pub mod midi_input;
 pub mod midi_player;
 mod midi_player_params;
 pub mod midi_sequencer;
 pub mod playback_order;
+mod render_ahead;
+pub mod synth_rack;
+pub mod tempo_map;
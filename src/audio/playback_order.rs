@@ -1,4 +1,4 @@
-use crate::parser::song_parser::{MeasureHeader, QUARTER_TIME};
+use crate::parser::song_parser::{DirectionSign, MeasureHeader, QUARTER_TIME};
 
 /// Compute the playback order of measures, expanding repeats and alternative endings.
 ///
@@ -16,6 +16,10 @@ use crate::parser::song_parser::{MeasureHeader, QUARTER_TIME};
 /// - a repeat_close on a skipped measure only clears the latch, it never jumps
 /// - a repeat_close inside an alternative ending always jumps back; the section
 ///   ends by falling through an ending without a repeat_close
+///
+/// Directions (D.C., D.S., coda, fine) are jumps taken once at the end of their
+/// measure, on its last pass. After a jump repeats are not taken again: each
+/// section plays its last pass only, so alternative endings play the last one.
 pub fn compute_playback_order(headers: &[MeasureHeader]) -> Vec<(usize, i64)> {
     let mut order: Vec<(usize, i64)> = Vec::new();
     // i64: keeps the accumulator itself from overflowing on absurd repeat
@@ -28,6 +32,8 @@ pub fn compute_playback_order(headers: &[MeasureHeader]) -> Vec<(usize, i64)> {
     let mut repeat_open = true; // first measure implicitly opens a repeat
     let mut repeat_number: i8 = 0; // 0-based repetition counter
     let mut repeat_alternative: u8 = 0; // latched alternative ending bitmask
+    let mut jump_mode: Option<JumpMode> = None; // set once a D.C. or D.S. was taken
+    let mut jumps_taken: Vec<usize> = Vec::new(); // measures whose jump was taken
 
     while index < headers.len() {
         let header = &headers[index];
@@ -36,8 +42,12 @@ pub fn compute_playback_order(headers: &[MeasureHeader]) -> Vec<(usize, i64)> {
         if header.repeat_open {
             repeat_start_index = index;
             repeat_open = true;
-            // reset counters only on the first pass over this measure
-            if index as i64 > last_played {
+            if jump_mode.is_some() {
+                // after a jump, only the last pass of the section plays
+                repeat_number = section_repeat_count(headers, index);
+                repeat_alternative = 0;
+            } else if index as i64 > last_played {
+                // reset counters only on the first pass over this measure
                 repeat_number = 0;
                 repeat_alternative = 0;
             }
@@ -67,7 +77,9 @@ pub fn compute_playback_order(headers: &[MeasureHeader]) -> Vec<(usize, i64)> {
             running_tick += i64::from(header.length());
 
             if repeat_open && header.repeat_close > 0 {
-                if repeat_number < header.repeat_close || repeat_alternative > 0 {
+                if jump_mode.is_none()
+                    && (repeat_number < header.repeat_close || repeat_alternative > 0)
+                {
                     repeat_number += 1;
                     repeat_alternative = 0;
                     index = repeat_start_index;
@@ -78,6 +90,44 @@ pub fn compute_playback_order(headers: &[MeasureHeader]) -> Vec<(usize, i64)> {
                 repeat_number = 0;
                 repeat_alternative = 0;
             }
+
+            let target = match jump_mode {
+                Some(JumpMode::AlFine) if header.directions.contains(&DirectionSign::Fine) => {
+                    break;
+                }
+                Some(mode @ (JumpMode::AlCoda | JumpMode::AlDoubleCoda)) => {
+                    let (to_coda, coda) = if mode == JumpMode::AlCoda {
+                        (DirectionSign::DaCoda, DirectionSign::Coda)
+                    } else {
+                        (DirectionSign::DaDoubleCoda, DirectionSign::DoubleCoda)
+                    };
+                    header
+                        .directions
+                        .contains(&to_coda)
+                        .then(|| find_target(headers, coda))
+                        .flatten()
+                        .inspect(|_| jump_mode = Some(JumpMode::ToEnd))
+                }
+                _ => None,
+            };
+            let target = target.or_else(|| {
+                if jumps_taken.contains(&index) {
+                    return None;
+                }
+                let (target, mode) = header.directions.iter().find_map(|sign| jump_of(*sign))?;
+                let target = target.map_or(Some(0), |sign| find_target(headers, sign))?;
+                jumps_taken.push(index);
+                jump_mode = Some(mode);
+                Some(target)
+            });
+            if let Some(target) = target {
+                index = target;
+                repeat_start_index = target;
+                repeat_open = true;
+                repeat_number = section_repeat_count(headers, target);
+                repeat_alternative = 0;
+                continue;
+            }
         }
         index += 1;
     }
@@ -85,6 +135,63 @@ pub fn compute_playback_order(headers: &[MeasureHeader]) -> Vec<(usize, i64)> {
     order
 }
 
+/// What to do after a D.C. or D.S. jump.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum JumpMode {
+    ToEnd,
+    AlCoda,
+    AlDoubleCoda,
+    AlFine,
+}
+
+/// Target sign (`None` for the start of the song) and mode of a jump sign.
+const fn jump_of(sign: DirectionSign) -> Option<(Option<DirectionSign>, JumpMode)> {
+    use DirectionSign::{Segno, SegnoSegno};
+    let jump = match sign {
+        DirectionSign::DaCapo => (None, JumpMode::ToEnd),
+        DirectionSign::DaCapoAlCoda => (None, JumpMode::AlCoda),
+        DirectionSign::DaCapoAlDoubleCoda => (None, JumpMode::AlDoubleCoda),
+        DirectionSign::DaCapoAlFine => (None, JumpMode::AlFine),
+        DirectionSign::DaSegno => (Some(Segno), JumpMode::ToEnd),
+        DirectionSign::DaSegnoAlCoda => (Some(Segno), JumpMode::AlCoda),
+        DirectionSign::DaSegnoAlDoubleCoda => (Some(Segno), JumpMode::AlDoubleCoda),
+        DirectionSign::DaSegnoAlFine => (Some(Segno), JumpMode::AlFine),
+        DirectionSign::DaSegnoSegno => (Some(SegnoSegno), JumpMode::ToEnd),
+        DirectionSign::DaSegnoSegnoAlCoda => (Some(SegnoSegno), JumpMode::AlCoda),
+        DirectionSign::DaSegnoSegnoAlDoubleCoda => (Some(SegnoSegno), JumpMode::AlDoubleCoda),
+        DirectionSign::DaSegnoSegnoAlFine => (Some(SegnoSegno), JumpMode::AlFine),
+        // targets, and "to coda" which is only followed after a jump
+        _ => return None,
+    };
+    Some(jump)
+}
+
+/// Measure carrying the target sign.
+fn find_target(headers: &[MeasureHeader], sign: DirectionSign) -> Option<usize> {
+    let target = headers.iter().position(|h| h.directions.contains(&sign));
+    if target.is_none() {
+        log::warn!("Ignoring jump to missing {sign:?}");
+    }
+    target
+}
+
+/// Repetitions of the section starting at `start`, so that its last pass can be played directly.
+fn section_repeat_count(headers: &[MeasureHeader], start: usize) -> i8 {
+    headers[start..]
+        .iter()
+        .enumerate()
+        .take_while(|(offset, header)| *offset == 0 || !header.repeat_open)
+        .map(|(_, header)| header.repeat_close)
+        .max()
+        .unwrap_or(0)
+        .max(0)
+}
+
+/// Each measure once in notated order, ignoring repeats and directions.
+pub fn notated_order(headers: &[MeasureHeader]) -> Vec<(usize, i64)> {
+    (0..headers.len()).map(|index| (index, 0)).collect()
+}
+
 /// Translate a tick from the original timeline into the expanded playback timeline.
 pub fn playback_tick(original_tick: u32, tick_offset: i64) -> u32 {
     (i64::from(original_tick) + tick_offset) as u32
@@ -116,6 +223,52 @@ pub fn first_playback_ticks(headers: &[MeasureHeader], order: &[(usize, i64)]) -
         .collect()
 }
 
+/// Measure index and playback start tick of each entry of the playback order.
+pub fn playback_starts(headers: &[MeasureHeader], order: &[(usize, i64)]) -> Vec<(usize, u32)> {
+    order
+        .iter()
+        .map(|&(measure_index, tick_offset)| {
+            (
+                measure_index,
+                playback_tick(headers[measure_index].start, tick_offset),
+            )
+        })
+        .collect()
+}
+
+/// Playback tick `pre_roll` measures before the first playback of the measure,
+/// counted in playback order so the lead-in is what is heard before it.
+/// Returns `None` if the measure is never played.
+pub fn pre_roll_tick(
+    starts: &[(usize, u32)],
+    measure_index: usize,
+    pre_roll: usize,
+) -> Option<u32> {
+    let position = starts
+        .iter()
+        .position(|(index, _)| *index == measure_index)?;
+    Some(starts[position.saturating_sub(pre_roll)].1)
+}
+
+/// Playback ticks bounding a section, from the first playback of `first_measure`
+/// to the end of the following playback of `last_measure`.
+/// The end is `None` if the section runs to the end of the song.
+/// Returns `None` if the measures are never played in that order.
+pub fn section_ticks(
+    starts: &[(usize, u32)],
+    first_measure: usize,
+    last_measure: usize,
+) -> Option<(u32, Option<u32>)> {
+    let first = starts
+        .iter()
+        .position(|(index, _)| *index == first_measure)?;
+    let last = first
+        + starts[first..]
+            .iter()
+            .position(|(index, _)| *index == last_measure)?;
+    Some((starts[first].1, starts.get(last + 1).map(|(_, tick)| *tick)))
+}
+
 /// Bit for the given repetition in an alternative ending bitmask.
 /// Repetitions beyond the 8th never match.
 const fn repetition_bit(repetition: i8) -> u8 {
@@ -129,6 +282,8 @@ const fn repetition_bit(repetition: i8) -> u8 {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::parser::song_parser::TimeSignature;
+    use crate::parser::song_parser_tests::TestRng;
 
     fn make_header(start: u32, repeat_open: bool, repeat_close: i8) -> MeasureHeader {
         MeasureHeader {
@@ -139,6 +294,21 @@ mod tests {
         }
     }
 
+    fn with_directions(start: u32, directions: &[DirectionSign]) -> MeasureHeader {
+        MeasureHeader {
+            start,
+            directions: directions.to_vec(),
+            ..MeasureHeader::default()
+        }
+    }
+
+    fn indices(headers: &[MeasureHeader]) -> Vec<usize> {
+        compute_playback_order(headers)
+            .iter()
+            .map(|(i, _)| *i)
+            .collect()
+    }
+
     #[test]
     fn no_repeats() {
         let headers = vec![
@@ -262,6 +432,51 @@ mod tests {
         assert_eq!(first_ticks, vec![960, 4800, 4800, 12480]);
     }
 
+    #[test]
+    fn pre_roll_follows_playback_order() {
+        // |: M0 | M1 :| M2
+        // Plays: M0 M1 M0 M1 M2
+        let measure_len = 3840_u32;
+        let headers = vec![
+            make_header(960, true, 0),
+            make_header(960 + measure_len, false, 1),
+            make_header(960 + measure_len * 2, false, 0),
+        ];
+        let order = compute_playback_order(&headers);
+        let starts = playback_starts(&headers, &order);
+        let ticks: Vec<u32> = starts.iter().map(|(_, tick)| *tick).collect();
+        assert_eq!(ticks, vec![960, 4800, 8640, 12480, 16320]);
+
+        // M2 is preceded by the repeated M1, not by its first playback
+        assert_eq!(pre_roll_tick(&starts, 2, 1), Some(12480));
+        assert_eq!(pre_roll_tick(&starts, 2, 2), Some(8640));
+        assert_eq!(pre_roll_tick(&starts, 2, 0), Some(16320));
+        // clamped to the start of the song
+        assert_eq!(pre_roll_tick(&starts, 1, 4), Some(960));
+        assert_eq!(pre_roll_tick(&starts, 3, 1), None);
+    }
+
+    #[test]
+    fn section_follows_playback_order() {
+        // |: M0 | M1 :| M2
+        // Plays: M0 M1 M0 M1 M2
+        let measure_len = 3840_u32;
+        let headers = vec![
+            make_header(960, true, 0),
+            make_header(960 + measure_len, false, 1),
+            make_header(960 + measure_len * 2, false, 0),
+        ];
+        let order = compute_playback_order(&headers);
+        let starts = playback_starts(&headers, &order);
+
+        // a single measure ends where the next one in playback order starts
+        assert_eq!(section_ticks(&starts, 1, 1), Some((4800, Some(8640))));
+        // the section spans the repeat up to its first playback of the last measure
+        assert_eq!(section_ticks(&starts, 1, 2), Some((4800, None)));
+        assert_eq!(section_ticks(&starts, 0, 1), Some((960, Some(8640))));
+        assert_eq!(section_ticks(&starts, 0, 3), None);
+    }
+
     #[test]
     fn three_alternatives() {
         // |: M0 | M1[1.] :| M2[2.] :| M3[3.] | M4
@@ -353,6 +568,69 @@ mod tests {
         assert!(playback_ticks.windows(2).all(|w| w[0] < w[1]));
     }
 
+    /// Random score of repeats, alternative endings and directions.
+    fn random_headers(rng: &mut TestRng) -> Vec<MeasureHeader> {
+        let signs = [
+            DirectionSign::Coda,
+            DirectionSign::Segno,
+            DirectionSign::Fine,
+            DirectionSign::DaCapo,
+            DirectionSign::DaCapoAlFine,
+            DirectionSign::DaSegnoAlCoda,
+            DirectionSign::DaCoda,
+        ];
+        let mut start = QUARTER_TIME;
+        (0..rng.below(24) + 1)
+            .map(|_| {
+                let header = MeasureHeader {
+                    start,
+                    time_signature: TimeSignature {
+                        numerator: rng.below(7) as u8 + 1,
+                        ..TimeSignature::default()
+                    },
+                    repeat_open: rng.chance(15),
+                    repeat_close: if rng.chance(15) {
+                        rng.below(3) as i8 + 1
+                    } else {
+                        0
+                    },
+                    repeat_alternative: if rng.chance(10) {
+                        rng.below(7) as u8 + 1
+                    } else {
+                        0
+                    },
+                    directions: if rng.chance(10) {
+                        vec![signs[rng.below(signs.len() as u64) as usize]]
+                    } else {
+                        Vec::new()
+                    },
+                    ..MeasureHeader::default()
+                };
+                start += header.length();
+                header
+            })
+            .collect()
+    }
+
+    #[test]
+    fn expansion_keeps_playback_ticks_contiguous() {
+        for seed in 0..1000 {
+            let mut rng = TestRng::new(seed);
+            let headers = random_headers(&mut rng);
+            let order = compute_playback_order(&headers);
+            let starts = playback_starts(&headers, &order);
+            // a score made only of unreachable alternative endings plays nothing
+            if let Some(&(_, first_tick)) = starts.first() {
+                assert!(first_tick >= headers[0].start, "seed {seed}");
+            }
+            // each measure starts where the previous one ends
+            for w in starts.windows(2) {
+                let (measure, tick) = w[0];
+                assert_eq!(tick + headers[measure].length(), w[1].1, "seed {seed}");
+            }
+        }
+    }
+
     #[test]
     fn alternative_on_last_pass_with_close() {
         // |: M0 | M1[1.+2.] :|
@@ -468,4 +746,89 @@ mod tests {
         let indices: Vec<usize> = order.iter().map(|(i, _)| *i).collect();
         assert_eq!(indices, vec![0, 1, 0, 2, 3, 4, 3, 5]);
     }
+
+    #[test]
+    fn da_capo_al_fine() {
+        // M0 | M1 Fine | M2 D.C. al Fine
+        // Plays: M0 M1 M2 M0 M1
+        let measure_len = 3840_u32;
+        let headers = vec![
+            make_header(960, false, 0),
+            with_directions(960 + measure_len, &[DirectionSign::Fine]),
+            with_directions(960 + measure_len * 2, &[DirectionSign::DaCapoAlFine]),
+        ];
+        assert_eq!(indices(&headers), vec![0, 1, 2, 0, 1]);
+    }
+
+    #[test]
+    fn dal_segno_al_coda() {
+        // M0 | M1 Segno | M2 To Coda | M3 D.S. al Coda | M4 Coda
+        // "To Coda" is ignored on the first pass.
+        // Plays: M0 M1 M2 M3 M1 M2 M4
+        let measure_len = 3840_u32;
+        let headers = vec![
+            make_header(960, false, 0),
+            with_directions(960 + measure_len, &[DirectionSign::Segno]),
+            with_directions(960 + measure_len * 2, &[DirectionSign::DaCoda]),
+            with_directions(960 + measure_len * 3, &[DirectionSign::DaSegnoAlCoda]),
+            with_directions(960 + measure_len * 4, &[DirectionSign::Coda]),
+        ];
+        let order = compute_playback_order(&headers);
+        assert_eq!(indices(&headers), vec![0, 1, 2, 3, 1, 2, 4]);
+        let playback_ticks: Vec<i64> = order
+            .iter()
+            .map(|(idx, offset)| i64::from(headers[*idx].start) + offset)
+            .collect();
+        assert!(playback_ticks.windows(2).all(|w| w[1] - w[0] == 3840));
+    }
+
+    #[test]
+    fn repeats_play_last_pass_after_jump() {
+        // |: M0 | M1[1.] :| M2[2.] | M3 D.C.
+        // Plays: M0 M1 M0 M2 M3, then M0 M2 M3 without repeating
+        let measure_len = 3840_u32;
+        let headers = vec![
+            make_header(960, true, 0),
+            MeasureHeader {
+                start: 960 + measure_len,
+                repeat_alternative: 1,
+                repeat_close: 1,
+                ..MeasureHeader::default()
+            },
+            MeasureHeader {
+                start: 960 + measure_len * 2,
+                repeat_alternative: 2,
+                ..MeasureHeader::default()
+            },
+            with_directions(960 + measure_len * 3, &[DirectionSign::DaCapo]),
+        ];
+        assert_eq!(indices(&headers), vec![0, 1, 0, 2, 3, 0, 2, 3]);
+    }
+
+    #[test]
+    fn jump_on_last_pass_of_repeat() {
+        // M0 Segno |: M1 D.S. :|
+        // Plays: M0 M1 M1 M0 M1
+        let measure_len = 3840_u32;
+        let headers = vec![
+            with_directions(960, &[DirectionSign::Segno]),
+            MeasureHeader {
+                start: 960 + measure_len,
+                repeat_open: true,
+                repeat_close: 1,
+                directions: vec![DirectionSign::DaSegno],
+                ..MeasureHeader::default()
+            },
+        ];
+        assert_eq!(indices(&headers), vec![0, 1, 1, 0, 1]);
+    }
+
+    #[test]
+    fn jump_to_missing_target_is_ignored() {
+        let headers = vec![
+            make_header(960, false, 0),
+            with_directions(4800, &[DirectionSign::DaSegnoAlFine]),
+        ];
+        assert_eq!(indices(&headers), vec![0, 1]);
+    }
 }
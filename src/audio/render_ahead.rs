@@ -0,0 +1,574 @@
+//! Render audio ahead of the output device.
+//! A producer thread runs the sequencer and the synthesizer a few hundred milliseconds
+//! ahead of playback and fills a lock-free ring buffer drained by the real-time callback,
+//! so a render that is occasionally too slow does not starve the device.
+
+use crate::audio::midi_event::{MidiEvent, MidiEventType};
+use crate::audio::midi_player_params::{MidiPlayerParams, TrackFilter};
+use crate::audio::midi_sequencer::MidiSequencer;
+use crate::audio::synth_rack::SynthRack;
+use rtrb::{Consumer, Producer, RingBuffer};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::Duration;
+use tokio::sync::Notify;
+
+/// Audio rendered in advance of the output device.
+pub const RENDER_AHEAD_MS: u32 = 300;
+
+/// Audio rendered per producer iteration.
+const CHUNK_MS: u32 = 10;
+
+/// Producer pause when the ring buffer is full.
+const IDLE_SLEEP: Duration = Duration::from_millis(2);
+
+/// Stereo samples.
+type Frame = (f32, f32);
+
+/// Share of the previous output level kept per device buffer, so the meter falls back smoothly.
+const LEVEL_DECAY: f32 = 0.9;
+
+/// Samples below this level pass the limiter unchanged, louder ones are bent towards full scale.
+const LIMITER_THRESHOLD: f32 = 0.8;
+
+/// Tick to display once the output has played past `frame`.
+#[derive(Debug, Clone, Copy)]
+struct TickMarker {
+    frame: u64,
+    tick: u32,
+}
+
+/// Handle on the producer thread, stops it when dropped.
+pub struct RenderThread {
+    running: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Drop for RenderThread {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Release);
+        if let Some(handle) = self.handle.take()
+            && handle.join().is_err()
+        {
+            log::error!("Audio render thread panicked");
+        }
+    }
+}
+
+/// Real-time side of the ring buffer, owned by the audio callback.
+pub struct FrameConsumer {
+    frames: Consumer<Frame>,
+    markers: Consumer<TickMarker>,
+    played_frames: u64,    // frames taken out of the ring buffer
+    flush_generation: u32, // last flush applied
+    underrun_frames: Arc<AtomicU64>,
+    current_tick: Arc<AtomicU32>,
+    beat_notify: Arc<Notify>,
+}
+
+impl FrameConsumer {
+    /// Fill the device buffer with rendered frames, missing frames are silenced.
+    pub fn fill(&mut self, output: &mut [f32], channel_count: usize, params: &MidiPlayerParams) {
+        // drop frames rendered before a seek
+        let generation = params.flush_generation();
+        if generation != self.flush_generation {
+            self.flush_generation = generation;
+            let stale = self.frames.slots();
+            if let Ok(chunk) = self.frames.read_chunk(stale) {
+                chunk.commit_all();
+            }
+            self.played_frames += stale as u64;
+        }
+
+        let frame_count = output.len() / channel_count;
+        params.set_buffer_frames(frame_count as u32);
+        let available = self.frames.slots().min(frame_count);
+        if available < frame_count {
+            self.underrun_frames
+                .fetch_add((frame_count - available) as u64, Ordering::Relaxed);
+        }
+        let master_volume = params.master_volume();
+        let mono = params.mono();
+        match self.frames.read_chunk(available) {
+            Ok(chunk) => {
+                let (first, second) = chunk.as_slices();
+                let (head, tail) = output.split_at_mut(first.len() * channel_count);
+                write_frames(head, first, channel_count, master_volume, mono);
+                write_frames(tail, second, channel_count, master_volume, mono);
+                chunk.commit_all();
+            }
+            Err(_) => write_frames(output, &[], channel_count, master_volume, mono),
+        }
+        // dense passages add up past full scale
+        for sample in output.iter_mut() {
+            *sample = soft_limit(*sample);
+        }
+        self.played_frames += available as u64;
+        self.publish_tick();
+
+        let (left, right) = output_levels(output, channel_count);
+        let (previous_left, previous_right) = params.output_levels();
+        params.set_output_levels(
+            left.max(previous_left * LEVEL_DECAY),
+            right.max(previous_right * LEVEL_DECAY),
+        );
+    }
+
+    /// Expose the tick of the latest frames played to the UI.
+    fn publish_tick(&mut self) {
+        let mut latest = None;
+        while let Ok(marker) = self.markers.peek()
+            && marker.frame < self.played_frames
+        {
+            latest = Some(marker.tick);
+            let _ = self.markers.pop();
+        }
+        if let Some(tick) = latest {
+            self.current_tick.store(tick, Ordering::Release);
+            self.beat_notify.notify_one();
+        }
+    }
+}
+
+/// Shared state driven by the producer thread.
+pub struct RenderSource {
+    pub sequencer: Arc<Mutex<MidiSequencer>>,
+    pub player_params: Arc<MidiPlayerParams>,
+    pub synthesizer: Arc<Mutex<SynthRack>>,
+    pub current_tick: Arc<AtomicU32>,
+    pub beat_notify: Arc<Notify>,
+}
+
+/// Spawn the producer thread rendering ahead at `sample_rate`.
+pub fn start(source: RenderSource, sample_rate: u32) -> (RenderThread, FrameConsumer) {
+    let chunk_len = (sample_rate * CHUNK_MS / 1000).max(1) as usize;
+    let capacity = (sample_rate * RENDER_AHEAD_MS / 1000) as usize + chunk_len;
+    let (frames_producer, frames) = RingBuffer::new(capacity);
+    // at most one marker per chunk
+    let (markers_producer, markers) = RingBuffer::new(capacity / chunk_len + 1);
+    let underrun_frames = Arc::new(AtomicU64::new(0));
+    let running = Arc::new(AtomicBool::new(true));
+    log::info!(
+        "Rendering audio {RENDER_AHEAD_MS} ms ahead in chunks of {chunk_len} frames at {sample_rate} Hz"
+    );
+
+    let consumer = FrameConsumer {
+        frames,
+        markers,
+        played_frames: 0,
+        flush_generation: source.player_params.flush_generation(),
+        underrun_frames: underrun_frames.clone(),
+        current_tick: source.current_tick.clone(),
+        beat_notify: source.beat_notify.clone(),
+    };
+    let producer = FrameProducer {
+        frames: frames_producer,
+        markers: markers_producer,
+        rendered_frames: 0,
+        left: vec![0.0; chunk_len],
+        right: vec![0.0; chunk_len],
+        sample_rate,
+        underrun_frames,
+        reported_underrun_frames: 0,
+    };
+    let thread_running = running.clone();
+    let handle = std::thread::Builder::new()
+        .name("audio-render".to_string())
+        .spawn(move || producer.run(&source, &thread_running))
+        .expect("failed to spawn audio render thread");
+    let thread = RenderThread {
+        running,
+        handle: Some(handle),
+    };
+    (thread, consumer)
+}
+
+struct FrameProducer {
+    frames: Producer<Frame>,
+    markers: Producer<TickMarker>,
+    rendered_frames: u64, // frames pushed into the ring buffer
+    left: Vec<f32>,
+    right: Vec<f32>,
+    sample_rate: u32,
+    underrun_frames: Arc<AtomicU64>,
+    reported_underrun_frames: u64,
+}
+
+impl FrameProducer {
+    fn run(mut self, source: &RenderSource, running: &AtomicBool) {
+        let chunk_len = self.left.len();
+        let mut previous_chunk = 0;
+        let mut was_halted = false;
+        while running.load(Ordering::Acquire) {
+            self.report_underruns();
+            if self.frames.slots() < chunk_len {
+                std::thread::sleep(IDLE_SLEEP);
+                continue;
+            }
+            let mut sequencer_guard = source.sequencer.lock().unwrap();
+            sequencer_guard.set_tempo_percentage(source.player_params.tempo_percentage());
+            sequencer_guard.advance(previous_chunk);
+            let mut synthesizer_guard = source.synthesizer.lock().unwrap();
+            if let Some(events) = sequencer_guard.get_next_events() {
+                let tick = sequencer_guard.get_tick();
+                if !events.is_empty() {
+                    let last_tick = sequencer_guard.get_last_tick();
+                    log::debug!(
+                        "---> Increase {} ticks [{} -> {}] ({} events)",
+                        tick - last_tick,
+                        last_tick,
+                        tick,
+                        events.len()
+                    );
+                }
+                if events.iter().any(MidiEvent::is_note_event) {
+                    // a full marker queue only delays the UI cursor
+                    let _ = self.markers.push(TickMarker {
+                        frame: self.rendered_frames,
+                        tick,
+                    });
+                }
+                dispatch_events(
+                    events,
+                    &mut synthesizer_guard,
+                    source.player_params.track_filter(),
+                );
+            }
+            dispatch_events(
+                sequencer_guard.click_events(),
+                &mut synthesizer_guard,
+                TrackFilter::AllTracks,
+            );
+            // count-in clicks before the repetition of the section
+            dispatch_events(
+                sequencer_guard.gap_events(),
+                &mut synthesizer_guard,
+                TrackFilter::AllTracks,
+            );
+            let halted = sequencer_guard.is_halted() || sequencer_guard.is_in_gap();
+            if halted && !was_halted {
+                // release the notes ringing into the next measure while waiting
+                synthesizer_guard.note_off_all(false);
+                let _ = self.markers.push(TickMarker {
+                    frame: self.rendered_frames,
+                    tick: sequencer_guard.get_tick(),
+                });
+            }
+            was_halted = halted;
+            synthesizer_guard.render(&mut self.left, &mut self.right);
+            drop(synthesizer_guard);
+
+            // push while holding the sequencer so a seek cannot interleave with stale frames
+            if let Ok(chunk) = self.frames.write_chunk_uninit(chunk_len) {
+                let frames = self.left.iter().copied().zip(self.right.iter().copied());
+                chunk.fill_from_iter(frames);
+            }
+            drop(sequencer_guard);
+            self.rendered_frames += chunk_len as u64;
+            previous_chunk = chunk_len as u32;
+        }
+    }
+
+    /// Log under-runs from this thread, the audio callback must not block on logging.
+    fn report_underruns(&mut self) {
+        let underrun_frames = self.underrun_frames.load(Ordering::Relaxed);
+        if underrun_frames > self.reported_underrun_frames {
+            let missing = underrun_frames - self.reported_underrun_frames;
+            self.reported_underrun_frames = underrun_frames;
+            let buffered = self.frames.buffer().capacity() - self.frames.slots();
+            log::warn!(
+                "Audio under-run: {:.1} ms of silence, {:.1} ms buffered ahead",
+                missing as f64 * 1000.0 / f64::from(self.sample_rate),
+                buffered as f64 * 1000.0 / f64::from(self.sample_rate)
+            );
+        }
+    }
+}
+
+/// Apply a batch of sequencer events to the synthesizer.
+pub fn dispatch_events(
+    events: &[MidiEvent],
+    synthesizer: &mut SynthRack,
+    track_filter: TrackFilter,
+) {
+    for midi_event in events {
+        match midi_event.event {
+            MidiEventType::NoteOn(channel, key, velocity) => {
+                // skip note on events of the tracks left out by solo or backing mode
+                if !track_filter.plays(midi_event.track) {
+                    continue;
+                }
+                log::debug!(
+                    "[{}] Note on: channel={}, key={}, velocity={}",
+                    midi_event.tick,
+                    channel,
+                    key,
+                    velocity
+                );
+                synthesizer.note_on(midi_event.track, channel, key, i32::from(velocity));
+            }
+            MidiEventType::NoteOff(channel, key) => {
+                log::debug!(
+                    "[{}] Note off: channel={}, key={}",
+                    midi_event.tick,
+                    channel,
+                    key
+                );
+                synthesizer.note_off(midi_event.track, channel, key);
+            }
+            MidiEventType::TempoChange(tempo) => {
+                // the sequencer follows its own tempo map
+                log::debug!("Tempo changed to {tempo}");
+            }
+            MidiEventType::MidiMessage(channel, command, data1, data2) => {
+                log::debug!(
+                    "[{}] Midi message: channel={}, command={}, data1={}, data2={}",
+                    midi_event.tick,
+                    channel,
+                    command,
+                    data1,
+                    data2
+                );
+                synthesizer.process_midi_message(midi_event.track, channel, command, data1, data2);
+            }
+        }
+    }
+}
+
+/// All notes off and reset controllers on every channel, so that no note hangs
+/// and no pitch bend or expression carries over to where playback resumes.
+/// Programs, volumes and pans are kept.
+pub fn silence(synthesizer: &mut SynthRack) {
+    synthesizer.note_off_all(false);
+    synthesizer.reset_all_controllers();
+}
+
+/// Interleave rendered stereo frames into the device's frame layout.
+///
+/// Mono devices get a downmix, as do both channels of a stereo device when `mono` is set.
+/// Channels beyond stereo are zeroed.
+/// Frames past the rendered ones are silenced explicitly: the output
+/// buffer is not guaranteed to be zeroed and would replay stale samples.
+fn write_frames(
+    output: &mut [f32],
+    rendered: &[Frame],
+    channel_count: usize,
+    master_volume: f32,
+    mono: bool,
+) {
+    let mut frames = output.chunks_exact_mut(channel_count);
+    for (i, frame) in frames.by_ref().enumerate() {
+        let (l, r) = rendered
+            .get(i)
+            .map_or((0.0, 0.0), |(l, r)| (l * master_volume, r * master_volume));
+        let (l, r) = if mono {
+            let downmix = f32::midpoint(l, r);
+            (downmix, downmix)
+        } else {
+            (l, r)
+        };
+        match frame {
+            [single] => *single = (l + r) / 2.0,
+            [first, second, rest @ ..] => {
+                *first = l;
+                *second = r;
+                rest.fill(0.0);
+            }
+            [] => {}
+        }
+    }
+    // leftover samples when the buffer is not a whole number of frames
+    frames.into_remainder().fill(0.0);
+}
+
+/// Soft limiter: linear up to the threshold, then a tanh knee approaching full scale without reaching it.
+fn soft_limit(sample: f32) -> f32 {
+    let level = sample.abs();
+    if level <= LIMITER_THRESHOLD {
+        return sample;
+    }
+    let headroom = 1.0 - LIMITER_THRESHOLD;
+    let limited = LIMITER_THRESHOLD + headroom * ((level - LIMITER_THRESHOLD) / headroom).tanh();
+    limited.copysign(sample)
+}
+
+/// RMS of the left & right channels of the device buffer, mono devices have the same level on both.
+fn output_levels(output: &[f32], channel_count: usize) -> (f32, f32) {
+    let frames = output.chunks_exact(channel_count);
+    let frame_count = frames.len();
+    if frame_count == 0 {
+        return (0.0, 0.0);
+    }
+    let (left, right) = frames.fold((0.0, 0.0), |(left, right), frame| match frame {
+        [mono] => (left + mono * mono, right + mono * mono),
+        [first, second, ..] => (left + first * first, right + second * second),
+        [] => (left, right),
+    });
+    (
+        (left / frame_count as f32).sqrt(),
+        (right / frame_count as f32).sqrt(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn consumer(
+        params: &MidiPlayerParams,
+    ) -> (Producer<Frame>, Producer<TickMarker>, FrameConsumer) {
+        let (frames_producer, frames) = RingBuffer::new(8);
+        let (markers_producer, markers) = RingBuffer::new(4);
+        let consumer = FrameConsumer {
+            frames,
+            markers,
+            played_frames: 0,
+            flush_generation: params.flush_generation(),
+            underrun_frames: Arc::new(AtomicU64::new(0)),
+            current_tick: Arc::new(AtomicU32::new(0)),
+            beat_notify: Arc::new(Notify::new()),
+        };
+        (frames_producer, markers_producer, consumer)
+    }
+
+    #[test]
+    fn consumer_plays_frames_and_reports_underruns() {
+        let params = MidiPlayerParams::new(100, TrackFilter::AllTracks);
+        let (mut frames, mut markers, mut consumer) = consumer(&params);
+        for i in 0..3 {
+            frames.push((i as f32 / 4.0, -(i as f32) / 4.0)).unwrap();
+        }
+        markers.push(TickMarker { frame: 0, tick: 10 }).unwrap();
+        markers.push(TickMarker { frame: 2, tick: 20 }).unwrap();
+
+        let mut output = [9.0_f32; 4];
+        consumer.fill(&mut output, 2, &params);
+        assert_eq!(output, [0.0, 0.0, 0.25, -0.25]);
+        // the marker of the next frame is not published yet
+        assert_eq!(consumer.current_tick.load(Ordering::Relaxed), 10);
+        assert_eq!(consumer.underrun_frames.load(Ordering::Relaxed), 0);
+
+        // one frame left for a request of two
+        consumer.fill(&mut output, 2, &params);
+        assert_eq!(output, [0.5, -0.5, 0.0, 0.0]);
+        assert_eq!(consumer.current_tick.load(Ordering::Relaxed), 20);
+        assert_eq!(consumer.underrun_frames.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn consumer_drops_frames_on_flush() {
+        let params = MidiPlayerParams::new(100, TrackFilter::AllTracks);
+        let (mut frames, mut markers, mut consumer) = consumer(&params);
+        for _ in 0..4 {
+            frames.push((1.0, 1.0)).unwrap();
+        }
+        markers.push(TickMarker { frame: 3, tick: 30 }).unwrap();
+        params.request_flush();
+
+        let mut output = [9.0_f32; 2];
+        consumer.fill(&mut output, 2, &params);
+        assert_eq!(output, [0.0, 0.0]);
+        assert_eq!(consumer.played_frames, 4);
+        // stale markers are consumed with their frames
+        assert!(consumer.markers.is_empty());
+
+        // frames rendered after the seek play normally
+        frames.push((0.5, 0.5)).unwrap();
+        consumer.fill(&mut output, 2, &params);
+        assert_eq!(output, [0.5, 0.5]);
+    }
+
+    #[test]
+    fn write_frames_stereo_applies_volume() {
+        let rendered = [(1.0, -1.0), (0.5, 0.25)];
+        let mut output = [9.0_f32; 4];
+        write_frames(&mut output, &rendered, 2, 0.5, false);
+        assert_eq!(output, [0.5, -0.5, 0.25, 0.125]);
+    }
+
+    #[test]
+    fn soft_limit_keeps_the_output_below_full_scale() {
+        assert_eq!(soft_limit(0.5), 0.5);
+        assert_eq!(soft_limit(-LIMITER_THRESHOLD), -LIMITER_THRESHOLD);
+        let limited = soft_limit(1.0);
+        assert!(limited > LIMITER_THRESHOLD && limited < 1.0);
+        assert!(soft_limit(1.5) > limited && soft_limit(1.5) < 1.0);
+        assert!(soft_limit(100.0) <= 1.0);
+        assert_eq!(soft_limit(-1.5), -soft_limit(1.5));
+
+        // the consumer limits a boosted output
+        let params = MidiPlayerParams::new(100, TrackFilter::AllTracks);
+        params.set_master_volume(2.0);
+        let (mut frames, _, mut consumer) = consumer(&params);
+        frames.push((0.75, -0.25)).unwrap();
+        let mut output = [9.0_f32; 2];
+        consumer.fill(&mut output, 2, &params);
+        assert_eq!(output, [soft_limit(1.5), -0.5]);
+    }
+
+    #[test]
+    fn write_frames_mono_downmixes() {
+        let rendered = [(1.0, 0.5), (0.5, 0.25)];
+        let mut output = [9.0_f32; 2];
+        write_frames(&mut output, &rendered, 1, 1.0, false);
+        assert_eq!(output, [0.75, 0.375]);
+    }
+
+    #[test]
+    fn write_frames_downmixes_stereo_on_request() {
+        let rendered = [(1.0, 0.0), (0.0, 0.5)];
+        let mut output = [9.0_f32; 4];
+        write_frames(&mut output, &rendered, 2, 1.0, true);
+        assert_eq!(output, [0.5, 0.5, 0.25, 0.25]);
+    }
+
+    #[test]
+    fn write_frames_zeroes_extra_channels() {
+        let rendered = [(1.0, 0.5)];
+        // 4-channel device: one frame, extra channels silenced
+        let mut output = [9.0_f32; 4];
+        write_frames(&mut output, &rendered, 4, 1.0, false);
+        assert_eq!(output, [1.0, 0.5, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn write_frames_silences_unrendered_tail() {
+        let rendered = [(1.0, 0.5)];
+        // device asks for 3 frames but only 1 was rendered:
+        // the stale tail must be silenced, not replayed
+        let mut output = [9.0_f32; 6];
+        write_frames(&mut output, &rendered, 2, 1.0, false);
+        assert_eq!(output, [1.0, 0.5, 0.0, 0.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn write_frames_zeroes_partial_frame_remainder() {
+        let rendered = [(1.0, 0.5)];
+        // 5 samples on a stereo device: the dangling half-frame is silenced
+        let mut output = [9.0_f32; 5];
+        write_frames(&mut output, &rendered, 2, 1.0, false);
+        assert_eq!(output, [1.0, 0.5, 0.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn output_levels_per_channel() {
+        let output = [1.0, 0.5, -1.0, -0.5, 1.0, 0.5, -1.0, 0.5];
+        assert_eq!(output_levels(&output, 2), (1.0, 0.5));
+        assert_eq!(output_levels(&[0.5, -0.5], 1), (0.5, 0.5));
+        assert_eq!(output_levels(&[], 2), (0.0, 0.0));
+
+        // the meter falls back instead of dropping to silence
+        let params = MidiPlayerParams::new(100, TrackFilter::AllTracks);
+        let (mut frames, _, mut consumer) = consumer(&params);
+        frames.push((0.5, 0.5)).unwrap();
+        let mut output = [9.0_f32; 2];
+        consumer.fill(&mut output, 2, &params);
+        assert_eq!(params.output_levels(), (0.5, 0.5));
+        consumer.fill(&mut output, 2, &params);
+        assert_eq!(
+            params.output_levels(),
+            (0.5 * LEVEL_DECAY, 0.5 * LEVEL_DECAY)
+        );
+    }
+}
@@ -0,0 +1,166 @@
+//! Synthesizers of the loaded sound fonts, each track is played by the synthesizer of its sound font.
+//! A single sound font (the default) renders with a single synthesizer.
+
+use rustysynth::{SoundFont, Synthesizer, SynthesizerSettings};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// Sound font of each track, the default one plays the tracks without assignment.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SoundFontAssignment {
+    pub default: Option<PathBuf>,         // embedded sound font when unset
+    pub tracks: BTreeMap<usize, PathBuf>, // track index to sound font file
+}
+
+impl SoundFontAssignment {
+    /// Sound font files besides the default one, without duplicates.
+    pub fn extra_files(&self) -> Vec<&PathBuf> {
+        let mut files: Vec<&PathBuf> = self
+            .tracks
+            .values()
+            .filter(|file| Some(*file) != self.default.as_ref())
+            .collect();
+        files.sort();
+        files.dedup();
+        files
+    }
+
+    /// Index of the sound font playing the track: 0 for the default one,
+    /// then the extra files in order.
+    pub fn sound_font_index(&self, track: usize) -> usize {
+        self.tracks
+            .get(&track)
+            .and_then(|file| self.extra_files().iter().position(|f| *f == file))
+            .map_or(0, |position| position + 1)
+    }
+}
+
+pub struct SynthRack {
+    sound_fonts: Vec<Arc<SoundFont>>, // default sound font first
+    synthesizers: Vec<Synthesizer>,   // one per sound font
+    track_synthesizers: Vec<usize>,   // synthesizer of each track
+    left: Vec<f32>,                   // rendering buffers of the additional synthesizers
+    right: Vec<f32>,
+}
+
+impl SynthRack {
+    /// Synthesizers for the sound fonts, `track_synthesizers` holds the index of the sound font of each track.
+    pub fn new(
+        sound_fonts: Vec<Arc<SoundFont>>,
+        track_synthesizers: Vec<usize>,
+        sample_rate: u32,
+    ) -> Result<Self, String> {
+        let settings = SynthesizerSettings::new(sample_rate as i32);
+        let synthesizers = sound_fonts
+            .iter()
+            .map(|sound_font| Synthesizer::new(sound_font, &settings).map_err(|e| e.to_string()))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self {
+            sound_fonts,
+            synthesizers,
+            track_synthesizers,
+            left: Vec::new(),
+            right: Vec::new(),
+        })
+    }
+
+    /// Same sound fonts and tracks at another sample rate.
+    pub fn with_sample_rate(&self, sample_rate: u32) -> Result<Self, String> {
+        Self::new(
+            self.sound_fonts.clone(),
+            self.track_synthesizers.clone(),
+            sample_rate,
+        )
+    }
+
+    /// Synthesizer playing the track, the default one for events without track.
+    fn synthesizer(&mut self, track: Option<u8>) -> &mut Synthesizer {
+        let index = track
+            .and_then(|track| self.track_synthesizers.get(usize::from(track)))
+            .copied()
+            .unwrap_or(0);
+        &mut self.synthesizers[index]
+    }
+
+    pub fn process_midi_message(
+        &mut self,
+        track: Option<u8>,
+        channel: i32,
+        command: i32,
+        data1: i32,
+        data2: i32,
+    ) {
+        self.synthesizer(track)
+            .process_midi_message(channel, command, data1, data2);
+    }
+
+    pub fn note_on(&mut self, track: Option<u8>, channel: i32, key: i32, velocity: i32) {
+        self.synthesizer(track).note_on(channel, key, velocity);
+    }
+
+    pub fn note_off(&mut self, track: Option<u8>, channel: i32, key: i32) {
+        self.synthesizer(track).note_off(channel, key);
+    }
+
+    pub fn note_off_all(&mut self, immediate: bool) {
+        for synthesizer in &mut self.synthesizers {
+            synthesizer.note_off_all(immediate);
+        }
+    }
+
+    pub fn reset_all_controllers(&mut self) {
+        for synthesizer in &mut self.synthesizers {
+            synthesizer.reset_all_controllers();
+        }
+    }
+
+    /// Render all the synthesizers mixed together.
+    pub fn render(&mut self, left: &mut [f32], right: &mut [f32]) {
+        let (first, others) = self
+            .synthesizers
+            .split_first_mut()
+            .expect("at least the default synthesizer");
+        first.render(left, right);
+        if others.is_empty() {
+            return;
+        }
+        self.left.resize(left.len(), 0.0);
+        self.right.resize(right.len(), 0.0);
+        for synthesizer in others {
+            synthesizer.render(&mut self.left, &mut self.right);
+            for (mixed, sample) in left.iter_mut().zip(&self.left) {
+                *mixed += sample;
+            }
+            for (mixed, sample) in right.iter_mut().zip(&self.right) {
+                *mixed += sample;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tracks_share_the_sound_font_files() {
+        let assignment = SoundFontAssignment {
+            default: Some(PathBuf::from("main.sf2")),
+            tracks: BTreeMap::from([
+                (1, PathBuf::from("drums.sf2")),
+                (2, PathBuf::from("bass.sf2")),
+                (3, PathBuf::from("drums.sf2")),
+                (4, PathBuf::from("main.sf2")),
+            ]),
+        };
+        assert_eq!(
+            assignment.extra_files(),
+            vec![&PathBuf::from("bass.sf2"), &PathBuf::from("drums.sf2")]
+        );
+        let indexes: Vec<usize> = (0..6)
+            .map(|track| assignment.sound_font_index(track))
+            .collect();
+        assert_eq!(indexes, vec![0, 2, 1, 2, 0, 0]);
+    }
+}
@@ -0,0 +1,276 @@
+//! Tempo along the playback timeline, to convert between ticks and seconds.
+
+use crate::audio::midi_event::{MidiEvent, MidiEventType};
+use crate::audio::playback_order::playback_tick;
+use crate::parser::song_parser::{QUARTER_TIME, Song, TempoChange};
+
+/// Ticks between the tempo steps of a gradual tempo change.
+const RAMP_STEP: u32 = QUARTER_TIME / 4;
+
+/// Tempo changes of the playback timeline, shared by the playback clock and the time displays.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TempoMap {
+    changes: Vec<(u32, u32)>, // (tick, tempo) sorted by tick, starts at tick 0
+}
+
+impl TempoMap {
+    /// Tempo map from the sorted events, starting at `initial_tempo`.
+    pub fn from_events(initial_tempo: u32, sorted_events: &[MidiEvent]) -> Self {
+        let changes = std::iter::once((0, initial_tempo))
+            .chain(sorted_events.iter().filter_map(|event| match event.event {
+                MidiEventType::TempoChange(tempo) => Some((event.tick, tempo)),
+                _ => None,
+            }))
+            .collect();
+        Self { changes }
+    }
+
+    /// Tempo map of the song in playback order, matching the tempo changes of the built events.
+    pub fn from_song(song: &Song, playback_order: &[(usize, i64)]) -> Self {
+        let changes = std::iter::once((0, song.tempo.value))
+            .chain(tempo_changes(song, playback_order))
+            .collect();
+        Self { changes }
+    }
+
+    /// Tempo changes (tick, tempo) sorted by tick, the first one at tick 0.
+    pub fn changes(&self) -> &[(u32, u32)] {
+        &self.changes
+    }
+
+    /// Index of the tempo in effect at `tick`.
+    fn index_at(&self, tick: f64) -> usize {
+        self.changes
+            .partition_point(|(change_tick, _)| f64::from(*change_tick) <= tick)
+            .saturating_sub(1)
+    }
+
+    /// Seconds to play from `from_tick` to `to_tick`, zero if `to_tick` comes first.
+    pub fn seconds_between(&self, from_tick: u32, to_tick: u32, tempo_percentage: u32) -> f64 {
+        let mut seconds = 0.0;
+        let mut tick = from_tick;
+        let mut index = self.index_at(f64::from(from_tick));
+        while tick < to_tick {
+            let segment_end = self
+                .changes
+                .get(index + 1)
+                .map_or(to_tick, |(change_tick, _)| (*change_tick).min(to_tick));
+            let ticks_per_sec = ticks_per_second(self.changes[index].1, tempo_percentage);
+            seconds += f64::from(segment_end - tick) / ticks_per_sec;
+            tick = segment_end;
+            index += 1;
+        }
+        seconds
+    }
+
+    /// Tick position reached after playing `seconds` from `start_tick`.
+    pub fn tick_after_seconds(&self, start_tick: f64, seconds: f64, tempo_percentage: u32) -> f64 {
+        let mut tick = start_tick;
+        let mut remaining_secs = seconds;
+        let mut index = self.index_at(start_tick);
+        loop {
+            let ticks_per_sec = ticks_per_second(self.changes[index].1, tempo_percentage);
+            let Some(&(next_change, _)) = self.changes.get(index + 1) else {
+                return tick + remaining_secs * ticks_per_sec;
+            };
+            let secs_to_change = (f64::from(next_change) - tick) / ticks_per_sec;
+            if remaining_secs <= secs_to_change {
+                return tick + remaining_secs * ticks_per_sec;
+            }
+            remaining_secs -= secs_to_change;
+            tick = f64::from(next_change);
+            index += 1;
+        }
+    }
+}
+
+/// Tempo changes (tick, tempo) along the playback order: the tempo of each measure
+/// and the beat tempo changes within it. Gradual changes are played in steps
+/// and carry on over the following measures.
+pub fn tempo_changes(song: &Song, playback_order: &[(usize, i64)]) -> Vec<(u32, u32)> {
+    // beat changes of each measure, from any track or voice
+    let mut beat_changes: Vec<Vec<(u32, TempoChange)>> =
+        vec![Vec::new(); song.measure_headers.len()];
+    for track in &song.tracks {
+        for (measure_index, measure) in track.measures.iter().enumerate() {
+            for beat in measure.voices.iter().flat_map(|voice| &voice.beats) {
+                if let Some(change) = beat.mix_table_change.as_ref().and_then(|m| m.tempo) {
+                    beat_changes[measure_index].push((beat.start, change));
+                }
+            }
+        }
+    }
+    for measure_changes in &mut beat_changes {
+        measure_changes.sort_by_key(|(start, _)| *start);
+        // the last parsed change of a beat wins, like for the measure headers
+        measure_changes.dedup_by(|later, earlier| {
+            let same_beat = later.0 == earlier.0;
+            if same_beat {
+                *earlier = *later;
+            }
+            same_beat
+        });
+    }
+
+    let mut changes = Vec::new();
+    let mut ramp_end = 0;
+    let mut song_end = 0;
+    for &(measure_index, tick_offset) in playback_order {
+        let header = &song.measure_headers[measure_index];
+        let measure_start = playback_tick(header.start, tick_offset);
+        song_end = measure_start + header.length();
+        // a gradual change in progress carries on over the measure start
+        if measure_start >= ramp_end {
+            push_change(
+                &mut changes,
+                song.tempo.value,
+                measure_start,
+                header.tempo.value,
+            );
+        }
+        for &(beat_start, change) in &beat_changes[measure_index] {
+            let start = playback_tick(beat_start, tick_offset);
+            // a new change interrupts the one in progress
+            changes.truncate(changes.partition_point(|(tick, _)| *tick < start));
+            let from = changes.last().map_or(song.tempo.value, |(_, tempo)| *tempo);
+            for (tick, tempo) in transition_steps(
+                start,
+                i64::from(from),
+                i64::from(change.value),
+                change.transition,
+            ) {
+                push_change(&mut changes, song.tempo.value, tick, tempo as u32);
+            }
+            ramp_end = start + u32::from(change.transition) * QUARTER_TIME;
+        }
+    }
+    // the end of a gradual change may fall after the song
+    changes.retain(|(tick, _)| *tick < song_end);
+    changes
+}
+
+/// Values of a change from `from` to `to` reached gradually over `transition` quarter notes
+/// from `start`, one step every `RAMP_STEP` ticks. A single step if the change is immediate.
+pub fn transition_steps(
+    start: u32,
+    from: i64,
+    to: i64,
+    transition: u8,
+) -> impl Iterator<Item = (u32, i64)> {
+    let steps = (u32::from(transition) * QUARTER_TIME / RAMP_STEP).max(1);
+    (1..=steps).map(move |step| {
+        let value = from + (to - from) * i64::from(step) / i64::from(steps);
+        (start + (step - 1) * RAMP_STEP, value)
+    })
+}
+
+fn push_change(changes: &mut Vec<(u32, u32)>, initial_tempo: u32, tick: u32, tempo: u32) {
+    let current = changes.last().map_or(initial_tempo, |(_, tempo)| *tempo);
+    if tempo != current {
+        changes.push((tick, tempo));
+    }
+}
+
+fn ticks_per_second(tempo: u32, tempo_percentage: u32) -> f64 {
+    // clamp to 1 BPM: at tempo 0 playback would never advance again,
+    // freezing it with no way to reach the next tempo change
+    let adjusted_tempo = (f64::from(tempo) * f64::from(tempo_percentage) / 100.0).max(1.0);
+    tick_increase(adjusted_tempo, 1.0)
+}
+
+fn tick_increase(tempo_bpm: f64, elapsed_seconds: f64) -> f64 {
+    let tempo_bps = tempo_bpm / 60.0;
+    f64::from(QUARTER_TIME) * tempo_bps * elapsed_seconds
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::midi_builder::MidiBuilder;
+    use crate::audio::playback_order::compute_playback_order;
+    use crate::parser::song_parser_tests::parse_gp_file;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[test]
+    fn test_tick_increase() {
+        let tempo = 100.0;
+        let elapsed = Duration::from_millis(32);
+        let result = tick_increase(tempo, elapsed.as_secs_f64());
+        assert!((result - 51.2).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_tick_increase_bis() {
+        let tempo = 120.0;
+        let elapsed = Duration::from_millis(100);
+        let result = tick_increase(tempo, elapsed.as_secs_f64());
+        assert!((result - 192.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn seconds_follow_tempo_changes_and_percentage() {
+        // 120 BPM then 60 BPM from the second quarter
+        let events = [MidiEvent::new_tempo_change(960, 60)];
+        let tempo_map = TempoMap::from_events(120, &events);
+        assert!((tempo_map.seconds_between(0, 960, 100) - 0.5).abs() < 1e-9);
+        assert!((tempo_map.seconds_between(0, 1920, 100) - 1.5).abs() < 1e-9);
+        assert!((tempo_map.seconds_between(480, 1440, 100) - 0.75).abs() < 1e-9);
+        // half speed doubles the time
+        assert!((tempo_map.seconds_between(0, 1920, 50) - 3.0).abs() < 1e-9);
+        assert!(tempo_map.seconds_between(1920, 0, 100).abs() < 1e-9);
+    }
+
+    #[test]
+    fn seconds_and_ticks_round_trip() {
+        let events = [
+            MidiEvent::new_tempo_change(960, 60),
+            MidiEvent::new_tempo_change(4000, 200),
+        ];
+        let tempo_map = TempoMap::from_events(120, &events);
+        for percentage in [25, 100, 200] {
+            for tick in [0, 500, 960, 3999, 4000, 10_000] {
+                let seconds = tempo_map.seconds_between(100, tick.max(100), percentage);
+                let back = tempo_map.tick_after_seconds(100.0, seconds, percentage);
+                assert!((back - f64::from(tick.max(100))).abs() < 1e-6);
+            }
+        }
+    }
+
+    #[test]
+    fn song_matches_the_built_events() {
+        for file in [
+            "Demo v5.gp5",
+            "Meshuggah - Bleed.gp5",
+            "Blind Guardian - Nightfall.gp3",
+            "Ghost - Cirice.gp5",
+        ] {
+            let song = Arc::new(parse_gp_file(&format!("test-files/{file}")).unwrap());
+            let order = compute_playback_order(&song.measure_headers);
+            let events = MidiBuilder::new().build_for_song_with_order(&song, &order);
+            assert_eq!(
+                TempoMap::from_song(&song, &order),
+                TempoMap::from_events(song.tempo.value, &events),
+                "{file}"
+            );
+        }
+    }
+
+    #[test]
+    fn gradual_tempo_changes_play_in_steps() {
+        let song = parse_gp_file("test-files/Blind Guardian - Nightfall.gp3").unwrap();
+        let order = compute_playback_order(&song.measure_headers);
+        let changes = tempo_changes(&song, &order);
+        // from 95 to 60 over 6 quarter notes, across the measure boundary
+        let ramp: Vec<(u32, u32)> = changes
+            .iter()
+            .copied()
+            .filter(|(tick, _)| (486_240..492_000).contains(tick))
+            .collect();
+        assert_eq!(ramp.len(), 24);
+        assert_eq!(ramp[0], (486_240, 94));
+        assert_eq!(ramp[23], (486_240 + 6 * QUARTER_TIME - RAMP_STEP, 60));
+        assert!(ramp.windows(2).all(|w| w[0].1 > w[1].1));
+        assert!(ramp.windows(2).all(|w| w[1].0 - w[0].0 == RAMP_STEP));
+    }
+}
@@ -1,79 +1,517 @@
 use std::{
-    env::home_dir,
     fs::{File, create_dir_all},
     io::{BufReader, Write},
-    path::PathBuf,
+    path::{Path, PathBuf},
 };
 
 use serde::{Deserialize, Serialize};
 
 use crate::RuxError;
+use crate::audio::metronome::Metronome;
+use crate::audio::midi_builder::{PresetRemaps, VibratoSettings};
+use crate::audio::midi_player::SectionEnd;
+use crate::paths::AppPaths;
+use crate::ui::midi_control::MidiBinding;
+use crate::ui::track_manager::TrackLayout;
+use std::collections::{BTreeMap, HashMap};
+
+/// Practice context of a file, restored when the file is opened again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FileSession {
+    pub track: usize,
+    pub tempo_percentage: u32,
+    pub section: Option<(usize, usize)>, // first and last measures of the loop region
+}
+
+impl Default for FileSession {
+    fn default() -> Self {
+        Self {
+            track: 0,
+            tempo_percentage: 100,
+            section: None,
+        }
+    }
+}
+
+/// Key of the settings remembered per file: the canonical path of a file on disk, the hash
+/// of the content read from the standard input or downloaded, their names are made up.
+pub fn file_key(path: Option<&Path>, contents: &[u8]) -> String {
+    match path {
+        Some(path) => std::fs::canonicalize(path)
+            .unwrap_or_else(|_| path.to_path_buf())
+            .to_string_lossy()
+            .into_owned(),
+        None => {
+            let mut crc = crc32fast::Hasher::new();
+            crc.update(contents);
+            format!("content:{:08x}:{}", crc.finalize(), contents.len())
+        }
+    }
+}
 
 #[derive(Default, Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
     tabs_folder: Option<PathBuf>,
+    #[serde(default)]
+    vibrato: VibratoSettings,
+    #[serde(default)]
+    pre_roll_measures: usize,
+    #[serde(default)]
+    section_end: SectionEnd, // stop or loop at the end of the section
+    #[serde(default)]
+    metronome: Metronome,
+    #[serde(default)]
+    measures_per_row: usize, // maximum measures on a tablature row, 0 fits the width
+    #[serde(default)]
+    midi_bindings: Vec<MidiBinding>,
+    #[serde(default)]
+    global_hotkeys: bool,
+    #[serde(default)]
+    hide_dynamics: bool,
+    #[serde(default)]
+    hide_directions: bool, // direction signs of the measure annotation row collapsed
+    #[serde(default)]
+    show_fretboard: bool,
+    #[serde(default)]
+    chord_suggestions: bool,
+    #[serde(default)]
+    scale_highlight: bool, // notes outside the key of the section highlighted
+    #[serde(default)]
+    note_names: bool, // pitches of the notes displayed instead of their frets
+    #[serde(default)]
+    difficulty_heatmap: bool, // difficulty of the measures on the tempo strip
+    #[serde(default)]
+    instant_scroll: bool, // jump to the row of the played measure instead of animating
+    #[serde(default)]
+    low_power: bool, // fewer redraws, no animation, paused updates while the window is unfocused
+    #[serde(default)]
+    audio_backend: Option<String>, // platform default when unset
+    #[serde(default)]
+    preset_remaps: HashMap<String, PresetRemaps>, // per sound font
+    #[serde(default)]
+    track_layouts: HashMap<String, TrackLayout>, // per file key
+    #[serde(default)]
+    master_volume: Option<f32>, // unity when unset
+    #[serde(default)]
+    mono_output: bool,
+    #[serde(default)]
+    sound_fonts: Vec<PathBuf>, // additional sound fonts offered to the tracks
+    #[serde(default)]
+    track_sound_fonts: HashMap<String, BTreeMap<usize, PathBuf>>, // per file key
+    #[serde(default)]
+    file_sessions: HashMap<String, FileSession>, // per file key
+    #[serde(default)]
+    multiple_instances: bool, // files opened from the file manager start another window
+    #[serde(skip)]
+    paths: Option<AppPaths>, // where the configuration is saved
 }
 
 impl Config {
-    // folder placed in $HOME directory
-    const FOLDER: &'static str = ".config/ruxguitar";
-
     pub fn get_tabs_folder(&self) -> Option<PathBuf> {
         self.tabs_folder.clone()
     }
 
-    pub fn set_tabs_folder(&mut self, new_tabs_folder: Option<PathBuf>) -> Result<(), RuxError> {
-        if self.tabs_folder == new_tabs_folder {
-            // no op
+    pub const fn get_vibrato(&self) -> VibratoSettings {
+        self.vibrato
+    }
+
+    pub const fn get_section_end(&self) -> SectionEnd {
+        self.section_end
+    }
+
+    pub fn set_section_end(&mut self, section_end: SectionEnd) -> Result<(), RuxError> {
+        if self.section_end == section_end {
             Ok(())
         } else {
-            self.tabs_folder = new_tabs_folder;
+            self.section_end = section_end;
             self.save_config()
         }
     }
 
-    fn get_base_path() -> Result<PathBuf, RuxError> {
-        let home = home_dir()
-            .ok_or_else(|| RuxError::ConfigError("Could not find home directory".to_string()))?;
-        let path = home.join(Self::FOLDER);
-        Ok(path)
+    pub const fn get_metronome(&self) -> Metronome {
+        self.metronome
+    }
+
+    pub fn set_metronome(&mut self, metronome: Metronome) -> Result<(), RuxError> {
+        if self.metronome == metronome {
+            Ok(())
+        } else {
+            self.metronome = metronome;
+            self.save_config()
+        }
     }
 
-    fn get_path() -> Result<PathBuf, RuxError> {
-        let base = Self::get_base_path()?;
-        Ok(base.join("config.json"))
+    pub const fn get_pre_roll_measures(&self) -> usize {
+        self.pre_roll_measures
     }
 
-    /// Creates config if it does not exist
-    pub fn read_config() -> Result<Self, RuxError> {
-        let base_path = Self::get_base_path()?;
-        if !base_path.exists() {
-            create_dir_all(base_path)?;
+    pub fn set_pre_roll_measures(&mut self, pre_roll_measures: usize) -> Result<(), RuxError> {
+        if self.pre_roll_measures == pre_roll_measures {
+            Ok(())
+        } else {
+            self.pre_roll_measures = pre_roll_measures;
+            self.save_config()
+        }
+    }
+
+    pub const fn get_measures_per_row(&self) -> usize {
+        self.measures_per_row
+    }
+
+    pub fn set_measures_per_row(&mut self, measures_per_row: usize) -> Result<(), RuxError> {
+        if self.measures_per_row == measures_per_row {
+            Ok(())
+        } else {
+            self.measures_per_row = measures_per_row;
+            self.save_config()
+        }
+    }
+
+    pub fn get_midi_bindings(&self) -> &[MidiBinding] {
+        &self.midi_bindings
+    }
+
+    pub fn set_midi_bindings(&mut self, midi_bindings: Vec<MidiBinding>) -> Result<(), RuxError> {
+        if self.midi_bindings == midi_bindings {
+            Ok(())
+        } else {
+            self.midi_bindings = midi_bindings;
+            self.save_config()
+        }
+    }
+
+    pub const fn get_global_hotkeys(&self) -> bool {
+        self.global_hotkeys
+    }
+
+    pub fn set_global_hotkeys(&mut self, global_hotkeys: bool) -> Result<(), RuxError> {
+        if self.global_hotkeys == global_hotkeys {
+            Ok(())
+        } else {
+            self.global_hotkeys = global_hotkeys;
+            self.save_config()
+        }
+    }
+
+    pub const fn get_hide_dynamics(&self) -> bool {
+        self.hide_dynamics
+    }
+
+    pub fn set_hide_dynamics(&mut self, hide_dynamics: bool) -> Result<(), RuxError> {
+        if self.hide_dynamics == hide_dynamics {
+            Ok(())
+        } else {
+            self.hide_dynamics = hide_dynamics;
+            self.save_config()
+        }
+    }
+
+    pub const fn get_hide_directions(&self) -> bool {
+        self.hide_directions
+    }
+
+    pub fn set_hide_directions(&mut self, hide_directions: bool) -> Result<(), RuxError> {
+        if self.hide_directions == hide_directions {
+            Ok(())
+        } else {
+            self.hide_directions = hide_directions;
+            self.save_config()
+        }
+    }
+
+    pub const fn get_show_fretboard(&self) -> bool {
+        self.show_fretboard
+    }
+
+    pub fn set_show_fretboard(&mut self, show_fretboard: bool) -> Result<(), RuxError> {
+        if self.show_fretboard == show_fretboard {
+            Ok(())
+        } else {
+            self.show_fretboard = show_fretboard;
+            self.save_config()
+        }
+    }
+
+    pub const fn get_chord_suggestions(&self) -> bool {
+        self.chord_suggestions
+    }
+
+    pub fn set_chord_suggestions(&mut self, chord_suggestions: bool) -> Result<(), RuxError> {
+        if self.chord_suggestions == chord_suggestions {
+            Ok(())
+        } else {
+            self.chord_suggestions = chord_suggestions;
+            self.save_config()
+        }
+    }
+
+    pub const fn get_scale_highlight(&self) -> bool {
+        self.scale_highlight
+    }
+
+    pub fn set_scale_highlight(&mut self, scale_highlight: bool) -> Result<(), RuxError> {
+        if self.scale_highlight == scale_highlight {
+            Ok(())
+        } else {
+            self.scale_highlight = scale_highlight;
+            self.save_config()
+        }
+    }
+
+    pub const fn get_note_names(&self) -> bool {
+        self.note_names
+    }
+
+    pub fn set_note_names(&mut self, note_names: bool) -> Result<(), RuxError> {
+        if self.note_names == note_names {
+            Ok(())
+        } else {
+            self.note_names = note_names;
+            self.save_config()
+        }
+    }
+
+    pub const fn get_instant_scroll(&self) -> bool {
+        self.instant_scroll
+    }
+
+    pub fn set_instant_scroll(&mut self, instant_scroll: bool) -> Result<(), RuxError> {
+        if self.instant_scroll == instant_scroll {
+            Ok(())
+        } else {
+            self.instant_scroll = instant_scroll;
+            self.save_config()
         }
-        let config_path = Self::get_path()?;
+    }
+
+    pub const fn get_low_power(&self) -> bool {
+        self.low_power
+    }
+
+    pub fn set_low_power(&mut self, low_power: bool) -> Result<(), RuxError> {
+        if self.low_power == low_power {
+            Ok(())
+        } else {
+            self.low_power = low_power;
+            self.save_config()
+        }
+    }
+
+    pub const fn get_difficulty_heatmap(&self) -> bool {
+        self.difficulty_heatmap
+    }
+
+    pub fn set_difficulty_heatmap(&mut self, difficulty_heatmap: bool) -> Result<(), RuxError> {
+        if self.difficulty_heatmap == difficulty_heatmap {
+            Ok(())
+        } else {
+            self.difficulty_heatmap = difficulty_heatmap;
+            self.save_config()
+        }
+    }
+
+    pub const fn get_mono_output(&self) -> bool {
+        self.mono_output
+    }
+
+    pub fn set_mono_output(&mut self, mono_output: bool) -> Result<(), RuxError> {
+        if self.mono_output == mono_output {
+            Ok(())
+        } else {
+            self.mono_output = mono_output;
+            self.save_config()
+        }
+    }
+
+    pub const fn get_multiple_instances(&self) -> bool {
+        self.multiple_instances
+    }
+
+    pub fn get_audio_backend(&self) -> Option<String> {
+        self.audio_backend.clone()
+    }
+
+    pub fn set_audio_backend(&mut self, audio_backend: Option<String>) -> Result<(), RuxError> {
+        if self.audio_backend == audio_backend {
+            Ok(())
+        } else {
+            self.audio_backend = audio_backend;
+            self.save_config()
+        }
+    }
+
+    pub fn get_preset_remaps(&self, sound_font: &str) -> PresetRemaps {
+        self.preset_remaps
+            .get(sound_font)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    pub fn set_preset_remaps(
+        &mut self,
+        sound_font: &str,
+        preset_remaps: PresetRemaps,
+    ) -> Result<(), RuxError> {
+        if self.get_preset_remaps(sound_font) == preset_remaps {
+            Ok(())
+        } else if preset_remaps.is_empty() {
+            self.preset_remaps.remove(sound_font);
+            self.save_config()
+        } else {
+            self.preset_remaps
+                .insert(sound_font.to_string(), preset_remaps);
+            self.save_config()
+        }
+    }
+
+    pub fn get_track_layout(&self, file_key: &str) -> TrackLayout {
+        self.track_layouts
+            .get(file_key)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    pub fn set_track_layout(
+        &mut self,
+        file_key: &str,
+        track_layout: TrackLayout,
+    ) -> Result<(), RuxError> {
+        if self.track_layouts.get(file_key) == Some(&track_layout) {
+            Ok(())
+        } else {
+            self.track_layouts
+                .insert(file_key.to_string(), track_layout);
+            self.save_config()
+        }
+    }
+
+    pub fn get_master_volume(&self) -> f32 {
+        self.master_volume.unwrap_or(1.0)
+    }
+
+    pub fn set_master_volume(&mut self, master_volume: f32) -> Result<(), RuxError> {
+        if self.master_volume == Some(master_volume) {
+            Ok(())
+        } else {
+            self.master_volume = Some(master_volume);
+            self.save_config()
+        }
+    }
+
+    pub fn get_sound_fonts(&self) -> &[PathBuf] {
+        &self.sound_fonts
+    }
+
+    pub fn add_sound_font(&mut self, sound_font: PathBuf) -> Result<(), RuxError> {
+        if self.sound_fonts.contains(&sound_font) {
+            Ok(())
+        } else {
+            self.sound_fonts.push(sound_font);
+            self.save_config()
+        }
+    }
+
+    pub fn get_track_sound_fonts(&self, file_key: &str) -> BTreeMap<usize, PathBuf> {
+        self.track_sound_fonts
+            .get(file_key)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    pub fn set_track_sound_fonts(
+        &mut self,
+        file_key: &str,
+        track_sound_fonts: BTreeMap<usize, PathBuf>,
+    ) -> Result<(), RuxError> {
+        if self.get_track_sound_fonts(file_key) == track_sound_fonts {
+            Ok(())
+        } else if track_sound_fonts.is_empty() {
+            self.track_sound_fonts.remove(file_key);
+            self.save_config()
+        } else {
+            self.track_sound_fonts
+                .insert(file_key.to_string(), track_sound_fonts);
+            self.save_config()
+        }
+    }
+
+    pub fn get_file_session(&self, file_key: &str) -> Option<FileSession> {
+        self.file_sessions.get(file_key).copied()
+    }
+
+    pub fn set_file_session(
+        &mut self,
+        file_key: &str,
+        file_session: FileSession,
+    ) -> Result<(), RuxError> {
+        if self.file_sessions.get(file_key) == Some(&file_session) {
+            Ok(())
+        } else {
+            self.file_sessions
+                .insert(file_key.to_string(), file_session);
+            self.save_config()
+        }
+    }
+
+    pub fn set_tabs_folder(&mut self, new_tabs_folder: Option<PathBuf>) -> Result<(), RuxError> {
+        if self.tabs_folder == new_tabs_folder {
+            // no op
+            Ok(())
+        } else {
+            self.tabs_folder = new_tabs_folder;
+            self.save_config()
+        }
+    }
+
+    /// Creates config if it does not exist
+    pub fn read_config(paths: AppPaths) -> Result<Self, RuxError> {
+        create_dir_all(paths.config_dir())?;
+        create_dir_all(paths.sound_fonts_dir())?;
+        let config_path = paths.config_file();
+        let default_config = Self {
+            paths: Some(paths),
+            ..Self::default()
+        };
         if !config_path.exists() {
             // create empty config
-            Config::default().save_config()?;
+            default_config.save_config()?;
         }
         let file = File::open(&config_path)?;
         let reader = BufReader::new(file);
-        match serde_json::from_reader(reader) {
-            Ok(config) => Ok(config),
+        match serde_json::from_reader::<_, Self>(reader) {
+            Ok(config) => Ok(Self {
+                paths: default_config.paths,
+                ..config
+            }),
             Err(err) => {
                 log::warn!(
                     "Could not read local configuration {}: {err}, resetting to default",
                     config_path.display()
                 );
-                let default_config = Config::default();
                 default_config.save_config()?;
                 Ok(default_config)
             }
         }
     }
 
+    /// Folder offered first when adding sound fonts.
+    pub fn get_sound_fonts_dir(&self) -> Option<PathBuf> {
+        self.paths.as_ref().map(AppPaths::sound_fonts_dir)
+    }
+
+    /// Port file of the running instance.
+    pub fn get_instance_file(&self) -> Option<PathBuf> {
+        self.paths.as_ref().map(AppPaths::instance_file)
+    }
+
     /// Assumes the config folder exists
     pub fn save_config(&self) -> Result<(), RuxError> {
-        let config_path = Self::get_path()?;
+        let config_path = self
+            .paths
+            .as_ref()
+            .map(AppPaths::config_file)
+            .ok_or_else(|| RuxError::ConfigError("Configuration folder not set".to_string()))?;
         let json = serde_json::to_string_pretty(self).map_err(|err| {
             RuxError::ConfigError(format!("Could not save local configuration {err:}"))
         })?;
@@ -82,3 +520,40 @@ impl Config {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_named_files_keep_their_own_session() {
+        let dir = std::env::temp_dir().join(format!("ruxguitar-config-{}", std::process::id()));
+        let (first, second) = (dir.join("first"), dir.join("second"));
+        for folder in [&first, &second] {
+            create_dir_all(folder).unwrap();
+            std::fs::write(folder.join("song.gp5"), b"same content").unwrap();
+        }
+        let first_key = file_key(Some(&first.join("song.gp5")), b"same content");
+        let second_key = file_key(Some(&second.join("song.gp5")), b"same content");
+        // the same file reached through another path
+        let relative = first.join("..").join("first").join("song.gp5");
+        assert_eq!(file_key(Some(&relative), b"same content"), first_key);
+
+        let paths = AppPaths::portable(dir.join("config"));
+        let mut config = Config::read_config(paths.clone()).unwrap();
+        let session = |track| FileSession {
+            track,
+            ..FileSession::default()
+        };
+        config.set_file_session(&first_key, session(1)).unwrap();
+        config.set_file_session(&second_key, session(2)).unwrap();
+        let config = Config::read_config(paths).unwrap();
+        assert_eq!(config.get_file_session(&first_key), Some(session(1)));
+        assert_eq!(config.get_file_session(&second_key), Some(session(2)));
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        // content without a file is told apart by its hash
+        assert_eq!(file_key(None, b"tab"), file_key(None, b"tab"));
+        assert_ne!(file_key(None, b"tab"), file_key(None, b"other tab"));
+    }
+}
@@ -0,0 +1,116 @@
+//! Undo and redo stacks of the versions of an edited value, with the label of each edit.
+
+/// Versions before and after the edits, the oldest are dropped past the limit.
+#[derive(Debug)]
+pub(super) struct History<T> {
+    limit: usize,
+    undo: Vec<(T, String)>, // versions before the edits, most recent last
+    redo: Vec<(T, String)>, // versions undone, most recent last
+}
+
+impl<T> History<T> {
+    pub(super) const fn new(limit: usize) -> Self {
+        Self {
+            limit,
+            undo: Vec::new(),
+            redo: Vec::new(),
+        }
+    }
+
+    /// Record the version before an edit, the undone edits cannot be redone anymore.
+    pub(super) fn record(&mut self, previous: T, label: String) {
+        if self.undo.len() == self.limit {
+            self.undo.remove(0);
+        }
+        self.undo.push((previous, label));
+        self.redo.clear();
+    }
+
+    /// Merge an edit into the last recorded one, undoing it goes back before both.
+    pub(super) fn amend(&mut self, label: String) -> bool {
+        let Some((_, last_label)) = self.undo.last_mut() else {
+            return false;
+        };
+        *last_label = label;
+        self.redo.clear();
+        true
+    }
+
+    /// Version before the last edit, `current` can be restored with `redo`.
+    pub(super) fn undo(&mut self, current: T) -> Result<T, T> {
+        let Some((previous, label)) = self.undo.pop() else {
+            return Err(current);
+        };
+        self.redo.push((current, label));
+        Ok(previous)
+    }
+
+    /// Version after the last undone edit, `current` can be restored with `undo`.
+    pub(super) fn redo(&mut self, current: T) -> Result<T, T> {
+        let Some((next, label)) = self.redo.pop() else {
+            return Err(current);
+        };
+        self.undo.push((current, label));
+        Ok(next)
+    }
+
+    /// Label of the edit `undo` reverts.
+    pub(super) fn undo_label(&self) -> Option<&str> {
+        self.undo.last().map(|(_, label)| label.as_str())
+    }
+
+    /// Label of the edit `redo` applies again.
+    pub(super) fn redo_label(&self) -> Option<&str> {
+        self.redo.last().map(|(_, label)| label.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn undo_and_redo_in_order() {
+        let mut history = History::new(10);
+        let mut value = 0;
+        for next in 1..=3 {
+            history.record(value, format!("set {next}"));
+            value = next;
+        }
+        assert_eq!(history.undo_label(), Some("set 3"));
+        value = history.undo(value).unwrap();
+        value = history.undo(value).unwrap();
+        assert_eq!(value, 1);
+        assert_eq!(history.redo_label(), Some("set 2"));
+        value = history.redo(value).unwrap();
+        assert_eq!(value, 2);
+
+        // a new edit drops the undone ones
+        history.record(value, "set 5".to_string());
+        value = 5;
+        assert_eq!(history.redo(value), Err(5));
+        assert_eq!(history.undo(value), Ok(2));
+    }
+
+    #[test]
+    fn amend_merges_with_the_last_edit() {
+        let mut history = History::new(10);
+        assert!(!history.amend("nothing".to_string()));
+        history.record(0, "set 1".to_string());
+        assert!(history.amend("set 12".to_string()));
+        assert_eq!(history.undo_label(), Some("set 12"));
+        assert_eq!(history.undo(12), Ok(0));
+        assert_eq!(history.undo(0), Err(0));
+    }
+
+    #[test]
+    fn oldest_versions_dropped_past_the_limit() {
+        let mut history = History::new(2);
+        for value in 0..5 {
+            history.record(value, format!("from {value}"));
+        }
+        assert_eq!(history.undo(5), Ok(4));
+        assert_eq!(history.undo(4), Ok(3));
+        assert_eq!(history.undo(3), Err(3));
+    }
+}
@@ -0,0 +1,388 @@
+//! Small corrections of the loaded song, e.g. fixing the effects of a badly transcribed note.
+//! Edits are applied to the song of a document, the tablature and the MIDI events are then
+//! rebuilt from it.
+
+mod history;
+
+use crate::editor::history::History;
+use crate::parser::song_parser::{Beat, Note, NoteEffect, NoteType, Song};
+use std::fmt::Display;
+use std::sync::Arc;
+
+/// Note of the first voice (the one displayed in the tablature) on a string of a beat.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NoteLocation {
+    pub track: usize,
+    pub measure: usize,
+    pub beat: usize,
+    pub string: i8,
+}
+
+/// Per-note flags that can be toggled from the tablature.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NoteFlag {
+    PalmMute,
+    LetRing,
+    Ghost,
+}
+
+impl NoteFlag {
+    pub const ALL: [Self; 3] = [Self::PalmMute, Self::LetRing, Self::Ghost];
+
+    pub const fn is_set(self, note: &Note) -> bool {
+        match self {
+            Self::PalmMute => note.effect.palm_mute,
+            Self::LetRing => note.effect.let_ring,
+            Self::Ghost => note.effect.ghost_note,
+        }
+    }
+
+    const fn toggle(self, note: &mut Note) {
+        let flag = match self {
+            Self::PalmMute => &mut note.effect.palm_mute,
+            Self::LetRing => &mut note.effect.let_ring,
+            Self::Ghost => &mut note.effect.ghost_note,
+        };
+        *flag = !*flag;
+    }
+}
+
+impl Display for NoteFlag {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::PalmMute => write!(f, "Palm mute"),
+            Self::LetRing => write!(f, "Let ring"),
+            Self::Ghost => write!(f, "Ghost note"),
+        }
+    }
+}
+
+/// Note of the song at the location, `None` if there is no note on that string.
+pub fn note_at(song: &Song, location: NoteLocation) -> Option<&Note> {
+    song.tracks
+        .get(location.track)?
+        .measures
+        .get(location.measure)?
+        .voices
+        .first()?
+        .beats
+        .get(location.beat)?
+        .notes
+        .iter()
+        .find(|note| note.string == location.string)
+}
+
+/// Beat of the location if its string exists on the track.
+fn beat_at_mut(song: &mut Song, location: NoteLocation) -> Option<&mut Beat> {
+    let track = song.tracks.get_mut(location.track)?;
+    let string_count = track.strings.len();
+    if !(1..=string_count).contains(&usize::try_from(location.string).ok()?) {
+        return None;
+    }
+    track
+        .measures
+        .get_mut(location.measure)?
+        .voices
+        .first_mut()?
+        .beats
+        .get_mut(location.beat)
+}
+
+fn note_at_mut(song: &mut Song, location: NoteLocation) -> Option<&mut Note> {
+    beat_at_mut(song, location)?
+        .notes
+        .iter_mut()
+        .find(|note| note.string == location.string)
+}
+
+/// Highest fret that can be entered on the track.
+pub fn max_fret(song: &Song, track: usize) -> u8 {
+    song.tracks.get(track).map_or(0, |track| track.fret_count)
+}
+
+/// Number of undoable edits kept.
+const HISTORY_LIMIT: usize = 100;
+
+/// Change of the song, every editing action goes through one to be undoable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Edit {
+    /// Toggle a flag of the note.
+    ToggleNoteFlag(NoteLocation, NoteFlag),
+    /// Play the fret on the string, replacing the note already there.
+    SetNoteFret(NoteLocation, u8),
+    /// Remove the note on the string, the beat becomes a rest without notes left.
+    DeleteNote(NoteLocation),
+}
+
+impl Edit {
+    /// Short description of the edit, e.g. for the undo button.
+    pub fn label(&self) -> String {
+        match self {
+            Self::ToggleNoteFlag(_, flag) => flag.to_string().to_lowercase(),
+            Self::SetNoteFret(_, fret) => format!("fret {fret}"),
+            Self::DeleteNote(_) => "note deletion".to_string(),
+        }
+    }
+
+    /// Apply the edit to the song, `false` if it does not apply (no note, fret out of range...).
+    /// The song may be partially changed in that case.
+    fn apply(self, song: &mut Song) -> bool {
+        match self {
+            Self::ToggleNoteFlag(location, flag) => note_at_mut(song, location)
+                .map(|note| flag.toggle(note))
+                .is_some(),
+            Self::SetNoteFret(location, fret) => {
+                if fret > max_fret(song, location.track) {
+                    return false;
+                }
+                let Some(beat) = beat_at_mut(song, location) else {
+                    return false;
+                };
+                if let Some(note) = beat.notes.iter_mut().find(|n| n.string == location.string) {
+                    note.value = i16::from(fret);
+                    note.kind = NoteType::Normal;
+                    return true;
+                }
+                // as loud as the rest of the chord
+                let mut note = Note::new(NoteEffect::default());
+                if let Some(other) = beat.notes.first() {
+                    note.velocity = other.velocity;
+                }
+                note.string = location.string;
+                note.value = i16::from(fret);
+                note.kind = NoteType::Normal;
+                let index = beat.notes.partition_point(|n| n.string < location.string);
+                beat.notes.insert(index, note);
+                // an empty beat takes its time once it plays a note
+                beat.empty = false;
+                true
+            }
+            Self::DeleteNote(location) => {
+                let Some(beat) = beat_at_mut(song, location) else {
+                    return false;
+                };
+                let count = beat.notes.len();
+                beat.notes.retain(|note| note.string != location.string);
+                beat.notes.len() != count
+            }
+        }
+    }
+}
+
+/// Song being edited, shared with the tablature and the player between the edits.
+/// Each edit works on a copy of the song, the previous versions are kept to undo it.
+#[derive(Debug)]
+pub struct Document {
+    song: Arc<Song>,
+    saved: Arc<Song>, // version opened or last saved
+    history: History<Arc<Song>>,
+}
+
+impl Document {
+    pub fn new(song: Arc<Song>) -> Self {
+        Self {
+            saved: song.clone(),
+            song,
+            history: History::new(HISTORY_LIMIT),
+        }
+    }
+
+    pub const fn song(&self) -> &Arc<Song> {
+        &self.song
+    }
+
+    /// Whether the song differs from the version saved, undoing every edit makes it clean again.
+    pub fn is_dirty(&self) -> bool {
+        !Arc::ptr_eq(&self.song, &self.saved)
+    }
+
+    /// Record the version written to the file, edits made since keep the song dirty.
+    pub fn mark_saved(&mut self, song: &Arc<Song>) {
+        self.saved = song.clone();
+    }
+
+    /// Label of the edit undo reverts, `None` if there is nothing to undo.
+    pub fn undo_label(&self) -> Option<&str> {
+        self.history.undo_label()
+    }
+
+    /// Label of the edit redo applies again, `None` if there is nothing to redo.
+    pub fn redo_label(&self) -> Option<&str> {
+        self.history.redo_label()
+    }
+
+    /// Apply the edit to a copy of the song, `false` leaves the song as it was.
+    /// An amending edit is undone along with the previous one, e.g. the second digit of a fret.
+    pub fn apply(&mut self, edit: Edit, amend: bool) -> bool {
+        let previous = self.song.clone();
+        // still shared with the previous version: the song is copied
+        if !edit.apply(Arc::make_mut(&mut self.song)) {
+            self.song = previous;
+            return false;
+        }
+        if !(amend && self.history.amend(edit.label())) {
+            self.history.record(previous, edit.label());
+        }
+        true
+    }
+
+    /// Go back to the version before the last edit, `false` if there is none.
+    pub fn undo(&mut self) -> bool {
+        let current = self.song.clone();
+        match self.history.undo(current) {
+            Ok(previous) => {
+                self.song = previous;
+                true
+            }
+            Err(_) => false,
+        }
+    }
+
+    /// Apply the last undone edit again, `false` if there is none.
+    pub fn redo(&mut self) -> bool {
+        let current = self.song.clone();
+        match self.history.redo(current) {
+            Ok(next) => {
+                self.song = next;
+                true
+            }
+            Err(_) => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::song_parser_tests::parse_gp_file;
+
+    fn first_note_location(song: &Song) -> NoteLocation {
+        let beats = &song.tracks[0].measures[0].voices[0].beats;
+        let (beat, note) = beats
+            .iter()
+            .enumerate()
+            .find_map(|(index, beat)| beat.notes.first().map(|note| (index, note)))
+            .unwrap();
+        NoteLocation {
+            track: 0,
+            measure: 0,
+            beat,
+            string: note.string,
+        }
+    }
+
+    #[test]
+    fn toggle_note_flags_on_a_copy() {
+        let song = Arc::new(parse_gp_file("test-files/Demo v5.gp5").unwrap());
+        let location = first_note_location(&song);
+        let mut document = Document::new(song.clone());
+        assert!(!document.is_dirty());
+
+        for flag in NoteFlag::ALL {
+            let before = flag.is_set(note_at(&song, location).unwrap());
+            assert!(document.apply(Edit::ToggleNoteFlag(location, flag), false));
+            assert_eq!(
+                flag.is_set(note_at(document.song(), location).unwrap()),
+                !before
+            );
+            // the shared version stays as it was
+            assert_eq!(flag.is_set(note_at(&song, location).unwrap()), before);
+        }
+        assert!(document.is_dirty());
+
+        for flag in NoteFlag::ALL {
+            document.apply(Edit::ToggleNoteFlag(location, flag), false);
+        }
+        assert_eq!(document.song().as_ref(), song.as_ref());
+    }
+
+    /// First beat of the first measure without a note on the string.
+    fn free_slot(song: &Song) -> NoteLocation {
+        let string_count = song.tracks[0].strings.len() as i8;
+        let beats = &song.tracks[0].measures[0].voices[0].beats;
+        let (beat, string) = beats
+            .iter()
+            .enumerate()
+            .find_map(|(index, beat)| {
+                (1..=string_count)
+                    .find(|s| beat.notes.iter().all(|n| n.string != *s))
+                    .map(|string| (index, string))
+            })
+            .unwrap();
+        NoteLocation {
+            track: 0,
+            measure: 0,
+            beat,
+            string,
+        }
+    }
+
+    #[test]
+    fn insert_and_delete_notes_with_undo() {
+        let song = Arc::new(parse_gp_file("test-files/Demo v5.gp5").unwrap());
+        let location = free_slot(&song);
+        let mut document = Document::new(song.clone());
+
+        assert!(document.apply(Edit::SetNoteFret(location, 5), false));
+        let note = note_at(document.song(), location).unwrap();
+        assert_eq!(note.value, 5);
+        assert_eq!(note.kind, NoteType::Normal);
+        let beat = &document.song().tracks[0].measures[0].voices[0].beats[location.beat];
+        assert!(beat.notes.windows(2).all(|w| w[0].string < w[1].string));
+        assert!(document.is_dirty());
+
+        // the second digit amends the fret
+        assert!(document.apply(Edit::SetNoteFret(location, 12), true));
+        assert_eq!(note_at(document.song(), location).unwrap().value, 12);
+        assert!(!document.apply(Edit::SetNoteFret(location, 200), true));
+
+        assert!(document.apply(Edit::DeleteNote(location), false));
+        assert!(note_at(document.song(), location).is_none());
+        assert!(!document.apply(Edit::DeleteNote(location), false));
+
+        assert!(document.undo());
+        assert_eq!(note_at(document.song(), location).unwrap().value, 12);
+        assert!(document.undo());
+        assert!(note_at(document.song(), location).is_none());
+        assert!(!document.is_dirty());
+        assert!(!document.undo());
+
+        assert!(document.redo());
+        assert_eq!(note_at(document.song(), location).unwrap().value, 12);
+        // a new edit drops the undone ones
+        assert!(document.apply(Edit::SetNoteFret(location, 3), false));
+        assert_eq!(document.redo_label(), None);
+        assert!(!document.redo());
+    }
+
+    #[test]
+    fn history_is_bounded() {
+        let song = Arc::new(parse_gp_file("test-files/Demo v5.gp5").unwrap());
+        let location = free_slot(&song);
+        let mut document = Document::new(song);
+        for fret in 0..=HISTORY_LIMIT {
+            assert!(document.apply(Edit::SetNoteFret(location, (fret % 20) as u8), false));
+        }
+        let mut undone = 0;
+        while document.undo() {
+            undone += 1;
+        }
+        assert_eq!(undone, HISTORY_LIMIT);
+        // the opened version is out of reach
+        assert!(document.is_dirty());
+    }
+
+    #[test]
+    fn no_toggle_without_a_note() {
+        let song = Arc::new(parse_gp_file("test-files/Demo v5.gp5").unwrap());
+        let mut document = Document::new(song);
+        let location = NoteLocation {
+            track: 0,
+            measure: 0,
+            beat: 0,
+            string: 42,
+        };
+        assert!(!document.apply(Edit::ToggleNoteFlag(location, NoteFlag::PalmMute), false));
+        assert!(!document.is_dirty());
+    }
+}
@@ -0,0 +1,341 @@
+//! ABC notation export, the text format of many tune collections.
+//!
+//! Each track becomes an ABC voice holding the first voice of its measures in standard
+//! notation: pitches, durations, ties, tuplets, bars with repeats and endings, key, time and
+//! tempo changes, markers and beat texts, plus the basic ornaments (grace notes, staccato,
+//! accents, trills and slides).
+
+use crate::analysis::is_percussion;
+use crate::export::{
+    LETTERS, exported_tracks, first_voice_beats, gcd, is_tied, key_accidentals, key_name,
+    next_beat, spell, time_signature, written_pitch,
+};
+use crate::parser::song_parser::{Beat, Duration, MeasureHeader, Note, QUARTER_TIME, Song, Track};
+use std::collections::HashMap;
+use std::fmt::Write;
+
+/// Note lengths are written in eighth notes (`L:1/8`).
+const UNIT_LENGTH: u32 = QUARTER_TIME / 2;
+
+const MEASURES_PER_LINE: usize = 4;
+
+/// Converts the track into an ABC tune, every track but the drums if `None`.
+pub fn export_abc(song: &Song, track_id: Option<usize>) -> String {
+    let first_header = song.measure_headers.first().cloned().unwrap_or_default();
+    let mut abc = String::new();
+    abc.push_str("X:1\n");
+    let title = song.song_info.name.trim();
+    let title = if title.is_empty() { "Untitled" } else { title };
+    writeln!(abc, "T:{title}").unwrap();
+    for (field, value) in [
+        ("T", &song.song_info.subtitle),
+        ("C", &song.song_info.artist),
+    ] {
+        if !value.trim().is_empty() {
+            writeln!(abc, "{field}:{}", value.trim()).unwrap();
+        }
+    }
+    writeln!(abc, "M:{}", time_signature(&first_header)).unwrap();
+    abc.push_str("L:1/8\n");
+    writeln!(abc, "Q:1/4={}", first_header.tempo.value).unwrap();
+    writeln!(abc, "K:{}", key_name(&first_header.key_signature)).unwrap();
+
+    for (voice, track) in exported_tracks(song, track_id).into_iter().enumerate() {
+        let name = track.name.trim().replace('"', "'");
+        writeln!(abc, "V:{} name=\"{name}\"", voice + 1).unwrap();
+        VoiceWriter::new(song, track).write(&mut abc);
+    }
+    abc
+}
+
+/// Length in eighth notes: `""` for one, `2`, `/` for a half, `3/2`...
+fn length(ticks: u32) -> String {
+    let divisor = gcd(ticks, UNIT_LENGTH);
+    match (ticks / divisor, UNIT_LENGTH / divisor) {
+        (1, 1) => String::new(),
+        (numerator, 1) => numerator.to_string(),
+        (1, 2) => "/".to_string(),
+        (1, denominator) => format!("/{denominator}"),
+        (numerator, denominator) => format!("{numerator}/{denominator}"),
+    }
+}
+
+/// Written length of the duration, tuplets are written with their notated value.
+fn written_ticks(duration: &Duration) -> u32 {
+    Duration {
+        tuplet_enters: 1,
+        tuplet_times: 1,
+        ..duration.clone()
+    }
+    .time()
+}
+
+/// Quoted text displayed above the next note.
+fn annotation(text: &str) -> String {
+    format!("\"^{}\"", text.trim().replace('"', "'"))
+}
+
+/// Writes the measures of a track, keeping the accidentals in effect until the end of the bar.
+struct VoiceWriter<'a> {
+    song: &'a Song,
+    track: &'a Track,
+    percussion: bool,
+    key: [i32; 7],
+    bar_accidentals: HashMap<(usize, i32), i32>, // by letter and octave
+    tuplet_left: u8,                             // notes left in the current tuplet
+}
+
+impl<'a> VoiceWriter<'a> {
+    fn new(song: &'a Song, track: &'a Track) -> Self {
+        let key = song
+            .measure_headers
+            .first()
+            .map(|header| key_accidentals(&header.key_signature))
+            .unwrap_or_default();
+        Self {
+            song,
+            track,
+            percussion: is_percussion(song, track),
+            key,
+            bar_accidentals: HashMap::new(),
+            tuplet_left: 0,
+        }
+    }
+
+    /// Drum notes are written at the pitch of their sound.
+    fn pitch(&self, note: &Note) -> Option<i32> {
+        written_pitch(self.track, self.percussion, note)
+    }
+
+    fn write(&mut self, abc: &mut String) {
+        let headers = &self.song.measure_headers;
+        let beats = first_voice_beats(self.track);
+        for (index, header) in headers.iter().enumerate() {
+            let previous = index.checked_sub(1).map(|i| &headers[i]);
+            if previous.is_none() && header.repeat_open {
+                abc.push_str("|: ");
+            }
+            if header.repeat_alternative != 0
+                && previous.is_none_or(|p| p.repeat_alternative != header.repeat_alternative)
+            {
+                let endings: Vec<String> = (0..8)
+                    .filter(|ending| header.repeat_alternative & (1 << ending) != 0)
+                    .map(|ending| (ending + 1).to_string())
+                    .collect();
+                write!(abc, "[{} ", endings.join(",")).unwrap();
+            }
+            if let Some(previous) = previous {
+                self.write_changes(abc, previous, header);
+            }
+            self.bar_accidentals.clear();
+            let mut annotations: Vec<String> = header
+                .marker
+                .iter()
+                .map(|marker| annotation(&marker.title))
+                .collect();
+            let measure_beats = beats.get(index).map_or(&[][..], Vec::as_slice);
+            if measure_beats.is_empty() {
+                write!(abc, "{}z{} ", annotations.concat(), length(header.length())).unwrap();
+            }
+            for (beat_index, beat) in measure_beats.iter().enumerate() {
+                let next = next_beat(&beats, index, beat_index);
+                if !beat.text.trim().is_empty() {
+                    annotations.push(annotation(&beat.text));
+                }
+                self.write_beat(abc, &annotations.concat(), beat, next);
+                annotations.clear();
+            }
+            let next = headers.get(index + 1);
+            let bar = match (header.repeat_close > 0, next.map(|n| n.repeat_open)) {
+                (true, None) => ":|",
+                (false, None) => "|]",
+                (true, Some(true)) => "::",
+                (true, Some(false)) => ":|",
+                (false, Some(true)) => "|:",
+                (false, Some(false)) => "|",
+            };
+            abc.push_str(bar);
+            let line_end = (index + 1) % MEASURES_PER_LINE == 0 || next.is_none();
+            abc.push(if line_end { '\n' } else { ' ' });
+        }
+    }
+
+    /// Inline fields for the time signature, key and tempo changing at the measure.
+    fn write_changes(
+        &mut self,
+        abc: &mut String,
+        previous: &MeasureHeader,
+        header: &MeasureHeader,
+    ) {
+        if time_signature(previous) != time_signature(header) {
+            write!(abc, "[M:{}] ", time_signature(header)).unwrap();
+        }
+        if previous.key_signature != header.key_signature {
+            write!(abc, "[K:{}] ", key_name(&header.key_signature)).unwrap();
+            self.key = key_accidentals(&header.key_signature);
+        }
+        if previous.tempo.value != header.tempo.value {
+            write!(abc, "[Q:1/4={}] ", header.tempo.value).unwrap();
+        }
+    }
+
+    /// Note with the accidental needed against the key and the previous notes of the bar.
+    fn note(&mut self, pitch: i32) -> String {
+        let (letter, accidental) = spell(pitch, &self.key);
+        let octave = (pitch - accidental).div_euclid(12) - 1;
+        let mut note = String::new();
+        let current = self
+            .bar_accidentals
+            .get(&(letter, octave))
+            .copied()
+            .unwrap_or(self.key[letter]);
+        if current != accidental {
+            note.push_str(match accidental {
+                2 => "^^",
+                1 => "^",
+                -1 => "_",
+                -2 => "__",
+                _ => "=",
+            });
+            self.bar_accidentals.insert((letter, octave), accidental);
+        }
+        // middle C is `C`, the octave above is lower case
+        if octave >= 5 {
+            note.push(LETTERS[letter].to_ascii_lowercase());
+            note.push_str(&"'".repeat((octave - 5) as usize));
+        } else {
+            note.push(LETTERS[letter]);
+            note.push_str(&",".repeat((4 - octave).max(0) as usize));
+        }
+        note
+    }
+
+    fn write_beat(
+        &mut self,
+        abc: &mut String,
+        annotations: &str,
+        beat: &Beat,
+        next: Option<&Beat>,
+    ) {
+        let duration = &beat.duration;
+        if duration.tuplet_enters == duration.tuplet_times {
+            self.tuplet_left = 0;
+        } else if self.tuplet_left == 0 {
+            if (duration.tuplet_enters, duration.tuplet_times) == (3, 2) {
+                abc.push_str("(3");
+            } else {
+                write!(abc, "({}:{}", duration.tuplet_enters, duration.tuplet_times).unwrap();
+            }
+            self.tuplet_left = duration.tuplet_enters;
+        }
+        self.tuplet_left = self.tuplet_left.saturating_sub(1);
+        abc.push_str(annotations);
+
+        let mut notes: Vec<(i32, &Note)> = beat
+            .notes
+            .iter()
+            .filter_map(|note| self.pitch(note).map(|pitch| (pitch, note)))
+            .collect();
+        notes.sort_by_key(|(pitch, _)| *pitch);
+        let length = length(written_ticks(duration));
+        if notes.is_empty() {
+            write!(abc, "z{length} ").unwrap();
+            return;
+        }
+
+        let grace = notes.iter().find_map(|(_, note)| {
+            let grace = note.effect.grace.as_ref().filter(|grace| !grace.is_dead)?;
+            let mut grace_note = Note::clone(note);
+            grace_note.value = i16::from(grace.fret);
+            self.pitch(&grace_note)
+        });
+        if let Some(pitch) = grace {
+            let grace_note = self.note(pitch);
+            write!(abc, "{{{grace_note}}}").unwrap();
+        }
+        let effects = notes.iter().map(|(_, note)| &note.effect);
+        if effects.clone().any(|effect| effect.staccato) {
+            abc.push('.');
+        }
+        if effects
+            .clone()
+            .any(|effect| effect.accentuated_note || effect.heavy_accentuated_note)
+        {
+            abc.push_str("!>!");
+        }
+        if effects.clone().any(|effect| effect.trill.is_some()) {
+            abc.push('T');
+        }
+        if effects.clone().any(|effect| effect.slide.is_some()) {
+            abc.push_str("!slide!");
+        }
+
+        let written: Vec<String> = notes.iter().map(|(pitch, _)| self.note(*pitch)).collect();
+        if written.len() == 1 {
+            abc.push_str(&written[0]);
+        } else {
+            write!(abc, "[{}]", written.concat()).unwrap();
+        }
+        abc.push_str(&length);
+        if is_tied(next) {
+            abc.push('-');
+        }
+        abc.push(' ');
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::song_parser::KeySignature;
+    use crate::parser::song_parser_tests::parse_gp_file;
+
+    #[test]
+    fn notes_and_lengths() {
+        let song = Song::default();
+        let track = Track::default();
+        let mut writer = VoiceWriter::new(&song, &track);
+        writer.key = key_accidentals(&KeySignature::new(1, false));
+        assert_eq!(writer.note(60), "C");
+        assert_eq!(writer.note(72), "c");
+        assert_eq!(writer.note(86), "d'");
+        assert_eq!(writer.note(43), "G,,");
+        // the key has F#, a natural stays in effect until the end of the bar
+        assert_eq!(writer.note(65), "=F");
+        assert_eq!(writer.note(65), "F");
+        assert_eq!(writer.note(66), "^F");
+
+        assert_eq!(length(480), "");
+        assert_eq!(length(1920), "4");
+        assert_eq!(length(240), "/");
+        assert_eq!(length(120), "/4");
+        assert_eq!(length(720), "3/2");
+    }
+
+    #[test]
+    fn export_demo_song() {
+        let song = parse_gp_file("test-files/Demo v5.gp5").unwrap();
+        let abc = export_abc(&song, Some(0));
+        let mut lines = abc.lines();
+        assert_eq!(lines.next(), Some("X:1"));
+        assert!(abc.contains("\nL:1/8\n"));
+        assert!(abc.contains("\nQ:1/4=165\n"));
+        assert_eq!(abc.matches("\nV:").count(), 1);
+        assert!(abc.contains("\"^Intro\""));
+        assert!(abc.trim_end().ends_with("|]") || abc.trim_end().ends_with(":|"));
+        // four measures per line
+        let body_lines = abc
+            .lines()
+            .skip_while(|line| !line.starts_with("V:"))
+            .skip(1);
+        assert_eq!(body_lines.count(), song.measure_headers.len().div_ceil(4));
+
+        let all = export_abc(&song, None);
+        let pitched = song
+            .tracks
+            .iter()
+            .filter(|track| !is_percussion(&song, track))
+            .count();
+        assert_eq!(all.matches("\nV:").count(), pitched);
+    }
+}
@@ -0,0 +1,402 @@
+//! LilyPond export, to engrave print quality scores.
+//!
+//! Each track gets a standard notation staff above a tablature staff tuned like the track,
+//! both reading the first voice of its measures. The notes keep their string so that the
+//! tablature shows the frets of the song. The measure structure (time signatures, keys,
+//! tempos, markers, repeats and endings) lives in a `global` variable shared by the staves.
+
+use crate::analysis::is_percussion;
+use crate::export::{
+    LETTERS, exported_tracks, first_voice_beats, is_tied, key_accidentals, next_beat, spell,
+    time_signature, written_pitch,
+};
+use crate::parser::song_parser::{Beat, KeySignature, Note, Song, Track};
+use std::fmt::Write;
+
+const LILYPOND_VERSION: &str = "2.24.0";
+
+// keys from 7 flats to 7 sharps
+const MAJOR_TONICS: [&str; 15] = [
+    "ces", "ges", "des", "aes", "ees", "bes", "f", "c", "g", "d", "a", "e", "b", "fis", "cis",
+];
+const MINOR_TONICS: [&str; 15] = [
+    "aes", "ees", "bes", "f", "c", "g", "d", "a", "e", "b", "fis", "cis", "gis", "dis", "ais",
+];
+
+/// Lowest string below the one of a guitar: the staff uses the bass clef.
+const BASS_CLEF_BELOW: i32 = 40;
+
+/// Converts the track into a LilyPond score, every track but the drums if `None`.
+pub fn export_lilypond(song: &Song, track_id: Option<usize>) -> String {
+    let mut ly = String::new();
+    writeln!(ly, "\\version \"{LILYPOND_VERSION}\"\n").unwrap();
+    ly.push_str("\\header {\n");
+    let title = song.song_info.name.trim();
+    let title = if title.is_empty() { "Untitled" } else { title };
+    writeln!(ly, "  title = {}", string(title)).unwrap();
+    for (field, value) in [
+        ("subtitle", &song.song_info.subtitle),
+        ("composer", &song.song_info.artist),
+    ] {
+        if !value.trim().is_empty() {
+            writeln!(ly, "  {field} = {}", string(value)).unwrap();
+        }
+    }
+    ly.push_str("  tagline = ##f\n}\n\n");
+
+    write_global(&mut ly, song);
+    let tracks = exported_tracks(song, track_id);
+    for (index, track) in tracks.iter().enumerate() {
+        write!(ly, "\n{} = {{\n", variable(index)).unwrap();
+        write_notes(&mut ly, song, track);
+        ly.push_str("}\n");
+    }
+
+    ly.push_str("\n\\score {\n  <<\n");
+    for (index, track) in tracks.iter().enumerate() {
+        let music = variable(index);
+        let name = string(&track.name);
+        if is_percussion(song, track) {
+            writeln!(
+                ly,
+                "    \\new Staff \\with {{ instrumentName = {name} }} << \\global \\{music} >>"
+            )
+            .unwrap();
+            continue;
+        }
+        let lowest = track.strings.iter().map(|(_, tuning)| *tuning).min();
+        let clef = if lowest.is_some_and(|lowest| lowest < BASS_CLEF_BELOW) {
+            "bass_8"
+        } else {
+            "treble_8"
+        };
+        writeln!(
+            ly,
+            "    \\new StaffGroup \\with {{ instrumentName = {name} }} <<\n      \
+             \\new Staff {{ \\clef \"{clef}\" \\omit StringNumber << \\global \\{music} >> }}\n      \
+             \\new TabStaff \\with {{ stringTunings = \\stringTuning {} }} << \\global \\{music} >>\n    \
+             >>",
+            string_tuning(track)
+        )
+        .unwrap();
+    }
+    ly.push_str("  >>\n  \\layout { }\n}\n");
+    ly
+}
+
+/// Quoted LilyPond string.
+fn string(text: &str) -> String {
+    format!(
+        "\"{}\"",
+        text.trim().replace('\\', "\\\\").replace('"', "\\\"")
+    )
+}
+
+/// Name of the music variable of a track, LilyPond names are made of letters only.
+fn variable(index: usize) -> String {
+    let mut letters = Vec::new();
+    let mut index = index;
+    loop {
+        letters.push(char::from(b'A' + (index % 26) as u8));
+        if index < 26 {
+            break;
+        }
+        index = index / 26 - 1;
+    }
+    format!("track{}", letters.iter().rev().collect::<String>())
+}
+
+fn key(key: &KeySignature) -> String {
+    let index = (key.key.clamp(-7, 7) + 7) as usize;
+    if key.is_minor {
+        format!("\\key {} \\minor", MINOR_TONICS[index])
+    } else {
+        format!("\\key {} \\major", MAJOR_TONICS[index])
+    }
+}
+
+/// Absolute pitch name: `c` is the C below middle C, `c'` is middle C.
+fn pitch(pitch: i32, key_accidentals: &[i32; 7]) -> String {
+    let (letter, accidental) = spell(pitch, key_accidentals);
+    let octave = (pitch - accidental).div_euclid(12) - 1;
+    let mut name = LETTERS[letter].to_ascii_lowercase().to_string();
+    name.push_str(match accidental {
+        2 => "isis",
+        1 => "is",
+        -1 => "es",
+        -2 => "eses",
+        _ => "",
+    });
+    let marks = octave - 3;
+    if marks > 0 {
+        name.push_str(&"'".repeat(marks as usize));
+    } else {
+        name.push_str(&",".repeat((-marks) as usize));
+    }
+    name
+}
+
+/// Open strings from the lowest to the highest, the capo included.
+fn string_tuning(track: &Track) -> String {
+    let mut strings = track.strings.clone();
+    strings.sort_by_key(|(number, _)| std::cmp::Reverse(*number));
+    let pitches: Vec<String> = strings
+        .iter()
+        .map(|(_, tuning)| pitch(tuning + track.offset, &[0; 7]))
+        .collect();
+    format!("<{}>", pitches.join(" "))
+}
+
+/// Spacer measures carrying the structure of the song.
+fn write_global(ly: &mut String, song: &Song) {
+    let headers = &song.measure_headers;
+    ly.push_str("global = {\n");
+    let mut volta_open = false;
+    for (index, header) in headers.iter().enumerate() {
+        let previous = index.checked_sub(1).map(|i| &headers[i]);
+        let mut commands = Vec::new();
+        if previous.is_none_or(|p| time_signature(p) != time_signature(header)) {
+            commands.push(format!("\\time {}", time_signature(header)));
+        }
+        if previous.is_none_or(|p| p.key_signature != header.key_signature) {
+            commands.push(key(&header.key_signature));
+        }
+        if previous.is_none_or(|p| p.tempo.value != header.tempo.value) {
+            commands.push(format!("\\tempo 4 = {}", header.tempo.value));
+        }
+        let closes = previous.is_some_and(|p| p.repeat_close > 0);
+        match (closes, header.repeat_open) {
+            (true, true) => commands.push("\\bar \":..:\"".to_string()),
+            (true, false) => commands.push("\\bar \":|.\"".to_string()),
+            (false, true) => commands.push("\\bar \".|:\"".to_string()),
+            (false, false) => {}
+        }
+        let alternative = header.repeat_alternative;
+        if previous.is_none_or(|p| p.repeat_alternative != alternative) {
+            let mut repeat_commands = Vec::new();
+            if volta_open {
+                repeat_commands.push("(volta #f)".to_string());
+            }
+            volta_open = alternative != 0;
+            if volta_open {
+                let endings: Vec<String> = (0..8)
+                    .filter(|ending| alternative & (1 << ending) != 0)
+                    .map(|ending| format!("{}.", ending + 1))
+                    .collect();
+                repeat_commands.push(format!("(volta \"{}\")", endings.join(", ")));
+            }
+            if !repeat_commands.is_empty() {
+                commands.push(format!(
+                    "\\set Score.repeatCommands = #'({})",
+                    repeat_commands.join(" ")
+                ));
+            }
+        }
+        if let Some(marker) = &header.marker {
+            commands.push(format!("\\mark {}", string(&marker.title)));
+        }
+        let spacer = format!("s1*{}", time_signature(header));
+        commands.push(spacer);
+        writeln!(ly, "  {} |", commands.join(" ")).unwrap();
+    }
+    let mut end = Vec::new();
+    if volta_open {
+        end.push("\\set Score.repeatCommands = #'((volta #f))".to_string());
+    }
+    let closes = headers.last().is_some_and(|h| h.repeat_close > 0);
+    end.push(format!("\\bar \"{}\"", if closes { ":|." } else { "|." }));
+    writeln!(ly, "  {}", end.join(" ")).unwrap();
+    ly.push_str("}\n");
+}
+
+/// Notes of the first voice of the track, one measure per line.
+fn write_notes(ly: &mut String, song: &Song, track: &Track) {
+    let percussion = is_percussion(song, track);
+    let beats = first_voice_beats(track);
+    let mut tuplet_left = 0;
+    for (index, header) in song.measure_headers.iter().enumerate() {
+        let key = key_accidentals(&header.key_signature);
+        ly.push(' ');
+        let measure_beats = beats.get(index).map_or(&[][..], Vec::as_slice);
+        if measure_beats.is_empty() {
+            write!(ly, " R1*{}", time_signature(header)).unwrap();
+        }
+        for (beat_index, beat) in measure_beats.iter().enumerate() {
+            let duration = &beat.duration;
+            let in_tuplet = duration.tuplet_enters != duration.tuplet_times;
+            if tuplet_left > 0 && !in_tuplet {
+                ly.push_str(" }");
+                tuplet_left = 0;
+            }
+            if in_tuplet && tuplet_left == 0 {
+                write!(
+                    ly,
+                    " \\tuplet {}/{} {{",
+                    duration.tuplet_enters, duration.tuplet_times
+                )
+                .unwrap();
+                tuplet_left = duration.tuplet_enters;
+            }
+            let next = next_beat(&beats, index, beat_index);
+            ly.push(' ');
+            ly.push_str(&beat_text(track, percussion, &key, beat, next));
+            if in_tuplet {
+                tuplet_left -= 1;
+                if tuplet_left == 0 {
+                    ly.push_str(" }");
+                }
+            }
+        }
+        // tuplets do not cross the bar line
+        if tuplet_left > 0 {
+            ly.push_str(" }");
+            tuplet_left = 0;
+        }
+        writeln!(ly, " | % {}", index + 1).unwrap();
+    }
+}
+
+/// Note, chord or rest of the beat with its duration, ornaments and text.
+fn beat_text(
+    track: &Track,
+    percussion: bool,
+    key: &[i32; 7],
+    beat: &Beat,
+    next: Option<&Beat>,
+) -> String {
+    let mut duration = beat.duration.value.to_string();
+    if beat.duration.dotted {
+        duration.push('.');
+    } else if beat.duration.double_dotted {
+        duration.push_str("..");
+    }
+    let mut notes: Vec<(i32, &Note)> = beat
+        .notes
+        .iter()
+        .filter_map(|note| written_pitch(track, percussion, note).map(|pitch| (pitch, note)))
+        .collect();
+    notes.sort_by_key(|(pitch, _)| *pitch);
+    let mut text = String::new();
+    if notes.is_empty() {
+        write!(text, "r{duration}").unwrap();
+    } else {
+        let grace = notes.iter().find_map(|(_, note)| {
+            let grace = note.effect.grace.as_ref().filter(|grace| !grace.is_dead)?;
+            let mut grace_note = Note::clone(note);
+            grace_note.value = i16::from(grace.fret);
+            let grace_pitch = written_pitch(track, percussion, &grace_note)?;
+            Some(format!(
+                "{}8{}",
+                pitch(grace_pitch, key),
+                string_number(note, percussion)
+            ))
+        });
+        if let Some(grace) = grace {
+            write!(text, "\\grace {grace} ").unwrap();
+        }
+        let written: Vec<String> = notes
+            .iter()
+            .map(|(note_pitch, note)| {
+                format!(
+                    "{}{}",
+                    pitch(*note_pitch, key),
+                    string_number(note, percussion)
+                )
+            })
+            .collect();
+        if let [single] = &written[..] {
+            // the string number follows the duration of a single note
+            let (name, string) = single.split_once('\\').unwrap_or((single, ""));
+            text.push_str(name);
+            text.push_str(&duration);
+            if !string.is_empty() {
+                write!(text, "\\{string}").unwrap();
+            }
+        } else {
+            write!(text, "<{}>{duration}", written.join(" ")).unwrap();
+        }
+        if is_tied(next) {
+            text.push('~');
+        }
+        let effects = notes.iter().map(|(_, note)| &note.effect);
+        if effects.clone().any(|effect| effect.staccato) {
+            text.push_str("-.");
+        }
+        if effects
+            .clone()
+            .any(|effect| effect.accentuated_note || effect.heavy_accentuated_note)
+        {
+            text.push_str("->");
+        }
+        if effects.clone().any(|effect| effect.trill.is_some()) {
+            text.push_str("\\trill");
+        }
+        if effects.clone().any(|effect| effect.palm_mute) {
+            text.push_str("^\"P.M.\"");
+        }
+        if effects.clone().any(|effect| effect.slide.is_some()) {
+            text.push_str("\\glissando");
+        }
+    }
+    if !beat.text.trim().is_empty() {
+        write!(text, "^{}", string(&beat.text)).unwrap();
+    }
+    text
+}
+
+/// String of the note, the tablature shows the fret of the song on it.
+fn string_number(note: &Note, percussion: bool) -> String {
+    if percussion {
+        String::new()
+    } else {
+        format!("\\{}", note.string)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::song_parser_tests::parse_gp_file;
+
+    #[test]
+    fn names() {
+        let d_major = key_accidentals(&KeySignature::new(2, false));
+        assert_eq!(pitch(60, &d_major), "c'");
+        assert_eq!(pitch(66, &d_major), "fis'");
+        assert_eq!(pitch(40, &[0; 7]), "e,");
+        assert_eq!(
+            pitch(63, &key_accidentals(&KeySignature::new(-3, false))),
+            "ees'"
+        );
+        assert_eq!(key(&KeySignature::new(-1, true)), "\\key d \\minor");
+        assert_eq!(variable(0), "trackA");
+        assert_eq!(variable(25), "trackZ");
+        assert_eq!(variable(26), "trackAA");
+        assert_eq!(string("Say \"hi\""), "\"Say \\\"hi\\\"\"");
+    }
+
+    #[test]
+    fn export_demo_song() {
+        let song = parse_gp_file("test-files/Demo v5.gp5").unwrap();
+        let ly = export_lilypond(&song, Some(0));
+        assert!(ly.starts_with("\\version \"2.24.0\""));
+        assert!(ly.contains("  title = \"Demo for Guitar Pro 5\"\n"));
+        assert!(ly.contains("\\time 4/4 \\key c \\major \\tempo 4 = 165"));
+        assert!(ly.contains("\\mark \"Intro\""));
+        assert!(ly.contains("stringTunings = \\stringTuning <e, a, d g b e'>"));
+        assert_eq!(ly.matches("\\new TabStaff").count(), 1);
+        // one line per measure in the music of the track
+        let measures = ly.lines().filter(|line| line.contains(" | % ")).count();
+        assert_eq!(measures, song.measure_headers.len());
+        // braces are balanced
+        assert_eq!(ly.matches('{').count(), ly.matches('}').count());
+
+        let all = export_lilypond(&song, None);
+        let pitched = song
+            .tracks
+            .iter()
+            .filter(|track| !is_percussion(&song, track))
+            .count();
+        assert_eq!(all.matches("\\new TabStaff").count(), pitched);
+    }
+}
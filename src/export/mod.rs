@@ -0,0 +1,182 @@
+//! Text exports of a song into the notation formats of other tools.
+
+use crate::RuxError;
+use crate::analysis::{is_percussion, note_pitch};
+use crate::parser::song_parser::{Beat, KeySignature, MeasureHeader, Note, NoteType, Song, Track};
+
+pub mod abc;
+pub mod lilypond;
+pub mod vextab;
+
+/// Notation formats the song can be exported to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum NotationFormat {
+    #[value(name = "lilypond")]
+    LilyPond,
+    Abc,
+    #[value(name = "vextab")]
+    VexTab,
+}
+
+impl NotationFormat {
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::LilyPond => "LilyPond",
+            Self::Abc => "ABC notation",
+            Self::VexTab => "VexTab",
+        }
+    }
+
+    pub const fn extension(self) -> &'static str {
+        match self {
+            Self::LilyPond => "ly",
+            Self::Abc => "abc",
+            Self::VexTab => "vextab",
+        }
+    }
+
+    /// Converts the track, every track but the drums if `None`.
+    /// The formats without drum notation refuse a drum track.
+    pub fn export(self, song: &Song, track_id: Option<usize>) -> Result<String, RuxError> {
+        match self {
+            Self::LilyPond => Ok(lilypond::export_lilypond(song, track_id)),
+            Self::Abc => Ok(abc::export_abc(song, track_id)),
+            Self::VexTab => vextab::export_vextab(song, track_id),
+        }
+    }
+}
+
+const LETTERS: [char; 7] = ['C', 'D', 'E', 'F', 'G', 'A', 'B'];
+const LETTER_PITCH_CLASSES: [i32; 7] = [0, 2, 4, 5, 7, 9, 11];
+
+// keys from 7 flats to 7 sharps
+const MAJOR_KEYS: [&str; 15] = [
+    "Cb", "Gb", "Db", "Ab", "Eb", "Bb", "F", "C", "G", "D", "A", "E", "B", "F#", "C#",
+];
+const MINOR_KEYS: [&str; 15] = [
+    "Abm", "Ebm", "Bbm", "Fm", "Cm", "Gm", "Dm", "Am", "Em", "Bm", "F#m", "C#m", "G#m", "D#m",
+    "A#m",
+];
+
+/// Greatest common divisor, to write note lengths as reduced fractions.
+const fn gcd(a: u32, b: u32) -> u32 {
+    if b == 0 { a } else { gcd(b, a % b) }
+}
+
+/// Tracks to export: the picked one, every track but the drums if `None`.
+fn exported_tracks(song: &Song, track_id: Option<usize>) -> Vec<&Track> {
+    match track_id {
+        Some(track_id) => song.tracks.get(track_id).into_iter().collect(),
+        None => song
+            .tracks
+            .iter()
+            .filter(|track| !is_percussion(song, track))
+            .collect(),
+    }
+}
+
+/// Pitch of the written note, drum notes are written at the pitch of their sound.
+fn written_pitch(track: &Track, percussion: bool, note: &Note) -> Option<i32> {
+    if percussion {
+        matches!(note.kind, NoteType::Normal | NoteType::Tie).then_some(i32::from(note.value))
+    } else {
+        note_pitch(track, note)
+    }
+}
+
+/// Beats of the first voice of each measure, the empty ones take no time.
+fn first_voice_beats(track: &Track) -> Vec<Vec<&Beat>> {
+    track
+        .measures
+        .iter()
+        .map(|measure| {
+            measure.voices.first().map_or_else(Vec::new, |voice| {
+                voice.beats.iter().filter(|beat| !beat.empty).collect()
+            })
+        })
+        .collect()
+}
+
+/// The beat following a beat of the first voice, in the same measure or the next one.
+fn next_beat<'a>(beats: &[Vec<&'a Beat>], measure: usize, beat: usize) -> Option<&'a Beat> {
+    beats[measure]
+        .get(beat + 1)
+        .or_else(|| beats.get(measure + 1).and_then(|next| next.first()))
+        .copied()
+}
+
+/// Whether the notes of the beat are held on the next beat.
+fn is_tied(next: Option<&Beat>) -> bool {
+    next.is_some_and(|next| next.notes.iter().any(|note| note.kind == NoteType::Tie))
+}
+
+/// Time signature as a fraction, e.g. `3/4`.
+fn time_signature(header: &MeasureHeader) -> String {
+    let signature = &header.time_signature;
+    format!("{}/{}", signature.numerator, signature.denominator.value)
+}
+
+/// Name of the key, e.g. `Bb` or `F#m`.
+fn key_name(key: &KeySignature) -> &'static str {
+    let index = (key.key.clamp(-7, 7) + 7) as usize;
+    if key.is_minor {
+        MINOR_KEYS[index]
+    } else {
+        MAJOR_KEYS[index]
+    }
+}
+
+/// Accidental of each letter in the key, in semitones.
+fn key_accidentals(key: &KeySignature) -> [i32; 7] {
+    let mut accidentals = [0; 7];
+    let count = key.key.clamp(-7, 7);
+    // sharps are added from F up by fifths, flats from B down by fifths
+    for i in 0..usize::from(count.unsigned_abs()) {
+        if count > 0 {
+            accidentals[(3 + 4 * i) % 7] = 1;
+        } else {
+            accidentals[(6 + 3 * i) % 7] = -1;
+        }
+    }
+    accidentals
+}
+
+/// Letter and accidental of the pitch: the letter of the key if one matches,
+/// else the natural letter, else a sharp in sharp keys and a flat in flat keys.
+fn spell(pitch: i32, key_accidentals: &[i32; 7]) -> (usize, i32) {
+    let pitch_class = pitch.rem_euclid(12);
+    let letter_with = |accidental: &dyn Fn(usize) -> i32| {
+        (0..LETTERS.len())
+            .find(|&letter| {
+                (LETTER_PITCH_CLASSES[letter] + accidental(letter)).rem_euclid(12) == pitch_class
+            })
+            .map(|letter| (letter, accidental(letter)))
+    };
+    let chromatic = if key_accidentals.contains(&-1) { -1 } else { 1 };
+    letter_with(&|letter| key_accidentals[letter])
+        .or_else(|| letter_with(&|_| 0))
+        .or_else(|| letter_with(&|_| chromatic))
+        .expect("every pitch class has a sharp and a flat spelling")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spelling_in_keys() {
+        let c_major = key_accidentals(&KeySignature::new(0, false));
+        let d_major = key_accidentals(&KeySignature::new(2, false));
+        let e_flat_major = key_accidentals(&KeySignature::new(-3, false));
+        assert_eq!(d_major, [1, 0, 0, 1, 0, 0, 0]);
+        assert_eq!(e_flat_major, [0, 0, -1, 0, 0, -1, -1]);
+        // F# is in D major, C natural is not
+        assert_eq!(spell(66, &d_major), (3, 1));
+        assert_eq!(spell(60, &d_major), (0, 0));
+        // chromatic notes follow the direction of the key
+        assert_eq!(spell(61, &c_major), (0, 1));
+        assert_eq!(spell(61, &e_flat_major), (1, -1));
+        assert_eq!(key_name(&KeySignature::new(-3, true)), "Cm");
+        assert_eq!(key_name(&KeySignature::new(1, false)), "G");
+    }
+}
@@ -0,0 +1,222 @@
+//! VexTab export, the text format rendered by VexFlow to embed tabs in web pages.
+//!
+//! Each fretted track is written as staves of four measures showing the notation above the
+//! tablature, from the first voice of its measures. The frets and strings of the song are
+//! kept, VexFlow derives the notation from them and the tuning of the stave.
+
+use crate::RuxError;
+use crate::analysis::{is_percussion, pitch_class};
+use crate::export::{first_voice_beats, is_tied, key_name, time_signature};
+use crate::parser::song_parser::{Beat, NoteType, Song, Track};
+use std::fmt::Write;
+
+const MEASURES_PER_STAVE: usize = 4;
+
+/// Converts the track into VexTab staves, every track but the drums if `None`.
+/// Drums have no frets, picking a drum track is an error.
+pub fn export_vextab(song: &Song, track_id: Option<usize>) -> Result<String, RuxError> {
+    if let Some(track) = track_id.and_then(|track_id| song.tracks.get(track_id))
+        && is_percussion(song, track)
+    {
+        return Err(RuxError::OtherError(format!(
+            "Drum track {} has no frets to export to VexTab",
+            track.name
+        )));
+    }
+    let mut vextab = String::from("options space=20\n");
+    let tracks = song
+        .tracks
+        .iter()
+        .enumerate()
+        .filter(|(index, _)| track_id.is_none_or(|track_id| track_id == *index))
+        .map(|(_, track)| track)
+        .filter(|track| !is_percussion(song, track));
+    for track in tracks {
+        write_track(&mut vextab, song, track);
+    }
+    Ok(vextab)
+}
+
+/// Open strings from the highest to the lowest, the capo included.
+/// VexFlow writes the guitar an octave above its sound: the high E string is `E/5`.
+fn tuning(track: &Track) -> String {
+    let mut strings = track.strings.clone();
+    strings.sort_by_key(|(number, _)| *number);
+    let names: Vec<String> = strings
+        .iter()
+        .map(|(_, tuning)| {
+            let pitch = tuning + track.offset;
+            format!("{}/{}", pitch_class(pitch), pitch.div_euclid(12))
+        })
+        .collect();
+    names.join(",")
+}
+
+/// Quarter `q`, eighth `8`...
+fn duration(beat: &Beat) -> String {
+    let mut duration = match beat.duration.value {
+        1 => "w".to_string(),
+        2 => "h".to_string(),
+        4 => "q".to_string(),
+        value => value.to_string(),
+    };
+    // VexTab has no double dots
+    if beat.duration.dotted || beat.duration.double_dotted {
+        duration.push('d');
+    }
+    duration
+}
+
+/// Annotation text displayed above the previous note, `$` delimits it.
+fn annotation(text: &str) -> String {
+    format!(" ${}$", text.trim().replace('$', ""))
+}
+
+fn write_track(vextab: &mut String, song: &Song, track: &Track) {
+    let headers = &song.measure_headers;
+    let beats = first_voice_beats(track);
+    let tuning = tuning(track);
+    let mut current_duration = String::new();
+    // a new stave every few measures and at the time signature or key changes
+    let mut stave_starts = vec![0];
+    for (index, pair) in headers.windows(2).enumerate() {
+        let measures = index + 1 - stave_starts.last().copied().unwrap_or_default();
+        if measures == MEASURES_PER_STAVE
+            || time_signature(&pair[0]) != time_signature(&pair[1])
+            || pair[0].key_signature != pair[1].key_signature
+        {
+            stave_starts.push(index + 1);
+        }
+    }
+    for (index, header) in headers.iter().enumerate() {
+        let previous = index.checked_sub(1).map(|i| &headers[i]);
+        let starts_stave = stave_starts.contains(&index);
+        if starts_stave {
+            write!(
+                vextab,
+                "\ntabstave notation=true key={} tuning={tuning}",
+                key_name(&header.key_signature)
+            )
+            .unwrap();
+            if previous.is_none_or(|previous| time_signature(previous) != time_signature(header)) {
+                write!(vextab, " time={}", time_signature(header)).unwrap();
+            }
+            vextab.push_str("\nnotes");
+            current_duration.clear();
+            if header.repeat_open {
+                vextab.push_str(" =|:");
+            }
+        }
+
+        let measure_beats = beats.get(index).map_or(&[][..], Vec::as_slice);
+        let mut markers = header.marker.iter().map(|marker| annotation(&marker.title));
+        if measure_beats.is_empty() {
+            write!(vextab, " :w ##").unwrap();
+            current_duration = "w".to_string();
+        }
+        let mut tuplet_left = 0;
+        for (beat_index, beat) in measure_beats.iter().enumerate() {
+            let duration = duration(beat);
+            if duration != current_duration {
+                write!(vextab, " :{duration}").unwrap();
+                current_duration = duration;
+            }
+            vextab.push(' ');
+            // a tie continues the previous note of the stave
+            if (!starts_stave || beat_index > 0) && is_tied(Some(beat)) {
+                vextab.push('T');
+            }
+            write_beat(vextab, beat);
+            if beat_index == 0 {
+                vextab.extend(markers.by_ref());
+            }
+            if !beat.text.trim().is_empty() {
+                vextab.push_str(&annotation(&beat.text));
+            }
+            // the tuplet is closed after its last note
+            let (enters, times) = (beat.duration.tuplet_enters, beat.duration.tuplet_times);
+            if enters != times {
+                if tuplet_left == 0 {
+                    tuplet_left = enters;
+                }
+                tuplet_left -= 1;
+                if tuplet_left == 0 {
+                    write!(vextab, " ^{enters}^").unwrap();
+                }
+            } else {
+                tuplet_left = 0;
+            }
+        }
+        // the bar line opens the repeat of the next measure on the same stave
+        let next = headers
+            .get(index + 1)
+            .map(|next| next.repeat_open && !stave_starts.contains(&(index + 1)));
+        let bar = match (header.repeat_close > 0, next) {
+            (true, Some(true)) => " =::",
+            (true, _) => " =:|",
+            (false, None) => " =|=",
+            (false, Some(true)) => " =|:",
+            (false, Some(false)) => " |",
+        };
+        vextab.push_str(bar);
+    }
+    vextab.push('\n');
+}
+
+/// Notes of the beat as `fret/string`, chords in parentheses, rests as `##`.
+fn write_beat(vextab: &mut String, beat: &Beat) {
+    let notes: Vec<String> = beat
+        .notes
+        .iter()
+        .filter_map(|note| match note.kind {
+            NoteType::Normal | NoteType::Tie => Some(format!("{}/{}", note.value, note.string)),
+            NoteType::Dead => Some(format!("X/{}", note.string)),
+            NoteType::Rest | NoteType::Unknown(_) => None,
+        })
+        .collect();
+    match &notes[..] {
+        [] => vextab.push_str("##"),
+        [note] => vextab.push_str(note),
+        notes => write!(vextab, "({})", notes.join(".")).unwrap(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::song_parser_tests::parse_gp_file;
+
+    #[test]
+    fn export_demo_song() {
+        let song = parse_gp_file("test-files/Demo v5.gp5").unwrap();
+        let vextab = export_vextab(&song, Some(0)).unwrap();
+        let mut lines = vextab.lines();
+        assert_eq!(lines.next(), Some("options space=20"));
+        assert_eq!(lines.next(), Some(""));
+        assert_eq!(
+            lines.next(),
+            Some("tabstave notation=true key=C tuning=E/5,B/4,G/4,D/4,A/3,E/3 time=4/4")
+        );
+        let notes = lines.next().unwrap();
+        assert!(notes.starts_with("notes =|: :8 (5/3.5/4.3/5) $Intro$ 3/5 3/5"));
+        let staves = vextab.matches("tabstave").count();
+        assert!(staves >= song.measure_headers.len().div_ceil(MEASURES_PER_STAVE));
+        assert!(vextab.trim_end().ends_with("=|="));
+
+        // the drums are not exported
+        let drums = song
+            .tracks
+            .iter()
+            .position(|track| is_percussion(&song, track))
+            .unwrap();
+        assert!(export_vextab(&song, Some(drums)).is_err());
+        let all = export_vextab(&song, None).unwrap();
+        let pitched = song
+            .tracks
+            .iter()
+            .filter(|track| !is_percussion(&song, track))
+            .count();
+        let first_staves = all.matches("time=4/4").count();
+        assert_eq!(first_staves, pitched);
+    }
+}
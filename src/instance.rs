@@ -0,0 +1,214 @@
+//! Single instance mode: a tab file opened while the application runs is handed to the
+//! running instance instead of starting a second window.
+//! The running instance listens on a local port recorded in the configuration folder,
+//! along with a random token required on every request so that other local users can not
+//! make it open files.
+
+use crate::tab_file::TabFile;
+use std::hash::{BuildHasher, RandomState};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Prefix of the requests and of the replies, tells a stale port taken by another program apart.
+const PROTOCOL: &str = "ruxguitar";
+
+/// A running instance answers right away, a slow answer is not worth delaying the startup.
+const TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Longest request line, a path or an URL.
+const MAX_REQUEST_SIZE: u64 = 8 * 1024;
+
+/// Hands the tab file to the running instance, `false` if none took it.
+/// Files read from the standard input are not handed off.
+pub fn hand_off(instance_file: &Path, tab_file: &TabFile) -> bool {
+    let Some((port, token)) = std::fs::read_to_string(instance_file)
+        .ok()
+        .as_deref()
+        .and_then(parse_instance_file)
+    else {
+        return false;
+    };
+    let Some(request) = encode_request(&token, tab_file) else {
+        return false;
+    };
+    match send_request(port, &request) {
+        Ok(handed_off) => handed_off,
+        Err(err) => {
+            // the previous instance did not exit cleanly
+            log::debug!("No running instance on port {port}: {err}");
+            false
+        }
+    }
+}
+
+/// Port and token of the running instance, written as `<port> <token>`.
+fn parse_instance_file(contents: &str) -> Option<(u16, String)> {
+    let (port, token) = contents.trim().split_once(' ')?;
+    Some((port.parse().ok()?, token.to_string()))
+}
+
+/// Unguessable token from the randomly seeded keys of the standard hasher.
+fn random_token() -> String {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+    let seed = (std::process::id(), nanos);
+    let high = RandomState::new().hash_one(seed);
+    let low = RandomState::new().hash_one(seed);
+    format!("{high:016x}{low:016x}")
+}
+
+/// Instance file readable by the current user only, an existing one is replaced.
+fn write_instance_file(instance_file: &Path, contents: &str) -> io::Result<()> {
+    match std::fs::remove_file(instance_file) {
+        Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(err),
+        _ => {}
+    }
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options.open(instance_file)?.write_all(contents.as_bytes())
+}
+
+fn send_request(port: u16, request: &str) -> io::Result<bool> {
+    let address = SocketAddr::from((Ipv4Addr::LOCALHOST, port));
+    let mut stream = TcpStream::connect_timeout(&address, TIMEOUT)?;
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+    stream.write_all(request.as_bytes())?;
+    let mut reply = String::new();
+    BufReader::new(stream.take(MAX_REQUEST_SIZE)).read_line(&mut reply)?;
+    Ok(reply.trim_end() == format!("{PROTOCOL} ok"))
+}
+
+/// Request line opening the tab file, paths are made absolute for the running instance.
+fn encode_request(token: &str, tab_file: &TabFile) -> Option<String> {
+    let (kind, value) = match tab_file {
+        TabFile::Path(path) => (
+            "path",
+            std::path::absolute(path).ok()?.to_str()?.to_string(),
+        ),
+        TabFile::Url(url) => ("url", url.clone()),
+        TabFile::Content(_) => return None,
+    };
+    // the request is a single line
+    (!value.contains('\n')).then(|| format!("{PROTOCOL} {token} {kind} {value}\n"))
+}
+
+/// Tab file of the request, `None` if invalid or without the token of the instance.
+fn decode_request(token: &str, line: &str) -> Option<TabFile> {
+    let request = line.strip_suffix('\n')?.strip_prefix(PROTOCOL)?;
+    let (request_token, request) = request.strip_prefix(' ')?.split_once(' ')?;
+    if request_token != token {
+        return None;
+    }
+    let (kind, value) = request.split_once(' ')?;
+    match kind {
+        "path" if Path::new(value).is_absolute() => Some(TabFile::Path(PathBuf::from(value))),
+        "url" => TabFile::from_arg(value)
+            .ok()
+            .filter(|tab_file| matches!(tab_file, TabFile::Url(_))),
+        _ => None,
+    }
+}
+
+/// Port of the running instance, taking over the file of an instance which did not exit cleanly.
+pub struct InstanceListener {
+    listener: TcpListener,
+    token: String,
+}
+
+impl InstanceListener {
+    pub fn bind(instance_file: &Path) -> io::Result<Self> {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))?;
+        let port = listener.local_addr()?.port();
+        let token = random_token();
+        write_instance_file(instance_file, &format!("{port} {token}"))?;
+        Ok(Self { listener, token })
+    }
+
+    /// Calls `on_request` with the tab files handed off, blocks as long as the port is open.
+    pub fn listen(&self, mut on_request: impl FnMut(TabFile)) {
+        for stream in self.listener.incoming() {
+            let request = stream.and_then(|stream| {
+                stream.set_read_timeout(Some(TIMEOUT))?;
+                stream.set_write_timeout(Some(TIMEOUT))?;
+                read_request(&self.token, stream)
+            });
+            match request {
+                Ok(Some(tab_file)) => on_request(tab_file),
+                Ok(None) => log::debug!("Ignored an invalid instance request"),
+                Err(err) => log::debug!("Instance request failed: {err}"),
+            }
+        }
+    }
+}
+
+fn read_request(token: &str, mut stream: TcpStream) -> io::Result<Option<TabFile>> {
+    let mut line = String::new();
+    BufReader::new((&stream).take(MAX_REQUEST_SIZE)).read_line(&mut line)?;
+    let tab_file = decode_request(token, &line);
+    if tab_file.is_some() {
+        stream.write_all(format!("{PROTOCOL} ok\n").as_bytes())?;
+    }
+    Ok(tab_file)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn requests_round_trip() {
+        let token = random_token();
+        assert_eq!(token.len(), 32);
+        assert_ne!(token, random_token());
+        let path = TabFile::Path(std::path::absolute("tabs/song.gp5").unwrap());
+        let request = encode_request(&token, &path).unwrap();
+        assert_eq!(decode_request(&token, &request), Some(path));
+
+        let url = TabFile::Url("https://example.com/tab.gp".to_string());
+        let request = encode_request(&token, &url).unwrap();
+        assert_eq!(decode_request(&token, &request), Some(url));
+        // a request without the token of the instance is refused
+        assert_eq!(decode_request(&random_token(), &request), None);
+        let absolute = std::path::absolute("song.gp5").unwrap();
+        let line = format!("ruxguitar path {}\n", absolute.display());
+        assert_eq!(decode_request(&token, &line), None);
+
+        // relative paths, unknown kinds and truncated lines are refused
+        let decode =
+            |request: &str| decode_request(&token, &format!("ruxguitar {token} {request}"));
+        assert_eq!(decode("path tabs/song.gp5\n"), None);
+        assert_eq!(decode("url /etc/passwd\n"), None);
+        assert_eq!(decode("quit now\n"), None);
+        assert_eq!(decode("url https://example.com"), None);
+        assert_eq!(decode_request(&token, "GET / HTTP/1.1\n"), None);
+    }
+
+    #[test]
+    fn hand_off_to_a_running_instance() {
+        let instance_file =
+            std::env::temp_dir().join(format!("ruxguitar-instance-{}", std::process::id()));
+        let tab_file = TabFile::Url("https://example.com/tab.gp".to_string());
+        // no instance running
+        assert!(!hand_off(&instance_file, &tab_file));
+
+        let listener = InstanceListener::bind(&instance_file).unwrap();
+        let (sender, receiver) = std::sync::mpsc::channel();
+        std::thread::spawn(move || listener.listen(|tab_file| sender.send(tab_file).unwrap()));
+        assert!(hand_off(&instance_file, &tab_file));
+        assert_eq!(receiver.recv().unwrap(), tab_file);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let metadata = std::fs::metadata(&instance_file).unwrap();
+            assert_eq!(metadata.permissions().mode() & 0o777, 0o600);
+        }
+        std::fs::remove_file(&instance_file).unwrap();
+    }
+}
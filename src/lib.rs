@@ -0,0 +1,50 @@
+use crate::config::Config;
+use crate::tab_file::TabFile;
+use std::io;
+use std::path::PathBuf;
+
+pub mod analysis;
+pub mod audio;
+pub mod config;
+pub mod editor;
+pub mod export;
+pub mod instance;
+pub mod parser;
+pub mod paths;
+pub mod tab_file;
+pub mod track_list;
+pub mod ui;
+pub mod validate;
+
+#[derive(Debug, Clone)]
+pub struct ApplicationArgs {
+    pub sound_font_bank: Option<PathBuf>,
+    pub tab_file: Option<TabFile>,
+    pub no_antialiasing: bool,
+    pub instance_file: Option<PathBuf>, // listened to in single instance mode
+    pub local_config: Config,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum RuxError {
+    #[error("iced error: {0}")]
+    IcedError(iced::Error),
+    #[error("configuration error: {0}")]
+    ConfigError(String),
+    #[error("parsing error: {0}")]
+    ParsingError(String),
+    #[error("other error: {0}")]
+    OtherError(String),
+}
+
+impl From<iced::Error> for RuxError {
+    fn from(error: iced::Error) -> Self {
+        Self::IcedError(error)
+    }
+}
+
+impl From<io::Error> for RuxError {
+    fn from(error: io::Error) -> Self {
+        Self::OtherError(error.to_string())
+    }
+}
@@ -1,14 +1,19 @@
-use crate::RuxError::ConfigError;
-use crate::ui::application::RuxApplication;
-use clap::Parser;
-use config::Config;
-use std::io;
-use std::path::PathBuf;
-
-mod audio;
-mod config;
-mod parser;
-mod ui;
+use clap::{Parser, Subcommand};
+use ruxguitar::RuxError::ConfigError;
+use ruxguitar::config::Config;
+use ruxguitar::export::NotationFormat;
+use ruxguitar::instance;
+use ruxguitar::parser::{open_tab_file, parse_gp_data};
+use ruxguitar::paths::AppPaths;
+use ruxguitar::tab_file::{TabFile, download};
+use ruxguitar::track_list::track_list;
+use ruxguitar::ui::application::RuxApplication;
+use ruxguitar::ui::snapshot::render_track_png;
+use ruxguitar::validate::validate_folder;
+use ruxguitar::{ApplicationArgs, RuxError};
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 fn main() {
     let result = main_result();
@@ -29,8 +34,32 @@ pub fn main_result() -> Result<(), RuxError> {
 
     // args
     let mut args = CliArgs::parse();
+    match args.command.take() {
+        Some(Command::Validate { folder, strict }) => return validate(&folder, strict),
+        Some(Command::Export {
+            file,
+            format,
+            track,
+            output,
+        }) => return export(&file, format, track, output.as_deref()),
+        None => {}
+    }
     let sound_font_file = args.sound_font_file.take();
-    let tab_file_path = args.tab_file_path.take();
+    let tab_file = match (args.tab_file.take(), args.tab_file_path.take()) {
+        (Some(arg), _) => Some(
+            TabFile::from_arg(&arg)
+                .map_err(|err| ConfigError(format!("Could not read tab file {arg}: {err}")))?,
+        ),
+        (None, path) => path.map(TabFile::Path),
+    };
+    if args.list_tracks {
+        let tab_file = tab_file.ok_or_else(|| ConfigError("No tab file to list".to_string()))?;
+        return list_tracks(tab_file);
+    }
+    if let Some(output) = args.render_png.take() {
+        let tab_file = tab_file.ok_or_else(|| ConfigError("No tab file to render".to_string()))?;
+        return render_png(tab_file, args.track, args.measures.take(), &output);
+    }
 
     // check if sound font file exists
     if let Some(sound_font_file) = &sound_font_file {
@@ -42,22 +71,42 @@ pub fn main_result() -> Result<(), RuxError> {
     }
 
     // check if tab file exists
-    if let Some(tab_file_path) = &tab_file_path {
-        if !tab_file_path.exists() {
-            let err = ConfigError(format!("Tab file not found {tab_file_path:?}"));
-            return Err(err);
+    match &tab_file {
+        Some(TabFile::Path(tab_file_path)) => {
+            if !tab_file_path.exists() {
+                let err = ConfigError(format!("Tab file not found {tab_file_path:?}"));
+                return Err(err);
+            }
+            log::info!("Starting with tab file {tab_file_path:?}");
         }
-        log::info!("Starting with tab file {tab_file_path:?}");
+        Some(TabFile::Content(content)) => {
+            log::info!("Starting with tab file {} from stdin", content.file_name);
+        }
+        Some(TabFile::Url(url)) => log::info!("Starting with tab file {url}"),
+        None => {}
     }
 
     // read local config
-    let local_config = Config::read_config()?;
+    let paths = AppPaths::resolve(args.portable)?;
+    log::info!("Configuration folder {}", paths.config_dir().display());
+    let local_config = Config::read_config(paths)?;
+
+    // hand the tab file to the running instance if any
+    let single_instance = !args.new_instance && !local_config.get_multiple_instances();
+    let instance_file = local_config.get_instance_file().filter(|_| single_instance);
+    if let (Some(instance_file), Some(tab_file)) = (&instance_file, &tab_file)
+        && instance::hand_off(instance_file, tab_file)
+    {
+        log::info!("Tab file opened in the running instance");
+        return Ok(());
+    }
 
     // bundle application args
     let args = ApplicationArgs {
         sound_font_bank: sound_font_file,
-        tab_file_path,
+        tab_file,
         no_antialiasing: args.no_antialiasing,
+        instance_file,
         local_config,
     };
 
@@ -66,9 +115,101 @@ pub fn main_result() -> Result<(), RuxError> {
     Ok(())
 }
 
+/// Prints the report of the tab files of the folder, fails if any of them is invalid.
+fn validate(folder: &Path, strict: bool) -> Result<(), RuxError> {
+    let report = validate_folder(folder, strict)
+        .map_err(|err| ConfigError(format!("Could not read folder {folder:?}: {err}")))?;
+    println!("{report}");
+    match report.failed_count() {
+        0 => Ok(()),
+        failed => Err(RuxError::OtherError(format!(
+            "{failed} of {} tab files failed validation",
+            report.files.len()
+        ))),
+    }
+}
+
+/// Content of the tab file, downloaded if needed.
+fn read_tab_file(tab_file: TabFile) -> Result<Vec<u8>, RuxError> {
+    match tab_file {
+        TabFile::Path(path) => std::fs::read(&path)
+            .map_err(|err| ConfigError(format!("Could not read tab file {path:?}: {err}"))),
+        TabFile::Content(content) => Ok(content.data),
+        TabFile::Url(url) => download(&url)
+            .map(|content| content.data)
+            .map_err(|err| ConfigError(format!("Could not download tab file {url}: {err}"))),
+    }
+}
+
+/// Prints the tracks of the tab file, one per line.
+fn list_tracks(tab_file: TabFile) -> Result<(), RuxError> {
+    let song = parse_gp_data(&read_tab_file(tab_file)?)?;
+    print!("{}", track_list(&song));
+    Ok(())
+}
+
+/// Draws the measures of the track into a PNG image, all the measures if `None`.
+fn render_png(
+    tab_file: TabFile,
+    track: usize,
+    measures: Option<RangeInclusive<usize>>,
+    output: &Path,
+) -> Result<(), RuxError> {
+    let song = parse_gp_data(&read_tab_file(tab_file)?)?;
+    if !(1..=song.tracks.len()).contains(&track) {
+        return Err(ConfigError(format!(
+            "Track {track} not found, the song has {} tracks",
+            song.tracks.len()
+        )));
+    }
+    // measures are numbered from 1 on the command line
+    let measures = measures.unwrap_or(1..=song.measure_headers.len());
+    let measures = measures.start().saturating_sub(1)..=measures.end().saturating_sub(1);
+    let png = render_track_png(Arc::new(song), track - 1, measures)
+        .map_err(|err| ConfigError(err.to_string()))?;
+    std::fs::write(output, png)?;
+    log::info!("Tablature image written to {}", output.display());
+    Ok(())
+}
+
+/// Writes the notation of the tab file to the output file, to the standard output if `None`.
+fn export(
+    file: &Path,
+    format: NotationFormat,
+    track: Option<usize>,
+    output: Option<&Path>,
+) -> Result<(), RuxError> {
+    let song = open_tab_file(file)?;
+    // tracks are numbered from 1 on the command line
+    let track_id = match track {
+        Some(number) if (1..=song.tracks.len()).contains(&number) => Some(number - 1),
+        Some(number) => {
+            return Err(ConfigError(format!(
+                "Track {number} not found, the song has {} tracks",
+                song.tracks.len()
+            )));
+        }
+        None => None,
+    };
+    let notation = format.export(&song, track_id)?;
+    match output {
+        Some(output) => {
+            std::fs::write(output, notation)?;
+            log::info!("{} written to {}", format.name(), output.display());
+        }
+        None => print!("{notation}"),
+    }
+    Ok(())
+}
+
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
 pub struct CliArgs {
+    #[command(subcommand)]
+    command: Option<Command>,
+    /// Tab file to open: a path, `-` for stdin or an http(s) URL.
+    #[arg(conflicts_with = "tab_file_path")]
+    tab_file: Option<String>,
     /// Optional path to a sound font file.
     #[arg(long)]
     sound_font_file: Option<PathBuf>,
@@ -78,36 +219,65 @@ pub struct CliArgs {
     /// Disable antialiasing.
     #[arg(long, default_value_t = false)]
     no_antialiasing: bool,
+    /// Store the settings next to the executable.
+    #[arg(long, default_value_t = false)]
+    portable: bool,
+    /// Start another window instead of opening the tab file in the running one.
+    #[arg(long, default_value_t = false)]
+    new_instance: bool,
+    /// Print the number, name, tuning, channel and measure count of the tracks of the tab file and exit.
+    #[arg(long, default_value_t = false, conflicts_with = "render_png")]
+    list_tracks: bool,
+    /// Draw the tablature of the tab file into a PNG image, without opening a window.
+    #[arg(long, value_name = "OUTPUT")]
+    render_png: Option<PathBuf>,
+    /// Measures of the image, counting from 1: `1..16` or a single measure, all if missing.
+    #[arg(long, requires = "render_png", value_parser = parse_measures)]
+    measures: Option<RangeInclusive<usize>>,
+    /// Track of the image, counting from 1.
+    #[arg(long, requires = "render_png", default_value_t = 1)]
+    track: usize,
 }
 
-#[derive(Debug, Clone)]
-pub struct ApplicationArgs {
-    sound_font_bank: Option<PathBuf>,
-    tab_file_path: Option<PathBuf>,
-    no_antialiasing: bool,
-    local_config: Config,
-}
-
-#[derive(Debug, thiserror::Error)]
-pub enum RuxError {
-    #[error("iced error: {0}")]
-    IcedError(iced::Error),
-    #[error("configuration error: {0}")]
-    ConfigError(String),
-    #[error("parsing error: {0}")]
-    ParsingError(String),
-    #[error("other error: {0}")]
-    OtherError(String),
-}
-
-impl From<iced::Error> for RuxError {
-    fn from(error: iced::Error) -> Self {
-        Self::IcedError(error)
+/// Measures range `first..last`, both included.
+fn parse_measures(arg: &str) -> Result<RangeInclusive<usize>, String> {
+    let (first, last) = arg.split_once("..").unwrap_or((arg, arg));
+    let parse = |number: &str| {
+        number
+            .parse::<usize>()
+            .ok()
+            .filter(|number| *number > 0)
+            .ok_or_else(|| format!("invalid measure number {number:?}, measures count from 1"))
+    };
+    let (first, last) = (parse(first)?, parse(last)?);
+    if first > last {
+        return Err(format!("measure {first} is after measure {last}"));
     }
+    Ok(first..=last)
 }
 
-impl From<io::Error> for RuxError {
-    fn from(error: io::Error) -> Self {
-        Self::OtherError(error.to_string())
-    }
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Parse every tab file of a folder and check its MIDI events, without opening a window.
+    Validate {
+        /// Folder of the tab files, sub folders included.
+        folder: PathBuf,
+        /// Fail the files with parsing warnings.
+        #[arg(long, default_value_t = false)]
+        strict: bool,
+    },
+    /// Write the notation of a tab file, without opening a window.
+    Export {
+        /// Tab file to export.
+        file: PathBuf,
+        /// Notation format.
+        #[arg(long, value_enum, default_value_t = NotationFormat::LilyPond)]
+        format: NotationFormat,
+        /// Track to export, counting from 1, all the tracks but the drums if missing.
+        #[arg(long)]
+        track: Option<usize>,
+        /// Output file, the standard output if missing.
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+    },
 }
@@ -0,0 +1,290 @@
+//! Structural differences between two parsed songs, e.g. to check that a converted
+//! or saved song still holds the original tracks, measures and beats.
+//!
+//! Values are compared field by field and reported with their debug representation,
+//! the report can be serialized to JSON.
+
+use crate::parser::song_parser::{Beat, MeasureHeader, Song, Track};
+use serde::Serialize;
+use std::fmt;
+
+/// Where a change is, from the song down to a beat.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct DiffLocation {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub track: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub measure: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub voice: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub beat: Option<usize>,
+}
+
+impl fmt::Display for DiffLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let parts: Vec<String> = [
+            ("track", self.track),
+            ("measure", self.measure),
+            ("voice", self.voice),
+            ("beat", self.beat),
+        ]
+        .into_iter()
+        .filter_map(|(name, index)| index.map(|index| format!("{name} {}", index + 1)))
+        .collect();
+        if parts.is_empty() {
+            f.write_str("song")
+        } else {
+            f.write_str(&parts.join(", "))
+        }
+    }
+}
+
+/// A field holding different values in the two songs.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Change {
+    pub location: DiffLocation,
+    pub field: &'static str,
+    pub left: String,
+    pub right: String,
+}
+
+/// Changes from the left song to the right one, in song order.
+/// Tracks, measures, voices and beats are matched by index, the extra ones are
+/// reported as a count change.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct SongDiff {
+    pub changes: Vec<Change>,
+}
+
+impl SongDiff {
+    pub fn between(left: &Song, right: &Song) -> Self {
+        let mut diff = Self::default();
+        let song = DiffLocation::default();
+        diff.compare(song, "name", &left.song_info.name, &right.song_info.name);
+        diff.compare(
+            song,
+            "artist",
+            &left.song_info.artist,
+            &right.song_info.artist,
+        );
+        diff.compare(song, "tempo", &left.tempo.value, &right.tempo.value);
+        diff.compare(
+            song,
+            "measure count",
+            &left.measure_headers.len(),
+            &right.measure_headers.len(),
+        );
+        diff.compare(song, "track count", &left.tracks.len(), &right.tracks.len());
+        for (measure, (left, right)) in left
+            .measure_headers
+            .iter()
+            .zip(&right.measure_headers)
+            .enumerate()
+        {
+            diff.compare_headers(measure, left, right);
+        }
+        for (track, (left, right)) in left.tracks.iter().zip(&right.tracks).enumerate() {
+            diff.compare_tracks(track, left, right);
+        }
+        diff
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("the diff is serializable")
+    }
+
+    fn compare<T: fmt::Debug + PartialEq + ?Sized>(
+        &mut self,
+        location: DiffLocation,
+        field: &'static str,
+        left: &T,
+        right: &T,
+    ) {
+        if left != right {
+            self.changes.push(Change {
+                location,
+                field,
+                left: format!("{left:?}"),
+                right: format!("{right:?}"),
+            });
+        }
+    }
+
+    fn compare_headers(&mut self, measure: usize, left: &MeasureHeader, right: &MeasureHeader) {
+        let location = DiffLocation {
+            measure: Some(measure),
+            ..DiffLocation::default()
+        };
+        self.compare(
+            location,
+            "time signature",
+            &left.time_signature,
+            &right.time_signature,
+        );
+        self.compare(location, "tempo", &left.tempo.value, &right.tempo.value);
+        self.compare(
+            location,
+            "marker",
+            &left.marker.as_ref().map(|m| &m.title),
+            &right.marker.as_ref().map(|m| &m.title),
+        );
+        self.compare(
+            location,
+            "repeat open",
+            &left.repeat_open,
+            &right.repeat_open,
+        );
+        self.compare(
+            location,
+            "repeat close",
+            &left.repeat_close,
+            &right.repeat_close,
+        );
+        self.compare(
+            location,
+            "alternative endings",
+            &left.repeat_alternative,
+            &right.repeat_alternative,
+        );
+        self.compare(
+            location,
+            "key signature",
+            &left.key_signature,
+            &right.key_signature,
+        );
+        self.compare(location, "directions", &left.directions, &right.directions);
+    }
+
+    fn compare_tracks(&mut self, track: usize, left: &Track, right: &Track) {
+        let location = DiffLocation {
+            track: Some(track),
+            ..DiffLocation::default()
+        };
+        self.compare(location, "name", &left.name, &right.name);
+        self.compare(location, "strings", &left.strings, &right.strings);
+        self.compare(location, "capo", &left.offset, &right.offset);
+        self.compare(location, "fret count", &left.fret_count, &right.fret_count);
+        self.compare(
+            location,
+            "measure count",
+            &left.measures.len(),
+            &right.measures.len(),
+        );
+        for (measure, (left, right)) in left.measures.iter().zip(&right.measures).enumerate() {
+            let location = DiffLocation {
+                measure: Some(measure),
+                ..location
+            };
+            self.compare(
+                location,
+                "voice count",
+                &left.voices.len(),
+                &right.voices.len(),
+            );
+            for (voice, (left, right)) in left.voices.iter().zip(&right.voices).enumerate() {
+                let location = DiffLocation {
+                    voice: Some(voice),
+                    ..location
+                };
+                self.compare(
+                    location,
+                    "beat count",
+                    &left.beats.len(),
+                    &right.beats.len(),
+                );
+                for (beat, (left, right)) in left.beats.iter().zip(&right.beats).enumerate() {
+                    let location = DiffLocation {
+                        beat: Some(beat),
+                        ..location
+                    };
+                    self.compare_beats(location, left, right);
+                }
+            }
+        }
+    }
+
+    fn compare_beats(&mut self, location: DiffLocation, left: &Beat, right: &Beat) {
+        self.compare(location, "duration", &left.duration, &right.duration);
+        self.compare(location, "start", &left.start, &right.start);
+        self.compare(location, "empty", &left.empty, &right.empty);
+        self.compare(location, "notes", &left.notes, &right.notes);
+        self.compare(location, "text", &left.text, &right.text);
+        self.compare(location, "effects", &left.effect, &right.effect);
+        self.compare(
+            location,
+            "mix table change",
+            &left.mix_table_change,
+            &right.mix_table_change,
+        );
+    }
+}
+
+/// One change per line, e.g. `track 1, measure 3, voice 1, beat 2: duration Duration { .. } -> ..`.
+impl fmt::Display for SongDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for change in &self.changes {
+            writeln!(
+                f,
+                "{}: {} {} -> {}",
+                change.location, change.field, change.left, change.right
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::ruxg::{parse_ruxg_data, write_ruxg};
+    use crate::parser::song_parser_tests::parse_gp_file;
+
+    #[test]
+    fn same_song_has_no_changes() {
+        let song = parse_gp_file("test-files/Demo v5.gp5").unwrap();
+        let diff = SongDiff::between(&song, &song.clone());
+        assert!(diff.is_empty());
+        assert_eq!(diff.to_string(), "");
+
+        // the native format round trip keeps the song
+        let saved = parse_ruxg_data(&write_ruxg(&song).unwrap()).unwrap();
+        let diff = SongDiff::between(&song, &saved);
+        assert!(diff.is_empty(), "{diff}");
+    }
+
+    #[test]
+    fn changes_are_located() {
+        let left = parse_gp_file("test-files/Demo v5.gp5").unwrap();
+        let mut right = left.clone();
+        right.tempo.value += 10;
+        right.tracks[0].name = "Lead".to_string();
+        right.tracks[0].measures[2].voices[0].beats[1].text = "fill".to_string();
+        right.tracks[1].measures.pop();
+
+        let diff = SongDiff::between(&left, &right);
+        let lines: Vec<String> = diff.to_string().lines().map(str::to_string).collect();
+        assert_eq!(lines.len(), 4, "{diff}");
+        assert_eq!(
+            lines[0],
+            format!("song: tempo {} -> {}", left.tempo.value, right.tempo.value)
+        );
+        assert!(lines[1].starts_with("track 1: name "));
+        assert_eq!(
+            lines[2],
+            "track 1, measure 3, voice 1, beat 2: text \"\" -> \"fill\""
+        );
+        assert!(lines[3].starts_with("track 2: measure count "));
+
+        let json: serde_json::Value = serde_json::from_str(&diff.to_json()).unwrap();
+        let change = &json["changes"][2];
+        assert_eq!(change["location"]["measure"], 2);
+        assert_eq!(change["field"], "text");
+        // song level changes have no location index
+        assert_eq!(json["changes"][0]["location"], serde_json::json!({}));
+    }
+}
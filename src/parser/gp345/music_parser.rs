@@ -1,14 +1,21 @@
 use crate::parser::gp345::primitive_parser::{
-    parse_byte_size_string, parse_i8, parse_int, parse_int_byte_sized_string, parse_u8, skip,
+    parse_byte_size_string, parse_i8, parse_int, parse_int_byte_sized_string, parse_short,
+    parse_u8, skip,
 };
 use crate::parser::gp345::song_parser::{
-    Beat, GpVersion, MAX_VOICES, Measure, Note, NoteEffect, NoteType, QUARTER_TIME, Song, Track,
-    Voice, convert_velocity, parse_beat_effects, parse_chord, parse_color, parse_duration,
-    parse_measure_headers, parse_note_effects,
+    Beat, DEFAULT_PERCUSSION_BANK, DirectionSign, GpVersion, MAX_FRET, MAX_VOICES, Measure,
+    MixTableChange, MixTableItem, Note, NoteEffect, NoteType, PORT_CHANNELS, PORT_COUNT,
+    QUARTER_TIME, Song, TempoChange, Track, Voice, WahEffect, convert_velocity, parse_beat_effects,
+    parse_chord, parse_color, parse_duration, parse_measure_headers, parse_note_effects,
+    parse_track_rse,
 };
+use crate::parser::warnings::{clamped, warn};
 use nom::multi::count;
 use nom::{IResult, Parser};
 
+/// Mix table change of a beat and the wah position it sets.
+type MixChange = (MixTableChange, Option<WahEffect>);
+
 pub struct MusicParser {
     song: Song,
 }
@@ -25,9 +32,21 @@ impl MusicParser {
         let mut i = i;
         let song_version = self.song.version;
 
+        // measure number (1 based) of each direction sign, GP5 only
+        let mut directions = Vec::new();
         if song_version >= GpVersion::GP5 {
-            // skip directions & master reverb
-            i = skip(i, 42);
+            for sign in DirectionSign::ALL {
+                let (inner, measure) = parse_short(i)?;
+                i = inner;
+                if measure > 0 {
+                    log::debug!("Direction {sign:?} at measure {measure}");
+                    directions.push((sign, measure as usize - 1));
+                }
+            }
+            let (inner, reverb) = parse_int(i)?;
+            i = inner;
+            log::debug!("Master reverb: {reverb}");
+            self.song.master_effect.get_or_insert_default().reverb = reverb;
         }
 
         let (i, (measure_count, track_count)) = (
@@ -44,6 +63,11 @@ impl MusicParser {
         let (i, measure_headers) =
             parse_measure_headers(measure_count, song_tempo, song_version)(i)?;
         self.song.measure_headers = measure_headers;
+        for (sign, measure_index) in directions {
+            if let Some(header) = self.song.measure_headers.get_mut(measure_index) {
+                header.directions.push(sign);
+            }
+        }
 
         let (i, tracks) = self.parse_tracks(track_count as usize)(i)?;
         self.song.tracks = tracks;
@@ -83,7 +107,7 @@ impl MusicParser {
         move |i| {
             log::debug!("--------");
             log::debug!("Parsing track {number}");
-            let mut i = skip(i, 1);
+            let mut i = i;
             let mut track = Track::default();
 
             if self.song.version >= GpVersion::GP5
@@ -92,6 +116,16 @@ impl MusicParser {
                 i = skip(i, 1);
             };
 
+            // track flags
+            let (inner, flags) = parse_u8(i)?;
+            i = inner;
+            let drums = (flags & 0x01) == 0x01;
+            if self.song.version >= GpVersion::GP5 {
+                track.solo = (flags & 0x10) == 0x10;
+                track.mute = (flags & 0x20) == 0x20;
+                log::debug!("Track solo:{} mute:{}", track.solo, track.mute);
+            }
+
             track.number = number as i32;
 
             // track name
@@ -104,7 +138,7 @@ impl MusicParser {
             let (inner, string_count) = parse_int(i)?;
             i = inner;
             log::debug!("String count: {string_count}");
-            assert!(string_count > 0);
+            let string_count = clamped("String count", string_count, 1..=7);
 
             // tunings
             let (inner, tunings) = count(parse_int, 7).parse(i)?;
@@ -121,10 +155,10 @@ impl MusicParser {
             let (inner, port) = parse_int(i)?;
             log::debug!("Midi port: {port:?}");
             i = inner;
-            track.midi_port = port as u8;
+            track.midi_port = clamped("MIDI port", port, 1..=i32::from(PORT_COUNT)) as u8;
 
             // parse track channel info
-            let (inner, channel_id) = self.parse_track_channel()(i)?;
+            let (inner, channel_id) = self.parse_track_channel(drums, track.midi_port)(i)?;
             log::debug!("Midi channel id: {channel_id:?}");
             track.channel_id = channel_id as u8;
             i = inner;
@@ -133,7 +167,7 @@ impl MusicParser {
             let (inner, fret_count) = parse_int(i)?;
             log::debug!("Fret count: {fret_count:?}");
             i = inner;
-            track.fret_count = fret_count as u8;
+            track.fret_count = clamped("Fret count", fret_count, 0..=MAX_FRET) as u8;
 
             // offset
             let (inner, offset) = parse_int(i)?;
@@ -147,19 +181,13 @@ impl MusicParser {
             i = inner;
             track.color = color;
 
-            if self.song.version == GpVersion::GP5 {
-                // skip 44
-                i = skip(i, 44);
-            } else if self.song.version == GpVersion::GP5_10 {
-                // skip 49
-                i = skip(i, 49);
-            };
-
-            if self.song.version > GpVersion::GP5 {
-                let (inner, _) = parse_int_byte_sized_string(i)?;
-                i = inner;
-                let (inner, _) = parse_int_byte_sized_string(i)?;
+            if self.song.version >= GpVersion::GP5 {
+                // skip display settings
+                i = skip(i, 2);
+                let (inner, rse) = parse_track_rse(self.song.version)(i)?;
+                log::debug!("Track RSE: {rse:?}");
                 i = inner;
+                track.rse = Some(rse);
             };
             Ok((i, track))
         }
@@ -167,22 +195,40 @@ impl MusicParser {
 
     /// Read MIDI channel. MIDI channel in Guitar Pro is represented by two integers.
     /// First is zero-based number of channel, second is zero-based number of channel used for effects.
-    fn parse_track_channel(&mut self) -> impl FnMut(&[u8]) -> IResult<&[u8], i32> + '_ {
+    /// The channels are numbered within the one-based `port`, the channel table holds all the ports.
+    /// The channel of a drums track plays the drum kits, whatever its number.
+    fn parse_track_channel(
+        &mut self,
+        drums: bool,
+        port: u8,
+    ) -> impl FnMut(&[u8]) -> IResult<&[u8], i32> + '_ {
         log::debug!("Parsing track channel");
-        |i| {
+        move |i| {
             let (i, (mut gm_channel_1, mut gm_channel_2)) = (parse_int, parse_int).parse(i)?;
-            gm_channel_1 -= 1;
-            gm_channel_2 -= 1;
+            let channels = 1..=i32::from(PORT_CHANNELS * PORT_COUNT);
+            gm_channel_1 = clamped("MIDI channel", gm_channel_1, channels.clone()) - 1;
+            gm_channel_2 = clamped("MIDI effect channel", gm_channel_2, channels) - 1;
+            let port_offset = i32::from(port - 1) * i32::from(PORT_CHANNELS);
+            if (0..i32::from(PORT_CHANNELS)).contains(&gm_channel_1) {
+                gm_channel_1 += port_offset;
+            }
+            if (0..i32::from(PORT_CHANNELS)).contains(&gm_channel_2) {
+                gm_channel_2 += port_offset;
+            }
 
             log::debug!("Track channel gm1: {gm_channel_1} gm2: {gm_channel_2}");
 
             if let Some(channel) = self.song.midi_channels.get_mut(gm_channel_1 as usize) {
+                if drums && !channel.is_percussion() {
+                    log::debug!("Drums track on channel {gm_channel_1}");
+                    channel.bank = DEFAULT_PERCUSSION_BANK;
+                }
                 // if not percussion - set effect channel
-                if channel.channel_id != 9 {
+                if !channel.is_percussion() {
                     channel.effect_channel_id = gm_channel_2 as u8;
                 }
             } else {
-                log::debug!("channel {gm_channel_1} not found");
+                warn(format!("MIDI channel {gm_channel_1} not found"));
                 debug_assert!(false, "channel {gm_channel_1} not found");
             }
             Ok((i, gm_channel_1))
@@ -340,7 +386,7 @@ impl MusicParser {
                 let (inner, text) = parse_int_byte_sized_string(i)?;
                 i = inner;
                 log::debug!("Beat text: {text}");
-                beat.text = text;
+                beat.text = text.trim().to_string();
             }
 
             let mut note_effect = NoteEffect::default();
@@ -353,8 +399,13 @@ impl MusicParser {
 
             // parse mix change
             if (flags & 0x10) != 0 {
-                let (inner, ()) = self.parse_mix_change(measure_index)(i)?;
+                let (inner, (mix_table_change, wah)) =
+                    self.parse_mix_change(measure_index, start)(i)?;
                 i = inner;
+                beat.effect.wah = wah;
+                // wah only changes are kept as a beat effect
+                beat.mix_table_change =
+                    (mix_table_change != MixTableChange::default()).then_some(mix_table_change);
             }
 
             // parse notes
@@ -375,6 +426,7 @@ impl MusicParser {
                     beat.notes.push(note);
                 }
             }
+            beat.normalize_rests();
 
             if self.song.version >= GpVersion::GP5 {
                 i = skip(i, 1);
@@ -420,21 +472,26 @@ impl MusicParser {
     fn parse_mix_change(
         &mut self,
         measure_index: usize,
-    ) -> impl FnMut(&[u8]) -> IResult<&[u8], ()> + '_ {
+        beat_start: u32,
+    ) -> impl FnMut(&[u8]) -> IResult<&[u8], MixChange> + '_ {
         move |i: &[u8]| {
             log::debug!("Parsing mix change");
             let mut i = i;
 
             // instrument
-            let (inner, _) = parse_i8(i)?;
+            let (inner, instrument) = parse_i8(i)?;
             i = inner;
+            let mut change = MixTableChange {
+                instrument: (instrument >= 0).then_some(i32::from(instrument)),
+                ..MixTableChange::default()
+            };
 
             if self.song.version >= GpVersion::GP5 {
                 i = skip(i, 16);
             }
 
-            let (inner, (volume, pan, chorus, reverb, phaser, tremolo)) =
-                (parse_i8, parse_i8, parse_i8, parse_i8, parse_i8, parse_i8).parse(i)?;
+            // volume, pan, chorus, reverb, phaser and tremolo, negative values are unchanged
+            let (inner, values) = count(parse_i8, 6).parse(i)?;
             i = inner;
 
             let tempo_name = if self.song.version >= GpVersion::GP5 {
@@ -449,47 +506,75 @@ impl MusicParser {
             let (inner, tempo_value) = parse_int(i)?;
             i = inner;
 
-            if volume >= 0 {
-                i = skip(i, 1);
-            }
-            if pan >= 0 {
-                i = skip(i, 1);
-            }
-            if chorus >= 0 {
-                i = skip(i, 1);
-            }
-            if reverb >= 0 {
-                i = skip(i, 1);
-            }
-            if phaser >= 0 {
-                i = skip(i, 1);
-            }
-            if tremolo >= 0 {
-                i = skip(i, 1);
+            // transition of each changed value
+            let mut items = [None; 6];
+            for (item, value) in items.iter_mut().zip(values) {
+                if value >= 0 {
+                    let (inner, transition) = parse_i8(i)?;
+                    i = inner;
+                    *item = Some(MixTableItem {
+                        value,
+                        transition: transition.max(0) as u8,
+                        all_tracks: false,
+                    });
+                }
             }
 
             if tempo_value >= 0 {
+                let (inner, transition) = parse_i8(i)?;
+                i = inner;
+                // clamp to 1 BPM like the gp67 builder: tempo 0 would freeze playback
+                let tempo = TempoChange {
+                    value: (tempo_value as u32).max(1),
+                    transition: transition.max(0) as u8,
+                };
+                // the header holds the tempo at the start of the measure,
+                // a later or gradual change only reaches the next measures
+                let at_measure_start = beat_start == self.song.measure_headers[measure_index].start;
+                let first_measure = if at_measure_start && tempo.transition == 0 {
+                    measure_index
+                } else {
+                    measure_index + 1
+                };
                 // update tempo value for all next measure headers
-                self.song.measure_headers[measure_index..]
+                self.song
+                    .measure_headers
                     .iter_mut()
+                    .skip(first_measure)
                     .for_each(|mh| {
-                        // clamp to 1 BPM like the gp67 builder: tempo 0 would freeze playback
-                        mh.tempo.value = (tempo_value as u32).max(1);
+                        mh.tempo.value = tempo.value;
                         mh.tempo.name = Some(tempo_name.clone());
                     });
-                i = skip(i, 1);
+                change.tempo = Some(tempo);
                 if self.song.version > GpVersion::GP5 {
                     i = skip(i, 1);
                 }
             }
 
-            // GP3 has no trailing byte after the mix change block.
+            // GP3 has no flags for the values applied to all tracks
             if self.song.version > GpVersion::GP3 {
-                i = skip(i, 1);
+                let (inner, all_tracks) = parse_u8(i)?;
+                i = inner;
+                for (bit, item) in items.iter_mut().enumerate() {
+                    if let Some(item) = item {
+                        item.all_tracks = all_tracks & (1 << bit) != 0;
+                    }
+                }
             }
+            let [volume, balance, chorus, reverb, phaser, tremolo] = items;
+            change.volume = volume;
+            change.balance = balance;
+            change.chorus = chorus;
+            change.reverb = reverb;
+            change.phaser = phaser;
+            change.tremolo = tremolo;
 
+            let mut wah = None;
             if self.song.version >= GpVersion::GP5 {
-                i = skip(i, 1);
+                let (inner, wah_value) = parse_i8(i)?;
+                i = inner;
+                log::debug!("Wah value: {wah_value}");
+                wah = WahEffect::from_value(wah_value);
                 if self.song.version > GpVersion::GP5 {
                     let (inner, _) =
                         (parse_int_byte_sized_string, parse_int_byte_sized_string).parse(i)?;
@@ -497,7 +582,7 @@ impl MusicParser {
                 }
             }
 
-            Ok((i, ()))
+            Ok((i, (change, wah)))
         }
     }
 
@@ -545,10 +630,10 @@ impl MusicParser {
                 let value = if note.kind == NoteType::Tie {
                     self.get_tied_note_value(string, track_index)
                 } else {
-                    i16::from(fret)
+                    clamped("Fret", i16::from(fret), 0..=MAX_FRET as i16)
                 };
                 // value is between 0 and 99
-                if (0..100).contains(&value) {
+                if (0..=MAX_FRET as i16).contains(&value) {
                     note.value = value;
                 } else {
                     note.value = 0;
@@ -581,3 +666,38 @@ impl MusicParser {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::gp345::song_parser::parse_midi_channels;
+
+    #[test]
+    fn track_channels_of_each_port() {
+        let table = vec![0_u8; 12 * 64];
+        let (_, midi_channels) = parse_midi_channels(&table).unwrap();
+        let mut parser = MusicParser::new(Song {
+            midi_channels,
+            ..Song::default()
+        });
+        let int = |value: i32| value.to_le_bytes();
+        // one-based channels within the one-based port
+        let channels = |main: i32, effect: i32| [int(main), int(effect)].concat();
+        for (port, drums, main, effect, channel_id) in [
+            (1, false, 3, 4, 2),
+            (2, false, 3, 4, 18),
+            (4, false, 16, 16, 63),
+            (3, true, 1, 2, 32),
+        ] {
+            let data = channels(main, effect);
+            let (_, parsed) = parser.parse_track_channel(drums, port)(&data).unwrap();
+            assert_eq!(parsed, channel_id, "port {port} channel {main}");
+        }
+        let channel = |id: usize| &parser.song.midi_channels[id];
+        assert_eq!(channel(18).effect_channel_id, 19);
+        assert_eq!(channel(2).effect_channel_id, 3);
+        // drums on any channel play the drum kits
+        assert!(channel(32).is_percussion());
+        assert!(!channel(0).is_percussion());
+    }
+}
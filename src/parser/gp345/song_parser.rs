@@ -4,6 +4,7 @@ use crate::parser::gp345::primitive_parser::{
     parse_bool, parse_byte_size_string, parse_i8, parse_int, parse_int_byte_sized_string,
     parse_int_sized_string, parse_short, parse_u8, skip,
 };
+use crate::parser::warnings::{clamped, time_signature_denominator, warn};
 use nom::IResult;
 use nom::Parser;
 use nom::bytes::complete::take;
@@ -28,6 +29,29 @@ const fn to_stroke_value(raw: i8) -> u16 {
     }
 }
 
+/// Root or bass of a chord diagram, negative values are unset.
+fn chord_pitch_class(value: i32, sharp: bool) -> Option<PitchClass> {
+    (0..12).contains(&value).then(|| PitchClass {
+        sharp,
+        ..PitchClass::from(value as i8, None, Some(sharp))
+    })
+}
+
+/// Barres of a chord diagram from their frets, starts and ends, `barre_count` are used.
+fn chord_barres(barre_count: usize, frets: &[u8], starts: &[u8], ends: &[u8]) -> Vec<Barre> {
+    frets
+        .iter()
+        .zip(starts)
+        .zip(ends)
+        .take(barre_count)
+        .map(|((fret, start), end)| Barre {
+            fret: *fret,
+            start: *start,
+            end: *end,
+        })
+        .collect()
+}
+
 pub fn parse_chord(
     string_count: u8,
     version: GpVersion,
@@ -36,6 +60,7 @@ pub fn parse_chord(
         log::debug!("Parsing chords for {string_count} strings");
         let mut i = i;
         let mut chord = Chord {
+            length: string_count,
             strings: vec![-1; string_count.into()],
             ..Default::default()
         };
@@ -45,6 +70,7 @@ pub fn parse_chord(
         // chord header defines the version as well
         if (chord_gp4_header & 0x01) == 0 {
             log::debug!("Parsing simple chord");
+            chord.new_format = Some(false);
             let (inner, chord_name) = parse_int_byte_sized_string(i)?;
             log::debug!("Chord name {chord_name}");
             i = inner;
@@ -64,11 +90,23 @@ pub fn parse_chord(
             }
         } else if version == GpVersion::GP3 {
             log::debug!("Parsing diagram style chord (GP3)");
-            i = skip(i, 25);
-            let (inner, chord_name) = parse_byte_size_string(34)(i)?;
-            i = inner;
+            chord.new_format = Some(true);
+            let (inner, sharp) = parse_bool(i)?;
+            i = skip(inner, 3);
+            // the chord type, extension and tonality are spelled by the name
+            let (inner, (root, _kind, _extension, bass, _tonality, add)) = (
+                parse_int, parse_int, parse_int, parse_int, parse_int, parse_bool,
+            )
+                .parse(i)?;
+            chord.sharp = Some(sharp);
+            chord.root = chord_pitch_class(root, sharp);
+            chord.bass = chord_pitch_class(bass, sharp);
+            chord.add = Some(add);
+            let (inner, chord_name) = parse_byte_size_string(22)(inner)?;
             log::debug!("Chord name {chord_name}");
             chord.name = chord_name;
+            // fifth, ninth and eleventh alterations
+            i = skip(inner, 12);
             let (inner, first_fret) = parse_int(i)?;
             i = inner;
             log::debug!("Chord first fret {first_fret}");
@@ -81,15 +119,36 @@ pub fn parse_chord(
                     chord.strings[c as usize] = fret as i8;
                 }
             }
-            i = skip(i, 36);
+            let (inner, (barre_count, frets, starts, ends, omissions)) = (
+                parse_int,
+                count(map(parse_int, |fret| fret as u8), 2),
+                count(map(parse_int, |start| start as u8), 2),
+                count(map(parse_int, |end| end as u8), 2),
+                count(parse_bool, 7),
+            )
+                .parse(i)?;
+            chord.barres = chord_barres(barre_count.max(0) as usize, &frets, &starts, &ends);
+            chord.omissions = omissions;
+            i = skip(inner, 1);
         } else {
             log::debug!("Parsing diagram style chord");
-            i = skip(i, 16);
-            let (inner, chord_name) = parse_byte_size_string(21)(i)?;
-            i = inner;
+            chord.new_format = Some(true);
+            let (inner, sharp) = parse_bool(i)?;
+            i = skip(inner, 3);
+            // the chord type, extension and tonality are spelled by the name
+            let (inner, (root, _kind, _extension, bass, _tonality, add)) = (
+                parse_u8, parse_u8, parse_u8, parse_int, parse_int, parse_bool,
+            )
+                .parse(i)?;
+            chord.sharp = Some(sharp);
+            chord.root = chord_pitch_class(i32::from(root), sharp);
+            chord.bass = chord_pitch_class(bass, sharp);
+            chord.add = Some(add);
+            let (inner, chord_name) = parse_byte_size_string(22)(inner)?;
             log::debug!("Chord name {chord_name}");
             chord.name = chord_name;
-            i = skip(i, 4);
+            // fifth, ninth and eleventh alterations
+            i = skip(inner, 3);
             let (inner, first_fret) = parse_int(i)?;
             i = inner;
             log::debug!("Chord first fret {first_fret}");
@@ -102,7 +161,22 @@ pub fn parse_chord(
                     chord.strings[c as usize] = fret as i8;
                 }
             }
-            i = skip(i, 32);
+            let (inner, (barre_count, frets, starts, ends, omissions)) = (
+                parse_u8,
+                count(parse_u8, 5),
+                count(parse_u8, 5),
+                count(parse_u8, 5),
+                count(parse_bool, 7),
+            )
+                .parse(i)?;
+            chord.barres = chord_barres(usize::from(barre_count), &frets, &starts, &ends);
+            chord.omissions = omissions;
+            let (inner, (mut fingerings, show)) =
+                preceded(take(1usize), (count(parse_i8, 7), parse_bool)).parse(inner)?;
+            fingerings.truncate(string_count.into());
+            chord.fingerings = fingerings;
+            chord.show = Some(show);
+            i = inner;
         }
         Ok((i, chord))
     }
@@ -437,7 +511,14 @@ pub fn parse_beat_effects<'a>(
         }
 
         if flags2 & 0x02 != 0 {
-            i = skip(i, 1);
+            let (inner, direction) = parse_i8(i)?;
+            i = inner;
+            log::debug!("Parsing pick stroke {direction}");
+            beat.effect.pick_stroke = match direction {
+                1 => BeatStrokeDirection::Up,
+                2 => BeatStrokeDirection::Down,
+                _ => BeatStrokeDirection::None,
+            };
         }
 
         Ok((i, ()))
@@ -516,6 +597,12 @@ pub fn parse_tremolo_bar(i: &[u8]) -> IResult<&[u8], TremoloBarEffect> {
 /// * *1*: eighth note
 /// * *2*: sixteenth note
 /// * *3*: thirty-second note
+/// * *4*: sixty-fourth note
+///
+/// Values outside of this range are read as a quarter note.
+///
+/// If flag at *0x01* is true, the note is dotted.
+/// The format has no double dot flag, double-dotted durations only come from GP6/7 files.
 ///
 /// If flag at *0x20* is true, the tuplet is read
 pub fn parse_duration(flags: u8) -> impl FnMut(&[u8]) -> IResult<&[u8], Duration> {
@@ -525,7 +612,13 @@ pub fn parse_duration(flags: u8) -> impl FnMut(&[u8]) -> IResult<&[u8], Duration
         let mut d = Duration::default();
         let (inner, value) = parse_i8(i)?;
         i = inner;
-        d.value = (2_u32.saturating_pow((value + 4) as u32) / 4) as u16;
+        if let Some(value) = duration_value(value) {
+            d.value = value;
+        } else {
+            warn(format!(
+                "Unknown duration value {value}, read as a quarter note"
+            ));
+        }
         log::debug!("Duration value: {}", d.value);
         d.dotted = flags & 0x01 != 0;
 
@@ -533,20 +626,13 @@ pub fn parse_duration(flags: u8) -> impl FnMut(&[u8]) -> IResult<&[u8], Duration
             let (inner, i_tuplet) = parse_int(i)?;
             i = inner;
 
-            match i_tuplet {
-                3 => {
-                    d.tuplet_enters = i_tuplet as u8;
-                    d.tuplet_times = 2;
-                }
-                5..=7 => {
-                    d.tuplet_enters = i_tuplet as u8;
-                    d.tuplet_times = 4;
-                }
-                9..=13 => {
-                    d.tuplet_enters = i_tuplet as u8;
-                    d.tuplet_times = 8;
-                }
-                x => log::debug!("Unknown tuplet: {x}"),
+            if let Some((enters, times)) = tuplet_ratio(i_tuplet) {
+                d.tuplet_enters = enters;
+                d.tuplet_times = times;
+            } else {
+                warn(format!(
+                    "Unknown tuplet {i_tuplet}, played as a regular duration"
+                ));
             }
         }
 
@@ -554,6 +640,23 @@ pub fn parse_duration(flags: u8) -> impl FnMut(&[u8]) -> IResult<&[u8], Duration
     }
 }
 
+/// Note value of a raw duration, from -2 (whole note) to 4 (sixty-fourth note).
+pub fn duration_value(raw: i8) -> Option<u16> {
+    (-2..=4)
+        .contains(&raw)
+        .then(|| (2_u32.pow((raw + 4) as u32) / 4) as u16)
+}
+
+/// Notes entering in the time of how many regular notes, for a raw tuplet.
+pub const fn tuplet_ratio(tuplet: i32) -> Option<(u8, u8)> {
+    match tuplet {
+        3 => Some((3, 2)),
+        5..=7 => Some((tuplet as u8, 4)),
+        9..=13 => Some((tuplet as u8, 8)),
+        _ => None,
+    }
+}
+
 pub fn parse_color(i: &[u8]) -> IResult<&[u8], i32> {
     log::debug!("Parsing RGB color");
     map(
@@ -605,7 +708,8 @@ pub fn parse_measure_header(
             log::debug!("Parsing numerator");
             let (inner, numerator) = parse_i8(i)?;
             i = inner;
-            mh.time_signature.numerator = numerator as u8;
+            mh.time_signature.numerator =
+                clamped("Time signature numerator", numerator, 1..=32) as u8;
         }
 
         // Denominator of the (key) signature
@@ -614,7 +718,7 @@ pub fn parse_measure_header(
             let (inner, denominator_value) = parse_i8(i)?;
             i = inner;
             let denominator = Duration {
-                value: denominator_value as u16,
+                value: time_signature_denominator(i32::from(denominator_value)),
                 ..Default::default()
             };
             mh.time_signature.denominator = denominator;
@@ -803,24 +907,34 @@ pub fn parse_midi_channel(channel_id: i32) -> impl FnMut(&[u8]) -> IResult<&[u8]
                 _blank,
                 _blank2,
             )| {
-                let bank = if channel_id == 9 {
+                let bank = if MidiChannel::is_percussion_channel(channel_id as u8) {
                     DEFAULT_PERCUSSION_BANK
                 } else {
                     DEFAULT_BANK
                 };
+                // unused channels have no instrument
                 if instrument < 0 {
                     instrument = 0;
                 }
+                let instrument = clamped(
+                    &format!("Channel {} instrument", channel_id + 1),
+                    instrument,
+                    0..=127,
+                );
+                // raw Guitar Pro levels
+                let level = |what: &str, value: i8| {
+                    clamped(&format!("Channel {} {what}", channel_id + 1), value, 0..=16)
+                };
                 MidiChannel {
                     channel_id: channel_id as u8,
                     effect_channel_id: 0, // filled at the track level
                     instrument,
-                    volume,
-                    balance,
-                    chorus,
-                    reverb,
-                    phaser,
-                    tremolo,
+                    volume: level("volume", volume),
+                    balance: level("balance", balance),
+                    chorus: level("chorus", chorus),
+                    reverb: level("reverb", reverb),
+                    phaser: level("phaser", phaser),
+                    tremolo: level("tremolo", tremolo),
                     bank,
                 }
             },
@@ -913,6 +1027,92 @@ pub fn parse_lyrics(i: &[u8]) -> IResult<&[u8], Lyrics> {
     .parse(i)
 }
 
+/// Parse a RSE equalizer: one value per band followed by the gain.
+pub fn parse_rse_equalizer(
+    value_count: usize,
+) -> impl FnMut(&[u8]) -> IResult<&[u8], RseEqualizer> {
+    move |i| {
+        map(count(parse_i8, value_count), |mut knobs| {
+            let gain = knobs.pop().unwrap_or_default();
+            RseEqualizer { knobs, gain }
+        })
+        .parse(i)
+    }
+}
+
+/// Parse the RSE master effect (GP5.10): volume and 10 bands equalizer.
+///
+/// The master reverb is stored later with the music data.
+pub fn parse_rse_master_effect(i: &[u8]) -> IResult<&[u8], RseMasterEffect> {
+    log::debug!("Parsing RSE master effect");
+    map(
+        (parse_int, take(4usize), parse_rse_equalizer(11)),
+        |(volume, _unknown, equalizer)| RseMasterEffect {
+            volume,
+            reverb: 0,
+            equalizer,
+        },
+    )
+    .parse(i)
+}
+
+/// Parse the RSE instrument of a track, its effect chain is read by [`parse_track_rse`].
+pub fn parse_rse_instrument(
+    version: GpVersion,
+) -> impl FnMut(&[u8]) -> IResult<&[u8], RseInstrument> {
+    move |i| {
+        let (i, (instrument, unknown, sound_bank)) = (parse_int, parse_int, parse_int).parse(i)?;
+        let (i, effect_number) = if version == GpVersion::GP5 {
+            let (i, effect_number) = parse_short(i)?;
+            (skip(i, 1), i32::from(effect_number))
+        } else {
+            parse_int(i)?
+        };
+        let instrument = RseInstrument {
+            instrument,
+            unknown,
+            sound_bank,
+            effect_number,
+            ..Default::default()
+        };
+        Ok((i, instrument))
+    }
+}
+
+/// Parse the RSE settings found at the end of a GP5 track.
+pub fn parse_track_rse(version: GpVersion) -> impl FnMut(&[u8]) -> IResult<&[u8], TrackRse> {
+    move |i| {
+        log::debug!("Parsing track RSE");
+        let (i, auto_accentuation) = parse_u8(i)?;
+        // MIDI bank, already known from the MIDI channel
+        let i = skip(i, 1);
+        let (i, humanize) = parse_u8(i)?;
+        // unknown
+        let i = skip(i, 24);
+        let (mut i, mut instrument) = parse_rse_instrument(version)(i)?;
+        let mut equalizer = None;
+        if version > GpVersion::GP5 {
+            let (inner, (eq, effect, effect_category)) = (
+                parse_rse_equalizer(4),
+                parse_int_byte_sized_string,
+                parse_int_byte_sized_string,
+            )
+                .parse(i)?;
+            i = inner;
+            equalizer = Some(eq);
+            instrument.effect = effect;
+            instrument.effect_category = effect_category;
+        }
+        let rse = TrackRse {
+            humanize,
+            auto_accentuation,
+            instrument,
+            equalizer,
+        };
+        Ok((i, rse))
+    }
+}
+
 /// Parse the version string from the file header.
 ///
 /// 30 character string (not counting the byte announcing the real length of the string)
@@ -1000,24 +1200,24 @@ pub fn parse_gp345_data(file_data: &[u8]) -> Result<Song, RuxError> {
     let (rest, base_song) = flat_map(parse_gp_version, |version| {
         map(
             (
-                parse_info(version),                                     // Song info
-                cond(version < GpVersion::GP5, parse_bool),              // Triplet feel
-                cond(version >= GpVersion::GP4, parse_lyrics),           // Lyrics
-                cond(version >= GpVersion::GP5_10, take(19usize)),       // Skip RSE master effect
-                cond(version >= GpVersion::GP5, parse_page_setup),       // Page setup
-                cond(version >= GpVersion::GP5, parse_int_sized_string), // Tempo name
-                parse_int,                                               // Tempo value
-                cond(version > GpVersion::GP5, parse_bool),              // Tempo hide
-                parse_i8,                                                // Key signature
-                take(3usize),                                            // unknown
-                cond(version > GpVersion::GP3, parse_i8),                // Octave
-                parse_midi_channels,                                     // Midi channels
+                parse_info(version),                                         // Song info
+                cond(version < GpVersion::GP5, parse_bool),                  // Triplet feel
+                cond(version >= GpVersion::GP4, parse_lyrics),               // Lyrics
+                cond(version >= GpVersion::GP5_10, parse_rse_master_effect), // RSE master effect
+                cond(version >= GpVersion::GP5, parse_page_setup),           // Page setup
+                cond(version >= GpVersion::GP5, parse_int_sized_string),     // Tempo name
+                parse_int,                                                   // Tempo value
+                cond(version > GpVersion::GP5, parse_bool),                  // Tempo hide
+                parse_i8,                                                    // Key signature
+                take(3usize),                                                // unknown
+                cond(version > GpVersion::GP3, parse_i8),                    // Octave
+                parse_midi_channels,                                         // Midi channels
             ),
             move |(
                 song_info,
                 triplet_feel,
                 lyrics,
-                _master_effect,
+                master_effect,
                 page_setup,
                 tempo_name,
                 tempo,
@@ -1039,9 +1239,11 @@ pub fn parse_gp345_data(file_data: &[u8]) -> Result<Song, RuxError> {
                     hide_tempo,
                     key_signature,
                     octave,
+                    master_effect,
                     midi_channels,
                     measure_headers: vec![],
                     tracks: vec![],
+                    warnings: vec![],
                 }
             },
         )
@@ -1085,6 +1287,205 @@ mod tests {
         assert!(GpVersion::GP3 < GpVersion::GP5);
     }
 
+    #[test]
+    fn test_parse_duration_values() {
+        let expected = [(-2, 1), (-1, 2), (0, 4), (1, 8), (2, 16), (3, 32), (4, 64)];
+        for (raw, value) in expected {
+            let data = [raw as u8];
+            let (rest, duration) = parse_duration(0)(&data).unwrap();
+            assert!(rest.is_empty());
+            assert_eq!(duration.value, value);
+            assert!(!duration.dotted);
+            assert!(!duration.double_dotted);
+        }
+    }
+
+    #[test]
+    fn test_parse_duration_unknown_value() {
+        // out of range values must not produce a zero length duration
+        for raw in [-5_i8, 5, 100] {
+            let (_, duration) = parse_duration(0)(&[raw as u8]).unwrap();
+            assert_eq!(duration.value, QUARTER);
+            assert_eq!(duration.time(), QUARTER_TIME);
+        }
+    }
+
+    #[test]
+    fn test_parse_duration_dotted_tuplet() {
+        // dotted eighth in a triplet
+        let data = [1, 3, 0, 0, 0];
+        let (rest, duration) = parse_duration(0x01 | 0x20)(&data).unwrap();
+        assert!(rest.is_empty());
+        assert_eq!(duration.value, 8);
+        assert!(duration.dotted);
+        assert_eq!((duration.tuplet_enters, duration.tuplet_times), (3, 2));
+        assert_eq!(duration.time(), 480);
+    }
+
+    #[test]
+    fn test_duration_time_dots() {
+        let mut duration = Duration {
+            value: 8,
+            ..Default::default()
+        };
+        assert_eq!(duration.time(), 480);
+        duration.dotted = true;
+        assert_eq!(duration.time(), 720);
+        duration.dotted = false;
+        duration.double_dotted = true;
+        assert_eq!(duration.time(), 840);
+        duration.value = 64;
+        assert_eq!(duration.time(), 105);
+    }
+
+    #[test]
+    fn test_parse_midi_channel_percussion() {
+        let data = [0_u8; 12];
+        // channel 10 of every port
+        for (channel_id, percussion) in [(8, false), (9, true), (24, false), (25, true), (41, true)]
+        {
+            let (_, channel) = parse_midi_channel(channel_id)(&data).unwrap();
+            assert_eq!(channel.is_percussion(), percussion, "channel {channel_id}");
+        }
+    }
+
+    #[test]
+    fn test_parse_midi_channel_clamped() {
+        // instrument 200, volume 200 read as -56, balance 40
+        let mut data = 200_i32.to_le_bytes().to_vec();
+        data.extend([200, 40, 0, 0, 0, 0, 0, 0]);
+        let ((_, channel), warnings) =
+            crate::parser::warnings::collect(|| parse_midi_channel(3)(&data).unwrap());
+        assert_eq!(channel.instrument, 127);
+        assert_eq!(channel.volume, 0);
+        assert_eq!(channel.balance, 16);
+        assert_eq!(
+            warnings,
+            vec![
+                "Channel 4 instrument 200 out of range 0..=127, clamped",
+                "Channel 4 volume -56 out of range 0..=16, clamped",
+                "Channel 4 balance 40 out of range 0..=16, clamped",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_gp3_chord_diagram() {
+        let int = |value: i32| value.to_le_bytes().to_vec();
+        let mut data = vec![0x01, 0, 0, 0, 0]; // diagram, flat
+        for value in [7, 0, 0, 11, 0] {
+            data.extend(int(value)); // root G, type, extension, bass B, tonality
+        }
+        data.push(0); // no added note
+        data.push(3);
+        data.extend(b"G/B".iter().chain(&[0; 19])); // name in 22 bytes
+        data.extend([0; 12]); // alterations
+        data.extend(int(1));
+        for fret in [3, 0, 0, 0, 2, -1] {
+            data.extend(int(fret));
+        }
+        data.extend(int(1)); // barres
+        for value in [2, 0, 1, 0, 5, 0] {
+            data.extend(int(value)); // frets, starts and ends
+        }
+        data.extend([0, 0, 0, 1, 1, 1, 1, 0]); // omissions and padding
+        let (rest, chord) = parse_chord(6, GpVersion::GP3)(&data).unwrap();
+        assert!(rest.is_empty());
+        assert_eq!(chord.name, "G/B");
+        assert_eq!(chord.sharp, Some(false));
+        assert_eq!(chord.root.map(|p| p.value), Some(7));
+        assert_eq!(chord.bass.map(|p| p.value), Some(11));
+        assert_eq!(chord.strings, vec![3, 0, 0, 0, 2, -1]);
+        assert_eq!(
+            chord.barres,
+            vec![Barre {
+                fret: 2,
+                start: 1,
+                end: 5
+            }]
+        );
+        assert_eq!(chord.omissions[3..], [true; 4]);
+        // GP3 diagrams have no fingering
+        assert!(chord.fingerings.is_empty());
+    }
+
+    #[test]
+    fn duration_round_trips_for_all_flags() {
+        let tuplets = [None, Some(3), Some(5), Some(6), Some(7)]
+            .into_iter()
+            .chain((9..=13).map(Some));
+        for tuplet in tuplets {
+            for raw in -2_i8..=4 {
+                for dotted in [false, true] {
+                    let mut data = vec![raw as u8];
+                    let mut flags = u8::from(dotted);
+                    if let Some(tuplet) = tuplet {
+                        flags |= 0x20;
+                        data.extend_from_slice(&i32::to_le_bytes(tuplet));
+                    }
+                    let (rest, duration) = parse_duration(flags)(&data).unwrap();
+                    assert!(rest.is_empty());
+                    assert_eq!(Some(duration.value), duration_value(raw));
+                    assert_eq!(duration.dotted, dotted);
+                    let (enters, times) = tuplet.and_then(tuplet_ratio).unwrap_or((1, 1));
+                    assert_eq!(
+                        (duration.tuplet_enters, duration.tuplet_times),
+                        (enters, times)
+                    );
+
+                    // exact fraction of a whole note, floored to a tick
+                    let (dot_num, dot_den) = if dotted { (3, 2) } else { (1, 1) };
+                    let expected = QUARTER_TIME * 4 * dot_num * u32::from(times)
+                        / (u32::from(duration.value) * dot_den * u32::from(enters));
+                    assert_eq!(duration.time(), expected, "{duration:?}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn double_dotted_durations_are_exact() {
+        for raw in -2_i8..=4 {
+            let duration = Duration {
+                value: duration_value(raw).unwrap(),
+                double_dotted: true,
+                ..Duration::default()
+            };
+            let plain = QUARTER_TIME * 4 / u32::from(duration.value);
+            assert_eq!(duration.time(), plain * 7 / 4);
+        }
+    }
+
+    #[test]
+    fn test_parse_rse_equalizer() {
+        let data = [-35_i8 as u8, 0, 20, 5];
+        let (rest, equalizer) = parse_rse_equalizer(4)(&data).unwrap();
+        assert!(rest.is_empty());
+        assert_eq!(equalizer.knobs, vec![-35, 0, 20]);
+        assert_eq!(equalizer.gain, 5);
+    }
+
+    #[test]
+    fn test_wah_effect_from_value() {
+        assert_eq!(WahEffect::from_value(-2), Some(WahEffect::Off));
+        assert_eq!(WahEffect::from_value(-1), None);
+        assert_eq!(WahEffect::from_value(0), Some(WahEffect::OPEN));
+        assert_eq!(WahEffect::from_value(100), Some(WahEffect::CLOSED));
+        assert_eq!(WahEffect::from_value(101), None);
+    }
+
+    #[test]
+    fn test_parse_pick_stroke() {
+        let mut beat = Beat::default();
+        let mut note_effect = NoteEffect::default();
+        // flags2 0x02: pick stroke byte follows (2 = down)
+        let data = [0x00, 0x02, 0x02];
+        let (rest, ()) =
+            parse_beat_effects(&mut beat, &mut note_effect, GpVersion::GP5)(&data).unwrap();
+        assert!(rest.is_empty());
+        assert_eq!(beat.effect.pick_stroke, BeatStrokeDirection::Down);
+    }
+
     #[test]
     fn test_convert_repeat_alternative() {
         // successive voltas 1, 2, 3 become bits 0, 1, 2
@@ -42,6 +42,9 @@ pub struct GpxTrack {
     pub gm_program: i32,
     pub gm_channel_1: i32,
     pub gm_channel_2: i32,
+    pub solo: bool,
+    pub mute: bool,
+    pub pan: Option<f32>, // mixer pan, 0.0 (left) to 1.0 (right)
 }
 
 #[derive(Debug, Default)]
@@ -65,6 +68,7 @@ pub struct GpxMasterBar {
     pub triplet_feel: Option<String>,
     pub alternate_endings: Option<Vec<i32>>,
     pub marker_text: Option<String>,
+    pub directions: Vec<String>, // `Target` and `Jump` names
 }
 
 #[derive(Debug, Default)]
@@ -96,6 +100,8 @@ pub struct GpxBeat {
     pub tremolo: Option<Vec<i32>>,
     pub fadding: Option<String>,
     pub text: String,
+    /// Wah pedal state: "Open" / "Closed".
+    pub wah: Option<String>,
     pub chord_id: Option<i32>,
     pub grace_notes: Option<String>,
     pub whammy_bar_enabled: bool,
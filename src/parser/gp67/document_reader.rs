@@ -11,6 +11,9 @@ use crate::parser::gp67::document::{
 };
 use roxmltree::{Document, Node};
 
+/// Position of the pan in the `<ChannelStrip>` parameters of a track.
+const CHANNEL_STRIP_PAN: usize = 11;
+
 /// GPIF schema flavour. GP6 (`.gpx`) and GP7 (`.gp`) share most of the document
 /// but differ in how a track's MIDI assignment and properties are laid out.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -91,6 +94,25 @@ fn read_tracks(root: Node, gpx: &mut GpxDocument, version: GpifVersion) {
             ..Default::default()
         };
 
+        // playback state of the mixer: Default, Solo or Mute
+        match child_text(node, "PlaybackState").as_deref() {
+            Some("Solo") => track.solo = true,
+            Some("Mute") => track.mute = true,
+            _ => {}
+        }
+
+        // mixer settings, listed without names in the RSE channel strip
+        track.pan = child(node, "RSE")
+            .and_then(|rse| child(rse, "ChannelStrip"))
+            .and_then(|strip| child_text(strip, "Parameters"))
+            .and_then(|parameters| {
+                parameters
+                    .split_whitespace()
+                    .nth(CHANNEL_STRIP_PAN)?
+                    .parse()
+                    .ok()
+            });
+
         match version {
             GpifVersion::Gp6 => read_track_midi_gp6(node, &mut track, gpx),
             GpifVersion::Gp7 => read_track_midi_gp7(node, &mut track, gpx),
@@ -151,19 +173,27 @@ fn read_track_midi_gp7(node: Node, track: &mut GpxTrack, gpx: &GpxDocument) {
         }
     }
 
+    let instrument_set = child(node, "InstrumentSet");
+    let drum_kit = instrument_set
+        .and_then(|set| child_text(set, "Type"))
+        .is_some_and(|s| s.to_lowercase().contains("drum"));
     if primary >= 0 && secondary >= 0 {
+        // drum kits are sometimes connected to a melodic channel
+        if drum_kit && primary.rem_euclid(16) != DEFAULT_PERCUSSION_CHANNEL {
+            log::debug!("Drum kit on channel {primary}");
+            primary = DEFAULT_PERCUSSION_CHANNEL;
+            secondary = DEFAULT_PERCUSSION_CHANNEL;
+        }
         track.gm_channel_1 = primary;
         track.gm_channel_2 = secondary;
         return;
     }
 
     // Unusual .gp file: channels are not defined, so guess percussion.
-    let mut is_percussion = false;
-    if let Some(instrument_set) = child(node, "InstrumentSet") {
+    let mut is_percussion = drum_kit;
+    if let Some(instrument_set) = instrument_set {
         is_percussion |=
             child_text(instrument_set, "Name").is_some_and(|s| s.to_lowercase().contains("drum"));
-        is_percussion |=
-            child_text(instrument_set, "Type").is_some_and(|s| s.to_lowercase().contains("drum"));
     }
     is_percussion &= track.gm_program == 0;
 
@@ -235,6 +265,14 @@ fn read_master_bars(root: Node, gpx: &mut GpxDocument) {
             mbar.accidental_count = child_int(key, "AccidentalCount", 0);
             mbar.mode = child_text(key, "Mode");
         }
+        if let Some(directions) = child(node, "Directions") {
+            mbar.directions = directions
+                .children()
+                .filter(Node::is_element)
+                .filter_map(|n| n.text())
+                .map(|t| t.trim().to_string())
+                .collect();
+        }
         if let Some(section) = child(node, "Section") {
             mbar.marker_text =
                 child_text(section, "Text").map(|t| t.replace('\n', "").trim().to_string());
@@ -287,6 +325,7 @@ fn read_beats(root: Node, gpx: &mut GpxDocument) {
             chord_id: child_int_opt(node, "Chord"),
             fadding: child_text(node, "Fadding"),
             grace_notes: child_text(node, "GraceNotes"),
+            wah: child_text(node, "Wah"),
             ..Default::default()
         };
 
@@ -608,5 +647,9 @@ mod tests {
         let track = &doc.tracks[0];
         assert!(!track.name.is_empty());
         assert!(track.tuning_pitches.as_ref().is_some_and(|t| !t.is_empty()));
+
+        // The rhythm guitars are panned apart.
+        let pans: Vec<Option<f32>> = doc.tracks.iter().map(|t| t.pan).collect();
+        assert_eq!(pans[..4], [Some(0.5), Some(0.5), Some(0.35), Some(0.65)]);
     }
 }
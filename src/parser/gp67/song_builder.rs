@@ -14,13 +14,14 @@ use crate::parser::gp67::file_system::GpxFileSystem;
 use crate::parser::song_parser::{
     BEND_EFFECT_MAX_POSITION_LENGTH, Beat, BeatEffects, BeatStroke, BeatStrokeDirection,
     BendEffect, BendPoint, Chord, DEFAULT_BANK, DEFAULT_PERCUSSION_BANK, DEFAULT_VELOCITY,
-    DURATION_SIXTEENTH, DURATION_SIXTY_FOURTH, DURATION_THIRTY_SECOND, Duration, GP_BEND_SEMITONE,
-    GpVersion, GraceEffect, GraceEffectTransition, HarmonicEffect, HarmonicType, KeySignature,
-    MAX_VOICES, Marker, Measure, MeasureHeader, MidiChannel, Note, NoteEffect, NoteType, QUARTER,
-    QUARTER_TIME, SEMITONE_LENGTH, SlapEffect, SlideType, Song, SongInfo, Tempo, TimeSignature,
-    Track, TremoloBarEffect, TremoloPickingEffect, TrillEffect, TripletFeel, Voice,
-    convert_velocity,
+    DURATION_SIXTEENTH, DURATION_SIXTY_FOURTH, DURATION_THIRTY_SECOND, DirectionSign, Duration,
+    GP_BEND_SEMITONE, GpVersion, GraceEffect, GraceEffectTransition, HarmonicEffect, HarmonicType,
+    KeySignature, MAX_FRET, MAX_VOICES, Marker, Measure, MeasureHeader, MidiChannel, Note,
+    NoteEffect, NoteType, PORT_CHANNELS, PORT_COUNT, QUARTER, QUARTER_TIME, SEMITONE_LENGTH,
+    SlapEffect, SlideType, Song, SongInfo, Tempo, TimeSignature, Track, TremoloBarEffect,
+    TremoloPickingEffect, TrillEffect, TripletFeel, Voice, WahEffect, convert_velocity,
 };
+use crate::parser::warnings::{clamped, time_signature_denominator, warn};
 
 /// Position units used by GPX bend/whammy offsets (a full bar = 100%).
 const GP_POSITION_GPX: f32 = 100.0;
@@ -28,6 +29,8 @@ const GP_POSITION_GPX: f32 = 100.0;
 const GP_WHAMMY_SEMITONE: f32 = 50.0;
 /// Clamp for tremolo-bar point values.
 const TREMOLO_BAR_MAX_VALUE: i8 = 12;
+/// Slide flags marking a pick scrape (downwards / upwards).
+const PICK_SCRAPE_FLAGS: i32 = 0x40 | 0x80;
 /// Stroke speed assigned to GPX brushes (the score stores only a direction).
 const BRUSH_STROKE_VALUE: u16 = DURATION_THIRTY_SECOND as u16;
 
@@ -118,6 +121,11 @@ fn build_song_info(doc: &GpxDocument) -> SongInfo {
     }
 }
 
+/// Mixer pan (0.0 - 1.0) in the raw GP channel scale (0-16), centered on 8.
+fn gp_channel_pan(pan: f32) -> i8 {
+    (pan.clamp(0.0, 1.0) * 16.0).round() as i8
+}
+
 /// Build the track shells (without measures) and their MIDI channels.
 fn build_tracks(doc: &GpxDocument) -> (Vec<Track>, Vec<MidiChannel>) {
     let mut tracks = Vec::with_capacity(doc.tracks.len());
@@ -125,16 +133,17 @@ fn build_tracks(doc: &GpxDocument) -> (Vec<Track>, Vec<MidiChannel>) {
     let mut next_channel: u8 = 0;
 
     for (index, gp_track) in doc.tracks.iter().enumerate() {
-        let is_percussion = gp_track.gm_channel_1 == DEFAULT_PERCUSSION_CHANNEL;
+        let is_percussion = gp_track.gm_channel_1.rem_euclid(16) == DEFAULT_PERCUSSION_CHANNEL;
+        // past the 15 melodic channels of a port the numbering continues on the next ports
         let channel_id = if is_percussion {
             9
         } else {
             let c = next_channel;
-            next_channel += 1;
-            if next_channel == 9 {
-                next_channel += 1; // reserve channel 9 for percussion
+            next_channel = next_channel.saturating_add(1);
+            if MidiChannel::is_percussion_channel(next_channel) {
+                next_channel += 1; // reserve channel 9 of each port for percussion
             }
-            c.min(15)
+            c.min(PORT_CHANNELS * PORT_COUNT - 1)
         };
 
         channels.push(MidiChannel {
@@ -143,11 +152,11 @@ fn build_tracks(doc: &GpxDocument) -> (Vec<Track>, Vec<MidiChannel>) {
             instrument: if is_percussion {
                 0
             } else {
-                gp_track.gm_program
+                clamped("Instrument", gp_track.gm_program, 0..=127)
             },
             // Raw GP channel scale (0-16), scaled to MIDI 0-127 at emit time.
             volume: 16, // full
-            balance: 8, // center
+            balance: gp_track.pan.map_or(8, gp_channel_pan),
             chorus: 0,
             reverb: 0,
             phaser: 0,
@@ -167,8 +176,11 @@ fn build_tracks(doc: &GpxDocument) -> (Vec<Track>, Vec<MidiChannel>) {
 
         tracks.push(Track {
             number: index as i32 + 1,
-            offset: gp_track.capo,
+            offset: clamped("Capo", gp_track.capo, 0..=MAX_FRET),
             channel_id,
+            midi_port: channel_id / PORT_CHANNELS + 1,
+            solo: gp_track.solo,
+            mute: gp_track.mute,
             name: gp_track.name.clone(),
             strings,
             color,
@@ -266,9 +278,9 @@ fn build_measure_header(
         && time.len() == 2
     {
         header.time_signature = TimeSignature {
-            numerator: time[0] as u8,
+            numerator: clamped("Time signature numerator", time[0], 1..=32) as u8,
             denominator: Duration {
-                value: time[1] as u16,
+                value: time_signature_denominator(time[1]),
                 ..Default::default()
             },
         };
@@ -288,6 +300,12 @@ fn build_measure_header(
         header.tempo.value = tempo.max(1) as u32;
     }
 
+    header.directions = mbar
+        .directions
+        .iter()
+        .filter_map(|name| DirectionSign::from_name(name))
+        .collect();
+
     if let Some(text) = &mbar.marker_text
         && !text.is_empty()
     {
@@ -376,6 +394,10 @@ fn build_voice(
 
     for &beat_id in &gp_voice.beat_ids {
         let Some(gp_beat) = doc.beat(beat_id) else {
+            warn(format!(
+                "Missing beat {beat_id} in measure {}",
+                measure_index + 1
+            ));
             continue;
         };
         let duration = rhythm_to_duration(doc.rhythm(gp_beat.rhythm_id));
@@ -404,30 +426,40 @@ fn build_voice(
             effect: BeatEffects {
                 stroke: stroke_of(gp_beat),
                 chord: chord_of(doc, gp_beat),
+                pick_stroke: pick_stroke_of(gp_beat),
+                wah: wah_of(gp_beat),
+                ..Default::default()
             },
             notes: Vec::new(),
+            mix_table_change: None,
         };
 
         if let Some(note_ids) = &gp_beat.note_ids {
             let velocity = dynamic_velocity(gp_beat.dynamic.as_deref());
             for &id in note_ids {
-                if let Some(gp_note) = doc.note(id)
-                    && let Some(note) = build_note(
-                        gp_note,
-                        strings,
-                        &beat.notes,
-                        velocity,
-                        gp_beat,
-                        previous_beat,
-                        &previous_grace_notes,
-                        &previous_duration,
-                    )
-                {
+                let Some(gp_note) = doc.note(id) else {
+                    warn(format!(
+                        "Missing note {id} in measure {}",
+                        measure_index + 1
+                    ));
+                    continue;
+                };
+                if let Some(note) = build_note(
+                    gp_note,
+                    strings,
+                    &beat.notes,
+                    velocity,
+                    gp_beat,
+                    previous_beat,
+                    &previous_grace_notes,
+                    &previous_duration,
+                ) {
                     beat.notes.push(note);
                 }
             }
         }
 
+        beat.effect.pick_scrape = beat_has_pick_scrape(doc, gp_beat);
         start += beat.duration.time();
         voice.beats.push(beat);
         previous_beat = Some(gp_beat);
@@ -453,7 +485,12 @@ fn rhythm_to_duration(rhythm: Option<&GpxRhythm>) -> Duration {
             Some("16th") => 16,
             Some("32nd") => 32,
             Some("64th") => 64,
-            _ => QUARTER,
+            other => {
+                warn(format!(
+                    "Unknown note value {other:?}, read as a quarter note"
+                ));
+                QUARTER
+            }
         };
     }
     duration
@@ -473,6 +510,30 @@ fn stroke_of(gp_beat: &GpxBeat) -> BeatStroke {
     }
 }
 
+fn pick_stroke_of(gp_beat: &GpxBeat) -> BeatStrokeDirection {
+    match gp_beat.pick_stroke.as_str() {
+        "Down" => BeatStrokeDirection::Down,
+        "Up" => BeatStrokeDirection::Up,
+        _ => BeatStrokeDirection::None,
+    }
+}
+
+fn wah_of(gp_beat: &GpxBeat) -> Option<WahEffect> {
+    match gp_beat.wah.as_deref() {
+        Some("Open") => Some(WahEffect::OPEN),
+        Some("Closed") => Some(WahEffect::CLOSED),
+        _ => None,
+    }
+}
+
+/// Pick scrapes are stored as slide flags on the notes of the beat.
+fn beat_has_pick_scrape(doc: &GpxDocument, gp_beat: &GpxBeat) -> bool {
+    gp_beat.note_ids.iter().flatten().any(|&id| {
+        doc.note(id)
+            .is_some_and(|n| n.slide && n.slide_flags & PICK_SCRAPE_FLAGS != 0)
+    })
+}
+
 fn chord_of(doc: &GpxDocument, gp_beat: &GpxBeat) -> Option<Chord> {
     let gp_chord = doc.chord(gp_beat.chord_id?)?;
     let mut chord = Chord {
@@ -530,7 +591,7 @@ fn build_note(
     }
 
     let mut note = Note::new(NoteEffect::default());
-    note.value = value as i16;
+    note.value = clamped("Fret", value, 0..=MAX_FRET) as i16;
     note.string = string as i8;
     note.velocity = velocity;
     note.kind = if gp_note.tie_destination {
@@ -561,7 +622,7 @@ fn build_note(
         SlapEffect::None
     };
     if gp_note.slide {
-        effect.slide = Some(slide_type_of(gp_note.slide_flags));
+        effect.slide = slide_type_of(gp_note.slide_flags);
     }
     effect.trill = trill_of(gp_note, value);
     effect.tremolo_picking = tremolo_picking_of(gp_beat);
@@ -605,19 +666,22 @@ fn string_for(strings: &[(i32, i32)], beat_notes: &[Note], value: i32) -> Option
     None
 }
 
-const fn slide_type_of(flags: i32) -> SlideType {
+/// Slide of a note, `None` when the flags only carry a pick scrape.
+const fn slide_type_of(flags: i32) -> Option<SlideType> {
     if flags & 0x02 != 0 {
-        SlideType::LegatoSlideTo
+        Some(SlideType::LegatoSlideTo)
     } else if flags & 0x04 != 0 {
-        SlideType::OutDownwards
+        Some(SlideType::OutDownwards)
     } else if flags & 0x08 != 0 {
-        SlideType::OutUpWards
+        Some(SlideType::OutUpWards)
     } else if flags & 0x10 != 0 {
-        SlideType::IntoFromBelow
+        Some(SlideType::IntoFromBelow)
     } else if flags & 0x20 != 0 {
-        SlideType::IntoFromAbove
+        Some(SlideType::IntoFromAbove)
+    } else if flags & PICK_SCRAPE_FLAGS != 0 && flags & 0x01 == 0 {
+        None
     } else {
-        SlideType::ShiftSlideTo
+        Some(SlideType::ShiftSlideTo)
     }
 }
 
@@ -1,11 +1,17 @@
+pub mod diff;
 pub mod gp345;
 pub mod gp67;
 pub mod model;
 mod parse;
+pub mod ruxg;
 pub mod song_parser_tests;
+pub mod warnings;
 
 // Top-level parsing entry point (dispatches by container format).
-pub use parse::parse_gp_data;
+pub use parse::{
+    ParseOutcome, SUPPORTED_EXTENSIONS, TabFormat, detect_extension, is_tab_file, open_tab_file,
+    parse_gp_data, parse_tab_data,
+};
 
 // The GP3/4/5 binary parser lives in `gp345`; re-export `song_parser` at the
 // parser root since it is the model re-export hub used across the audio, UI
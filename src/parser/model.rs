@@ -1,5 +1,7 @@
 //! Data model for parsed Guitar Pro songs (version-agnostic).
 
+use serde::{Deserialize, Serialize};
+
 pub const MAX_VOICES: u32 = 2;
 
 pub const QUARTER_TIME: u32 = 960;
@@ -21,6 +23,12 @@ pub const SHARP_NOTES: [&str; 12] = [
 ];
 
 pub const DEFAULT_PERCUSSION_BANK: u8 = 128;
+/// Highest fret of the notes and of the fretboards.
+pub const MAX_FRET: i32 = 99;
+
+/// MIDI channels per port, Guitar Pro addresses 4 ports.
+pub const PORT_CHANNELS: u8 = 16;
+pub const PORT_COUNT: u8 = 4;
 
 pub const DEFAULT_BANK: u8 = 0;
 
@@ -33,7 +41,23 @@ pub const fn convert_velocity(v: i16) -> i16 {
     MIN_VELOCITY + (VELOCITY_INCREMENT * v) - VELOCITY_INCREMENT
 }
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Default)]
+/// Dynamic markings of the Guitar Pro dynamic values 1 to 8.
+pub const DYNAMIC_MARKINGS: [&str; 8] = ["ppp", "pp", "p", "mp", "mf", "f", "ff", "fff"];
+
+/// Dynamic marking of a raw MIDI velocity, the reverse of `convert_velocity`.
+pub const fn dynamic_marking(velocity: i16) -> &'static str {
+    let level = (velocity - MIN_VELOCITY + VELOCITY_INCREMENT / 2) / VELOCITY_INCREMENT;
+    let index = if level < 0 {
+        0
+    } else if level as usize >= DYNAMIC_MARKINGS.len() {
+        DYNAMIC_MARKINGS.len() - 1
+    } else {
+        level as usize
+    };
+    DYNAMIC_MARKINGS[index]
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Default, Serialize, Deserialize)]
 pub enum GpVersion {
     #[default]
     GP3,
@@ -45,7 +69,7 @@ pub enum GpVersion {
     GP7,
 }
 
-#[derive(Debug, PartialEq, Default)]
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
 pub struct Song {
     pub version: GpVersion,
     pub song_info: SongInfo,
@@ -56,12 +80,40 @@ pub struct Song {
     pub hide_tempo: Option<bool>,
     pub key_signature: i8,
     pub octave: Option<i8>,
+    pub master_effect: Option<RseMasterEffect>, // only >= GP5
     pub midi_channels: Vec<MidiChannel>,
     pub measure_headers: Vec<MeasureHeader>,
     pub tracks: Vec<Track>,
+    #[serde(skip)]
+    pub warnings: Vec<String>, // non-fatal parsing issues
+}
+
+// the song is shared with background threads building events, it must stay Send & Sync
+const _: () = {
+    const fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<Song>();
+};
+
+impl Song {
+    /// Whether the file's playback flags silence the track:
+    /// solo tracks silence all the others, otherwise muted tracks are silent.
+    pub fn is_track_silenced(&self, track_id: usize) -> bool {
+        let Some(track) = self.tracks.get(track_id) else {
+            return false;
+        };
+        if self.tracks.iter().any(|t| t.solo) {
+            !track.solo
+        } else {
+            track.mute
+        }
+    }
+
+    pub const fn measure_count(&self) -> usize {
+        self.measure_headers.len()
+    }
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct MidiChannel {
     pub channel_id: u8,
     pub effect_channel_id: u8,
@@ -79,22 +131,37 @@ impl MidiChannel {
     pub const fn is_percussion(&self) -> bool {
         self.bank == DEFAULT_PERCUSSION_BANK
     }
+
+    /// Channel 10 of every port is reserved for percussion in General MIDI.
+    pub const fn is_percussion_channel(channel_id: u8) -> bool {
+        channel_id % PORT_CHANNELS == 9
+    }
+
+    /// Zero-based MIDI port of the channel, the channel ids run through the ports.
+    pub const fn port(&self) -> u8 {
+        self.channel_id / PORT_CHANNELS
+    }
+
+    /// Channel within its port.
+    pub const fn port_channel(&self) -> u8 {
+        self.channel_id % PORT_CHANNELS
+    }
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Padding {
     pub right: i32,
     pub top: i32,
     pub left: i32,
     pub bottom: i32,
 }
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Point {
     pub x: i32,
     pub y: i32,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct PageSetup {
     pub page_size: Point,
     pub page_margin: Padding,
@@ -110,13 +177,50 @@ pub struct PageSetup {
     pub copyright: String,
     pub page_number: String,
 }
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Lyrics {
     pub track_choice: i32,
     pub lines: Vec<(i32, String)>,
 }
 
-#[derive(Debug, PartialEq, Eq, Default)]
+/// Realistic Sound Engine equalizer.
+/// Values are stored in tenths of decibels with an inverted sign (-35 is +3.5dB).
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RseEqualizer {
+    pub knobs: Vec<i8>,
+    pub gain: i8,
+}
+
+/// Realistic Sound Engine master effect.
+/// The master reverb is present from GP5, the volume and equalizer from GP5.10.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RseMasterEffect {
+    pub volume: i32,
+    pub reverb: i32,
+    pub equalizer: RseEqualizer,
+}
+
+/// Realistic Sound Engine instrument and its effect chain.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RseInstrument {
+    pub instrument: i32,
+    pub unknown: i32,
+    pub sound_bank: i32,
+    pub effect_number: i32,
+    pub effect: String,          // only >= GP5.10
+    pub effect_category: String, // only >= GP5.10
+}
+
+/// Realistic Sound Engine settings of a track.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TrackRse {
+    pub humanize: u8,
+    pub auto_accentuation: u8,
+    pub instrument: RseInstrument,
+    pub equalizer: Option<RseEqualizer>, // only >= GP5.10
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
 pub struct SongInfo {
     pub name: String,
     pub subtitle: String,
@@ -130,7 +234,98 @@ pub struct SongInfo {
     pub notices: Vec<String>,
 }
 
-#[derive(Debug, PartialEq, Eq)]
+/// Navigation symbol attached to a measure.
+/// Targets mark where a jump lands, jumps are taken at the end of their measure.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DirectionSign {
+    // targets
+    Coda,
+    DoubleCoda,
+    Segno,
+    SegnoSegno,
+    Fine,
+    // jumps
+    DaCapo,
+    DaCapoAlCoda,
+    DaCapoAlDoubleCoda,
+    DaCapoAlFine,
+    DaSegno,
+    DaSegnoAlCoda,
+    DaSegnoAlDoubleCoda,
+    DaSegnoAlFine,
+    DaSegnoSegno,
+    DaSegnoSegnoAlCoda,
+    DaSegnoSegnoAlDoubleCoda,
+    DaSegnoSegnoAlFine,
+    DaCoda,
+    DaDoubleCoda,
+}
+
+impl DirectionSign {
+    /// Order of the directions block in GP5 files, also the GP6/7 element names.
+    pub const ALL: [Self; 19] = [
+        Self::Coda,
+        Self::DoubleCoda,
+        Self::Segno,
+        Self::SegnoSegno,
+        Self::Fine,
+        Self::DaCapo,
+        Self::DaCapoAlCoda,
+        Self::DaCapoAlDoubleCoda,
+        Self::DaCapoAlFine,
+        Self::DaSegno,
+        Self::DaSegnoAlCoda,
+        Self::DaSegnoAlDoubleCoda,
+        Self::DaSegnoAlFine,
+        Self::DaSegnoSegno,
+        Self::DaSegnoSegnoAlCoda,
+        Self::DaSegnoSegnoAlDoubleCoda,
+        Self::DaSegnoSegnoAlFine,
+        Self::DaCoda,
+        Self::DaDoubleCoda,
+    ];
+
+    /// Parse a GP6/7 `Target` or `Jump` name.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|sign| format!("{sign:?}") == name)
+    }
+
+    pub const fn is_target(self) -> bool {
+        matches!(
+            self,
+            Self::Coda | Self::DoubleCoda | Self::Segno | Self::SegnoSegno | Self::Fine
+        )
+    }
+
+    /// Label displayed above the measure.
+    pub const fn label(self) -> &'static str {
+        match self {
+            Self::Coda => "\u{1D10C}",
+            Self::DoubleCoda => "\u{1D10C}\u{1D10C}",
+            Self::Segno => "\u{1D10B}",
+            Self::SegnoSegno => "\u{1D10B}\u{1D10B}",
+            Self::Fine => "Fine",
+            Self::DaCapo => "D.C.",
+            Self::DaCapoAlCoda => "D.C. al Coda",
+            Self::DaCapoAlDoubleCoda => "D.C. al Double Coda",
+            Self::DaCapoAlFine => "D.C. al Fine",
+            Self::DaSegno => "D.S.",
+            Self::DaSegnoAlCoda => "D.S. al Coda",
+            Self::DaSegnoAlDoubleCoda => "D.S. al Double Coda",
+            Self::DaSegnoAlFine => "D.S. al Fine",
+            Self::DaSegnoSegno => "D.S.S.",
+            Self::DaSegnoSegnoAlCoda => "D.S.S. al Coda",
+            Self::DaSegnoSegnoAlDoubleCoda => "D.S.S. al Double Coda",
+            Self::DaSegnoSegnoAlFine => "D.S.S. al Fine",
+            Self::DaCoda => "To Coda",
+            Self::DaDoubleCoda => "To Double Coda",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Marker {
     pub title: String,
     pub color: i32,
@@ -173,7 +368,7 @@ pub const KEY_SIGNATURES: [&str; 34] = [
     "E# minor",
 ];
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct KeySignature {
     pub key: i8,
     pub is_minor: bool,
@@ -196,14 +391,14 @@ impl std::fmt::Display for KeySignature {
     }
 }
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum TripletFeel {
     None,
     Eighth,
     Sixteenth,
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Tempo {
     pub value: u32,
     pub name: Option<String>,
@@ -224,7 +419,49 @@ impl Default for Tempo {
     }
 }
 
-#[derive(Debug, PartialEq, Eq)]
+/// Tempo change set by the mix table of a beat.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TempoChange {
+    pub value: u32,
+    pub transition: u8, // quarter notes to reach the value gradually, 0 is immediate
+}
+
+/// Channel value set by a mix table change.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MixTableItem {
+    pub value: i8,        // Guitar Pro channel scale (0-16)
+    pub transition: u8,   // quarter notes to reach the value gradually, 0 is immediate
+    pub all_tracks: bool, // the change applies to every track
+}
+
+/// Instrument, channel values and tempo set by the mix table of a beat, `None` keeps the current one.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MixTableChange {
+    pub instrument: Option<i32>,
+    pub volume: Option<MixTableItem>,
+    pub balance: Option<MixTableItem>,
+    pub chorus: Option<MixTableItem>,
+    pub reverb: Option<MixTableItem>,
+    pub phaser: Option<MixTableItem>,
+    pub tremolo: Option<MixTableItem>,
+    pub tempo: Option<TempoChange>,
+}
+
+impl MixTableChange {
+    /// Channel values in order: volume, balance, chorus, reverb, phaser and tremolo.
+    pub const fn items(&self) -> [Option<MixTableItem>; 6] {
+        [
+            self.volume,
+            self.balance,
+            self.chorus,
+            self.reverb,
+            self.phaser,
+            self.tremolo,
+        ]
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct MeasureHeader {
     pub start: u32,
     pub time_signature: TimeSignature,
@@ -238,6 +475,7 @@ pub struct MeasureHeader {
     pub repeat_close: i8,
     pub triplet_feel: TripletFeel,
     pub key_signature: KeySignature,
+    pub directions: Vec<DirectionSign>,
 }
 
 impl Default for MeasureHeader {
@@ -252,6 +490,7 @@ impl Default for MeasureHeader {
             repeat_close: 0,
             triplet_feel: TripletFeel::None,
             key_signature: KeySignature::new(0, false),
+            directions: Vec::new(),
         }
     }
 }
@@ -264,7 +503,7 @@ impl MeasureHeader {
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
 pub struct TimeSignature {
     pub numerator: u8,
     pub denominator: Duration,
@@ -279,7 +518,7 @@ impl Default for TimeSignature {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Duration {
     pub value: u16,
     pub dotted: bool,
@@ -322,7 +561,7 @@ impl Duration {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct BendPoint {
     pub position: u8,
     pub value: i8,
@@ -335,7 +574,7 @@ impl BendPoint {
     }
 }
 
-#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct BendEffect {
     pub points: Vec<BendPoint>,
 }
@@ -357,12 +596,12 @@ impl BendEffect {
     }
 }
 
-#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct TremoloBarEffect {
     pub points: Vec<BendPoint>,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct GraceEffect {
     pub duration: u8,
     pub fret: i8,
@@ -391,7 +630,7 @@ impl Default for GraceEffect {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum GraceEffectTransition {
     /// No transition
     None = 0,
@@ -415,7 +654,7 @@ impl GraceEffectTransition {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct PitchClass {
     pub note: String,
     pub just: i8,
@@ -466,7 +705,7 @@ impl PitchClass {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum HarmonicType {
     Natural,
     Artificial,
@@ -475,7 +714,7 @@ pub enum HarmonicType {
     Semi,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Octave {
     None,
     Ottava,
@@ -497,7 +736,7 @@ impl Octave {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct HarmonicEffect {
     pub kind: HarmonicType,
     // artificial harmonic
@@ -518,7 +757,7 @@ impl Default for HarmonicEffect {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum SlideType {
     IntoFromAbove,
     IntoFromBelow,
@@ -528,7 +767,7 @@ pub enum SlideType {
     OutUpWards,
 }
 
-#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct TrillEffect {
     pub fret: i8,
     pub duration: Duration,
@@ -545,7 +784,7 @@ impl TrillEffect {
     }
 }
 
-#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct TremoloPickingEffect {
     pub duration: Duration,
 }
@@ -561,7 +800,7 @@ impl TremoloPickingEffect {
     }
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum NoteType {
     Rest,
     Normal,
@@ -582,7 +821,7 @@ impl NoteType {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct NoteEffect {
     pub accentuated_note: bool,
     pub bend: Option<BendEffect>,
@@ -627,7 +866,7 @@ impl Default for NoteEffect {
     }
 }
 
-#[derive(Debug, Default, PartialEq, Eq)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Chord {
     pub length: u8,
     pub sharp: Option<bool>,
@@ -637,19 +876,35 @@ pub struct Chord {
     pub name: String,
     pub first_fret: Option<u32>,
     pub strings: Vec<i8>,
+    // the barres and fingerings are missing from the native files saved before they were parsed
+    #[serde(default)]
+    pub barres: Vec<Barre>,
+    /// Chord tones left out: root, third, fifth, seventh, ninth, eleventh and thirteenth.
     pub omissions: Vec<bool>,
+    /// Finger per string: -2 unknown, -1 none, 0 thumb, then index to little finger.
+    #[serde(default)]
+    pub fingerings: Vec<i8>,
     pub show: Option<bool>,
     pub new_format: Option<bool>,
 }
 
-#[derive(Debug, PartialEq, Eq)]
+/// Barre of a chord diagram, over the strings from `start` to `end`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Barre {
+    pub fret: u8,
+    pub start: u8,
+    pub end: u8,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub enum BeatStrokeDirection {
+    #[default]
     None,
     Up,
     Down,
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct BeatStroke {
     pub direction: BeatStrokeDirection,
     pub value: u16,
@@ -680,7 +935,7 @@ impl Default for BeatStroke {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum SlapEffect {
     None,
     Tapping,
@@ -688,13 +943,38 @@ pub enum SlapEffect {
     Popping,
 }
 
-#[derive(Debug, Default, PartialEq, Eq)]
+/// Wah pedal change applied from a beat onward.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WahEffect {
+    Off,
+    /// Pedal position from 0 (open) to 100 (closed).
+    Position(u8),
+}
+
+impl WahEffect {
+    pub const OPEN: Self = Self::Position(0);
+    pub const CLOSED: Self = Self::Position(100);
+
+    /// Read a raw Guitar Pro wah value: -2 is off, -1 is no change, 0 to 100 is the pedal position.
+    pub const fn from_value(value: i8) -> Option<Self> {
+        match value {
+            -2 => Some(Self::Off),
+            0..=100 => Some(Self::Position(value as u8)),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub struct BeatEffects {
     pub stroke: BeatStroke,
     pub chord: Option<Chord>,
+    pub pick_stroke: BeatStrokeDirection,
+    pub pick_scrape: bool,
+    pub wah: Option<WahEffect>,
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Note {
     pub value: i16,
     pub velocity: i16,
@@ -719,7 +999,7 @@ impl Note {
     }
 }
 
-#[derive(Debug, Default, PartialEq, Eq)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Beat {
     pub notes: Vec<Note>,
     pub duration: Duration,
@@ -727,15 +1007,34 @@ pub struct Beat {
     pub text: String,
     pub start: u32,
     pub effect: BeatEffects,
+    pub mix_table_change: Option<MixTableChange>,
+}
+
+impl Beat {
+    /// Whether the beat plays notes: a beat is either empty (no content, no time taken),
+    /// a rest (time taken without notes) or plays its notes.
+    pub const fn has_notes(&self) -> bool {
+        !self.empty && !self.notes.is_empty()
+    }
+
+    /// Canonical form of the rests: an empty beat holds no notes and the rest notes
+    /// are dropped, a beat left without notes is a rest.
+    pub fn normalize_rests(&mut self) {
+        if self.empty {
+            self.notes.clear();
+        } else {
+            self.notes.retain(|note| note.kind != NoteType::Rest);
+        }
+    }
 }
 
-#[derive(Debug, Default, PartialEq, Eq)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Voice {
     pub measure_index: i16,
     pub beats: Vec<Beat>,
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Measure {
     pub key_signature: KeySignature,
     pub time_signature: TimeSignature,
@@ -756,7 +1055,7 @@ impl Default for Measure {
     }
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Track {
     pub number: i32,
     pub offset: i32,
@@ -769,6 +1068,7 @@ pub struct Track {
     pub color: i32,
     pub midi_port: u8,
     pub fret_count: u8,
+    pub rse: Option<TrackRse>, // only >= GP5
     pub measures: Vec<Measure>,
 }
 
@@ -786,6 +1086,7 @@ impl Default for Track {
             color: 0,
             midi_port: 0,
             fret_count: 24,
+            rse: None,
             measures: vec![],
         }
     }
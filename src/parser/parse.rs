@@ -4,19 +4,284 @@
 use crate::RuxError;
 use crate::parser::gp67::song_builder::{parse_gp7_data, parse_gpx_data};
 use crate::parser::gp345::song_parser::parse_gp345_data;
-use crate::parser::model::Song;
+use crate::parser::model::{GpVersion, MeasureHeader, Song, Voice};
+use crate::parser::ruxg::{RUXG_EXTENSION, is_ruxg_data, parse_ruxg_data};
+use crate::parser::warnings::{self, warn};
+use std::path::Path;
+
+/// Container format of a tab file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TabFormat {
+    Gp345, // GP3/GP4/GP5 flat binary
+    Gpx,   // GP6 container
+    Gp7,   // GP7 ZIP container
+    Ruxg,  // native JSON of a saved song
+}
+
+impl TabFormat {
+    /// Format of the data from its magic, the GP3/GP4/GP5 files have none.
+    ///
+    /// - `BCFS` / `BCFZ` magic → GP6 (`.gpx`) container.
+    /// - `PK\x03\x04` (ZIP) magic → GP7 (`.gp`) container.
+    /// - JSON object with `"format": "ruxguitar"` → native `.ruxg` JSON of a saved song.
+    /// - otherwise → GP3/GP4/GP5 flat binary.
+    pub fn of(file_data: &[u8]) -> Self {
+        if file_data.starts_with(b"BCFS") || file_data.starts_with(b"BCFZ") {
+            Self::Gpx
+        } else if file_data.starts_with(b"PK\x03\x04") {
+            Self::Gp7
+        } else if is_ruxg_data(file_data) {
+            Self::Ruxg
+        } else {
+            Self::Gp345
+        }
+    }
+}
+
+/// Parsed song with what the parsing found out about the file, for diagnostics.
+#[derive(Debug, Clone)]
+pub struct ParseOutcome {
+    pub song: Song,            // its warnings are moved to the outcome
+    pub warnings: Vec<String>, // non-fatal parsing issues
+    pub version: GpVersion,    // Guitar Pro version the song was written with
+    pub format: TabFormat,
+}
+
+/// Parse any supported tab file, keeping the non-fatal issues apart from the song.
+pub fn parse_tab_data(file_data: &[u8]) -> Result<ParseOutcome, RuxError> {
+    let format = TabFormat::of(file_data);
+    let (song, warnings) = warnings::collect(|| -> Result<Song, RuxError> {
+        let mut song = match format {
+            TabFormat::Gpx => parse_gpx_data(file_data),
+            TabFormat::Gp7 => parse_gp7_data(file_data),
+            TabFormat::Ruxg => parse_ruxg_data(file_data),
+            TabFormat::Gp345 => parse_gp345_data(file_data),
+        }?;
+        check_voice_durations(&song);
+        song.warnings.clear();
+        Ok(song)
+    });
+    let song = song?;
+    Ok(ParseOutcome {
+        version: song.version,
+        format,
+        warnings,
+        song,
+    })
+}
 
 /// Parse any supported Guitar Pro file into a [`Song`].
 ///
-/// - `BCFS` / `BCFZ` magic → GP6 (`.gpx`) container.
-/// - `PK\x03\x04` (ZIP) magic → GP7 (`.gp`) container.
-/// - otherwise → GP3/GP4/GP5 flat binary.
+/// Non-fatal issues are reported in [`Song::warnings`].
 pub fn parse_gp_data(file_data: &[u8]) -> Result<Song, RuxError> {
-    if file_data.starts_with(b"BCFS") || file_data.starts_with(b"BCFZ") {
-        parse_gpx_data(file_data)
-    } else if file_data.starts_with(b"PK\x03\x04") {
-        parse_gp7_data(file_data)
-    } else {
-        parse_gp345_data(file_data)
+    parse_tab_data(file_data).map(|outcome| Song {
+        warnings: outcome.warnings,
+        ..outcome.song
+    })
+}
+
+/// File extensions of the supported formats.
+pub const SUPPORTED_EXTENSIONS: [&str; 6] = ["gp5", "gp4", "gp3", "gpx", "gp", RUXG_EXTENSION];
+
+/// Whether the file has the extension of a supported format, whatever its case.
+pub fn is_tab_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| SUPPORTED_EXTENSIONS.contains(&e.to_lowercase().as_str()))
+}
+
+/// Open a tab file of any supported format.
+///
+/// The content picks the parser: a file named with the extension of another supported
+/// format, or without a supported extension, still opens if its content is detected.
+pub fn open_tab_file(path: &Path) -> Result<Song, RuxError> {
+    let data = std::fs::read(path)
+        .map_err(|err| RuxError::OtherError(format!("Could not read tab file {path:?}: {err}")))?;
+    if !is_tab_file(path) && detect_extension(&data).is_none() {
+        return Err(RuxError::ParsingError(format!(
+            "Unsupported tab file {path:?}"
+        )));
+    }
+    parse_gp_data(&data)
+}
+
+/// Extension of the format of the data, `None` when it is no supported file.
+///
+/// Used to check files without name or extension (standard input, downloads).
+pub fn detect_extension(file_data: &[u8]) -> Option<&'static str> {
+    // GP3/GP4/GP5 start with the length of the version string
+    let gp345_version = |version: &[u8]| {
+        file_data
+            .get(1..)
+            .is_some_and(|header| header.starts_with(version))
+    };
+    match TabFormat::of(file_data) {
+        TabFormat::Gpx => Some("gpx"),
+        TabFormat::Gp7 => Some("gp"),
+        TabFormat::Ruxg => Some(RUXG_EXTENSION),
+        TabFormat::Gp345 if gp345_version(b"FICHIER GUITAR PRO v5") => Some("gp5"),
+        TabFormat::Gp345 if gp345_version(b"FICHIER GUITAR PRO v4") => Some("gp4"),
+        TabFormat::Gp345 if gp345_version(b"FICHIER GUITAR PRO v3") => Some("gp3"),
+        TabFormat::Gp345 => None,
+    }
+}
+
+/// Report voices lasting longer than their measure, their extra beats overlap the next measure.
+fn check_voice_durations(song: &Song) {
+    for track in &song.tracks {
+        for (index, (measure, header)) in
+            track.measures.iter().zip(&song.measure_headers).enumerate()
+        {
+            for (voice_index, voice) in measure.voices.iter().enumerate() {
+                let overflow = voice_overflow(header, voice);
+                if overflow > 0 {
+                    warn(format!(
+                        "Track {} measure {} voice {} lasts {} ticks longer than the measure",
+                        track.number,
+                        index + 1,
+                        voice_index + 1,
+                        overflow
+                    ));
+                }
+            }
+        }
+    }
+}
+
+/// Ticks played by the voice after the end of its measure.
+fn voice_overflow(header: &MeasureHeader, voice: &Voice) -> u32 {
+    let measure_end = header.start + header.length();
+    let voice_end = voice
+        .beats
+        .last()
+        .map_or(header.start, |beat| beat.start + beat.duration.time());
+    voice_end.saturating_sub(measure_end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::gp345::song_parser::duration_value;
+    use crate::parser::model::{Beat, Duration, QUARTER_TIME, TimeSignature};
+    use crate::parser::song_parser_tests::TestRng;
+
+    #[test]
+    fn detect_the_format_of_the_test_files() {
+        for (file, extension) in [
+            ("Demo v5.gp5", "gp5"),
+            ("canon_rock.gp4", "gp4"),
+            ("Blind Guardian - Nightfall.gp3", "gp3"),
+            ("Tyr - Evening Star.gpx", "gpx"),
+            ("The Black Dahlia Murder - Nightbringers.gp", "gp"),
+        ] {
+            let data = std::fs::read(format!("test-files/{file}")).unwrap();
+            assert_eq!(detect_extension(&data), Some(extension), "{file}");
+        }
+        assert_eq!(detect_extension(b"<!DOCTYPE html><html>"), None);
+        assert_eq!(detect_extension(b""), None);
+    }
+
+    #[test]
+    fn open_tab_files_by_extension() {
+        let song = open_tab_file(Path::new("test-files/Demo v5.gp5")).unwrap();
+        assert_eq!(song.version, GpVersion::GP5);
+        assert!(is_tab_file(Path::new("song.GPX")));
+        assert!(!is_tab_file(Path::new("song.txt")));
+        assert!(!is_tab_file(Path::new("gp5")));
+        assert!(matches!(
+            open_tab_file(Path::new("README.md")),
+            Err(RuxError::ParsingError(_))
+        ));
+        // the content is detected without a supported extension
+        let copy = std::env::temp_dir().join(format!("ruxguitar-tab-{}", std::process::id()));
+        std::fs::copy("test-files/Demo v5.gp5", &copy).unwrap();
+        let opened = open_tab_file(&copy);
+        std::fs::remove_file(&copy).unwrap();
+        assert_eq!(opened.unwrap().version, GpVersion::GP5);
+        assert!(matches!(
+            open_tab_file(Path::new("test-files/missing.gp5")),
+            Err(RuxError::OtherError(_))
+        ));
+    }
+
+    #[test]
+    fn outcome_of_the_parsing() {
+        let data = std::fs::read("test-files/Tyr - Evening Star.gpx").unwrap();
+        let outcome = parse_tab_data(&data).unwrap();
+        assert_eq!(outcome.format, TabFormat::Gpx);
+        assert_eq!(outcome.version, GpVersion::GP6);
+
+        let file = "test-files/John Petrucci - Damage Control (ver 6 by Feio666).gp5";
+        let data = std::fs::read(file).unwrap();
+        let outcome = parse_tab_data(&data).unwrap();
+        assert_eq!(outcome.format, TabFormat::Gp345);
+        assert_eq!(outcome.version, outcome.song.version);
+        assert_eq!(outcome.warnings.len(), 29);
+        assert!(outcome.song.warnings.is_empty());
+        // the wrapper keeps the warnings with the song
+        assert_eq!(parse_gp_data(&data).unwrap().warnings, outcome.warnings);
+    }
+
+    /// Voice of random beats laid out from the start of the measure, like the parsers do.
+    fn random_voice(rng: &mut TestRng, header: &MeasureHeader, beat_count: usize) -> Voice {
+        let mut start = header.start;
+        let beats = (0..beat_count)
+            .map(|_| {
+                let duration = Duration {
+                    value: duration_value(rng.below(7) as i8 - 2).unwrap(),
+                    dotted: rng.chance(20),
+                    ..Duration::default()
+                };
+                let beat = Beat {
+                    duration,
+                    start,
+                    ..Beat::default()
+                };
+                start += beat.duration.time();
+                beat
+            })
+            .collect();
+        Voice {
+            measure_index: 0,
+            beats,
+        }
+    }
+
+    #[test]
+    fn beats_within_the_measure_do_not_overflow() {
+        for seed in 0..500 {
+            let mut rng = TestRng::new(seed);
+            let header = MeasureHeader {
+                start: QUARTER_TIME + rng.below(100) as u32 * QUARTER_TIME * 4,
+                time_signature: TimeSignature {
+                    numerator: rng.below(12) as u8 + 1,
+                    denominator: Duration {
+                        value: duration_value(rng.below(4) as i8 - 1).unwrap(),
+                        ..Duration::default()
+                    },
+                },
+                ..MeasureHeader::default()
+            };
+            let beat_count = rng.below(12) as usize;
+            let voice = random_voice(&mut rng, &header, beat_count);
+            let played: u32 = voice.beats.iter().map(|b| b.duration.time()).sum();
+            let overflow = voice_overflow(&header, &voice);
+            assert_eq!(
+                overflow,
+                played.saturating_sub(header.length()),
+                "seed {seed}"
+            );
+            if overflow == 0 {
+                let measure_end = header.start + header.length();
+                assert!(
+                    voice
+                        .beats
+                        .iter()
+                        .all(|b| b.start >= header.start
+                            && b.start + b.duration.time() <= measure_end),
+                    "seed {seed}"
+                );
+            }
+        }
     }
 }
@@ -0,0 +1,129 @@
+//! Native `.ruxg` format: the song model as JSON, to save and reload edited songs.
+
+use crate::RuxError;
+use crate::parser::model::Song;
+use serde::{Deserialize, Serialize};
+
+pub const RUXG_EXTENSION: &str = "ruxg";
+
+/// Marker of the format, also used to detect the files before parsing them.
+const FORMAT: &str = "ruxguitar";
+
+/// Version of the format, bumped when the model changes in an incompatible way.
+const VERSION: u32 = 1;
+
+#[derive(Serialize)]
+struct RuxgWrite<'a> {
+    format: &'a str,
+    version: u32,
+    song: &'a Song,
+}
+
+/// Only the marker of a file, the other fields are skipped.
+#[derive(Deserialize)]
+struct RuxgFormat {
+    format: String,
+}
+
+#[derive(Deserialize)]
+struct RuxgRead {
+    format: String,
+    version: u32,
+    song: Song,
+}
+
+/// Whether the data is a `.ruxg` file: a JSON object with the format marker,
+/// whatever its formatting.
+pub fn is_ruxg_data(data: &[u8]) -> bool {
+    // the binary tab formats are not worth parsing as JSON
+    data.trim_ascii_start().starts_with(b"{")
+        && serde_json::from_slice::<RuxgFormat>(data).is_ok_and(|file| file.format == FORMAT)
+}
+
+/// Serialize the song to the `.ruxg` format.
+pub fn write_ruxg(song: &Song) -> Result<Vec<u8>, RuxError> {
+    let file = RuxgWrite {
+        format: FORMAT,
+        version: VERSION,
+        song,
+    };
+    serde_json::to_vec(&file).map_err(|e| RuxError::OtherError(format!("ruxg writing: {e}")))
+}
+
+/// Parse a song saved in the `.ruxg` format.
+pub fn parse_ruxg_data(data: &[u8]) -> Result<Song, RuxError> {
+    let file: RuxgRead = serde_json::from_slice(data)
+        .map_err(|e| RuxError::ParsingError(format!("ruxg parsing: {e}")))?;
+    if file.format != FORMAT || file.version > VERSION {
+        return Err(RuxError::ParsingError(format!(
+            "unsupported ruxg file: {} version {}",
+            file.format, file.version
+        )));
+    }
+    Ok(file.song)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_gp_data;
+    use crate::parser::song_parser_tests::parse_gp_file;
+
+    #[test]
+    fn saved_songs_reload_identical() {
+        for file in [
+            "Demo v5.gp5",
+            "Blind Guardian - Nightfall.gp3",
+            "Tyr - Evening Star.gpx",
+            "The Black Dahlia Murder - Nightbringers.gp",
+        ] {
+            let song = parse_gp_file(&format!("test-files/{file}")).unwrap();
+            let data = write_ruxg(&song).unwrap();
+            assert!(is_ruxg_data(&data), "{file}");
+            // loaded like any other file, the warnings are checked again
+            let reloaded = parse_gp_data(&data).unwrap();
+            assert_eq!(
+                Song {
+                    warnings: Vec::new(),
+                    ..reloaded
+                },
+                Song {
+                    warnings: Vec::new(),
+                    ..song
+                },
+                "{file}"
+            );
+        }
+    }
+
+    #[test]
+    fn newer_versions_rejected() {
+        let song = parse_gp_file("test-files/Demo v5.gp5").unwrap();
+        let data = String::from_utf8(write_ruxg(&song).unwrap()).unwrap();
+        let newer = data.replacen("\"version\":1", "\"version\":2", 1);
+        assert!(parse_ruxg_data(newer.as_bytes()).is_err());
+        assert!(parse_ruxg_data(b"{\"format\":\"ruxguitar\"").is_err());
+    }
+
+    #[test]
+    fn reformatted_files_detected() {
+        let song = parse_gp_file("test-files/Demo v5.gp5").unwrap();
+        let data: serde_json::Value = serde_json::from_slice(&write_ruxg(&song).unwrap()).unwrap();
+        let mut pretty = b"\n  ".to_vec();
+        pretty.extend(serde_json::to_vec_pretty(&data).unwrap());
+        assert!(is_ruxg_data(&pretty));
+        assert_eq!(parse_gp_data(&pretty).unwrap().tracks, song.tracks);
+        assert!(!is_ruxg_data(b"{\"format\":\"other\"}"));
+    }
+
+    #[test]
+    fn chords_saved_without_diagram_details() {
+        let mut chord = serde_json::to_value(crate::parser::model::Chord::default()).unwrap();
+        let fields = chord.as_object_mut().unwrap();
+        fields.remove("barres");
+        fields.remove("fingerings");
+        let chord: crate::parser::model::Chord = serde_json::from_value(chord).unwrap();
+        assert!(chord.barres.is_empty());
+        assert!(chord.fingerings.is_empty());
+    }
+}
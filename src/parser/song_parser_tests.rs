@@ -15,12 +15,43 @@ pub fn parse_gp_file(file_path: &str) -> Result<Song, RuxError> {
     parse_gp_data(&file_data)
 }
 
+/// Small deterministic xorshift generator for property tests,
+/// a failing case is reproduced from its seed.
+#[cfg(test)]
+pub struct TestRng(u64);
+
+#[cfg(test)]
+impl TestRng {
+    pub const fn new(seed: u64) -> Self {
+        // the state must not be zero
+        Self(seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1)
+    }
+
+    pub const fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    /// Uniform value in `0..bound`.
+    pub const fn below(&mut self, bound: u64) -> u64 {
+        self.next_u64() % bound
+    }
+
+    /// True with the given probability in percent.
+    pub const fn chance(&mut self, percent: u64) -> bool {
+        self.below(100) < percent
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::parser::song_parser::{
-        BendEffect, BendPoint, Duration, GpVersion, KeySignature, Marker, NoteType, Padding, Point,
-        TripletFeel,
+        Barre, Beat, BendEffect, BendPoint, DirectionSign, Duration, GpVersion, KeySignature,
+        Marker, MixTableChange, MixTableItem, Note, NoteEffect, NoteType, Padding, Point,
+        RseEqualizer, RseInstrument, TripletFeel,
     };
 
     fn init_logger() {
@@ -792,4 +823,300 @@ mod tests {
         assert!(GpVersion::GP4_06 < GpVersion::GP5);
         assert!(GpVersion::GP5 < GpVersion::GP5_10);
     }
+
+    #[test]
+    fn parse_gp5_rse_data() {
+        init_logger();
+        const FILE_PATH: &str = "test-files/Demo v5.gp5";
+        let song = parse_gp_file(FILE_PATH).unwrap();
+        // no RSE master effect before GP5.10, only the master reverb
+        let master_effect = song.master_effect.unwrap();
+        assert_eq!(master_effect.reverb, -1);
+        assert_eq!(master_effect.equalizer, RseEqualizer::default());
+
+        let rse = song.tracks[0].rse.as_ref().unwrap();
+        assert_eq!(
+            rse.instrument,
+            RseInstrument {
+                instrument: 27,
+                unknown: 1,
+                sound_bank: 2,
+                effect_number: 500,
+                ..Default::default()
+            }
+        );
+        assert_eq!(rse.equalizer, None);
+    }
+
+    #[test]
+    fn parse_gp5_directions() {
+        init_logger();
+        const FILE_PATH: &str = "test-files/Demo v5.gp5";
+        let song = parse_gp_file(FILE_PATH).unwrap();
+        let directions: Vec<(usize, Vec<DirectionSign>)> = song
+            .measure_headers
+            .iter()
+            .enumerate()
+            .filter(|(_, h)| !h.directions.is_empty())
+            .map(|(i, h)| (i, h.directions.clone()))
+            .collect();
+        assert_eq!(
+            directions,
+            vec![
+                (17, vec![DirectionSign::Segno]),
+                (24, vec![DirectionSign::DaCoda]),
+                (36, vec![DirectionSign::DaSegnoAlCoda]),
+                (37, vec![DirectionSign::Coda]),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_gp5_chord_diagrams() {
+        init_logger();
+        const FILE_PATH: &str = "test-files/Demo v5.gp5";
+        let song = parse_gp_file(FILE_PATH).unwrap();
+        let chord_at = |measure: usize| {
+            song.tracks[0].measures[measure].voices[0].beats[0]
+                .effect
+                .chord
+                .clone()
+                .unwrap()
+        };
+        let power_chord = chord_at(0);
+        assert_eq!(power_chord.name, "C5");
+        assert_eq!(power_chord.new_format, Some(true));
+        assert_eq!(power_chord.root.as_ref().map(|p| p.value), Some(0));
+        assert_eq!(power_chord.add, Some(false));
+        assert_eq!(power_chord.strings, vec![-1, -1, 5, 5, 3, -1]);
+        assert_eq!(power_chord.fingerings, vec![-1, -1, 4, 3, 1, -1]);
+        // only the root and the fifth
+        assert_eq!(
+            power_chord.omissions,
+            vec![false, true, false, true, true, true, true]
+        );
+        assert!(power_chord.barres.is_empty());
+        assert_eq!(power_chord.show, Some(true));
+
+        let added = chord_at(5);
+        assert_eq!(added.name, "Bbsus4add9");
+        assert_eq!(added.root.as_ref().map(|p| p.value), Some(10));
+        assert_eq!(added.bass.as_ref().map(|p| p.value), Some(0));
+        assert_eq!(added.add, Some(true));
+        assert_eq!(
+            added.barres,
+            vec![Barre {
+                fret: 3,
+                start: 1,
+                end: 5
+            }]
+        );
+        assert_eq!(added.fingerings, vec![-1, 2, 1, 1, 1, -1]);
+    }
+
+    #[test]
+    fn parse_gp5_beat_text() {
+        init_logger();
+        const FILE_PATH: &str = "test-files/Guthrie Govan - Eric.gp5";
+        let song = parse_gp_file(FILE_PATH).unwrap();
+        let texts: Vec<(usize, &str)> = song.tracks[0]
+            .measures
+            .iter()
+            .flat_map(|m| m.voices.iter().flat_map(|v| &v.beats))
+            .filter(|b| !b.text.is_empty())
+            .map(|b| (b.start as usize, b.text.as_str()))
+            .collect();
+        // surrounding whitespace is trimmed
+        assert_eq!(
+            texts,
+            vec![
+                (960, "Press F5 for credits"),
+                (6720, "If RSE is on, turn drum track volu"),
+                (12480, "me to 6"),
+                (26880, "Slide on"),
+                (152_160, "Slide off"),
+                (153_600, "Tabbed by [Unregistered] (Press F5 for more info)"),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_beat_tempo_changes() {
+        init_logger();
+        let tempo_changes = |song: &Song| -> Vec<(u32, u32, u8)> {
+            song.tracks
+                .iter()
+                .flat_map(|t| &t.measures)
+                .flat_map(|m| m.voices.iter().flat_map(|v| &v.beats))
+                .filter_map(|b| {
+                    let tempo = b.mix_table_change.as_ref()?.tempo?;
+                    Some((b.start, tempo.value, tempo.transition))
+                })
+                .collect()
+        };
+        let header_tempos = |song: &Song, measures: std::ops::Range<usize>| -> Vec<u32> {
+            song.measure_headers[measures]
+                .iter()
+                .map(|h| h.tempo.value)
+                .collect()
+        };
+
+        // ritardando over 6 then 3 quarter notes
+        let song = parse_gp_file("test-files/Blind Guardian - Nightfall.gp3").unwrap();
+        assert_eq!(
+            tempo_changes(&song),
+            vec![(486_240, 60, 6), (492_000, 30, 3), (494_880, 95, 0)]
+        );
+        // headers hold the tempo at the start of the measure
+        assert_eq!(header_tempos(&song, 168..173), vec![95, 95, 60, 60, 95]);
+
+        // immediate changes in the middle of a measure apply from the next one
+        let song = parse_gp_file("test-files/Ghost - Cirice.gp5").unwrap();
+        let header = &song.measure_headers[17];
+        assert_eq!(header.start, 70_080);
+        assert!(tempo_changes(&song).contains(&(72_960, 85, 0)));
+        assert_eq!(header_tempos(&song, 17..19), vec![90, 90]);
+    }
+
+    #[test]
+    fn parse_mix_table_changes() {
+        init_logger();
+        let first_change = |song: &Song, track: usize, measure: usize| -> MixTableChange {
+            song.tracks[track].measures[measure]
+                .voices
+                .iter()
+                .flat_map(|v| &v.beats)
+                .find_map(|b| b.mix_table_change.clone())
+                .unwrap()
+        };
+        let item = |value, transition, all_tracks| MixTableItem {
+            value,
+            transition,
+            all_tracks,
+        };
+
+        let song = parse_gp_file("test-files/Demo v5.gp5").unwrap();
+        let change = first_change(&song, 0, 37);
+        assert_eq!(change.instrument, Some(27));
+        assert_eq!(change.tempo.map(|t| t.value), Some(120));
+        assert_eq!(first_change(&song, 0, 41).instrument, Some(29));
+        assert_eq!(first_change(&song, 3, 37).instrument, Some(36));
+        // fade out of every track over 16 quarter notes
+        assert_eq!(
+            first_change(&song, 0, 45),
+            MixTableChange {
+                volume: Some(item(2, 16, true)),
+                ..MixTableChange::default()
+            }
+        );
+
+        let song = parse_gp_file("test-files/Guthrie Govan - Eric.gp5").unwrap();
+        let change = first_change(&song, 1, 92);
+        assert_eq!(change.volume.map(|v| v.value), Some(8));
+        assert_eq!(change.balance.map(|v| v.value), Some(16));
+        assert_eq!(first_change(&song, 1, 93).volume, Some(item(16, 5, false)));
+
+        // GP3 has no all tracks flags
+        let song = parse_gp_file("test-files/Blind Guardian - Nightfall.gp3").unwrap();
+        let change = first_change(&song, 2, 39);
+        assert_eq!(change.instrument, Some(0));
+        assert_eq!(change.volume, Some(item(16, 0, false)));
+        let change = first_change(&song, 2, 40);
+        assert_eq!(change.instrument, Some(25));
+        assert_eq!(change.volume, Some(item(13, 0, false)));
+    }
+
+    #[test]
+    fn rests_have_a_single_representation() {
+        let note = |kind| {
+            let mut note = Note::new(NoteEffect::default());
+            note.kind = kind;
+            note
+        };
+        // rest notes are dropped, leaving a rest
+        let mut beat = Beat {
+            notes: vec![note(NoteType::Rest), note(NoteType::Normal)],
+            ..Beat::default()
+        };
+        beat.normalize_rests();
+        assert_eq!(beat.notes, vec![note(NoteType::Normal)]);
+        assert!(beat.has_notes());
+        beat.notes = vec![note(NoteType::Rest)];
+        beat.normalize_rests();
+        assert!(beat.notes.is_empty());
+        assert!(!beat.has_notes());
+        // an empty beat holds nothing
+        let mut beat = Beat {
+            notes: vec![note(NoteType::Normal)],
+            empty: true,
+            ..Beat::default()
+        };
+        beat.normalize_rests();
+        assert!(beat.notes.is_empty());
+
+        for entry in std::fs::read_dir("test-files").unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                continue;
+            }
+            let song = parse_gp_file(path.to_str().unwrap()).unwrap();
+            let beats = song
+                .tracks
+                .iter()
+                .flat_map(|t| &t.measures)
+                .flat_map(|m| &m.voices)
+                .flat_map(|v| &v.beats);
+            for beat in beats {
+                assert!(!beat.empty || beat.notes.is_empty(), "{path:?}");
+                assert!(
+                    beat.notes.iter().all(|n| n.kind != NoteType::Rest),
+                    "{path:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn parse_warnings_overfull_voices() {
+        init_logger();
+        const FILE_PATH: &str = "test-files/John Petrucci - Damage Control (ver 6 by Feio666).gp5";
+        let song = parse_gp_file(FILE_PATH).unwrap();
+        assert_eq!(song.warnings.len(), 29);
+        assert_eq!(
+            song.warnings[0],
+            "Track 2 measure 39 voice 1 lasts 480 ticks longer than the measure"
+        );
+
+        let song = parse_gp_file("test-files/Demo v5.gp5").unwrap();
+        assert!(song.warnings.is_empty());
+    }
+
+    #[test]
+    fn parse_gp5_10_rse_data() {
+        init_logger();
+        const FILE_PATH: &str = "test-files/Meshuggah - Bleed.gp5";
+        let song = parse_gp_file(FILE_PATH).unwrap();
+        let master_effect = song.master_effect.unwrap();
+        assert_eq!(master_effect.volume, 100);
+        assert_eq!(master_effect.reverb, 0);
+        assert_eq!(master_effect.equalizer.knobs, vec![0; 10]);
+        assert_eq!(master_effect.equalizer.gain, 0);
+
+        let effects: Vec<_> = song
+            .tracks
+            .iter()
+            .map(|t| t.rse.as_ref().unwrap())
+            .map(|rse| {
+                (
+                    rse.instrument.effect.as_str(),
+                    rse.instrument.effect_category.as_str(),
+                )
+            })
+            .collect();
+        assert_eq!(effects[0], ("American Clean - Pi Distortion", "Amp Tones"));
+        assert_eq!(effects[2], ("Bass - Default", "Bass Tones"));
+        assert_eq!(effects[3], ("Drums - Default", "Drums Tones"));
+        let equalizer = song.tracks[0].rse.as_ref().unwrap().equalizer.as_ref();
+        assert_eq!(equalizer.unwrap().knobs.len(), 3);
+    }
 }
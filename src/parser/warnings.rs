@@ -0,0 +1,117 @@
+//! Non-fatal issues found while parsing, e.g. unknown values replaced by a default
+//! or out of range values clamped.
+//!
+//! The parsers are plain functions without a shared context, so the warnings
+//! are gathered per thread while [`collect`] runs.
+
+use std::cell::RefCell;
+use std::fmt::Display;
+use std::ops::RangeInclusive;
+
+thread_local! {
+    static WARNINGS: RefCell<Option<Vec<String>>> = const { RefCell::new(None) };
+}
+
+/// Log the issue and record it for the file being parsed.
+pub fn warn(message: String) {
+    log::warn!("{message}");
+    WARNINGS.with_borrow_mut(|warnings| {
+        if let Some(warnings) = warnings {
+            warnings.push(message);
+        }
+    });
+}
+
+/// Bring a parsed value back into its valid range, warning about the `what` that was out of it.
+/// Out of range values would otherwise wrap when narrowed and play absurd MIDI values.
+pub fn clamped<T: PartialOrd + Copy + Display>(
+    what: &str,
+    value: T,
+    range: RangeInclusive<T>,
+) -> T {
+    let (min, max) = (*range.start(), *range.end());
+    if range.contains(&value) {
+        value
+    } else {
+        warn(format!(
+            "{what} {value} out of range {min}..={max}, clamped"
+        ));
+        if value < min { min } else { max }
+    }
+}
+
+/// Time signature denominators are note values, a power of two from a whole note to a 32nd.
+/// Other values are replaced by the power of two below them, to keep the measure lengths close.
+pub fn time_signature_denominator(value: i32) -> u16 {
+    let clamped = clamped("Time signature denominator", value, 1..=32) as u16;
+    if clamped.is_power_of_two() {
+        clamped
+    } else {
+        let replaced = 1 << clamped.ilog2();
+        warn(format!(
+            "Time signature denominator {clamped} is not a power of two, replaced by {replaced}"
+        ));
+        replaced
+    }
+}
+
+/// Run the parser and return the warnings it reported.
+pub fn collect<T>(parse: impl FnOnce() -> T) -> (T, Vec<String>) {
+    let previous = WARNINGS.replace(Some(Vec::new()));
+    let result = parse();
+    let warnings = WARNINGS.replace(previous).unwrap_or_default();
+    (result, warnings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn warnings_are_scoped_to_the_parse() {
+        warn("outside".to_string());
+        let (value, outer) = collect(|| {
+            warn("first".to_string());
+            let ((), inner) = collect(|| warn("nested".to_string()));
+            assert_eq!(inner, vec!["nested"]);
+            warn("second".to_string());
+            42
+        });
+        assert_eq!(value, 42);
+        assert_eq!(outer, vec!["first", "second"]);
+    }
+
+    #[test]
+    fn values_clamped_with_a_warning() {
+        let (values, warnings) = collect(|| {
+            [
+                clamped("Volume", 12, 0..=16),
+                clamped("Volume", -56, 0..=16),
+                clamped("Volume", 90, 0..=16),
+            ]
+        });
+        assert_eq!(values, [12, 0, 16]);
+        assert_eq!(
+            warnings,
+            vec![
+                "Volume -56 out of range 0..=16, clamped",
+                "Volume 90 out of range 0..=16, clamped"
+            ]
+        );
+    }
+
+    #[test]
+    fn time_signature_denominators_are_powers_of_two() {
+        let (values, warnings) = collect(|| [8, 3, 7, 0, 100].map(time_signature_denominator));
+        assert_eq!(values, [8, 2, 4, 1, 32]);
+        assert_eq!(
+            warnings,
+            vec![
+                "Time signature denominator 3 is not a power of two, replaced by 2",
+                "Time signature denominator 7 is not a power of two, replaced by 4",
+                "Time signature denominator 0 out of range 1..=32, clamped",
+                "Time signature denominator 100 out of range 1..=32, clamped"
+            ]
+        );
+    }
+}
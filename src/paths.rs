@@ -0,0 +1,151 @@
+//! Folders holding the configuration and the user data (sound fonts).
+//! Portable mode keeps everything next to the executable, otherwise the platform
+//! conventions apply: XDG on Linux, `AppData` on Windows and `Library` on macOS.
+
+use crate::RuxError;
+use std::env::home_dir;
+use std::path::{Path, PathBuf};
+
+const APP_FOLDER: &str = "ruxguitar";
+
+/// Folder next to the executable used in portable mode, its presence enables portable mode.
+pub const PORTABLE_FOLDER: &str = "ruxguitar-data";
+
+/// Configuration folder of the versions predating the platform conventions.
+const LEGACY_CONFIG_FOLDER: &str = ".config/ruxguitar";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AppPaths {
+    config_dir: PathBuf,
+    data_dir: PathBuf,
+}
+
+impl AppPaths {
+    /// Portable folder when requested or already present next to the executable,
+    /// platform folders otherwise.
+    pub fn resolve(portable: bool) -> Result<Self, RuxError> {
+        let portable_dir = std::env::current_exe()
+            .ok()
+            .and_then(|exe| exe.parent().map(|dir| dir.join(PORTABLE_FOLDER)));
+        match portable_dir {
+            Some(dir) if portable || dir.is_dir() => Ok(Self::portable(dir)),
+            None if portable => Err(RuxError::ConfigError(
+                "Could not find the executable folder for portable mode".to_string(),
+            )),
+            _ => {
+                let home = home_dir().ok_or_else(|| {
+                    RuxError::ConfigError("Could not find home directory".to_string())
+                })?;
+                Ok(Self::platform(std::env::consts::OS, &home, |name| {
+                    std::env::var_os(name).map(PathBuf::from)
+                }))
+            }
+        }
+    }
+
+    pub(crate) fn portable(dir: PathBuf) -> Self {
+        Self {
+            config_dir: dir.clone(),
+            data_dir: dir,
+        }
+    }
+
+    /// Platform folders, `var` looks up environment variables.
+    fn platform(os: &str, home: &Path, var: impl Fn(&str) -> Option<PathBuf>) -> Self {
+        // relative values are invalid per the XDG specification
+        let absolute_var = |name: &str| var(name).filter(|path| path.is_absolute());
+        let (config_base, data_base) = match os {
+            "windows" => {
+                let app_data =
+                    absolute_var("APPDATA").unwrap_or_else(|| home.join("AppData").join("Roaming"));
+                (app_data.clone(), app_data)
+            }
+            "macos" => {
+                let support = home.join("Library").join("Application Support");
+                (support.clone(), support)
+            }
+            _ => (
+                absolute_var("XDG_CONFIG_HOME").unwrap_or_else(|| home.join(".config")),
+                absolute_var("XDG_DATA_HOME").unwrap_or_else(|| home.join(".local").join("share")),
+            ),
+        };
+        let config_dir = config_base.join(APP_FOLDER);
+        // keep using the configuration of previous versions
+        let legacy = home.join(LEGACY_CONFIG_FOLDER);
+        let config_dir = if !config_dir.exists() && legacy.exists() {
+            legacy
+        } else {
+            config_dir
+        };
+        Self {
+            config_dir,
+            data_dir: data_base.join(APP_FOLDER),
+        }
+    }
+
+    pub fn config_dir(&self) -> &Path {
+        &self.config_dir
+    }
+
+    pub fn config_file(&self) -> PathBuf {
+        self.config_dir.join("config.json")
+    }
+
+    /// Port of the running instance in single instance mode.
+    pub fn instance_file(&self) -> PathBuf {
+        self.config_dir.join("instance.port")
+    }
+
+    pub fn sound_fonts_dir(&self) -> PathBuf {
+        self.data_dir.join("sound-fonts")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn platform_conventions() {
+        let home = Path::new("/nonexistent/home/player");
+        let no_var = |_: &str| None;
+
+        let linux = AppPaths::platform("linux", home, no_var);
+        assert_eq!(
+            linux.config_file(),
+            home.join(".config/ruxguitar/config.json")
+        );
+        assert_eq!(
+            linux.sound_fonts_dir(),
+            home.join(".local/share/ruxguitar/sound-fonts")
+        );
+
+        let xdg = AppPaths::platform("linux", home, |name| match name {
+            "XDG_CONFIG_HOME" => Some(PathBuf::from("/xdg/config")),
+            "XDG_DATA_HOME" => Some(PathBuf::from("relative/data")),
+            _ => None,
+        });
+        assert_eq!(xdg.config_dir(), Path::new("/xdg/config/ruxguitar"));
+        // relative values are ignored
+        assert_eq!(
+            xdg.sound_fonts_dir(),
+            home.join(".local/share/ruxguitar/sound-fonts")
+        );
+
+        let macos = AppPaths::platform("macos", home, no_var);
+        assert_eq!(
+            macos.config_dir(),
+            home.join("Library/Application Support/ruxguitar")
+        );
+
+        let portable = AppPaths::portable(PathBuf::from("/usb/ruxguitar-data"));
+        assert_eq!(
+            portable.config_file(),
+            Path::new("/usb/ruxguitar-data/config.json")
+        );
+        assert_eq!(
+            portable.sound_fonts_dir(),
+            Path::new("/usb/ruxguitar-data/sound-fonts")
+        );
+    }
+}
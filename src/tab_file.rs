@@ -0,0 +1,195 @@
+//! Tab file given on the command line: a path, `-` for the standard input or an http(s) URL.
+//! Files read in memory are validated by size and by content as they have no trusted extension.
+
+use crate::parser::{SUPPORTED_EXTENSIONS, detect_extension};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Largest tab file read from the standard input or downloaded, tabs weigh a few hundred KB.
+pub const MAX_TAB_FILE_SIZE: u64 = 20 * 1024 * 1024;
+
+const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Tab file to open at startup.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TabFile {
+    Path(PathBuf),
+    Content(TabContent), // already read from the standard input
+    Url(String),         // downloaded once the application runs
+}
+
+impl TabFile {
+    /// Tab file of a command line argument, the standard input is read right away.
+    pub fn from_arg(arg: &str) -> Result<Self, TabFileError> {
+        let lowercase = arg.to_ascii_lowercase();
+        if arg == "-" {
+            read_content(std::io::stdin().lock(), "stdin").map(Self::Content)
+        } else if lowercase.starts_with("https://") || lowercase.starts_with("http://") {
+            Ok(Self::Url(arg.to_string()))
+        } else {
+            Ok(Self::Path(PathBuf::from(arg)))
+        }
+    }
+}
+
+/// Tab file read in memory, it has no folder on disk.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TabContent {
+    pub data: Vec<u8>,
+    pub file_name: String,
+}
+
+#[derive(Debug, Clone, thiserror::Error)]
+pub enum TabFileError {
+    #[error("file larger than {} MB", MAX_TAB_FILE_SIZE / (1024 * 1024))]
+    TooLarge,
+    #[error("not a Guitar Pro file")]
+    UnsupportedContent,
+    #[error("{0}")]
+    Io(String),
+}
+
+/// Downloads the tab file at the URL, blocks until done.
+pub fn download(url: &str) -> Result<TabContent, TabFileError> {
+    log::info!("Downloading file: {url}");
+    let agent = ureq::Agent::config_builder()
+        .timeout_global(Some(DOWNLOAD_TIMEOUT))
+        .build()
+        .new_agent();
+    let mut response = agent
+        .get(url)
+        .call()
+        .map_err(|err| TabFileError::Io(err.to_string()))?;
+    // refuse the announced oversized files without reading them
+    if response
+        .body()
+        .content_length()
+        .is_some_and(|length| length > MAX_TAB_FILE_SIZE)
+    {
+        return Err(TabFileError::TooLarge);
+    }
+    let file_name = url_file_name(url);
+    let mut content = read_content(response.body_mut().as_reader(), "download")?;
+    if let Some(file_name) = file_name {
+        content.file_name = file_name;
+    }
+    Ok(content)
+}
+
+/// Reads a tab file up to the size limit, `name` gets the extension of the detected format.
+fn read_content(reader: impl Read, name: &str) -> Result<TabContent, TabFileError> {
+    let mut data = Vec::new();
+    reader
+        .take(MAX_TAB_FILE_SIZE + 1)
+        .read_to_end(&mut data)
+        .map_err(|err| TabFileError::Io(err.to_string()))?;
+    if data.len() as u64 > MAX_TAB_FILE_SIZE {
+        return Err(TabFileError::TooLarge);
+    }
+    let extension = detect_extension(&data).ok_or(TabFileError::UnsupportedContent)?;
+    Ok(TabContent {
+        data,
+        file_name: format!("{name}.{extension}"),
+    })
+}
+
+/// Name of the file at the end of the URL path, when it has a supported extension.
+fn url_file_name(url: &str) -> Option<String> {
+    let path = url.split(['?', '#']).next()?;
+    let name = percent_decode(path.rsplit('/').next()?);
+    let extension = Path::new(&name).extension()?.to_str()?.to_lowercase();
+    SUPPORTED_EXTENSIONS
+        .contains(&extension.as_str())
+        .then_some(name)
+}
+
+/// Decodes the `%XX` escapes of a URL segment, e.g. the spaces of the file names.
+fn percent_decode(segment: &str) -> String {
+    let bytes = segment.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        let escaped = bytes
+            .get(index + 1..index + 3)
+            .filter(|hex| bytes[index] == b'%' && hex.iter().all(u8::is_ascii_hexdigit))
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                index += 3;
+            }
+            None => {
+                decoded.push(bytes[index]);
+                index += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn arguments_and_url_file_names() {
+        assert_eq!(
+            TabFile::from_arg("tabs/song.gp5").unwrap(),
+            TabFile::Path(PathBuf::from("tabs/song.gp5"))
+        );
+        assert_eq!(
+            TabFile::from_arg("HTTPS://example.com/tab.gp").unwrap(),
+            TabFile::Url("HTTPS://example.com/tab.gp".to_string())
+        );
+
+        assert_eq!(
+            url_file_name("https://example.com/tabs/Demo%20v5.GP5?version=2#top"),
+            Some("Demo v5.GP5".to_string())
+        );
+        assert_eq!(
+            url_file_name("https://example.com/100%25.gp4").as_deref(),
+            Some("100%.gp4")
+        );
+        assert_eq!(url_file_name("https://example.com/download?id=12"), None);
+        assert_eq!(url_file_name("https://example.com"), None);
+    }
+
+    #[test]
+    fn content_is_validated() {
+        let data = std::fs::read("test-files/Demo v5.gp5").unwrap();
+        let content = read_content(data.as_slice(), "stdin").unwrap();
+        assert_eq!(content.file_name, "stdin.gp5");
+        assert_eq!(content.data, data);
+
+        assert!(matches!(
+            read_content(b"<html></html>".as_slice(), "download"),
+            Err(TabFileError::UnsupportedContent)
+        ));
+        let oversized = std::io::repeat(0).take(MAX_TAB_FILE_SIZE + 1);
+        assert!(matches!(
+            read_content(oversized, "stdin"),
+            Err(TabFileError::TooLarge)
+        ));
+    }
+
+    #[test]
+    fn download_from_a_local_server() {
+        use std::io::Write;
+        let data = std::fs::read("test-files/canon_rock.gp4").unwrap();
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = [0; 1024];
+            let _ = stream.read(&mut request).unwrap();
+            let header = format!("HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n", data.len());
+            stream.write_all(header.as_bytes()).unwrap();
+            stream.write_all(&data).unwrap();
+        });
+        let content = download(&format!("http://{address}/tabs/canon_rock.gp4")).unwrap();
+        server.join().unwrap();
+        assert_eq!(content.file_name, "canon_rock.gp4");
+        assert_eq!(detect_extension(&content.data), Some("gp4"));
+    }
+}
@@ -0,0 +1,47 @@
+//! Tracks of a tab file printed on the command line, e.g. to find the track of a batch export.
+
+use crate::analysis::is_percussion;
+use crate::parser::song_parser::Song;
+use crate::ui::tuning::tuning_summary;
+use std::fmt::Write;
+
+/// One tab separated line per track: number, name, tuning, MIDI channel and measure count.
+/// The tracks and channels count from 1 like the `--track` arguments and Guitar Pro,
+/// the channels of the other ports follow the 16 of the first one.
+pub fn track_list(song: &Song) -> String {
+    let mut list = String::new();
+    for (index, track) in song.tracks.iter().enumerate() {
+        let tuning = if is_percussion(song, track) {
+            "Drums".to_string()
+        } else {
+            tuning_summary(&track.strings).unwrap_or_default()
+        };
+        writeln!(
+            list,
+            "{}\t{}\t{tuning}\t{}\t{}",
+            index + 1,
+            track.name.trim(),
+            u16::from(track.channel_id) + 1,
+            track.measures.len()
+        )
+        .unwrap();
+    }
+    list
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::song_parser_tests::parse_gp_file;
+
+    #[test]
+    fn demo_song_tracks() {
+        let song = parse_gp_file("test-files/Demo v5.gp5").unwrap();
+        let list = track_list(&song);
+        let lines: Vec<&str> = list.lines().collect();
+        assert_eq!(lines.len(), song.tracks.len());
+        assert_eq!(lines[0], "1\tRhythm Guitar\tStandard E: E A D G B E\t1\t49");
+        assert_eq!(lines[3], "4\tBass\tStandard E: E A D G\t5\t49");
+        assert_eq!(lines[4], "5\tPercussions\tDrums\t10\t49");
+    }
+}
@@ -1,49 +1,132 @@
 use iced::advanced::text::Shaping::Auto;
 use iced::widget::operation::scroll_to;
 use iced::widget::space::horizontal;
-use iced::widget::{Id, Text, column, container, pick_list, row, rule, selector, slider, text};
+use iced::widget::{
+    Id, Text, button, checkbox, column, container, pick_list, row, rule, selector, slider, text,
+    text_input,
+};
 use iced::{
     Alignment, Border, Element, Length, Size, Subscription, Task, Theme, keyboard, stream, window,
 };
 use std::fmt::Display;
 
 use crate::ApplicationArgs;
-use crate::audio::midi_player::AudioPlayer;
+use crate::analysis::instrument::{InstrumentFamily, instrument_family};
+use crate::audio::metronome::Metronome;
+use crate::audio::midi_export::{MidiExportOptions, export_smf, export_stems, stem_file_name};
+use crate::audio::midi_input::{self, MidiTrigger};
+use crate::audio::midi_player::{
+    AudioPlayer, AudioPlayerError, MAX_MASTER_VOLUME, SectionEnd, TrackFilter,
+};
 use crate::audio::playback_order::compute_playback_order;
-use crate::config::Config;
+use crate::audio::synth_rack::SoundFontAssignment;
+use crate::config::{Config, FileSession, file_key};
+use crate::editor::{Document, Edit, NoteFlag, NoteLocation, max_fret, note_at};
+use crate::export::NotationFormat;
+use crate::instance::InstanceListener;
 use crate::parser::parse_gp_data;
-use crate::parser::song_parser::{GpVersion, MeasureHeader, QUARTER_TIME, Song};
-use crate::ui::icons::{open_icon, pause_icon, play_icon, solo_icon, stop_icon};
-use crate::ui::picker::{FilePickerError, load_file, open_file_dialog};
-use crate::ui::tablature::Tablature;
-use crate::ui::tuning::tuning_label;
-use crate::ui::utils::{action_gated, action_toggle, modal, untitled_text_table_box};
+use crate::parser::ruxg::{RUXG_EXTENSION, write_ruxg};
+use crate::parser::song_parser::{GpVersion, Song};
+use crate::tab_file::TabFile;
+use crate::ui::compare::Comparison;
+use crate::ui::diagnostics::{AudioBackendSelection, Diagnostics, diagnostics_view};
+use crate::ui::export::export_view;
+use crate::ui::file_watch::{WatchedFile, file_watch_subscription};
+use crate::ui::fretboard::Fretboard;
+use crate::ui::global_hotkeys::{GlobalHotkeys, global_hotkey_subscription};
+use crate::ui::icons::{
+    backing_icon, family_icon, open_icon, pause_icon, play_icon, solo_icon, stop_icon,
+};
+use crate::ui::issues::{issues_label, issues_view};
+use crate::ui::library::{LibraryPreview, library_files, library_view, read_preview};
+use crate::ui::metronome::metronome_view;
+use crate::ui::midi_control::{TransportAction, action_for, bind, remote_control_view};
+use crate::ui::note_menu::note_menu_view;
+use crate::ui::picker::{
+    FilePickerError, download_file, is_native_file, load_file, open_file_dialog,
+    pick_sound_font_dialog, save_file, save_image_dialog, save_midi_dialog, save_notation_dialog,
+    save_song_dialog, save_stems_dialog,
+};
+use crate::ui::presets::{PresetChoice, SoundFontChoice, SoundFontTracks, presets_view};
+use crate::ui::search::{SearchQuery, SearchResult, search_results_view, search_song};
+use crate::ui::shortcuts::message_for_key;
+use crate::ui::snapshot::{SnapshotError, copy_measure_image};
+use crate::ui::song_info::song_info_view;
+use crate::ui::tablature::{FollowMode, Tablature};
+use crate::ui::tempo_strip::{TempoStrip, difficulty_heatmap};
+use crate::ui::timeline::{PlaybackSegment, playback_segments, timeline_view};
+use crate::ui::track_manager::{TrackLayout, track_manager_view};
+use crate::ui::tuning::{tuning_label, tuning_summary};
+use crate::ui::utils::{
+    COLOR_GRAY, action_gated, action_toggle, catch_panic, format_mmss, level_meter, modal,
+    take_caught_panic, track_color, untitled_text_table_box,
+};
 use iced::futures::{SinkExt, Stream};
-use iced::keyboard::key::Named::{ArrowDown, ArrowLeft, ArrowRight, ArrowUp, F11, Space};
+use iced::time::Instant;
 use iced::widget::scrollable::AbsoluteOffset;
-use std::path::PathBuf;
-use std::rc::Rc;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::Duration;
 use tokio::sync::Notify;
 
+/// Errors kept for the diagnostics report.
+const RECENT_ERRORS: usize = 5;
+
+/// Redraw interval of the output level meter while playing.
+const OUTPUT_LEVEL_REFRESH: Duration = Duration::from_millis(50);
+const SCROLL_FRAME: Duration = Duration::from_millis(16); // about 60 frames per second
+
 const ICONS_FONT: &[u8] = include_bytes!("../../resources/icons.ttf");
 
 pub struct RuxApplication {
-    song_info: Option<SongDisplayInfo>, // parsed song
-    track_selection: TrackSelection,    // selected track
-    all_tracks: Vec<TrackSelection>,    // all possible tracks
-    tablature: Option<Tablature>,       // loaded tablature
-    tablature_id: Id,                   // tablature container id
-    tempo_selection: TempoSelection,    // tempo percentage for playback
-    audio_player: Option<AudioPlayer>,  // audio player
-    tab_file_is_loading: bool,          // file loading flag in progress
-    sound_font_file: Option<PathBuf>,   // sound font file
-    current_tick: Arc<AtomicU32>,       // latest tick published by audio callback
-    beat_notify: Arc<Notify>,           // wake-up signal from audio callback
-    config: Config,                     // local configuration
-    error_message: Option<String>,      // error message to display
-    is_fullscreen: bool,                // F11 toggles fullscreen + hides chrome
+    song_info: Option<SongDisplayInfo>,        // parsed song
+    track_selection: TrackSelection,           // selected track
+    previous_track: Option<usize>,             // track selected before the current one
+    all_tracks: Vec<TrackSelection>,           // all possible tracks
+    tablature: Option<Tablature>,              // loaded tablature
+    tablature_id: Id,                          // tablature container id
+    comparison: Option<Comparison>,            // another tab of the song displayed next to it
+    tempo_selection: TempoSelection,           // tempo percentage for playback
+    audio_player: Option<AudioPlayer>,         // audio player
+    tab_file_is_loading: bool,                 // file loading flag in progress
+    sound_font_file: Option<PathBuf>,          // sound font file
+    current_tick: Arc<AtomicU32>,              // latest tick published by audio callback
+    beat_notify: Arc<Notify>,                  // wake-up signal from audio callback
+    config: Config,                            // local configuration
+    error_message: Option<String>,             // error message to display
+    is_fullscreen: bool,                       // F11 toggles fullscreen + hides chrome
+    search_input: String,                      // song search input
+    search_results: Option<Vec<SearchResult>>, // results of the submitted search
+    play_muted_tracks: bool,                   // ignore the solo/mute flags from the file
+    practice_mode: bool,                       // pause at the end of each measure
+    remote_control_open: bool,                 // remote control panel displayed
+    midi_learning: Option<TransportAction>,    // action bound to the next MIDI message
+    global_hotkeys: Option<GlobalHotkeys>,     // hotkeys grabbed while unfocused
+    playback_segments: Vec<PlaybackSegment>,   // playback order with repeats expanded
+    export_open: bool,                         // export panel displayed
+    diagnostics_open: bool,                    // diagnostics panel displayed
+    presets_open: bool,                        // sound font presets panel displayed
+    recent_errors: Vec<String>,                // last errors reported, for the diagnostics
+    export_options: MidiExportOptions,         // MIDI export settings
+    issues_open: bool,                         // parsing warnings panel displayed
+    song_info_open: bool,                      // song metadata panel displayed
+    metronome_open: bool,                      // metronome panel displayed
+    document: Option<Document>,                // song being edited
+    note_menu: Option<NoteLocation>,           // note whose flags are displayed
+    typed_fret: String,                        // digits typed in the selected note slot
+    saved_path: Option<PathBuf>,               // native file the song is saved to
+    watched_file: Option<WatchedFile>,         // displayed file, reloaded when edited elsewhere
+    file_changed_on_disk: bool,                // reload offered
+    reload_position: Option<(usize, u32)>,     // track & tick restored after the reload
+    track_layout: TrackLayout,                 // display order and hidden tracks of the file
+    track_manager_open: bool,                  // track manager panel displayed
+    instance_file: Option<PathBuf>,            // port file of the single instance mode
+    library_files: Option<Vec<PathBuf>>,       // tab files of the library panel when displayed
+    library_preview: Option<LibraryPreview>,   // first measures of the hovered library file
+    library_hovered: Option<PathBuf>,          // library file the preview is shown for
+    window_focused: bool, // the playback updates pause without focus in low power mode
 }
 
 #[derive(Debug)]
@@ -56,11 +139,12 @@ struct SongDisplayInfo {
     writer: String,
     copyright: String,
     gp_version: GpVersion,
-    file_name: String,
+    file_name: String, // displayed only, the settings of the file are saved under its key
+    file_key: String,
 }
 
 impl SongDisplayInfo {
-    fn new(song: &Song, file_name: String) -> Self {
+    fn new(song: &Song, file_name: String, file_key: String) -> Self {
         Self {
             name: song.song_info.name.clone(),
             artist: song.song_info.artist.clone(),
@@ -71,6 +155,7 @@ impl SongDisplayInfo {
             copyright: song.song_info.copyright.clone(),
             gp_version: song.version,
             file_name,
+            file_key,
         }
     }
 
@@ -107,10 +192,26 @@ impl Default for TempoSelection {
 }
 
 impl TempoSelection {
+    // the synthesizer renders the notes at their pitch, only the MIDI clock follows the tempo
+    const MIN: u32 = 20;
+    const MAX: u32 = 300;
+
     const fn new(percentage: u32) -> Self {
         Self { percentage }
     }
 
+    /// Tempo moved by `step` percents, within the supported range.
+    const fn nudged(self, step: i32) -> Self {
+        let percentage = self.percentage.saturating_add_signed(step);
+        if percentage < Self::MIN {
+            Self::new(Self::MIN)
+        } else if percentage > Self::MAX {
+            Self::new(Self::MAX)
+        } else {
+            Self::new(percentage)
+        }
+    }
+
     const PRESET: [Self; 9] = {
         [
             Self::new(25),
@@ -132,21 +233,92 @@ impl Display for TempoSelection {
     }
 }
 
+/// Measures played before a clicked measure.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct PreRollSelection {
+    measures: usize,
+}
+
+impl PreRollSelection {
+    const fn new(measures: usize) -> Self {
+        Self { measures }
+    }
+
+    const PRESET: [Self; 4] = [Self::new(0), Self::new(1), Self::new(2), Self::new(4)];
+}
+
+impl Display for PreRollSelection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.measures {
+            0 => write!(f, "Off"),
+            1 => write!(f, "1 bar"),
+            n => write!(f, "{n} bars"),
+        }
+    }
+}
+
+/// Maximum number of measures on a tablature row.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct MeasuresPerRowSelection {
+    measures: usize,
+}
+
+impl MeasuresPerRowSelection {
+    const fn new(measures: usize) -> Self {
+        Self { measures }
+    }
+
+    const PRESET: [Self; 5] = [
+        Self::new(0),
+        Self::new(2),
+        Self::new(4),
+        Self::new(6),
+        Self::new(8),
+    ];
+}
+
+impl Display for MeasuresPerRowSelection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.measures {
+            0 => write!(f, "Fit"),
+            n => write!(f, "{n} per row"),
+        }
+    }
+}
+
 #[derive(Debug, Default, Clone, Eq, PartialEq)]
 pub struct TrackSelection {
     index: usize,
+    family: InstrumentFamily,
     name: String,
     tuning: Option<String>,
+    silenced: bool, // muted by the file's solo/mute flags
 }
 
 impl TrackSelection {
-    const fn new(index: usize, name: String, tuning: Option<String>) -> Self {
+    const fn new(
+        index: usize,
+        family: InstrumentFamily,
+        name: String,
+        tuning: Option<String>,
+        silenced: bool,
+    ) -> Self {
         Self {
             index,
+            family,
             name,
             tuning,
+            silenced,
         }
     }
+
+    pub const fn index(&self) -> usize {
+        self.index
+    }
+
+    pub const fn family(&self) -> InstrumentFamily {
+        self.family
+    }
 }
 
 impl Display for TrackSelection {
@@ -155,6 +327,9 @@ impl Display for TrackSelection {
         if let Some(tuning) = &self.tuning {
             write!(f, " ({tuning})")?;
         }
+        if self.silenced {
+            write!(f, " [muted]")?;
+        }
         Ok(())
     }
 }
@@ -169,18 +344,118 @@ pub enum Message {
     FocusTick(u32),                // focus on a specific tick in the tablature
     NextMeasure,                   // focus next measure
     PreviousMeasure,               // focus previous measure
+    NextBeat,                      // focus next beat in playback order
+    PreviousBeat,                  // focus previous beat in playback order
+    NextTrack,                     // select next track
+    PreviousTrack,                 // select previous track
+    ToggleLastTrack,               // select the track selected before the current one
+    CloseOverlay,                  // close the topmost panel or message
     PlayPause,                     // toggle play/pause
     StopPlayer,                    // stop playback
     ToggleSolo,                    // toggle solo mode
+    ToggleBacking,                 // toggle backing track mode, the selected track is muted
+    TogglePlayMutedTracks(bool),   // override the solo/mute flags from the file
+    TogglePracticeMode,            // toggle bar by bar practice mode
+    SelectSection(usize),          // bound the playback from the focused measure to this one
+    ClearSection,                  // play the whole song again
+    ToggleDynamics(bool),          // show or hide the dynamic markings
+    ToggleDirections(bool),        // show or collapse the direction signs of the measures
+    ToggleFretboard(bool),         // show or hide the fretboard under the tablature
+    ToggleChordSuggestions(bool),  // show or hide the detected chord names
+    ToggleScaleHighlight(bool),    // highlight the notes outside the key of the section
+    ToggleNoteNames(bool),         // label the notes with their pitches instead of their frets
+    ToggleSmoothScroll(bool),      // animate the scroll following the playback
+    ScrollFrame(Instant),          // next step of the animated scroll
+    ToggleLowPower(bool),          // fewer redraws, no animation, paused updates without focus
+    WindowFocused(bool),           // the window gained or lost the focus
+    ToggleDifficulty(bool),        // show or hide the difficulty heatmap
     WindowResized,                 // window resized
     TablatureResized(Size),        // tablature resized
+    ScaleFactorChanged(f32),       // window scale factor queried or changed
+    TablatureScrolled(f32, f32),   // tablature scroll offset & maximum offset
+    ResumeFollowing,               // resume auto-scroll after a manual scroll
     TempoSelected(TempoSelection), // tempo selected
+    TempoNudged(i32),              // tempo moved by a few percents
+    PreRollSelected(PreRollSelection), // measures played before a clicked measure
+    SectionEndSelected(SectionEnd), // stop or loop at the end of the section
+    ToggleLoop,                    // switch the section end between stop and loop
+    ToggleMetronomePanel,          // show or hide the metronome settings
+    MetronomeChanged(Metronome),   // metronome settings edited
+    MeasuresPerRowSelected(MeasuresPerRowSelection), // tablature layout density
     IncreaseTempo,                 // increase tempo
     DecreaseTempo,                 // decrease selection
     ClearError,                    // clear error message
     ReportError(String),           // report error message
     ToggleFullscreen,              // toggle fullscreen + hide chrome
-    MasterVolumeChanged(f32),      // master volume slider (0.0 .. 1.0)
+    MasterVolumeChanged(f32),      // master volume slider (0.0 .. 2.0)
+    MasterVolumeReleased,          // save the master volume
+    RefreshOutputLevel,            // redraw the output level meter while playing
+    SearchInputChanged(String),    // song search input edited
+    SearchSubmitted,               // run song search
+    SearchResultSelected(SearchResult), // jump to search result
+    ClearSearch,                   // close search results
+    OpenRemoteControl,             // open remote control panel
+    CloseRemoteControl,            // close remote control panel
+    ToggleGlobalHotkeys(bool),     // grab media keys and shortcuts while unfocused
+    MidiLearn(TransportAction),    // bind the next MIDI message to the action
+    ClearMidiBinding(TransportAction), // remove the MIDI binding of the action
+    MidiTriggered(MidiTrigger),    // MIDI message received from a controller
+    SeekTick(u32),                 // seek to a tick of the expanded playback order
+    OpenDiagnostics,               // open diagnostics panel
+    CloseDiagnostics,              // close diagnostics panel
+    CopyDiagnostics,               // copy the diagnostics report to the clipboard
+    AudioBackendSelected(AudioBackendSelection), // play through another audio backend
+    ToggleMonoOutput(bool),        // downmix both channels
+    OpenPresets,                   // open sound font presets panel
+    ClosePresets,                  // close sound font presets panel
+    PresetRemapped(u8, PresetChoice), // play the program with another preset
+    AddSoundFont,                  // open sound font dialog
+    SoundFontAdded(Result<PathBuf, FilePickerError>), // picked sound font file
+    TrackSoundFontSelected(usize, SoundFontChoice), // play the track with another sound font
+    OpenExport,                    // open export panel
+    CloseExport,                   // close export panel
+    ToggleExpandRepeats(bool),     // expand repeats in the exported file
+    ToggleBeatTextCues(bool),      // export beat texts as cue points
+    ToggleStems(bool),             // export one file per track
+    ExportMidi,                    // pick a destination and write the MIDI file
+    MidiExported(Result<PathBuf, FilePickerError>), // exported file path
+    ExportNotation(NotationFormat, bool), // write the selected track, or all of them, as notation
+    NotationExported(Result<PathBuf, FilePickerError>), // exported notation file path
+    CopyMeasureImage,              // copy the focused measure to the clipboard as PNG
+    MeasureImageCopied(usize, Result<(), SnapshotError>), // copied measure index
+    SaveRowImage,                  // save the row of the focused measure as PNG
+    RowImageSaved(Result<PathBuf, FilePickerError>), // saved image file path
+    ToggleIssues,                  // show or hide the parsing warnings
+    ToggleSongInfo,                // show or hide the song metadata
+    FileChangedOnDisk,             // the displayed file was written
+    ReloadFile,                    // load the version on disk
+    DismissReload,                 // keep the displayed version
+    OpenNoteMenu(NoteLocation),    // right-click on a note
+    CloseNoteMenu,                 // close the note menu
+    ToggleNoteFlag(NoteFlag),      // edit the note of the open menu
+    ToggleEditing(bool),           // note entry mode
+    SelectNoteSlot(NoteLocation),  // click on a string slot in note entry mode
+    TypeFretDigit(u8),             // fret digit, or track number without a slot
+    DeleteNote,                    // remove the note of the selected note slot
+    Undo,                          // undo the last edit
+    Redo,                          // redo the last undone edit
+    SaveSong,                      // save the song to its native file
+    SaveSongAs,                    // pick a native file to save the song to
+    SongSaved(Arc<Song>, Result<PathBuf, FilePickerError>), // saved version & file path
+    OpenTrackManager,              // open track manager panel
+    CloseTrackManager,             // close track manager panel
+    MoveTrack(usize, bool),        // move the track up (true) or down the display order
+    ToggleTrackHidden(usize, bool), // hide (true) or show the track in the track picker
+    InstanceRequest(TabFile),      // tab file opened by another launch of the application
+    OpenLibrary,                   // open the library panel listing the tabs folder
+    OpenCompareDialog,             // pick another tab of the song to compare
+    CompareOpened(Result<(Vec<u8>, Option<PathBuf>, String), FilePickerError>), // compared tab
+    CloseCompare,                  // close the compared tab
+    CompareResized(Size),          // compared tab resized
+    CompareScrolled(f32, f32),     // compared tab scrolled (offset, max offset)
+    CloseLibrary,                  // close the library panel
+    PreviewLibraryFile(PathBuf),   // library file hovered
+    LibraryPreviewLoaded(PathBuf, Option<Arc<Song>>), // parsed library file, if valid
 }
 
 impl RuxApplication {
@@ -188,9 +463,11 @@ impl RuxApplication {
         Self {
             song_info: None,
             track_selection: TrackSelection::default(),
+            previous_track: None,
             all_tracks: vec![],
             tablature: None,
             tablature_id: Id::new("tablature-outer-container"),
+            comparison: None,
             tempo_selection: TempoSelection::default(),
             audio_player: None,
             tab_file_is_loading: false,
@@ -200,22 +477,75 @@ impl RuxApplication {
             config,
             error_message: None,
             is_fullscreen: false,
+            search_input: String::new(),
+            search_results: None,
+            play_muted_tracks: false,
+            practice_mode: false,
+            remote_control_open: false,
+            midi_learning: None,
+            global_hotkeys: None,
+            playback_segments: Vec::new(),
+            export_open: false,
+            diagnostics_open: false,
+            presets_open: false,
+            recent_errors: Vec::new(),
+            export_options: MidiExportOptions::default(),
+            issues_open: false,
+            song_info_open: false,
+            metronome_open: false,
+            document: None,
+            note_menu: None,
+            typed_fret: String::new(),
+            saved_path: None,
+            watched_file: None,
+            file_changed_on_disk: false,
+            reload_position: None,
+            track_layout: TrackLayout::default(),
+            track_manager_open: false,
+            instance_file: None,
+            library_files: None,
+            library_preview: None,
+            library_hovered: None,
+            window_focused: true,
         }
     }
 
     fn boot(args: &ApplicationArgs) -> (Self, Task<Message>) {
-        let app = Self::new(args.sound_font_bank.clone(), args.local_config.clone());
+        let mut app = Self::new(args.sound_font_bank.clone(), args.local_config.clone());
+        if app.config.get_global_hotkeys() {
+            app.global_hotkeys = GlobalHotkeys::register()
+                .inspect_err(|err| log::warn!("Could not register global hotkeys: {err}"))
+                .ok();
+        }
 
-        let init_task = args
-            .tab_file_path
-            .as_ref()
-            .map_or_else(Task::none, |f| Task::done(Message::OpenFile(f.clone())));
+        app.instance_file.clone_from(&args.instance_file);
+
+        let init_task = match &args.tab_file {
+            Some(tab_file) => app.open_tab_file(tab_file.clone()),
+            None => Task::none(),
+        };
         (app, init_task)
     }
 
+    fn open_tab_file(&mut self, tab_file: TabFile) -> Task<Message> {
+        match tab_file {
+            TabFile::Path(path) => Task::done(Message::OpenFile(path)),
+            TabFile::Content(content) => Task::done(Message::FileOpened(Ok((
+                content.data,
+                None,
+                content.file_name,
+            )))),
+            TabFile::Url(_) if self.tab_file_is_loading => Task::none(),
+            TabFile::Url(url) => {
+                self.tab_file_is_loading = true;
+                Task::perform(download_file(url), Message::FileOpened)
+            }
+        }
+    }
+
     pub fn start(args: ApplicationArgs) -> iced::Result {
         let antialiasing = !args.no_antialiasing;
-        iced::application(move || Self::boot(&args), Self::update, Self::view)
+        iced::application(move || Self::boot(&args), Self::guarded_update, Self::view)
             .title(Self::title)
             .subscription(Self::subscription)
             .default_font(iced::Font::MONOSPACE)
@@ -229,7 +559,12 @@ impl RuxApplication {
 
     fn title(&self) -> String {
         match &self.song_info {
-            Some(song_info) => format!("Ruxguitar - {}", song_info.file_name),
+            Some(song_info) => {
+                // unsaved edits are flagged like in most editors
+                let dirty = self.document.as_ref().is_some_and(Document::is_dirty);
+                let marker = if dirty { " *" } else { "" };
+                format!("Ruxguitar - {}{marker}", song_info.file_name)
+            }
             None => String::from("Ruxguitar - untitled"),
         }
     }
@@ -239,14 +574,175 @@ impl RuxApplication {
             return Task::none();
         };
         tablature.focus_on_measure(measure_id);
-        let scroll_offset = tablature.scroll_offset_for_measure(measure_id);
+        let scroll_offset = tablature.scroll_to_measure(measure_id);
         let scroll_id = tablature.scroll_id.clone();
         if let Some(audio_player) = &self.audio_player {
             audio_player.focus_measure(measure_id);
         }
-        scroll_offset.map_or_else(Task::none, |y| {
+        let scroll = scroll_offset.map_or_else(Task::none, |y| {
             scroll_to(scroll_id, AbsoluteOffset { x: 0.0, y })
-        })
+        });
+        Task::batch([scroll, self.sync_comparison()])
+    }
+
+    /// Focus the compared tab on the measure index focused in the open song.
+    fn sync_comparison(&mut self) -> Task<Message> {
+        let (Some(tablature), Some(comparison)) = (&self.tablature, &mut self.comparison) else {
+            return Task::none();
+        };
+        let scroll_id = comparison.tablature.scroll_id.clone();
+        comparison
+            .sync_to_measure(tablature.focused_measure())
+            .map_or_else(Task::none, |y| {
+                scroll_to(scroll_id, AbsoluteOffset { x: 0.0, y })
+            })
+    }
+
+    /// Move the tablature and player focus by one beat along the playback order.
+    fn focus_adjacent_beat(&self, forward: bool) -> Task<Message> {
+        let current_tick = self.current_tick.load(Ordering::Relaxed);
+        let Some(tick) = self
+            .tablature
+            .as_ref()
+            .and_then(|t| t.adjacent_beat_tick(current_tick, forward))
+        else {
+            return Task::none();
+        };
+        if let Some(audio_player) = &self.audio_player {
+            audio_player.seek(tick);
+        } else {
+            self.current_tick.store(tick, Ordering::Relaxed);
+        }
+        Task::done(Message::FocusTick(tick))
+    }
+
+    /// Select the visible track next to the current one in display order, wrapping around.
+    fn select_adjacent_track(&self, forward: bool) -> Task<Message> {
+        let visible: Vec<usize> = self.track_layout.visible().collect();
+        let track_count = visible.len();
+        if track_count == 0 {
+            return Task::none();
+        }
+        // a hidden current track moves to the first visible one
+        let current = visible
+            .iter()
+            .position(|index| *index == self.track_selection.index);
+        let position = match current {
+            Some(current) if forward => (current + 1) % track_count,
+            Some(current) => (current + track_count - 1) % track_count,
+            None => 0,
+        };
+        Task::done(Message::TrackSelected(
+            self.all_tracks[visible[position]].clone(),
+        ))
+    }
+
+    /// Select the track numbered from 1 in the track picker.
+    fn select_track_number(&self, number: u8) -> Task<Message> {
+        let track = usize::from(number)
+            .checked_sub(1)
+            .and_then(|position| self.track_layout.visible().nth(position));
+        match track.and_then(|track| self.all_tracks.get(track)) {
+            Some(selection) => Task::done(Message::TrackSelected(selection.clone())),
+            None => Task::none(),
+        }
+    }
+
+    /// Tracks of the track picker, in display order.
+    fn visible_tracks(&self) -> Vec<TrackSelection> {
+        self.track_layout
+            .visible()
+            .filter_map(|index| self.all_tracks.get(index).cloned())
+            .collect()
+    }
+
+    /// Persist the selected track, the tempo and the section of the file to restore them on opening.
+    fn save_file_session(&mut self) -> Task<Message> {
+        let Some(song_info) = &self.song_info else {
+            return Task::none();
+        };
+        let file_session = FileSession {
+            track: self.track_selection.index,
+            tempo_percentage: self.tempo_selection.percentage,
+            section: self.tablature.as_ref().and_then(Tablature::section),
+        };
+        match self
+            .config
+            .set_file_session(&song_info.file_key, file_session)
+        {
+            Ok(()) => Task::none(),
+            Err(err) => Task::done(Message::ReportError(format!(
+                "Failed to save file session: {err}"
+            ))),
+        }
+    }
+
+    /// Persist the track layout of the file, the selected track stays visible.
+    fn save_track_layout(&mut self) -> Task<Message> {
+        let Some(song_info) = &self.song_info else {
+            return Task::none();
+        };
+        if let Err(err) = self
+            .config
+            .set_track_layout(&song_info.file_key, self.track_layout.clone())
+        {
+            return Task::done(Message::ReportError(format!(
+                "Failed to save track layout: {err}"
+            )));
+        }
+        if self.track_layout.is_hidden(self.track_selection.index) {
+            self.select_adjacent_track(true)
+        } else {
+            Task::none()
+        }
+    }
+
+    /// Error boundary of the application: a panic while handling a message closes the song
+    /// to get back to a consistent state, panics caught while drawing are reported as well.
+    fn guarded_update(&mut self, message: Message) -> Task<Message> {
+        let task = catch_panic("message handling", || None, || Some(self.update(message)));
+        let caught = take_caught_panic();
+        match (task, caught) {
+            (Some(task), None) => task,
+            (Some(task), Some(reason)) => Task::batch([
+                task,
+                Task::done(Message::ReportError(format!("Internal error in {reason}"))),
+            ]),
+            (None, reason) => {
+                self.close_song();
+                let reason = reason.unwrap_or_else(|| "message handling".to_string());
+                Task::done(Message::ReportError(format!(
+                    "Internal error in {reason}, the song was closed"
+                )))
+            }
+        }
+    }
+
+    /// Back to the empty state of the startup, the settings are kept.
+    fn close_song(&mut self) {
+        if let Some(audio_player) = &mut self.audio_player {
+            audio_player.stop();
+        }
+        self.audio_player = None;
+        self.tablature = None;
+        self.comparison = None;
+        self.document = None;
+        self.song_info = None;
+        self.all_tracks.clear();
+        self.previous_track = None;
+        self.playback_segments.clear();
+        self.note_menu = None;
+        self.typed_fret.clear();
+        self.saved_path = None;
+        self.watched_file = None;
+        self.file_changed_on_disk = false;
+        self.reload_position = None;
+        self.tab_file_is_loading = false;
+        self.export_open = false;
+        self.presets_open = false;
+        self.issues_open = false;
+        self.song_info_open = false;
+        self.track_manager_open = false;
     }
 
     fn update(&mut self, message: Message) -> Task<Message> {
@@ -255,8 +751,14 @@ impl RuxApplication {
                 if let Some(tablature) = self.tablature.as_mut() {
                     tablature.update_track(selection.index);
                 }
+                if let Some(comparison) = &mut self.comparison {
+                    comparison.select_track(selection.index);
+                }
+                if selection.index != self.track_selection.index {
+                    self.previous_track = Some(self.track_selection.index);
+                }
                 self.track_selection = selection;
-                Task::none()
+                self.save_file_session()
             }
             Message::OpenFileDialog => {
                 if self.tab_file_is_loading {
@@ -270,6 +772,9 @@ impl RuxApplication {
                 }
             }
             Message::OpenFile(path) => {
+                self.library_files = None;
+                self.library_preview = None;
+                self.library_hovered = None;
                 if self.tab_file_is_loading {
                     Task::none()
                 } else {
@@ -279,6 +784,7 @@ impl RuxApplication {
             }
             Message::FileOpened(result) => {
                 self.tab_file_is_loading = false;
+                let reload_position = self.reload_position.take();
                 // stop and drop the previous audio player if any: if loading
                 // fails below, the old song must not keep playing against the
                 // new tablature state
@@ -288,7 +794,13 @@ impl RuxApplication {
                 self.audio_player = None;
                 match result {
                     Ok((contents, parent_folder, file_name)) => {
-                        if let Err(err) = self.config.set_tabs_folder(parent_folder) {
+                        // saving a native file overwrites it, other formats are saved as
+                        let opened_path = parent_folder.as_ref().map(|f| f.join(&file_name));
+                        let file_key = file_key(opened_path.as_deref(), &contents);
+                        // files read from the standard input or downloaded have no folder
+                        if parent_folder.is_some()
+                            && let Err(err) = self.config.set_tabs_folder(parent_folder)
+                        {
                             return Task::done(Message::ReportError(format!(
                                 "Failed to set tabs folder: {err}"
                             )));
@@ -306,7 +818,13 @@ impl RuxApplication {
                                         .find(|c| c.channel_id == track.channel_id)
                                         .filter(|c| !c.is_percussion())
                                         .and_then(|_| tuning_label(&track.strings));
-                                    TrackSelection::new(index, track.name.clone(), tuning)
+                                    TrackSelection::new(
+                                        index,
+                                        instrument_family(&song, track),
+                                        track.name.clone(),
+                                        tuning,
+                                        song.is_track_silenced(index),
+                                    )
                                 })
                                 .collect();
                             if track_selections.is_empty() {
@@ -315,33 +833,79 @@ impl RuxApplication {
                                 ));
                             }
                             self.all_tracks.clone_from(&track_selections);
-                            self.song_info = Some(SongDisplayInfo::new(&song, file_name));
-                            // select first track by default
-                            let default_track = 0;
+                            self.track_layout = self
+                                .config
+                                .get_track_layout(&file_key)
+                                .for_track_count(track_selections.len());
+                            self.track_manager_open = false;
+                            self.search_results = None;
+                            self.issues_open = false;
+                            self.song_info_open = false;
+                            self.note_menu = None;
+                            // practice context of the last time the file was open
+                            let file_session = self
+                                .config
+                                .get_file_session(&file_key)
+                                .filter(|_| reload_position.is_none());
+                            if let Some(file_session) = file_session {
+                                self.tempo_selection =
+                                    TempoSelection::new(file_session.tempo_percentage).nudged(0);
+                            }
+                            self.song_info = Some(SongDisplayInfo::new(&song, file_name, file_key));
+                            // select the first visible track by default, the same one on reload
+                            let default_track = reload_position
+                                .map(|(track, _)| track)
+                                .or_else(|| file_session.map(|session| session.track))
+                                .filter(|track| *track < track_selections.len())
+                                .or_else(|| self.track_layout.visible().next())
+                                .unwrap_or(0);
                             let default_track_selection = track_selections[default_track].clone();
                             self.track_selection = default_track_selection;
+                            self.previous_track = None;
                             // share song ownership with tablature and player
-                            let song_rc = Rc::new(song);
-                            let playback_order = compute_playback_order(&song_rc.measure_headers);
+                            self.watched_file = opened_path.clone().map(WatchedFile::new);
+                            self.file_changed_on_disk = false;
+                            self.saved_path = opened_path.filter(|p| is_native_file(p));
+                            let song_arc = Arc::new(song);
+                            self.document = Some(Document::new(song_arc.clone()));
+                            let playback_order = compute_playback_order(&song_arc.measure_headers);
+                            self.playback_segments =
+                                playback_segments(&song_arc.measure_headers, &playback_order);
                             let tablature_scroll_id = Id::new("tablature-scroll-elements");
-                            let tablature = Tablature::new(
-                                song_rc.clone(),
+                            let mut tablature = Tablature::new(
+                                song_arc.clone(),
                                 default_track,
                                 tablature_scroll_id.clone(),
                                 &playback_order,
                             );
+                            tablature.set_tempo_percentage(self.tempo_selection.percentage);
+                            tablature.set_show_dynamics(!self.config.get_hide_dynamics());
+                            tablature.set_show_directions(!self.config.get_hide_directions());
+                            tablature.set_measures_per_row(self.config.get_measures_per_row());
+                            tablature.set_smooth_scroll(!self.config.get_instant_scroll());
+                            tablature.set_low_power(self.config.get_low_power());
+                            tablature.set_chord_suggestions(self.config.get_chord_suggestions());
+                            tablature.set_scale_highlight(self.config.get_scale_highlight());
+                            tablature.set_note_names(self.config.get_note_names());
+                            let section = file_session
+                                .and_then(|session| session.section)
+                                .filter(|(_, last)| *last < song_arc.measure_headers.len());
+                            if let Some(section) = section {
+                                tablature.set_section(Some(section));
+                                tablature.focus_on_measure(section.0);
+                            }
                             self.tablature = Some(tablature);
                             // audio player initialization
-                            match AudioPlayer::new(
-                                song_rc.clone(),
-                                song_rc.tempo.value,
-                                self.tempo_selection.percentage,
-                                self.sound_font_file.clone(),
-                                self.current_tick.clone(),
-                                self.beat_notify.clone(),
-                                &playback_order,
-                            ) {
+                            match self.new_audio_player(song_arc, &playback_order) {
                                 Ok(audio_player) => {
+                                    // back to where the previous version was left
+                                    let restore = match reload_position {
+                                        Some((_, tick)) => {
+                                            audio_player.seek(tick);
+                                            Task::done(Message::FocusTick(tick))
+                                        }
+                                        None => Task::none(),
+                                    };
                                     self.audio_player = Some(audio_player);
                                     // reset tablature scroll and trigger layout computation
                                     Task::batch([
@@ -351,6 +915,7 @@ impl RuxApplication {
                                         ),
                                         Task::done(Message::WindowResized),
                                     ])
+                                    .chain(restore)
                                 }
                                 Err(err) => Task::done(Message::ReportError(format!(
                                     "Failed to initialize audio: {err}"
@@ -370,18 +935,44 @@ impl RuxApplication {
                 if let Some(tablature) = &mut self.tablature {
                     tablature.focus_on_measure(measure_id);
                 }
-                // focus measure in player
+                // focus measure in player, leading into it with the pre-roll
                 if let Some(audio_player) = &self.audio_player {
-                    audio_player.focus_measure(measure_id);
+                    let pre_roll = self.config.get_pre_roll_measures();
+                    audio_player.focus_measure_with_pre_roll(measure_id, pre_roll);
                 }
-                Task::none()
+                self.sync_comparison()
+            }
+            Message::SelectSection(measure_id) => {
+                let Some(tablature) = &mut self.tablature else {
+                    return Task::none();
+                };
+                let focused = tablature.focused_measure();
+                let section = (focused.min(measure_id), focused.max(measure_id));
+                tablature.set_section(Some(section));
+                tablature.focus_on_measure(section.0);
+                if let Some(audio_player) = &mut self.audio_player {
+                    audio_player.set_section(Some(section));
+                }
+                self.save_file_session()
+            }
+            Message::ClearSection => {
+                if let Some(tablature) = &mut self.tablature {
+                    tablature.set_section(None);
+                }
+                if let Some(audio_player) = &mut self.audio_player {
+                    audio_player.set_section(None);
+                }
+                self.save_file_session()
             }
             Message::FocusTick(tick) => {
+                let mut scroll = Task::none();
                 if let Some(tablature) = &mut self.tablature
-                    && let Some(scroll_offset) = tablature.focus_on_tick(tick)
+                    && let Some(scroll_offset) = tablature
+                        .focus_on_tick(tick)
+                        .and_then(|offset| tablature.scroll_towards(offset, Instant::now()))
                 {
                     // scroll to the focused measure
-                    return scroll_to(
+                    scroll = scroll_to(
                         tablature.scroll_id.clone(),
                         AbsoluteOffset {
                             x: 0.0,
@@ -389,8 +980,60 @@ impl RuxApplication {
                         },
                     );
                 }
+                Task::batch([scroll, self.sync_comparison()])
+            }
+            Message::ScrollFrame(now) => match &mut self.tablature {
+                Some(tablature) => tablature.scroll_frame(now).map_or_else(Task::none, |y| {
+                    scroll_to(tablature.scroll_id.clone(), AbsoluteOffset { x: 0.0, y })
+                }),
+                None => Task::none(),
+            },
+            Message::ToggleLowPower(low_power) => {
+                if let Some(tablature) = &mut self.tablature {
+                    tablature.set_low_power(low_power);
+                }
+                if let Some(comparison) = &mut self.comparison {
+                    comparison.tablature.set_low_power(low_power);
+                }
+                if let Err(err) = self.config.set_low_power(low_power) {
+                    return Task::done(Message::ReportError(format!(
+                        "Failed to save low power mode: {err}"
+                    )));
+                }
+                Task::none()
+            }
+            Message::WindowFocused(focused) => {
+                self.window_focused = focused;
+                let is_playing = self
+                    .audio_player
+                    .as_ref()
+                    .is_some_and(AudioPlayer::is_playing);
+                // catch up with the beats played while the updates were paused
+                if focused && is_playing && self.config.get_low_power() {
+                    Task::done(Message::FocusTick(
+                        self.current_tick.load(Ordering::Acquire),
+                    ))
+                } else {
+                    Task::none()
+                }
+            }
+            Message::ToggleSmoothScroll(smooth_scroll) => {
+                if let Some(tablature) = &mut self.tablature {
+                    tablature.set_smooth_scroll(smooth_scroll);
+                }
+                if let Err(err) = self.config.set_instant_scroll(!smooth_scroll) {
+                    return Task::done(Message::ReportError(format!(
+                        "Failed to save smooth scroll: {err}"
+                    )));
+                }
                 Task::none()
             }
+            Message::SeekTick(tick) => {
+                if let Some(audio_player) = &self.audio_player {
+                    audio_player.seek(tick);
+                }
+                Task::done(Message::FocusTick(tick))
+            }
             Message::NextMeasure => {
                 let target = self.tablature.as_ref().and_then(|t| {
                     let next = t.focused_measure() + 1;
@@ -405,14 +1048,66 @@ impl RuxApplication {
                     .and_then(|t| t.focused_measure().checked_sub(1));
                 target.map_or_else(Task::none, |m| self.focus_measure_with_scroll(m))
             }
+            Message::NextBeat => self.focus_adjacent_beat(true),
+            Message::PreviousBeat => self.focus_adjacent_beat(false),
+            Message::NextTrack => self.select_adjacent_track(true),
+            Message::PreviousTrack => self.select_adjacent_track(false),
+            Message::ToggleLastTrack => self
+                .previous_track
+                .and_then(|track| self.all_tracks.get(track).cloned())
+                .map_or_else(Task::none, |selection| {
+                    Task::done(Message::TrackSelected(selection))
+                }),
+            Message::CloseOverlay => {
+                // topmost first
+                if self.error_message.is_some() {
+                    self.error_message = None;
+                } else if self.export_open {
+                    self.export_open = false;
+                } else if self.presets_open {
+                    self.presets_open = false;
+                } else if self.diagnostics_open {
+                    self.diagnostics_open = false;
+                } else if self.track_manager_open {
+                    self.track_manager_open = false;
+                } else if self.library_files.is_some() {
+                    self.library_files = None;
+                    self.library_preview = None;
+                    self.library_hovered = None;
+                } else if self.note_menu.is_some() {
+                    self.note_menu = None;
+                } else if self.remote_control_open {
+                    self.remote_control_open = false;
+                    self.midi_learning = None;
+                } else if self.search_results.is_some() {
+                    self.search_results = None;
+                } else if self.issues_open {
+                    self.issues_open = false;
+                } else if self.song_info_open {
+                    self.song_info_open = false;
+                } else if self.metronome_open {
+                    self.metronome_open = false;
+                }
+                Task::none()
+            }
             Message::PlayPause => {
                 if self.tab_file_is_loading {
                     return Task::none();
                 }
-                if let Some(audio_player) = &mut self.audio_player
-                    && let Some(err) = audio_player.toggle_play()
+                if let Some(audio_player) = &mut self.audio_player {
+                    if audio_player.is_playing() && audio_player.is_waiting_at_measure_end() {
+                        // practice mode: continue with the next measure
+                        audio_player.next_measure();
+                    } else if let Some(err) = audio_player.toggle_play() {
+                        return Task::done(Message::ReportError(err));
+                    }
+                }
+                // follow the playback again when it starts
+                if let (Some(audio_player), Some(tablature)) =
+                    (&self.audio_player, &mut self.tablature)
+                    && audio_player.is_playing()
                 {
-                    return Task::done(Message::ReportError(err));
+                    tablature.follow_playback();
                 }
                 // Hack to make sure the tablature is aware of its size
                 Task::done(Message::WindowResized)
@@ -425,7 +1120,9 @@ impl RuxApplication {
                     audio_player.stop();
                     // reset tablature focus
                     tablature.focus_on_measure(0);
+                    tablature.follow_playback();
                     // reset tablature scroll
+                    tablature.expect_scroll(0.0);
                     scroll_to(
                         tablature.scroll_id.clone(),
                         AbsoluteOffset::<f32>::default(),
@@ -441,178 +1138,1525 @@ impl RuxApplication {
                 }
                 Task::none()
             }
-            Message::WindowResized => {
-                // query tablature container size
-                selector::find(self.tablature_id.clone()).then(|target| {
-                    // the container can be fully clipped out of the viewport
-                    // (e.g. window resized very short): skip the layout update
-                    target
-                        .and_then(|t| t.visible_bounds())
-                        .map_or_else(Task::none, |bounds| {
-                            Task::done(Message::TablatureResized(bounds.size()))
-                        })
-                })
+            Message::ToggleBacking => {
+                if let Some(audio_player) = &self.audio_player {
+                    let track = self.track_selection.index;
+                    audio_player.toggle_backing_mode(track);
+                }
+                Task::none()
             }
-            Message::TablatureResized(tablature_container_size) => {
+            Message::PreRollSelected(selection) => {
+                if let Err(err) = self.config.set_pre_roll_measures(selection.measures) {
+                    return Task::done(Message::ReportError(format!(
+                        "Failed to save pre-roll: {err}"
+                    )));
+                }
+                Task::none()
+            }
+            Message::ToggleLoop => {
+                let section_end = if self.config.get_section_end() == SectionEnd::Stop {
+                    SectionEnd::Loop
+                } else {
+                    SectionEnd::Stop
+                };
+                Task::done(Message::SectionEndSelected(section_end))
+            }
+            Message::SectionEndSelected(section_end) => {
+                if let Some(audio_player) = &mut self.audio_player {
+                    audio_player.set_section_end(section_end);
+                }
+                if let Err(err) = self.config.set_section_end(section_end) {
+                    return Task::done(Message::ReportError(format!(
+                        "Failed to save section end: {err}"
+                    )));
+                }
+                Task::none()
+            }
+            Message::ToggleMetronomePanel => {
+                self.metronome_open = !self.metronome_open;
+                Task::none()
+            }
+            Message::MetronomeChanged(metronome) => {
+                if let Some(audio_player) = &self.audio_player {
+                    audio_player.set_metronome(metronome);
+                }
+                if let Err(err) = self.config.set_metronome(metronome) {
+                    return Task::done(Message::ReportError(format!(
+                        "Failed to save metronome: {err}"
+                    )));
+                }
+                Task::none()
+            }
+            Message::MeasuresPerRowSelected(selection) => {
                 if let Some(tablature) = &mut self.tablature {
-                    tablature.update_container_width(tablature_container_size.width);
+                    tablature.set_measures_per_row(selection.measures);
+                }
+                if let Some(comparison) = &mut self.comparison {
+                    comparison
+                        .tablature
+                        .set_measures_per_row(selection.measures);
+                }
+                if let Err(err) = self.config.set_measures_per_row(selection.measures) {
+                    return Task::done(Message::ReportError(format!(
+                        "Failed to save measures per row: {err}"
+                    )));
                 }
                 Task::none()
             }
-            Message::TempoSelected(tempos_selection) => {
+            Message::TogglePracticeMode => {
+                self.practice_mode = !self.practice_mode;
                 if let Some(audio_player) = &self.audio_player {
-                    audio_player.set_tempo_percentage(tempos_selection.percentage);
+                    audio_player.set_practice_mode(self.practice_mode);
                 }
-                self.tempo_selection = tempos_selection;
                 Task::none()
             }
-            Message::IncreaseTempo => {
-                if self.tab_file_is_loading {
-                    return Task::none();
+            Message::ToggleFretboard(show_fretboard) => {
+                if let Err(err) = self.config.set_show_fretboard(show_fretboard) {
+                    return Task::done(Message::ReportError(format!(
+                        "Failed to save fretboard display: {err}"
+                    )));
                 }
-                if let Some(current_index) = TempoSelection::PRESET
-                    .iter()
-                    .position(|t| t == &self.tempo_selection)
-                    && current_index < TempoSelection::PRESET.len() - 1
-                {
-                    let next_tempo = TempoSelection::PRESET[current_index + 1];
-                    return Task::done(Message::TempoSelected(next_tempo));
+                Task::none()
+            }
+            Message::ToggleDynamics(show_dynamics) => {
+                if let Some(tablature) = &mut self.tablature {
+                    tablature.set_show_dynamics(show_dynamics);
+                }
+                if let Err(err) = self.config.set_hide_dynamics(!show_dynamics) {
+                    return Task::done(Message::ReportError(format!(
+                        "Failed to save dynamics display: {err}"
+                    )));
                 }
                 Task::none()
             }
-            Message::DecreaseTempo => {
-                if self.tab_file_is_loading {
-                    return Task::none();
+            Message::ToggleDirections(show_directions) => {
+                if let Some(tablature) = &mut self.tablature {
+                    tablature.set_show_directions(show_directions);
                 }
-                if let Some(current_index) = TempoSelection::PRESET
-                    .iter()
-                    .position(|t| t == &self.tempo_selection)
-                    && current_index > 0
-                {
-                    let previous_tempo = TempoSelection::PRESET[current_index - 1];
-                    return Task::done(Message::TempoSelected(previous_tempo));
+                if let Err(err) = self.config.set_hide_directions(!show_directions) {
+                    return Task::done(Message::ReportError(format!(
+                        "Failed to save directions display: {err}"
+                    )));
                 }
                 Task::none()
             }
-            Message::ToggleFullscreen => {
-                self.is_fullscreen = !self.is_fullscreen;
-                let mode = if self.is_fullscreen {
-                    window::Mode::Fullscreen
-                } else {
-                    window::Mode::Windowed
-                };
-                window::latest().and_then(move |id| window::set_mode(id, mode))
+            Message::ToggleChordSuggestions(chord_suggestions) => {
+                if let Some(tablature) = &mut self.tablature {
+                    tablature.set_chord_suggestions(chord_suggestions);
+                }
+                if let Err(err) = self.config.set_chord_suggestions(chord_suggestions) {
+                    return Task::done(Message::ReportError(format!(
+                        "Failed to save chord names display: {err}"
+                    )));
+                }
+                Task::none()
             }
-            Message::MasterVolumeChanged(volume) => {
-                if let Some(audio_player) = &self.audio_player {
-                    audio_player.set_master_volume(volume);
+            Message::ToggleScaleHighlight(scale_highlight) => {
+                if let Some(tablature) = &mut self.tablature {
+                    tablature.set_scale_highlight(scale_highlight);
+                }
+                if let Err(err) = self.config.set_scale_highlight(scale_highlight) {
+                    return Task::done(Message::ReportError(format!(
+                        "Failed to save scale highlight: {err}"
+                    )));
                 }
                 Task::none()
             }
-            Message::ClearError => {
-                self.error_message = None;
+            Message::ToggleNoteNames(note_names) => {
+                if let Some(tablature) = &mut self.tablature {
+                    tablature.set_note_names(note_names);
+                }
+                if let Some(comparison) = &mut self.comparison {
+                    comparison.tablature.set_note_names(note_names);
+                }
+                if let Err(err) = self.config.set_note_names(note_names) {
+                    return Task::done(Message::ReportError(format!(
+                        "Failed to save note names display: {err}"
+                    )));
+                }
                 Task::none()
             }
-            Message::ReportError(error) => {
-                log::warn!("{error}");
-                self.error_message = Some(error);
+            Message::ToggleDifficulty(difficulty_heatmap) => {
+                if let Err(err) = self.config.set_difficulty_heatmap(difficulty_heatmap) {
+                    return Task::done(Message::ReportError(format!(
+                        "Failed to save difficulty display: {err}"
+                    )));
+                }
                 Task::none()
             }
-        }
-    }
-
-    fn view(&self) -> Element<'_, Message> {
-        let open_file = action_gated(
-            open_icon(),
-            "Open file",
-            (!self.tab_file_is_loading).then_some(Message::OpenFileDialog),
-        );
-
-        let player_control = if let Some(audio_player) = &self.audio_player {
-            let (icon, message) = if audio_player.is_playing() {
-                (pause_icon(), "Pause")
-            } else {
-                (play_icon(), "Play")
-            };
-            let play_button = action_gated(icon, message, Some(Message::PlayPause));
-            let stop_button = action_gated(stop_icon(), "Stop", Some(Message::StopPlayer));
-            let counter = self
-                .tablature
-                .as_ref()
-                .map(|tab| {
-                    let headers = &tab.song.measure_headers;
-                    let focused = tab.focused_measure();
-                    let total_measures = tab.measure_count();
-                    let current_seconds = song_time_up_to_measure(headers, focused);
-                    let total_seconds = song_time_up_to_measure(headers, total_measures);
-                    format!(
-                        "Measure {}/{} \u{2022} {}/{}",
-                        focused + 1,
-                        total_measures,
-                        format_mmss(current_seconds),
-                        format_mmss(total_seconds),
-                    )
-                })
-                .unwrap_or_default();
-            row![play_button, stop_button, text(counter).size(14)]
-                .spacing(10)
-                .align_y(Alignment::Center)
-        } else {
-            row![horizontal()]
-        };
-
-        let track_control = if self.all_tracks.is_empty() {
-            row![horizontal()]
-        } else {
-            let tempo_label = text("Tempo").size(14);
-            let tempo_percentage = pick_list(
+            Message::TogglePlayMutedTracks(play_muted_tracks) => {
+                self.play_muted_tracks = play_muted_tracks;
+                let Some(tablature) = &mut self.tablature else {
+                    return Task::none();
+                };
+                // the flags are applied when building the events: rebuild the player
+                if self.audio_player.is_some() {
+                    tablature.focus_on_measure(0);
+                }
+                match self.rebuild_audio_player() {
+                    Ok(()) => Task::none(),
+                    Err(err) => Task::done(Message::ReportError(format!(
+                        "Failed to initialize audio: {err}"
+                    ))),
+                }
+            }
+            Message::OpenNoteMenu(location) => {
+                self.note_menu = Some(location);
+                Task::none()
+            }
+            Message::CloseNoteMenu => {
+                self.note_menu = None;
+                Task::none()
+            }
+            Message::ToggleNoteFlag(flag) => {
+                let Some(location) = self.note_menu else {
+                    return Task::none();
+                };
+                self.apply_edit(Edit::ToggleNoteFlag(location, flag), false)
+            }
+            Message::ToggleEditing(editing) => {
+                if let Some(tablature) = &mut self.tablature {
+                    tablature.set_editing(editing);
+                }
+                self.typed_fret.clear();
+                Task::none()
+            }
+            Message::SelectNoteSlot(location) => {
+                if let Some(tablature) = &mut self.tablature {
+                    tablature.set_edit_cursor(Some(location));
+                }
+                self.typed_fret.clear();
+                Task::done(Message::FocusMeasure(location.measure))
+            }
+            Message::TypeFretDigit(digit) => {
+                let location = self.tablature.as_ref().and_then(Tablature::edit_cursor);
+                let Some(location) = location else {
+                    // without a note slot the digits select the tracks of the picker
+                    return self.select_track_number(digit);
+                };
+                let Some(document) = &self.document else {
+                    return Task::none();
+                };
+                // a digit following another one completes the fret if it exists
+                let max_fret = max_fret(document.song(), location.track);
+                self.typed_fret.push(char::from(b'0' + digit));
+                let fret = match self.typed_fret.parse::<u8>() {
+                    Ok(fret) if self.typed_fret.len() <= 2 && fret <= max_fret => fret,
+                    _ => {
+                        self.typed_fret = digit.to_string();
+                        digit
+                    }
+                };
+                // the second digit amends the edit of the first one
+                let amend = self.typed_fret.len() > 1;
+                self.apply_edit(Edit::SetNoteFret(location, fret), amend)
+            }
+            Message::DeleteNote => {
+                let Some(location) = self.tablature.as_ref().and_then(Tablature::edit_cursor)
+                else {
+                    return Task::none();
+                };
+                self.typed_fret.clear();
+                self.apply_edit(Edit::DeleteNote(location), false)
+            }
+            Message::Undo | Message::Redo => {
+                let Some(document) = &mut self.document else {
+                    return Task::none();
+                };
+                self.typed_fret.clear();
+                let changed = if matches!(message, Message::Undo) {
+                    document.undo()
+                } else {
+                    document.redo()
+                };
+                if changed {
+                    self.show_edited_song()
+                } else {
+                    Task::none()
+                }
+            }
+            Message::WindowResized => {
+                // query tablature container size
+                let resized = selector::find(self.tablature_id.clone()).then(|target| {
+                    // the container can be fully clipped out of the viewport
+                    // (e.g. window resized very short): skip the layout update
+                    target
+                        .and_then(|t| t.visible_bounds())
+                        .map_or_else(Task::none, |bounds| {
+                            Task::done(Message::TablatureResized(bounds.size()))
+                        })
+                });
+                let compare_resized = self.comparison.as_ref().map_or_else(Task::none, |c| {
+                    selector::find(c.container_id.clone()).then(|target| {
+                        target
+                            .and_then(|t| t.visible_bounds())
+                            .map_or_else(Task::none, |bounds| {
+                                Task::done(Message::CompareResized(bounds.size()))
+                            })
+                    })
+                });
+                let scale_factor = window::latest()
+                    .and_then(window::scale_factor)
+                    .map(Message::ScaleFactorChanged);
+                Task::batch([resized, compare_resized, scale_factor])
+            }
+            Message::ScaleFactorChanged(scale_factor) => {
+                if let Some(tablature) = &mut self.tablature {
+                    tablature.set_scale_factor(scale_factor);
+                }
+                if let Some(comparison) = &mut self.comparison {
+                    comparison.tablature.set_scale_factor(scale_factor);
+                }
+                Task::none()
+            }
+            Message::TablatureResized(tablature_container_size) => {
+                if let Some(tablature) = &mut self.tablature {
+                    tablature.update_container_width(tablature_container_size.width);
+                    tablature.set_viewport_height(tablature_container_size.height);
+                }
+                Task::none()
+            }
+            Message::CompareResized(size) => {
+                if let Some(comparison) = &mut self.comparison {
+                    comparison.tablature.update_container_width(size.width);
+                    comparison.tablature.set_viewport_height(size.height);
+                }
+                Task::none()
+            }
+            Message::CompareScrolled(offset, max_offset) => {
+                if let Some(comparison) = &mut self.comparison {
+                    let is_playing = self
+                        .audio_player
+                        .as_ref()
+                        .is_some_and(AudioPlayer::is_playing);
+                    comparison
+                        .tablature
+                        .on_scrolled(offset, max_offset, is_playing);
+                }
+                Task::none()
+            }
+            Message::TablatureScrolled(offset, max_offset) => {
+                if let Some(tablature) = &mut self.tablature {
+                    let is_playing = self
+                        .audio_player
+                        .as_ref()
+                        .is_some_and(AudioPlayer::is_playing);
+                    tablature.on_scrolled(offset, max_offset, is_playing);
+                }
+                Task::none()
+            }
+            Message::ResumeFollowing => {
+                if let Some(tablature) = &mut self.tablature {
+                    tablature.resume_following();
+                }
+                Task::none()
+            }
+            Message::TempoSelected(tempos_selection) => {
+                let tempos_selection = tempos_selection.nudged(0);
+                if let Some(audio_player) = &self.audio_player {
+                    audio_player.set_tempo_percentage(tempos_selection.percentage);
+                }
+                if let Some(tablature) = &mut self.tablature {
+                    tablature.set_tempo_percentage(tempos_selection.percentage);
+                }
+                self.tempo_selection = tempos_selection;
+                self.save_file_session()
+            }
+            Message::IncreaseTempo => {
+                if self.tab_file_is_loading {
+                    return Task::none();
+                }
+                // next preset, from a tempo set with the slider as well
+                if let Some(next_tempo) = TempoSelection::PRESET
+                    .iter()
+                    .find(|t| t.percentage > self.tempo_selection.percentage)
+                {
+                    return Task::done(Message::TempoSelected(*next_tempo));
+                }
+                Task::none()
+            }
+            Message::DecreaseTempo => {
+                if self.tab_file_is_loading {
+                    return Task::none();
+                }
+                if let Some(previous_tempo) = TempoSelection::PRESET
+                    .iter()
+                    .rev()
+                    .find(|t| t.percentage < self.tempo_selection.percentage)
+                {
+                    return Task::done(Message::TempoSelected(*previous_tempo));
+                }
+                Task::none()
+            }
+            Message::TempoNudged(step) => {
+                if self.tab_file_is_loading {
+                    return Task::none();
+                }
+                Task::done(Message::TempoSelected(self.tempo_selection.nudged(step)))
+            }
+            Message::ToggleFullscreen => {
+                self.is_fullscreen = !self.is_fullscreen;
+                let mode = if self.is_fullscreen {
+                    window::Mode::Fullscreen
+                } else {
+                    window::Mode::Windowed
+                };
+                window::latest().and_then(move |id| window::set_mode(id, mode))
+            }
+            Message::MasterVolumeChanged(volume) => {
+                if let Some(audio_player) = &self.audio_player {
+                    audio_player.set_master_volume(volume);
+                }
+                Task::none()
+            }
+            Message::MasterVolumeReleased => {
+                let Some(audio_player) = &self.audio_player else {
+                    return Task::none();
+                };
+                if let Err(err) = self.config.set_master_volume(audio_player.master_volume()) {
+                    return Task::done(Message::ReportError(format!(
+                        "Failed to save master volume: {err}"
+                    )));
+                }
+                Task::none()
+            }
+            Message::RefreshOutputLevel => Task::none(),
+            Message::SearchInputChanged(input) => {
+                self.search_input = input;
+                Task::none()
+            }
+            Message::SearchSubmitted => {
+                let query = SearchQuery::parse(&self.search_input);
+                self.search_results = self
+                    .tablature
+                    .as_ref()
+                    .zip(query)
+                    .map(|(tablature, query)| search_song(&tablature.song, &query));
+                Task::none()
+            }
+            Message::SearchResultSelected(result) => {
+                // results on another track switch the tablature to it
+                if let Some(track_id) = result.track_id
+                    && track_id != self.track_selection.index
+                    && let Some(selection) = self.all_tracks.get(track_id).cloned()
+                {
+                    if let Some(tablature) = self.tablature.as_mut() {
+                        tablature.update_track(track_id);
+                    }
+                    self.previous_track = Some(self.track_selection.index);
+                    self.track_selection = selection;
+                }
+                self.focus_measure_with_scroll(result.measure_id)
+            }
+            Message::ClearSearch => {
+                self.search_input.clear();
+                self.search_results = None;
+                Task::none()
+            }
+            Message::OpenRemoteControl => {
+                self.remote_control_open = true;
+                Task::none()
+            }
+            Message::CloseRemoteControl => {
+                self.remote_control_open = false;
+                self.midi_learning = None;
+                Task::none()
+            }
+            Message::ToggleGlobalHotkeys(enabled) => {
+                // dropping the previous registration releases the keys
+                self.global_hotkeys = None;
+                if enabled {
+                    match GlobalHotkeys::register() {
+                        Ok(global_hotkeys) => self.global_hotkeys = Some(global_hotkeys),
+                        Err(err) => {
+                            return Task::done(Message::ReportError(format!(
+                                "Failed to register global hotkeys: {err}"
+                            )));
+                        }
+                    }
+                }
+                if let Err(err) = self.config.set_global_hotkeys(enabled) {
+                    return Task::done(Message::ReportError(format!(
+                        "Failed to save global hotkeys: {err}"
+                    )));
+                }
+                Task::none()
+            }
+            Message::MidiLearn(action) => {
+                self.midi_learning = Some(action);
+                Task::none()
+            }
+            Message::ClearMidiBinding(action) => {
+                let mut bindings = self.config.get_midi_bindings().to_vec();
+                bindings.retain(|b| b.action != action);
+                if let Err(err) = self.config.set_midi_bindings(bindings) {
+                    return Task::done(Message::ReportError(format!(
+                        "Failed to save MIDI bindings: {err}"
+                    )));
+                }
+                Task::none()
+            }
+            Message::MidiTriggered(trigger) => {
+                if let Some(action) = self.midi_learning.take() {
+                    let mut bindings = self.config.get_midi_bindings().to_vec();
+                    bind(&mut bindings, action, trigger);
+                    if let Err(err) = self.config.set_midi_bindings(bindings) {
+                        return Task::done(Message::ReportError(format!(
+                            "Failed to save MIDI bindings: {err}"
+                        )));
+                    }
+                    return Task::none();
+                }
+                action_for(self.config.get_midi_bindings(), trigger)
+                    .map_or_else(Task::none, |action| Task::done(action.message()))
+            }
+            Message::OpenDiagnostics => {
+                self.diagnostics_open = true;
+                Task::none()
+            }
+            Message::CloseDiagnostics => {
+                self.diagnostics_open = false;
+                Task::none()
+            }
+            Message::CopyDiagnostics => iced::clipboard::write(self.diagnostics().report()),
+            Message::AudioBackendSelected(selection) => {
+                let backend = selection.into_inner();
+                if let Some(audio_player) = &mut self.audio_player {
+                    audio_player.set_audio_backend(backend.clone());
+                }
+                if let Err(err) = self.config.set_audio_backend(backend) {
+                    return Task::done(Message::ReportError(format!(
+                        "Failed to save audio backend: {err}"
+                    )));
+                }
+                Task::none()
+            }
+            Message::ToggleMonoOutput(mono) => {
+                if let Some(audio_player) = &self.audio_player {
+                    audio_player.set_mono_output(mono);
+                }
+                if let Err(err) = self.config.set_mono_output(mono) {
+                    return Task::done(Message::ReportError(format!(
+                        "Failed to save mono output: {err}"
+                    )));
+                }
+                Task::none()
+            }
+            Message::OpenPresets => {
+                self.presets_open = true;
+                Task::none()
+            }
+            Message::ClosePresets => {
+                self.presets_open = false;
+                Task::none()
+            }
+            Message::PresetRemapped(program, choice) => {
+                let sound_font = self.sound_font_key();
+                let mut remaps = self.config.get_preset_remaps(&sound_font);
+                remaps.set(program, choice.preset_id());
+                if let Err(err) = self.config.set_preset_remaps(&sound_font, remaps) {
+                    return Task::done(Message::ReportError(format!(
+                        "Failed to save preset remapping: {err}"
+                    )));
+                }
+                if let Err(err) = self.rebuild_audio_player() {
+                    return Task::done(Message::ReportError(format!(
+                        "Failed to initialize audio: {err}"
+                    )));
+                }
+                Task::none()
+            }
+            Message::AddSoundFont => Task::perform(
+                pick_sound_font_dialog(self.config.get_sound_fonts_dir()),
+                Message::SoundFontAdded,
+            ),
+            Message::SoundFontAdded(result) => match result {
+                Ok(path) => {
+                    if let Err(err) = self.config.add_sound_font(path) {
+                        return Task::done(Message::ReportError(format!(
+                            "Failed to save sound font: {err}"
+                        )));
+                    }
+                    Task::none()
+                }
+                Err(FilePickerError::DialogClosed) => Task::none(),
+                Err(err) => Task::done(Message::ReportError(format!(
+                    "Failed to pick sound font: {err}"
+                ))),
+            },
+            Message::TrackSoundFontSelected(index, choice) => {
+                let Some(song_info) = &self.song_info else {
+                    return Task::none();
+                };
+                let file_key = song_info.file_key.clone();
+                let mut assignment = self.config.get_track_sound_fonts(&file_key);
+                match choice.into_path() {
+                    Some(path) => assignment.insert(index, path),
+                    None => assignment.remove(&index),
+                };
+                if let Err(err) = self.config.set_track_sound_fonts(&file_key, assignment) {
+                    return Task::done(Message::ReportError(format!(
+                        "Failed to save track sound font: {err}"
+                    )));
+                }
+                if let Err(err) = self.rebuild_audio_player() {
+                    return Task::done(Message::ReportError(format!(
+                        "Failed to initialize audio: {err}"
+                    )));
+                }
+                Task::none()
+            }
+            Message::OpenExport => {
+                self.export_open = true;
+                Task::none()
+            }
+            Message::CloseExport => {
+                self.export_open = false;
+                Task::none()
+            }
+            Message::ToggleExpandRepeats(expand_repeats) => {
+                self.export_options.expand_repeats = expand_repeats;
+                Task::none()
+            }
+            Message::ToggleBeatTextCues(beat_text_cues) => {
+                self.export_options.beat_text_cues = beat_text_cues;
+                Task::none()
+            }
+            Message::ToggleStems(stems) => {
+                self.export_options.stems = stems;
+                Task::none()
+            }
+            Message::ExportMidi => {
+                let (Some(tablature), Some(song_info)) = (&self.tablature, &self.song_info) else {
+                    return Task::none();
+                };
+                self.export_open = false;
+                let song = tablature.song.clone();
+                let vibrato = self.config.get_vibrato();
+                let options = self.export_options;
+                let picker_folder = self.config.get_tabs_folder();
+                if options.stems {
+                    let song_file_name = song_info.file_name.clone();
+                    return Task::perform(
+                        async move {
+                            let stems = export_stems(&song, vibrato, options)
+                                .into_iter()
+                                .zip(&song.tracks)
+                                .enumerate()
+                                .map(|(index, (content, track))| {
+                                    (stem_file_name(&song_file_name, index, &track.name), content)
+                                })
+                                .collect();
+                            save_stems_dialog(stems, picker_folder).await
+                        },
+                        Message::MidiExported,
+                    );
+                }
+                let file_name = PathBuf::from(&song_info.file_name)
+                    .with_extension("mid")
+                    .to_string_lossy()
+                    .to_string();
+                // build the events off the UI thread
+                Task::perform(
+                    async move {
+                        let content = export_smf(&song, vibrato, options);
+                        save_midi_dialog(content, file_name, picker_folder).await
+                    },
+                    Message::MidiExported,
+                )
+            }
+            Message::MidiExported(result) => match result {
+                Ok(path) => {
+                    log::info!("MIDI file exported to {}", path.display());
+                    Task::none()
+                }
+                Err(FilePickerError::DialogClosed) => Task::none(),
+                Err(err) => Task::done(Message::ReportError(format!(
+                    "Failed to export MIDI file: {err}"
+                ))),
+            },
+            Message::ExportNotation(format, all_tracks) => {
+                let (Some(tablature), Some(song_info)) = (&self.tablature, &self.song_info) else {
+                    return Task::none();
+                };
+                self.export_open = false;
+                let track_id = (!all_tracks).then_some(tablature.track_id);
+                let content = match format.export(&tablature.song, track_id) {
+                    Ok(content) => content,
+                    Err(err) => {
+                        return Task::done(Message::ReportError(format!(
+                            "Failed to export notation: {err}"
+                        )));
+                    }
+                };
+                let file_name = PathBuf::from(&song_info.file_name)
+                    .with_extension(format.extension())
+                    .to_string_lossy()
+                    .to_string();
+                Task::perform(
+                    save_notation_dialog(
+                        content,
+                        file_name,
+                        format.name(),
+                        self.config.get_tabs_folder(),
+                    ),
+                    Message::NotationExported,
+                )
+            }
+            Message::NotationExported(result) => match result {
+                Ok(path) => {
+                    log::info!("Notation exported to {}", path.display());
+                    Task::none()
+                }
+                Err(FilePickerError::DialogClosed) => Task::none(),
+                Err(err) => Task::done(Message::ReportError(format!(
+                    "Failed to export notation: {err}"
+                ))),
+            },
+            Message::CopyMeasureImage => {
+                let Some(tablature) = &self.tablature else {
+                    return Task::none();
+                };
+                self.export_open = false;
+                let measure = tablature.focused_measure();
+                Task::perform(
+                    copy_measure_image(tablature.focused_measure_copy()),
+                    move |result| Message::MeasureImageCopied(measure, result),
+                )
+            }
+            Message::MeasureImageCopied(measure, result) => match result {
+                Ok(()) => {
+                    log::info!("Measure {} copied as PNG", measure + 1);
+                    Task::none()
+                }
+                Err(err) => Task::done(Message::ReportError(format!(
+                    "Failed to copy the measure image: {err}"
+                ))),
+            },
+            Message::SaveRowImage => {
+                let (Some(tablature), Some(song_info)) = (&mut self.tablature, &self.song_info)
+                else {
+                    return Task::none();
+                };
+                self.export_open = false;
+                let png = match tablature.focused_row_png() {
+                    Ok(png) => png,
+                    Err(err) => {
+                        return Task::done(Message::ReportError(format!(
+                            "Failed to render the row image: {err}"
+                        )));
+                    }
+                };
+                let file_name = PathBuf::from(&song_info.file_name)
+                    .with_extension("png")
+                    .to_string_lossy()
+                    .to_string();
+                Task::perform(
+                    save_image_dialog(png, file_name, self.config.get_tabs_folder()),
+                    Message::RowImageSaved,
+                )
+            }
+            Message::RowImageSaved(result) => match result {
+                Ok(path) => {
+                    log::info!("Row image saved to {}", path.display());
+                    Task::none()
+                }
+                Err(FilePickerError::DialogClosed) => Task::none(),
+                Err(err) => Task::done(Message::ReportError(format!(
+                    "Failed to save the row image: {err}"
+                ))),
+            },
+            Message::SaveSong => self.save_song(self.saved_path.clone()),
+            Message::SaveSongAs => self.save_song(None),
+            Message::SongSaved(song, result) => match result {
+                Ok(path) => {
+                    log::info!("Song saved to {}", path.display());
+                    if let Some(document) = &mut self.document {
+                        document.mark_saved(&song);
+                    }
+                    if let Some(song_info) = &mut self.song_info
+                        && let Some(file_name) = path.file_name()
+                    {
+                        song_info.file_name = file_name.to_string_lossy().to_string();
+                        song_info.file_key = file_key(Some(&path), &[]);
+                    }
+                    // the write notified for the save may have been handled first
+                    self.watched_file = Some(WatchedFile::new(path.clone()));
+                    self.file_changed_on_disk = false;
+                    self.saved_path = Some(path);
+                    Task::none()
+                }
+                Err(FilePickerError::DialogClosed) => Task::none(),
+                Err(err) => Task::done(Message::ReportError(format!("Failed to save song: {err}"))),
+            },
+            Message::OpenTrackManager => {
+                self.track_manager_open = true;
+                Task::none()
+            }
+            Message::CloseTrackManager => {
+                self.track_manager_open = false;
+                Task::none()
+            }
+            Message::MoveTrack(index, up) => {
+                if self.track_layout.move_track(index, up) {
+                    self.save_track_layout()
+                } else {
+                    Task::none()
+                }
+            }
+            Message::ToggleTrackHidden(index, hidden) => {
+                if self.track_layout.set_hidden(index, hidden) {
+                    self.save_track_layout()
+                } else {
+                    Task::none()
+                }
+            }
+            Message::ToggleIssues => {
+                self.issues_open = !self.issues_open;
+                Task::none()
+            }
+            Message::FileChangedOnDisk => {
+                self.file_changed_on_disk |= self
+                    .watched_file
+                    .as_ref()
+                    .is_some_and(WatchedFile::changed_on_disk);
+                Task::none()
+            }
+            Message::ReloadFile => {
+                let Some(watched_file) = &self.watched_file else {
+                    return Task::none();
+                };
+                if self.tab_file_is_loading {
+                    return Task::none();
+                }
+                self.tab_file_is_loading = true;
+                self.file_changed_on_disk = false;
+                let tick = self.current_tick.load(Ordering::Relaxed);
+                self.reload_position = Some((self.track_selection.index, tick));
+                Task::perform(
+                    load_file(watched_file.path().to_path_buf()),
+                    Message::FileOpened,
+                )
+            }
+            Message::DismissReload => {
+                self.file_changed_on_disk = false;
+                if let Some(watched_file) = &mut self.watched_file {
+                    watched_file.acknowledge();
+                }
+                Task::none()
+            }
+            Message::ToggleSongInfo => {
+                self.song_info_open = !self.song_info_open;
+                Task::none()
+            }
+            Message::InstanceRequest(tab_file) => {
+                // bring the window up for the file opened from the file manager
+                let focus = window::latest().and_then(window::gain_focus);
+                Task::batch([self.open_tab_file(tab_file), focus])
+            }
+            Message::OpenCompareDialog => Task::perform(
+                open_file_dialog(self.config.get_tabs_folder()),
+                Message::CompareOpened,
+            ),
+            Message::CompareOpened(result) => match result {
+                Ok((contents, _, file_name)) => match parse_gp_data(&contents) {
+                    Ok(song) if !song.tracks.is_empty() => {
+                        let mut comparison =
+                            Comparison::new(song, file_name, self.track_selection.index);
+                        comparison
+                            .tablature
+                            .set_show_dynamics(!self.config.get_hide_dynamics());
+                        comparison
+                            .tablature
+                            .set_show_directions(!self.config.get_hide_directions());
+                        comparison
+                            .tablature
+                            .set_measures_per_row(self.config.get_measures_per_row());
+                        comparison
+                            .tablature
+                            .set_low_power(self.config.get_low_power());
+                        comparison
+                            .tablature
+                            .set_note_names(self.config.get_note_names());
+                        self.comparison = Some(comparison);
+                        // lay out the split view before focusing the measure
+                        Task::done(Message::WindowResized).chain(Task::done(Message::FocusMeasure(
+                            self.tablature
+                                .as_ref()
+                                .map_or(0, Tablature::focused_measure),
+                        )))
+                    }
+                    Ok(_) => Task::done(Message::ReportError(
+                        "No tracks found in GP file".to_string(),
+                    )),
+                    Err(err) => Task::done(Message::ReportError(format!(
+                        "Failed to parse compared file: {err}"
+                    ))),
+                },
+                Err(FilePickerError::DialogClosed) => Task::none(),
+                Err(err) => Task::done(Message::ReportError(format!(
+                    "Failed to open compared file: {err}"
+                ))),
+            },
+            Message::CloseCompare => {
+                self.comparison = None;
+                Task::done(Message::WindowResized)
+            }
+            Message::OpenLibrary => {
+                self.library_files = self.config.get_tabs_folder().map(|f| library_files(&f));
+                Task::none()
+            }
+            Message::CloseLibrary => {
+                self.library_files = None;
+                self.library_preview = None;
+                self.library_hovered = None;
+                Task::none()
+            }
+            Message::PreviewLibraryFile(path) => {
+                self.library_hovered = Some(path.clone());
+                if self
+                    .library_preview
+                    .as_ref()
+                    .is_some_and(|p| p.path() == path)
+                {
+                    return Task::none();
+                }
+                Task::perform(
+                    async move {
+                        let song = read_preview(&path);
+                        (path, song)
+                    },
+                    |(path, song)| Message::LibraryPreviewLoaded(path, song),
+                )
+            }
+            Message::LibraryPreviewLoaded(path, song) => {
+                // the panel may have been closed or another file hovered while parsing
+                if self.library_files.is_some() && self.library_hovered.as_ref() == Some(&path) {
+                    self.library_preview = Some(LibraryPreview::new(path, song));
+                }
+                Task::none()
+            }
+            Message::ClearError => {
+                self.error_message = None;
+                Task::none()
+            }
+            Message::ReportError(error) => {
+                log::warn!("{error}");
+                if self.recent_errors.len() == RECENT_ERRORS {
+                    self.recent_errors.remove(0);
+                }
+                self.recent_errors.push(error.clone());
+                self.error_message = Some(error);
+                Task::none()
+            }
+        }
+    }
+
+    /// Save the song to the path, or to a path picked in a dialog if `None`.
+    fn save_song(&self, path: Option<PathBuf>) -> Task<Message> {
+        let (Some(document), Some(song_info)) = (&self.document, &self.song_info) else {
+            return Task::none();
+        };
+        let song = document.song().clone();
+        let picker_folder = self.config.get_tabs_folder();
+        let file_name = PathBuf::from(&song_info.file_name)
+            .with_extension(RUXG_EXTENSION)
+            .to_string_lossy()
+            .to_string();
+        let saved = song.clone();
+        Task::perform(
+            async move {
+                let content =
+                    write_ruxg(&song).map_err(|err| FilePickerError::IoError(err.to_string()))?;
+                match path {
+                    Some(path) => save_file(path, content).await,
+                    None => save_song_dialog(content, file_name, picker_folder).await,
+                }
+            },
+            move |result| Message::SongSaved(saved, result),
+        )
+    }
+
+    /// Apply the edit to the document, undoable with Ctrl+Z.
+    fn apply_edit(&mut self, edit: Edit, amend: bool) -> Task<Message> {
+        let Some(document) = &mut self.document else {
+            return Task::none();
+        };
+        if document.apply(edit, amend) {
+            self.show_edited_song()
+        } else {
+            Task::none()
+        }
+    }
+
+    /// Display and play the edited song, the player is rebuilt where it was.
+    fn show_edited_song(&mut self) -> Task<Message> {
+        let (Some(document), Some(tablature)) = (&self.document, &mut self.tablature) else {
+            return Task::none();
+        };
+        tablature.set_song(document.song().clone());
+        let focused = tablature.focused_measure();
+        match self.rebuild_audio_player() {
+            Ok(()) => {
+                if let Some(audio_player) = &self.audio_player {
+                    audio_player.focus_measure(focused);
+                }
+                Task::none()
+            }
+            Err(err) => Task::done(Message::ReportError(format!(
+                "Failed to initialize audio: {err}"
+            ))),
+        }
+    }
+
+    /// Key of the loaded sound font in the configuration.
+    fn sound_font_key(&self) -> String {
+        self.sound_font_file
+            .as_ref()
+            .map_or_else(|| "embedded".to_string(), |path| path.display().to_string())
+    }
+
+    /// Name and program of the melodic tracks, percussion kits are not remapped.
+    fn track_programs(&self) -> Vec<(String, u8)> {
+        let Some(tablature) = &self.tablature else {
+            return Vec::new();
+        };
+        let song = &tablature.song;
+        song.tracks
+            .iter()
+            .filter_map(|track| {
+                let channel = song
+                    .midi_channels
+                    .iter()
+                    .find(|c| c.channel_id == track.channel_id)
+                    .filter(|c| !c.is_percussion())?;
+                let program = u8::try_from(channel.instrument).ok()?;
+                Some((track.name.clone(), program))
+            })
+            .collect()
+    }
+
+    /// Sound fonts assigned to the tracks of the displayed file.
+    fn track_sound_fonts(&self) -> BTreeMap<usize, PathBuf> {
+        self.song_info
+            .as_ref()
+            .map(|song_info| self.config.get_track_sound_fonts(&song_info.file_key))
+            .unwrap_or_default()
+    }
+
+    /// Audio and file details shown in the diagnostics panel.
+    fn diagnostics(&self) -> Diagnostics<'_> {
+        let audio_player = self.audio_player.as_ref();
+        Diagnostics {
+            backend: AudioBackendSelection::new(self.config.get_audio_backend()),
+            stream_info: audio_player.and_then(AudioPlayer::stream_info),
+            mono_output: self.config.get_mono_output(),
+            sound_font: self.sound_font_file.as_deref(),
+            preset_count: audio_player.map(AudioPlayer::preset_count),
+            file: self
+                .song_info
+                .as_ref()
+                .map(|song| format!("{} ({:?})", song.file_name, song.gp_version)),
+            recent_errors: &self.recent_errors,
+        }
+    }
+
+    /// Replace the audio player by one built from the displayed song,
+    /// keeping the volume and the solo or backing track of the previous one.
+    fn rebuild_audio_player(&mut self) -> Result<(), AudioPlayerError> {
+        let Some(tablature) = &self.tablature else {
+            return Ok(());
+        };
+        let song = tablature.song.clone();
+        let playback_order = compute_playback_order(&song.measure_headers);
+        let previous = self.audio_player.take();
+        let audio_player = self.new_audio_player(song, &playback_order)?;
+        if let Some(previous) = previous {
+            audio_player.set_master_volume(previous.master_volume());
+            audio_player.set_track_filter(previous.track_filter());
+        }
+        self.audio_player = Some(audio_player);
+        Ok(())
+    }
+
+    /// Create an audio player for the song with the current playback settings.
+    fn new_audio_player(
+        &self,
+        song: Arc<Song>,
+        playback_order: &[(usize, i64)],
+    ) -> Result<AudioPlayer, AudioPlayerError> {
+        let song_tempo = song.tempo.value;
+        let mut audio_player = AudioPlayer::new(
+            song,
+            song_tempo,
+            self.tempo_selection.percentage,
+            &SoundFontAssignment {
+                default: self.sound_font_file.clone(),
+                tracks: self.track_sound_fonts(),
+            },
+            self.current_tick.clone(),
+            self.beat_notify.clone(),
+            playback_order,
+            self.config.get_vibrato(),
+            self.play_muted_tracks,
+            self.config.get_preset_remaps(&self.sound_font_key()),
+        )?;
+        audio_player.set_practice_mode(self.practice_mode);
+        audio_player.set_master_volume(self.config.get_master_volume());
+        audio_player.set_mono_output(self.config.get_mono_output());
+        audio_player.set_audio_backend(self.config.get_audio_backend());
+        audio_player.set_section_end(self.config.get_section_end());
+        audio_player.set_metronome(self.config.get_metronome());
+        audio_player.set_section(self.tablature.as_ref().and_then(Tablature::section));
+        Ok(audio_player)
+    }
+
+    /// Offer to load the version of the file written by another application.
+    fn reload_banner(&self) -> Element<'_, Message> {
+        let dirty = self.document.as_ref().is_some_and(Document::is_dirty);
+        let message = if dirty {
+            "The file changed on disk, reloading discards the unsaved edits"
+        } else {
+            "The file changed on disk"
+        };
+        let reload = button(text("Reload").size(12)).on_press(Message::ReloadFile);
+        let dismiss = button(text("Dismiss").size(12))
+            .style(button::secondary)
+            .on_press(Message::DismissReload);
+        container(
+            row![text(message).size(14), reload, dismiss]
+                .spacing(10)
+                .align_y(Alignment::Center),
+        )
+        .padding(10)
+        .width(Length::Fill)
+        .style(|_theme| container::Style {
+            border: Border::default()
+                .color(crate::ui::utils::COLOR_GRAY)
+                .width(1),
+            ..Default::default()
+        })
+        .into()
+    }
+
+    fn view(&self) -> Element<'_, Message> {
+        let open_file = action_gated(
+            open_icon(),
+            "Open file (Ctrl+O)",
+            (!self.tab_file_is_loading).then_some(Message::OpenFileDialog),
+        );
+
+        let player_control = if let Some(audio_player) = &self.audio_player {
+            let waiting = audio_player.is_playing() && audio_player.is_waiting_at_measure_end();
+            let (icon, message) = if waiting && audio_player.is_at_section_end() {
+                (play_icon(), "Replay section (Space)")
+            } else if waiting {
+                (play_icon(), "Next measure (Space)")
+            } else if audio_player.is_playing() {
+                (pause_icon(), "Pause (Space)")
+            } else {
+                (play_icon(), "Play (Space)")
+            };
+            let play_button = action_gated(icon, message, Some(Message::PlayPause));
+            let stop_button = action_gated(stop_icon(), "Stop (Home)", Some(Message::StopPlayer));
+            let counter = self
+                .tablature
+                .as_ref()
+                .map(|tab| {
+                    let focused = tab.focused_measure();
+                    let total_measures = tab.measure_count();
+                    let current_tick = self.current_tick.load(Ordering::Relaxed);
+                    let current_seconds = tab.elapsed_seconds(current_tick);
+                    let total_seconds = tab.duration_seconds();
+                    format!(
+                        "Measure {}/{} \u{2022} {}/{}",
+                        focused + 1,
+                        total_measures,
+                        format_mmss(current_seconds),
+                        format_mmss(total_seconds),
+                    )
+                })
+                .unwrap_or_default();
+            // offer to follow the playback again after a manual scroll
+            let follow_mode = self.tablature.as_ref().map(Tablature::follow_mode);
+            let resume_following: Element<Message> = match follow_mode {
+                Some(FollowMode::Paused) if audio_player.is_playing() => {
+                    button(text("Resume following").size(14))
+                        .on_press(Message::ResumeFollowing)
+                        .into()
+                }
+                Some(FollowMode::ResumeAtNextMeasure) if audio_player.is_playing() => {
+                    button(text("Resume following").size(14))
+                        .style(button::secondary)
+                        .into()
+                }
+                _ => horizontal().width(0).into(),
+            };
+            let practice = checkbox(self.practice_mode)
+                .label("Bar by bar")
+                .text_size(14)
+                .on_toggle(|_| Message::TogglePracticeMode);
+            // section selected with shift-click
+            let section = self.tablature.as_ref().and_then(Tablature::section);
+            let clear_section: Element<Message> = if let Some((first, last)) = section {
+                button(text(format!("Clear section {}-{}", first + 1, last + 1)).size(14))
+                    .style(button::secondary)
+                    .on_press(Message::ClearSection)
+                    .into()
+            } else {
+                horizontal().width(0).into()
+            };
+            row![
+                play_button,
+                stop_button,
+                practice,
+                clear_section,
+                text(counter).size(14),
+                resume_following
+            ]
+            .spacing(10)
+            .align_y(Alignment::Center)
+        } else {
+            row![horizontal()]
+        };
+
+        let track_control = if self.all_tracks.is_empty() {
+            row![horizontal()]
+        } else {
+            let tempo_label = text("Tempo").size(14);
+            let tempo_percentage = pick_list(
                 TempoSelection::PRESET,
                 Some(&self.tempo_selection),
                 Message::TempoSelected,
             )
             .text_size(14)
             .padding([5, 10]);
+            let tempo_slider = slider(
+                TempoSelection::MIN..=TempoSelection::MAX,
+                self.tempo_selection.percentage,
+                |percentage| Message::TempoSelected(TempoSelection::new(percentage)),
+            )
+            .step(1_u32)
+            .width(100);
+
+            let pre_roll_label = text("Pre-roll").size(14);
+            let pre_roll = pick_list(
+                PreRollSelection::PRESET,
+                Some(PreRollSelection::new(self.config.get_pre_roll_measures())),
+                Message::PreRollSelected,
+            )
+            .text_size(14)
+            .padding([5, 10]);
+
+            // looping only matters once a section is selected
+            let section_end: Element<Message> = if self
+                .tablature
+                .as_ref()
+                .and_then(Tablature::section)
+                .is_some()
+            {
+                pick_list(
+                    SectionEnd::ALL,
+                    Some(self.config.get_section_end()),
+                    Message::SectionEndSelected,
+                )
+                .text_size(14)
+                .padding([5, 10])
+                .into()
+            } else {
+                horizontal().width(0).into()
+            };
+
+            let metronome = button(text("Metronome").size(14))
+                .style(if self.config.get_metronome().enabled {
+                    button::primary
+                } else {
+                    button::secondary
+                })
+                .on_press(Message::ToggleMetronomePanel);
 
+            let track_filter = self
+                .audio_player
+                .as_ref()
+                .map(AudioPlayer::track_filter)
+                .unwrap_or_default();
             let solo_mode = action_toggle(
                 solo_icon(),
-                "Solo",
+                "Solo (S)",
                 Message::ToggleSolo,
-                self.audio_player
-                    .as_ref()
-                    .is_some_and(|p| p.solo_track_id().is_some()),
+                matches!(track_filter, TrackFilter::Solo(_)),
+            );
+            let backing_mode = action_toggle(
+                backing_icon(),
+                "Play without the selected track (B)",
+                Message::ToggleBacking,
+                matches!(track_filter, TrackFilter::Backing(_)),
             );
 
+            // selected track accent, also used by the tablature
+            let track_color = self.tablature.as_ref().map_or(COLOR_GRAY, |t| {
+                let index = self.track_selection.index;
+                track_color(t.song.tracks[index].color, index)
+            });
+            let track_swatch =
+                container(horizontal().width(0))
+                    .width(12)
+                    .height(12)
+                    .style(move |_theme| container::Style {
+                        background: Some(track_color.into()),
+                        border: Border::default().rounded(2),
+                        ..Default::default()
+                    });
             let track_pick_list = pick_list(
-                self.all_tracks.as_slice(),
+                self.visible_tracks(),
                 Some(&self.track_selection),
                 Message::TrackSelected,
             )
             .text_size(14)
-            .padding([5, 10]);
+            .padding([5, 10])
+            .style(move |theme, status| {
+                let mut style = pick_list::default(theme, status);
+                style.border = style.border.color(track_color);
+                style
+            });
+
+            // reorder and hide tracks
+            let manage_tracks: Element<Message> = if self.all_tracks.len() > 1 {
+                button(text("Tracks").size(14))
+                    .style(button::secondary)
+                    .on_press(Message::OpenTrackManager)
+                    .into()
+            } else {
+                horizontal().width(0).into()
+            };
 
             let volume_label = text("Volume").size(14);
             let current_volume = self
                 .audio_player
                 .as_ref()
-                .map_or(1.0, AudioPlayer::master_volume);
-            let volume_slider = slider(0.0..=1.0, current_volume, Message::MasterVolumeChanged)
-                .step(0.01_f32)
-                .width(100);
+                .map_or(self.config.get_master_volume(), AudioPlayer::master_volume);
+            let volume_slider = slider(
+                0.0..=MAX_MASTER_VOLUME,
+                current_volume,
+                Message::MasterVolumeChanged,
+            )
+            .on_release(Message::MasterVolumeReleased)
+            .step(0.01_f32)
+            .width(100);
+            // output level while playing, to tell a silent sound font from a muted channel
+            let output_level: Element<Message> = match &self.audio_player {
+                Some(audio_player) if audio_player.is_playing() => {
+                    level_meter(audio_player.output_levels())
+                }
+                _ => horizontal().width(0).into(),
+            };
+
+            // override only offered when the file silences some tracks
+            let play_muted: Element<Message> = if self.all_tracks.iter().any(|t| t.silenced) {
+                checkbox(self.play_muted_tracks)
+                    .label("Play muted")
+                    .text_size(14)
+                    .on_toggle(Message::TogglePlayMutedTracks)
+                    .into()
+            } else {
+                horizontal().width(0).into()
+            };
+
+            // tablature density, dense songs no longer cram a dozen measures in a row
+            let measures_per_row = pick_list(
+                MeasuresPerRowSelection::PRESET,
+                Some(MeasuresPerRowSelection::new(
+                    self.config.get_measures_per_row(),
+                )),
+                Message::MeasuresPerRowSelected,
+            )
+            .text_size(14)
+            .padding([5, 10]);
+
+            let dynamics = checkbox(!self.config.get_hide_dynamics())
+                .label("Dynamics")
+                .text_size(14)
+                .on_toggle(Message::ToggleDynamics);
+
+            let directions = checkbox(!self.config.get_hide_directions())
+                .label("Directions")
+                .text_size(14)
+                .on_toggle(Message::ToggleDirections);
+
+            let chord_suggestions = checkbox(self.config.get_chord_suggestions())
+                .label("Chord names")
+                .text_size(14)
+                .on_toggle(Message::ToggleChordSuggestions);
+
+            // key of the focused section in the label, notes outside its scale highlighted
+            let scale_label = self
+                .tablature
+                .as_ref()
+                .and_then(Tablature::focused_key)
+                .map_or_else(|| "Scale".to_string(), |key| format!("Scale ({key})"));
+            let scale_highlight = checkbox(self.config.get_scale_highlight())
+                .label(scale_label)
+                .text_size(14)
+                .on_toggle(Message::ToggleScaleHighlight);
+            // pitches like "G#4" instead of the frets, for reading along other instruments
+            let note_names = checkbox(self.config.get_note_names())
+                .label("Note names")
+                .text_size(14)
+                .on_toggle(Message::ToggleNoteNames);
+
+            let difficulty = checkbox(self.config.get_difficulty_heatmap())
+                .label("Difficulty")
+                .text_size(14)
+                .on_toggle(Message::ToggleDifficulty);
+
+            // low power mode never animates the scroll
+            let low_power = self.config.get_low_power();
+            let smooth_scroll = checkbox(!self.config.get_instant_scroll() && !low_power)
+                .label("Smooth scroll")
+                .text_size(14)
+                .on_toggle_maybe((!low_power).then_some(Message::ToggleSmoothScroll));
+
+            let low_power = checkbox(low_power)
+                .label("Low power")
+                .text_size(14)
+                .on_toggle(Message::ToggleLowPower);
+
+            let fretboard = checkbox(self.config.get_show_fretboard())
+                .label("Fretboard")
+                .text_size(14)
+                .on_toggle(Message::ToggleFretboard);
+
+            // note entry: click a string slot and type the fret, undo with Ctrl+Z
+            let editing = self.tablature.as_ref().is_some_and(Tablature::editing);
+            let edit_notes = checkbox(editing)
+                .label("Edit notes")
+                .text_size(14)
+                .on_toggle(Message::ToggleEditing);
+            // any edit can be undone, including the ones from the note menu
+            let undo_label = self.document.as_ref().and_then(Document::undo_label);
+            let redo_label = self.document.as_ref().and_then(Document::redo_label);
+            let history: Element<Message> =
+                if undo_label.is_some() || redo_label.is_some() {
+                    row![
+                        button(
+                            text(undo_label.map_or_else(
+                                || "Undo".to_string(),
+                                |label| format!("Undo {label}")
+                            ))
+                            .size(14)
+                        )
+                        .style(button::secondary)
+                        .on_press_maybe(undo_label.map(|_| Message::Undo)),
+                        button(
+                            text(redo_label.map_or_else(
+                                || "Redo".to_string(),
+                                |label| format!("Redo {label}")
+                            ))
+                            .size(14)
+                        )
+                        .style(button::secondary)
+                        .on_press_maybe(redo_label.map(|_| Message::Redo)),
+                    ]
+                    .spacing(5)
+                    .into()
+                } else {
+                    horizontal().width(0).into()
+                };
 
             row![
                 tempo_label,
                 tempo_percentage,
+                tempo_slider,
                 volume_label,
                 volume_slider,
+                output_level,
+                pre_roll_label,
+                pre_roll,
+                section_end,
+                metronome,
                 solo_mode,
+                backing_mode,
+                play_muted,
+                measures_per_row,
+                dynamics,
+                directions,
+                chord_suggestions,
+                scale_highlight,
+                note_names,
+                difficulty,
+                smooth_scroll,
+                low_power,
+                fretboard,
+                edit_notes,
+                history,
+                track_swatch,
+                family_icon(self.track_selection.family()),
                 track_pick_list,
+                manage_tracks,
             ]
             .spacing(10)
             .align_y(Alignment::Center)
         };
 
+        let library = button(text("Library").size(14))
+            .style(button::secondary)
+            .on_press_maybe(
+                self.config
+                    .get_tabs_folder()
+                    .is_some()
+                    .then_some(Message::OpenLibrary),
+            );
+
+        let compare = button(text("Compare").size(14))
+            .style(button::secondary)
+            .on_press_maybe(
+                self.tablature
+                    .is_some()
+                    .then_some(Message::OpenCompareDialog),
+            );
+
+        let remote_control = button(text("Remote").size(14))
+            .style(button::secondary)
+            .on_press(Message::OpenRemoteControl);
+
+        let diagnostics = button(text("Diagnostics").size(14))
+            .style(button::secondary)
+            .on_press(Message::OpenDiagnostics);
+
+        let presets = button(text("Presets").size(14))
+            .style(button::secondary)
+            .on_press_maybe(self.audio_player.is_some().then_some(Message::OpenPresets));
+
+        let export = button(text("Export").size(14))
+            .style(button::secondary)
+            .on_press_maybe(self.tablature.is_some().then_some(Message::OpenExport));
+
+        let save = button(text("Save").size(14))
+            .style(button::secondary)
+            .on_press_maybe(self.document.is_some().then_some(Message::SaveSong));
+
+        let search_input = if self.all_tracks.is_empty() {
+            row![horizontal()]
+        } else {
+            let input = text_input("Search string/fret, chord or marker", &self.search_input)
+                .on_input(Message::SearchInputChanged)
+                .on_submit(Message::SearchSubmitted)
+                .size(14)
+                .padding([5, 10])
+                .width(260);
+            row![input]
+        };
+
         let controls = row![
             open_file,
+            library,
+            compare,
+            remote_control,
+            diagnostics,
+            presets,
+            export,
+            save,
+            search_input,
             horizontal(),
             player_control,
             horizontal(),
@@ -645,13 +2689,31 @@ impl RuxApplication {
             .and_then(SongDisplayInfo::metadata_line)
             .unwrap_or_default();
 
+        // tuning of the selected track at a glance, not for percussion tracks
+        let tuning = self
+            .tablature
+            .as_ref()
+            .filter(|_| self.track_selection.tuning.is_some())
+            .and_then(|tab| tuning_summary(&tab.song.tracks[self.track_selection.index].strings))
+            .map(|summary| format!("{summary} \u{2022} "))
+            .unwrap_or_default();
         let gp_version = if let Some(song) = &self.song_info {
-            format!("{:?}", song.gp_version)
+            format!("{tuning}{:?}", song.gp_version)
         } else {
             String::new()
         };
+        // all the metadata on demand, the status bar only fits a line
+        let song_info = row![Text::new(song_info).shaping(Auto)]
+            .push(self.song_info.is_some().then(|| {
+                button(text("Info").size(12))
+                    .style(button::secondary)
+                    .padding([2, 8])
+                    .on_press(Message::ToggleSongInfo)
+            }))
+            .spacing(10)
+            .align_y(Alignment::Center);
         let status = row![
-            container(Text::new(song_info).shaping(Auto))
+            container(song_info)
                 .width(Length::FillPortion(1))
                 .align_x(Alignment::Start),
             container(Text::new(metadata_line).shaping(Auto))
@@ -661,7 +2723,23 @@ impl RuxApplication {
                 .width(Length::FillPortion(1))
                 .align_x(Alignment::End),
         ]
-        .spacing(10);
+        .spacing(10)
+        .align_y(Alignment::Center);
+
+        // parsing warnings of the loaded file
+        let warnings = self
+            .tablature
+            .as_ref()
+            .map_or(&[][..], |t| t.song.warnings.as_slice());
+        let status = if warnings.is_empty() {
+            status
+        } else {
+            let issues = button(text(issues_label(warnings)).size(12))
+                .style(button::secondary)
+                .padding([2, 8])
+                .on_press(Message::ToggleIssues);
+            status.push(issues)
+        };
 
         let status = container(status).padding(4);
 
@@ -671,14 +2749,151 @@ impl RuxApplication {
             .map_or_else(|| untitled_text_table_box().into(), |t| t.view());
 
         let tablature = container(tablature_view).id(self.tablature_id.clone());
+        // split view with the compared tab
+        let tablature: Element<Message> = match &self.comparison {
+            Some(comparison) => row![tablature.width(Length::Fill), comparison.view()]
+                .spacing(10)
+                .into(),
+            None => tablature.into(),
+        };
 
         let base: Element<Message> = if self.is_fullscreen {
             column![tablature].spacing(20).padding(10).into()
         } else {
-            column![controls, tablature, rule::horizontal(1), status,]
-                .spacing(20)
-                .padding(10)
-                .into()
+            let mut base = column![controls].spacing(20).padding(10);
+            if self.file_changed_on_disk {
+                base = base.push(self.reload_banner());
+            }
+            if let Some(results) = &self.search_results {
+                base = base.push(search_results_view(results));
+            }
+            if self.issues_open && !warnings.is_empty() {
+                base = base.push(issues_view(warnings));
+            }
+            if self.song_info_open
+                && let Some(tab) = &self.tablature
+            {
+                base = base.push(song_info_view(&tab.song.song_info));
+            }
+            if self.metronome_open {
+                base = base.push(metronome_view(self.config.get_metronome()));
+            }
+            // only worth showing when repeats or jumps reorder the measures
+            if self.playback_segments.len() > 1 {
+                let current_tick = self.current_tick.load(Ordering::Relaxed);
+                base = base.push(timeline_view(&self.playback_segments, current_tick));
+            }
+            // only worth showing when the tempo changes or with the difficulty heatmap
+            if let Some(tab) = &self.tablature
+                && let (Some(first), Some(last)) = (
+                    self.playback_segments.first(),
+                    self.playback_segments.last(),
+                )
+            {
+                let current_tick = self.current_tick.load(Ordering::Relaxed);
+                let ticks = first.start_tick..last.end_tick;
+                let mut strip = TempoStrip::new(tab.tempo_map().changes(), ticks, current_tick);
+                if self.config.get_difficulty_heatmap() {
+                    strip = strip.with_heatmap(difficulty_heatmap(
+                        &self.playback_segments,
+                        &tab.song.measure_headers,
+                        tab.difficulty(),
+                    ));
+                }
+                if !strip.is_empty() {
+                    base = base.push(strip.view());
+                }
+            }
+            base = base.push(tablature);
+            // notes of the focused beat, following the playback
+            if self.config.get_show_fretboard()
+                && let Some(tab) = &self.tablature
+                && self.track_selection.tuning.is_some()
+            {
+                let track = &tab.song.tracks[tab.track_id];
+                let color = track_color(track.color, tab.track_id);
+                base = base.push(Fretboard::new(track, tab.focused_beat(), color).view());
+            }
+            base.push(rule::horizontal(1)).push(status).into()
+        };
+
+        let base = if self.remote_control_open {
+            let bindings = self.config.get_midi_bindings();
+            modal(
+                base,
+                remote_control_view(self.global_hotkeys.is_some(), bindings, self.midi_learning),
+                Message::CloseRemoteControl,
+            )
+        } else {
+            base
+        };
+
+        let edited_note = self.note_menu.and_then(|location| {
+            let document = self.document.as_ref()?;
+            note_at(document.song(), location).map(|note| (location, note))
+        });
+        let base = if let Some((location, note)) = edited_note {
+            modal(base, note_menu_view(location, note), Message::CloseNoteMenu)
+        } else {
+            base
+        };
+
+        let base = if self.track_manager_open {
+            modal(
+                base,
+                track_manager_view(&self.all_tracks, &self.track_layout),
+                Message::CloseTrackManager,
+            )
+        } else {
+            base
+        };
+
+        let tabs_folder = self.config.get_tabs_folder();
+        let base = match (&tabs_folder, &self.library_files) {
+            (Some(folder), Some(files)) => modal(
+                base,
+                library_view(folder, files, self.library_preview.as_ref()),
+                Message::CloseLibrary,
+            ),
+            _ => base,
+        };
+
+        let base = if self.diagnostics_open {
+            modal(
+                base,
+                diagnostics_view(&self.diagnostics()),
+                Message::CloseDiagnostics,
+            )
+        } else {
+            base
+        };
+
+        let base = match &self.audio_player {
+            Some(audio_player) if self.presets_open => modal(
+                base,
+                presets_view(
+                    &self.track_programs(),
+                    &audio_player.presets(),
+                    &self.config.get_preset_remaps(&self.sound_font_key()),
+                    &SoundFontTracks {
+                        tracks: &self
+                            .all_tracks
+                            .iter()
+                            .map(|track| (track.index(), track.to_string()))
+                            .collect::<Vec<_>>(),
+                        sound_fonts: self.config.get_sound_fonts(),
+                        assignment: &self.track_sound_fonts(),
+                    },
+                ),
+                Message::ClosePresets,
+            ),
+            _ => base,
+        };
+
+        let base = if self.export_open {
+            modal(base, export_view(self.export_options), Message::CloseExport)
+        } else {
+            base
         };
 
         // add error modal if any
@@ -711,8 +2926,44 @@ impl RuxApplication {
         })
     }
 
+    fn midi_input_subscription() -> impl Stream<Item = Message> {
+        stream::channel(16, async |mut output| {
+            let listening = midi_input::listen(move |trigger| {
+                // drop the message if the UI is lagging behind
+                if let Err(err) = output.try_send(Message::MidiTriggered(trigger)) {
+                    log::debug!("MIDI input message dropped: {err}");
+                }
+            });
+            if let Err(err) = listening {
+                log::info!("MIDI controller input unavailable: {err}");
+            }
+        })
+    }
+
+    fn instance_subscription(instance_file: &Path) -> impl Stream<Item = Message> + use<> {
+        let instance_file = instance_file.to_path_buf();
+        stream::channel(4, async move |mut output| {
+            let listener = match InstanceListener::bind(&instance_file) {
+                Ok(listener) => listener,
+                Err(err) => {
+                    log::warn!("Single instance mode unavailable: {err}");
+                    return;
+                }
+            };
+            std::thread::spawn(move || {
+                listener.listen(|tab_file| {
+                    if let Err(err) = output.try_send(Message::InstanceRequest(tab_file)) {
+                        log::debug!("Instance request dropped: {err}");
+                    }
+                });
+            });
+            // keep listening as long as the subscription runs
+            std::future::pending::<()>().await;
+        })
+    }
+
     fn subscription(&self) -> Subscription<Message> {
-        let mut subscriptions = Vec::with_capacity(4);
+        let mut subscriptions = Vec::with_capacity(8);
 
         // keyboard event subscription
         let keyboard_subscription = keyboard::listen().filter_map(|event| {
@@ -724,70 +2975,81 @@ impl RuxApplication {
             else {
                 return None;
             };
-            match modified_key.as_ref() {
-                keyboard::Key::Named(Space) => Some(Message::PlayPause),
-                keyboard::Key::Named(ArrowUp) if modifiers.control() => {
-                    Some(Message::IncreaseTempo)
-                }
-                keyboard::Key::Named(ArrowDown) if modifiers.control() => {
-                    Some(Message::DecreaseTempo)
-                }
-                keyboard::Key::Named(ArrowLeft) => Some(Message::PreviousMeasure),
-                keyboard::Key::Named(ArrowRight) => Some(Message::NextMeasure),
-                keyboard::Key::Character(c) if c.eq_ignore_ascii_case("s") => {
-                    Some(Message::ToggleSolo)
-                }
-                keyboard::Key::Named(F11) => Some(Message::ToggleFullscreen),
-                _ => None,
-            }
+            message_for_key(modified_key.as_ref(), modifiers)
         });
         subscriptions.push(keyboard_subscription);
 
+        // the playback updates pause in the background in low power mode
+        let updates_paused = self.config.get_low_power() && !self.window_focused;
+
         // next beat notifier subscription
-        subscriptions.push(Subscription::run_with(
-            BeatSubscriptionData(self.current_tick.clone(), self.beat_notify.clone()),
-            |data| Self::audio_player_beat_subscription(data.0.clone(), data.1.clone()),
-        ));
+        if !updates_paused {
+            subscriptions.push(Subscription::run_with(
+                BeatSubscriptionData(self.current_tick.clone(), self.beat_notify.clone()),
+                |data| Self::audio_player_beat_subscription(data.0.clone(), data.1.clone()),
+            ));
+        }
+
+        // MIDI controller subscription
+        subscriptions.push(Subscription::run(Self::midi_input_subscription));
+
+        // global hotkeys subscription
+        subscriptions.push(Subscription::run(global_hotkey_subscription));
+
+        // edits of the displayed file by another application
+        if let Some(watched_file) = &self.watched_file {
+            subscriptions.push(Subscription::run_with(
+                watched_file.path().to_path_buf(),
+                |path| file_watch_subscription(path),
+            ));
+        }
+
+        // tab files opened by other launches of the application
+        if let Some(instance_file) = &self.instance_file {
+            subscriptions.push(Subscription::run_with(
+                instance_file.clone(),
+                |instance_file| Self::instance_subscription(instance_file),
+            ));
+        }
+
+        // output level meter, the beat notifications pause during long notes
+        if !updates_paused
+            && self
+                .audio_player
+                .as_ref()
+                .is_some_and(AudioPlayer::is_playing)
+        {
+            subscriptions
+                .push(iced::time::every(OUTPUT_LEVEL_REFRESH).map(|_| Message::RefreshOutputLevel));
+        }
+
+        // animated scroll following the playback
+        if self
+            .tablature
+            .as_ref()
+            .is_some_and(Tablature::is_scroll_animated)
+        {
+            subscriptions.push(iced::time::every(SCROLL_FRAME).map(Message::ScrollFrame));
+        }
 
         let window_resized = window::resize_events().map(|_| Message::WindowResized);
         subscriptions.push(window_resized);
 
-        let file_dropped = window::events().filter_map(|(_, event)| {
-            if let window::Event::FileDropped(path) = event {
-                Some(Message::OpenFile(path))
-            } else {
-                None
+        let window_events = window::events().filter_map(|(_, event)| match event {
+            window::Event::FileDropped(path) => Some(Message::OpenFile(path)),
+            window::Event::Rescaled(scale_factor) => {
+                Some(Message::ScaleFactorChanged(scale_factor))
             }
+            window::Event::Focused => Some(Message::WindowFocused(true)),
+            window::Event::Unfocused => Some(Message::WindowFocused(false)),
+            _ => None,
         });
-        subscriptions.push(file_dropped);
+        subscriptions.push(window_events);
 
         Subscription::batch(subscriptions)
     }
 }
 
-/// Seconds elapsed from the song's start up to (but not including) `measure_idx`.
-/// Tempo changes across measures are honored. Repeats are ignored — we compute
-/// the song's linear duration, not expanded playback time.
-fn song_time_up_to_measure(headers: &[MeasureHeader], measure_idx: usize) -> f32 {
-    headers
-        .iter()
-        .take(measure_idx)
-        .enumerate()
-        .map(|(i, h)| {
-            let next_start = headers
-                .get(i + 1)
-                .map_or(h.start + h.length(), |next| next.start);
-            let duration_ticks = next_start.saturating_sub(h.start) as f32;
-            duration_ticks / QUARTER_TIME as f32 * 60.0 / h.tempo.value as f32
-        })
-        .sum()
-}
-
-fn format_mmss(seconds: f32) -> String {
-    let total = seconds.max(0.0) as u32;
-    format!("{}:{:02}", total / 60, total % 60)
-}
-
 struct BeatSubscriptionData(Arc<AtomicU32>, Arc<Notify>);
 
 impl std::hash::Hash for BeatSubscriptionData {
@@ -1,16 +1,22 @@
+use crate::analysis::{note_name, note_pitch};
+use crate::editor::{NoteLocation, note_at};
 use crate::parser::song_parser::{
-    Beat, BeatStrokeDirection, HarmonicType, Note, NoteEffect, NoteType, SlapEffect, SlideType,
-    Song, TimeSignature,
+    BEND_EFFECT_MAX_POSITION_LENGTH, Beat, BeatStrokeDirection, HarmonicType, Note, NoteEffect,
+    NoteType, SlapEffect, SlideType, Song, TimeSignature, Track, TremoloBarEffect, dynamic_marking,
 };
 use crate::ui::application::Message;
+use crate::ui::pixel_grid::PixelGrid;
+use crate::ui::utils::{catch_panic, format_mmss};
 use iced::advanced::mouse;
 use iced::advanced::text::Shaping::Auto;
+use iced::keyboard;
 use iced::mouse::{Cursor, Interaction};
 use iced::widget::canvas::{Cache, Event, Frame, Geometry, Path, Stroke, Text};
 use iced::widget::text::Alignment;
 use iced::widget::{Action, Canvas, canvas};
-use iced::{Color, Element, Length, Point, Rectangle, Renderer, Size, Theme};
-use std::rc::Rc;
+use iced::{Color, Element, Font, Length, Point, Rectangle, Renderer, Size, Theme, font};
+use std::cell::Cell;
+use std::sync::Arc;
 
 // Unicode symbols for musical notation
 const TEMPO_SIGN: char = '\u{1D15F}'; // 𝅗𝅥 https://unicodeplus.com/U+1D15F
@@ -22,11 +28,13 @@ const LEGATO_SLIDE: char = '\u{27CB}'; // ⟋ https://unicodeplus.com/U+27CB
 const ARROW_UP: char = '\u{2191}'; // ↑ https://unicodeplus.com/U+2191
 const ARROW_DOWN: char = '\u{2193}'; // ↓ https://unicodeplus.com/U+2193
 const TIE: char = '\u{2323}'; // ⌣ https://unicodeplus.com/U+2323
+const DOWN_STROKE: &str = "\u{2293}"; // ⊓ https://unicodeplus.com/U+2293
+const UP_STROKE: &str = "V";
 
 // Drawing constants
 
 // Vertical layout above the staff (y grows downward):
-//   y=3   MEASURE_ANNOTATION_Y   measure number / marker title
+//   y=3   MEASURE_ANNOTATION_Y   tempo / marker title, directions on the right
 //   y=15  CHORD_ANNOTATION_Y     chord name
 //   y=27  NOTE_EFFECT_ANNOTATION_Y  vibrato / hammer / slide labels
 //   y=38  BEAT_TEXT_ANNOTATION_Y beat.text ("Verse", "fill", ...)
@@ -41,6 +49,15 @@ const FIRST_STRING_Y: f32 = 60.0;
 // Space below the last string (just enough for focus box clearance).
 const BOTTOM_PADDING: f32 = 16.0;
 
+// Space below the staff for the dynamic markings (unless hidden).
+const DYNAMICS_AREA_HEIGHT: f32 = 10.0;
+
+// Space below the staff for the tremolo bar curves (only on tracks using the tremolo bar).
+const TREMOLO_BAR_AREA_HEIGHT: f32 = 24.0;
+
+// Maximum vertical deviation of a tremolo bar curve from its baseline
+const TREMOLO_BAR_AMPLITUDE: f32 = 8.0;
+
 // Distance between strings
 const STRING_LINE_HEIGHT: f32 = 13.0;
 
@@ -52,37 +69,235 @@ const BEAT_LENGTH: f32 = 24.0;
 
 const HALF_BEAT_LENGTH: f32 = BEAT_LENGTH / 2.0 + 1.0;
 
+// Length of a beat labeled with note names, room for "(G#4)" and an annotation after it
+const NOTE_NAME_BEAT_LENGTH: f32 = 36.0;
+
+// Font size of the fret labels, the note names are a bit smaller
+const NOTE_LABEL_SIZE: f32 = 10.0;
+const NOTE_NAME_LABEL_SIZE: f32 = 9.0;
+
+// Approximate width of a label char relative to the font size
+const CHAR_WIDTH_RATIO: f32 = 0.55;
+
 // minimum measure width
 const MIN_MEASURE_WIDTH: f32 = 60.0;
 
+// Time signature font size (shrunk on staves too short to hold both digits)
+const TIME_SIGNATURE_SIZE: f32 = 17.0;
+
+// Opacity of the accent shading the section playback is bounded to
+const SECTION_SHADE_ALPHA: f32 = 0.15;
+
+// Notes outside the scale of the key of the section
+const COLOR_OUTSIDE_SCALE: Color = Color::from_rgb8(0xE8, 0x8E, 0x3A);
+
+/// Height of the staff from the first to the last string line.
+fn staff_height(string_count: usize) -> f32 {
+    STRING_LINE_HEIGHT * string_count.saturating_sub(1) as f32
+}
+
+/// Vertical offset (from the first string) and font size of the time signature,
+/// centered on the staff whatever the number of strings.
+fn time_signature_layout(string_count: usize) -> (f32, f32) {
+    let staff_height = staff_height(string_count);
+    // a 4-string staff fits the stacked digits exactly
+    let offset_y = (staff_height - STRING_LINE_HEIGHT * 3.0) / 2.0;
+    let size = TIME_SIGNATURE_SIZE.min(staff_height / 2.0).max(10.0);
+    (offset_y, size)
+}
+
+/// Vertical offsets of the two repeat dots, placed in the spaces around the middle of the staff.
+fn repeat_dots_offsets(string_count: usize) -> (f32, f32) {
+    let middle = staff_height(string_count) / 2.0;
+    // an even string count has its middle in a space, an odd one on a line
+    let gap = if string_count.is_multiple_of(2) {
+        STRING_LINE_HEIGHT
+    } else {
+        STRING_LINE_HEIGHT / 2.0
+    };
+    (middle - gap, middle + gap)
+}
+
+/// Whether the track needs the tremolo bar area below its staff.
+pub fn has_tremolo_bar(track: &Track) -> bool {
+    track
+        .measures
+        .iter()
+        .flat_map(|m| &m.voices)
+        .flat_map(|v| &v.beats)
+        .flat_map(|b| &b.notes)
+        .any(|n| n.effect.tremolo_bar.is_some())
+}
+
+/// Dynamic marking of the beat from its loudest played note, `None` without any.
+fn beat_dynamic(beat: &Beat) -> Option<&'static str> {
+    beat.notes
+        .iter()
+        .filter(|n| matches!(n.kind, NoteType::Normal | NoteType::Dead))
+        .map(|n| n.velocity)
+        .max()
+        .map(dynamic_marking)
+}
+
+/// Dynamic in effect at the start of each measure of the track, from the displayed beats before it.
+pub fn measure_entry_dynamics(track: &Track) -> Vec<Option<&'static str>> {
+    let mut current = None;
+    track
+        .measures
+        .iter()
+        .map(|measure| {
+            let entry = current;
+            if let Some(voice) = measure.voices.first() {
+                current = voice.beats.iter().rev().find_map(beat_dynamic).or(current);
+            }
+            entry
+        })
+        .collect()
+}
+
+/// Beat and string of the note slot under the position, given the start of the beats
+/// and the space taken by each of them.
+fn note_slot_at(
+    position: Point,
+    beats_start_x: f32,
+    width_per_beat: f32,
+    beat_count: usize,
+    string_count: usize,
+) -> Option<(usize, i8)> {
+    // the note labels are centered a few pixels after the beat position
+    let first_note_x = beats_start_x + MEASURE_NOTES_PADDING + 3.0;
+    let beat = ((position.x - first_note_x) / width_per_beat).round();
+    let string = ((position.y - FIRST_STRING_Y) / STRING_LINE_HEIGHT).round() + 1.0;
+    let beat_x = first_note_x + beat * width_per_beat;
+    let in_reach = (position.x - beat_x).abs() <= HALF_BEAT_LENGTH;
+    (in_reach
+        && (0.0..beat_count as f32).contains(&beat)
+        && (1.0..=string_count as f32).contains(&string))
+    .then_some((beat as usize, string as i8))
+}
+
+/// Points of the tremolo bar curve as offsets from its start on the baseline.
+/// Dips go down and pulls go up, scaled so the deepest point reaches the amplitude.
+fn tremolo_bar_curve(tremolo_bar: &TremoloBarEffect, width: f32) -> Vec<(f32, f32)> {
+    let max_value = tremolo_bar
+        .points
+        .iter()
+        .map(|p| f32::from(p.value).abs())
+        .fold(1.0, f32::max);
+    tremolo_bar
+        .points
+        .iter()
+        .map(|p| {
+            let x = f32::from(p.position).min(BEND_EFFECT_MAX_POSITION_LENGTH) * width
+                / BEND_EFFECT_MAX_POSITION_LENGTH;
+            let y = -f32::from(p.value) / max_value * TREMOLO_BAR_AMPLITUDE;
+            (x, y)
+        })
+        .collect()
+}
+
+/// Point of the tremolo bar moving the pitch the most, in semitones.
+fn tremolo_bar_depth(tremolo_bar: &TremoloBarEffect) -> i8 {
+    tremolo_bar
+        .points
+        .iter()
+        .map(|p| p.value)
+        .max_by_key(|value| value.unsigned_abs())
+        .unwrap_or_default()
+}
+
 #[derive(Debug)]
 pub struct CanvasMeasure {
     pub measure_id: usize,
     track_id: usize,
-    song: Rc<Song>,
+    song: Arc<Song>,
     is_focused: bool,
     focused_beat: usize,
-    canvas_cache: Cache,
+    highlight_beat: bool, // the focused beat is highlighted, else only the focused measure
+    canvas_cache: Cache,  // staff, notes and annotations
+    focus_cache: Cache,   // focused box, focused beat and edit cursor drawn over the staff
     measure_len: f32,
     pub total_measure_len: f32,
     pub vertical_measure_height: f32,
     has_time_signature: bool,
     pub is_first_on_line: bool,
+    pixel_grid: Cell<PixelGrid>,           // grid of the cached geometry
+    start_seconds: f64,                    // playback time of the first play of the measure
+    show_dynamics: bool,                   // dynamic markings displayed below the staff
+    show_directions: bool,                 // direction signs displayed in the annotation row
+    entry_dynamic: Option<&'static str>,   // dynamic in effect before the first beat
+    in_section: bool,                      // part of the section playback is bounded to
+    editing: bool,                         // clicks select the note slots to type frets in
+    edit_cursor: Option<(usize, i8)>,      // beat and string of the selected note slot
+    suggested_chords: Vec<Option<String>>, // detected chord names of the beats, empty if hidden
+    outside_scale: Vec<Vec<i8>>,           // strings of the notes outside the scale per beat
+    note_names: bool,                      // pitches displayed instead of the frets
 }
 
 impl CanvasMeasure {
     pub fn new(
         measure_id: usize,
         track_id: usize,
-        song: Rc<Song>,
+        song: Arc<Song>,
         focused: bool,
         has_time_signature: bool,
+        has_tremolo_bar_area: bool,
+        show_dynamics: bool,
     ) -> Self {
-        let track = &song.tracks[track_id];
-        let measure = &track.measures[measure_id];
+        let (measure_len, total_measure_len) =
+            Self::widths(&song, track_id, measure_id, has_time_signature, false);
+        let vertical_measure_height = Self::height(
+            song.tracks[track_id].strings.len(),
+            show_dynamics,
+            has_tremolo_bar_area,
+        );
+        Self {
+            measure_id,
+            track_id,
+            song,
+            is_focused: focused,
+            focused_beat: 0,
+            highlight_beat: true,
+            canvas_cache: Cache::default(),
+            focus_cache: Cache::default(),
+            measure_len,
+            total_measure_len,
+            vertical_measure_height,
+            has_time_signature,
+            is_first_on_line: false,
+            pixel_grid: Cell::default(),
+            start_seconds: 0.0,
+            show_dynamics,
+            show_directions: true,
+            entry_dynamic: None,
+            in_section: false,
+            editing: false,
+            edit_cursor: None,
+            suggested_chords: Vec::new(),
+            outside_scale: Vec::new(),
+            note_names: false,
+        }
+    }
+
+    /// Width of the beats area and total width of the measure at its natural size,
+    /// known without building the measure to lay out the rows.
+    /// The beats are wider when labeled with the note names.
+    pub fn widths(
+        song: &Song,
+        track_id: usize,
+        measure_id: usize,
+        has_time_signature: bool,
+        note_names: bool,
+    ) -> (f32, f32) {
+        let measure = &song.tracks[track_id].measures[measure_id];
         let measure_header = &song.measure_headers[measure_id];
         let beat_count = measure.voices[0].beats.len();
-        let measure_len = MIN_MEASURE_WIDTH.max(beat_count as f32 * BEAT_LENGTH);
+        let beat_length = if note_names {
+            NOTE_NAME_BEAT_LENGTH
+        } else {
+            BEAT_LENGTH
+        };
+        let measure_len = MIN_MEASURE_WIDTH.max(beat_count as f32 * beat_length);
         // total length of measure (padding on both sides)
         let mut total_measure_len = measure_len + MEASURE_NOTES_PADDING * 2.0;
         // extra space for time signature
@@ -97,23 +312,20 @@ impl CanvasMeasure {
         if measure_header.repeat_close > 0 {
             total_measure_len += BEAT_LENGTH + HALF_BEAT_LENGTH;
         }
-        let string_count = track.strings.len();
-        // total height of measure (same for all measures in track)
-        let vertical_measure_height = STRING_LINE_HEIGHT * (string_count - 1) as f32;
-        let vertical_measure_height = vertical_measure_height + FIRST_STRING_Y + BOTTOM_PADDING;
-        Self {
-            measure_id,
-            track_id,
-            song,
-            is_focused: focused,
-            focused_beat: 0,
-            canvas_cache: Cache::default(),
-            measure_len,
-            total_measure_len,
-            vertical_measure_height,
-            has_time_signature,
-            is_first_on_line: false,
+        (measure_len, total_measure_len)
+    }
+
+    /// Total height of a measure, the same for all measures of the track.
+    pub fn height(string_count: usize, show_dynamics: bool, has_tremolo_bar_area: bool) -> f32 {
+        let mut vertical_measure_height =
+            staff_height(string_count) + FIRST_STRING_Y + BOTTOM_PADDING;
+        if show_dynamics {
+            vertical_measure_height += DYNAMICS_AREA_HEIGHT;
+        }
+        if has_tremolo_bar_area {
+            vertical_measure_height += TREMOLO_BAR_AREA_HEIGHT;
         }
+        vertical_measure_height
     }
 
     pub const fn set_first_on_line(&mut self, value: bool) {
@@ -143,23 +355,199 @@ impl CanvasMeasure {
         self.total_measure_len - self.measure_len
     }
 
+    /// Playback time at which the measure is first played, shown on hover.
+    pub const fn set_start_seconds(&mut self, start_seconds: f64) {
+        self.start_seconds = start_seconds;
+    }
+
+    #[cfg(test)]
+    pub const fn start_seconds(&self) -> f64 {
+        self.start_seconds
+    }
+
+    /// Dynamic in effect before the measure, only markings changing it are displayed.
+    pub const fn set_entry_dynamic(&mut self, entry_dynamic: Option<&'static str>) {
+        self.entry_dynamic = entry_dynamic;
+    }
+
+    /// Chord names detected on the beats without an authored chord, shown dimmed.
+    pub fn set_suggested_chords(&mut self, suggested_chords: Vec<Option<String>>) {
+        self.suggested_chords = suggested_chords;
+    }
+
+    /// Notes outside the scale of the key, highlighted per beat by their strings.
+    pub fn set_outside_scale(&mut self, outside_scale: Vec<Vec<i8>>) {
+        self.outside_scale = outside_scale;
+    }
+
+    /// Show the direction signs (segno, coda, D.C., ...) at the end of the annotation row.
+    pub const fn set_show_directions(&mut self, show_directions: bool) {
+        self.show_directions = show_directions;
+    }
+
+    /// Label the notes with their pitches, e.g. "G#4", instead of their frets.
+    /// The measure is widened to fit the longer labels.
+    pub fn set_note_names(&mut self, note_names: bool) {
+        self.note_names = note_names;
+        (self.measure_len, self.total_measure_len) = Self::widths(
+            &self.song,
+            self.track_id,
+            self.measure_id,
+            self.has_time_signature,
+            note_names,
+        );
+    }
+
+    /// Track the note labels are named from, `None` to label the frets.
+    fn pitch_track(&self) -> Option<&Track> {
+        self.note_names.then(|| &self.song.tracks[self.track_id])
+    }
+
+    /// Dynamic markings displayed under the beats of the measure, `None` if hidden or unchanged.
+    fn displayed_dynamics(&self) -> Vec<Option<&'static str>> {
+        let beats = &self.song.tracks[self.track_id].measures[self.measure_id].voices[0].beats;
+        let mut current = self.entry_dynamic;
+        beats
+            .iter()
+            .map(|beat| {
+                let dynamic = beat_dynamic(beat);
+                let changed = dynamic.filter(|d| self.show_dynamics && current != Some(*d));
+                current = dynamic.or(current);
+                changed
+            })
+            .collect()
+    }
+
+    /// Shade the measure as part of the section playback is bounded to.
+    pub fn set_in_section(&mut self, in_section: bool) {
+        if self.in_section != in_section {
+            self.in_section = in_section;
+            self.clear_canvas_cache();
+        }
+    }
+
+    /// Note entry: clicks select a note slot instead of only focusing the measure.
+    pub const fn set_editing(&mut self, editing: bool) {
+        self.editing = editing;
+    }
+
+    /// Highlight the note slot the typed frets go to, `None` clears it.
+    pub fn set_edit_cursor(&mut self, edit_cursor: Option<(usize, i8)>) {
+        if self.edit_cursor != edit_cursor {
+            self.edit_cursor = edit_cursor;
+            self.focus_cache.clear();
+        }
+    }
+
     pub fn toggle_focused(&mut self) {
         // reset focus state
         self.is_focused = !self.is_focused;
         self.focused_beat = 0;
-        // clear cache
-        self.canvas_cache.clear();
+        // only the highlight changes
+        self.focus_cache.clear();
+    }
+
+    pub const fn focused_beat(&self) -> usize {
+        self.focused_beat
     }
 
     pub fn focus_beat(&mut self, beat_id: usize) {
         if self.focused_beat != beat_id {
             self.focused_beat = beat_id;
-            self.canvas_cache.clear();
+            if self.highlight_beat {
+                self.focus_cache.clear();
+            }
         }
     }
 
+    /// Highlight the focused beat, else only the focused measure to redraw it less often.
+    pub fn set_highlight_beat(&mut self, highlight_beat: bool) {
+        if self.highlight_beat != highlight_beat {
+            self.highlight_beat = highlight_beat;
+            self.focus_cache.clear();
+        }
+    }
+
+    /// Lines are snapped to the physical pixels of the window scale factor.
+    pub fn set_scale_factor(&self, scale_factor: f32) {
+        self.pixel_grid.set(PixelGrid::new(scale_factor));
+        self.clear_canvas_cache();
+    }
+
     pub fn clear_canvas_cache(&self) {
         self.canvas_cache.clear();
+        self.focus_cache.clear();
+    }
+
+    /// Geometry of the measure for an offscreen image, without the focus, the section shade
+    /// and the edit cursor. The first measure of the image draws its opening bar line.
+    pub fn draw_snapshot(
+        &mut self,
+        renderer: &Renderer,
+        bounds: Rectangle,
+        scale_factor: f32,
+        first_on_row: bool,
+    ) -> Vec<Geometry> {
+        let displayed = (
+            self.is_focused,
+            self.in_section,
+            self.edit_cursor.take(),
+            self.is_first_on_line,
+        );
+        let grid = self.pixel_grid.replace(PixelGrid::new(scale_factor));
+        self.is_focused = false;
+        self.in_section = false;
+        self.is_first_on_line = first_on_row;
+        self.clear_canvas_cache();
+        let geometry = self.draw_measure(renderer, bounds, Cursor::Unavailable);
+        (
+            self.is_focused,
+            self.in_section,
+            self.edit_cursor,
+            self.is_first_on_line,
+        ) = displayed;
+        self.pixel_grid.set(grid);
+        // the cached geometry belongs to the offscreen renderer
+        self.clear_canvas_cache();
+        geometry
+    }
+
+    /// Start of the beats area, after the time signature and the repeat bar.
+    fn beats_start_x(&self) -> f32 {
+        let mut beats_start_x = 0.0;
+        if self.has_time_signature {
+            beats_start_x += BEAT_LENGTH;
+        }
+        if self.song.measure_headers[self.measure_id].repeat_open {
+            beats_start_x += BEAT_LENGTH;
+        }
+        beats_start_x
+    }
+
+    /// Note slot under the position within the measure of the given width.
+    fn slot_location_at(&self, position: Point, width: f32) -> Option<NoteLocation> {
+        let track = &self.song.tracks[self.track_id];
+        let beat_count = track.measures[self.measure_id].voices[0].beats.len();
+        let width_per_beat = (width - self.overhead_width()) / beat_count as f32;
+        let (beat, string) = note_slot_at(
+            position,
+            self.beats_start_x(),
+            width_per_beat,
+            beat_count,
+            track.strings.len(),
+        )?;
+        Some(NoteLocation {
+            track: self.track_id,
+            measure: self.measure_id,
+            beat,
+            string,
+        })
+    }
+
+    /// Note drawn under the position within the measure of the given width.
+    fn note_location_at(&self, position: Point, width: f32) -> Option<NoteLocation> {
+        self.slot_location_at(position, width)
+            .filter(|location| note_at(&self.song, *location).is_some())
     }
 }
 
@@ -167,11 +555,18 @@ impl CanvasMeasure {
 pub enum MeasureInteraction {
     #[default]
     None,
+    Hovered,
     Clicked,
 }
 
+#[derive(Debug, Default)]
+pub struct MeasureState {
+    interaction: MeasureInteraction,
+    modifiers: keyboard::Modifiers, // shift-click selects a section
+}
+
 impl canvas::Program<Message> for CanvasMeasure {
-    type State = MeasureInteraction;
+    type State = MeasureState;
 
     fn update(
         &self,
@@ -180,24 +575,100 @@ impl canvas::Program<Message> for CanvasMeasure {
         bounds: Rectangle,
         cursor: Cursor,
     ) -> Option<Action<Message>> {
+        catch_panic(
+            "measure event handling",
+            || None,
+            || self.handle_event(state, event, bounds, cursor),
+        )
+    }
+
+    fn draw(
+        &self,
+        _state: &Self::State,
+        renderer: &Renderer,
+        _theme: &Theme,
+        bounds: Rectangle,
+        cursor: Cursor,
+    ) -> Vec<Geometry> {
+        catch_panic("measure drawing", Vec::new, || {
+            self.draw_measure(renderer, bounds, cursor)
+        })
+    }
+
+    fn mouse_interaction(
+        &self,
+        _state: &Self::State,
+        _bounds: Rectangle,
+        _cursor: Cursor,
+    ) -> Interaction {
+        Interaction::default()
+    }
+}
+
+impl CanvasMeasure {
+    fn handle_event(
+        &self,
+        state: &mut MeasureState,
+        event: &Event,
+        bounds: Rectangle,
+        cursor: Cursor,
+    ) -> Option<Action<Message>> {
+        if let Event::Keyboard(keyboard::Event::ModifiersChanged(modifiers)) = event {
+            state.modifiers = *modifiers;
+        }
+        if let Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Right)) = event
+            && let Some(cursor_position) = cursor.position_in(bounds)
+        {
+            // right-click on a note opens its menu
+            return self
+                .note_location_at(cursor_position, bounds.width)
+                .map(|location| Action::publish(Message::OpenNoteMenu(location)));
+        }
         if let Event::Mouse(mouse::Event::ButtonPressed(_)) = event
-            && let Some(_cursor_position) = cursor.position_in(bounds)
+            && let Some(cursor_position) = cursor.position_in(bounds)
         {
             log::info!("Clicked on measure {:?}", self.measure_id);
-            *state = MeasureInteraction::Clicked;
-            return Some(Action::publish(Message::FocusMeasure(self.measure_id)));
+            state.interaction = MeasureInteraction::Clicked;
+            let slot = self
+                .editing
+                .then(|| self.slot_location_at(cursor_position, bounds.width))
+                .flatten();
+            let message = if state.modifiers.shift() {
+                Message::SelectSection(self.measure_id)
+            } else if let Some(location) = slot {
+                Message::SelectNoteSlot(location)
+            } else {
+                Message::FocusMeasure(self.measure_id)
+            };
+            return Some(Action::publish(message));
+        }
+        // redraw to show or hide the start time when the cursor enters or leaves
+        if let Event::Mouse(_) = event {
+            let hovered = cursor.is_over(bounds);
+            if hovered != matches!(state.interaction, MeasureInteraction::Hovered) {
+                state.interaction = if hovered {
+                    MeasureInteraction::Hovered
+                } else {
+                    MeasureInteraction::None
+                };
+                return Some(Action::request_redraw());
+            }
         }
         None
     }
 
-    fn draw(
+    fn draw_measure(
         &self,
-        _state: &Self::State,
         renderer: &Renderer,
-        _theme: &Theme,
         bounds: Rectangle,
-        _cursor: Cursor,
+        cursor: Cursor,
     ) -> Vec<Geometry> {
+        // snapping depends on the position within the physical pixel
+        let grid = self.pixel_grid.get().at(bounds.position());
+        if grid != self.pixel_grid.get() {
+            self.pixel_grid.set(grid);
+            self.clear_canvas_cache();
+        }
         // the cache will not redraw its geometry unless the dimensions of its layer change, or it is explicitly cleared.
         let tab = self.canvas_cache.draw(renderer, bounds.size(), |frame| {
             log::debug!("Re-drawing measure {}", self.measure_id);
@@ -211,7 +682,7 @@ impl canvas::Program<Message> for CanvasMeasure {
             let actual_measure_len = actual_width - self.overhead_width();
 
             // distance between lines of measures
-            let vertical_measure_height = STRING_LINE_HEIGHT * (string_count - 1) as f32;
+            let vertical_measure_height = staff_height(string_count);
 
             // Positive x-values extend to the right, and positive y-values extend downwards.
             let measure_start_x = 0.0;
@@ -220,15 +691,21 @@ impl canvas::Program<Message> for CanvasMeasure {
             // colors
             let color_gray = crate::ui::utils::COLOR_GRAY;
             let color_dark_red = crate::ui::utils::COLOR_DARK_RED;
+            let color_accent = crate::ui::utils::track_color(track.color, self.track_id);
 
-            // draw focused box
-            if self.is_focused {
-                draw_focused_box(
-                    frame,
-                    actual_width,
-                    vertical_measure_height,
-                    measure_start_x,
-                    measure_start_y,
+            // shade the section playback is bounded to, behind everything else
+            if self.in_section {
+                let section_color = Color {
+                    a: SECTION_SHADE_ALPHA,
+                    ..color_accent
+                };
+                frame.fill_rectangle(
+                    Point::new(measure_start_x, measure_start_y - STRING_LINE_HEIGHT),
+                    Size::new(
+                        actual_width,
+                        vertical_measure_height + STRING_LINE_HEIGHT * 2.0,
+                    ),
+                    section_color,
                 );
             }
 
@@ -237,16 +714,14 @@ impl canvas::Program<Message> for CanvasMeasure {
                 // down position
                 let local_start_y = string_id as f32 * STRING_LINE_HEIGHT;
                 // add 1 to x to avoid overlapping with vertical line
-                let start_point =
-                    Point::new(measure_start_x + 1.0, measure_start_y + local_start_y);
                 // draw at the same y until end of container
-                let end_point = Point::new(
+                let line = grid.horizontal_line(
+                    measure_start_x + 1.0,
                     measure_start_x + actual_width,
                     measure_start_y + local_start_y,
+                    0.8,
                 );
-                let line = Path::line(start_point, end_point);
-                let stroke = Stroke::default().with_width(0.8).with_color(color_gray);
-                frame.stroke(&line, stroke);
+                frame.stroke(&line, grid.stroke(0.8, color_gray));
             }
 
             // measure headers
@@ -262,13 +737,16 @@ impl canvas::Program<Message> for CanvasMeasure {
             if measure_header.repeat_open {
                 draw_open_repeat(
                     frame,
+                    grid,
                     measure_start_x,
                     measure_start_y,
                     vertical_measure_height,
+                    string_count,
                 );
             } else if self.measure_id == 0 {
                 draw_open_section(
                     frame,
+                    grid,
                     measure_start_x,
                     measure_start_y,
                     vertical_measure_height,
@@ -279,6 +757,7 @@ impl canvas::Program<Message> for CanvasMeasure {
                 if self.is_first_on_line {
                     draw_measure_vertical_line(
                         frame,
+                        grid,
                         vertical_measure_height,
                         measure_start_x,
                         measure_start_y,
@@ -334,11 +813,31 @@ impl canvas::Program<Message> for CanvasMeasure {
                 frame.fill_text(marker_text);
             }
 
+            // direction signs (segno, coda, D.C., ...) at the end of the annotation row
+            if self.show_directions && !measure_header.directions.is_empty() {
+                let targets = measure_header.directions.iter().filter(|d| d.is_target());
+                let jumps = measure_header.directions.iter().filter(|d| !d.is_target());
+                let label: Vec<&str> = targets.chain(jumps).map(|d| d.label()).collect();
+                let directions_text = Text {
+                    shaping: Auto,
+                    content: label.join(" "),
+                    color: Color::WHITE,
+                    size: 11.0.into(),
+                    position: Point::new(
+                        measure_start_x + actual_width - MEASURE_NOTES_PADDING / 2.0,
+                        MEASURE_ANNOTATION_Y,
+                    ),
+                    align_x: Alignment::Right,
+                    ..Text::default()
+                };
+                frame.fill_text(directions_text);
+            }
+
             // measure count label
             let measure_count_text = Text {
                 shaping: Auto,
                 content: format!("{}", self.measure_id + 1),
-                color: color_dark_red,
+                color: color_accent,
                 size: 10.0.into(),
                 position: Point::new(measure_start_x, FIRST_STRING_Y - 15.0),
                 ..Text::default()
@@ -349,6 +848,7 @@ impl canvas::Program<Message> for CanvasMeasure {
             if measure_header.repeat_alternative > 0 {
                 draw_alternative_ending(
                     frame,
+                    grid,
                     measure_header.repeat_alternative,
                     measure_start_x,
                     actual_width,
@@ -361,30 +861,23 @@ impl canvas::Program<Message> for CanvasMeasure {
             let beats = &measure.voices[0].beats;
             let beats_len = beats.len();
             log::debug!("{beats_len} beats");
-            let mut beat_start = measure_start_x;
-            if self.has_time_signature {
-                beat_start += BEAT_LENGTH;
-            }
-            if measure_header.repeat_open {
-                beat_start += BEAT_LENGTH;
-            }
+            let dynamics = self.displayed_dynamics();
+            let beat_start = measure_start_x + self.beats_start_x();
             for (b_id, beat) in beats.iter().enumerate() {
-                // pick color if beat under focus
-                let beat_color = if self.is_focused && b_id == self.focused_beat {
-                    color_dark_red
-                } else {
-                    Color::WHITE
-                };
-                // draw beat
                 draw_beat(
                     frame,
                     actual_measure_len,
                     beat_start,
                     measure_start_y,
+                    string_count,
                     beats_len,
                     b_id,
                     beat,
-                    beat_color,
+                    dynamics[b_id],
+                    self.show_dynamics,
+                    self.suggested_chords.get(b_id).and_then(Option::as_deref),
+                    self.outside_scale.get(b_id).map_or(&[][..], Vec::as_slice),
+                    self.pitch_track(),
                 );
             }
 
@@ -392,14 +885,17 @@ impl canvas::Program<Message> for CanvasMeasure {
             if measure_header.repeat_close > 0 {
                 draw_close_repeat(
                     frame,
+                    grid,
                     measure_start_x + actual_width,
                     measure_start_y,
                     vertical_measure_height,
+                    string_count,
                     measure_header.repeat_close,
                 );
             } else if next_measure_header.is_none() {
                 draw_end_section(
                     frame,
+                    grid,
                     measure_start_x + actual_width,
                     measure_start_y,
                     vertical_measure_height,
@@ -408,6 +904,7 @@ impl canvas::Program<Message> for CanvasMeasure {
                 // vertical measure end
                 draw_measure_vertical_line(
                     frame,
+                    grid,
                     vertical_measure_height,
                     measure_start_x + actual_width, // end of measure
                     measure_start_y,
@@ -415,21 +912,89 @@ impl canvas::Program<Message> for CanvasMeasure {
             }
         });
 
-        vec![tab]
-    }
+        // the highlight is redrawn on its own as the playback moves from beat to beat
+        let focus = self.focus_cache.draw(renderer, bounds.size(), |frame| {
+            if !self.is_focused && self.edit_cursor.is_none() {
+                return;
+            }
+            let track = &self.song.tracks[self.track_id];
+            let string_count = track.strings.len();
+            let actual_width = frame.width();
+            let actual_measure_len = actual_width - self.overhead_width();
+            let color_accent = crate::ui::utils::track_color(track.color, self.track_id);
+            let beats = &track.measures[self.measure_id].voices[0].beats;
+            let beat_start = self.beats_start_x();
+            if self.is_focused {
+                draw_focused_box(
+                    frame,
+                    grid,
+                    actual_width,
+                    staff_height(string_count),
+                    0.0,
+                    FIRST_STRING_Y,
+                );
+                if let Some(beat) = beats.get(self.focused_beat).filter(|_| self.highlight_beat) {
+                    let mut label_backgrounds = vec![Theme::Dark.palette().background];
+                    if self.in_section {
+                        label_backgrounds.push(Color {
+                            a: SECTION_SHADE_ALPHA,
+                            ..color_accent
+                        });
+                    }
+                    draw_focused_beat(
+                        frame,
+                        actual_measure_len,
+                        beat_start,
+                        FIRST_STRING_Y,
+                        string_count,
+                        beats.len(),
+                        self.focused_beat,
+                        beat,
+                        color_accent,
+                        &label_backgrounds,
+                        self.pitch_track(),
+                    );
+                }
+            }
+            if let Some((b_id, string)) = self.edit_cursor {
+                draw_edit_cursor(
+                    frame,
+                    actual_measure_len,
+                    beat_start,
+                    FIRST_STRING_Y,
+                    beats.len(),
+                    b_id,
+                    string,
+                    color_accent,
+                );
+            }
+        });
 
-    fn mouse_interaction(
-        &self,
-        _state: &Self::State,
-        _bounds: Rectangle,
-        _cursor: Cursor,
-    ) -> Interaction {
-        Interaction::default()
+        if !cursor.is_over(bounds) {
+            return vec![tab, focus];
+        }
+        // start time on hover, drawn outside the cache
+        let mut frame = Frame::new(renderer, bounds.size());
+        let start_time_text = Text {
+            shaping: Auto,
+            content: format_mmss(self.start_seconds),
+            color: Color::WHITE,
+            size: 10.0.into(),
+            position: Point::new(
+                frame.width() - MEASURE_NOTES_PADDING / 2.0,
+                FIRST_STRING_Y - 15.0,
+            ),
+            align_x: Alignment::Right,
+            ..Text::default()
+        };
+        frame.fill_text(start_time_text);
+        vec![tab, focus, frame.into_geometry()]
     }
 }
 
 fn draw_focused_box(
     frame: &mut Frame<Renderer>,
+    grid: PixelGrid,
     total_measure_len: f32,
     vertical_measure_height: f32,
     measure_start_x: f32,
@@ -453,21 +1018,24 @@ fn draw_focused_box(
 
     let top_left = Point::new(x, y);
     let rectangle_size = Size::new(width, height);
-    let stroke = Stroke::default().with_width(1.0).with_color(Color::WHITE);
-    frame.stroke_rectangle(top_left, rectangle_size, stroke);
+    let rectangle = grid.rectangle(top_left, rectangle_size, 1.0);
+    frame.stroke(&rectangle, grid.stroke(1.0, Color::WHITE));
 }
 
 fn draw_measure_vertical_line(
     frame: &mut Frame<Renderer>,
+    grid: PixelGrid,
     vertical_measure_height: f32,
     measure_start_x: f32,
     measure_start_y: f32,
 ) {
-    let start_point = Point::new(measure_start_x, measure_start_y);
-    let end_point = Point::new(measure_start_x, measure_start_y + vertical_measure_height);
-    let vertical_line = Path::line(start_point, end_point);
-    let stroke = Stroke::default().with_width(1.5).with_color(Color::WHITE);
-    frame.stroke(&vertical_line, stroke);
+    let vertical_line = grid.vertical_line(
+        measure_start_x,
+        measure_start_y,
+        measure_start_y + vertical_measure_height,
+        1.5,
+    );
+    frame.stroke(&vertical_line, grid.stroke(1.5, Color::WHITE));
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -476,10 +1044,15 @@ fn draw_beat(
     measure_len: f32,
     measure_start_x: f32,
     measure_start_y: f32,
+    string_count: usize,
     beats_len: usize,
     b_id: usize,
     beat: &Beat,
-    beat_color: Color,
+    dynamic: Option<&str>,
+    has_dynamics_area: bool,
+    suggested_chord: Option<&str>,
+    outside_scale: &[i8],
+    pitch_track: Option<&Track>,
 ) {
     // position to draw beat
     let width_per_beat = measure_len / beats_len as f32;
@@ -497,27 +1070,95 @@ fn draw_beat(
             ..Text::default()
         };
         frame.fill_text(note_effect_text);
+    } else if let Some(suggested_chord) = suggested_chord {
+        let suggestion_text = Text {
+            shaping: Auto,
+            content: suggested_chord.to_string(),
+            color: Color {
+                a: 0.5,
+                ..Color::WHITE
+            },
+            size: 8.0.into(),
+            position: Point::new(beat_position_x + 3.0, CHORD_ANNOTATION_Y),
+            ..Text::default()
+        };
+        frame.fill_text(suggestion_text);
     }
-    if !beat.effect.stroke.is_empty() && !beat.notes.is_empty() {
+    if !beat.effect.stroke.is_empty() && beat.has_notes() {
         draw_stroke_arrow(frame, beat, beat_position_x, measure_start_y);
     }
 
     // Annotate note effect above (same position for all notes)
     let mut beat_annotations = Vec::new();
 
+    beat_annotations.extend(pick_stroke_annotation(&beat.effect.pick_stroke));
+
     // draw notes for beat
     for note in &beat.notes {
+        if note.string < 1 || note.string as usize > string_count {
+            log::warn!(
+                "Note on string {} outside of {string_count} strings",
+                note.string
+            );
+            continue;
+        }
         beat_annotations.extend(above_note_effect_annotation(&note.effect));
+        let note_color = if outside_scale.contains(&note.string) {
+            COLOR_OUTSIDE_SCALE
+        } else {
+            Color::WHITE
+        };
         draw_note(
             frame,
             measure_start_y,
             beat_position_x,
             width_per_beat,
             note,
-            beat_color,
+            note_color,
+            pitch_track,
         );
     }
 
+    // tremolo bar curve below the staff
+    if let Some(tremolo_bar) = beat
+        .notes
+        .iter()
+        .find_map(|n| n.effect.tremolo_bar.as_ref())
+    {
+        let mut area_start_y = measure_start_y + staff_height(string_count) + BOTTOM_PADDING;
+        if has_dynamics_area {
+            area_start_y += DYNAMICS_AREA_HEIGHT;
+        }
+        draw_tremolo_bar(
+            frame,
+            tremolo_bar,
+            beat_position_x,
+            area_start_y,
+            width_per_beat,
+        );
+    }
+
+    // dynamic marking below the staff, italic like in Guitar Pro
+    if let Some(dynamic) = dynamic {
+        let dynamic_text = Text {
+            shaping: Auto,
+            content: dynamic.to_string(),
+            color: Color::WHITE,
+            size: 10.0.into(),
+            font: Font {
+                weight: font::Weight::Bold,
+                style: font::Style::Italic,
+                ..Font::DEFAULT
+            },
+            position: Point::new(
+                beat_position_x,
+                measure_start_y + staff_height(string_count) + BOTTOM_PADDING - 4.0,
+            ),
+            ..Text::default()
+        };
+        frame.fill_text(dynamic_text);
+    }
+
     // merge and display beat annotations
     if !beat_annotations.is_empty() {
         beat_annotations.sort_unstable();
@@ -535,13 +1176,17 @@ fn draw_beat(
         frame.fill_text(note_effect_text);
     }
 
-    // user-authored text attached to the beat (e.g. "Verse", "fill")
+    // user-authored cue attached to the beat (e.g. "Verse", "fill"), italic like in Guitar Pro
     if !beat.text.is_empty() {
         let beat_text = Text {
             shaping: Auto,
             content: beat.text.clone(),
             color: Color::WHITE,
             size: 8.0.into(),
+            font: Font {
+                style: font::Style::Italic,
+                ..Font::MONOSPACE
+            },
             position: Point::new(beat_position_x + 3.0, BEAT_TEXT_ANNOTATION_Y),
             ..Text::default()
         };
@@ -549,37 +1194,57 @@ fn draw_beat(
     }
 }
 
+/// Box around the note slot the typed frets go to.
+#[allow(clippy::too_many_arguments)]
+fn draw_edit_cursor(
+    frame: &mut Frame<Renderer>,
+    measure_len: f32,
+    measure_start_x: f32,
+    measure_start_y: f32,
+    beats_len: usize,
+    b_id: usize,
+    string: i8,
+    color: Color,
+) {
+    let width_per_beat = measure_len / beats_len as f32;
+    let beat_position_x = measure_start_x + MEASURE_NOTES_PADDING + b_id as f32 * width_per_beat;
+    // centered on the note label
+    let center = Point::new(
+        beat_position_x + 3.0,
+        measure_start_y + (f32::from(string) - 1.0) * STRING_LINE_HEIGHT,
+    );
+    let half_size = STRING_LINE_HEIGHT / 2.0;
+    let cursor = Path::rectangle(
+        Point::new(center.x - half_size, center.y - half_size),
+        Size::new(half_size * 2.0, half_size * 2.0),
+    );
+    frame.stroke(&cursor, Stroke::default().with_width(1.0).with_color(color));
+}
+
 fn draw_note(
     frame: &mut Frame<Renderer>,
     measure_start_y: f32,
     beat_position_x: f32,
     width_per_beat: f32,
     note: &Note,
-    beat_color: Color,
+    note_color: Color,
+    pitch_track: Option<&Track>,
 ) {
-    // note label (pushed down on the right string)
-    let note_label = note_value(note);
-    let local_beat_position_y = (f32::from(note.string) - 1.0) * STRING_LINE_HEIGHT;
-    // center the notes with more than one char
-    let note_position_x = beat_position_x + 3.0 - note_label.chars().count() as f32 / 2.0;
-    let note_position_y = measure_start_y + local_beat_position_y - 5.0;
-    let note_text = Text {
-        shaping: Auto,
-        content: note_label,
-        color: beat_color,
-        size: 10.0.into(),
-        position: Point::new(note_position_x, note_position_y),
-        align_x: Alignment::Center,
-        ..Text::default()
-    };
-    frame.fill_text(note_text);
+    let (note_position_x, note_position_y, label_width) = draw_note_label(
+        frame,
+        measure_start_y,
+        beat_position_x,
+        note,
+        note_color,
+        &[],
+        pitch_track,
+    );
 
     // Annotate some effects on the string after the note
     let inlined_annotation_width = 10.0;
     let inlined_annotation_label = inlined_note_effect_annotation(&note.effect);
-    // note_x + half of inter-beat space - half of annotation width
     let annotation_position_x =
-        note_position_x + width_per_beat / 2.0 - inlined_annotation_width / 2.0;
+        note_position_x + annotation_offset(width_per_beat, label_width, inlined_annotation_width);
     let note_effect_text = Text {
         shaping: Auto,
         content: inlined_annotation_label,
@@ -591,8 +1256,98 @@ fn draw_note(
     frame.fill_text(note_effect_text);
 }
 
+/// Offset of the annotation after a note label: half of the inter-beat space minus half of
+/// the annotation width, pushed right of a long label.
+fn annotation_offset(width_per_beat: f32, label_width: f32, annotation_width: f32) -> f32 {
+    (width_per_beat / 2.0 - annotation_width / 2.0).max(label_width / 2.0)
+}
+
+/// Approximate width of a label drawn at the font size.
+fn label_width(label: &str, size: f32) -> f32 {
+    label.chars().count() as f32 * size * CHAR_WIDTH_RATIO
+}
+
+/// Note label pushed down on its string, over the `backgrounds` filled in order behind it.
+/// Returns its position and its width.
+fn draw_note_label(
+    frame: &mut Frame<Renderer>,
+    measure_start_y: f32,
+    beat_position_x: f32,
+    note: &Note,
+    note_color: Color,
+    backgrounds: &[Color],
+    pitch_track: Option<&Track>,
+) -> (f32, f32, f32) {
+    let note_label = note_value(note, pitch_track);
+    let size = if pitch_track.is_some() {
+        NOTE_NAME_LABEL_SIZE
+    } else {
+        NOTE_LABEL_SIZE
+    };
+    let width = label_width(&note_label, size);
+    let local_beat_position_y = (f32::from(note.string) - 1.0) * STRING_LINE_HEIGHT;
+    // center the notes with more than one char
+    let note_position_x = beat_position_x + 3.0 - note_label.chars().count() as f32 / 2.0;
+    let note_position_y = measure_start_y + local_beat_position_y - 5.0;
+    for background in backgrounds {
+        frame.fill_rectangle(
+            Point::new(note_position_x - width / 2.0 - 1.0, note_position_y),
+            Size::new(width + 2.0, size + 2.0),
+            *background,
+        );
+    }
+    let note_text = Text {
+        shaping: Auto,
+        content: note_label,
+        color: note_color,
+        size: size.into(),
+        position: Point::new(note_position_x, note_position_y),
+        align_x: Alignment::Center,
+        ..Text::default()
+    };
+    frame.fill_text(note_text);
+    (note_position_x, note_position_y, width)
+}
+
+/// Notes of the focused beat in the accent color, over their labels on the staff.
+/// The labels below are covered by `label_backgrounds` so their antialiasing does not show.
+/// The focus color wins over the scale highlight.
+#[allow(clippy::too_many_arguments)]
+fn draw_focused_beat(
+    frame: &mut Frame<Renderer>,
+    measure_len: f32,
+    measure_start_x: f32,
+    measure_start_y: f32,
+    string_count: usize,
+    beats_len: usize,
+    b_id: usize,
+    beat: &Beat,
+    color: Color,
+    label_backgrounds: &[Color],
+    pitch_track: Option<&Track>,
+) {
+    let width_per_beat = measure_len / beats_len as f32;
+    let beat_position_x = measure_start_x + MEASURE_NOTES_PADDING + b_id as f32 * width_per_beat;
+    let notes = beat
+        .notes
+        .iter()
+        .filter(|note| note.string >= 1 && note.string as usize <= string_count);
+    for note in notes {
+        draw_note_label(
+            frame,
+            measure_start_y,
+            beat_position_x,
+            note,
+            color,
+            label_backgrounds,
+            pitch_track,
+        );
+    }
+}
+
 fn draw_open_section(
     frame: &mut Frame<Renderer>,
+    grid: PixelGrid,
     measure_start_x: f32,
     measure_start_y: f32,
     vertical_measure_height: f32,
@@ -600,15 +1355,18 @@ fn draw_open_section(
     let position_x = measure_start_x;
 
     // draw first thick one
-    let start_point = Point::new(position_x, measure_start_y);
-    let end_point = Point::new(position_x, measure_start_y + vertical_measure_height);
-    let tick_vertical_line = Path::line(start_point, end_point);
-    let stroke = Stroke::default().with_width(4.0).with_color(Color::WHITE);
-    frame.stroke(&tick_vertical_line, stroke);
+    let tick_vertical_line = grid.vertical_line(
+        position_x,
+        measure_start_y,
+        measure_start_y + vertical_measure_height,
+        4.0,
+    );
+    frame.stroke(&tick_vertical_line, grid.stroke(4.0, Color::WHITE));
 
     // then thin one
     draw_measure_vertical_line(
         frame,
+        grid,
         vertical_measure_height,
         measure_start_x + 6.0,
         measure_start_y,
@@ -617,12 +1375,15 @@ fn draw_open_section(
 
 fn draw_open_repeat(
     frame: &mut Frame<Renderer>,
+    grid: PixelGrid,
     measure_start_x: f32,
     measure_start_y: f32,
     vertical_measure_height: f32,
+    string_count: usize,
 ) {
     draw_open_section(
         frame,
+        grid,
         measure_start_x,
         measure_start_y,
         vertical_measure_height,
@@ -632,19 +1393,22 @@ fn draw_open_repeat(
         frame,
         measure_start_x + HALF_BEAT_LENGTH,
         measure_start_y,
-        vertical_measure_height,
+        string_count,
     );
 }
 
 fn draw_close_repeat(
     frame: &mut Frame<Renderer>,
+    grid: PixelGrid,
     measure_end_x: f32,
     measure_start_y: f32,
     vertical_measure_height: f32,
+    string_count: usize,
     repeat_count: i8,
 ) {
     draw_end_section(
         frame,
+        grid,
         measure_end_x,
         measure_start_y,
         vertical_measure_height,
@@ -654,7 +1418,7 @@ fn draw_close_repeat(
         frame,
         measure_end_x - HALF_BEAT_LENGTH,
         measure_start_y,
-        vertical_measure_height,
+        string_count,
     );
     // add repeat count text
     let repeat_count_text = Text {
@@ -717,8 +1481,53 @@ fn draw_stroke_arrow(
     }
 }
 
+fn draw_tremolo_bar(
+    frame: &mut Frame<Renderer>,
+    tremolo_bar: &TremoloBarEffect,
+    beat_position_x: f32,
+    area_start_y: f32,
+    width_per_beat: f32,
+) {
+    let baseline_y = area_start_y + TREMOLO_BAR_AREA_HEIGHT / 2.0;
+    let width = (width_per_beat - 6.0).max(6.0);
+    let curve = tremolo_bar_curve(tremolo_bar, width);
+    if curve.len() < 2 {
+        return;
+    }
+    let path = Path::new(|builder| {
+        let (x, y) = curve[0];
+        builder.move_to(Point::new(beat_position_x + x, baseline_y + y));
+        for (x, y) in &curve[1..] {
+            builder.line_to(Point::new(beat_position_x + x, baseline_y + y));
+        }
+    });
+    let stroke = Stroke::default().with_width(1.0).with_color(Color::WHITE);
+    frame.stroke(&path, stroke);
+
+    // depth label on the opposite side of the curve
+    let depth = tremolo_bar_depth(tremolo_bar);
+    if depth != 0 {
+        let label_y = if depth < 0 {
+            baseline_y - 11.0
+        } else {
+            baseline_y + 2.0
+        };
+        let depth_text = Text {
+            shaping: Auto,
+            content: format!("{depth:+}"),
+            color: Color::WHITE,
+            size: 8.0.into(),
+            position: Point::new(beat_position_x + width / 2.0, label_y),
+            align_x: Alignment::Center,
+            ..Text::default()
+        };
+        frame.fill_text(depth_text);
+    }
+}
+
 fn draw_alternative_ending(
     frame: &mut Frame<Renderer>,
+    grid: PixelGrid,
     repeat_alternative: u8,
     measure_start_x: f32,
     measure_width: f32,
@@ -728,16 +1537,15 @@ fn draw_alternative_ending(
     let bracket_start = measure_start_x + 2.0;
     let bracket_end = measure_start_x + measure_width;
 
-    let stroke = Stroke::default().with_width(1.0).with_color(Color::WHITE);
+    let stroke = grid.stroke(1.0, Color::WHITE);
 
     // vertical line down
-    let start = Point::new(bracket_start, bracket_y);
-    let down = Point::new(bracket_start, bracket_y + bracket_height);
-    frame.stroke(&Path::line(start, down), stroke);
+    let down = grid.vertical_line(bracket_start, bracket_y, bracket_y + bracket_height, 1.0);
+    frame.stroke(&down, stroke);
 
     // horizontal line across
-    let right = Point::new(bracket_end, bracket_y);
-    frame.stroke(&Path::line(start, right), stroke);
+    let across = grid.horizontal_line(bracket_start, bracket_end, bracket_y, 1.0);
+    frame.stroke(&across, stroke);
 
     // build label from bitmask (e.g., 1 → "1.", 2 → "2.", 3 → "1.2.")
     let mut label = String::new();
@@ -762,14 +1570,11 @@ fn draw_alternative_ending(
     frame.fill_text(label_text);
 }
 
-fn draw_repeat_dots(
-    frame: &mut Frame<Renderer>,
-    start_x: f32,
-    start_y: f32,
-    vertical_measure_height: f32,
-) {
+fn draw_repeat_dots(frame: &mut Frame<Renderer>, start_x: f32, start_y: f32, string_count: usize) {
+    let (top_offset_y, bottom_offset_y) = repeat_dots_offsets(string_count);
+
     // top dot
-    let top_position_y = start_y + vertical_measure_height / 3.0;
+    let top_position_y = start_y + top_offset_y;
     let center = Point::new(start_x, top_position_y);
     let circle = Path::circle(center, 1.0);
 
@@ -779,7 +1584,7 @@ fn draw_repeat_dots(
     );
 
     // bottom dot
-    let bottom_position_y = start_y + (vertical_measure_height / 3.0) * 2.0;
+    let bottom_position_y = start_y + bottom_offset_y;
     let center = Point::new(start_x, bottom_position_y);
     let circle = Path::circle(center, 1.0);
 
@@ -791,6 +1596,7 @@ fn draw_repeat_dots(
 
 fn draw_end_section(
     frame: &mut Frame<Renderer>,
+    grid: PixelGrid,
     measure_end_x: f32,
     measure_start_y: f32,
     vertical_measure_height: f32,
@@ -798,6 +1604,7 @@ fn draw_end_section(
     // draw first thin one
     draw_measure_vertical_line(
         frame,
+        grid,
         vertical_measure_height,
         measure_end_x - 8.0,
         measure_start_y,
@@ -805,11 +1612,13 @@ fn draw_end_section(
 
     // then thick one
     let position_x = measure_end_x - 2.0;
-    let start_point = Point::new(position_x, measure_start_y);
-    let end_point = Point::new(position_x, measure_start_y + vertical_measure_height);
-    let thick_vertical_line = Path::line(start_point, end_point);
-    let stroke = Stroke::default().with_width(4.0).with_color(Color::WHITE);
-    frame.stroke(&thick_vertical_line, stroke);
+    let thick_vertical_line = grid.vertical_line(
+        position_x,
+        measure_start_y,
+        measure_start_y + vertical_measure_height,
+        4.0,
+    );
+    frame.stroke(&thick_vertical_line, grid.stroke(4.0, Color::WHITE));
 }
 
 fn draw_time_signature(
@@ -824,18 +1633,14 @@ fn draw_time_signature(
     } else {
         HALF_BEAT_LENGTH
     };
-    let position_y = if string_count > 4 {
-        (STRING_LINE_HEIGHT * (string_count - 4) as f32) / 2.0
-    } else {
-        0.0
-    };
+    let (position_y, size) = time_signature_layout(string_count);
     let numerator = time_signature.numerator;
     let denominator = time_signature.denominator.value;
     let tempo_text = Text {
         shaping: Auto,
         content: format!("{numerator}\n{denominator}"),
         color: Color::WHITE,
-        size: 17.into(),
+        size: size.into(),
         position: Point::new(
             measure_start_x + position_x,
             (FIRST_STRING_Y - 1.0) + position_y,
@@ -845,6 +1650,15 @@ fn draw_time_signature(
     frame.fill_text(tempo_text);
 }
 
+/// Picking direction symbol shown with the annotations above the beat.
+const fn pick_stroke_annotation(pick_stroke: &BeatStrokeDirection) -> Option<&'static str> {
+    match pick_stroke {
+        BeatStrokeDirection::Down => Some(DOWN_STROKE),
+        BeatStrokeDirection::Up => Some(UP_STROKE),
+        BeatStrokeDirection::None => None,
+    }
+}
+
 // Similar to `https://www.tuxguitar.app/files/1.6.0/desktop/help/edit_effects.html`
 fn above_note_effect_annotation(note_effect: &NoteEffect) -> Vec<&'static str> {
     let mut annotations: Vec<&'static str> = vec![];
@@ -881,9 +1695,6 @@ fn above_note_effect_annotation(note_effect: &NoteEffect) -> Vec<&'static str> {
     if note_effect.tremolo_picking.is_some() {
         annotations.push("T.P");
     }
-    if note_effect.tremolo_bar.is_some() {
-        annotations.push("T.B");
-    }
     if note_effect.slap == SlapEffect::Tapping {
         annotations.push("T");
     }
@@ -918,17 +1729,21 @@ fn inlined_note_effect_annotation(note_effect: &NoteEffect) -> String {
     annotation
 }
 
-fn note_value(note: &Note) -> String {
+/// Fret of the note, or its pitch name when labeled from the tuning of `pitch_track`.
+fn note_value(note: &Note, pitch_track: Option<&Track>) -> String {
     match note.kind {
         NoteType::Rest => {
             log::debug!("NoteType Rest");
             String::new()
         }
         NoteType::Normal => {
+            let value = pitch_track
+                .and_then(|track| note_pitch(track, note))
+                .map_or_else(|| note.value.to_string(), note_name);
             if note.effect.ghost_note {
-                format!("({})", note.value)
+                format!("({value})")
             } else {
-                note.value.to_string()
+                value
             }
         }
         NoteType::Tie => {
@@ -942,3 +1757,259 @@ fn note_value(note: &Note) -> String {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::song_parser::{
+        BendPoint, DEFAULT_VELOCITY, DYNAMIC_MARKINGS, convert_velocity,
+    };
+    use crate::parser::song_parser_tests::parse_gp_file;
+
+    #[test]
+    fn staff_height_grows_with_strings() {
+        assert_eq!(staff_height(0), 0.0);
+        assert_eq!(staff_height(1), 0.0);
+        assert_eq!(staff_height(4), STRING_LINE_HEIGHT * 3.0);
+        assert_eq!(staff_height(6), STRING_LINE_HEIGHT * 5.0);
+    }
+
+    #[test]
+    fn time_signature_centered_on_staff() {
+        // unchanged layout for the common string counts
+        assert_eq!(time_signature_layout(4), (0.0, TIME_SIGNATURE_SIZE));
+        assert_eq!(
+            time_signature_layout(6),
+            (STRING_LINE_HEIGHT, TIME_SIGNATURE_SIZE)
+        );
+        for string_count in 3..=10 {
+            let (offset_y, size) = time_signature_layout(string_count);
+            // the middle of the stacked digits matches the middle of the staff
+            let middle = offset_y + STRING_LINE_HEIGHT * 1.5;
+            assert_eq!(middle, staff_height(string_count) / 2.0);
+            assert!((10.0..=TIME_SIGNATURE_SIZE).contains(&size));
+        }
+    }
+
+    #[test]
+    fn repeat_dots_between_string_lines() {
+        for string_count in 3..=10 {
+            let staff_height = staff_height(string_count);
+            let (top, bottom) = repeat_dots_offsets(string_count);
+            assert!(top > 0.0 && bottom < staff_height, "{string_count} strings");
+            for dot in [top, bottom] {
+                let line_position = dot / STRING_LINE_HEIGHT;
+                assert_eq!(
+                    line_position.fract(),
+                    0.5,
+                    "dot on a line for {string_count} strings"
+                );
+            }
+        }
+    }
+
+    fn tremolo_bar(points: &[(u8, i8)]) -> TremoloBarEffect {
+        TremoloBarEffect {
+            points: points
+                .iter()
+                .map(|&(position, value)| BendPoint { position, value })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn tremolo_bar_dip_goes_down() {
+        let dip = tremolo_bar(&[(0, 0), (6, -2), (12, 0)]);
+        assert_eq!(
+            tremolo_bar_curve(&dip, 24.0),
+            vec![(0.0, 0.0), (12.0, TREMOLO_BAR_AMPLITUDE), (24.0, 0.0)]
+        );
+        assert_eq!(tremolo_bar_depth(&dip), -2);
+    }
+
+    #[test]
+    fn tremolo_bar_pull_goes_up() {
+        let pull = tremolo_bar(&[(0, 0), (3, 2), (6, -1), (12, 0)]);
+        let curve = tremolo_bar_curve(&pull, 12.0);
+        assert_eq!(curve[1], (3.0, -TREMOLO_BAR_AMPLITUDE));
+        assert_eq!(curve[2], (6.0, TREMOLO_BAR_AMPLITUDE / 2.0));
+        assert_eq!(tremolo_bar_depth(&pull), 2);
+    }
+
+    #[test]
+    fn tremolo_bar_small_values_not_exaggerated() {
+        // a flat curve stays on the baseline
+        let flat = tremolo_bar(&[(0, 0), (12, 0)]);
+        assert!(
+            tremolo_bar_curve(&flat, 12.0)
+                .iter()
+                .all(|(_, y)| *y == 0.0)
+        );
+        assert_eq!(tremolo_bar_depth(&flat), 0);
+    }
+
+    #[test]
+    fn tremolo_bar_area_only_for_tracks_using_it() {
+        let song = parse_gp_file("test-files/Demo v5.gp5").unwrap();
+        assert!(song.tracks.iter().any(has_tremolo_bar));
+        assert!(!song.tracks.iter().all(has_tremolo_bar));
+    }
+
+    #[test]
+    fn notes_fit_on_staff_for_all_files() {
+        for entry in std::fs::read_dir("test-files").unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                continue;
+            }
+            let song = parse_gp_file(path.to_str().unwrap()).unwrap();
+            for track in &song.tracks {
+                let string_count = track.strings.len();
+                assert!((3..=10).contains(&string_count), "{path:?}");
+                for measure in &track.measures {
+                    for beat in measure.voices.iter().flat_map(|v| &v.beats) {
+                        for note in &beat.notes {
+                            assert!(
+                                note.string >= 1 && note.string as usize <= string_count,
+                                "{path:?} track {}",
+                                track.name
+                            );
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn note_slot_under_the_cursor() {
+        let first_note_x = MEASURE_NOTES_PADDING + 3.0;
+        let slot = |x: f32, y: f32| note_slot_at(Point::new(x, y), 0.0, BEAT_LENGTH, 4, 6);
+        assert_eq!(slot(first_note_x, FIRST_STRING_Y), Some((0, 1)));
+        assert_eq!(
+            slot(
+                first_note_x + BEAT_LENGTH * 2.0 + 5.0,
+                FIRST_STRING_Y + 27.0
+            ),
+            Some((2, 3))
+        );
+        assert_eq!(slot(first_note_x + BEAT_LENGTH * 4.0, FIRST_STRING_Y), None);
+        assert_eq!(
+            slot(first_note_x, FIRST_STRING_Y + STRING_LINE_HEIGHT * 6.0),
+            None
+        );
+        assert_eq!(slot(first_note_x, MEASURE_ANNOTATION_Y), None);
+        assert_eq!(slot(0.0, FIRST_STRING_Y), None);
+    }
+
+    #[test]
+    fn notes_labeled_with_frets_or_pitches() {
+        let song = parse_gp_file("test-files/Demo v5.gp5").unwrap();
+        let track = &song.tracks[0];
+        let beat = &track.measures[0].voices[0].beats[0];
+        let label = |string: i8, pitch_track: Option<&Track>| {
+            let note = beat
+                .notes
+                .iter()
+                .find(|note| note.string == string)
+                .unwrap();
+            note_value(note, pitch_track)
+        };
+        assert_eq!(label(3, None), "5");
+        // fret 5 on the G3 string is middle C
+        assert_eq!(label(3, Some(track)), "C4");
+        assert_eq!(label(5, Some(track)), "C3");
+
+        let mut ghost = beat.notes[0].clone();
+        ghost.effect.ghost_note = true;
+        ghost.string = 1;
+        ghost.value = 4;
+        assert_eq!(note_value(&ghost, Some(track)), "(G#4)");
+        ghost.kind = NoteType::Dead;
+        assert_eq!(note_value(&ghost, Some(track)), "x");
+    }
+
+    #[test]
+    fn note_names_fit_sixteenth_notes() {
+        let song = Arc::new(parse_gp_file("test-files/canon_rock.gp4").unwrap());
+        let track = &song.tracks[0];
+        // sixteen sixteenth notes
+        let beats = &track.measures[96].voices[0].beats;
+        assert_eq!(beats.len(), 16);
+        let mut measure = CanvasMeasure::new(96, 0, song.clone(), false, false, false, true);
+        let fret_len = measure.measure_len;
+        measure.set_note_names(true);
+        assert!(measure.measure_len > fret_len);
+        assert_eq!(
+            measure.total_measure_len - measure.measure_len,
+            MEASURE_NOTES_PADDING * 2.0
+        );
+
+        // the longest label and the annotation after it end before the labels of the next beat
+        let width_per_beat = measure.measure_len / beats.len() as f32;
+        let longest = label_width("(G#4)", NOTE_NAME_LABEL_SIZE);
+        for beat in beats {
+            for note in &beat.notes {
+                let width = label_width(&note_value(note, Some(track)), NOTE_NAME_LABEL_SIZE);
+                let annotation_end = annotation_offset(width_per_beat, width, 10.0) + 10.0;
+                assert!(annotation_end <= width_per_beat - longest / 2.0);
+            }
+        }
+    }
+
+    #[test]
+    fn pick_strokes_annotated_above_beats() {
+        assert_eq!(
+            pick_stroke_annotation(&BeatStrokeDirection::Down),
+            Some(DOWN_STROKE)
+        );
+        assert_eq!(
+            pick_stroke_annotation(&BeatStrokeDirection::Up),
+            Some(UP_STROKE)
+        );
+        assert_eq!(pick_stroke_annotation(&BeatStrokeDirection::None), None);
+    }
+
+    #[test]
+    fn dynamic_markings_match_gp_dynamics() {
+        for (level, marking) in (1..=8).zip(DYNAMIC_MARKINGS) {
+            assert_eq!(dynamic_marking(convert_velocity(level)), marking);
+        }
+        assert_eq!(dynamic_marking(DEFAULT_VELOCITY), "f");
+        // out of range velocities stick to the extremes
+        assert_eq!(dynamic_marking(0), "ppp");
+        assert_eq!(dynamic_marking(127), "fff");
+    }
+
+    #[test]
+    fn dynamics_displayed_when_they_change() {
+        let song = Arc::new(parse_gp_file("test-files/Demo v5.gp5").unwrap());
+        let track = &song.tracks[1];
+        let entry_dynamics = measure_entry_dynamics(track);
+        assert_eq!(entry_dynamics[0], None);
+        assert_eq!(entry_dynamics[22], Some("f"));
+        assert_eq!(entry_dynamics[23], Some("fff"));
+
+        let displayed = |measure_id: usize, show_dynamics: bool| {
+            let mut measure = CanvasMeasure::new(
+                measure_id,
+                1,
+                song.clone(),
+                false,
+                false,
+                false,
+                show_dynamics,
+            );
+            measure.set_entry_dynamic(entry_dynamics[measure_id]);
+            measure.displayed_dynamics()
+        };
+        // the accent is marked once, then the return to forte
+        let accent = displayed(22, true);
+        assert_eq!(accent.iter().flatten().collect::<Vec<_>>(), vec![&"fff"]);
+        let back = displayed(23, true);
+        assert_eq!(back.iter().flatten().next(), Some(&"f"));
+        // unchanged dynamics are not repeated
+        assert!(displayed(30, true).iter().all(Option::is_none));
+        assert!(displayed(22, false).iter().all(Option::is_none));
+    }
+}
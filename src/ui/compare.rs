@@ -0,0 +1,112 @@
+use crate::audio::playback_order::compute_playback_order;
+use crate::parser::song_parser::Song;
+use crate::ui::application::Message;
+use crate::ui::tablature::Tablature;
+use iced::advanced::text::Shaping::Auto;
+use iced::widget::{Id, Text, button, column, container, row, text};
+use iced::{Alignment, Element, Length};
+use std::sync::Arc;
+
+/// Another transcription of the open song displayed next to it.
+/// It is not played, its focus follows the measure index of the open song.
+pub struct Comparison {
+    file_name: String,
+    pub tablature: Tablature,
+    pub container_id: Id,
+}
+
+impl Comparison {
+    /// Displays the same track as the open song if the file has it, the first one otherwise.
+    pub fn new(song: Song, file_name: String, track_id: usize) -> Self {
+        let track_id = if track_id < song.tracks.len() {
+            track_id
+        } else {
+            0
+        };
+        let playback_order = compute_playback_order(&song.measure_headers);
+        let tablature = Tablature::new(
+            Arc::new(song),
+            track_id,
+            Id::new("compare-scroll-elements"),
+            &playback_order,
+        );
+        Self {
+            file_name,
+            tablature,
+            container_id: Id::new("compare-outer-container"),
+        }
+    }
+
+    /// Focus the measure at the same index as the open song, the last one if the file is shorter.
+    /// Returns the scroll offset bringing it into view if the focus moved.
+    pub fn sync_to_measure(&mut self, measure_id: usize) -> Option<f32> {
+        let measure_id = measure_id.min(self.tablature.measure_count().checked_sub(1)?);
+        if measure_id == self.tablature.focused_measure() {
+            return None;
+        }
+        self.tablature.focus_on_measure(measure_id);
+        self.tablature.scroll_to_measure(measure_id)
+    }
+
+    /// Shows the same track as the open song if the file has it.
+    pub fn select_track(&mut self, track_id: usize) {
+        if track_id < self.tablature.song.tracks.len() {
+            self.tablature.update_track(track_id);
+        }
+    }
+
+    pub fn view(&self) -> Element<'_, Message> {
+        let header = row![
+            Text::new(&self.file_name).size(12).shaping(Auto),
+            button(text("Close").size(12))
+                .style(button::secondary)
+                .padding([2, 8])
+                .on_press(Message::CloseCompare),
+        ]
+        .spacing(10)
+        .align_y(Alignment::Center);
+        let tablature =
+            container(self.tablature.view().map(comparison_message)).id(self.container_id.clone());
+        column![header, tablature]
+            .spacing(5)
+            .width(Length::Fill)
+            .into()
+    }
+}
+
+/// Interactions with the compared tab: clicking a measure focuses it in both tabs,
+/// its scrolling is its own.
+fn comparison_message(message: Message) -> Message {
+    match message {
+        Message::TablatureScrolled(offset, max_offset) => {
+            Message::CompareScrolled(offset, max_offset)
+        }
+        Message::SelectSection(measure_id) => Message::FocusMeasure(measure_id),
+        Message::OpenNoteMenu(location) | Message::SelectNoteSlot(location) => {
+            Message::FocusMeasure(location.measure)
+        }
+        message => message,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::song_parser_tests::parse_gp_file;
+
+    #[test]
+    fn focus_follows_the_measure_index() {
+        let song = parse_gp_file("test-files/canon_rock.gp4").unwrap();
+        let measure_count = song.measure_headers.len();
+        // the open song has no such track
+        let mut comparison = Comparison::new(song, "canon_rock.gp4".to_string(), 99);
+        assert_eq!(comparison.tablature.track_id, 0);
+
+        comparison.sync_to_measure(3);
+        assert_eq!(comparison.tablature.focused_measure(), 3);
+        // a longer song focuses the last measure of the shorter one
+        comparison.sync_to_measure(measure_count + 10);
+        assert_eq!(comparison.tablature.focused_measure(), measure_count - 1);
+        assert_eq!(comparison.sync_to_measure(measure_count + 11), None);
+    }
+}
@@ -0,0 +1,233 @@
+use crate::audio::midi_player::{RENDER_AHEAD_MS, StreamInfo, audio_backends};
+use crate::ui::application::Message;
+use iced::widget::{Column, button, checkbox, column, container, pick_list, row, text};
+use iced::{Alignment, Border, Element, Length};
+use std::fmt::Display;
+use std::path::Path;
+
+/// Audio backend picked in the diagnostics panel, the platform default when unset.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AudioBackendSelection(Option<String>);
+
+impl AudioBackendSelection {
+    pub const fn new(backend: Option<String>) -> Self {
+        Self(backend)
+    }
+
+    pub fn into_inner(self) -> Option<String> {
+        self.0
+    }
+
+    /// Platform default followed by the backends available in this build.
+    fn options() -> Vec<Self> {
+        std::iter::once(Self(None))
+            .chain(
+                audio_backends()
+                    .into_iter()
+                    .map(|name| Self(Some(name.to_string()))),
+            )
+            .collect()
+    }
+}
+
+impl Display for AudioBackendSelection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.0 {
+            Some(name) => write!(f, "{name}"),
+            None => write!(f, "Default"),
+        }
+    }
+}
+
+/// State of the application worth attaching to a bug report.
+pub struct Diagnostics<'a> {
+    pub backend: AudioBackendSelection,
+    pub stream_info: Option<StreamInfo>,
+    pub mono_output: bool,
+    pub sound_font: Option<&'a Path>, // embedded sound font when unset
+    pub preset_count: Option<usize>,  // known once a song is loaded
+    pub file: Option<String>,         // loaded file and its format
+    pub recent_errors: &'a [String],  // oldest first
+}
+
+impl Diagnostics<'_> {
+    /// Sections of the report: title and lines.
+    fn sections(&self) -> Vec<(&'static str, Vec<String>)> {
+        let sound_font = self.sound_font.map_or_else(
+            || "embedded TimGM6mb".to_string(),
+            |path| path.display().to_string(),
+        );
+        let presets = self
+            .preset_count
+            .map_or_else(String::new, |count| format!(" ({count} presets)"));
+        let errors = if self.recent_errors.is_empty() {
+            vec!["none".to_string()]
+        } else {
+            self.recent_errors.to_vec()
+        };
+        vec![
+            (
+                "Application",
+                vec![
+                    format!("Version: {}", env!("CARGO_PKG_VERSION")),
+                    format!(
+                        "Platform: {} {}",
+                        std::env::consts::OS,
+                        std::env::consts::ARCH
+                    ),
+                    format!("File: {}", self.file.as_deref().unwrap_or("none")),
+                ],
+            ),
+            (
+                "Audio",
+                std::iter::once(format!("Selected backend: {}", self.backend))
+                    .chain(stream_lines(self.stream_info.as_ref()))
+                    .chain([
+                        format!("Sound font: {sound_font}{presets}"),
+                        format!(
+                            "Output: {}",
+                            if self.mono_output { "mono" } else { "stereo" }
+                        ),
+                    ])
+                    .collect(),
+            ),
+            ("Recent errors", errors),
+        ]
+    }
+
+    /// Plain text report to paste in a bug report.
+    pub fn report(&self) -> String {
+        self.sections()
+            .into_iter()
+            .map(|(title, lines)| format!("{title}\n  {}", lines.join("\n  ")))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// Lines describing the output stream, the stream is only opened on the first play.
+fn stream_lines(stream_info: Option<&StreamInfo>) -> Vec<String> {
+    let Some(info) = stream_info else {
+        return vec!["Audio output opened on the first play".to_string()];
+    };
+    let buffer = match (info.buffer_frames, info.buffer_ms()) {
+        (Some(frames), Some(ms)) => format!("{frames} frames ({ms:.1} ms)"),
+        _ => "unknown until audio is requested".to_string(),
+    };
+    vec![
+        format!("Backend: {}", info.backend),
+        format!("Device: {}", info.device),
+        format!(
+            "Sample rate: {} Hz, {} channels",
+            info.sample_rate, info.channels
+        ),
+        format!("Device buffer: {buffer}"),
+        format!("Rendered ahead: {RENDER_AHEAD_MS} ms"),
+    ]
+}
+
+/// Diagnostics panel: audio backend selection and the report to copy for bug reports.
+pub fn diagnostics_view<'a>(diagnostics: &Diagnostics) -> Element<'a, Message> {
+    let header = text("Diagnostics").size(18);
+
+    let backend_picker = row![
+        text("Audio backend").size(14),
+        pick_list(
+            AudioBackendSelection::options(),
+            Some(diagnostics.backend.clone()),
+            Message::AudioBackendSelected
+        )
+        .text_size(14)
+        .padding([5, 10]),
+    ]
+    .spacing(10)
+    .align_y(Alignment::Center);
+    let hint = text("Changing the backend stops the playback").size(12);
+    let mono_output = checkbox(diagnostics.mono_output)
+        .label("Mono output, for a single speaker")
+        .text_size(14)
+        .on_toggle(Message::ToggleMonoOutput);
+
+    let report = diagnostics.sections().into_iter().fold(
+        Column::new().spacing(5),
+        |column, (title, lines)| {
+            let column = column.push(text(title).size(16));
+            lines
+                .into_iter()
+                .fold(column, |column, line| column.push(text(line).size(14)))
+        },
+    );
+
+    let copy = button(text("Copy report").size(12)).on_press(Message::CopyDiagnostics);
+    let close = button(text("Close").size(12))
+        .style(button::secondary)
+        .on_press(Message::CloseDiagnostics);
+
+    let content = column![
+        header,
+        backend_picker,
+        hint,
+        mono_output,
+        report,
+        row![copy, close].spacing(10)
+    ]
+    .spacing(10)
+    .width(Length::Shrink);
+
+    container(content)
+        .padding(20)
+        .style(|theme: &iced::Theme| container::Style {
+            background: Some(theme.palette().background.into()),
+            border: Border::default()
+                .color(crate::ui::utils::COLOR_GRAY)
+                .width(1),
+            ..Default::default()
+        })
+        .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stream_details() {
+        assert_eq!(stream_lines(None).len(), 1);
+        let info = StreamInfo {
+            backend: "JACK",
+            device: "system".to_string(),
+            sample_rate: 48000,
+            channels: 2,
+            buffer_frames: Some(256),
+        };
+        let lines = stream_lines(Some(&info));
+        assert_eq!(lines[0], "Backend: JACK");
+        assert_eq!(lines[3], "Device buffer: 256 frames (5.3 ms)");
+        assert_eq!(AudioBackendSelection::new(None).to_string(), "Default");
+    }
+
+    #[test]
+    fn report_for_bug_reports() {
+        let errors = vec!["Failed to create audio stream: busy".to_string()];
+        let diagnostics = Diagnostics {
+            backend: AudioBackendSelection::new(Some("ALSA".to_string())),
+            stream_info: None,
+            mono_output: false,
+            sound_font: None,
+            preset_count: Some(235),
+            file: Some("Demo v5.gp5 (GP5)".to_string()),
+            recent_errors: &errors,
+        };
+        let report = diagnostics.report();
+        assert!(report.starts_with("Application\n  Version: "));
+        assert!(report.contains("\n  File: Demo v5.gp5 (GP5)\n"));
+        assert!(
+            report
+                .contains("\n  Selected backend: ALSA\n  Audio output opened on the first play\n")
+        );
+        assert!(
+            report.contains("\n  Sound font: embedded TimGM6mb (235 presets)\n  Output: stereo\n")
+        );
+        assert!(report.ends_with("Recent errors\n  Failed to create audio stream: busy"));
+    }
+}
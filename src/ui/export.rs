@@ -0,0 +1,80 @@
+use crate::audio::midi_export::MidiExportOptions;
+use crate::export::NotationFormat;
+use crate::ui::application::Message;
+use iced::widget::{button, checkbox, column, container, row, text};
+use iced::{Border, Element, Length};
+
+/// Export panel: MIDI file options and save button, notation and image exports.
+pub fn export_view<'a>(options: MidiExportOptions) -> Element<'a, Message> {
+    let header = text("Export").size(18);
+    let expand = checkbox(options.expand_repeats)
+        .label("Expand repeats and jumps to match the playback")
+        .text_size(14)
+        .on_toggle(Message::ToggleExpandRepeats);
+    let cues = checkbox(options.beat_text_cues)
+        .label("Beat texts as cue points")
+        .text_size(14)
+        .on_toggle(Message::ToggleBeatTextCues);
+    let stems = checkbox(options.stems)
+        .label("One file per track (stems)")
+        .text_size(14)
+        .on_toggle(Message::ToggleStems);
+    let hint = text("All tracks are exported, including the muted ones").size(12);
+
+    let save_label = if options.stems {
+        "Save MIDI files to a folder"
+    } else {
+        "Save MIDI file"
+    };
+    let save = button(text(save_label).size(12)).on_press(Message::ExportMidi);
+    let notation = [
+        NotationFormat::LilyPond,
+        NotationFormat::Abc,
+        NotationFormat::VexTab,
+    ]
+    .map(|format| {
+        row![
+            text(format.name()).size(14).width(120),
+            button(text("Selected track").size(12))
+                .on_press(Message::ExportNotation(format, false)),
+            button(text("All tracks").size(12)).on_press(Message::ExportNotation(format, true)),
+        ]
+        .spacing(10)
+        .align_y(iced::Alignment::Center)
+        .into()
+    });
+    let images = row![
+        text("PNG image").size(14).width(120),
+        button(text("Copy focused measure").size(12)).on_press(Message::CopyMeasureImage),
+        button(text("Save its row").size(12)).on_press(Message::SaveRowImage),
+    ]
+    .spacing(10)
+    .align_y(iced::Alignment::Center);
+    let close = button(text("Close").size(12))
+        .style(button::secondary)
+        .on_press(Message::CloseExport);
+
+    let content = column![
+        header,
+        expand,
+        cues,
+        stems,
+        hint,
+        row![save, close].spacing(10),
+        column(notation).spacing(5),
+        images,
+    ]
+    .spacing(10)
+    .width(Length::Shrink);
+
+    container(content)
+        .padding(20)
+        .style(|theme: &iced::Theme| container::Style {
+            background: Some(theme.palette().background.into()),
+            border: Border::default()
+                .color(crate::ui::utils::COLOR_GRAY)
+                .width(1),
+            ..Default::default()
+        })
+        .into()
+}
@@ -0,0 +1,111 @@
+use crate::ui::application::Message;
+use iced::futures::Stream;
+use iced::stream;
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// File displayed by the application and the modification time of the displayed version.
+#[derive(Debug, Clone)]
+pub struct WatchedFile {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+}
+
+impl WatchedFile {
+    pub fn new(path: PathBuf) -> Self {
+        let modified = modified_time(&path);
+        Self { path, modified }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Whether another version of the file is on disk, a deleted file has none to offer.
+    pub fn changed_on_disk(&self) -> bool {
+        modified_time(&self.path).is_some_and(|modified| Some(modified) != self.modified)
+    }
+
+    /// Take the version on disk as the displayed one, its changes are not offered again.
+    pub fn acknowledge(&mut self) {
+        self.modified = modified_time(&self.path);
+    }
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// Whether the event writes the file, editors often save through a new file renamed over it.
+fn is_write_of(event: &Event, file: &Path) -> bool {
+    matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_))
+        && event.paths.iter().any(|path| path == file)
+}
+
+/// Notify the writes of the file, the folder is watched to follow renames over the file.
+pub fn file_watch_subscription(file: &Path) -> impl Stream<Item = Message> + use<> {
+    let file = file.to_path_buf();
+    stream::channel(4, async move |mut output| {
+        let Some(folder) = file.parent().map(Path::to_path_buf) else {
+            return;
+        };
+        let watched_file = file.clone();
+        let watcher = notify::recommended_watcher(move |result: notify::Result<Event>| {
+            match result {
+                Ok(event) if is_write_of(&event, &watched_file) => {
+                    // a full channel already holds a change to look at
+                    let _ = output.try_send(Message::FileChangedOnDisk);
+                }
+                Ok(_) => {}
+                Err(err) => log::warn!("File watch error: {err}"),
+            }
+        });
+        let mut watcher = match watcher {
+            Ok(watcher) => watcher,
+            Err(err) => {
+                log::warn!("Could not watch {}: {err}", file.display());
+                return;
+            }
+        };
+        if let Err(err) = watcher.watch(&folder, RecursiveMode::NonRecursive) {
+            log::warn!("Could not watch {}: {err}", folder.display());
+            return;
+        }
+        // keep watching as long as the subscription runs
+        std::future::pending::<()>().await;
+        drop(watcher);
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use notify::event::{CreateKind, ModifyKind, RemoveKind};
+
+    #[test]
+    fn writes_of_the_watched_file() {
+        let file = Path::new("/tabs/song.gp5");
+        let event = |kind, path: &str| Event::new(kind).add_path(PathBuf::from(path));
+        assert!(is_write_of(
+            &event(EventKind::Modify(ModifyKind::Any), "/tabs/song.gp5"),
+            file
+        ));
+        assert!(is_write_of(
+            &event(EventKind::Create(CreateKind::File), "/tabs/song.gp5"),
+            file
+        ));
+        assert!(!is_write_of(
+            &event(EventKind::Modify(ModifyKind::Any), "/tabs/other.gp5"),
+            file
+        ));
+        assert!(!is_write_of(
+            &event(EventKind::Remove(RemoveKind::File), "/tabs/song.gp5"),
+            file
+        ));
+
+        // a missing file has no new version
+        let missing = WatchedFile::new(PathBuf::from("/nonexistent/song.gp5"));
+        assert!(!missing.changed_on_disk());
+    }
+}
@@ -0,0 +1,229 @@
+use crate::parser::song_parser::{Beat, NoteType, Track};
+use crate::ui::application::Message;
+use crate::ui::utils::catch_panic;
+use iced::advanced::mouse;
+use iced::alignment::Vertical;
+use iced::widget::canvas::{self, Frame, Geometry, Path, Stroke, Text};
+use iced::widget::text::Alignment;
+use iced::{Color, Element, Length, Point, Rectangle, Renderer, Theme};
+
+// Distance between strings
+const STRING_SPACING: f32 = 14.0;
+
+// Space above the first string and below the last one (fret numbers)
+const VERTICAL_PADDING: f32 = 12.0;
+
+// Space left of the nut for the open strings
+const OPEN_STRING_WIDTH: f32 = 28.0;
+
+// Fewest frets shown, even on instruments declaring less
+const MIN_FRET_COUNT: u8 = 12;
+
+const NOTE_RADIUS: f32 = 6.0;
+
+// Frets marked with a dot, the octaves with two
+const INLAY_FRETS: [u8; 12] = [3, 5, 7, 9, 12, 15, 17, 19, 21, 24, 27, 29];
+
+/// Fretboard of a track lighting up the notes of the focused beat.
+pub struct Fretboard {
+    string_count: usize,
+    fret_count: u8,
+    notes: Vec<(i8, u8)>, // string & fret of the sounding notes
+    color: Color,
+}
+
+impl Fretboard {
+    pub fn new(track: &Track, beat: Option<&Beat>, color: Color) -> Self {
+        let notes = beat.map(sounding_notes).unwrap_or_default();
+        let highest_fret = notes.iter().map(|(_, fret)| *fret).max().unwrap_or(0);
+        Self {
+            string_count: track.strings.len(),
+            fret_count: track.fret_count.max(MIN_FRET_COUNT).max(highest_fret),
+            notes,
+            color,
+        }
+    }
+
+    pub fn view(self) -> Element<'static, Message> {
+        let height =
+            STRING_SPACING * self.string_count.saturating_sub(1) as f32 + VERTICAL_PADDING * 2.0;
+        canvas::Canvas::new(self)
+            .width(Length::Fill)
+            .height(height)
+            .into()
+    }
+
+    fn fret_width(&self, width: f32) -> f32 {
+        (width - OPEN_STRING_WIDTH) / f32::from(self.fret_count)
+    }
+}
+
+/// String & fret of the notes played or held by the beat, rests and dead notes are silent.
+fn sounding_notes(beat: &Beat) -> Vec<(i8, u8)> {
+    if !beat.has_notes() {
+        return Vec::new();
+    }
+    beat.notes
+        .iter()
+        .filter(|note| matches!(note.kind, NoteType::Normal | NoteType::Tie))
+        .filter_map(|note| Some((note.string, u8::try_from(note.value).ok()?)))
+        .collect()
+}
+
+/// Horizontal center of a fret, open strings left of the nut.
+fn fret_center_x(fret: u8, fret_width: f32) -> f32 {
+    if fret == 0 {
+        OPEN_STRING_WIDTH / 2.0
+    } else {
+        OPEN_STRING_WIDTH + (f32::from(fret) - 0.5) * fret_width
+    }
+}
+
+fn string_y(string: i8) -> f32 {
+    VERTICAL_PADDING + f32::from(string - 1) * STRING_SPACING
+}
+
+impl canvas::Program<Message> for Fretboard {
+    type State = ();
+
+    fn draw(
+        &self,
+        _state: &Self::State,
+        renderer: &Renderer,
+        _theme: &Theme,
+        bounds: Rectangle,
+        _cursor: mouse::Cursor,
+    ) -> Vec<Geometry> {
+        catch_panic("fretboard drawing", Vec::new, || {
+            self.draw_fretboard(renderer, bounds)
+        })
+    }
+}
+
+impl Fretboard {
+    fn draw_fretboard(&self, renderer: &Renderer, bounds: Rectangle) -> Vec<Geometry> {
+        let mut frame = Frame::new(renderer, bounds.size());
+        let color_gray = crate::ui::utils::COLOR_GRAY;
+        let fret_width = self.fret_width(frame.width());
+        let first_y = string_y(1);
+        let last_y = string_y(self.string_count as i8);
+        let middle_y = f32::midpoint(first_y, last_y);
+
+        // inlays first, below the strings
+        for fret in INLAY_FRETS.into_iter().filter(|f| *f <= self.fret_count) {
+            let x = fret_center_x(fret, fret_width);
+            let dots: &[f32] = if fret % 12 == 0 {
+                &[middle_y - STRING_SPACING, middle_y + STRING_SPACING]
+            } else {
+                &[middle_y]
+            };
+            for y in dots {
+                frame.fill(&Path::circle(Point::new(x, *y), 3.0), color_gray);
+            }
+            frame.fill_text(Text {
+                content: fret.to_string(),
+                color: color_gray,
+                size: 10.0.into(),
+                position: Point::new(x, last_y + VERTICAL_PADDING / 2.0),
+                align_x: Alignment::Center,
+                align_y: Vertical::Center,
+                ..Text::default()
+            });
+        }
+
+        // nut and fret wires
+        let nut = Path::line(
+            Point::new(OPEN_STRING_WIDTH, first_y),
+            Point::new(OPEN_STRING_WIDTH, last_y),
+        );
+        frame.stroke(
+            &nut,
+            Stroke::default().with_width(3.0).with_color(Color::WHITE),
+        );
+        for fret in 1..=self.fret_count {
+            let x = OPEN_STRING_WIDTH + f32::from(fret) * fret_width;
+            let wire = Path::line(Point::new(x, first_y), Point::new(x, last_y));
+            frame.stroke(
+                &wire,
+                Stroke::default().with_width(1.0).with_color(color_gray),
+            );
+        }
+
+        // strings, the lowest ones thicker
+        for string in 1..=self.string_count {
+            let y = string_y(string as i8);
+            let line = Path::line(Point::new(0.0, y), Point::new(frame.width(), y));
+            let width = 1.0 + string as f32 / self.string_count as f32;
+            frame.stroke(
+                &line,
+                Stroke::default().with_width(width).with_color(Color::WHITE),
+            );
+        }
+
+        // sounding notes on top
+        for (string, fret) in &self.notes {
+            if *string < 1 || *string as usize > self.string_count {
+                continue;
+            }
+            let center = Point::new(fret_center_x(*fret, fret_width), string_y(*string));
+            frame.fill(&Path::circle(center, NOTE_RADIUS), self.color);
+            frame.fill_text(Text {
+                content: fret.to_string(),
+                color: Color::BLACK,
+                size: 9.0.into(),
+                position: center,
+                align_x: Alignment::Center,
+                align_y: Vertical::Center,
+                ..Text::default()
+            });
+        }
+
+        vec![frame.into_geometry()]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::song_parser::{Note, NoteEffect};
+
+    fn note(string: i8, value: i16, kind: NoteType) -> Note {
+        let mut note = Note::new(NoteEffect::default());
+        note.value = value;
+        note.string = string;
+        note.kind = kind;
+        note
+    }
+
+    #[test]
+    fn only_played_and_held_notes_sound() {
+        let beat = Beat {
+            notes: vec![
+                note(1, 3, NoteType::Normal),
+                note(2, 5, NoteType::Tie),
+                note(3, 7, NoteType::Dead),
+                note(4, 0, NoteType::Normal),
+            ],
+            ..Beat::default()
+        };
+        assert_eq!(sounding_notes(&beat), vec![(1, 3), (2, 5), (4, 0)]);
+        let empty = Beat {
+            empty: true,
+            ..beat
+        };
+        assert!(sounding_notes(&empty).is_empty());
+    }
+
+    #[test]
+    fn frets_between_the_wires() {
+        assert_eq!(fret_center_x(0, 20.0), OPEN_STRING_WIDTH / 2.0);
+        assert_eq!(fret_center_x(1, 20.0), OPEN_STRING_WIDTH + 10.0);
+        assert_eq!(fret_center_x(12, 20.0), OPEN_STRING_WIDTH + 230.0);
+        let track = Track {
+            fret_count: 24,
+            ..Track::default()
+        };
+        let fretboard = Fretboard::new(&track, None, Color::WHITE);
+        assert_eq!(fretboard.fret_width(OPEN_STRING_WIDTH + 480.0), 20.0);
+    }
+}
@@ -0,0 +1,122 @@
+use crate::ui::application::Message;
+use global_hotkey::hotkey::{Code, HotKey, Modifiers};
+use global_hotkey::{GlobalHotKeyEvent, GlobalHotKeyManager, HotKeyState};
+use iced::futures::Stream;
+use iced::stream;
+
+/// Keys grabbed system wide, active while the window is unfocused.
+fn hotkeys() -> [(HotKey, Message); 11] {
+    let shortcut = Some(Modifiers::CONTROL | Modifiers::ALT);
+    [
+        // media keys (X11 reports play/pause as play)
+        (HotKey::new(None, Code::MediaPlayPause), Message::PlayPause),
+        (HotKey::new(None, Code::MediaPlay), Message::PlayPause),
+        (HotKey::new(None, Code::MediaPause), Message::PlayPause),
+        (HotKey::new(None, Code::MediaStop), Message::StopPlayer),
+        (
+            HotKey::new(None, Code::MediaTrackNext),
+            Message::NextMeasure,
+        ),
+        (
+            HotKey::new(None, Code::MediaTrackPrevious),
+            Message::PreviousMeasure,
+        ),
+        // same shortcuts as in the window with Ctrl+Alt
+        (HotKey::new(shortcut, Code::Space), Message::PlayPause),
+        (
+            HotKey::new(shortcut, Code::ArrowRight),
+            Message::NextMeasure,
+        ),
+        (
+            HotKey::new(shortcut, Code::ArrowLeft),
+            Message::PreviousMeasure,
+        ),
+        (HotKey::new(shortcut, Code::ArrowUp), Message::IncreaseTempo),
+        (
+            HotKey::new(shortcut, Code::ArrowDown),
+            Message::DecreaseTempo,
+        ),
+    ]
+}
+
+fn message_for(event: GlobalHotKeyEvent) -> Option<Message> {
+    if event.state != HotKeyState::Pressed {
+        return None;
+    }
+    hotkeys()
+        .into_iter()
+        .find(|(hotkey, _)| hotkey.id() == event.id)
+        .map(|(_, message)| message)
+}
+
+/// Registered hotkeys, released on drop.
+pub struct GlobalHotkeys {
+    _manager: GlobalHotKeyManager,
+}
+
+impl GlobalHotkeys {
+    /// Must be called on the main thread.
+    /// Keys already grabbed by another application are skipped.
+    pub fn register() -> Result<Self, global_hotkey::Error> {
+        let manager = GlobalHotKeyManager::new()?;
+        for (hotkey, _) in hotkeys() {
+            if let Err(err) = manager.register(hotkey) {
+                log::warn!("Could not register global hotkey {hotkey}: {err}");
+            }
+        }
+        Ok(Self { _manager: manager })
+    }
+}
+
+/// Forward the hotkey events, only received while hotkeys are registered.
+pub fn global_hotkey_subscription() -> impl Stream<Item = Message> {
+    stream::channel(16, async |mut output| {
+        let spawned = std::thread::Builder::new()
+            .name("global-hotkeys".to_string())
+            .spawn(move || {
+                while let Ok(event) = GlobalHotKeyEvent::receiver().recv() {
+                    if let Some(message) = message_for(event)
+                        && let Err(err) = output.try_send(message)
+                    {
+                        log::debug!("Global hotkey dropped: {err}");
+                    }
+                }
+            });
+        if let Err(err) = spawned {
+            log::warn!("Could not listen to global hotkeys: {err}");
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hotkeys_are_distinct() {
+        let mut ids: Vec<_> = hotkeys().iter().map(|(h, _)| h.id()).collect();
+        ids.sort_unstable();
+        ids.dedup();
+        assert_eq!(ids.len(), hotkeys().len());
+    }
+
+    #[test]
+    fn pressed_hotkeys_only() {
+        let id = HotKey::new(None, Code::MediaPlay).id();
+        let pressed = GlobalHotKeyEvent {
+            id,
+            state: HotKeyState::Pressed,
+        };
+        assert!(matches!(message_for(pressed), Some(Message::PlayPause)));
+        let released = GlobalHotKeyEvent {
+            id,
+            state: HotKeyState::Released,
+        };
+        assert!(message_for(released).is_none());
+        let unknown = GlobalHotKeyEvent {
+            id: HotKey::new(None, Code::KeyQ).id(),
+            state: HotKeyState::Pressed,
+        };
+        assert!(message_for(unknown).is_none());
+    }
+}
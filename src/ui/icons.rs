@@ -1,5 +1,6 @@
 //! Icons coming from <https://fontello.com/>
 
+use crate::analysis::instrument::InstrumentFamily;
 use iced::widget::text;
 use iced::{Element, Font};
 
@@ -11,6 +12,10 @@ pub fn solo_icon<'a, Message>() -> Element<'a, Message> {
     text('S').into()
 }
 
+pub fn backing_icon<'a, Message>() -> Element<'a, Message> {
+    text('B').into()
+}
+
 pub fn pause_icon<'a, Message>() -> Element<'a, Message> {
     icon('\u{0e802}')
 }
@@ -23,6 +28,19 @@ pub fn stop_icon<'a, Message>() -> Element<'a, Message> {
     icon('\u{0e801}')
 }
 
+/// Symbol of the instrument family of a track.
+pub fn family_icon<'a, Message>(family: InstrumentFamily) -> Element<'a, Message> {
+    icon(match family {
+        InstrumentFamily::Guitar => '\u{0e803}',
+        InstrumentFamily::Bass => '\u{0e804}',
+        InstrumentFamily::Drums => '\u{0e805}',
+        InstrumentFamily::Keys => '\u{0e806}',
+        InstrumentFamily::Vocals => '\u{0e807}',
+        InstrumentFamily::Orchestral => '\u{0e808}',
+        InstrumentFamily::Other => '\u{0e809}',
+    })
+}
+
 fn icon<'a, Message>(codepoint: char) -> Element<'a, Message> {
     const ICON_FONT: Font = Font::with_name("ruxguitar-icons");
 
@@ -0,0 +1,81 @@
+use crate::ui::application::Message;
+use iced::widget::{Column, button, container, row, scrollable, text};
+use iced::{Alignment, Border, Element, Length};
+
+const ISSUES_HEIGHT: f32 = 120.0;
+
+/// Identical warnings with their number of occurrences, in order of first occurrence.
+fn group_warnings(warnings: &[String]) -> Vec<(&str, usize)> {
+    let mut grouped: Vec<(&str, usize)> = Vec::new();
+    for warning in warnings {
+        match grouped.iter_mut().find(|(message, _)| *message == warning) {
+            Some((_, count)) => *count += 1,
+            None => grouped.push((warning, 1)),
+        }
+    }
+    grouped
+}
+
+/// Label of the button toggling the issues panel.
+pub fn issues_label(warnings: &[String]) -> String {
+    match warnings.len() {
+        1 => "1 issue".to_string(),
+        count => format!("{count} issues"),
+    }
+}
+
+/// Parsing warnings of the loaded file.
+pub fn issues_view(warnings: &[String]) -> Element<'_, Message> {
+    let close = button(text("Close").size(12))
+        .style(button::secondary)
+        .on_press(Message::ToggleIssues);
+    let header = row![
+        text(format!("{} while parsing the file", issues_label(warnings))).size(14),
+        close
+    ]
+    .spacing(10)
+    .align_y(Alignment::Center);
+
+    let entries = group_warnings(warnings)
+        .into_iter()
+        .map(|(message, count)| {
+            let label = if count > 1 {
+                format!("{message} (x{count})")
+            } else {
+                message.to_string()
+            };
+            text(label).size(12).into()
+        });
+    let list = scrollable(Column::with_children(entries).width(Length::Fill))
+        .height(Length::Fixed(ISSUES_HEIGHT));
+
+    container(Column::new().push(header).push(list).spacing(5))
+        .padding(10)
+        .width(Length::Fill)
+        .style(|_theme| container::Style {
+            border: Border::default()
+                .color(crate::ui::utils::COLOR_GRAY)
+                .width(1),
+            ..Default::default()
+        })
+        .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn identical_warnings_are_grouped() {
+        let warnings: Vec<String> = ["a", "b", "a", "c", "a"]
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            group_warnings(&warnings),
+            vec![("a", 3), ("b", 1), ("c", 1)]
+        );
+        assert_eq!(issues_label(&warnings), "5 issues");
+        assert_eq!(issues_label(&warnings[..1]), "1 issue");
+    }
+}
@@ -0,0 +1,170 @@
+use crate::parser::song_parser::Song;
+use crate::parser::{is_tab_file, open_tab_file};
+use crate::ui::application::Message;
+use crate::ui::canvas_measure::{CanvasMeasure, has_tremolo_bar};
+use iced::advanced::text::Shaping::Auto;
+use iced::widget::{
+    Column, Row, Text, button, column, container, mouse_area, row, scrollable, text,
+};
+use iced::{Border, Element, Length};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Measures of the first track displayed in the preview.
+const PREVIEW_MEASURES: usize = 2;
+
+const LIBRARY_LIST_WIDTH: f32 = 300.0;
+const LIBRARY_HEIGHT: f32 = 400.0;
+
+/// Tab files of the folder sorted by name, sub folders are not browsed.
+pub fn library_files(folder: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(folder) else {
+        return Vec::new();
+    };
+    let mut files: Vec<_> = entries
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| is_tab_file(path) && path.is_file())
+        .collect();
+    files.sort_by_key(|path| path.file_name().map(std::ffi::OsStr::to_ascii_lowercase));
+    files
+}
+
+/// Parses the file for its preview, `None` if it is not a valid tab file.
+pub fn read_preview(path: &Path) -> Option<Arc<Song>> {
+    open_tab_file(path)
+        .inspect_err(|err| log::debug!("No preview for {}: {err}", path.display()))
+        .ok()
+        .map(Arc::new)
+}
+
+/// First measures of the first track of a file, drawn like the tablature.
+pub struct LibraryPreview {
+    path: PathBuf,
+    measures: Vec<CanvasMeasure>, // empty if the file could not be parsed
+}
+
+impl LibraryPreview {
+    pub fn new(path: PathBuf, song: Option<Arc<Song>>) -> Self {
+        let measures = song
+            .filter(|song| !song.tracks.is_empty())
+            .map(|song| {
+                let track = &song.tracks[0];
+                let has_tremolo_bar_area = has_tremolo_bar(track);
+                let headers = &song.measure_headers;
+                let count = PREVIEW_MEASURES.min(track.measures.len());
+                (0..count)
+                    .map(|i| {
+                        let has_time_signature =
+                            i == 0 || headers[i].time_signature != headers[i - 1].time_signature;
+                        CanvasMeasure::new(
+                            i,
+                            0,
+                            song.clone(),
+                            false,
+                            has_time_signature,
+                            has_tremolo_bar_area,
+                            false,
+                        )
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        Self { path, measures }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+/// Library panel: the tab files of the tabs folder, hovering a file previews its first measures.
+pub fn library_view<'a>(
+    folder: &Path,
+    files: &'a [PathBuf],
+    preview: Option<&'a LibraryPreview>,
+) -> Element<'a, Message> {
+    let header = Text::new(format!("Library - {}", folder.display()))
+        .size(16)
+        .shaping(Auto);
+
+    let entries = if files.is_empty() {
+        Column::new().push(text("No tab files in this folder").size(12))
+    } else {
+        files.iter().fold(Column::new().spacing(2), |column, path| {
+            let open = button(Text::new(file_name(path)).size(12).shaping(Auto))
+                .style(button::text)
+                .width(Length::Fill)
+                .on_press(Message::OpenFile(path.clone()));
+            column.push(mouse_area(open).on_enter(Message::PreviewLibraryFile(path.clone())))
+        })
+    };
+    let list = scrollable(entries.width(Length::Fixed(LIBRARY_LIST_WIDTH)))
+        .height(Length::Fixed(LIBRARY_HEIGHT));
+
+    let preview: Element<'a, Message> = match preview {
+        Some(preview) if preview.measures.is_empty() => text("No preview").size(12).into(),
+        Some(preview) => {
+            let name = Text::new(file_name(&preview.path)).size(12).shaping(Auto);
+            // clicking the preview opens the file
+            let measures = preview.measures.iter().fold(Row::new(), |row, measure| {
+                let path = preview.path.clone();
+                row.push(measure.view().map(move |_| Message::OpenFile(path.clone())))
+            });
+            column![name, measures].spacing(5).into()
+        }
+        None => text("Hover a file to preview its first measures")
+            .size(12)
+            .into(),
+    };
+
+    let close = button(text("Close").size(12))
+        .style(button::secondary)
+        .on_press(Message::CloseLibrary);
+
+    let content = column![header, row![list, preview].spacing(20), close]
+        .spacing(10)
+        .width(Length::Shrink);
+    container(content)
+        .padding(20)
+        .style(|theme: &iced::Theme| container::Style {
+            background: Some(theme.palette().background.into()),
+            border: Border::default()
+                .color(crate::ui::utils::COLOR_GRAY)
+                .width(1),
+            ..Default::default()
+        })
+        .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn preview_of_the_first_measures() {
+        let files = library_files(Path::new("test-files"));
+        assert!(!files.is_empty());
+        let names: Vec<_> = files.iter().map(|path| file_name(path)).collect();
+        let mut sorted = names.clone();
+        sorted.sort_by_key(|name| name.to_ascii_lowercase());
+        assert_eq!(names, sorted);
+
+        let path = PathBuf::from("test-files/Demo v5.gp5");
+        let song = read_preview(&path);
+        assert!(song.is_some());
+        let preview = LibraryPreview::new(path.clone(), song);
+        assert_eq!(preview.path(), path);
+        assert_eq!(preview.measures.len(), PREVIEW_MEASURES);
+
+        let missing = PathBuf::from("test-files/missing.gp5");
+        let preview = LibraryPreview::new(missing.clone(), read_preview(&missing));
+        assert!(preview.measures.is_empty());
+    }
+}
@@ -0,0 +1,60 @@
+use crate::audio::metronome::{Metronome, Subdivision};
+use crate::ui::application::Message;
+use iced::widget::{Column, button, checkbox, container, pick_list, row, text};
+use iced::{Alignment, Border, Element, Length};
+
+/// Metronome panel: clicks along the playback, below the controls.
+pub fn metronome_view(metronome: Metronome) -> Element<'static, Message> {
+    let close = button(text("Close").size(12))
+        .style(button::secondary)
+        .on_press(Message::ToggleMetronomePanel);
+    let header = row![text("Metronome").size(14), close]
+        .spacing(10)
+        .align_y(Alignment::Center);
+
+    let enabled = checkbox(metronome.enabled)
+        .label("Click")
+        .text_size(14)
+        .on_toggle(move |enabled| {
+            Message::MetronomeChanged(Metronome {
+                enabled,
+                ..metronome
+            })
+        });
+    // subdivisions slow passages down to the fast notes
+    let subdivision = pick_list(
+        Subdivision::ALL,
+        Some(metronome.subdivision),
+        move |subdivision| {
+            Message::MetronomeChanged(Metronome {
+                subdivision,
+                ..metronome
+            })
+        },
+    )
+    .text_size(14)
+    .padding([5, 10]);
+    let accent_downbeat = checkbox(metronome.accent_downbeat)
+        .label("Accent the downbeat")
+        .text_size(14)
+        .on_toggle(move |accent_downbeat| {
+            Message::MetronomeChanged(Metronome {
+                accent_downbeat,
+                ..metronome
+            })
+        });
+    let settings = row![enabled, subdivision, accent_downbeat]
+        .spacing(10)
+        .align_y(Alignment::Center);
+
+    container(Column::new().push(header).push(settings).spacing(5))
+        .padding(10)
+        .width(Length::Fill)
+        .style(|_theme| container::Style {
+            border: Border::default()
+                .color(crate::ui::utils::COLOR_GRAY)
+                .width(1),
+            ..Default::default()
+        })
+        .into()
+}
@@ -0,0 +1,210 @@
+use crate::audio::midi_input::MidiTrigger;
+use crate::ui::application::Message;
+use iced::widget::{Column, button, checkbox, container, row, text};
+use iced::{Alignment, Border, Element, Length};
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+
+/// Transport action that can be driven by a MIDI controller.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TransportAction {
+    PlayPause,
+    TogglePracticeMode,
+    ToggleLoop,
+    TempoUp,
+    TempoDown,
+    NextMeasure,
+    PreviousMeasure,
+}
+
+impl TransportAction {
+    pub const ALL: [Self; 7] = [
+        Self::PlayPause,
+        Self::TogglePracticeMode,
+        Self::ToggleLoop,
+        Self::TempoUp,
+        Self::TempoDown,
+        Self::NextMeasure,
+        Self::PreviousMeasure,
+    ];
+
+    pub const fn message(self) -> Message {
+        match self {
+            Self::PlayPause => Message::PlayPause,
+            Self::TogglePracticeMode => Message::TogglePracticeMode,
+            Self::ToggleLoop => Message::ToggleLoop,
+            Self::TempoUp => Message::IncreaseTempo,
+            Self::TempoDown => Message::DecreaseTempo,
+            Self::NextMeasure => Message::NextMeasure,
+            Self::PreviousMeasure => Message::PreviousMeasure,
+        }
+    }
+}
+
+impl Display for TransportAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let label = match self {
+            Self::PlayPause => "Play/pause",
+            Self::TogglePracticeMode => "Bar by bar",
+            Self::ToggleLoop => "Loop section",
+            Self::TempoUp => "Tempo up",
+            Self::TempoDown => "Tempo down",
+            Self::NextMeasure => "Next measure",
+            Self::PreviousMeasure => "Previous measure",
+        };
+        write!(f, "{label}")
+    }
+}
+
+/// MIDI message bound to a transport action, persisted in the local configuration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MidiBinding {
+    pub action: TransportAction,
+    pub trigger: MidiTrigger,
+}
+
+/// Bind the trigger to the action, replacing previous bindings of either.
+pub fn bind(bindings: &mut Vec<MidiBinding>, action: TransportAction, trigger: MidiTrigger) {
+    bindings.retain(|b| b.action != action && b.trigger != trigger);
+    bindings.push(MidiBinding { action, trigger });
+}
+
+pub fn action_for(bindings: &[MidiBinding], trigger: MidiTrigger) -> Option<TransportAction> {
+    bindings
+        .iter()
+        .find(|b| b.trigger == trigger)
+        .map(|b| b.action)
+}
+
+/// Remote control panel: global hotkeys toggle and MIDI learn with one row per action.
+pub fn remote_control_view(
+    global_hotkeys: bool,
+    bindings: &[MidiBinding],
+    learning: Option<TransportAction>,
+) -> Element<'_, Message> {
+    let header = text("Remote control").size(18);
+    let hotkeys = checkbox(global_hotkeys)
+        .label("Media keys and Ctrl+Alt shortcuts while unfocused")
+        .text_size(14)
+        .on_toggle(Message::ToggleGlobalHotkeys);
+    let midi_header = text("MIDI controller").size(16);
+    let hint = text("Click Learn then press a pedal or key on the controller").size(12);
+
+    let rows = TransportAction::ALL.into_iter().map(|action| {
+        let binding = bindings
+            .iter()
+            .find(|b| b.action == action)
+            .map_or_else(|| "-".to_string(), |b| b.trigger.to_string());
+        let learn = if learning == Some(action) {
+            button(text("Waiting...").size(12)).style(button::secondary)
+        } else {
+            button(text("Learn").size(12)).on_press(Message::MidiLearn(action))
+        };
+        let clear = button(text("Clear").size(12))
+            .style(button::secondary)
+            .on_press_maybe((binding != "-").then_some(Message::ClearMidiBinding(action)));
+        row![
+            text(action.to_string()).size(14).width(160),
+            text(binding).size(14).width(140),
+            learn,
+            clear,
+        ]
+        .spacing(10)
+        .align_y(Alignment::Center)
+        .into()
+    });
+
+    let close = button(text("Close").size(12))
+        .style(button::secondary)
+        .on_press(Message::CloseRemoteControl);
+
+    let content = Column::new()
+        .push(header)
+        .push(hotkeys)
+        .push(midi_header)
+        .push(hint)
+        .push(Column::with_children(rows).spacing(5))
+        .push(close)
+        .spacing(10)
+        .width(Length::Shrink);
+
+    container(content)
+        .padding(20)
+        .style(|theme: &iced::Theme| container::Style {
+            background: Some(theme.palette().background.into()),
+            border: Border::default()
+                .color(crate::ui::utils::COLOR_GRAY)
+                .width(1),
+            ..Default::default()
+        })
+        .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PEDAL: MidiTrigger = MidiTrigger::ControlChange {
+        channel: 0,
+        controller: 64,
+    };
+    const PAD: MidiTrigger = MidiTrigger::Note {
+        channel: 9,
+        key: 36,
+    };
+
+    #[test]
+    fn learn_replaces_previous_bindings() {
+        let mut bindings = Vec::new();
+        bind(&mut bindings, TransportAction::PlayPause, PEDAL);
+        bind(&mut bindings, TransportAction::NextMeasure, PAD);
+        assert_eq!(
+            action_for(&bindings, PEDAL),
+            Some(TransportAction::PlayPause)
+        );
+        assert_eq!(
+            action_for(&bindings, PAD),
+            Some(TransportAction::NextMeasure)
+        );
+
+        // the pedal moves to another action
+        bind(&mut bindings, TransportAction::TempoUp, PEDAL);
+        assert_eq!(action_for(&bindings, PEDAL), Some(TransportAction::TempoUp));
+        assert!(
+            !bindings
+                .iter()
+                .any(|b| b.action == TransportAction::PlayPause)
+        );
+
+        // the action gets another trigger
+        bind(&mut bindings, TransportAction::NextMeasure, PEDAL);
+        assert_eq!(bindings.len(), 1);
+        assert_eq!(action_for(&bindings, PAD), None);
+    }
+
+    #[test]
+    fn bindings_round_trip() {
+        let bindings = vec![
+            MidiBinding {
+                action: TransportAction::PlayPause,
+                trigger: PEDAL,
+            },
+            MidiBinding {
+                action: TransportAction::PreviousMeasure,
+                trigger: PAD,
+            },
+        ];
+        let json = serde_json::to_string(&bindings).unwrap();
+        let parsed: Vec<MidiBinding> = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, bindings);
+
+        // the loop toggle learned on the pedal
+        let mut bindings = parsed;
+        bind(&mut bindings, TransportAction::ToggleLoop, PEDAL);
+        let json = serde_json::to_string(&bindings).unwrap();
+        let parsed: Vec<MidiBinding> = serde_json::from_str(&json).unwrap();
+        let action = action_for(&parsed, PEDAL);
+        assert_eq!(action, Some(TransportAction::ToggleLoop));
+        assert!(matches!(action.unwrap().message(), Message::ToggleLoop));
+    }
+}
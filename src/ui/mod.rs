@@ -1,7 +1,27 @@
 pub mod application;
 mod canvas_measure;
+mod compare;
+mod diagnostics;
+mod export;
+mod file_watch;
+mod fretboard;
+mod global_hotkeys;
 mod icons;
+mod issues;
+mod library;
+mod metronome;
+pub mod midi_control;
+mod note_menu;
 mod picker;
+mod pixel_grid;
+mod presets;
+mod search;
+mod shortcuts;
+pub mod snapshot;
+mod song_info;
 mod tablature;
-mod tuning;
+mod tempo_strip;
+mod timeline;
+pub mod track_manager;
+pub mod tuning;
 mod utils;
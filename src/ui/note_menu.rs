@@ -0,0 +1,40 @@
+use crate::editor::{NoteFlag, NoteLocation};
+use crate::parser::song_parser::Note;
+use crate::ui::application::Message;
+use iced::widget::{Column, button, checkbox, container, text};
+use iced::{Border, Element, Length};
+
+/// Note context menu: flags of the right-clicked note.
+pub fn note_menu_view<'a>(location: NoteLocation, note: &Note) -> Element<'a, Message> {
+    let header = text(format!(
+        "Measure {} \u{2022} beat {} \u{2022} string {}",
+        location.measure + 1,
+        location.beat + 1,
+        location.string
+    ))
+    .size(14);
+
+    let mut content = Column::new().push(header).spacing(10).width(Length::Shrink);
+    for flag in NoteFlag::ALL {
+        content = content.push(
+            checkbox(flag.is_set(note))
+                .label(flag.to_string())
+                .text_size(14)
+                .on_toggle(move |_| Message::ToggleNoteFlag(flag)),
+        );
+    }
+    let close = button(text("Close").size(12))
+        .style(button::secondary)
+        .on_press(Message::CloseNoteMenu);
+
+    container(content.push(close))
+        .padding(20)
+        .style(|theme: &iced::Theme| container::Style {
+            background: Some(theme.palette().background.into()),
+            border: Border::default()
+                .color(crate::ui::utils::COLOR_GRAY)
+                .width(1),
+            ..Default::default()
+        })
+        .into()
+}
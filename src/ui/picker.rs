@@ -1,8 +1,8 @@
-use std::path::PathBuf;
-
-/// File extensions supported by the parser; used by both the dialog filter
-/// and the validation of files loaded directly (CLI argument, drag and drop).
-const SUPPORTED_EXTENSIONS: [&str; 5] = ["gp5", "gp4", "gp3", "gpx", "gp"];
+use crate::parser::ruxg::RUXG_EXTENSION;
+use crate::parser::{SUPPORTED_EXTENSIONS, is_tab_file};
+use crate::tab_file::download;
+use iced::futures::channel::oneshot;
+use std::path::{Path, PathBuf};
 
 #[derive(Debug, Clone, thiserror::Error)]
 pub enum FilePickerError {
@@ -10,6 +10,8 @@ pub enum FilePickerError {
     DialogClosed,
     #[error("IO error: {0}")]
     IoError(String),
+    #[error("download error: {0}")]
+    DownloadError(String),
 }
 
 /// Opens a file dialog and returns the content of the picked file.
@@ -31,6 +33,194 @@ pub async fn open_file_dialog(
     load_file(picked_file).await
 }
 
+/// Opens a file dialog and returns the path of the picked sound font.
+pub async fn pick_sound_font_dialog(
+    picker_folder: Option<PathBuf>,
+) -> Result<PathBuf, FilePickerError> {
+    let mut picker = rfd::AsyncFileDialog::new()
+        .add_filter("Sound fonts", &["sf2"])
+        .set_title("Select a sound font");
+
+    if let Some(folder) = picker_folder {
+        picker = picker.set_directory(folder);
+    }
+
+    let picked_file = picker
+        .pick_file()
+        .await
+        .ok_or(FilePickerError::DialogClosed)?;
+    Ok(picked_file.path().to_path_buf())
+}
+
+/// Opens a save dialog and writes the MIDI file content to the picked path.
+pub async fn save_midi_dialog(
+    content: Vec<u8>,
+    file_name: String,
+    picker_folder: Option<PathBuf>,
+) -> Result<PathBuf, FilePickerError> {
+    let mut picker = rfd::AsyncFileDialog::new()
+        .add_filter("MIDI files", &["mid"])
+        .set_title("Export MIDI file")
+        .set_file_name(file_name);
+
+    if let Some(folder) = picker_folder {
+        picker = picker.set_directory(folder);
+    }
+
+    let picked_file = picker
+        .save_file()
+        .await
+        .ok_or(FilePickerError::DialogClosed)?;
+    let path = picked_file.path().to_path_buf();
+    log::info!("Exporting MIDI file: {}", path.display());
+    tokio::fs::write(&path, content)
+        .await
+        .map_err(|error| FilePickerError::IoError(error.to_string()))
+        .map(|()| path)
+}
+
+/// Opens a save dialog and writes the PNG image to the picked path.
+pub async fn save_image_dialog(
+    png: Vec<u8>,
+    file_name: String,
+    picker_folder: Option<PathBuf>,
+) -> Result<PathBuf, FilePickerError> {
+    let mut picker = rfd::AsyncFileDialog::new()
+        .add_filter("PNG images", &["png"])
+        .set_title("Save image")
+        .set_file_name(file_name);
+
+    if let Some(folder) = picker_folder {
+        picker = picker.set_directory(folder);
+    }
+
+    let picked_file = picker
+        .save_file()
+        .await
+        .ok_or(FilePickerError::DialogClosed)?;
+    let path = picked_file.path().to_path_buf();
+    log::info!("Saving image: {}", path.display());
+    tokio::fs::write(&path, png)
+        .await
+        .map_err(|error| FilePickerError::IoError(error.to_string()))
+        .map(|()| path)
+}
+
+/// Opens a save dialog and writes the notation text to the picked path,
+/// the extension of the file name is the one of the format.
+pub async fn save_notation_dialog(
+    content: String,
+    file_name: String,
+    format_name: &str,
+    picker_folder: Option<PathBuf>,
+) -> Result<PathBuf, FilePickerError> {
+    let extension = Path::new(&file_name)
+        .extension()
+        .map(|e| e.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let mut picker = rfd::AsyncFileDialog::new()
+        .add_filter(format_name, &[extension])
+        .set_title("Export notation")
+        .set_file_name(file_name);
+
+    if let Some(folder) = picker_folder {
+        picker = picker.set_directory(folder);
+    }
+
+    let picked_file = picker
+        .save_file()
+        .await
+        .ok_or(FilePickerError::DialogClosed)?;
+    let path = picked_file.path().to_path_buf();
+    log::info!("Exporting notation: {}", path.display());
+    tokio::fs::write(&path, content)
+        .await
+        .map_err(|error| FilePickerError::IoError(error.to_string()))
+        .map(|()| path)
+}
+
+/// Opens a folder dialog and writes the MIDI files of the track stems in the picked folder.
+pub async fn save_stems_dialog(
+    stems: Vec<(String, Vec<u8>)>,
+    picker_folder: Option<PathBuf>,
+) -> Result<PathBuf, FilePickerError> {
+    let mut picker = rfd::AsyncFileDialog::new().set_title("Export track stems to a folder");
+
+    if let Some(folder) = picker_folder {
+        picker = picker.set_directory(folder);
+    }
+
+    let picked_folder = picker
+        .pick_folder()
+        .await
+        .ok_or(FilePickerError::DialogClosed)?;
+    let folder = picked_folder.path().to_path_buf();
+    log::info!(
+        "Exporting {} track stems to: {}",
+        stems.len(),
+        folder.display()
+    );
+    for (file_name, content) in stems {
+        tokio::fs::write(folder.join(file_name), content)
+            .await
+            .map_err(|error| FilePickerError::IoError(error.to_string()))?;
+    }
+    Ok(folder)
+}
+
+/// Opens a save dialog and writes the song in the native format to the picked path.
+pub async fn save_song_dialog(
+    content: Vec<u8>,
+    file_name: String,
+    picker_folder: Option<PathBuf>,
+) -> Result<PathBuf, FilePickerError> {
+    let mut picker = rfd::AsyncFileDialog::new()
+        .add_filter("Ruxguitar files", &[RUXG_EXTENSION])
+        .set_title("Save song")
+        .set_file_name(file_name);
+
+    if let Some(folder) = picker_folder {
+        picker = picker.set_directory(folder);
+    }
+
+    let picked_file = picker
+        .save_file()
+        .await
+        .ok_or(FilePickerError::DialogClosed)?;
+    save_file(picked_file.path().to_path_buf(), content).await
+}
+
+/// Writes the song content to the path of a file it was already saved to.
+pub async fn save_file(path: PathBuf, content: Vec<u8>) -> Result<PathBuf, FilePickerError> {
+    log::info!("Saving song: {}", path.display());
+    tokio::fs::write(&path, content)
+        .await
+        .map_err(|error| FilePickerError::IoError(error.to_string()))
+        .map(|()| path)
+}
+
+/// Whether the file is in the native format, saving overwrites it without asking for a path.
+pub fn is_native_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| e.eq_ignore_ascii_case(RUXG_EXTENSION))
+}
+
+/// Downloads the file at the URL on its own thread, the content has no parent folder.
+pub async fn download_file(
+    url: String,
+) -> Result<(Vec<u8>, Option<PathBuf>, String), FilePickerError> {
+    let (sender, receiver) = oneshot::channel();
+    std::thread::spawn(move || {
+        let _ = sender.send(download(&url));
+    });
+    let content = receiver
+        .await
+        .map_err(|_| FilePickerError::DownloadError("download interrupted".to_string()))?
+        .map_err(|err| FilePickerError::DownloadError(err.to_string()))?;
+    Ok((content.data, None, content.file_name))
+}
+
 /// Loads the content of a file at the given path.
 ///
 /// Return the content of the file and its name.
@@ -38,12 +228,8 @@ pub async fn load_file(
     path: impl Into<PathBuf>,
 ) -> Result<(Vec<u8>, Option<PathBuf>, String), FilePickerError> {
     let path = path.into();
-    let file_extension = path
-        .extension()
-        .and_then(|e| e.to_str())
-        .map(str::to_lowercase)
-        .unwrap_or_default();
-    if !SUPPORTED_EXTENSIONS.contains(&file_extension.as_str()) {
+    if !is_tab_file(&path) {
+        let file_extension = path.extension().unwrap_or_default().to_string_lossy();
         return Err(FilePickerError::IoError(format!(
             "Unsupported file extension: {file_extension}"
         )));
@@ -0,0 +1,141 @@
+use iced::widget::canvas::{Path, Stroke};
+use iced::{Color, Point, Size};
+
+/// Physical pixel grid of a canvas frame, used to draw straight lines that cover whole pixels.
+///
+/// Without snapping, a thin line falling between two pixels is smeared over both with
+/// antialiasing, or rendered with an uneven width without it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PixelGrid {
+    scale: f32,    // physical pixels per logical pixel
+    origin: Point, // offset of the frame origin within its physical pixel
+}
+
+impl Default for PixelGrid {
+    fn default() -> Self {
+        Self::new(1.0)
+    }
+}
+
+impl PixelGrid {
+    pub const fn new(scale: f32) -> Self {
+        Self {
+            scale,
+            origin: Point::ORIGIN,
+        }
+    }
+
+    /// Same grid for a frame placed at the given logical position in the window.
+    pub fn at(self, position: Point) -> Self {
+        Self {
+            scale: self.scale,
+            origin: Point::new(
+                (position.x * self.scale).rem_euclid(1.0),
+                (position.y * self.scale).rem_euclid(1.0),
+            ),
+        }
+    }
+
+    /// Whole number of physical pixels, at least one.
+    fn physical_width(self, width: f32) -> f32 {
+        (width * self.scale).round().max(1.0)
+    }
+
+    /// Line width rounded to whole physical pixels.
+    pub fn width(self, width: f32) -> f32 {
+        self.physical_width(width) / self.scale
+    }
+
+    /// Center of a line so that it covers whole pixels: odd widths are centered
+    /// in a pixel, even widths on a pixel boundary.
+    fn snap_center(self, coordinate: f32, origin: f32, width: f32) -> f32 {
+        let physical = coordinate * self.scale + origin;
+        let snapped = if self.physical_width(width) % 2.0 == 1.0 {
+            physical.floor() + 0.5
+        } else {
+            physical.round()
+        };
+        (snapped - origin) / self.scale
+    }
+
+    /// End of a line on the nearest pixel boundary.
+    fn snap_edge(self, coordinate: f32, origin: f32) -> f32 {
+        ((coordinate * self.scale + origin).round() - origin) / self.scale
+    }
+
+    pub fn stroke(self, width: f32, color: Color) -> Stroke<'static> {
+        Stroke::default()
+            .with_width(self.width(width))
+            .with_color(color)
+    }
+
+    pub fn horizontal_line(self, from_x: f32, to_x: f32, y: f32, width: f32) -> Path {
+        let y = self.snap_center(y, self.origin.y, width);
+        Path::line(
+            Point::new(self.snap_edge(from_x, self.origin.x), y),
+            Point::new(self.snap_edge(to_x, self.origin.x), y),
+        )
+    }
+
+    /// Rectangle outline with each side snapped like a line.
+    pub fn rectangle(self, top_left: Point, size: Size, width: f32) -> Path {
+        let left = self.snap_center(top_left.x, self.origin.x, width);
+        let right = self.snap_center(top_left.x + size.width, self.origin.x, width);
+        let top = self.snap_center(top_left.y, self.origin.y, width);
+        let bottom = self.snap_center(top_left.y + size.height, self.origin.y, width);
+        Path::rectangle(Point::new(left, top), Size::new(right - left, bottom - top))
+    }
+
+    pub fn vertical_line(self, x: f32, from_y: f32, to_y: f32, width: f32) -> Path {
+        let x = self.snap_center(x, self.origin.x, width);
+        Path::line(
+            Point::new(x, self.snap_edge(from_y, self.origin.y)),
+            Point::new(x, self.snap_edge(to_y, self.origin.y)),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn widths_cover_whole_pixels() {
+        let grid = PixelGrid::new(1.0);
+        assert_eq!(grid.width(0.8), 1.0);
+        assert_eq!(grid.width(1.5), 2.0);
+        assert_eq!(grid.width(0.2), 1.0);
+        let grid = PixelGrid::new(1.25);
+        assert_eq!(grid.width(0.8), 0.8);
+        assert_eq!(grid.width(1.5), 1.6);
+        let grid = PixelGrid::new(2.0);
+        assert_eq!(grid.width(0.8), 1.0);
+        assert_eq!(grid.width(4.0), 4.0);
+    }
+
+    #[test]
+    fn odd_widths_centered_in_pixels() {
+        let grid = PixelGrid::new(1.0);
+        assert_eq!(grid.snap_center(60.0, 0.0, 1.0), 60.5);
+        assert_eq!(grid.snap_center(60.7, 0.0, 1.0), 60.5);
+        // even widths on pixel boundaries
+        assert_eq!(grid.snap_center(60.4, 0.0, 2.0), 60.0);
+        assert_eq!(grid.snap_center(60.6, 0.0, 2.0), 61.0);
+
+        // 1.5 logical pixels is 3 physical pixels at 2x
+        let grid = PixelGrid::new(2.0);
+        assert_eq!(grid.snap_center(10.0, 0.0, 1.5), 10.25);
+    }
+
+    #[test]
+    fn snapping_accounts_for_frame_position() {
+        let grid = PixelGrid::new(1.0).at(Point::new(100.25, 40.0));
+        let x = grid.snap_center(10.0, grid.origin.x, 1.0);
+        // 100.25 + 10.25 = 110.5, the center of a physical pixel
+        assert_eq!(x, 10.25);
+        assert_eq!(grid.snap_edge(10.0, grid.origin.x), 9.75);
+
+        let grid = PixelGrid::new(1.5).at(Point::new(-3.0, 0.5));
+        assert_eq!(grid.origin, Point::new(0.5, 0.75));
+    }
+}
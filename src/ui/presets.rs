@@ -0,0 +1,203 @@
+use crate::audio::midi_builder::{PresetId, PresetRemaps};
+use crate::ui::application::Message;
+use iced::widget::{Column, button, column, container, pick_list, row, scrollable, text};
+use iced::{Alignment, Border, Element, Length};
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::path::PathBuf;
+
+/// Banks from this one hold percussion kits.
+const PERCUSSION_BANK: u8 = 128;
+
+/// Preset picked for a program, the sound font preset of the program when unset.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PresetChoice(Option<(PresetId, String)>);
+
+impl PresetChoice {
+    pub fn preset_id(&self) -> Option<PresetId> {
+        self.0.as_ref().map(|(id, _)| *id)
+    }
+}
+
+impl Display for PresetChoice {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.0 {
+            Some((id, name)) => write!(f, "{id} {name}"),
+            None => write!(f, "Program preset"),
+        }
+    }
+}
+
+/// Sound font playing a track, the default one when unset.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SoundFontChoice(Option<PathBuf>);
+
+impl SoundFontChoice {
+    pub fn into_path(self) -> Option<PathBuf> {
+        self.0
+    }
+}
+
+impl Display for SoundFontChoice {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.0 {
+            Some(path) => {
+                let name = path.file_name().unwrap_or(path.as_os_str());
+                write!(f, "{}", name.to_string_lossy())
+            }
+            None => write!(f, "Default sound font"),
+        }
+    }
+}
+
+/// Sound fonts assigned to the tracks, `tracks` holds the index and name of every track.
+pub struct SoundFontTracks<'a> {
+    pub tracks: &'a [(usize, String)],
+    pub sound_fonts: &'a [PathBuf], // additional sound fonts
+    pub assignment: &'a BTreeMap<usize, PathBuf>,
+}
+
+/// Whether the sound font lacks the GM preset of the program, the synthesizer plays its first preset instead.
+fn is_missing(presets: &[(PresetId, String)], program: u8) -> bool {
+    !presets
+        .iter()
+        .any(|(id, _)| id.bank == 0 && id.patch == program)
+}
+
+/// Presets panel: the presets of the sound font and the remapping of the track programs.
+/// `tracks` holds the name and program of the melodic tracks.
+pub fn presets_view<'a>(
+    tracks: &[(String, u8)],
+    presets: &[(PresetId, String)],
+    remaps: &PresetRemaps,
+    sound_font_tracks: &SoundFontTracks,
+) -> Element<'a, Message> {
+    let header = text("Sound font presets").size(18);
+    let hint = text("Play a program with another preset, remembered for this sound font").size(12);
+
+    let choices: Vec<PresetChoice> = std::iter::once(PresetChoice(None))
+        .chain(
+            presets
+                .iter()
+                .filter(|(id, _)| id.bank < PERCUSSION_BANK)
+                .cloned()
+                .map(|preset| PresetChoice(Some(preset))),
+        )
+        .collect();
+    let programs = tracks
+        .iter()
+        .fold(Column::new().spacing(5), |column, (name, program)| {
+            let program = *program;
+            let status = if is_missing(presets, program) {
+                "missing"
+            } else {
+                "available"
+            };
+            let selected = choices
+                .iter()
+                .find(|choice| choice.preset_id() == remaps.get(program))
+                .cloned();
+            let picker = pick_list(choices.clone(), selected, move |choice| {
+                Message::PresetRemapped(program, choice)
+            })
+            .text_size(14)
+            .padding([5, 10]);
+            column.push(
+                row![
+                    text(format!("{name}: program {program} ({status})")).size(14),
+                    picker
+                ]
+                .spacing(10)
+                .align_y(Alignment::Center),
+            )
+        });
+
+    let sound_font_choices: Vec<SoundFontChoice> = std::iter::once(SoundFontChoice(None))
+        .chain(
+            sound_font_tracks
+                .sound_fonts
+                .iter()
+                .map(|path| SoundFontChoice(Some(path.clone()))),
+        )
+        .collect();
+    let sound_fonts =
+        sound_font_tracks
+            .tracks
+            .iter()
+            .fold(Column::new().spacing(5), |column, (index, name)| {
+                let index = *index;
+                let selected = SoundFontChoice(sound_font_tracks.assignment.get(&index).cloned());
+                let picker = pick_list(sound_font_choices.clone(), Some(selected), move |choice| {
+                    Message::TrackSoundFontSelected(index, choice)
+                })
+                .text_size(14)
+                .padding([5, 10]);
+                column.push(
+                    row![text(name.clone()).size(14), picker]
+                        .spacing(10)
+                        .align_y(Alignment::Center),
+                )
+            });
+    let add_sound_font = button(text("Add sound font").size(12))
+        .style(button::secondary)
+        .on_press(Message::AddSoundFont);
+
+    let available = presets
+        .iter()
+        .fold(Column::new().spacing(2), |column, (id, name)| {
+            column.push(text(format!("{id} {name}")).size(12))
+        });
+    let available = scrollable(available).height(200).width(Length::Fill);
+
+    let close = button(text("Close").size(12))
+        .style(button::secondary)
+        .on_press(Message::ClosePresets);
+
+    let content = column![
+        header,
+        hint,
+        programs,
+        text("Track sound fonts").size(18),
+        sound_fonts,
+        add_sound_font,
+        text(format!("{} presets (bank:patch)", presets.len())).size(14),
+        available,
+        close
+    ]
+    .spacing(10)
+    .width(Length::Shrink);
+
+    container(content)
+        .padding(20)
+        .style(|theme: &iced::Theme| container::Style {
+            background: Some(theme.palette().background.into()),
+            border: Border::default()
+                .color(crate::ui::utils::COLOR_GRAY)
+                .width(1),
+            ..Default::default()
+        })
+        .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn programs_missing_from_the_sound_font() {
+        let presets = vec![
+            (PresetId { bank: 0, patch: 0 }, "Piano".to_string()),
+            (PresetId { bank: 0, patch: 29 }, "Overdrive".to_string()),
+            (PresetId { bank: 8, patch: 30 }, "Feedback".to_string()),
+        ];
+        assert!(!is_missing(&presets, 29));
+        // only found in a variation bank
+        assert!(is_missing(&presets, 30));
+        let choice = PresetChoice(Some(presets[1].clone()));
+        assert_eq!(choice.to_string(), "000:029 Overdrive");
+        assert_eq!(choice.preset_id(), Some(PresetId { bank: 0, patch: 29 }));
+        let sound_font = SoundFontChoice(Some(PathBuf::from("/fonts/drums.sf2")));
+        assert_eq!(sound_font.to_string(), "drums.sf2");
+        assert_eq!(SoundFontChoice(None).to_string(), "Default sound font");
+    }
+}
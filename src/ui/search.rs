@@ -0,0 +1,258 @@
+use crate::parser::song_parser::{NoteType, Song};
+use crate::ui::application::Message;
+use iced::widget::{Column, button, container, row, scrollable, text};
+use iced::{Alignment, Border, Element, Length};
+use std::fmt::Display;
+
+// Height of the search results list
+const RESULTS_HEIGHT: f32 = 120.0;
+
+/// What to look for in the song.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SearchQuery {
+    /// Fret played on a string, written `string/fret` (e.g. `3/5`).
+    Note { string: i8, fret: i16 },
+    /// Chord name or marker text, case insensitive.
+    Text(String),
+}
+
+impl SearchQuery {
+    /// Returns `None` for a blank input.
+    pub fn parse(input: &str) -> Option<Self> {
+        let input = input.trim();
+        if input.is_empty() {
+            return None;
+        }
+        if let Some((string, fret)) = input.split_once('/')
+            && let (Ok(string), Ok(fret)) = (string.trim().parse(), fret.trim().parse())
+        {
+            return Some(Self::Note { string, fret });
+        }
+        Some(Self::Text(input.to_lowercase()))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchMatch {
+    Note,
+    Chord,
+    Marker,
+}
+
+/// Occurrence of the query in the song.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchResult {
+    pub track_id: Option<usize>, // markers belong to all tracks
+    pub measure_id: usize,
+    pub kind: SearchMatch,
+    pub label: String,
+}
+
+impl Display for SearchResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Measure {}", self.measure_id + 1)?;
+        if let Some(track_id) = self.track_id {
+            write!(f, " \u{2022} track {}", track_id + 1)?;
+        }
+        let kind = match self.kind {
+            SearchMatch::Note => "note",
+            SearchMatch::Chord => "chord",
+            SearchMatch::Marker => "marker",
+        };
+        write!(f, " \u{2022} {kind} {}", self.label)
+    }
+}
+
+/// Find all occurrences of the query, at most one per track and measure, in song order.
+pub fn search_song(song: &Song, query: &SearchQuery) -> Vec<SearchResult> {
+    let mut results = Vec::new();
+    for (measure_id, header) in song.measure_headers.iter().enumerate() {
+        if let SearchQuery::Text(text) = query
+            && let Some(marker) = &header.marker
+            && marker.title.to_lowercase().contains(text)
+        {
+            results.push(SearchResult {
+                track_id: None,
+                measure_id,
+                kind: SearchMatch::Marker,
+                label: marker.title.clone(),
+            });
+        }
+        for (track_id, track) in song.tracks.iter().enumerate() {
+            let Some(measure) = track.measures.get(measure_id) else {
+                continue;
+            };
+            let mut beats = measure.voices.iter().flat_map(|v| &v.beats);
+            let found = match query {
+                SearchQuery::Note { string, fret } => beats
+                    .any(|beat| {
+                        beat.notes.iter().any(|note| {
+                            note.string == *string
+                                && note.value == *fret
+                                && note.kind == NoteType::Normal
+                        })
+                    })
+                    .then(|| (SearchMatch::Note, format!("{string}/{fret}"))),
+                SearchQuery::Text(text) => beats
+                    .find_map(|beat| {
+                        beat.effect
+                            .chord
+                            .as_ref()
+                            .filter(|chord| chord.name.to_lowercase().contains(text))
+                    })
+                    .map(|chord| (SearchMatch::Chord, chord.name.clone())),
+            };
+            if let Some((kind, label)) = found {
+                results.push(SearchResult {
+                    track_id: Some(track_id),
+                    measure_id,
+                    kind,
+                    label,
+                });
+            }
+        }
+    }
+    results
+}
+
+/// Results list, clicking a result jumps to its measure.
+pub fn search_results_view(results: &[SearchResult]) -> Element<'_, Message> {
+    let summary = match results.len() {
+        0 => "No results".to_string(),
+        1 => "1 result".to_string(),
+        count => format!("{count} results"),
+    };
+    let close = button(text("Close").size(12))
+        .style(button::secondary)
+        .on_press(Message::ClearSearch);
+    let header = row![text(summary).size(14), close]
+        .spacing(10)
+        .align_y(Alignment::Center);
+
+    let entries = results.iter().map(|result| {
+        button(text(result.to_string()).size(12))
+            .style(button::text)
+            .padding([2, 5])
+            .on_press(Message::SearchResultSelected(result.clone()))
+            .into()
+    });
+    let list = scrollable(Column::with_children(entries).width(Length::Fill))
+        .height(Length::Fixed(RESULTS_HEIGHT));
+
+    container(Column::new().push(header).push(list).spacing(5))
+        .padding(10)
+        .width(Length::Fill)
+        .style(|_theme| container::Style {
+            border: Border::default()
+                .color(crate::ui::utils::COLOR_GRAY)
+                .width(1),
+            ..Default::default()
+        })
+        .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::song_parser_tests::parse_gp_file;
+
+    #[test]
+    fn parse_query() {
+        assert_eq!(SearchQuery::parse("  "), None);
+        assert_eq!(
+            SearchQuery::parse("3/5"),
+            Some(SearchQuery::Note { string: 3, fret: 5 })
+        );
+        assert_eq!(
+            SearchQuery::parse(" 6 / 12 "),
+            Some(SearchQuery::Note {
+                string: 6,
+                fret: 12
+            })
+        );
+        assert_eq!(
+            SearchQuery::parse("Am7"),
+            Some(SearchQuery::Text("am7".to_string()))
+        );
+        // not a note, searched as text
+        assert_eq!(
+            SearchQuery::parse("A/E"),
+            Some(SearchQuery::Text("a/e".to_string()))
+        );
+    }
+
+    #[test]
+    fn search_notes() {
+        let song = parse_gp_file("test-files/Demo v5.gp5").unwrap();
+        let query = SearchQuery::Note { string: 3, fret: 5 };
+        let results = search_song(&song, &query);
+        assert!(!results.is_empty());
+        for result in &results {
+            assert_eq!(result.kind, SearchMatch::Note);
+            let track = &song.tracks[result.track_id.unwrap()];
+            let measure = &track.measures[result.measure_id];
+            assert!(
+                measure
+                    .voices
+                    .iter()
+                    .flat_map(|v| &v.beats)
+                    .flat_map(|b| &b.notes)
+                    .any(|n| n.string == 3 && n.value == 5)
+            );
+        }
+        // one result per track and measure, in song order
+        let mut sorted = results.clone();
+        sorted.sort_by_key(|r| (r.measure_id, r.track_id));
+        sorted.dedup_by_key(|r| (r.measure_id, r.track_id));
+        assert_eq!(sorted, results);
+    }
+
+    #[test]
+    fn search_markers_and_chords() {
+        let song = parse_gp_file("test-files/Demo v5.gp5").unwrap();
+        let (measure_id, marker) = song
+            .measure_headers
+            .iter()
+            .enumerate()
+            .find_map(|(i, h)| h.marker.as_ref().map(|m| (i, m.title.clone())))
+            .unwrap();
+        let query = SearchQuery::parse(&marker.to_uppercase()).unwrap();
+        let results = search_song(&song, &query);
+        assert!(results.contains(&SearchResult {
+            track_id: None,
+            measure_id,
+            kind: SearchMatch::Marker,
+            label: marker,
+        }));
+
+        let chord = song
+            .tracks
+            .iter()
+            .flat_map(|t| &t.measures)
+            .flat_map(|m| &m.voices)
+            .flat_map(|v| &v.beats)
+            .find_map(|b| b.effect.chord.as_ref())
+            .unwrap();
+        let query = SearchQuery::parse(&chord.name).unwrap();
+        let results = search_song(&song, &query);
+        assert!(
+            results
+                .iter()
+                .any(|r| r.kind == SearchMatch::Chord && r.label == chord.name)
+        );
+    }
+
+    #[test]
+    fn search_result_label() {
+        let result = SearchResult {
+            track_id: Some(1),
+            measure_id: 4,
+            kind: SearchMatch::Chord,
+            label: "Am".to_string(),
+        };
+        assert_eq!(
+            result.to_string(),
+            "Measure 5 \u{2022} track 2 \u{2022} chord Am"
+        );
+    }
+}
@@ -0,0 +1,166 @@
+use crate::ui::application::Message;
+use iced::keyboard::key::Named::{
+    ArrowDown, ArrowLeft, ArrowRight, ArrowUp, Backspace, Delete, Escape, F11, Home, PageDown,
+    Space, Tab,
+};
+use iced::keyboard::{Key, Modifiers};
+
+/// Keyboard shortcuts, every mouse interaction has an equivalent here.
+pub fn message_for_key(key: Key<&str>, modifiers: Modifiers) -> Option<Message> {
+    match key {
+        // page down is what most page turner footswitches send
+        Key::Named(Space | PageDown) => Some(Message::PlayPause),
+        Key::Named(Home) => Some(Message::StopPlayer),
+        Key::Named(ArrowUp) if modifiers.control() && modifiers.shift() => {
+            Some(Message::TempoNudged(1))
+        }
+        Key::Named(ArrowDown) if modifiers.control() && modifiers.shift() => {
+            Some(Message::TempoNudged(-1))
+        }
+        Key::Named(ArrowUp) if modifiers.control() => Some(Message::IncreaseTempo),
+        Key::Named(ArrowDown) if modifiers.control() => Some(Message::DecreaseTempo),
+        Key::Named(ArrowLeft) if modifiers.shift() => Some(Message::PreviousBeat),
+        Key::Named(ArrowRight) if modifiers.shift() => Some(Message::NextBeat),
+        Key::Named(ArrowLeft) => Some(Message::PreviousMeasure),
+        Key::Named(ArrowRight) => Some(Message::NextMeasure),
+        Key::Named(Escape) => Some(Message::CloseOverlay),
+        Key::Named(F11) => Some(Message::ToggleFullscreen),
+        Key::Character(c) if c.eq_ignore_ascii_case("o") && modifiers.control() => {
+            Some(Message::OpenFileDialog)
+        }
+        Key::Character(c) if c.eq_ignore_ascii_case("s") && modifiers.control() => {
+            Some(if modifiers.shift() {
+                Message::SaveSongAs
+            } else {
+                Message::SaveSong
+            })
+        }
+        Key::Character(c) if c.eq_ignore_ascii_case("c") && modifiers.control() => {
+            modifiers.shift().then_some(Message::CopyMeasureImage)
+        }
+        Key::Character(c) if c.eq_ignore_ascii_case("s") => Some(Message::ToggleSolo),
+        Key::Character(c) if c.eq_ignore_ascii_case("b") => Some(Message::ToggleBacking),
+        Key::Character(c) if c.eq_ignore_ascii_case("p") => Some(Message::TogglePracticeMode),
+        Key::Character(c) if c.eq_ignore_ascii_case("t") && modifiers.shift() => {
+            Some(Message::PreviousTrack)
+        }
+        Key::Character(c) if c.eq_ignore_ascii_case("t") => Some(Message::NextTrack),
+        // flips between two parts, e.g. of a duet
+        Key::Named(Tab) => Some(Message::ToggleLastTrack),
+        // note entry, the digits select the tracks unless a note slot is selected
+        Key::Character(c) if c.eq_ignore_ascii_case("z") && modifiers.control() => {
+            Some(if modifiers.shift() {
+                Message::Redo
+            } else {
+                Message::Undo
+            })
+        }
+        Key::Character(c) if c.eq_ignore_ascii_case("y") && modifiers.control() => {
+            Some(Message::Redo)
+        }
+        Key::Character(c) if c.len() == 1 && c.chars().all(|ch| ch.is_ascii_digit()) => {
+            c.parse().ok().map(Message::TypeFretDigit)
+        }
+        Key::Named(Delete | Backspace) => Some(Message::DeleteNote),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn arrows_move_by_measure_or_beat() {
+        let none = Modifiers::empty();
+        let right = Key::Named(ArrowRight);
+        let left = Key::Named(ArrowLeft);
+        assert!(matches!(
+            message_for_key(right.clone(), none),
+            Some(Message::NextMeasure)
+        ));
+        assert!(matches!(
+            message_for_key(right, Modifiers::SHIFT),
+            Some(Message::NextBeat)
+        ));
+        assert!(matches!(
+            message_for_key(left, Modifiers::SHIFT),
+            Some(Message::PreviousBeat)
+        ));
+        assert!(message_for_key(Key::Named(ArrowUp), none).is_none());
+        assert!(matches!(
+            message_for_key(Key::Named(ArrowUp), Modifiers::CTRL),
+            Some(Message::IncreaseTempo)
+        ));
+        assert!(matches!(
+            message_for_key(Key::Named(ArrowDown), Modifiers::CTRL | Modifiers::SHIFT),
+            Some(Message::TempoNudged(-1))
+        ));
+    }
+
+    #[test]
+    fn letters_ignore_case() {
+        let none = Modifiers::empty();
+        assert!(matches!(
+            message_for_key(Key::Character("t"), none),
+            Some(Message::NextTrack)
+        ));
+        // shift reports the upper case character
+        assert!(matches!(
+            message_for_key(Key::Character("T"), Modifiers::SHIFT),
+            Some(Message::PreviousTrack)
+        ));
+        assert!(matches!(
+            message_for_key(Key::Character("S"), none),
+            Some(Message::ToggleSolo)
+        ));
+        assert!(matches!(
+            message_for_key(Key::Character("b"), none),
+            Some(Message::ToggleBacking)
+        ));
+        assert!(message_for_key(Key::Character("o"), none).is_none());
+        assert!(matches!(
+            message_for_key(Key::Character("s"), Modifiers::CTRL),
+            Some(Message::SaveSong)
+        ));
+        assert!(matches!(
+            message_for_key(Key::Character("S"), Modifiers::CTRL | Modifiers::SHIFT),
+            Some(Message::SaveSongAs)
+        ));
+        assert!(matches!(
+            message_for_key(Key::Character("o"), Modifiers::CTRL),
+            Some(Message::OpenFileDialog)
+        ));
+    }
+
+    #[test]
+    fn note_entry_keys() {
+        let none = Modifiers::empty();
+        assert!(matches!(
+            message_for_key(Key::Character("7"), none),
+            Some(Message::TypeFretDigit(7))
+        ));
+        assert!(message_for_key(Key::Character("+7"), none).is_none());
+        assert!(matches!(
+            message_for_key(Key::Named(Tab), none),
+            Some(Message::ToggleLastTrack)
+        ));
+        assert!(matches!(
+            message_for_key(Key::Named(Backspace), none),
+            Some(Message::DeleteNote)
+        ));
+        assert!(message_for_key(Key::Character("z"), none).is_none());
+        assert!(matches!(
+            message_for_key(Key::Character("z"), Modifiers::CTRL),
+            Some(Message::Undo)
+        ));
+        assert!(matches!(
+            message_for_key(Key::Character("Z"), Modifiers::CTRL | Modifiers::SHIFT),
+            Some(Message::Redo)
+        ));
+        assert!(matches!(
+            message_for_key(Key::Character("y"), Modifiers::CTRL),
+            Some(Message::Redo)
+        ));
+    }
+}
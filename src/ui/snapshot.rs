@@ -0,0 +1,371 @@
+//! Offscreen rendering of tablature measures into PNG images, to share a snippet of a tab
+//! without a screenshot of the whole window or to render tabs on a server.
+
+use crate::audio::playback_order::compute_playback_order;
+use crate::parser::song_parser::Song;
+use crate::ui::canvas_measure::CanvasMeasure;
+use crate::ui::tablature::Tablature;
+use flate2::Compression;
+use flate2::write::ZlibEncoder;
+use iced::advanced::graphics::geometry::Renderer as _;
+use iced::advanced::renderer::Headless;
+use iced::futures::channel::oneshot;
+use iced::widget::Id;
+use iced::{Font, Pixels, Rectangle, Renderer, Size, Theme};
+use std::io::Write;
+use std::ops::{Range, RangeInclusive};
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::Arc;
+
+/// Images are drawn at twice the logical size to stay sharp once shared.
+const SCALE_FACTOR: f32 = 2.0;
+
+/// Empty space around the measures.
+const MARGIN: f32 = 10.0;
+
+/// Width of the rows of the rendered tracks, the measures wrap to a new row beyond it.
+const ROW_WIDTH: f32 = 1200.0;
+
+#[derive(Debug, Clone, thiserror::Error)]
+pub enum SnapshotError {
+    #[error("no offscreen renderer available")]
+    NoRenderer,
+    #[error("measures {0} to {1} not found, the song has {2} measures")]
+    MeasuresNotFound(usize, usize, usize),
+    #[error("no clipboard tool found (wl-copy or xclip on Linux)")]
+    NoClipboardTool,
+    #[error("clipboard error: {0}")]
+    Clipboard(String),
+}
+
+/// Draws measures of the track as displayed in the tablature, without a window.
+/// Measures are indexed from 0, they wrap in rows like in the tablature.
+pub fn render_track_png(
+    song: Arc<Song>,
+    track_id: usize,
+    measures: RangeInclusive<usize>,
+) -> Result<Vec<u8>, SnapshotError> {
+    let measure_count = song.measure_headers.len();
+    let not_found =
+        SnapshotError::MeasuresNotFound(*measures.start() + 1, *measures.end() + 1, measure_count);
+    let playback_order = compute_playback_order(&song.measure_headers);
+    let mut tablature = Tablature::new(song, track_id, Id::unique(), &playback_order);
+    let measures = tablature.measures_mut(measures).ok_or(not_found)?;
+    render_measures_png(measures, ROW_WIDTH)
+}
+
+/// Measures in rows no wider than `row_width`, a wider measure is alone on its row.
+fn rows(widths: &[f32], row_width: f32) -> Vec<Range<usize>> {
+    let mut rows = Vec::new();
+    let mut start = 0;
+    let mut width = 0.0;
+    for (index, measure_width) in widths.iter().enumerate() {
+        if index > start && width + measure_width > row_width {
+            rows.push(start..index);
+            start = index;
+            width = 0.0;
+        }
+        width += measure_width;
+    }
+    if start < widths.len() {
+        rows.push(start..widths.len());
+    }
+    rows
+}
+
+/// Draws the measures in rows on the background of the theme and encodes them as PNG.
+/// The rows but the last are stretched to the widest one, like in the tablature.
+/// The CPU renderer is used, it does not need a window or a GPU.
+pub fn render_measures_png(
+    measures: &mut [CanvasMeasure],
+    row_width: f32,
+) -> Result<Vec<u8>, SnapshotError> {
+    let widths: Vec<f32> = measures.iter().map(|m| m.total_measure_len).collect();
+    let rows = rows(&widths, row_width);
+    let natural_width = |row: &Range<usize>| widths[row.clone()].iter().sum::<f32>();
+    let width = rows.iter().map(natural_width).fold(0.0, f32::max);
+    let height: f32 = rows
+        .iter()
+        .map(|row| {
+            measures[row.clone()]
+                .iter()
+                .map(|m| m.vertical_measure_height)
+                .fold(0.0, f32::max)
+        })
+        .sum();
+    let background = Theme::Dark.palette().background;
+    let mut image = Image::new(
+        physical(width + MARGIN * 2.0),
+        physical(height + MARGIN * 2.0),
+        background.into_rgba8(),
+    );
+    let mut y = MARGIN;
+    for (row_index, row) in rows.iter().enumerate() {
+        let stretch = if row_index + 1 < rows.len() {
+            width / natural_width(row)
+        } else {
+            1.0
+        };
+        let mut x = MARGIN;
+        let mut row_height: f32 = 0.0;
+        for (index, measure) in measures[row.clone()].iter_mut().enumerate() {
+            // measures start on whole physical pixels
+            let left = physical(x);
+            x += measure.total_measure_len * stretch;
+            let size = Size::new(
+                physical(x) - left,
+                physical(measure.vertical_measure_height),
+            );
+            let rgba = render_measure(measure, size, index == 0)?;
+            image.copy(&rgba, size, left, physical(y));
+            row_height = row_height.max(measure.vertical_measure_height);
+        }
+        y += row_height;
+    }
+    Ok(encode_png(image.width, image.height, &image.rgba))
+}
+
+/// Logical length in whole physical pixels.
+fn physical(length: f32) -> u32 {
+    (length * SCALE_FACTOR).round() as u32
+}
+
+/// Pixels of the measure drawn alone, a renderer per measure keeps its geometry untranslated.
+fn render_measure(
+    measure: &mut CanvasMeasure,
+    size: Size<u32>,
+    first_on_row: bool,
+) -> Result<Vec<u8>, SnapshotError> {
+    let mut renderer = iced::futures::executor::block_on(<Renderer as Headless>::new(
+        Font::MONOSPACE,
+        Pixels(16.0),
+        Some("tiny-skia"),
+    ))
+    .ok_or(SnapshotError::NoRenderer)?;
+    let bounds = Rectangle::with_size(Size::new(
+        size.width as f32 / SCALE_FACTOR,
+        size.height as f32 / SCALE_FACTOR,
+    ));
+    for geometry in measure.draw_snapshot(&renderer, bounds, SCALE_FACTOR, first_on_row) {
+        renderer.draw_geometry(geometry);
+    }
+    Ok(renderer.screenshot(size, SCALE_FACTOR, Theme::Dark.palette().background))
+}
+
+/// RGBA pixels the measures are copied into.
+struct Image {
+    width: u32,
+    height: u32,
+    rgba: Vec<u8>,
+}
+
+impl Image {
+    fn new(width: u32, height: u32, background: [u8; 4]) -> Self {
+        Self {
+            width,
+            height,
+            rgba: background.repeat(width as usize * height as usize),
+        }
+    }
+
+    /// Copies the pixels at the position, cropped to the image.
+    fn copy(&mut self, rgba: &[u8], size: Size<u32>, left: u32, top: u32) {
+        let columns = size.width.min(self.width.saturating_sub(left)) as usize * 4;
+        for (line, pixels) in rgba.chunks(size.width as usize * 4).enumerate() {
+            let y = top as usize + line;
+            if y >= self.height as usize {
+                break;
+            }
+            let start = (y * self.width as usize + left as usize) * 4;
+            self.rgba[start..start + columns].copy_from_slice(&pixels[..columns]);
+        }
+    }
+}
+
+/// Minimal PNG encoder: 8 bits RGBA, unfiltered scanlines compressed in a single chunk.
+pub fn encode_png(width: u32, height: u32, rgba: &[u8]) -> Vec<u8> {
+    let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+    let mut header = Vec::with_capacity(13);
+    header.extend(width.to_be_bytes());
+    header.extend(height.to_be_bytes());
+    // bit depth, RGBA color type, deflate compression, adaptive filtering, no interlace
+    header.extend([8, 6, 0, 0, 0]);
+    write_chunk(&mut png, b"IHDR", &header);
+
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    for scanline in rgba.chunks(width as usize * 4) {
+        // filter type of the scanline: none
+        encoder.write_all(&[0]).expect("write to memory");
+        encoder.write_all(scanline).expect("write to memory");
+    }
+    let data = encoder.finish().expect("write to memory");
+    write_chunk(&mut png, b"IDAT", &data);
+    write_chunk(&mut png, b"IEND", &[]);
+    png
+}
+
+/// Length, type, data and CRC of the type and data.
+fn write_chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend((data.len() as u32).to_be_bytes());
+    png.extend(kind);
+    png.extend(data);
+    let mut crc = crc32fast::Hasher::new();
+    crc.update(kind);
+    crc.update(data);
+    png.extend(crc.finalize().to_be_bytes());
+}
+
+/// Renders the measure and copies it to the clipboard on its own thread,
+/// the rendering and the clipboard tools would block the interface.
+pub async fn copy_measure_image(mut measure: CanvasMeasure) -> Result<(), SnapshotError> {
+    let (sender, receiver) = oneshot::channel();
+    std::thread::spawn(move || {
+        let copied = render_measures_png(std::slice::from_mut(&mut measure), f32::INFINITY)
+            .and_then(|png| copy_png_to_clipboard(&png));
+        let _ = sender.send(copied);
+    });
+    receiver
+        .await
+        .map_err(|_| SnapshotError::Clipboard("copy interrupted".to_string()))?
+}
+
+/// Copies the PNG image to the clipboard through the tools of the platform,
+/// the iced clipboard only holds text.
+fn copy_png_to_clipboard(png: &[u8]) -> Result<(), SnapshotError> {
+    let path = std::env::temp_dir().join(format!("ruxguitar-snapshot-{}.png", std::process::id()));
+    std::fs::write(&path, png).map_err(|err| SnapshotError::Clipboard(err.to_string()))?;
+    let mut result = Err(SnapshotError::NoClipboardTool);
+    for mut command in clipboard_commands(&path) {
+        result = match command.status() {
+            Ok(status) if status.success() => Ok(()),
+            Ok(status) => Err(SnapshotError::Clipboard(format!(
+                "{} failed with {status}",
+                command.get_program().to_string_lossy()
+            ))),
+            // try the next tool
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(err) => Err(SnapshotError::Clipboard(err.to_string())),
+        };
+        break;
+    }
+    let _ = std::fs::remove_file(&path);
+    result
+}
+
+/// Commands copying the image file to the clipboard, by order of preference.
+fn clipboard_commands(path: &Path) -> Vec<Command> {
+    if cfg!(target_os = "macos") {
+        let script = format!(
+            "set the clipboard to (read (POSIX file \"{}\") as «class PNGf»)",
+            path.display()
+        );
+        let mut osascript = Command::new("osascript");
+        osascript.args(["-e", &script]);
+        vec![osascript]
+    } else if cfg!(windows) {
+        let script = format!(
+            "Add-Type -AssemblyName System.Windows.Forms, System.Drawing; \
+             [System.Windows.Forms.Clipboard]::SetImage([System.Drawing.Image]::FromFile('{}'))",
+            path.display()
+        );
+        let mut powershell = Command::new("powershell");
+        powershell.args(["-NoProfile", "-STA", "-Command", &script]);
+        vec![powershell]
+    } else {
+        let mut commands = Vec::new();
+        if std::env::var_os("WAYLAND_DISPLAY").is_some()
+            && let Ok(file) = std::fs::File::open(path)
+        {
+            let mut wl_copy = Command::new("wl-copy");
+            wl_copy
+                .args(["--type", "image/png"])
+                .stdin(Stdio::from(file));
+            commands.push(wl_copy);
+        }
+        let mut xclip = Command::new("xclip");
+        xclip
+            .args(["-selection", "clipboard", "-target", "image/png", "-in"])
+            .arg(path);
+        commands.push(xclip);
+        commands
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::song_parser_tests::parse_gp_file;
+    use flate2::read::ZlibDecoder;
+    use std::io::Read;
+
+    #[test]
+    fn png_chunks() {
+        let rgba = [255, 0, 0, 255, 0, 0, 255, 128];
+        let png = encode_png(1, 2, &rgba);
+        assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+        // IHDR: length, type, width and height
+        assert_eq!(&png[8..16], b"\0\0\0\x0dIHDR");
+        assert_eq!(&png[16..24], &[0, 0, 0, 1, 0, 0, 0, 2]);
+        // the well-known CRC of the empty IEND chunk closes the file
+        assert_eq!(&png[png.len() - 12..], b"\0\0\0\0IEND\xae\x42\x60\x82");
+
+        let idat_start = 8 + 25;
+        let idat_len = u32::from_be_bytes(png[idat_start..idat_start + 4].try_into().unwrap());
+        assert_eq!(&png[idat_start + 4..idat_start + 8], b"IDAT");
+        let data = &png[idat_start + 8..idat_start + 8 + idat_len as usize];
+        let mut scanlines = Vec::new();
+        ZlibDecoder::new(data).read_to_end(&mut scanlines).unwrap();
+        assert_eq!(scanlines, [0, 255, 0, 0, 255, 0, 0, 0, 255, 128]);
+    }
+
+    #[test]
+    fn render_measures_offscreen() {
+        let song = Arc::new(parse_gp_file("test-files/Demo v5.gp5").unwrap());
+        let mut measures: Vec<CanvasMeasure> = (0..2)
+            .map(|id| CanvasMeasure::new(id, 0, song.clone(), id == 0, id == 0, false, true))
+            .collect();
+        let png = render_measures_png(&mut measures, f32::INFINITY).unwrap();
+        let width = measures.iter().map(|m| m.total_measure_len).sum::<f32>() + MARGIN * 2.0;
+        let expected = physical(width).to_be_bytes();
+        assert_eq!(&png[16..20], &expected);
+        // the string lines and frets are drawn over the background
+        let idat_len = u32::from_be_bytes(png[33..37].try_into().unwrap()) as usize;
+        let mut scanlines = Vec::new();
+        ZlibDecoder::new(&png[41..41 + idat_len])
+            .read_to_end(&mut scanlines)
+            .unwrap();
+        // the top left corner is in the margin
+        let background = &scanlines[1..5];
+        let drawn = scanlines
+            .chunks(u32::from_be_bytes(expected) as usize * 4 + 1)
+            .flat_map(|scanline| scanline[1..].chunks(4))
+            .filter(|pixel| pixel != &background)
+            .count();
+        assert!(drawn > 1000, "{drawn} pixels drawn");
+    }
+
+    #[test]
+    fn measures_wrap_in_rows() {
+        let widths = [100.0, 100.0, 300.0, 50.0, 60.0];
+        assert_eq!(rows(&widths, 250.0), vec![0..2, 2..3, 3..5]);
+        assert_eq!(rows(&widths, f32::INFINITY), vec![0..5]);
+        assert!(rows(&[], 250.0).is_empty());
+    }
+
+    #[test]
+    fn render_track_rows() {
+        let song = Arc::new(parse_gp_file("test-files/Demo v5.gp5").unwrap());
+        let png = render_track_png(song.clone(), 0, 0..=15).unwrap();
+        let width = u32::from_be_bytes(png[16..20].try_into().unwrap());
+        let height = u32::from_be_bytes(png[20..24].try_into().unwrap());
+        assert!(width <= ((ROW_WIDTH + MARGIN * 2.0) * SCALE_FACTOR) as u32);
+        assert!(height > width / 4, "several rows {width}x{height}");
+
+        let count = song.measure_headers.len();
+        assert!(matches!(
+            render_track_png(song, 0, 0..=count),
+            Err(SnapshotError::MeasuresNotFound(1, _, _))
+        ));
+    }
+}
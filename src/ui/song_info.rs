@@ -0,0 +1,94 @@
+use crate::parser::song_parser::SongInfo;
+use crate::ui::application::Message;
+use iced::advanced::text::Shaping::Auto;
+use iced::widget::{Column, Text, button, container, row, scrollable, text};
+use iced::{Alignment, Border, Element, Length};
+
+const SONG_INFO_HEIGHT: f32 = 160.0;
+
+// Width of the field labels, so the values line up
+const LABEL_WIDTH: f32 = 100.0;
+
+/// Labeled metadata of the song, empty fields are left out.
+fn info_fields(info: &SongInfo) -> Vec<(&'static str, String)> {
+    let words = info.words.clone().unwrap_or_default();
+    let notices = info.notices.join("\n");
+    [
+        ("Title", info.name.clone()),
+        ("Subtitle", info.subtitle.clone()),
+        ("Artist", info.artist.clone()),
+        ("Album", info.album.clone()),
+        ("Music", info.author.clone()),
+        ("Words", words),
+        ("Copyright", info.copyright.clone()),
+        ("Transcriber", info.writer.clone()),
+        ("Instructions", info.instructions.clone()),
+        ("Notices", notices),
+    ]
+    .into_iter()
+    .map(|(label, value)| (label, value.trim().to_string()))
+    .filter(|(_, value)| !value.is_empty())
+    .collect()
+}
+
+/// Song info panel: all the metadata of the file, below the controls.
+pub fn song_info_view(info: &SongInfo) -> Element<'_, Message> {
+    let close = button(text("Close").size(12))
+        .style(button::secondary)
+        .on_press(Message::ToggleSongInfo);
+    let header = row![text("Song info").size(14), close]
+        .spacing(10)
+        .align_y(Alignment::Center);
+
+    let fields = info_fields(info);
+    let entries = if fields.is_empty() {
+        Column::new().push(text("No metadata in this file").size(12))
+    } else {
+        fields
+            .into_iter()
+            .fold(Column::new().spacing(2), |column, (label, value)| {
+                column.push(row![
+                    text(label).size(12).width(LABEL_WIDTH),
+                    Text::new(value).size(12).shaping(Auto)
+                ])
+            })
+    };
+    let list = scrollable(entries.width(Length::Fill)).height(Length::Fixed(SONG_INFO_HEIGHT));
+
+    container(Column::new().push(header).push(list).spacing(5))
+        .padding(10)
+        .width(Length::Fill)
+        .style(|_theme| container::Style {
+            border: Border::default()
+                .color(crate::ui::utils::COLOR_GRAY)
+                .width(1),
+            ..Default::default()
+        })
+        .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_fields_are_left_out() {
+        let info = SongInfo {
+            name: "Sarabande".to_string(),
+            author: "J.S. Bach".to_string(),
+            words: Some(" ".to_string()),
+            writer: "anonymous".to_string(),
+            notices: vec!["BWV 1002".to_string(), "Partita No. 1".to_string()],
+            ..SongInfo::default()
+        };
+        assert_eq!(
+            info_fields(&info),
+            vec![
+                ("Title", "Sarabande".to_string()),
+                ("Music", "J.S. Bach".to_string()),
+                ("Transcriber", "anonymous".to_string()),
+                ("Notices", "BWV 1002\nPartita No. 1".to_string()),
+            ]
+        );
+    }
+}
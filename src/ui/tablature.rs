@@ -1,29 +1,108 @@
-use crate::audio::playback_order::playback_tick;
-use crate::parser::song_parser::Song;
+use crate::analysis::chords::suggested_chords;
+use crate::analysis::difficulty::track_difficulty;
+use crate::analysis::is_percussion;
+use crate::analysis::keys::{Key, outside_scale, section_keys};
+use crate::audio::playback_order::{first_playback_ticks, playback_tick};
+use crate::audio::tempo_map::TempoMap;
+use crate::editor::NoteLocation;
+use crate::parser::song_parser::{Beat, Song};
 use crate::ui::application::Message;
-use crate::ui::canvas_measure::CanvasMeasure;
-use iced::widget::{Id, Row, column, scrollable};
-use iced::{Element, Length};
+use crate::ui::canvas_measure::{CanvasMeasure, has_tremolo_bar, measure_entry_dynamics};
+use crate::ui::snapshot::{SnapshotError, render_measures_png};
+use crate::ui::tuning::tuning_summary;
+use crate::ui::utils::COLOR_GRAY;
+use iced::time::{Duration, Instant};
+use iced::widget::{Id, Row, column, container, scrollable, space, text};
+use iced::{Border, Element, Length, Theme};
 use std::collections::BTreeMap;
-use std::rc::Rc;
+use std::ops::RangeInclusive;
+use std::sync::Arc;
 
 const INNER_PADDING: f32 = 10.0;
 const SCROLLBAR_WIDTH: f32 = 10.0; // iced default scrollbar width (iced_widget/src/scrollable.rs)
+const SCROLL_TOLERANCE: f32 = 1.0; // scroll distance not considered as a manual scroll
+const SCROLL_ANIMATION: Duration = Duration::from_millis(350); // duration of the eased scroll
+const ROWS_AHEAD: u32 = 4; // rows built below the visible ones
+
+/// How the tablature scroll follows the playback.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FollowMode {
+    #[default]
+    Following,
+    /// The user scrolled away during playback.
+    Paused,
+    /// Following again once the playback reaches the next measure.
+    ResumeAtNextMeasure,
+}
+
+/// Eased scroll toward the row of the focused measure.
+#[derive(Debug, Clone, Copy)]
+struct ScrollAnimation {
+    from: f32,
+    to: f32,
+    start: Instant,
+    requested: f32, // offset of the last frame
+    previous: f32,  // offset of the frame before, its scroll event may arrive late
+}
+
+impl ScrollAnimation {
+    /// Offset at the instant, `None` once arrived.
+    fn offset_at(&self, now: Instant) -> Option<f32> {
+        let elapsed = now.saturating_duration_since(self.start).as_secs_f32();
+        let progress = elapsed / SCROLL_ANIMATION.as_secs_f32();
+        (progress < 1.0).then(|| self.from + (self.to - self.from) * ease_in_out(progress))
+    }
+}
+
+/// Cubic ease-in-out: slow start, fast middle, slow arrival.
+fn ease_in_out(progress: f32) -> f32 {
+    if progress < 0.5 {
+        4.0 * progress.powi(3)
+    } else {
+        1.0 - (2.0 - 2.0 * progress).powi(3) / 2.0
+    }
+}
 
 pub struct Tablature {
-    pub song: Rc<Song>,
+    pub song: Arc<Song>,
     pub track_id: usize,
-    pub canvas_measures: Vec<CanvasMeasure>,
+    pub canvas_measures: Vec<CanvasMeasure>, // built measures, from the first one
+    measure_widths: Vec<f32>,                // natural width of every measure, for the layout
     canvas_measure_height: f32,
     focused_measure: usize,
     line_tracker: LineTracker,
     pub scroll_id: Id,
     measure_per_tick: BTreeMap<u32, u32>, // tick to measure index as u32
+    follow_mode: FollowMode,
+    auto_scroll_offset: f32, // last scroll offset requested by the application
+    scale_factor: f32,       // window scale factor used to snap lines to pixels
+    tempo_map: TempoMap,     // tempo along the playback timeline
+    first_ticks: Vec<u32>,   // first playback tick of each measure
+    tempo_percentage: u32,   // playback speed used for the displayed times
+    show_dynamics: bool,     // dynamic markings displayed under the notes
+    show_directions: bool,   // direction signs displayed in the annotation row
+    chord_suggestions: bool, // detected chord names where none is authored
+    scale_highlight: bool,   // notes outside the key of the section highlighted
+    note_names: bool,        // pitches displayed instead of the frets
+    keys: Vec<Option<Key>>,  // key of each measure, empty for drum tracks
+    entry_dynamics: Vec<Option<&'static str>>, // dynamic in effect before each measure
+    suggested_chords: Vec<Vec<Option<String>>>, // detected chords per measure, empty if hidden
+    has_tremolo_bar_area: bool, // room below the staff for the tremolo bar curves
+    difficulty: Vec<f32>,    // difficulty of each measure of the track
+    section: Option<(usize, usize)>, // first and last measures playback is bounded to
+    editing: bool,           // note entry mode
+    edit_cursor: Option<NoteLocation>, // note slot the typed frets go to
+    measures_per_row: usize, // maximum measures on a row, 0 fits the width
+    scroll_offset: f32,      // current vertical scroll offset
+    viewport_height: f32,    // height of the visible part of the tablature
+    smooth_scroll: bool,     // animate the scroll following the playback
+    low_power: bool,         // highlight per measure and no animation
+    scroll_animation: Option<ScrollAnimation>,
 }
 
 impl Tablature {
     pub fn new(
-        song: Rc<Song>,
+        song: Arc<Song>,
         track_id: usize,
         scroll_id: Id,
         playback_order: &[(usize, i64)],
@@ -36,81 +115,406 @@ impl Tablature {
             let tick = playback_tick(header.start, *tick_offset);
             measure_per_tick.insert(tick, *measure_index as u32);
         }
+        let tempo_map = TempoMap::from_song(&song, playback_order);
+        let first_ticks = first_playback_ticks(&song.measure_headers, playback_order);
         let mut tab = Self {
             song,
             track_id,
             canvas_measures: Vec::with_capacity(measure_count),
+            measure_widths: Vec::with_capacity(measure_count),
             canvas_measure_height: 0.0,
             focused_measure: 0,
             line_tracker: LineTracker::default(),
             scroll_id,
             measure_per_tick,
+            follow_mode: FollowMode::default(),
+            auto_scroll_offset: 0.0,
+            scale_factor: 1.0,
+            tempo_map,
+            first_ticks,
+            tempo_percentage: 100,
+            show_dynamics: true,
+            show_directions: true,
+            chord_suggestions: false,
+            scale_highlight: false,
+            note_names: false,
+            keys: Vec::new(),
+            entry_dynamics: Vec::new(),
+            suggested_chords: Vec::new(),
+            has_tremolo_bar_area: false,
+            difficulty: Vec::new(),
+            section: None,
+            editing: false,
+            edit_cursor: None,
+            measures_per_row: 0,
+            scroll_offset: 0.0,
+            viewport_height: 0.0,
+            smooth_scroll: true,
+            low_power: false,
+            scroll_animation: None,
         };
         tab.load_measures();
         tab
     }
 
+    /// Lays out the measures of the track, only the first rows are built right away.
+    /// The others are built as the scroll approaches them.
     pub fn load_measures(&mut self) {
         // clear existing measures
         self.canvas_measures.clear();
 
-        // load new measures
         let track = &self.song.tracks[self.track_id];
-        let measures = track.measures.len();
-        for i in 0..measures {
-            let measure_header = &self.song.measure_headers[i];
-            let previous_measure_header = if i > 0 {
-                self.song.measure_headers.get(i - 1)
-            } else {
-                None
-            };
-            let focused = self.focused_measure == i;
-            let has_time_signature = i == 0
-                || measure_header.time_signature != previous_measure_header.unwrap().time_signature;
-            let measure = CanvasMeasure::new(
-                i,
-                self.track_id,
-                self.song.clone(),
-                focused,
-                has_time_signature,
-            );
-            if i == 0 {
-                // all measures have the same height - grab first one
-                self.canvas_measure_height = measure.vertical_measure_height;
-            }
-            self.canvas_measures.push(measure);
-        }
+        self.has_tremolo_bar_area = has_tremolo_bar(track);
+        self.entry_dynamics = measure_entry_dynamics(track);
+        self.suggested_chords = if self.chord_suggestions && !self.is_percussion() {
+            suggested_chords(track)
+        } else {
+            Vec::new()
+        };
+        self.keys = if self.is_percussion() {
+            Vec::new()
+        } else {
+            section_keys(&self.song)
+        };
+        self.difficulty = track_difficulty(&self.song, self.track_id);
+        // all measures have the same height
+        self.canvas_measure_height = CanvasMeasure::height(
+            track.strings.len(),
+            self.show_dynamics,
+            self.has_tremolo_bar_area,
+        );
+        self.measure_widths = (0..self.song.measure_headers.len())
+            .map(|i| {
+                CanvasMeasure::widths(
+                    &self.song,
+                    self.track_id,
+                    i,
+                    self.has_time_signature(i),
+                    self.pitch_labels(),
+                )
+                .1
+            })
+            .collect();
         // recompute line tracker with existing width
         let existing_width = self.line_tracker.tablature_container_width;
-        self.line_tracker = LineTracker::make(&self.canvas_measures, existing_width);
-        self.update_first_on_line();
+        self.line_tracker = LineTracker::make_from_widths(
+            &self.measure_widths,
+            existing_width,
+            self.measures_per_row,
+        );
+        self.build_visible_measures();
+    }
+
+    /// Time signature displayed on the first measure and when it changes.
+    fn has_time_signature(&self, measure_id: usize) -> bool {
+        measure_id == 0
+            || self.song.measure_headers[measure_id].time_signature
+                != self.song.measure_headers[measure_id - 1].time_signature
+    }
+
+    fn build_measure(&self, i: usize) -> CanvasMeasure {
+        let track = &self.song.tracks[self.track_id];
+        let mut measure = CanvasMeasure::new(
+            i,
+            self.track_id,
+            self.song.clone(),
+            self.focused_measure == i,
+            self.has_time_signature(i),
+            self.has_tremolo_bar_area,
+            self.show_dynamics,
+        );
+        measure.set_entry_dynamic(self.entry_dynamics[i]);
+        measure.set_show_directions(self.show_directions);
+        measure.set_suggested_chords(self.suggested_chords.get(i).cloned().unwrap_or_default());
+        if self.scale_highlight
+            && let Some(key) = self.measure_key(i)
+        {
+            measure.set_outside_scale(outside_scale(track, &track.measures[i], key));
+        }
+        measure.set_note_names(self.pitch_labels());
+        measure.set_in_section(self.in_section(i));
+        measure.set_editing(self.editing);
+        measure.set_edit_cursor(self.edit_cursor_in(i));
+        measure.set_start_seconds(self.elapsed_seconds(self.first_ticks[i]));
+        measure.set_scale_factor(self.scale_factor);
+        measure.set_first_on_line(self.is_first_on_line(i));
+        measure.set_highlight_beat(!self.low_power);
+        measure
+    }
+
+    /// Builds the measures up to the end of the row of `measure_id`, rows are never partial.
+    fn build_measures_until(&mut self, measure_id: usize) {
+        let measure_count = self.measure_count();
+        if measure_id >= measure_count {
+            return;
+        }
+        let line = self.line_tracker.get_line(measure_id);
+        let last = (measure_id..measure_count)
+            .take_while(|&i| self.line_tracker.get_line(i) == line)
+            .last()
+            .unwrap_or(measure_id);
+        while self.canvas_measures.len() <= last {
+            let measure = self.build_measure(self.canvas_measures.len());
+            self.canvas_measures.push(measure);
+        }
+    }
+
+    /// Builds the rows in view, a few rows below them and the focused measure.
+    fn build_visible_measures(&mut self) {
+        if self.canvas_measure_height > 0.0 {
+            let (top_line, visible_lines) = self.visible_lines();
+            let last_line = top_line + visible_lines + ROWS_AHEAD;
+            let last = (0..self.measure_count())
+                .take_while(|&i| self.line_tracker.get_line(i) <= last_line)
+                .last();
+            if let Some(last) = last {
+                self.build_measures_until(last);
+            }
+        }
+        self.build_measures_until(self.focused_measure);
+    }
+
+    /// First line in view and the number of lines the viewport holds.
+    fn visible_lines(&self) -> (u32, u32) {
+        let top_line =
+            1 + ((self.scroll_offset - INNER_PADDING).max(0.0) / self.canvas_measure_height) as u32;
+        let visible_lines = (self.viewport_height / self.canvas_measure_height).max(1.0) as u32;
+        (top_line, visible_lines)
     }
 
     pub fn update_container_width(&mut self, width: f32) {
         // recompute line tracker on width change
-        self.line_tracker = LineTracker::make(
-            &self.canvas_measures,
+        self.line_tracker = LineTracker::make_from_widths(
+            &self.measure_widths,
             width - (INNER_PADDING * 2.0) - SCROLLBAR_WIDTH, // remove padding and scrollbar
+            self.measures_per_row,
         );
         // mark which measures start a new line and clear caches
         self.update_first_on_line();
+        self.build_visible_measures();
+    }
+
+    /// Height of the visible part of the tablature, to know which measures are in view.
+    pub fn set_viewport_height(&mut self, height: f32) {
+        self.viewport_height = height;
+        self.build_visible_measures();
+    }
+
+    /// Low power mode: only the focused measure is highlighted and the scroll jumps.
+    pub fn set_low_power(&mut self, low_power: bool) {
+        self.low_power = low_power;
+        if low_power {
+            self.scroll_animation = None;
+        }
+        for measure in &mut self.canvas_measures {
+            measure.set_highlight_beat(!low_power);
+        }
+    }
+
+    /// Maximum number of measures on a row, `0` fits as many as the width allows.
+    pub fn set_measures_per_row(&mut self, measures_per_row: usize) {
+        if self.measures_per_row != measures_per_row {
+            self.measures_per_row = measures_per_row;
+            let existing_width = self.line_tracker.tablature_container_width;
+            self.line_tracker = LineTracker::make_from_widths(
+                &self.measure_widths,
+                existing_width,
+                measures_per_row,
+            );
+            self.update_first_on_line();
+            self.build_visible_measures();
+        }
+    }
+
+    pub fn set_scale_factor(&mut self, scale_factor: f32) {
+        if self.scale_factor != scale_factor {
+            self.scale_factor = scale_factor;
+            for cm in &self.canvas_measures {
+                cm.set_scale_factor(scale_factor);
+            }
+        }
+    }
+
+    /// Shade the measures from `first` to `last` playback is bounded to, `None` clears it.
+    pub fn set_section(&mut self, section: Option<(usize, usize)>) {
+        self.section = section;
+        for i in 0..self.canvas_measures.len() {
+            let in_section = self.in_section(i);
+            self.canvas_measures[i].set_in_section(in_section);
+        }
+    }
+
+    pub const fn section(&self) -> Option<(usize, usize)> {
+        self.section
+    }
+
+    fn in_section(&self, measure_id: usize) -> bool {
+        self.section
+            .is_some_and(|(first, last)| (first..=last).contains(&measure_id))
+    }
+
+    /// Note entry mode: clicking a note slot selects it, leaving the mode clears the selection.
+    pub fn set_editing(&mut self, editing: bool) {
+        self.editing = editing;
+        for measure in &mut self.canvas_measures {
+            measure.set_editing(editing);
+        }
+        if !editing {
+            self.set_edit_cursor(None);
+        }
+    }
+
+    pub const fn editing(&self) -> bool {
+        self.editing
+    }
+
+    /// Select the note slot the typed frets go to, `None` clears it.
+    pub fn set_edit_cursor(&mut self, edit_cursor: Option<NoteLocation>) {
+        let previous = self.edit_cursor.map(|location| location.measure);
+        self.edit_cursor = edit_cursor;
+        let current = edit_cursor.map(|location| location.measure);
+        for measure_id in previous.into_iter().chain(current) {
+            let cursor = self.edit_cursor_in(measure_id);
+            if let Some(measure) = self.canvas_measures.get_mut(measure_id) {
+                measure.set_edit_cursor(cursor);
+            }
+        }
+    }
+
+    pub const fn edit_cursor(&self) -> Option<NoteLocation> {
+        self.edit_cursor
+    }
+
+    /// Beat and string of the edit cursor if it is in the measure.
+    fn edit_cursor_in(&self, measure_id: usize) -> Option<(usize, i8)> {
+        self.edit_cursor
+            .filter(|location| location.measure == measure_id)
+            .map(|location| (location.beat, location.string))
+    }
+
+    /// Display an edited version of the song, its measures and tempos are unchanged.
+    pub fn set_song(&mut self, song: Arc<Song>) {
+        self.song = song;
+        self.load_measures();
+    }
+
+    /// Show or hide the dynamic markings, the measures are reloaded to fit them.
+    pub fn set_show_dynamics(&mut self, show_dynamics: bool) {
+        if self.show_dynamics != show_dynamics {
+            self.show_dynamics = show_dynamics;
+            self.load_measures();
+        }
+    }
+
+    /// Show or collapse the direction signs of the annotation row, the measures are reloaded.
+    pub fn set_show_directions(&mut self, show_directions: bool) {
+        if self.show_directions != show_directions {
+            self.show_directions = show_directions;
+            self.load_measures();
+        }
+    }
+
+    /// Show or hide the detected chord names, the measures are reloaded to compute them.
+    pub fn set_chord_suggestions(&mut self, chord_suggestions: bool) {
+        if self.chord_suggestions != chord_suggestions {
+            self.chord_suggestions = chord_suggestions;
+            self.load_measures();
+        }
+    }
+
+    /// Highlight the notes outside the key of their section, the measures are reloaded.
+    pub fn set_scale_highlight(&mut self, scale_highlight: bool) {
+        if self.scale_highlight != scale_highlight {
+            self.scale_highlight = scale_highlight;
+            self.load_measures();
+        }
+    }
+
+    /// Show the pitches of the notes instead of their frets, the measures are reloaded.
+    pub fn set_note_names(&mut self, note_names: bool) {
+        if self.note_names != note_names {
+            self.note_names = note_names;
+            self.load_measures();
+        }
+    }
+
+    /// Key guessed for the section of the measure, `None` for drum tracks.
+    pub fn measure_key(&self, measure_id: usize) -> Option<Key> {
+        self.keys.get(measure_id).copied().flatten()
+    }
+
+    /// Difficulty of each measure of the track, only meaningful compared to the other measures.
+    pub fn difficulty(&self) -> &[f32] {
+        &self.difficulty
+    }
+
+    /// Key of the section of the focused measure.
+    pub fn focused_key(&self) -> Option<Key> {
+        self.measure_key(self.focused_measure)
+    }
+
+    /// Drum tracks have no chords nor keys.
+    /// Whether the notes are labeled with their names, drum notes are drum sounds.
+    fn pitch_labels(&self) -> bool {
+        self.note_names && !self.is_percussion()
+    }
+
+    fn is_percussion(&self) -> bool {
+        is_percussion(&self.song, &self.song.tracks[self.track_id])
+    }
+
+    /// Playback speed of the displayed times, updates the start time of each measure.
+    pub fn set_tempo_percentage(&mut self, tempo_percentage: u32) {
+        if self.tempo_percentage != tempo_percentage {
+            self.tempo_percentage = tempo_percentage;
+            for i in 0..self.canvas_measures.len() {
+                let start_seconds = self.elapsed_seconds(self.first_ticks[i]);
+                self.canvas_measures[i].set_start_seconds(start_seconds);
+            }
+        }
+    }
+
+    /// Tempo along the playback timeline.
+    pub const fn tempo_map(&self) -> &TempoMap {
+        &self.tempo_map
+    }
+
+    /// Seconds of playback from the start of the song to the playback tick.
+    pub fn elapsed_seconds(&self, tick: u32) -> f64 {
+        let Some((&first_tick, _)) = self.measure_per_tick.first_key_value() else {
+            return 0.0;
+        };
+        self.tempo_map
+            .seconds_between(first_tick, tick, self.tempo_percentage)
+    }
+
+    /// Seconds of playback of the whole song, repeats and jumps included.
+    pub fn duration_seconds(&self) -> f64 {
+        let Some((&last_tick, &measure_index)) = self.measure_per_tick.last_key_value() else {
+            return 0.0;
+        };
+        let end_tick = last_tick + self.song.measure_headers[measure_index as usize].length();
+        self.elapsed_seconds(end_tick)
     }
 
     /// Update the `is_first_on_line` flag on each measure based on the line tracker
     /// and clear caches for measures that changed line assignment.
     fn update_first_on_line(&mut self) {
-        let mut prev_line = 0_u32;
-        for cm in &mut self.canvas_measures {
-            let line = self.line_tracker.get_line(cm.measure_id);
-            let is_first = line != prev_line;
+        for i in 0..self.canvas_measures.len() {
+            let is_first = self.is_first_on_line(i);
+            let cm = &mut self.canvas_measures[i];
             if cm.is_first_on_line != is_first {
                 cm.set_first_on_line(is_first);
                 cm.clear_canvas_cache();
             }
-            prev_line = line;
         }
     }
 
+    fn is_first_on_line(&self, measure_id: usize) -> bool {
+        measure_id == 0
+            || self.line_tracker.get_line(measure_id) != self.line_tracker.get_line(measure_id - 1)
+    }
+
     /// Get the measure and beat indexes for the given tick
     /// The measure index is the first measure containing the tick
     ///
@@ -159,6 +563,40 @@ impl Tablature {
         (measure_index, beat_index)
     }
 
+    /// Playback tick of the beat after (or before) the beat at the given tick,
+    /// crossing measures along the playback order.
+    pub fn adjacent_beat_tick(&self, tick: u32, forward: bool) -> Option<u32> {
+        let (&first_tick, _) = self.measure_per_tick.first_key_value()?;
+        let tick = tick.max(first_tick);
+        let (&measure_tick, &measure_index) = self.measure_per_tick.range(..=tick).next_back()?;
+        // playback ticks of the beats of a measure played at `measure_tick`
+        let beat_ticks = |measure_tick: u32, measure_index: u32| -> Vec<u32> {
+            let measure_index = measure_index as usize;
+            let header_start = self.song.measure_headers[measure_index].start;
+            let voice = &self.song.tracks[self.track_id].measures[measure_index].voices[0];
+            voice
+                .beats
+                .iter()
+                .map(|beat| measure_tick + beat.start.saturating_sub(header_start))
+                .collect()
+        };
+        let ticks = beat_ticks(measure_tick, measure_index);
+        if forward {
+            if let Some(&next) = ticks.iter().find(|&&t| t > tick) {
+                return Some(next);
+            }
+            let (&next_tick, &next_index) = self.measure_per_tick.range(tick + 1..).next()?;
+            beat_ticks(next_tick, next_index).first().copied()
+        } else {
+            if let Some(&previous) = ticks.iter().rev().find(|&&t| t < tick) {
+                return Some(previous);
+            }
+            let (&previous_tick, &previous_index) =
+                self.measure_per_tick.range(..measure_tick).next_back()?;
+            beat_ticks(previous_tick, previous_index).last().copied()
+        }
+    }
+
     /// Focus on the beat at the given tick
     ///
     /// Returns the amount of scroll needed to focus on the beat
@@ -177,14 +615,27 @@ impl Tablature {
             // move to next measure
             current_canvas.toggle_focused();
             let next_focus_id = new_measure_id;
-            if next_focus_id < self.canvas_measures.len() {
+            if next_focus_id < self.measure_count() {
+                self.build_measures_until(next_focus_id);
                 self.focused_measure = next_focus_id;
                 let next_canvas = self.canvas_measures.get_mut(next_focus_id).unwrap();
                 next_canvas.toggle_focused();
                 // beat notifications coalesce, so the first tick in a measure
                 // may already be past beat 0
                 next_canvas.focus_beat(new_beat_id);
-                return self.scroll_offset_for_measure(next_focus_id);
+                let scroll_offset = match self.follow_mode {
+                    FollowMode::Following => self.scroll_offset_for_measure(next_focus_id),
+                    FollowMode::Paused => None,
+                    FollowMode::ResumeAtNextMeasure => {
+                        self.follow_mode = FollowMode::Following;
+                        // bring the measure back into view even on the first lines
+                        Some(self.scroll_offset_for_measure(next_focus_id).unwrap_or(0.0))
+                    }
+                };
+                if let Some(offset) = scroll_offset {
+                    self.auto_scroll_offset = offset;
+                }
+                return scroll_offset;
             }
         }
         None
@@ -195,6 +646,7 @@ impl Tablature {
         if current_focus_id != new_measure_id {
             let current_canvas = self.canvas_measures.get_mut(current_focus_id).unwrap();
             current_canvas.toggle_focused();
+            self.build_measures_until(new_measure_id);
             self.focused_measure = new_measure_id;
             let next_canvas = self.canvas_measures.get_mut(new_measure_id).unwrap();
             next_canvas.toggle_focused();
@@ -205,8 +657,148 @@ impl Tablature {
         self.focused_measure
     }
 
+    /// Copy of the focused measure with the display settings of the tablature,
+    /// to render it away from the view.
+    pub fn focused_measure_copy(&self) -> CanvasMeasure {
+        self.build_measure(self.focused_measure)
+    }
+
+    /// PNG image of the row of the focused measure, as laid out in the view.
+    pub fn focused_row_png(&mut self) -> Result<Vec<u8>, SnapshotError> {
+        let line = self.line_tracker.get_line(self.focused_measure);
+        let on_line = |id: &usize| self.line_tracker.get_line(*id) == line;
+        let first = (0..self.focused_measure)
+            .rev()
+            .take_while(on_line)
+            .last()
+            .unwrap_or(self.focused_measure);
+        let last = (self.focused_measure..self.canvas_measures.len())
+            .take_while(on_line)
+            .last()
+            .unwrap_or(self.focused_measure);
+        // the rows of built measures are complete
+        render_measures_png(&mut self.canvas_measures[first..=last], f32::INFINITY)
+    }
+
+    /// Beat under focus in the first voice of the focused measure.
+    pub fn focused_beat(&self) -> Option<&Beat> {
+        let beat_id = self
+            .canvas_measures
+            .get(self.focused_measure)?
+            .focused_beat();
+        self.song.tracks[self.track_id].measures[self.focused_measure].voices[0]
+            .beats
+            .get(beat_id)
+    }
+
     pub const fn measure_count(&self) -> usize {
-        self.canvas_measures.len()
+        self.measure_widths.len()
+    }
+
+    /// Built measures from `measures.start()` to `measures.end()`, `None` past the last one.
+    pub fn measures_mut(
+        &mut self,
+        measures: RangeInclusive<usize>,
+    ) -> Option<&mut [CanvasMeasure]> {
+        self.build_measures_until(*measures.end());
+        self.canvas_measures.get_mut(measures)
+    }
+
+    pub const fn follow_mode(&self) -> FollowMode {
+        self.follow_mode
+    }
+
+    /// Follow the playback again, e.g. when it starts.
+    pub const fn follow_playback(&mut self) {
+        self.follow_mode = FollowMode::Following;
+    }
+
+    /// Re-engage following at the next measure boundary.
+    pub fn resume_following(&mut self) {
+        if self.follow_mode == FollowMode::Paused {
+            self.follow_mode = FollowMode::ResumeAtNextMeasure;
+        }
+    }
+
+    /// Record a scroll requested by the application, to tell it apart from manual scrolls.
+    pub const fn expect_scroll(&mut self, offset: f32) {
+        self.auto_scroll_offset = offset;
+        self.scroll_animation = None;
+    }
+
+    /// Animate the scroll following the playback, or jump to the focused row.
+    pub const fn set_smooth_scroll(&mut self, smooth_scroll: bool) {
+        self.smooth_scroll = smooth_scroll;
+        self.scroll_animation = None;
+    }
+
+    /// Scroll toward the offset following the playback: the offset to jump to,
+    /// `None` if animated through `scroll_frame`.
+    pub fn scroll_towards(&mut self, offset: f32, now: Instant) -> Option<f32> {
+        if !self.smooth_scroll || self.low_power {
+            return Some(offset);
+        }
+        // a new row while animating continues from the current position
+        let from = self
+            .scroll_animation
+            .map_or(self.scroll_offset, |animation| animation.requested);
+        self.scroll_animation = Some(ScrollAnimation {
+            from,
+            to: offset,
+            start: now,
+            requested: from,
+            previous: from,
+        });
+        None
+    }
+
+    pub const fn is_scroll_animated(&self) -> bool {
+        self.scroll_animation.is_some()
+    }
+
+    /// Offset of the animated scroll at the instant, the last one is the target.
+    pub fn scroll_frame(&mut self, now: Instant) -> Option<f32> {
+        let mut animation = self.scroll_animation?;
+        let offset = animation.offset_at(now).unwrap_or(animation.to);
+        animation.previous = animation.requested;
+        animation.requested = offset;
+        self.scroll_animation = (offset != animation.to).then_some(animation);
+        self.auto_scroll_offset = offset;
+        Some(offset)
+    }
+
+    /// Scroll offset to show the measure, the view follows the playback again.
+    pub fn scroll_to_measure(&mut self, measure_id: usize) -> Option<f32> {
+        self.follow_mode = FollowMode::Following;
+        self.scroll_animation = None;
+        let offset = self.scroll_offset_for_measure(measure_id);
+        if let Some(offset) = offset {
+            self.auto_scroll_offset = offset;
+        }
+        offset
+    }
+
+    /// Pause following when the user scrolls away from the auto-scroll position during playback.
+    /// `max_offset` accounts for requested offsets clamped at the end of the content.
+    pub fn on_scrolled(&mut self, offset: f32, max_offset: f32, is_playing: bool) {
+        self.scroll_offset = offset;
+        self.build_visible_measures();
+        let clamp = |expected: f32| expected.min(max_offset.max(0.0));
+        let expected = clamp(self.auto_scroll_offset);
+        if (offset - expected).abs() <= SCROLL_TOLERANCE {
+            return;
+        }
+        if let Some(animation) = self.scroll_animation {
+            if (offset - clamp(animation.previous)).abs() <= SCROLL_TOLERANCE {
+                return;
+            }
+            // the user takes over the scroll
+            self.scroll_animation = None;
+        }
+        if is_playing && self.follow_mode == FollowMode::Following {
+            log::debug!("Manual scroll to {offset} (expected {expected}), pause following");
+            self.follow_mode = FollowMode::Paused;
+        }
     }
 
     pub fn scroll_offset_for_measure(&self, measure_id: usize) -> Option<f32> {
@@ -218,6 +810,65 @@ impl Tablature {
         Some(INNER_PADDING + scroll_line as f32 * self.canvas_measure_height)
     }
 
+    /// Measure described by the header: the focused one while in view,
+    /// else the first one of the top row.
+    fn header_measure(&self) -> usize {
+        if self.canvas_measure_height <= 0.0 {
+            return self.focused_measure;
+        }
+        let (top_line, visible_lines) = self.visible_lines();
+        let focus_line = self.line_tracker.get_line(self.focused_measure);
+        if (top_line..top_line + visible_lines).contains(&focus_line) {
+            return self.focused_measure;
+        }
+        (0..self.measure_count())
+            .find(|&measure| self.line_tracker.get_line(measure) >= top_line)
+            .unwrap_or(self.focused_measure)
+    }
+
+    /// Pinned above the measures: tuning of the track, section, measure and time signature.
+    fn header_view(&self) -> Element<'_, Message> {
+        let measure = self.header_measure();
+        let track = &self.song.tracks[self.track_id];
+        let mut parts = Vec::new();
+        if !self.is_percussion()
+            && let Some(tuning) = tuning_summary(&track.strings)
+        {
+            parts.push(tuning);
+        }
+        if track.offset > 0 {
+            parts.push(format!("Capo {}", track.offset));
+        }
+        // the section lasts until the next marker
+        let section = self.song.measure_headers[..=measure]
+            .iter()
+            .rev()
+            .find_map(|header| header.marker.as_ref());
+        if let Some(marker) = section {
+            parts.push(marker.title.clone());
+        }
+        let time_signature = &self.song.measure_headers[measure].time_signature;
+        parts.push(format!(
+            "Bar {} \u{2022} {}/{}",
+            measure + 1,
+            time_signature.numerator,
+            time_signature.denominator.value
+        ));
+        container(
+            text(parts.join(" \u{2022} "))
+                .size(12)
+                .shaping(text::Shaping::Advanced),
+        )
+        .padding([2, INNER_PADDING as u16])
+        .width(Length::Fill)
+        .style(|theme: &Theme| container::Style {
+            background: Some(theme.palette().background.into()),
+            border: Border::default().color(COLOR_GRAY).width(1),
+            ..Default::default()
+        })
+        .into()
+    }
+
     pub fn view(&self) -> Element<'_, Message> {
         let has_layout = self.line_tracker.tablature_container_width > 0.0;
 
@@ -244,6 +895,15 @@ impl Tablature {
             if !current_row.is_empty() {
                 rows.push(Row::with_children(current_row).width(row_width).into());
             }
+            // the rows not built yet keep their room for the scrollbar
+            let last_line = self
+                .measure_count()
+                .checked_sub(1)
+                .map_or(0, |last| self.line_tracker.get_line(last));
+            if last_line > current_line {
+                let height = (last_line - current_line) as f32 * self.canvas_measure_height;
+                rows.push(space().height(height).into());
+            }
 
             column(rows).padding(INNER_PADDING).into()
         } else {
@@ -259,18 +919,27 @@ impl Tablature {
                 .into()
         };
 
-        scrollable(content)
+        let measures = scrollable(content)
             .id(self.scroll_id.clone())
+            .on_scroll(|viewport| {
+                let max_offset = viewport.content_bounds().height - viewport.bounds().height;
+                Message::TablatureScrolled(viewport.absolute_offset().y, max_offset)
+            })
             .height(Length::Fill)
             .width(Length::Fill)
-            .direction(scrollable::Direction::default())
-            .into()
+            .direction(scrollable::Direction::default());
+        if self.canvas_measures.is_empty() {
+            return measures.into();
+        }
+        column![self.header_view(), measures].into()
     }
 
     pub fn update_track(&mut self, track: usize) {
         // No op if track is the same
         if track != self.track_id {
             self.track_id = track;
+            // the edit cursor is on a string of the previous track
+            self.edit_cursor = None;
             self.load_measures();
         }
     }
@@ -283,23 +952,29 @@ struct LineTracker {
 }
 
 impl LineTracker {
-    pub fn make(measures: &[CanvasMeasure], tablature_container_width: f32) -> Self {
-        let widths: Vec<f32> = measures.iter().map(|m| m.total_measure_len).collect();
-        Self::make_from_widths(&widths, tablature_container_width)
-    }
-
-    fn make_from_widths(widths: &[f32], tablature_container_width: f32) -> Self {
+    /// A new line starts when the width is exceeded or the line holds `measures_per_row`
+    /// measures (unlimited if `0`).
+    fn make_from_widths(
+        widths: &[f32],
+        tablature_container_width: f32,
+        measures_per_row: usize,
+    ) -> Self {
         let mut line_tracker = Self {
             measure_to_line: vec![0; widths.len()],
             tablature_container_width,
         };
         let mut current_line = 1;
         let mut horizontal_cursor = 0.0;
+        let mut line_measures = 0;
         for (i, &width) in widths.iter().enumerate() {
             horizontal_cursor += width;
-            if horizontal_cursor > tablature_container_width {
+            line_measures += 1;
+            if horizontal_cursor > tablature_container_width
+                || (measures_per_row > 0 && line_measures > measures_per_row)
+            {
                 current_line += 1;
                 horizontal_cursor = width;
+                line_measures = 1;
             }
             line_tracker.measure_to_line[i] = current_line;
         }
@@ -318,7 +993,7 @@ mod tests {
     #[test]
     fn line_tracker_single_line() {
         let widths = vec![100.0, 100.0, 100.0];
-        let tracker = LineTracker::make_from_widths(&widths, 500.0);
+        let tracker = LineTracker::make_from_widths(&widths, 500.0, 0);
         assert_eq!(tracker.get_line(0), 1);
         assert_eq!(tracker.get_line(1), 1);
         assert_eq!(tracker.get_line(2), 1);
@@ -327,7 +1002,7 @@ mod tests {
     #[test]
     fn line_tracker_wraps_to_multiple_lines() {
         let widths = vec![100.0, 100.0, 100.0, 100.0];
-        let tracker = LineTracker::make_from_widths(&widths, 250.0);
+        let tracker = LineTracker::make_from_widths(&widths, 250.0, 0);
         // first two fit (200 < 250), third overflows (300 >= 250)
         assert_eq!(tracker.get_line(0), 1);
         assert_eq!(tracker.get_line(1), 1);
@@ -339,7 +1014,7 @@ mod tests {
     fn line_tracker_exact_fit_stays() {
         // measures that exactly fill the width should stay on the same line
         let widths = vec![100.0, 100.0, 100.0];
-        let tracker = LineTracker::make_from_widths(&widths, 200.0);
+        let tracker = LineTracker::make_from_widths(&widths, 200.0, 0);
         assert_eq!(tracker.get_line(0), 1);
         assert_eq!(tracker.get_line(1), 1); // 200 == 200, fits exactly
         assert_eq!(tracker.get_line(2), 2); // 300 > 200, wraps
@@ -349,7 +1024,7 @@ mod tests {
     fn line_tracker_single_wide_measure() {
         // a measure wider than the container gets its own line
         let widths = vec![50.0, 300.0, 50.0];
-        let tracker = LineTracker::make_from_widths(&widths, 200.0);
+        let tracker = LineTracker::make_from_widths(&widths, 200.0, 0);
         assert_eq!(tracker.get_line(0), 1);
         assert_eq!(tracker.get_line(1), 2);
         assert_eq!(tracker.get_line(2), 3);
@@ -358,7 +1033,7 @@ mod tests {
     #[test]
     fn line_tracker_varying_widths() {
         let widths = vec![80.0, 60.0, 90.0, 70.0, 50.0];
-        let tracker = LineTracker::make_from_widths(&widths, 200.0);
+        let tracker = LineTracker::make_from_widths(&widths, 200.0, 0);
         // line 1: 80 + 60 = 140 < 200
         // line 1: 140 + 90 = 230 >= 200 → wrap
         // line 2: 90 + 70 = 160 < 200
@@ -370,17 +1045,28 @@ mod tests {
         assert_eq!(tracker.get_line(4), 3);
     }
 
+    #[test]
+    fn line_tracker_measures_per_row() {
+        let widths = vec![50.0; 7];
+        let tracker = LineTracker::make_from_widths(&widths, 1000.0, 3);
+        let lines: Vec<u32> = (0..widths.len()).map(|i| tracker.get_line(i)).collect();
+        assert_eq!(lines, vec![1, 1, 1, 2, 2, 2, 3]);
+        // the width still wraps before the limit
+        let tracker = LineTracker::make_from_widths(&widths, 120.0, 3);
+        assert_eq!(tracker.get_line(2), 2);
+    }
+
     #[test]
     fn line_tracker_empty() {
         let widths: Vec<f32> = vec![];
-        let tracker = LineTracker::make_from_widths(&widths, 500.0);
+        let tracker = LineTracker::make_from_widths(&widths, 500.0, 0);
         assert_eq!(tracker.measure_to_line.len(), 0);
     }
 
     #[test]
     fn first_on_line_detection() {
         let widths = vec![100.0, 100.0, 100.0, 100.0];
-        let tracker = LineTracker::make_from_widths(&widths, 250.0);
+        let tracker = LineTracker::make_from_widths(&widths, 250.0, 0);
         // lines: [1, 1, 2, 2]
         let mut prev_line = 0_u32;
         let mut first_on_line = Vec::new();
@@ -391,4 +1077,248 @@ mod tests {
         }
         assert_eq!(first_on_line, vec![true, false, true, false]);
     }
+
+    fn demo_tablature(container_width: f32) -> Tablature {
+        let song =
+            crate::parser::song_parser_tests::parse_gp_file("test-files/Demo v5.gp5").unwrap();
+        let playback_order =
+            crate::audio::playback_order::compute_playback_order(&song.measure_headers);
+        let mut tablature = Tablature::new(Arc::new(song), 0, Id::new("test"), &playback_order);
+        tablature.update_container_width(container_width);
+        tablature
+    }
+
+    #[test]
+    fn displayed_times_follow_playback_order_and_tempo() {
+        let mut tablature = demo_tablature(1000.0);
+        let headers = &tablature.song.measure_headers;
+        // every played measure at its own tempo
+        let order = crate::audio::playback_order::compute_playback_order(headers);
+        let expected: f64 = order
+            .iter()
+            .map(|(index, _)| {
+                let header = &headers[*index];
+                f64::from(header.length()) / 960.0 * 60.0 / f64::from(header.tempo.value)
+            })
+            .sum();
+        assert!((tablature.duration_seconds() - expected).abs() < 1e-6);
+        assert!(tablature.elapsed_seconds(measure_tick(&tablature, 0)).abs() < 1e-9);
+        // a 4/4 measure at 165 BPM lasts 240/165 seconds
+        let second_measure = tablature.elapsed_seconds(measure_tick(&tablature, 1));
+        assert!((second_measure - 240.0 / 165.0).abs() < 1e-9);
+
+        // half speed doubles every time
+        tablature.set_tempo_percentage(50);
+        assert!((tablature.duration_seconds() - expected * 2.0).abs() < 1e-6);
+        assert!((tablature.canvas_measures[1].start_seconds() - second_measure * 2.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn header_follows_focus_or_scroll() {
+        let mut tablature = demo_tablature(1000.0);
+        tablature.set_measures_per_row(1);
+        let height = tablature.canvas_measure_height;
+        tablature.set_viewport_height(height * 3.0);
+        assert_eq!(tablature.header_measure(), 0);
+        // the focused measure scrolled out of view, the top row is described
+        tablature.on_scrolled(INNER_PADDING + height * 5.0, f32::MAX, false);
+        assert_eq!(tablature.header_measure(), 5);
+        tablature.focus_on_measure(6);
+        assert_eq!(tablature.header_measure(), 6);
+    }
+
+    #[test]
+    fn measures_built_as_scrolling_approaches() {
+        let mut tablature = demo_tablature(1000.0);
+        tablature.set_measures_per_row(1);
+        let height = tablature.canvas_measure_height;
+        tablature.set_viewport_height(height * 2.0);
+        // the rows in view and a few below them
+        assert!(tablature.canvas_measures.len() < tablature.measure_count());
+        tablature.on_scrolled(INNER_PADDING + height * 30.0, f32::MAX, false);
+        // 30 rows above, 2 in view, one partly visible and the rows ahead
+        assert_eq!(
+            tablature.canvas_measures.len(),
+            30 + 2 + 1 + ROWS_AHEAD as usize
+        );
+        // jumping ahead builds the measures before the focus
+        let last = tablature.measure_count() - 1;
+        tablature.focus_on_measure(last);
+        assert_eq!(tablature.canvas_measures.len(), tablature.measure_count());
+        assert!(tablature.canvas_measures[last].is_first_on_line);
+    }
+
+    #[test]
+    fn animated_scroll() {
+        assert!(ease_in_out(0.0).abs() < f32::EPSILON);
+        assert!((ease_in_out(0.5) - 0.5).abs() < f32::EPSILON);
+        assert!((ease_in_out(1.0) - 1.0).abs() < f32::EPSILON);
+
+        let mut tablature = demo_tablature(1000.0);
+        let start = Instant::now();
+        assert_eq!(tablature.scroll_towards(300.0, start), None);
+        let halfway = tablature
+            .scroll_frame(start + SCROLL_ANIMATION / 2)
+            .unwrap();
+        assert!((halfway - 150.0).abs() < 1.0);
+        // the late event of the previous frame is not a manual scroll
+        let three_quarters = tablature
+            .scroll_frame(start + SCROLL_ANIMATION * 3 / 4)
+            .unwrap();
+        tablature.on_scrolled(halfway, 1000.0, true);
+        tablature.on_scrolled(three_quarters, 1000.0, true);
+        assert_eq!(tablature.follow_mode(), FollowMode::Following);
+        assert_eq!(
+            tablature.scroll_frame(start + SCROLL_ANIMATION),
+            Some(300.0)
+        );
+        assert!(!tablature.is_scroll_animated());
+
+        // a manual scroll interrupts the animation
+        tablature.scroll_towards(600.0, start);
+        tablature.scroll_frame(start + SCROLL_ANIMATION / 4);
+        tablature.on_scrolled(100.0, 1000.0, true);
+        assert!(!tablature.is_scroll_animated());
+        assert_eq!(tablature.follow_mode(), FollowMode::Paused);
+
+        // low power mode jumps as well
+        tablature.set_low_power(true);
+        assert_eq!(tablature.scroll_towards(300.0, start), Some(300.0));
+        tablature.set_low_power(false);
+
+        // instant jumps
+        tablature.set_smooth_scroll(false);
+        assert_eq!(tablature.scroll_towards(300.0, start), Some(300.0));
+    }
+
+    // narrow container: one measure per line
+    fn narrow_demo_tablature() -> Tablature {
+        demo_tablature(1.0)
+    }
+
+    /// First playback tick of the measure.
+    fn measure_tick(tablature: &Tablature, measure_id: usize) -> u32 {
+        tablature
+            .measure_per_tick
+            .iter()
+            .find(|(_, m)| **m as usize == measure_id)
+            .map(|(tick, _)| *tick)
+            .unwrap()
+    }
+
+    #[test]
+    fn manual_scroll_pauses_following() {
+        let mut tablature = narrow_demo_tablature();
+        let offset = tablature.focus_on_tick(measure_tick(&tablature, 4));
+        assert!(offset.is_some());
+        // notification of the auto-scroll itself
+        tablature.on_scrolled(offset.unwrap(), 10_000.0, true);
+        assert_eq!(tablature.follow_mode(), FollowMode::Following);
+
+        // user scrolls away
+        tablature.on_scrolled(0.0, 10_000.0, true);
+        assert_eq!(tablature.follow_mode(), FollowMode::Paused);
+        assert_eq!(tablature.focus_on_tick(measure_tick(&tablature, 5)), None);
+        assert_eq!(tablature.focused_measure(), 5);
+    }
+
+    #[test]
+    fn manual_scroll_when_stopped_is_ignored() {
+        let mut tablature = narrow_demo_tablature();
+        tablature.on_scrolled(500.0, 10_000.0, false);
+        assert_eq!(tablature.follow_mode(), FollowMode::Following);
+    }
+
+    #[test]
+    fn clamped_auto_scroll_is_not_manual() {
+        let mut tablature = narrow_demo_tablature();
+        tablature.expect_scroll(900.0);
+        // content end reached before the requested offset
+        tablature.on_scrolled(600.0, 600.0, true);
+        assert_eq!(tablature.follow_mode(), FollowMode::Following);
+    }
+
+    #[test]
+    fn resume_following_at_next_measure() {
+        let mut tablature = narrow_demo_tablature();
+        tablature.focus_on_tick(measure_tick(&tablature, 4));
+        tablature.on_scrolled(0.0, 10_000.0, true);
+        tablature.resume_following();
+        assert_eq!(tablature.follow_mode(), FollowMode::ResumeAtNextMeasure);
+
+        // same measure: still waiting for the boundary
+        assert_eq!(
+            tablature.focus_on_tick(measure_tick(&tablature, 4) + 1),
+            None
+        );
+        assert_eq!(tablature.follow_mode(), FollowMode::ResumeAtNextMeasure);
+
+        let offset = tablature.focus_on_tick(measure_tick(&tablature, 5));
+        assert_eq!(offset, tablature.scroll_offset_for_measure(5));
+        assert_eq!(tablature.follow_mode(), FollowMode::Following);
+    }
+
+    #[test]
+    fn resume_following_scrolls_back_to_first_lines() {
+        // wide container: all measures on the first line
+        let mut tablature = demo_tablature(100_000.0);
+        tablature.on_scrolled(500.0, 10_000.0, true);
+        tablature.resume_following();
+        // the first lines do not need scrolling but the view was moved away
+        assert_eq!(
+            tablature.focus_on_tick(measure_tick(&tablature, 1)),
+            Some(0.0)
+        );
+    }
+
+    #[test]
+    fn beat_navigation_follows_playback_order() {
+        let tablature = demo_tablature(1000.0);
+        let beat_count = |measure_id: usize| {
+            tablature.song.tracks[0].measures[measure_id].voices[0]
+                .beats
+                .len()
+        };
+        let first = measure_tick(&tablature, 0);
+        assert_eq!(tablature.adjacent_beat_tick(first, false), None);
+        // before the first measure
+        assert_eq!(
+            tablature.adjacent_beat_tick(1, true),
+            tablature.adjacent_beat_tick(first, true)
+        );
+
+        let mut tick = first;
+        for _ in 0..beat_count(0) {
+            tick = tablature.adjacent_beat_tick(tick, true).unwrap();
+        }
+        assert_eq!(tick, measure_tick(&tablature, 1));
+        for _ in 0..beat_count(0) {
+            tick = tablature.adjacent_beat_tick(tick, false).unwrap();
+        }
+        assert_eq!(tick, first);
+
+        // the repeat goes back to the first measure after the fourth one
+        let mut tick = measure_tick(&tablature, 3);
+        for _ in 0..beat_count(3) {
+            tick = tablature.adjacent_beat_tick(tick, true).unwrap();
+        }
+        let second_pass = tablature
+            .measure_per_tick
+            .iter()
+            .filter(|(_, m)| **m == 0)
+            .nth(1)
+            .map(|(tick, _)| *tick);
+        assert_eq!(Some(tick), second_pass);
+    }
+
+    #[test]
+    fn navigation_follows_again() {
+        let mut tablature = narrow_demo_tablature();
+        tablature.on_scrolled(500.0, 10_000.0, true);
+        assert_eq!(tablature.follow_mode(), FollowMode::Paused);
+        tablature.focus_on_measure(6);
+        let offset = tablature.scroll_to_measure(6);
+        assert!(offset.is_some());
+        assert_eq!(tablature.follow_mode(), FollowMode::Following);
+    }
 }
@@ -0,0 +1,314 @@
+use crate::parser::song_parser::MeasureHeader;
+use crate::ui::application::Message;
+use crate::ui::timeline::PlaybackSegment;
+use crate::ui::utils::catch_panic;
+use iced::advanced::mouse;
+use iced::alignment::Vertical;
+use iced::widget::canvas::{self, Action, Event, Frame, Geometry, Path, Stroke, Text};
+use iced::widget::text::Alignment;
+use iced::{Color, Element, Length, Point, Rectangle, Renderer, Theme};
+use std::ops::Range;
+
+const STRIP_HEIGHT: f32 = 28.0;
+
+// Space above and below the tempo line
+const VERTICAL_PADDING: f32 = 5.0;
+
+// Space right of the tempo line for the tempo range
+const LABEL_WIDTH: f32 = 60.0;
+
+// Heatmap from the easiest measures to the hardest ones
+const COLOR_EASY: Color = Color::from_rgb8(0x3C, 0x8D, 0x40);
+const COLOR_MEDIUM: Color = Color::from_rgb8(0xD8, 0xB4, 0x2C);
+const COLOR_HARD: Color = Color::from_rgb8(0xC8, 0x32, 0x32);
+
+/// Tempo along the playback timeline, clicking the strip seeks to the clicked position.
+/// The difficulty of the played measures can be shown as a heatmap under the tempo.
+pub struct TempoStrip {
+    changes: Vec<(u32, u32)>, // (tick, tempo) sorted by tick, from the tempo of the first measure
+    ticks: Range<u32>,        // playback ticks from the first measure to the end of the last one
+    current_tick: u32,
+    heatmap: Vec<(Range<u32>, f32)>, // played measures ticks and difficulty from 0 to 1
+}
+
+impl TempoStrip {
+    pub fn new(changes: &[(u32, u32)], ticks: Range<u32>, current_tick: u32) -> Self {
+        // the tempos before the first measure are not played
+        let first = changes
+            .partition_point(|(tick, _)| *tick <= ticks.start)
+            .saturating_sub(1);
+        Self {
+            changes: changes[first..].to_vec(),
+            ticks,
+            current_tick,
+            heatmap: Vec::new(),
+        }
+    }
+
+    pub fn with_heatmap(mut self, heatmap: Vec<(Range<u32>, f32)>) -> Self {
+        self.heatmap = heatmap;
+        self
+    }
+
+    pub fn view(self) -> Element<'static, Message> {
+        canvas::Canvas::new(self)
+            .width(Length::Fill)
+            .height(STRIP_HEIGHT)
+            .into()
+    }
+
+    /// Whether the tempo changes during the song.
+    pub fn has_changes(&self) -> bool {
+        self.changes.windows(2).any(|pair| pair[0].1 != pair[1].1)
+    }
+
+    /// Not worth showing without tempo changes nor heatmap.
+    pub fn is_empty(&self) -> bool {
+        !self.has_changes() && self.heatmap.is_empty()
+    }
+
+    /// Slowest and fastest tempos of the song.
+    fn tempo_range(&self) -> (u32, u32) {
+        let tempos = self.changes.iter().map(|(_, tempo)| *tempo);
+        (
+            tempos.clone().min().unwrap_or_default(),
+            tempos.max().unwrap_or_default(),
+        )
+    }
+
+    fn tick_count(&self) -> f32 {
+        self.ticks.len().max(1) as f32
+    }
+
+    fn tick_x(&self, tick: u32, width: f32) -> f32 {
+        let tick = tick.clamp(self.ticks.start, self.ticks.end);
+        (tick - self.ticks.start) as f32 / self.tick_count() * width
+    }
+
+    /// Playback tick under the horizontal position, the end of the strip is the last tick played.
+    fn x_tick(&self, x: f32, width: f32) -> u32 {
+        let offset = ((x / width).clamp(0.0, 1.0) * self.tick_count()) as u32;
+        (self.ticks.start + offset).min(self.ticks.end.saturating_sub(1))
+    }
+}
+
+/// Difficulty of the measures along the playback timeline, relative to the hardest measure.
+pub fn difficulty_heatmap(
+    segments: &[PlaybackSegment],
+    headers: &[MeasureHeader],
+    difficulty: &[f32],
+) -> Vec<(Range<u32>, f32)> {
+    let hardest = difficulty.iter().copied().fold(0.0, f32::max);
+    if hardest <= 0.0 {
+        return Vec::new();
+    }
+    let mut heatmap = Vec::new();
+    for segment in segments {
+        let mut tick = segment.start_tick;
+        for (measure, header) in headers
+            .iter()
+            .enumerate()
+            .take(segment.last_measure + 1)
+            .skip(segment.first_measure)
+        {
+            let end = tick + header.length();
+            let score = difficulty.get(measure).copied().unwrap_or_default();
+            heatmap.push((tick..end, score / hardest));
+            tick = end;
+        }
+    }
+    heatmap
+}
+
+fn mix(from: Color, to: Color, ratio: f32) -> Color {
+    Color::from_rgb(
+        from.r + (to.r - from.r) * ratio,
+        from.g + (to.g - from.g) * ratio,
+        from.b + (to.b - from.b) * ratio,
+    )
+}
+
+/// Green for the easy measures, yellow then red for the hardest ones.
+fn heat_color(difficulty: f32) -> Color {
+    let difficulty = difficulty.clamp(0.0, 1.0);
+    if difficulty < 0.5 {
+        mix(COLOR_EASY, COLOR_MEDIUM, difficulty * 2.0)
+    } else {
+        mix(COLOR_MEDIUM, COLOR_HARD, (difficulty - 0.5) * 2.0)
+    }
+}
+
+impl canvas::Program<Message> for TempoStrip {
+    type State = ();
+
+    fn update(
+        &self,
+        _state: &mut Self::State,
+        event: &Event,
+        bounds: Rectangle,
+        cursor: mouse::Cursor,
+    ) -> Option<Action<Message>> {
+        if let Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Left)) = event
+            && let Some(position) = cursor.position_in(bounds)
+        {
+            let tick = catch_panic(
+                "tempo strip event handling",
+                || None,
+                || Some(self.x_tick(position.x, bounds.width - LABEL_WIDTH)),
+            )?;
+            return Some(Action::publish(Message::SeekTick(tick)));
+        }
+        None
+    }
+
+    fn draw(
+        &self,
+        _state: &Self::State,
+        renderer: &Renderer,
+        theme: &Theme,
+        bounds: Rectangle,
+        _cursor: mouse::Cursor,
+    ) -> Vec<Geometry> {
+        catch_panic("tempo strip drawing", Vec::new, || {
+            self.draw_strip(renderer, theme, bounds)
+        })
+    }
+}
+
+impl TempoStrip {
+    fn draw_strip(&self, renderer: &Renderer, theme: &Theme, bounds: Rectangle) -> Vec<Geometry> {
+        let mut frame = Frame::new(renderer, bounds.size());
+        let width = frame.width() - LABEL_WIDTH;
+        let (slowest, fastest) = self.tempo_range();
+        let tempo_y = |tempo: u32| {
+            let line_height = STRIP_HEIGHT - VERTICAL_PADDING * 2.0;
+            if fastest == slowest {
+                STRIP_HEIGHT / 2.0
+            } else {
+                let ratio = (tempo - slowest) as f32 / (fastest - slowest) as f32;
+                VERTICAL_PADDING + (1.0 - ratio) * line_height
+            }
+        };
+
+        frame.fill_rectangle(
+            Point::ORIGIN,
+            iced::Size::new(width, STRIP_HEIGHT),
+            crate::ui::utils::COLOR_GRAY,
+        );
+
+        // difficulty of the played measures under the tempo
+        for (ticks, difficulty) in &self.heatmap {
+            let start_x = self.tick_x(ticks.start, width);
+            let end_x = self.tick_x(ticks.end, width);
+            frame.fill_rectangle(
+                Point::new(start_x, 0.0),
+                iced::Size::new(end_x - start_x, STRIP_HEIGHT),
+                heat_color(*difficulty),
+            );
+        }
+
+        // steps of the tempo, held until the next change
+        let line = Path::new(|builder| {
+            for (i, (tick, tempo)) in self.changes.iter().enumerate() {
+                let x = self.tick_x(*tick, width);
+                let y = tempo_y(*tempo);
+                if i == 0 {
+                    builder.move_to(Point::new(x, y));
+                } else {
+                    builder.line_to(Point::new(x, y));
+                }
+                let next_x = self
+                    .changes
+                    .get(i + 1)
+                    .map_or(width, |(next_tick, _)| self.tick_x(*next_tick, width));
+                builder.line_to(Point::new(next_x, y));
+            }
+        });
+        frame.stroke(
+            &line,
+            Stroke::default().with_width(1.5).with_color(Color::WHITE),
+        );
+
+        // playback position
+        let current_x = self.tick_x(self.current_tick, width);
+        let marker = Path::line(
+            Point::new(current_x, 0.0),
+            Point::new(current_x, STRIP_HEIGHT),
+        );
+        frame.stroke(
+            &marker,
+            Stroke::default()
+                .with_width(2.0)
+                .with_color(theme.palette().primary),
+        );
+
+        let range = if slowest == fastest {
+            format!("{slowest} bpm")
+        } else {
+            format!("{slowest}-{fastest} bpm")
+        };
+        frame.fill_text(Text {
+            content: range,
+            color: Color::WHITE,
+            size: 11.0.into(),
+            position: Point::new(frame.width() - LABEL_WIDTH / 2.0, STRIP_HEIGHT / 2.0),
+            align_x: Alignment::Center,
+            align_y: Vertical::Center,
+            ..Text::default()
+        });
+
+        vec![frame.into_geometry()]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn positions_along_the_strip() {
+        // the song starts after the first quarter note
+        let strip = TempoStrip::new(&[(0, 120), (960, 90), (2880, 140)], 960..4800, 0);
+        assert!(strip.has_changes());
+        assert_eq!(strip.tempo_range(), (90, 140));
+        assert!((strip.tick_x(2880, 400.0) - 200.0).abs() < f32::EPSILON);
+        assert!(strip.tick_x(0, 400.0).abs() < f32::EPSILON);
+        assert_eq!(strip.x_tick(100.0, 400.0), 1920);
+        // clicks past the strip seek to its ends
+        assert_eq!(strip.x_tick(-10.0, 400.0), 960);
+        assert_eq!(strip.x_tick(500.0, 400.0), 4799);
+
+        // the song tempo is replaced by the tempo of the first measure
+        let strip = TempoStrip::new(&[(0, 120), (960, 90), (2880, 90)], 960..4800, 0);
+        assert!(!strip.has_changes());
+        assert!(strip.is_empty());
+        assert_eq!(strip.tempo_range(), (90, 90));
+        assert!(!strip.with_heatmap(vec![(960..4800, 1.0)]).is_empty());
+    }
+
+    #[test]
+    fn heatmap_follows_the_playback() {
+        let headers = vec![MeasureHeader::default(); 3];
+        let length = headers[0].length();
+        // the first two measures are repeated
+        let segments = [
+            PlaybackSegment {
+                first_measure: 0,
+                last_measure: 1,
+                start_tick: 960,
+                end_tick: 960 + 2 * length,
+            },
+            PlaybackSegment {
+                first_measure: 0,
+                last_measure: 2,
+                start_tick: 960 + 2 * length,
+                end_tick: 960 + 5 * length,
+            },
+        ];
+        let heatmap = difficulty_heatmap(&segments, &headers, &[2.0, 4.0, 1.0]);
+        let scores: Vec<f32> = heatmap.iter().map(|(_, score)| *score).collect();
+        assert_eq!(scores, vec![0.5, 1.0, 0.5, 1.0, 0.25]);
+        assert_eq!(heatmap[2].0, 960 + 2 * length..960 + 3 * length);
+        assert!(difficulty_heatmap(&segments, &headers, &[0.0; 3]).is_empty());
+    }
+}
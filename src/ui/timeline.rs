@@ -0,0 +1,113 @@
+use crate::audio::playback_order::playback_starts;
+use crate::parser::song_parser::MeasureHeader;
+use crate::ui::application::Message;
+use iced::widget::{Row, button, text};
+use iced::{Element, Length};
+
+/// Run of consecutive measures in the playback order, e.g. a repeated section.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PlaybackSegment {
+    pub first_measure: usize,
+    pub last_measure: usize,
+    pub start_tick: u32, // playback tick of the first measure
+    pub end_tick: u32,   // playback tick after the last measure
+}
+
+impl PlaybackSegment {
+    const fn measure_count(&self) -> usize {
+        self.last_measure - self.first_measure + 1
+    }
+
+    fn label(&self) -> String {
+        if self.first_measure == self.last_measure {
+            format!("{}", self.first_measure + 1)
+        } else {
+            format!("{}-{}", self.first_measure + 1, self.last_measure + 1)
+        }
+    }
+}
+
+/// Split the playback order into segments, a new one starts at each jump (repeat, ending or direction).
+pub fn playback_segments(
+    headers: &[MeasureHeader],
+    order: &[(usize, i64)],
+) -> Vec<PlaybackSegment> {
+    let mut segments: Vec<PlaybackSegment> = Vec::new();
+    for (measure_index, start_tick) in playback_starts(headers, order) {
+        let end_tick = start_tick + headers[measure_index].length();
+        match segments.last_mut() {
+            Some(segment) if segment.last_measure + 1 == measure_index => {
+                segment.last_measure = measure_index;
+                segment.end_tick = end_tick;
+            }
+            _ => segments.push(PlaybackSegment {
+                first_measure: measure_index,
+                last_measure: measure_index,
+                start_tick,
+                end_tick,
+            }),
+        }
+    }
+    segments
+}
+
+/// Strip of the playback segments sized by measure count, clicking a segment seeks to its start.
+pub fn timeline_view(segments: &[PlaybackSegment], current_tick: u32) -> Element<'_, Message> {
+    let entries = segments.iter().map(|segment| {
+        let is_current = (segment.start_tick..segment.end_tick).contains(&current_tick);
+        let style = if is_current {
+            button::primary
+        } else {
+            button::secondary
+        };
+        button(text(segment.label()).size(11).center().width(Length::Fill))
+            .style(style)
+            .padding([2, 0])
+            .width(Length::FillPortion(segment.measure_count() as u16))
+            .on_press(Message::SeekTick(segment.start_tick))
+            .into()
+    });
+    Row::with_children(entries)
+        .spacing(2)
+        .width(Length::Fill)
+        .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::playback_order::compute_playback_order;
+    use crate::parser::song_parser_tests::parse_gp_file;
+
+    #[test]
+    fn segments_follow_jumps() {
+        let song = parse_gp_file("test-files/Demo v5.gp5").unwrap();
+        let headers = &song.measure_headers;
+        let order = compute_playback_order(headers);
+        let segments = playback_segments(headers, &order);
+        let labels: Vec<String> = segments.iter().map(PlaybackSegment::label).collect();
+        // repeat with alternative endings, then D.S. al Coda
+        assert_eq!(labels, vec!["1-4", "1-3", "5-37", "18-25", "38-49"]);
+
+        // segments are contiguous on the playback timeline
+        assert_eq!(segments[0].start_tick, headers[0].start);
+        assert!(
+            segments
+                .windows(2)
+                .all(|w| w[0].end_tick == w[1].start_tick)
+        );
+        let measures: usize = segments.iter().map(PlaybackSegment::measure_count).sum();
+        assert_eq!(measures, order.len());
+    }
+
+    #[test]
+    fn single_measure_label() {
+        let segment = PlaybackSegment {
+            first_measure: 3,
+            last_measure: 3,
+            start_tick: 960,
+            end_tick: 4800,
+        };
+        assert_eq!(segment.label(), "4");
+    }
+}
@@ -0,0 +1,177 @@
+use crate::ui::application::{Message, TrackSelection};
+use crate::ui::icons::family_icon;
+use iced::widget::{Column, button, checkbox, column, container, row, text};
+use iced::{Alignment, Border, Element, Length};
+use serde::{Deserialize, Serialize};
+
+/// Display order and hidden tracks of a file, the song itself is unchanged.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TrackLayout {
+    order: Vec<usize>,  // track indexes in display order
+    hidden: Vec<usize>, // track indexes left out of the track picker
+}
+
+impl TrackLayout {
+    /// Layout fitting the tracks of the song: unknown tracks are dropped,
+    /// missing ones are appended in song order and at least one track stays visible.
+    pub fn for_track_count(mut self, track_count: usize) -> Self {
+        self.order.retain(|index| *index < track_count);
+        let mut seen = vec![false; track_count];
+        self.order
+            .retain(|index| !std::mem::replace(&mut seen[*index], true));
+        self.order
+            .extend((0..track_count).filter(|index| !seen[*index]));
+        self.hidden.retain(|index| *index < track_count);
+        self.hidden.sort_unstable();
+        self.hidden.dedup();
+        if self.hidden.len() == track_count {
+            self.hidden.clear();
+        }
+        self
+    }
+
+    /// Track indexes in display order, hidden tracks included.
+    pub fn order(&self) -> &[usize] {
+        &self.order
+    }
+
+    pub fn is_hidden(&self, index: usize) -> bool {
+        self.hidden.contains(&index)
+    }
+
+    /// Visible track indexes in display order.
+    pub fn visible(&self) -> impl Iterator<Item = usize> + '_ {
+        self.order
+            .iter()
+            .copied()
+            .filter(|index| !self.is_hidden(*index))
+    }
+
+    /// Move the track one place up or down the display order, `false` at either end.
+    pub fn move_track(&mut self, index: usize, up: bool) -> bool {
+        let Some(position) = self.order.iter().position(|i| *i == index) else {
+            return false;
+        };
+        let target = if up {
+            position.checked_sub(1)
+        } else {
+            Some(position + 1).filter(|p| *p < self.order.len())
+        };
+        target.is_some_and(|target| {
+            self.order.swap(position, target);
+            true
+        })
+    }
+
+    /// Hide or show the track, the last visible track cannot be hidden.
+    pub fn set_hidden(&mut self, index: usize, hidden: bool) -> bool {
+        if hidden == self.is_hidden(index) {
+            return false;
+        }
+        if hidden {
+            if self.visible().count() <= 1 {
+                return false;
+            }
+            self.hidden.push(index);
+            self.hidden.sort_unstable();
+        } else {
+            self.hidden.retain(|i| *i != index);
+        }
+        true
+    }
+}
+
+/// Track manager panel: display order and visibility of the tracks.
+pub fn track_manager_view<'a>(
+    tracks: &'a [TrackSelection],
+    layout: &TrackLayout,
+) -> Element<'a, Message> {
+    let header = text("Tracks").size(18);
+    let hint =
+        text("Hidden tracks are still played, they are left out of the track picker").size(12);
+
+    let last = layout.order().len().saturating_sub(1);
+    let entries = layout
+        .order()
+        .iter()
+        .enumerate()
+        .filter_map(|(position, index)| tracks.get(*index).map(|track| (position, track)))
+        .fold(Column::new().spacing(5), |column, (position, track)| {
+            let index = track.index();
+            let move_up = button(text("\u{2191}").size(12))
+                .style(button::secondary)
+                .on_press_maybe((position > 0).then_some(Message::MoveTrack(index, true)));
+            let move_down = button(text("\u{2193}").size(12))
+                .style(button::secondary)
+                .on_press_maybe((position < last).then_some(Message::MoveTrack(index, false)));
+            let visible = checkbox(!layout.is_hidden(index))
+                .label(track.to_string())
+                .text_size(14)
+                .on_toggle(move |visible| Message::ToggleTrackHidden(index, !visible));
+            column.push(
+                row![move_up, move_down, family_icon(track.family()), visible]
+                    .spacing(5)
+                    .align_y(Alignment::Center),
+            )
+        });
+
+    let close = button(text("Close").size(12))
+        .style(button::secondary)
+        .on_press(Message::CloseTrackManager);
+
+    let content = column![header, hint, entries, close]
+        .spacing(10)
+        .width(Length::Shrink);
+    container(content)
+        .padding(20)
+        .style(|theme: &iced::Theme| container::Style {
+            background: Some(theme.palette().background.into()),
+            border: Border::default()
+                .color(crate::ui::utils::COLOR_GRAY)
+                .width(1),
+            ..Default::default()
+        })
+        .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layout(order: Vec<usize>, hidden: Vec<usize>) -> TrackLayout {
+        TrackLayout { order, hidden }
+    }
+
+    #[test]
+    fn layout_fits_the_song_tracks() {
+        let fitted = layout(vec![2, 7, 0, 2], vec![9, 1]).for_track_count(4);
+        assert_eq!(fitted.order(), &[2, 0, 1, 3]);
+        assert_eq!(fitted.visible().collect::<Vec<_>>(), vec![2, 0, 3]);
+        // a file with fewer tracks than hidden ones shows them all again
+        let all_hidden = layout(vec![], vec![0, 1]).for_track_count(2);
+        assert_eq!(all_hidden.visible().collect::<Vec<_>>(), vec![0, 1]);
+    }
+
+    #[test]
+    fn move_tracks_within_the_order() {
+        let mut layout = TrackLayout::default().for_track_count(3);
+        assert!(layout.move_track(2, true));
+        assert_eq!(layout.order(), &[0, 2, 1]);
+        assert!(layout.move_track(0, false));
+        assert_eq!(layout.order(), &[2, 0, 1]);
+        assert!(!layout.move_track(2, true));
+        assert!(!layout.move_track(1, false));
+        assert!(!layout.move_track(5, false));
+    }
+
+    #[test]
+    fn last_visible_track_stays() {
+        let mut layout = TrackLayout::default().for_track_count(2);
+        assert!(layout.set_hidden(0, true));
+        assert!(!layout.set_hidden(0, true));
+        assert!(!layout.set_hidden(1, true));
+        assert_eq!(layout.visible().collect::<Vec<_>>(), vec![1]);
+        assert!(layout.set_hidden(0, false));
+        assert_eq!(layout.visible().collect::<Vec<_>>(), vec![0, 1]);
+    }
+}
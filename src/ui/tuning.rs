@@ -1,3 +1,5 @@
+use crate::analysis::{note_name, pitch_class};
+
 /// Returns a human-readable tuning label for a stringed track.
 /// Returns `None` for tracks with no strings (non-string instruments).
 pub fn tuning_label(strings: &[(i32, i32)]) -> Option<String> {
@@ -10,6 +12,9 @@ pub fn tuning_label(strings: &[(i32, i32)]) -> Option<String> {
     if let Some(preset) = preset_name(&pitches) {
         return Some(preset.to_string());
     }
+    if let Some(name) = standard_or_drop_name(&pitches) {
+        return Some(name);
+    }
 
     Some(
         pitches
@@ -57,13 +62,57 @@ fn preset_name(pitches_sorted: &[i32]) -> Option<&'static str> {
     }
 }
 
-fn note_name(midi_pitch: i32) -> String {
-    const NOTES: [&str; 12] = [
-        "C", "C#", "D", "D#", "E", "F", "F#", "G", "G#", "A", "A#", "B",
-    ];
-    let note = NOTES[midi_pitch.rem_euclid(12) as usize];
-    let octave = midi_pitch / 12 - 1;
-    format!("{note}{octave}")
+/// Intervals between the strings of a standard tuning, lowest string first:
+/// fourths with a major third before the last string on guitars, only fourths on basses.
+fn standard_intervals(string_count: usize) -> Option<Vec<i32>> {
+    match string_count {
+        4 | 5 => Some(vec![5; string_count - 1]),
+        6..=9 => {
+            let mut intervals = vec![5; string_count - 1];
+            intervals[string_count - 3] = 4;
+            Some(intervals)
+        }
+        _ => None,
+    }
+}
+
+/// Standard tuning or its drop variant (lowest string a whole step down) on any pitch,
+/// named after the lowest string, e.g. a 7-string "Drop G#" or a bass "Standard C".
+fn standard_or_drop_name(pitches_sorted: &[i32]) -> Option<String> {
+    let standard = standard_intervals(pitches_sorted.len())?;
+    let intervals: Vec<i32> = pitches_sorted.windows(2).map(|w| w[1] - w[0]).collect();
+    let lowest = pitch_class(pitches_sorted[0]);
+    if intervals == standard {
+        // a 6-string bass is tuned in fourths like the other basses
+        return Some(format!("Standard {lowest}"));
+    }
+    let (first, rest) = intervals.split_first()?;
+    (*first == standard[0] + 2 && rest == &standard[1..]).then(|| format!("Drop {lowest}"))
+}
+
+/// Tuning name followed by the notes of the strings, e.g. "Drop D: D A D G B E",
+/// only the notes with their octave for an unnamed tuning.
+pub fn tuning_summary(strings: &[(i32, i32)]) -> Option<String> {
+    let label = tuning_label(strings)?;
+    let mut pitches: Vec<i32> = strings.iter().map(|(_, pitch)| *pitch).collect();
+    pitches.sort_unstable();
+    let named = preset_name(&pitches).is_some() || standard_or_drop_name(&pitches).is_some();
+    Some(if named {
+        format!("{label}: {}", tuning_notes(strings))
+    } else {
+        label
+    })
+}
+
+/// Notes of the strings from the lowest, without octave, e.g. "D A D G B E".
+fn tuning_notes(strings: &[(i32, i32)]) -> String {
+    let mut pitches: Vec<i32> = strings.iter().map(|(_, pitch)| *pitch).collect();
+    pitches.sort_unstable();
+    pitches
+        .iter()
+        .map(|p| pitch_class(*p))
+        .collect::<Vec<_>>()
+        .join(" ")
 }
 
 #[cfg(test)]
@@ -213,11 +262,62 @@ mod tests {
 
     #[test]
     fn unknown_tuning_falls_back_to_notes() {
-        // arbitrary 6-string tuning
+        // arbitrary 6-string tuning (the major third on the wrong strings)
+        let strings = vec![(1, 64), (2, 60), (3, 55), (4, 50), (5, 45), (6, 40)];
+        assert_eq!(tuning_label(&strings).as_deref(), Some("E2 A2 D3 G3 C4 E4"));
+        // while the same intervals as standard E are named after the lowest string
         let strings = vec![(1, 65), (2, 60), (3, 56), (4, 51), (5, 46), (6, 41)];
+        assert_eq!(tuning_label(&strings).as_deref(), Some("Standard F"));
+    }
+
+    #[test]
+    fn standard_and_drop_tunings_on_any_pitch() {
+        // 6-string drop C#: C# G# C# F# A# D#
+        let strings = vec![(1, 63), (2, 58), (3, 54), (4, 49), (5, 44), (6, 37)];
+        assert_eq!(tuning_label(&strings).as_deref(), Some("Drop C#"));
+        // 7-string drop G#: G# D# G# C# F# A# D#
+        let strings = vec![
+            (1, 63),
+            (2, 58),
+            (3, 54),
+            (4, 49),
+            (5, 44),
+            (6, 39),
+            (7, 32),
+        ];
+        assert_eq!(tuning_label(&strings).as_deref(), Some("Drop G#"));
+        // 8-string standard F#: F# B E A D G B E
+        let strings = vec![
+            (1, 64),
+            (2, 59),
+            (3, 55),
+            (4, 50),
+            (5, 45),
+            (6, 40),
+            (7, 35),
+            (8, 30),
+        ];
+        assert_eq!(tuning_label(&strings).as_deref(), Some("Standard F#"));
+        // 4-string bass drop C: C G C F
+        let strings = vec![(1, 41), (2, 36), (3, 31), (4, 24)];
+        assert_eq!(tuning_label(&strings).as_deref(), Some("Drop C"));
+        // 5-string bass standard A: A D G C F
+        let strings = vec![(1, 41), (2, 36), (3, 31), (4, 26), (5, 21)];
+        assert_eq!(tuning_label(&strings).as_deref(), Some("Standard A"));
+    }
+
+    #[test]
+    fn tuning_notes_from_the_lowest_string() {
+        let strings = vec![(1, 64), (2, 59), (3, 55), (4, 50), (5, 45), (6, 38)];
+        assert_eq!(tuning_notes(&strings), "D A D G B E");
         assert_eq!(
-            tuning_label(&strings).as_deref(),
-            Some("F2 A#2 D#3 G#3 C4 F4")
+            tuning_summary(&strings).as_deref(),
+            Some("Drop D: D A D G B E")
+        );
+        let strings = vec![(1, 64), (2, 60), (3, 55), (4, 50), (5, 45), (6, 40)];
+        assert_eq!(
+            tuning_summary(&strings).as_deref(),
+            Some("E2 A2 D3 G3 C4 E4")
         );
     }
 
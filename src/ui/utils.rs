@@ -1,19 +1,54 @@
 use crate::ui::application::Message;
 use iced::widget::{
-    Container, Text, button, center, container, mouse_area, opaque, stack, tooltip,
+    Container, Text, button, center, column, container, mouse_area, opaque, progress_bar, stack,
+    tooltip,
 };
 use iced::{Color, Element, Length};
+use std::any::Any;
+use std::panic::{AssertUnwindSafe, catch_unwind};
+use std::sync::{Mutex, PoisonError};
 
 // Shared UI colors
 pub const COLOR_GRAY: Color = Color::from_rgb8(0x40, 0x44, 0x4B);
 pub const COLOR_DARK_RED: Color = Color::from_rgb8(200, 50, 50);
 
+// Track colors used when the file does not define one
+const TRACK_PALETTE: [Color; 8] = [
+    Color::from_rgb8(200, 50, 50),
+    Color::from_rgb8(70, 150, 230),
+    Color::from_rgb8(90, 190, 90),
+    Color::from_rgb8(230, 170, 40),
+    Color::from_rgb8(170, 100, 220),
+    Color::from_rgb8(40, 190, 190),
+    Color::from_rgb8(230, 110, 170),
+    Color::from_rgb8(160, 160, 160),
+];
+
+/// Accent color of a track from its `0xRRGGBB` file color.
+/// Black (unset) picks from the palette and dark colors are lightened to stay readable on the dark theme.
+pub fn track_color(color: i32, track_index: usize) -> Color {
+    if color <= 0 {
+        return TRACK_PALETTE[track_index % TRACK_PALETTE.len()];
+    }
+    let color = Color::from_rgb8((color >> 16) as u8, (color >> 8) as u8, color as u8);
+    if color.relative_luminance() < 0.15 {
+        Color::from_rgb(
+            (color.r + 1.0) / 2.0,
+            (color.g + 1.0) / 2.0,
+            (color.b + 1.0) / 2.0,
+        )
+    } else {
+        color
+    }
+}
+
 pub fn untitled_text_table_box() -> Container<'static, Message> {
     let message = "Tips:\n \
         - use the space bar to play/pause\n \
         - use ctrl+up/down to change the tempo\n \
         - use left/right to navigate measures\n \
         - use s to toggle solo mode\n \
+        - use p to pause at the end of each measure, space or page down plays the next one\n \
         - use F11 to toggle fullscreen";
     let text = Text::new(message).color(Color::WHITE);
 
@@ -94,3 +129,110 @@ where
     ]
     .into()
 }
+
+/// Quietest output level shown by the meter.
+const METER_FLOOR_DB: f32 = -60.0;
+
+/// Share of the meter filled by an RMS level, on a decibel scale from -60 dB to full scale.
+pub fn meter_fill(level: f32) -> f32 {
+    if level <= 0.0 {
+        return 0.0;
+    }
+    let db = 20.0 * level.log10();
+    (1.0 - db / METER_FLOOR_DB).clamp(0.0, 1.0)
+}
+
+/// Left & right output level bars, a silent channel stays empty while playing.
+pub fn level_meter<'a, Message: 'a>((left, right): (f32, f32)) -> Element<'a, Message> {
+    let bar = |level| {
+        progress_bar(0.0..=1.0, meter_fill(level))
+            .length(60)
+            .girth(4)
+    };
+    tooltip(
+        column![bar(left), bar(right)].spacing(2),
+        "Output level (left / right)",
+        tooltip::Position::Bottom,
+    )
+    .style(container::rounded_box)
+    .into()
+}
+
+/// Duration as minutes and seconds, e.g. `3:07`.
+pub fn format_mmss(seconds: f64) -> String {
+    let total = seconds.max(0.0) as u32;
+    format!("{}:{:02}", total / 60, total % 60)
+}
+
+/// Panic caught by an error boundary, reported by the next update.
+static CAUGHT_PANIC: Mutex<Option<String>> = Mutex::new(None);
+
+/// Error boundary: a panic in `f` is logged and kept for the error report, `fallback` is returned instead.
+/// The UI keeps running, e.g. a canvas failing to draw stays blank.
+pub fn catch_panic<T>(context: &str, fallback: impl FnOnce() -> T, f: impl FnOnce() -> T) -> T {
+    match catch_unwind(AssertUnwindSafe(f)) {
+        Ok(value) => value,
+        Err(payload) => {
+            let reason = format!("{context}: {}", panic_message(payload.as_ref()));
+            log::error!("Caught panic in {reason}");
+            *CAUGHT_PANIC.lock().unwrap_or_else(PoisonError::into_inner) = Some(reason);
+            fallback()
+        }
+    }
+}
+
+/// Last panic caught since the previous call.
+pub fn take_caught_panic() -> Option<String> {
+    CAUGHT_PANIC
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .take()
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("unknown panic")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn track_color_from_file_or_palette() {
+        assert_eq!(track_color(0x00FF_0000, 0), Color::from_rgb8(255, 0, 0));
+        // unset colors cycle through the palette
+        assert_eq!(track_color(0, 1), TRACK_PALETTE[1]);
+        assert_eq!(track_color(0, 9), TRACK_PALETTE[1]);
+        // dark colors are lightened
+        let navy = track_color(0x0000_0080, 0);
+        assert!(navy.relative_luminance() > Color::from_rgb8(0, 0, 0x80).relative_luminance());
+        assert!(navy.b > navy.r);
+    }
+
+    #[test]
+    fn meter_fill_in_decibels() {
+        assert_eq!(meter_fill(1.0), 1.0);
+        assert_eq!(meter_fill(2.0), 1.0);
+        assert!((meter_fill(0.1) - 2.0 / 3.0).abs() < 1e-6);
+        assert_eq!(meter_fill(0.0001), 0.0);
+        assert_eq!(meter_fill(0.0), 0.0);
+    }
+
+    #[test]
+    fn panics_are_caught_and_reported() {
+        assert_eq!(catch_panic("test", || 0, || 1), 1);
+        assert_eq!(take_caught_panic(), None);
+
+        let values = [1, 2];
+        let index = values.len();
+        assert_eq!(catch_panic("test", || 0, || values[index]), 0);
+        let reason = take_caught_panic().unwrap();
+        assert!(reason.starts_with("test: index out of bounds"), "{reason}");
+        // reported once
+        assert_eq!(take_caught_panic(), None);
+    }
+}
@@ -0,0 +1,220 @@
+//! Batch verification of a tab collection: every tab of a folder is parsed and its MIDI
+//! events are built and checked, e.g. to spot the files which would not play correctly.
+
+use crate::audio::midi_builder::MidiBuilder;
+use crate::audio::midi_event::{MidiEvent, MidiEventType};
+use crate::audio::playback_order::compute_playback_order;
+use crate::parser::{is_tab_file, parse_gp_data};
+use std::collections::HashSet;
+use std::fmt;
+use std::panic::{AssertUnwindSafe, catch_unwind};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// MIDI has 16 channels per port.
+const CHANNEL_COUNT: i32 = 16;
+
+/// Outcome of the verification of a tab file.
+#[derive(Debug)]
+pub struct FileReport {
+    pub path: PathBuf,
+    pub errors: Vec<String>,   // the file does not play correctly
+    pub warnings: Vec<String>, // parsing issues, errors in strict mode
+}
+
+impl FileReport {
+    pub const fn is_valid(&self, strict: bool) -> bool {
+        self.errors.is_empty() && (!strict || self.warnings.is_empty())
+    }
+}
+
+/// Reports of the tab files of the folder and its sub folders, sorted by path.
+#[derive(Debug)]
+pub struct ValidationReport {
+    pub files: Vec<FileReport>,
+    pub strict: bool, // parsing warnings fail the files
+}
+
+impl ValidationReport {
+    pub fn failed_count(&self) -> usize {
+        self.files
+            .iter()
+            .filter(|file| !file.is_valid(self.strict))
+            .count()
+    }
+}
+
+/// One line per file, followed by its issues.
+impl fmt::Display for ValidationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for file in &self.files {
+            let status = if file.is_valid(self.strict) {
+                "ok"
+            } else {
+                "FAILED"
+            };
+            writeln!(f, "{status} {}", file.path.display())?;
+            for error in &file.errors {
+                writeln!(f, "  error: {error}")?;
+            }
+            for warning in &file.warnings {
+                writeln!(f, "  warning: {warning}")?;
+            }
+        }
+        write!(
+            f,
+            "{} files, {} failed",
+            self.files.len(),
+            self.failed_count()
+        )
+    }
+}
+
+/// Verifies all the tab files found in the folder.
+pub fn validate_folder(folder: &Path, strict: bool) -> std::io::Result<ValidationReport> {
+    let mut paths = Vec::new();
+    collect_tab_files(folder, &mut paths)?;
+    paths.sort();
+    let files = paths
+        .into_iter()
+        .map(|path| match std::fs::read(&path) {
+            Ok(data) => validate_data(path, &data),
+            Err(err) => FileReport {
+                path,
+                errors: vec![format!("read failed: {err}")],
+                warnings: Vec::new(),
+            },
+        })
+        .collect();
+    Ok(ValidationReport { files, strict })
+}
+
+fn collect_tab_files(folder: &Path, paths: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(folder)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_tab_files(&path, paths)?;
+        } else if is_tab_file(&path) {
+            paths.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Parses the tab file, then builds and checks its MIDI events.
+pub fn validate_data(path: PathBuf, data: &[u8]) -> FileReport {
+    let song = match parse_gp_data(data) {
+        Ok(song) => Arc::new(song),
+        Err(err) => {
+            return FileReport {
+                path,
+                errors: vec![err.to_string()],
+                warnings: Vec::new(),
+            };
+        }
+    };
+    let warnings = song.warnings.clone();
+    let order = compute_playback_order(&song.measure_headers);
+    // a malformed song must not stop the verification of the other files
+    let built = catch_unwind(AssertUnwindSafe(|| {
+        MidiBuilder::new()
+            .with_muted_tracks(true)
+            .build_for_song_with_order(&song, &order)
+    }));
+    let errors = match built {
+        Ok(events) => check_events(&events),
+        Err(_) => vec!["building the MIDI events failed".to_string()],
+    };
+    FileReport {
+        path,
+        errors,
+        warnings,
+    }
+}
+
+/// Invariants of the built events: sorted by tick, channels in range and every note released.
+pub fn check_events(events: &[MidiEvent]) -> Vec<String> {
+    let mut errors = Vec::new();
+    if let Some(pair) = events.windows(2).find(|pair| pair[0].tick > pair[1].tick) {
+        errors.push(format!(
+            "events not sorted: tick {} before tick {}",
+            pair[0].tick, pair[1].tick
+        ));
+    }
+    let mut sounding = HashSet::new();
+    let mut invalid_channels = HashSet::new();
+    for event in events {
+        let channel = match event.event {
+            MidiEventType::NoteOn(channel, key, _) => {
+                sounding.insert((channel, key));
+                Some(channel)
+            }
+            MidiEventType::NoteOff(channel, key) => {
+                sounding.remove(&(channel, key));
+                Some(channel)
+            }
+            MidiEventType::MidiMessage(channel, ..) => Some(channel),
+            MidiEventType::TempoChange(_) => None,
+        };
+        if let Some(channel) = channel.filter(|c| !(0..CHANNEL_COUNT).contains(c)) {
+            invalid_channels.insert(channel);
+        }
+    }
+    let mut invalid_channels: Vec<i32> = invalid_channels.into_iter().collect();
+    invalid_channels.sort_unstable();
+    for channel in invalid_channels {
+        errors.push(format!("invalid MIDI channel {channel}"));
+    }
+    let mut hanging: Vec<(i32, i32)> = sounding.into_iter().collect();
+    hanging.sort_unstable();
+    for (channel, key) in hanging {
+        errors.push(format!("note {key} on channel {channel} is never released"));
+    }
+    errors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn broken_events_are_reported() {
+        let events = [
+            MidiEvent::new_note_on(10, 0, 60, 95, 0),
+            MidiEvent::new_note_on(20, 0, 64, 95, 16),
+            MidiEvent::new_note_off(15, 0, 64, 16),
+            MidiEvent::new_note_off(30, 0, 62, 0),
+        ];
+        assert_eq!(
+            check_events(&events),
+            vec![
+                "events not sorted: tick 20 before tick 15",
+                "invalid MIDI channel 16",
+                "note 60 on channel 0 is never released",
+            ]
+        );
+    }
+
+    #[test]
+    fn validate_test_files() {
+        let report = validate_folder(Path::new("test-files"), false).unwrap();
+        // every format found, the expected MIDI files of the sub-folder skipped
+        for file in [
+            "Blind Guardian - Nightfall.gp3",
+            "canon_rock.gp4",
+            "Demo v5.gp5",
+            "Tyr - Evening Star.gpx",
+            "The Black Dahlia Murder - Nightbringers.gp",
+        ] {
+            let path = Path::new("test-files").join(file);
+            assert!(report.files.iter().any(|f| f.path == path), "{file}");
+        }
+        assert!(report.files.iter().all(|f| is_tab_file(&f.path)));
+        assert!(report.files.is_sorted_by_key(|f| f.path.clone()));
+        assert_eq!(report.failed_count(), 0, "{report}");
+        assert!(report.to_string().ends_with(" failed"));
+
+        let garbage = validate_data(PathBuf::from("garbage.gp5"), b"not a tab");
+        assert!(!garbage.is_valid(false));
+    }
+}